@@ -1,17 +1,44 @@
 #!/bin/sh
 set -e
 
-ARGS=""
+WORKFLOW_PATH="$1"
+STRICT="$2"
+CONFIG_FLAG="$3"
+CONFIG_INPUT="$4"
+SCAN_SELF="$5"
+SUBMIT_SNAPSHOT="$6"
+
+# Build the arguments as positional parameters so a path with spaces stays one argument
+set --
 
 # Process arguments
-if [ "$2" = "--strict" ]; then
-  ARGS="$ARGS --strict"
+if [ "$STRICT" = "--strict" ]; then
+  set -- "$@" --strict
+fi
+
+if [ "$SCAN_SELF" = "--self" ]; then
+  set -- "$@" --self
 fi
 
-CONFIG_PATH="critical_dependencies.yaml"
-if [ "$3" = "--config" ] && [ -n "$4" ]; then
-  CONFIG_PATH="$4"
+if [ "$SUBMIT_SNAPSHOT" = "--submit-dependency-snapshot" ]; then
+  set -- "$@" --submit-dependency-snapshot
 fi
 
-# Run the scanner with appropriate arguments
-gh-action-security-scanner "$1" $ARGS --config "$CONFIG_PATH"
\ No newline at end of file
+# A config-file input must exist; without one the conventional location is used when present,
+# else the scanner's own default (critical_dependencies.yaml, optional)
+CONFIG_PATH=""
+if [ "$CONFIG_FLAG" = "--config" ] && [ -n "$CONFIG_INPUT" ]; then
+  CONFIG_PATH="$CONFIG_INPUT"
+elif [ -f ".github/gh-action-security-config.yaml" ]; then
+  CONFIG_PATH=".github/gh-action-security-config.yaml"
+fi
+if [ -n "$CONFIG_PATH" ]; then
+  set -- "$@" --config "$CONFIG_PATH"
+fi
+
+# Run the scanner with appropriate arguments; with no workflow path it scans $GITHUB_WORKSPACE/.github/workflows
+if [ -n "$WORKFLOW_PATH" ]; then
+  gh-action-security-scanner "$WORKFLOW_PATH" "$@"
+else
+  gh-action-security-scanner "$@"
+fi
@@ -0,0 +1,106 @@
+use crate::cache::{Cached, DiskCache};
+use crate::config::Config;
+use crate::github::GitHubApi;
+use std::collections::BTreeSet;
+
+/// Publishers of widely used actions, so a misspelling of one is caught even if no workflow in
+/// the checkout uses it yet
+const WELL_KNOWN_OWNERS: [&str; 12] = [
+    "actions",
+    "github",
+    "docker",
+    "aws-actions",
+    "azure",
+    "google-github-actions",
+    "hashicorp",
+    "microsoft",
+    "dependabot",
+    "codecov",
+    "peter-evans",
+    "softprops",
+];
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions, so `docekr` is
+/// one edit from `docker`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Candidates within two edits of `name` (one for short names), closest first
+fn near_misses<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let name = name.to_ascii_lowercase();
+    let limit = if name.len() <= 4 { 1 } else { 2 };
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|c| !c.eq_ignore_ascii_case(&name))
+        .map(|c| (edit_distance(&name, &c.to_ascii_lowercase()), c))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    close.sort();
+    close.dedup();
+    close.into_iter().map(|(_, c)| c).collect()
+}
+
+/// Whether a GitHub user or organization exists, through the cache. Accounts are renamed and
+/// deleted rarely enough that the normal TTLs apply.
+async fn account_exists(api: &GitHubApi<'_>, cache: &DiskCache, owner: &str) -> Result<bool, std::io::Error> {
+    match cache.get("accounts", owner) {
+        Cached::Hit(_) => return Ok(true),
+        Cached::NegativeHit => return Ok(false),
+        Cached::Miss => {}
+    }
+    let account = api.get(&format!("/users/{}", owner)).await?;
+    match &account {
+        Some(account) => cache.put("accounts", owner, account),
+        None => cache.put_negative("accounts", owner),
+    }
+    Ok(account.is_some())
+}
+
+/// `config check --online`: verify every trusted owner is a real account, warning about those that
+/// are not with any near-miss owner from `known` or the well-known publishers. Only this command
+/// calls it; scans never look accounts up.
+pub async fn check_trusted_owners(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    config: &Config,
+    known: &BTreeSet<String>,
+) -> Vec<String> {
+    let candidates: BTreeSet<&str> = known.iter().map(String::as_str).chain(WELL_KNOWN_OWNERS).collect();
+    let mut warnings = Vec::new();
+    // Globs name no one account to look up
+    for owner in config.trusted_owners.iter().flatten().filter(|o| !o.contains(['*', '?', '['])) {
+        match account_exists(api, cache, owner).await {
+            Ok(true) => {}
+            Ok(false) => {
+                let suggestions = near_misses(owner, candidates.iter().copied());
+                let hint = match suggestions.as_slice() {
+                    [] => String::new(),
+                    names => format!("; did you mean {}?", names.join(" or ")),
+                };
+                warnings.push(format!("trusted_owners entry {} is not a GitHub account{}", owner, hint));
+            }
+            Err(e) => warnings.push(format!("could not verify trusted_owners entry {}: {}", owner, e)),
+        }
+    }
+    warnings
+}
@@ -0,0 +1,45 @@
+//! The `action.yml` of a GitHub-hosted action at its pinned ref, which `--transitive` and
+//! `--check-runtime` both read. Each file is fetched once and cached as its text.
+
+use crate::ActionRef;
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::pr::decode_content;
+use serde_json::Value;
+
+/// The action's metadata, `action.yml` or `action.yaml` in its directory, at its ref. `None` when
+/// it has neither there.
+pub async fn fetch(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<serde_yaml::Value>, std::io::Error> {
+    let path = action_ref.path.as_deref().unwrap_or("");
+    let key = format!("{}/{}@{}", action_ref.full_name(), path, action_ref.version);
+    let text = match cache.get("action-metadata", &key) {
+        Cached::Hit(value) => value.as_str().unwrap_or("").to_string(),
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {
+            let mut found = None;
+            for file in ["action.yml", "action.yaml"] {
+                let file = if path.is_empty() { file.to_string() } else { format!("{}/{}", path, file) };
+                let url = format!("/repos/{}/contents/{}?ref={}", action_ref.full_name(), file, action_ref.version);
+                if let Some(contents) = api.get(&url).await? {
+                    found = Some(contents);
+                    break;
+                }
+            }
+            let Some(contents) = found else {
+                cache.put_negative("action-metadata", &key);
+                return Ok(None);
+            };
+            let text = decode_content(&contents)?;
+            cache.put("action-metadata", &key, &Value::String(text.clone()));
+            text
+        }
+    };
+    let metadata = serde_yaml::from_str(&text)
+        .map_err(|e| std::io::Error::other(format!("{} metadata at {}: {}", action_ref.full_name(), action_ref.version, e)))?;
+    Ok(Some(metadata))
+}
+
+/// The metadata's `runs.using`, such as `node20`, `composite` or `docker`
+pub fn runs_using(metadata: &serde_yaml::Value) -> Option<&str> {
+    metadata.get("runs").and_then(|r| r.get("using")).and_then(|u| u.as_str())
+}
@@ -0,0 +1,235 @@
+use crate::blame;
+use crate::fix::split_line_ending;
+use crate::report::{Finding, ScanReport};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Comments the scanner inserts start with this, so a later run can recognize and replace them
+const MARKER: &str = "# dependency-scanner:";
+
+/// What `--annotate-workflow` did with one workflow
+pub struct Annotated {
+    pub file: String,
+    pub written: PathBuf,
+    pub comments: usize,
+}
+
+fn is_marker(line: &str) -> bool {
+    line.trim_start().starts_with(MARKER)
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// The line of the step (the `- ` list item) that contains `line`, 1-based; `line` itself when it
+/// is not inside a step
+fn step_start(lines: &[&str], line: usize) -> usize {
+    let Some(target) = line.checked_sub(1).and_then(|index| lines.get(index)) else { return line };
+    if target.trim_start().starts_with("- ") {
+        return line;
+    }
+    let mut limit = indent(target);
+    for index in (0..line - 1).rev() {
+        let candidate = lines[index];
+        if candidate.trim().is_empty() || candidate.trim_start().starts_with('#') {
+            continue;
+        }
+        if indent(candidate) < limit {
+            if candidate.trim_start().starts_with("- ") {
+                return index + 1;
+            }
+            // A mapping key above the step list: the finding is not on a step
+            limit = indent(candidate);
+            if limit == 0 {
+                break;
+            }
+        }
+    }
+    line
+}
+
+/// The workflow with one marker comment per finding above the step it points at. Marker comments
+/// from an earlier run are dropped, everything else is copied byte for byte.
+fn annotate(report: &ScanReport, content: &str, findings: &[(usize, &Finding)]) -> (String, usize) {
+    let lines: Vec<&str> = content.split_inclusive('\n').map(|l| split_line_ending(l).0).collect();
+    let ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut above: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    for (line, finding) in findings {
+        let step = step_start(&lines, *line);
+        let message = finding.message.replace(['\r', '\n'], " ");
+        let comment = format!("{} {} {}", MARKER, report.rule_id(finding.rule), message);
+        if seen.insert((step, comment.clone())) {
+            above.entry(step).or_default().push(comment);
+        }
+    }
+
+    let mut output = String::with_capacity(content.len());
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line = split_line_ending(raw_line).0;
+        if is_marker(line) {
+            continue;
+        }
+        if let Some(comments) = above.get(&(index + 1)) {
+            let pad = &line[..indent(line)];
+            for comment in comments {
+                output.push_str(pad);
+                output.push_str(comment);
+                output.push_str(ending);
+            }
+        }
+        output.push_str(raw_line);
+    }
+    (output, seen.len())
+}
+
+/// Where the annotated copy of a workflow goes: next to it, with a suffix GitHub does not run
+fn copy_path(file: &str) -> PathBuf {
+    PathBuf::from(format!("{}.annotated", file))
+}
+
+/// `--annotate-workflow`: write each scanned workflow with its findings as comments, to a copy or
+/// with `in_place` over the original. Workflows with no findings and no stale markers are left
+/// alone.
+pub fn write_all(report: &ScanReport, in_place: bool) -> Result<Vec<Annotated>, Box<dyn std::error::Error>> {
+    let mut by_file: BTreeMap<&str, Vec<(usize, &Finding)>> = BTreeMap::new();
+    for file in report.workflow_actions.keys() {
+        by_file.entry(file).or_default();
+    }
+    for (finding, sites) in report.findings.iter().zip(blame::sites(report)) {
+        // A finding used in several places is annotated at each of them
+        for (file, line) in sites {
+            if let Some(findings) = by_file.get_mut(file.as_str()) {
+                findings.push((line, finding));
+            }
+        }
+    }
+
+    let mut annotated = Vec::new();
+    for (file, findings) in by_file {
+        let content = report.workflow_source(file).map_err(|e| format!("{}: {}", file, e))?;
+        let (updated, comments) = annotate(report, &content, &findings);
+        if comments == 0 && !content.lines().any(is_marker) {
+            continue;
+        }
+        let written = if in_place { PathBuf::from(file) } else { copy_path(file) };
+        if !in_place || updated != content {
+            fs::write(&written, &updated).map_err(|e| format!("{}: {}", written.display(), e))?;
+        }
+        annotated.push(Annotated { file: file.to_string(), written, comments });
+    }
+    Ok(annotated)
+}
+
+/// List the annotated workflows
+pub fn print(out: &mut dyn Write, annotated: &[Annotated]) -> io::Result<()> {
+    if annotated.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nAnnotated workflows:")?;
+    for entry in annotated {
+        writeln!(out, "- {}: {} comment(s) in {}", entry.file, entry.comments, entry.written.display())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{Level, Rule};
+
+    const WORKFLOW: &str = "\
+# Build on every push
+on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest   # keep in sync with deploy
+    steps:
+      - uses: actions/checkout@v4
+
+      # third-party linter
+      - name: lint
+        uses: acme/lint@main
+        with:
+          args: --strict
+";
+
+    fn untrusted(action: &str) -> Finding {
+        Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, action, format!("Non-trusted action {} should use commit SHA", action))
+    }
+
+    fn annotated(content: &str, findings: &[(usize, Finding)]) -> (String, usize) {
+        let findings: Vec<(usize, &Finding)> = findings.iter().map(|(line, finding)| (*line, finding)).collect();
+        annotate(&ScanReport::default(), content, &findings)
+    }
+
+    /// `content` with the scanner's markers taken out again
+    fn without_markers(content: &str) -> String {
+        content.split_inclusive('\n').filter(|l| !is_marker(l)).collect()
+    }
+
+    #[test]
+    fn a_finding_inside_a_step_is_placed_above_its_list_item() {
+        let lines: Vec<&str> = WORKFLOW.lines().collect();
+        assert_eq!(step_start(&lines, 11), 10, "uses: under a named step");
+        assert_eq!(step_start(&lines, 13), 10, "a nested with: key");
+        assert_eq!(step_start(&lines, 7), 7, "the list item itself");
+        assert_eq!(step_start(&lines, 5), 5, "a job key is not in a step");
+        assert_eq!(step_start(&lines, 99), 99);
+    }
+
+    #[test]
+    fn comments_go_above_the_step_with_its_indentation() {
+        let (output, comments) = annotated(WORKFLOW, &[(11, untrusted("acme/lint@main"))]);
+        assert_eq!(comments, 1);
+        let expected = WORKFLOW.replace(
+            "      - name: lint\n",
+            "      # dependency-scanner: untrusted-owner-no-sha Non-trusted action acme/lint@main should use commit SHA\n      - name: lint\n",
+        );
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn everything_but_the_markers_round_trips_byte_for_byte() {
+        let findings = [(7, untrusted("actions/checkout@v4")), (11, untrusted("acme/lint@main"))];
+        let (output, comments) = annotated(WORKFLOW, &findings);
+        assert_eq!(comments, 2);
+        assert_eq!(without_markers(&output), WORKFLOW);
+
+        let crlf = WORKFLOW.replace('\n', "\r\n");
+        let (output, _) = annotated(&crlf, &findings);
+        assert!(output.contains("# dependency-scanner: untrusted-owner-no-sha Non-trusted action acme/lint@main should use commit SHA\r\n"), "{:?}", output);
+        assert_eq!(without_markers(&output), crlf);
+    }
+
+    #[test]
+    fn re_annotating_replaces_the_earlier_markers() {
+        let findings = [(11, untrusted("acme/lint@main"))];
+        let (once, _) = annotated(WORKFLOW, &findings);
+        // The markers shift the lines; a re-run's findings point into the annotated file
+        let (twice, comments) = annotated(&once, &[(12, untrusted("acme/lint@main"))]);
+        assert_eq!((twice.as_str(), comments), (once.as_str(), 1));
+
+        let (cleared, comments) = annotated(&once, &[]);
+        assert_eq!((cleared.as_str(), comments), (WORKFLOW, 0));
+    }
+
+    #[test]
+    fn a_finding_at_several_lines_of_one_step_is_one_comment() {
+        let finding = untrusted("acme/lint@main");
+        let (output, comments) = annotated(WORKFLOW, &[(10, finding.clone()), (11, finding)]);
+        assert_eq!(comments, 1);
+        assert_eq!(output.lines().filter(|l| is_marker(l)).count(), 1, "{}", output);
+    }
+
+    #[test]
+    fn multi_line_messages_stay_on_one_comment_line() {
+        let mut finding = untrusted("acme/lint@main");
+        finding.message = "first\nsecond\r\nthird".to_string();
+        let (output, _) = annotated(WORKFLOW, &[(11, finding)]);
+        assert!(output.contains("# dependency-scanner: untrusted-owner-no-sha first second  third\n      - name: lint"), "{}", output);
+    }
+}
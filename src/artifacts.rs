@@ -0,0 +1,515 @@
+use crate::{ActionRef, DockerRef};
+use crate::blame;
+use crate::decision::{Decision, Outcome};
+use crate::render::Marker;
+use crate::report::{Category, Finding, Level, Rule, ScanReport};
+use crate::severity::Severity;
+use crate::snapshot::package_url;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A file `--output-dir` can write, rendered from the finished report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Artifact {
+    Json,
+    Sarif,
+    Markdown,
+    Cyclonedx,
+}
+
+impl Artifact {
+    pub const ALL: [Artifact; 4] = [Artifact::Json, Artifact::Sarif, Artifact::Markdown, Artifact::Cyclonedx];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Artifact::Json => "json",
+            Artifact::Sarif => "sarif",
+            Artifact::Markdown => "markdown",
+            Artifact::Cyclonedx => "cyclonedx",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Artifact::Json => "report.json",
+            Artifact::Sarif => "report.sarif",
+            Artifact::Markdown => "report.md",
+            Artifact::Cyclonedx => "report.cdx.json",
+        }
+    }
+
+    fn render(self, report: &ScanReport, decision: &Decision) -> Result<String, String> {
+        let pretty = |value: &Value| serde_json::to_string_pretty(value).map(|s| s + "\n").map_err(|e| e.to_string());
+        match self {
+            Artifact::Json => pretty(&json_report(report, decision)?),
+            Artifact::Sarif => pretty(&sarif(report, decision)),
+            Artifact::Markdown => Ok(markdown(report, decision)),
+            Artifact::Cyclonedx => pretty(&cyclonedx(report)),
+        }
+    }
+}
+
+impl std::str::FromStr for Artifact {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Artifact::ALL.into_iter().find(|a| a.name() == value).ok_or_else(|| {
+            let names: Vec<&str> = Artifact::ALL.iter().map(|a| a.name()).collect();
+            format!("unknown artifact {} (expected one of {})", value, names.join(", "))
+        })
+    }
+}
+
+/// The report as `schema report` describes it, plus the findings' verdict (`exit`, `passed`,
+/// `outcome` and its check-run `conclusion`),
+/// per-file verdicts, and each action with the checks it failed and its advisories. This is both
+/// `--format json` and the json artifact.
+pub fn json_report(report: &ScanReport, decision: &Decision) -> Result<Value, String> {
+    let mut value = serde_json::to_value(report).map_err(|e| e.to_string())?;
+    for (index, finding) in report.findings.iter().enumerate() {
+        value["findings"][index]["rule"] = json!(report.rule_id(finding.rule));
+    }
+    value["passed"] = json!(decision.exit.code == 0);
+    value["exit"] = serde_json::to_value(&decision.exit).map_err(|e| e.to_string())?;
+    value["outcome"] = json!(decision.outcome.name());
+    value["conclusion"] = json!(decision.outcome.conclusion());
+    value["files"] = serde_json::to_value(&decision.files).map_err(|e| e.to_string())?;
+    value["actions"] = json!(action_entries(report));
+    value["workflows"] = workflow_tree(report);
+    Ok(value)
+}
+
+/// Key of the job-level `uses:` of a reusable workflow call, which has no step
+const JOB_LEVEL: &str = "(job)";
+
+/// Findings nested under workflow file, job and step, a per-action finding under every step that
+/// uses the action. Each step of a matrix job appears once.
+fn workflow_tree(report: &ScanReport) -> Value {
+    let mut tree: BTreeMap<&str, BTreeMap<String, BTreeMap<String, Vec<Value>>>> = BTreeMap::new();
+    for file in report.workflow_actions.keys() {
+        tree.entry(file).or_default();
+    }
+    for finding in &report.findings {
+        for site in report.sites(finding) {
+            let Some(file) = report.workflow_actions.keys().find(|f| **f == site.file) else { continue };
+            let step = site.step.unwrap_or_else(|| JOB_LEVEL.to_string());
+            let entries = tree.entry(file).or_default().entry(site.job).or_default().entry(step).or_default();
+            let entry = json!({
+                "rule": report.rule_id(finding.rule),
+                "level": finding.level,
+                "action": finding.action,
+                "message": finding.message,
+                "fingerprint": finding.fingerprint,
+            });
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+    }
+    json!(tree)
+}
+
+fn action_entries(report: &ScanReport) -> Vec<Value> {
+    let actions: BTreeSet<&String> = report.workflow_actions.values().flatten().collect();
+    actions
+        .into_iter()
+        .map(|action| {
+            let findings: Vec<&Finding> = report.findings.iter().filter(|f| &f.action == action).collect();
+            let failed: BTreeSet<&str> = findings.iter().map(|f| report.rule_id(f.rule)).collect();
+            let advisories: Vec<Value> = findings
+                .iter()
+                .filter_map(|f| {
+                    let advisory = f.advisory.as_ref()?;
+                    Some(json!({ "id": advisory.id, "title": advisory.title, "severity": f.severity, "sources": advisory.sources }))
+                })
+                .collect();
+            let mut entry = json!({
+                "action": action,
+                "failed_checks": failed,
+                "advisories": advisories,
+                "unchecked": report.unchecked.contains(action),
+            });
+            if let Some(version) = report.pins.version(action) {
+                entry["pin_version"] = json!(version);
+            }
+            if let Some(parsed) = ActionRef::from_action_string(action) {
+                entry["owner"] = json!(parsed.owner);
+                entry["repo"] = json!(parsed.repo);
+                entry["path"] = json!(parsed.path);
+                entry["version"] = json!(parsed.version);
+                entry["is_commit_sha"] = json!(parsed.is_commit_sha());
+                entry["ref_kind"] = json!(parsed.kind.describe());
+            } else if let Some(image) = DockerRef::parse(action) {
+                entry["registry"] = json!(image.registry);
+                entry["namespace"] = json!(image.namespace);
+                entry["image"] = json!(image.name);
+                entry["version"] = json!(image.tag);
+                entry["digest"] = json!(image.digest);
+            }
+            entry
+        })
+        .collect()
+}
+
+fn sarif_level(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Note => "note",
+    }
+}
+
+/// The `security-severity` score code scanning ranks alerts by: the midpoint of the CVSS band
+fn security_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Low => "2.0",
+        Severity::Medium => "5.5",
+        Severity::High => "8.0",
+        Severity::Critical => "9.5",
+    }
+}
+
+/// SARIF 2.1.0, as `--format sarif` and the sarif artifact, for `upload-sarif`. A finding about an
+/// action is located at the `uses:` lines of the action; findings with no file are given the action
+/// as a logical location. A rule is scored by the most severe advisory among its results. The
+/// run's `fileVerdicts` map each workflow file to its verdict, as the JSON report's `files` list.
+pub fn sarif(report: &ScanReport, decision: &Decision) -> Value {
+    // Several rules can share an id under --rule-id-compat
+    let mut by_id: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+    for finding in &report.findings {
+        by_id.entry(report.rule_id(finding.rule)).or_default().push(finding);
+    }
+    let rules: Vec<Value> = by_id
+        .into_iter()
+        .map(|(id, findings)| {
+            let mut properties = json!({ "category": format!("{:?}", findings[0].rule.category()) });
+            if let Some(severity) = findings.iter().filter_map(|f| f.severity).max() {
+                properties["security-severity"] = json!(security_severity(severity));
+            }
+            json!({ "id": id, "name": id, "properties": properties })
+        })
+        .collect();
+    let sites = blame::sites(report);
+    let results: Vec<Value> = report
+        .findings
+        .iter()
+        .zip(&sites)
+        .map(|(finding, sites)| {
+            let mut result = json!({
+                "ruleId": report.rule_id(finding.rule),
+                "level": sarif_level(finding.level),
+                "message": { "text": finding.message },
+            });
+            if let Some(severity) = finding.severity {
+                result["properties"] = json!({ "severity": severity.name() });
+            }
+            if let Some(suppression) = finding.suppression.as_ref().filter(|_| finding.is_suppressed()) {
+                result["suppressions"] = json!([{ "kind": "external", "justification": suppression.reason }]);
+            }
+            if !finding.fingerprint.is_empty() {
+                result["partialFingerprints"] = json!({ "findingFingerprint/v1": finding.fingerprint });
+            }
+            let locations: Vec<Value> = sites
+                .iter()
+                .map(|(file, line)| {
+                    json!({ "physicalLocation": { "artifactLocation": { "uri": file }, "region": { "startLine": line } } })
+                })
+                .collect();
+            if !locations.is_empty() {
+                result["locations"] = json!(locations);
+            } else if let Some(file) = &finding.file {
+                result["locations"] = json!([{ "physicalLocation": { "artifactLocation": { "uri": file } } }]);
+            } else if !finding.action.is_empty() {
+                result["locations"] = json!([{ "logicalLocations": [{ "name": finding.action, "kind": "module" }] }]);
+            }
+            result
+        })
+        .collect();
+    let file_verdicts: BTreeMap<&str, Value> = decision
+        .files
+        .iter()
+        .map(|verdict| {
+            let mut entry = json!({ "passed": verdict.passed, "counts": verdict.counts });
+            if let Some(worst) = verdict.worst {
+                entry["worst"] = json!(sarif_level(worst));
+            }
+            (verdict.file.as_str(), entry)
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": { "name": "gh-action-security-scanner", "version": env!("CARGO_PKG_VERSION"), "rules": rules } },
+            "results": results,
+            "properties": { "summary": report.summary, "fileVerdicts": file_verdicts },
+        }],
+    })
+}
+
+/// Rules about how an action is pinned, which the markdown report tables with a suggested fix
+pub(crate) const PINNING: [Rule; 8] = [
+    Rule::UnpinnedDependency,
+    Rule::UnstableReference,
+    Rule::TagPinnedCritical,
+    Rule::UntrustedOwnerNoSha,
+    Rule::AbbreviatedSha,
+    Rule::DockerTagPinned,
+    Rule::UnpinnedDispatch,
+    Rule::DynamicReference,
+];
+
+/// The sections of the markdown report a finding goes in, besides the list of other findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Vulnerable,
+    Pinning,
+    UntrustedOwner,
+    Other,
+}
+
+impl Section {
+    fn of(finding: &Finding) -> Section {
+        match finding.rule {
+            _ if finding.rule.category() == Category::Vulnerability => Section::Vulnerable,
+            rule if PINNING.contains(&rule) => Section::Pinning,
+            Rule::UntrustedOwner => Section::UntrustedOwner,
+            _ => Section::Other,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Section::Vulnerable => "vulnerable",
+            Section::Pinning => "insecure pinning",
+            Section::UntrustedOwner => "untrusted owner",
+            Section::Other => "other",
+        }
+    }
+}
+
+/// Text for a table cell, which a `|` or a line break would end early
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// The advisory's page: GitHub's for GHSA ids, OSV's for the rest
+fn advisory_link(id: &str) -> String {
+    match id.starts_with("GHSA-") {
+        true => format!("[{}](https://github.com/advisories/{})", id, id),
+        false => format!("[{}](https://osv.dev/vulnerability/{})", id, id),
+    }
+}
+
+fn suggested_fix(finding: &Finding) -> &'static str {
+    match finding.rule {
+        Rule::TagPinnedCritical | Rule::UntrustedOwnerNoSha => "Pin to the tag's commit SHA; the `fix` subcommand can rewrite it",
+        Rule::AbbreviatedSha => "Use the full 40-character commit SHA",
+        Rule::DockerTagPinned => "Pin the image to its `sha256` digest",
+        Rule::DynamicReference => "Name the action and ref literally",
+        _ => "Pin to a full commit SHA",
+    }
+}
+
+/// The markdown artifact, for a PR comment: the verdict, a summary line with failures by section,
+/// tables of vulnerable actions, insecure pins and untrusted owners, then every other finding.
+/// A scan with nothing to report says so in an all-clear section.
+pub(crate) fn markdown(report: &ScanReport, decision: &Decision) -> String {
+    let mut out = String::from("### Action security scan\n\n");
+    let verdict = match decision.outcome {
+        Outcome::Clean => (Marker::Ok, "pass"),
+        Outcome::PassedWithWarnings => (Marker::Warn, "pass with warnings"),
+        Outcome::Failed => (Marker::Fail, "fail"),
+    };
+    out.push_str(&format!("**Verdict: {} {}** ({})\n\n", verdict.0, verdict.1, decision.exit.reason));
+    let shown: Vec<&Finding> = report.findings.iter().filter(|f| !f.is_suppressed()).collect();
+    let sections = [Section::Vulnerable, Section::Pinning, Section::UntrustedOwner, Section::Other];
+    let failures: Vec<String> = sections
+        .iter()
+        .map(|section| (section, shown.iter().filter(|f| f.level == Level::Error && Section::of(f) == *section).count()))
+        .filter(|(_, count)| *count > 0)
+        .map(|(section, count)| format!("{} {}", count, section.name()))
+        .collect();
+    let failures = match failures.is_empty() {
+        true => String::new(),
+        false => format!("; failures: {}", failures.join(", ")),
+    };
+    out.push_str(&format!(
+        "{} actions scanned: {} error, {} warning, {} note{}\n",
+        report.actions_scanned, decision.counts.error, decision.counts.warning, decision.counts.note, failures
+    ));
+
+    if !decision.files.is_empty() {
+        out.push_str("\n| Workflow | Result | Errors | Warnings | Notes | Trigger surface |\n|---|---|---|---|---|---|\n");
+        for file in &decision.files {
+            let result = if file.passed { Marker::Ok } else { Marker::Fail };
+            let surface = report.trigger_surfaces.get(&file.file).map(|s| s.describe()).unwrap_or_default();
+            out.push_str(&format!(
+                "| `{}` | {} | {} | {} | {} | {} |\n",
+                file.file, result, file.counts.error, file.counts.warning, file.counts.note, surface
+            ));
+        }
+    }
+    if shown.is_empty() {
+        out.push_str(&format!(
+            "\n#### All clear\n\nNo findings: {} workflow file(s) and {} actions scanned.\n",
+            report.workflow_actions.len(),
+            report.actions_scanned
+        ));
+    }
+
+    let vulnerable: Vec<&&Finding> = shown.iter().filter(|f| Section::of(f) == Section::Vulnerable).collect();
+    if !vulnerable.is_empty() {
+        out.push_str("\n#### Vulnerable actions\n\n| Action | Advisory | Severity | Title |\n|---|---|---|---|\n");
+        for finding in vulnerable {
+            let (advisory, title) = match &finding.advisory {
+                Some(advisory) => (advisory_link(&advisory.id), cell(&advisory.title)),
+                None => ("-".to_string(), cell(&report.pins.annotate(&finding.message))),
+            };
+            let severity = finding.severity.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string());
+            // An advisory inside its grace period says when it starts failing
+            let title = match &finding.due_by {
+                Some(due_by) => format!("{} (due by {})", title, due_by),
+                None => title,
+            };
+            out.push_str(&format!("| `{}` | {} | {} | {} |\n", report.pins.display(&finding.action), advisory, severity, title));
+        }
+    }
+
+    let pinning: Vec<&&Finding> = shown.iter().filter(|f| Section::of(f) == Section::Pinning).collect();
+    if !pinning.is_empty() {
+        out.push_str("\n#### Insecure pinning\n\n| Action | File | Line | Problem | Suggested fix |\n|---|---|---|---|---|\n");
+        for finding in pinning {
+            // A finding about an action is listed at each line that uses it
+            let mut sites = blame::finding_sites(report, finding);
+            if sites.is_empty() {
+                sites.push((finding.file.clone().unwrap_or_else(|| "-".to_string()), 0));
+            }
+            for (file, line) in sites {
+                let line = if line == 0 { "-".to_string() } else { line.to_string() };
+                out.push_str(&format!(
+                    "| `{}` | `{}` | {} | {} {} | {} |\n",
+                    report.pins.display(&finding.action),
+                    file,
+                    line,
+                    finding.level.marker(),
+                    cell(&report.pins.annotate(&finding.message)),
+                    suggested_fix(finding)
+                ));
+            }
+        }
+    }
+
+    let untrusted: Vec<&&Finding> = shown.iter().filter(|f| Section::of(f) == Section::UntrustedOwner).collect();
+    if !untrusted.is_empty() {
+        out.push_str("\n#### Untrusted owners\n\n| Action | Owner | Level |\n|---|---|---|\n");
+        for finding in untrusted {
+            let owner = finding.action.split('/').next().unwrap_or("");
+            out.push_str(&format!("| `{}` | `{}` | {} {} |\n", report.pins.display(&finding.action), owner, finding.level.marker(), finding.level));
+        }
+    }
+
+    // Suppressed findings are listed here too, with their reason
+    let other: Vec<&Finding> = report.findings.iter().filter(|f| f.is_suppressed() || Section::of(f) == Section::Other).collect();
+    if !other.is_empty() {
+        out.push_str("\n#### Other findings\n\n");
+        for finding in other {
+            let location = finding.location().map(|l| format!(" `{}`", l)).unwrap_or_default();
+            let suppressed = match &finding.suppression {
+                Some(s) if s.expired => format!(" (suppression expired: {})", s.reason),
+                Some(s) => format!(" (suppressed: {})", s.reason),
+                None => String::new(),
+            };
+            out.push_str(&format!(
+                "- {} **{}**{}: {}{}\n",
+                finding.level.marker(),
+                report.rule_id(finding.rule),
+                location,
+                report.pins.annotate(&finding.message),
+                suppressed
+            ));
+        }
+    }
+    if !report.unchecked.is_empty() {
+        out.push_str("\n#### Could not be checked\n\n");
+        for action in &report.unchecked {
+            out.push_str(&format!("- `{}`\n", report.pins.display(action)));
+        }
+    }
+    out
+}
+
+/// CycloneDX 1.5 SBOM with one component per action used, identified by package URL
+fn cyclonedx(report: &ScanReport) -> Value {
+    let actions: BTreeSet<&String> = report.workflow_actions.values().flatten().collect();
+    let components: Vec<Value> = actions
+        .into_iter()
+        .filter_map(|action| {
+            let purl = package_url(action)?;
+            let (name, version) = action.split_once('@')?;
+            Some(json!({
+                "type": "application",
+                "bom-ref": purl,
+                "name": name,
+                "version": version,
+                "purl": purl,
+            }))
+        })
+        .collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": report.generated_at,
+            "tools": { "components": [{ "type": "application", "name": "gh-action-security-scanner", "version": env!("CARGO_PKG_VERSION") }] },
+        },
+        "components": components,
+    })
+}
+
+/// What happened to one requested artifact
+pub struct Written {
+    pub artifact: Artifact,
+    pub path: PathBuf,
+    pub result: Result<(), String>,
+}
+
+/// Render and write each artifact. A failure is recorded for that artifact and the rest are
+/// still written.
+pub fn write_all(dir: &Path, artifacts: &[Artifact], report: &ScanReport, decision: &Decision) -> Vec<Written> {
+    let created = fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e));
+    let requested: BTreeSet<Artifact> = artifacts.iter().copied().collect();
+    requested
+        .into_iter()
+        .map(|artifact| {
+            let path = dir.join(artifact.file_name());
+            let result = created
+                .clone()
+                .and_then(|_| artifact.render(report, decision))
+                .and_then(|content| fs::write(&path, content).map_err(|e| e.to_string()));
+            Written { artifact, path, result }
+        })
+        .collect()
+}
+
+/// `--report`: one artifact, written to `path` rather than under its usual name
+pub fn write_report(path: &Path, artifact: Artifact, report: &ScanReport, decision: &Decision) -> Written {
+    let result = artifact.render(report, decision).and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+    Written { artifact, path: path.to_path_buf(), result }
+}
+
+/// The index of artifacts written, and the reason for each that was not
+pub fn print_index(out: &mut dyn Write, written: &[Written]) -> io::Result<()> {
+    writeln!(out, "\nArtifacts:")?;
+    for entry in written {
+        match &entry.result {
+            Ok(()) => writeln!(out, "{} {}: {}", Marker::Ok, entry.artifact.name(), entry.path.display())?,
+            Err(e) => writeln!(out, "{} {}: {} not written: {}", Marker::Fail, entry.artifact.name(), entry.path.display(), e)?,
+        }
+    }
+    Ok(())
+}
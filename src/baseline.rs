@@ -0,0 +1,159 @@
+//! `--baseline`: findings a repository had when it adopted the scanner, accepted until they are
+//! fixed, so that only new ones fail. `--write-baseline` records them.
+
+use crate::compat;
+use crate::report::{Category, Finding, Level, Rule, ScanReport, Suppression, canonical_action};
+use crate::version::SCANNER_VERSION;
+use crate::ScanError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+/// One accepted finding: its check, in one workflow file, for one action string as written
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Entry {
+    /// Workflow file; empty for scan-level findings
+    pub file: String,
+    /// Empty for findings about no one action
+    pub action: String,
+    /// Rule id, e.g. unpinned-dependency, or under `--rule-id-compat` the tool's id for it
+    pub rule: String,
+    /// Advisory id, for vulnerability findings, so that a new advisory is a new finding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub advisory: Option<String>,
+}
+
+impl Entry {
+    /// The entry with its action as GitHub resolves it, which matching compares, so an entry
+    /// still covers a finding whose `uses:` only changed case
+    fn canonical(&self) -> Entry {
+        Entry { action: canonical_action(&self.action), ..self.clone() }
+    }
+
+    /// e.g. `known-vulnerability (GHSA-xxxx) for owner/repo@v1 in .github/workflows/ci.yml`
+    pub fn describe(&self) -> String {
+        let mut out = self.rule.clone();
+        if let Some(advisory) = &self.advisory {
+            out.push_str(&format!(" ({})", advisory));
+        }
+        if !self.action.is_empty() {
+            out.push_str(&format!(" for {}", self.action));
+        }
+        if !self.file.is_empty() {
+            out.push_str(&format!(" in {}", self.file));
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The scanner that wrote the file
+    #[serde(default)]
+    pub scanner_version: Option<String>,
+    pub entries: BTreeSet<Entry>,
+}
+
+/// Whether a baseline may cover the finding. Bans and informational findings never fail on
+/// their own account, so they are left out.
+fn eligible(finding: &Finding) -> bool {
+    !matches!(finding.rule.category(), Category::Blocked | Category::Informational) && !finding.is_suppressed()
+}
+
+/// The entries a finding needs: one per workflow file it is in. A finding about an action, such
+/// as an advisory, is in every file that uses the action, so a new use of it fails.
+fn keys(report: &ScanReport, finding: &Finding) -> Vec<Entry> {
+    let files: Vec<String> = match &finding.file {
+        Some(file) => vec![file.clone()],
+        None if !finding.action.is_empty() => report
+            .workflow_actions
+            .iter()
+            .filter(|(_, actions)| actions.contains(&finding.action))
+            .map(|(file, _)| file.clone())
+            .collect(),
+        None => vec![String::new()],
+    };
+    files
+        .into_iter()
+        .map(|file| Entry {
+            file,
+            action: finding.action.clone(),
+            rule: finding.rule.id().to_string(),
+            advisory: finding.advisory.as_ref().map(|a| a.id.clone()),
+        })
+        .collect()
+}
+
+/// The baseline of the report's findings, for `--write-baseline`
+pub fn snapshot(report: &ScanReport) -> Baseline {
+    let entries = report.findings.iter().filter(|f| eligible(f)).flat_map(|f| keys(report, f)).collect();
+    Baseline { scanner_version: Some(SCANNER_VERSION.to_string()), entries }
+}
+
+/// Read a baseline file; the caller checks the version it records
+pub fn load(path: &str) -> Result<Baseline, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path).map_err(|e| format!("baseline {}: {}", path, e))?;
+    let baseline: Baseline = serde_json::from_str(&content).map_err(|e| format!("baseline {}: {}", path, e))?;
+    Ok(baseline)
+}
+
+pub fn write(path: &str, baseline: &Baseline) -> Result<(), ScanError> {
+    let context = format!("baseline {}", path);
+    let content = serde_json::to_string_pretty(baseline).map_err(|e| ScanError::io(&context, e.into()))? + "\n";
+    fs::write(path, content).map_err(|e| ScanError::io(&context, e))
+}
+
+/// Mark the findings the baseline covers in every file they are in as baselined: still reported,
+/// but not failing. Actions are compared in their canonical form, and rules by their own ids or
+/// the `--rule-id-compat` tool's. Returns the entries that matched nothing, whose findings are gone.
+pub fn apply(report: &mut ScanReport, baseline: &Baseline, path: &str) -> Vec<Entry> {
+    let mut accepted: BTreeMap<Entry, Vec<&Entry>> = BTreeMap::new();
+    for entry in &baseline.entries {
+        accepted.entry(entry.canonical()).or_default().push(entry);
+    }
+    let mut used: BTreeSet<&Entry> = BTreeSet::new();
+    let covered: Vec<bool> = report
+        .findings
+        .iter()
+        .map(|finding| {
+            // An entry may name the rule by its own id or, under --rule-id-compat, by the tool's
+            let ids = [finding.rule.id(), compat::rule_id(report.rule_ids, finding.rule)];
+            let matched: Vec<Vec<&Entry>> = keys(report, finding)
+                .iter()
+                .map(|key| {
+                    let key = key.canonical();
+                    let mut entries: Vec<&Entry> = Vec::new();
+                    for id in ids {
+                        if let Some(found) = accepted.get(&Entry { rule: id.to_string(), ..key.clone() }) {
+                            entries.extend(found);
+                        }
+                    }
+                    entries
+                })
+                .collect();
+            let covered = eligible(finding) && !matched.is_empty() && matched.iter().all(|entries| !entries.is_empty());
+            if covered {
+                used.extend(matched.into_iter().flatten());
+            }
+            covered
+        })
+        .collect();
+    for (finding, covered) in report.findings.iter_mut().zip(covered) {
+        if covered {
+            finding.suppression = Some(Suppression {
+                reason: format!("in baseline {}", path),
+                expires: None,
+                expired: false,
+                baseline: true,
+                unchanged: false,
+            });
+        }
+    }
+    baseline.entries.iter().filter(|entry| !used.contains(entry)).cloned().collect()
+}
+
+/// A baseline entry whose finding is gone, which `--update-baseline` removes
+pub fn unused_finding(entry: &Entry) -> Finding {
+    let message = format!("baseline entry {} matched no finding; --update-baseline removes it", entry.describe());
+    Finding::scan_level(Rule::UnusedConfigEntry, Level::Warning, message)
+}
@@ -0,0 +1,218 @@
+use crate::report::{Finding, ScanReport};
+use crate::rules;
+use chrono::{TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::process::Command;
+
+/// The commit that last changed a finding's line (`--blame`)
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Blame {
+    pub sha: String,
+    pub author: String,
+    /// Author date, YYYY-MM-DD
+    pub date: String,
+}
+
+impl Blame {
+    /// "introduced by jane@ 2023-11-02 in a1b2c3d"
+    pub fn describe(&self) -> String {
+        let user = self.author.split('@').next().unwrap_or(&self.author);
+        format!("introduced by {}@ {} in {}", user, self.date, &self.sha[..self.sha.len().min(7)])
+    }
+}
+
+/// The warning when `--blame` finds no `git` to run, such as in a minimal CI image. The scan goes
+/// on and reports its findings without blame.
+pub const GIT_MISSING: &str = "Warning: --blame needs the git command, which is not installed or not on PATH; findings are reported without blame";
+
+fn git(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git").args(args).output().map_err(|e| format!("could not run git: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether blame can run: false when there is no `git` to run it with (see [`GIT_MISSING`]).
+/// Fails early when the scan does not run inside a git checkout.
+pub fn check_checkout() -> Result<bool, String> {
+    match Command::new("git").args(["rev-parse", "--is-inside-work-tree"]).output() {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Ok(output) if output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true" => Ok(true),
+        _ => Err("--blame needs a git checkout".to_string()),
+    }
+}
+
+fn is_shallow() -> bool {
+    git(&["rev-parse", "--is-shallow-repository"]).is_ok_and(|s| s.trim() == "true")
+}
+
+#[derive(Default)]
+struct CommitInfo {
+    author: String,
+    time: i64,
+    boundary: bool,
+}
+
+/// Outcome of blaming one line
+enum LineBlame {
+    Found(Blame),
+    /// The line has not been committed
+    Uncommitted,
+    /// A shallow clone's boundary commit: the real author lies beyond the fetched history
+    Boundary,
+}
+
+/// Blame the given lines of one file in a single `git blame` run
+fn blame_lines(file: &str, lines: &BTreeSet<usize>) -> Result<BTreeMap<usize, LineBlame>, String> {
+    let ranges: Vec<String> = lines.iter().map(|line| format!("{},{}", line, line)).collect();
+    let mut args = vec!["blame", "--porcelain"];
+    for range in &ranges {
+        args.extend(["-L", range.as_str()]);
+    }
+    args.extend(["--", file]);
+    let output = git(&args)?;
+
+    let mut commits: BTreeMap<String, CommitInfo> = BTreeMap::new();
+    let mut found: Vec<(usize, String)> = Vec::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            current = None;
+            continue;
+        }
+        if current.is_none() {
+            // Header: <sha> <original line> <final line> [<group size>]
+            let mut fields = line.split(' ');
+            let (Some(sha), Some(_), Some(final_line)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            if let Ok(final_line) = final_line.parse() {
+                found.push((final_line, sha.to_string()));
+                commits.entry(sha.to_string()).or_default();
+                current = Some(sha.to_string());
+            }
+            continue;
+        }
+        let Some(info) = current.as_ref().and_then(|sha| commits.get_mut(sha)) else { continue };
+        match line.split_once(' ') {
+            Some(("author-mail", mail)) => info.author = mail.trim_matches(|c| c == '<' || c == '>').to_string(),
+            Some(("author-time", time)) => info.time = time.parse().unwrap_or(0),
+            _ if line == "boundary" => info.boundary = true,
+            _ => {}
+        }
+    }
+
+    let shallow = is_shallow();
+    Ok(found
+        .into_iter()
+        .map(|(line, sha)| {
+            let info = &commits[&sha];
+            let blame = if sha.bytes().all(|b| b == b'0') {
+                LineBlame::Uncommitted
+            } else if info.boundary && shallow {
+                LineBlame::Boundary
+            } else {
+                let date = Utc.timestamp_opt(info.time, 0).single().map(|d| d.format("%Y-%m-%d").to_string());
+                LineBlame::Found(Blame {
+                    sha,
+                    author: info.author.clone(),
+                    date: date.unwrap_or_default(),
+                })
+            };
+            (line, blame)
+        })
+        .collect())
+}
+
+/// Lines of a workflow file whose `uses:` is exactly `action`
+fn use_sites(report: &ScanReport, file: &str, action: &str) -> Vec<usize> {
+    let Ok(source) = report.workflow_source(file) else { return Vec::new() };
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| rules::uses_value(line) == Some(action))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Where each finding points: its own line, or for findings about an action, the lines that use
+/// the action (in the finding's file, else in every workflow). SARIF output locates findings the
+/// same way.
+pub fn sites(report: &ScanReport) -> Vec<Vec<(String, usize)>> {
+    report.findings.iter().map(|finding| finding_sites(report, finding)).collect()
+}
+
+/// Where one finding of the report points, as [`sites`] has it
+pub fn finding_sites(report: &ScanReport, finding: &Finding) -> Vec<(String, usize)> {
+    match (&finding.file, finding.line) {
+        (Some(file), Some(line)) => vec![(file.clone(), line)],
+        _ if finding.action.is_empty() => Vec::new(),
+        (Some(file), None) => use_sites(report, file, &finding.action).into_iter().map(|line| (file.clone(), line)).collect(),
+        (None, _) => report
+            .workflow_actions
+            .iter()
+            .filter(|(_, actions)| actions.contains(&finding.action))
+            .flat_map(|(file, _)| use_sites(report, file, &finding.action).into_iter().map(move |line| (file.clone(), line)))
+            .collect(),
+    }
+}
+
+/// Attach blame to every finding that points at a line. A per-action finding gets the use of the
+/// action that came first. Returns the blame of every line, and notes for the files or lines whose
+/// blame was omitted: untracked files, uncommitted lines and shallow-clone boundaries.
+pub fn assign(report: &mut ScanReport) -> (Vec<(String, usize, Blame)>, Vec<String>) {
+    let sites = sites(report);
+    let mut wanted: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    for (file, line) in sites.iter().flatten() {
+        wanted.entry(file.clone()).or_default().insert(*line);
+    }
+
+    let mut notes = Vec::new();
+    let mut blamed: BTreeMap<(String, usize), Blame> = BTreeMap::new();
+    for (file, lines) in &wanted {
+        match blame_lines(file, lines) {
+            Ok(lines) => {
+                let uncommitted = lines.values().filter(|b| matches!(b, LineBlame::Uncommitted)).count();
+                let boundary = lines.values().filter(|b| matches!(b, LineBlame::Boundary)).count();
+                if uncommitted > 0 {
+                    notes.push(format!("{}: {} line(s) not committed yet", file, uncommitted));
+                }
+                if boundary > 0 {
+                    notes.push(format!("{}: {} line(s) predate the shallow clone's history", file, boundary));
+                }
+                for (line, blame) in lines {
+                    if let LineBlame::Found(blame) = blame {
+                        blamed.insert((file.clone(), line), blame);
+                    }
+                }
+            }
+            Err(e) if e.contains("no such path") => notes.push(format!("{}: not tracked by git", file)),
+            Err(e) => notes.push(format!("{}: {}", file, e.lines().next().unwrap_or("git blame failed"))),
+        }
+    }
+
+    for (finding, sites) in report.findings.iter_mut().zip(&sites) {
+        finding.blame = sites.iter().filter_map(|site| blamed.get(site)).min_by(|a, b| a.date.cmp(&b.date)).cloned();
+    }
+    let lines = blamed.into_iter().map(|((file, line), blame)| (file, line, blame)).collect();
+    (lines, notes)
+}
+
+/// Print who introduced each line a finding points at, and why blame is missing where it is
+pub fn print(out: &mut dyn Write, lines: &[(String, usize, Blame)], notes: &[String]) -> io::Result<()> {
+    if lines.is_empty() && notes.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nIntroduced by:")?;
+    for (file, line, blame) in lines {
+        writeln!(out, "- {}:{}: {}", file, line, blame.describe())?;
+    }
+    for note in notes {
+        writeln!(out, "- blame omitted for {}", note)?;
+    }
+    Ok(())
+}
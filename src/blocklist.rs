@@ -0,0 +1,35 @@
+use crate::ActionRef;
+use crate::config::{BlockedEntry, Config};
+use crate::report::{Finding, Level, Rule};
+
+/// The `blocked_actions` entry, else the `blocked_owners` entry, that bans the action, with the
+/// key it is under. Names and refs compare case-insensitively, as GitHub resolves them; an entry
+/// with an `@ref` bans only that ref.
+pub fn matching<'c>(action_ref: &ActionRef, config: &'c Config) -> Option<(&'static str, &'c BlockedEntry)> {
+    let full_name = action_ref.full_name();
+    let action = config.blocked_actions.iter().flatten().find(|entry| match entry.name().split_once('@') {
+        Some((name, version)) => name.eq_ignore_ascii_case(&full_name) && version.eq_ignore_ascii_case(&action_ref.version),
+        None => entry.name().eq_ignore_ascii_case(&full_name),
+    });
+    if let Some(entry) = action {
+        return Some(("blocked_actions", entry));
+    }
+    let owner = config.blocked_owners.iter().flatten().find(|entry| entry.name().eq_ignore_ascii_case(&action_ref.owner));
+    owner.map(|entry| ("blocked_owners", entry))
+}
+
+pub fn is_blocked(action_ref: &ActionRef, config: &Config) -> bool {
+    matching(action_ref, config).is_some()
+}
+
+/// Report `action` if the config bans it. The finding fails the scan whatever its pinning, level
+/// or ignore-file entries.
+pub fn check(action: &str, config: &Config) -> Option<Finding> {
+    let action_ref = ActionRef::from_action_string(action)?;
+    let (key, entry) = matching(&action_ref, config)?;
+    let mut message = format!("Action {} is blocked by {} entry {}", action, key, entry.name());
+    if let Some(reason) = entry.reason() {
+        message.push_str(&format!(": {}", reason));
+    }
+    Some(Finding::new(Rule::BlockedDependency, Level::Error, action, message))
+}
@@ -0,0 +1,542 @@
+use crate::cache::DiskCache;
+use crate::config::Config;
+use crate::decision::{self, Decision, ExitPolicy};
+use crate::filter::ActionFilter;
+use crate::fix::resolve_tag_sha_cached;
+use crate::github::GitHubApi;
+use crate::{indicators, inputs, lookup};
+use crate::pincomment::CommentStyle;
+use crate::render::{self, Marker};
+use crate::report::{Finding, Level, Rule, ScanReport};
+use crate::scanner::{ScanEvents, Scanner};
+use crate::{ActionRef, AdvisoryPolicy, RefKind, Workflow, blocklist, is_trusted, rules};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The change the `review-bump` subcommand reviews, and how it reports the verdict
+pub struct ReviewOptions {
+    pub base: Option<String>,
+    pub head: Option<String>,
+    /// Pull request event payload to take the base and head from when they are not given
+    pub event_path: Option<PathBuf>,
+    /// Repository directories to look for changed workflows in; `.github/workflows` when empty
+    pub workflows_dirs: Vec<String>,
+    /// Write the markdown verdict to a file instead of stdout
+    pub output: Option<PathBuf>,
+    /// Fail when a bumped action could not be checked
+    pub fail_on_unchecked: bool,
+    /// List at most this many findings in the verdict, highest severity first
+    pub max_findings: Option<usize>,
+}
+
+/// One `uses:` reference whose pin differs between the base and head revisions
+#[derive(Debug, Clone)]
+pub struct PinBump {
+    pub workflow: String,
+    pub line: usize,
+    /// `owner/repo` (or `owner/repo/path`) without the ref
+    pub name: String,
+    /// The versions the base revision used for the same action; empty for a newly added action
+    pub from: Vec<String>,
+    /// The full `uses:` value at head
+    pub to: String,
+    /// The tag from a trailing `# v1.2.3` comment, as Dependabot writes next to SHA pins, read
+    /// with the configured pin comment style
+    pub comment_tag: Option<String>,
+}
+
+impl PinBump {
+    fn version(&self) -> &str {
+        self.to.split_once('@').map_or("", |(_, version)| version)
+    }
+}
+
+/// The revisions to compare: `--base`/`--head`, else the pull request in `GITHUB_EVENT_PATH`
+pub fn revisions(opt: &ReviewOptions) -> Result<(String, String), Box<dyn std::error::Error>> {
+    if let (Some(base), Some(head)) = (&opt.base, &opt.head) {
+        return Ok((base.clone(), head.clone()));
+    }
+    let path = opt
+        .event_path
+        .as_ref()
+        .ok_or("review-bump needs --base and --head, or a pull request event in GITHUB_EVENT_PATH")?;
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let payload: Value = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (base, head) = event_revisions(&payload).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok((opt.base.clone().unwrap_or(base), opt.head.clone().unwrap_or(head)))
+}
+
+/// Base and head commit SHAs of a `pull_request` / `pull_request_target` event payload
+pub fn event_revisions(payload: &Value) -> Result<(String, String), String> {
+    let pull_request = payload.get("pull_request").ok_or("event is not a pull request event")?;
+    let sha = |side: &str| {
+        pull_request[side]["sha"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("pull_request.{}.sha is missing", side))
+    };
+    Ok((sha("base")?, sha("head")?))
+}
+
+fn git(args: &[&str]) -> Result<String, std::io::Error> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A file's content at a revision, or `None` when it does not exist there
+fn show(revision: &str, path: &str) -> Option<String> {
+    git(&["show", &format!("{}:{}", revision, path)]).ok()
+}
+
+/// Every `uses:` value in a workflow's source with its line and the version its trailing comment names
+fn pins(source: &str, style: &CommentStyle) -> Vec<(usize, String, Option<String>)> {
+    let uses_regex = Regex::new(r#"^\s*(?:-\s+)?uses:\s*["']?([^\s"'#]+)["']?(?:\s+#(.*))?"#).unwrap();
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let caps = uses_regex.captures(line)?;
+            let version = caps.get(2).and_then(|m| style.version(m.as_str())).map(str::to_string);
+            Some((index + 1, caps[1].to_string(), version))
+        })
+        .filter(|(_, value, _)| value.contains('@') && !value.contains("${{"))
+        .collect()
+}
+
+/// Pins present at `head` but not at `base`, across the workflow files that changed between them
+pub fn changed_pins(base: &str, head: &str, dirs: &[String], style: &CommentStyle) -> Result<Vec<PinBump>, std::io::Error> {
+    let mut args = vec!["diff", "--name-only", base, head, "--"];
+    args.extend(dirs.iter().map(String::as_str));
+    let mut bumps = Vec::new();
+    for path in git(&args)?.lines().filter(|p| p.ends_with(".yml") || p.ends_with(".yaml")) {
+        // Deleted workflows bump nothing
+        let Some(head_source) = show(head, path) else { continue };
+        let base_pins: BTreeSet<String> = show(base, path)
+            .map(|source| pins(&source, style).into_iter().map(|(_, value, _)| value).collect())
+            .unwrap_or_default();
+        for (line, value, comment_tag) in pins(&head_source, style) {
+            if base_pins.contains(&value) {
+                continue;
+            }
+            let Some((name, _)) = value.split_once('@') else { continue };
+            let from = base_pins
+                .iter()
+                .filter_map(|old| old.split_once('@'))
+                .filter(|(old_name, _)| *old_name == name)
+                .map(|(_, version)| version.to_string())
+                .collect();
+            bumps.push(PinBump {
+                workflow: path.to_string(),
+                line,
+                name: name.to_string(),
+                from,
+                to: value,
+                comment_tag,
+            });
+        }
+    }
+    Ok(bumps)
+}
+
+/// `owner/repo` of every action the workflows at `revision` use, in any workflow file
+fn repositories_at(revision: &str, dirs: &[String], style: &CommentStyle) -> Result<BTreeSet<String>, std::io::Error> {
+    let mut args = vec!["ls-tree", "-r", "--name-only", revision, "--"];
+    args.extend(dirs.iter().map(String::as_str));
+    let mut repositories = BTreeSet::new();
+    for path in git(&args)?.lines().filter(|p| p.ends_with(".yml") || p.ends_with(".yaml")) {
+        let Some(source) = show(revision, path) else { continue };
+        for (_, value, _) in pins(&source, style) {
+            if let Some(action_ref) = ActionRef::from_action_string(&value) {
+                repositories.insert(action_ref.full_name().to_ascii_lowercase());
+            }
+        }
+    }
+    Ok(repositories)
+}
+
+/// Numeric components of a tag like `v4.1.2`, padded to three so `v4` compares as `v4.0.0`
+fn version_key(tag: &str) -> Option<Vec<u64>> {
+    let mut key = tag
+        .strip_prefix('v')
+        .unwrap_or(tag)
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    while key.len() < 3 {
+        key.push(0);
+    }
+    Some(key)
+}
+
+/// A bump to a lower version than the base used, comparing tags (or the tag comments of SHA pins)
+fn check_downgrade(bump: &PinBump, base_tags: &[String]) -> Option<Finding> {
+    let to_tag = match &bump.comment_tag {
+        Some(tag) => tag.as_str(),
+        // A SHA names no version, even one whose hex happens to be all digits
+        None if RefKind::classify(bump.version()) == RefKind::FullSha => return None,
+        None => bump.version(),
+    };
+    let to_key = version_key(to_tag)?;
+    let from_tag = base_tags
+        .iter()
+        .filter_map(|tag| version_key(tag).map(|key| (key, tag)))
+        .max()
+        .map(|(key, tag)| (key, tag.clone()))?;
+    if to_key >= from_tag.0 {
+        return None;
+    }
+    let message = format!("{} moves from {} down to {}", bump.name, from_tag.1, to_tag);
+    Some(Finding::new(Rule::PinDowngrade, Level::Error, &bump.to, message))
+}
+
+/// Check that a bump's ref resolves: a tag must exist, and a SHA pin must be the commit its tag
+/// comment names
+async fn check_tag_resolution(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    events: &dyn ScanEvents,
+    bump: &PinBump,
+    report: &mut ScanReport,
+) -> Option<Finding> {
+    let action_ref = ActionRef::from_action_string(&bump.to)?;
+    let tag = match (&bump.comment_tag, action_ref.is_commit_sha()) {
+        (Some(tag), true) => tag.clone(),
+        // A bare SHA names no tag to compare against
+        (None, true) => return None,
+        (_, false) => action_ref.version.clone(),
+    };
+    let tag_ref = ActionRef { version: tag.clone(), ..action_ref.clone() };
+    match resolve_tag_sha_cached(api, cache, &tag_ref).await {
+        Ok(Some(sha)) if action_ref.is_commit_sha() && !sha.eq_ignore_ascii_case(&action_ref.version) => {
+            let message = format!(
+                "{} is pinned to {} but tag {} resolves to {}",
+                bump.name, action_ref.version, tag, sha
+            );
+            Some(Finding::new(Rule::PinTagMismatch, Level::Error, &bump.to, message))
+        }
+        Ok(Some(_)) => None,
+        Ok(None) => {
+            let message = format!("{} has no tag {} in {}", bump.to, tag, action_ref.full_name());
+            Some(Finding::new(Rule::PinTagMismatch, Level::Error, &bump.to, message))
+        }
+        Err(e) => {
+            let error = format!("Failed to resolve {}@{}: {}", action_ref.full_name(), tag, e);
+            events.warning(&error);
+            report.unchecked.push(bump.to.clone());
+            report.errors.push(error);
+            None
+        }
+    }
+}
+
+/// A bump that brings in a repository no base workflow uses, from an untrusted owner, and that
+/// `approved_new_dependencies` does not list. The finding carries the `vendor` summary so the
+/// approval can be decided from the review comment.
+async fn check_new_dependency(scanner: &Scanner, config: &Config, bump: &PinBump, base_repositories: &BTreeSet<String>) -> Option<Finding> {
+    let action_ref = ActionRef::from_action_string(&bump.to)?;
+    let repository = action_ref.full_name();
+    let approved = config.approved_new_dependencies.iter().flatten().any(|a| a.eq_ignore_ascii_case(&repository));
+    if base_repositories.contains(&repository.to_ascii_lowercase()) || approved || is_trusted(&action_ref, config) {
+        return None;
+    }
+    let summary = match lookup::summarize(scanner, config, &bump.to).await {
+        Ok(summary) => summary.brief(),
+        Err(e) => format!("vendor lookup failed: {}", e),
+    };
+    let message = format!(
+        "{} is a new dependency and needs security approval in approved_new_dependencies ({})",
+        repository, summary
+    );
+    Some(Finding::new(Rule::NewDependency, Level::Error, &bump.to, message))
+}
+
+/// Whether a `critical_dependencies` entry names the bumped action, at any ref
+fn is_critical(bump: &PinBump, config: &Config) -> bool {
+    config
+        .critical_dependencies
+        .iter()
+        .flatten()
+        .any(|dep| dep.split_once('@').map_or(dep.as_str(), |(name, _)| name) == bump.name)
+}
+
+/// Run the `review-bump` subcommand. Returns the exit code of the verdict.
+pub async fn run(scanner: &Scanner, opt: &ReviewOptions) -> Result<i32, Box<dyn std::error::Error>> {
+    let configs = scanner.configs();
+    let cache = scanner.cache();
+    let (base, head) = revisions(opt)?;
+    // Compare against where the branch forked, so unrelated base-branch changes are not bumps
+    let fork_point = git(&["merge-base", &base, &head])?.trim().to_string();
+    let dirs = if opt.workflows_dirs.is_empty() {
+        vec![".github/workflows".to_string()]
+    } else {
+        opt.workflows_dirs.clone()
+    };
+    let style = CommentStyle::from_config(configs.root())?;
+    let bumps = changed_pins(&fork_point, &head, &dirs, &style)?;
+    let base_repositories = repositories_at(&fork_point, &dirs, &style)?;
+    scanner.events().progress(&format!("Reviewing {} changed action pin(s) between {} and {}", bumps.len(), short(&base), short(&head)));
+
+    let api = scanner.api();
+    let (visibility, _) = scanner.visibility().await;
+    let indicators = indicators::builtin().map_err(|e| format!("built-in compromised action list is invalid: {}", e))?;
+    let advisory_policy = AdvisoryPolicy::new(configs.root(), scanner.clock());
+    let mut report = ScanReport::default();
+
+    let mut by_workflow: BTreeMap<&str, Vec<&PinBump>> = BTreeMap::new();
+    for bump in &bumps {
+        by_workflow.entry(&bump.workflow).or_default().push(bump);
+    }
+    for (path, file_bumps) in &by_workflow {
+        let effective = configs.for_workflow(std::path::Path::new(path))?;
+        let changed: BTreeSet<String> = file_bumps.iter().map(|b| b.to.clone()).collect();
+        for bump in file_bumps {
+            let mut findings = Vec::from_iter(indicators::check(&bump.to, &indicators));
+            findings.extend(scanner.check_action_policy(&bump.to, &effective.config));
+            findings.extend(check_downgrade(bump, &bump.from));
+            findings.extend(check_tag_resolution(&api, cache, scanner.events(), bump, &mut report).await);
+            if is_critical(bump, &effective.config) {
+                let (drift, errors) = inputs::check_drift(&api, cache, bump).await;
+                findings.extend(drift);
+                errors.iter().for_each(|e| scanner.events().warning(e));
+            }
+            findings.extend(check_new_dependency(scanner, &effective.config, bump, &base_repositories).await);
+            report.findings.extend(findings.into_iter().map(|f| f.at(path, Some(bump.line))));
+        }
+
+        // Workflow-level rules, keeping only what they say about the bumped references
+        if let Some(source) = show(&head, path) {
+            let workflow = Workflow::parse(path, &source).map_err(|e| format!("{} at {}", e, short(&head)))?;
+            let findings = rules::check_workflow(&workflow, &effective.config, &ActionFilter::default(), visibility);
+            report.findings.extend(findings.into_iter().filter(|f| changed.contains(&f.action)));
+        }
+        report.workflow_actions.insert(path.to_string(), changed);
+    }
+
+    let unique: BTreeSet<&str> = bumps.iter().map(|b| b.to.as_str()).collect();
+    for action in unique {
+        if let Some(action_ref) = ActionRef::from_action_string(action)
+            && !blocklist::is_blocked(&action_ref, configs.root())
+        {
+            scanner.check_advisories(&advisory_policy, action, &action_ref, &mut report).await;
+        }
+    }
+    report.actions_scanned = bumps.len();
+    report.sort();
+
+    let policy = ExitPolicy {
+        fail_on_unchecked: opt.fail_on_unchecked || configs.root().fail_on_unchecked == Some(true),
+        ..Default::default()
+    };
+    let decision = decision::decide(&report, &policy);
+    let markdown = render_markdown(&base, &head, &bumps, &report, &decision, opt.max_findings);
+    match &opt.output {
+        Some(path) => fs::write(path, &markdown).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => print!("\n{}", markdown),
+    }
+    Ok(decision.exit.code)
+}
+
+fn short(revision: &str) -> &str {
+    if revision.len() == 40 { &revision[..7] } else { revision }
+}
+
+fn code(value: &str) -> String {
+    format!("`{}`", value)
+}
+
+/// The PR-comment verdict: one table row per bumped pin, then the findings against them
+pub fn render_markdown(
+    base: &str,
+    head: &str,
+    bumps: &[PinBump],
+    report: &ScanReport,
+    decision: &Decision,
+    max_findings: Option<usize>,
+) -> String {
+    let mut out = format!("### Action bump review: {}...{}\n\n", short(base), short(head));
+    if bumps.is_empty() {
+        out.push_str(&format!("{} No action pins changed.\n", Marker::Ok));
+        return out;
+    }
+    let verdict = if decision.exit.code == 0 { (Marker::Ok, "pass") } else { (Marker::Fail, "fail") };
+    out.push_str(&format!("**Verdict: {} {}** ({})\n\n", verdict.0, verdict.1, decision.exit.reason));
+
+    out.push_str("| Workflow | Action | From | To | Result |\n|---|---|---|---|---|\n");
+    for bump in bumps {
+        let findings: Vec<&Finding> = report
+            .findings
+            .iter()
+            .filter(|f| f.action == bump.to && f.file.as_deref().is_none_or(|file| file == bump.workflow))
+            .collect();
+        let result = match findings.iter().map(|f| f.level).max() {
+            Some(Level::Error) => Marker::Fail,
+            Some(Level::Warning) => Marker::Warn,
+            _ if report.unchecked.contains(&bump.to) => Marker::Warn,
+            _ => Marker::Ok,
+        };
+        let from = if bump.from.is_empty() {
+            "(new)".to_string()
+        } else {
+            bump.from.iter().map(|v| code(v)).collect::<Vec<_>>().join(", ")
+        };
+        let to = match &bump.comment_tag {
+            Some(tag) => format!("{} ({})", code(short(bump.version())), code(tag)),
+            None => code(bump.version()),
+        };
+        out.push_str(&format!(
+            "| {}:{} | {} | {} | {} | {} |\n",
+            code(&bump.workflow),
+            bump.line,
+            code(&bump.name),
+            from,
+            to,
+            result
+        ));
+    }
+
+    if !report.findings.is_empty() {
+        out.push_str("\n#### Findings\n\n");
+        let (findings, hidden) = report.prioritized(max_findings);
+        for finding in findings {
+            let location = finding.location().map(|l| format!(" {}", code(&l))).unwrap_or_default();
+            out.push_str(&format!(
+                "- {} **{}**{}: {}\n",
+                finding.level.marker(),
+                report.rule_id(finding.rule),
+                location,
+                finding.message
+            ));
+        }
+        if hidden > 0 {
+            out.push_str(&format!("- {}\n", render::overflow_line(hidden, "raise --max-findings to list them")));
+        }
+    }
+    if !report.unchecked.is_empty() {
+        out.push_str("\n#### Could not be checked\n\n");
+        for action in &report.unchecked {
+            out.push_str(&format!("- {}\n", code(action)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(file: &str) -> Value {
+        let content = match file {
+            "dependabot-pull-request.json" => include_str!("../tests/fixtures/events/dependabot-pull-request.json"),
+            "dependabot-pull-request-target.json" => include_str!("../tests/fixtures/events/dependabot-pull-request-target.json"),
+            "pull-request-without-head-sha.json" => include_str!("../tests/fixtures/events/pull-request-without-head-sha.json"),
+            "push.json" => include_str!("../tests/fixtures/events/push.json"),
+            _ => unreachable!("no fixture {}", file),
+        };
+        serde_json::from_str(content).unwrap()
+    }
+
+    const BASE: &str = "9c5f0d7e0b1c4a2f8e3d6b5a4c3b2a1f0e9d8c7b";
+    const HEAD: &str = "1a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d";
+
+    #[test]
+    fn dependabot_pull_request_events_give_base_and_head() {
+        for file in ["dependabot-pull-request.json", "dependabot-pull-request-target.json"] {
+            assert_eq!(event_revisions(&event(file)), Ok((BASE.to_string(), HEAD.to_string())), "{}", file);
+        }
+    }
+
+    #[test]
+    fn other_events_are_refused() {
+        assert_eq!(event_revisions(&event("push.json")), Err("event is not a pull request event".to_string()));
+        assert_eq!(
+            event_revisions(&event("pull-request-without-head-sha.json")),
+            Err("pull_request.head.sha is missing".to_string())
+        );
+        assert_eq!(event_revisions(&Value::Null), Err("event is not a pull request event".to_string()));
+    }
+
+    fn options(base: Option<&str>, head: Option<&str>, event_path: Option<std::path::PathBuf>) -> ReviewOptions {
+        ReviewOptions {
+            base: base.map(str::to_string),
+            head: head.map(str::to_string),
+            event_path,
+            workflows_dirs: Vec::new(),
+            output: None,
+            fail_on_unchecked: false,
+            max_findings: None,
+        }
+    }
+
+    #[test]
+    fn flags_win_over_the_event_payload() {
+        let dir = crate::scratch::Scratch::new("bump-event");
+        let path = dir.join("event.json");
+        fs::write(&path, event("dependabot-pull-request.json").to_string()).unwrap();
+        let event_path = Some(path.clone());
+        assert_eq!(revisions(&options(None, None, event_path.clone())).unwrap(), (BASE.to_string(), HEAD.to_string()));
+        assert_eq!(revisions(&options(Some("main"), None, event_path.clone())).unwrap(), ("main".to_string(), HEAD.to_string()));
+        // Both flags need no payload at all
+        let missing = Some(path.with_extension("missing"));
+        assert_eq!(revisions(&options(Some("main"), Some("HEAD"), missing.clone())).unwrap(), ("main".to_string(), "HEAD".to_string()));
+        assert!(revisions(&options(None, Some("HEAD"), missing)).unwrap_err().to_string().contains("event.missing"));
+        let error = revisions(&options(None, None, None)).unwrap_err().to_string();
+        assert_eq!(error, "review-bump needs --base and --head, or a pull request event in GITHUB_EVENT_PATH");
+    }
+
+    #[test]
+    fn pins_are_read_with_their_line_and_tag_comment() {
+        let source = "steps:\n  - uses: actions/checkout@v4\n  - uses: \"acme/tool@0123456789abcdef0123456789abcdef01234567\" # v1.2.3\n  - run: echo uses: x@y\n  - uses: ./local\n  - uses: acme/${{ matrix.tool }}@v1\n";
+        let pins = pins(source, &CommentStyle::default());
+        let expected = [
+            (2, "actions/checkout@v4".to_string(), None),
+            (3, "acme/tool@0123456789abcdef0123456789abcdef01234567".to_string(), Some("v1.2.3".to_string())),
+        ];
+        assert_eq!(pins, expected);
+    }
+
+    fn bump(from: &[&str], to: &str, comment_tag: Option<&str>) -> PinBump {
+        PinBump {
+            workflow: "ci.yml".to_string(),
+            line: 1,
+            name: to.split_once('@').unwrap().0.to_string(),
+            from: from.iter().map(|v| v.to_string()).collect(),
+            to: to.to_string(),
+            comment_tag: comment_tag.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn only_a_lower_version_is_a_downgrade() {
+        let found = check_downgrade(&bump(&["v4.1.1"], "actions/checkout@v4.1.0", None), &["v4.1.1".to_string()]).unwrap();
+        assert_eq!((found.rule, found.message.as_str()), (Rule::PinDowngrade, "actions/checkout moves from v4.1.1 down to v4.1.0"));
+        // `v4` is `v4.0.0`, and the highest base version is the one compared against
+        let base = ["v3.6.0".to_string(), "v4".to_string()];
+        assert!(check_downgrade(&bump(&[], "actions/checkout@v4.0.0", None), &base).is_none());
+        assert!(check_downgrade(&bump(&[], "actions/checkout@v3.6.0", None), &base).is_some());
+        // A SHA pin is compared by its tag comment; without versions there is nothing to compare
+        let sha = format!("actions/checkout@{}", "0".repeat(40));
+        assert!(check_downgrade(&bump(&[], &sha, Some("v3.0.0")), &base).is_some());
+        assert!(check_downgrade(&bump(&[], &sha, None), &base).is_none());
+        assert!(check_downgrade(&bump(&[], "actions/checkout@main", None), &base).is_none());
+        assert!(check_downgrade(&bump(&[], "actions/checkout@v1", None), &[]).is_none());
+    }
+
+    #[test]
+    fn a_change_without_bumps_says_so() {
+        let report = ScanReport::default();
+        let decision = decision::decide(&report, &ExitPolicy::default());
+        let markdown = render::with_charset(render::Charset::Ascii, || render_markdown("main", &"a".repeat(40), &[], &report, &decision, None));
+        assert_eq!(markdown, "### Action bump review: main...aaaaaaa\n\n[OK] No action pins changed.\n");
+    }
+}
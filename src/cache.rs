@@ -0,0 +1,466 @@
+use crate::output::Verbosity;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Lock file in the cache directory: scans hold it shared, `--cache-clear` exclusively
+const LOCK_FILE: &str = ".lock";
+
+/// What a cache lookup found
+#[derive(Debug)]
+pub enum Cached {
+    /// A stored result that is still fresh
+    Hit(Value),
+    /// A stored "nothing there" result (no advisories, 404) that is still fresh
+    NegativeHit,
+    Miss,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    stored_at: u64,
+    negative: bool,
+    #[serde(default)]
+    value: Value,
+}
+
+/// On-disk cache of API results, one JSON file per entry.
+///
+/// Negative results get their own, usually shorter, TTL so typo'd refs and advisory-free actions
+/// stop costing a request every run without hiding newly published data for long.
+///
+/// Several scans may share the directory. Entries are written to a temporary file and renamed into
+/// place, so a reader sees the old entry or the new one, never half of one.
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+    negative_ttl: Duration,
+    /// `--no-cache`: every lookup misses and nothing is written
+    disabled: bool,
+    /// Shared lock on the directory, held for the life of the cache (see `locked`)
+    _lock: Option<File>,
+    /// Tells apart the temporary files of concurrent writes within this process
+    writes: AtomicU64,
+    /// `--verbose` reports each hit
+    verbosity: Verbosity,
+    /// Entries that could not be written, until the scan reports them (see `take_warnings`)
+    warnings: Mutex<Vec<String>>,
+    /// Lookups this cache answered, for the summary
+    hits: AtomicUsize,
+}
+
+/// The directory's lock file, which holds the advisory lock; the lock goes with the handle
+fn lock_file(dir: &Path) -> Result<File, std::io::Error> {
+    fs::create_dir_all(dir)?;
+    File::options().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE))
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, ttl: Duration, negative_ttl: Duration) -> Self {
+        DiskCache {
+            dir,
+            ttl,
+            negative_ttl,
+            disabled: false,
+            _lock: None,
+            writes: AtomicU64::new(0),
+            verbosity: Verbosity::default(),
+            warnings: Mutex::default(),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_verbosity(self, verbosity: Verbosity) -> Self {
+        DiskCache { verbosity, ..self }
+    }
+
+    /// A cache that never answers and never stores, for `--no-cache`
+    pub fn disabled() -> Self {
+        DiskCache { disabled: true, ..DiskCache::new(PathBuf::new(), Duration::ZERO, Duration::ZERO) }
+    }
+
+    /// The cache holding a shared lock on its directory, so `--cache-clear` in another scan waits
+    /// for this one. Fails without waiting when the lock cannot be had, as while another scan
+    /// clears the cache; the scan then goes without the cache rather than wait or fail.
+    pub fn locked(self) -> Result<Self, std::io::Error> {
+        let shared = lock_file(&self.dir).and_then(|file| match file.try_lock_shared() {
+            Ok(()) => Ok(file),
+            Err(e) => Err(std::io::Error::from(e)),
+        });
+        match shared {
+            Ok(file) => Ok(DiskCache { _lock: Some(file), ..self }),
+            Err(e) => Err(std::io::Error::new(e.kind(), format!("{}: could not lock the cache: {}", self.dir.display(), e))),
+        }
+    }
+
+    /// Lookups this cache has answered so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The entries that could not be written since the last call, as warnings for the scan to
+    /// report
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// `--cache-clear`: remove every entry. Only the entry files the cache writes and the
+    /// namespace directories left empty are removed, so a mistyped --cache-dir cannot wipe
+    /// anything else. Blocks until other scans using the directory finish, so async callers run it
+    /// where blocking is allowed. Returns how many entries were removed.
+    pub fn clear(&self) -> Result<usize, std::io::Error> {
+        if !self.dir.is_dir() {
+            return Ok(0);
+        }
+        let _lock = lock_file(&self.dir)
+            .and_then(|file| file.lock().map(|()| file))
+            .map_err(|e| std::io::Error::other(format!("{}: could not lock the cache: {}", self.dir.display(), e)))?;
+        let mut removed = 0;
+        let namespaces = match fs::read_dir(&self.dir) {
+            Ok(namespaces) => namespaces,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(std::io::Error::other(format!("{}: {}", self.dir.display(), e))),
+        };
+        for namespace in namespaces {
+            let namespace = namespace?.path();
+            if !namespace.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&namespace)? {
+                let entry = entry?.path();
+                // Temporary files are left behind by writes that were interrupted
+                if entry.is_file() && entry.extension().is_some_and(|ext| ext == "json" || ext == "tmp") {
+                    fs::remove_file(&entry).map_err(|e| std::io::Error::other(format!("{}: {}", entry.display(), e)))?;
+                    removed += 1;
+                }
+            }
+            // Still holds something the cache did not write
+            let _ = fs::remove_dir(&namespace);
+        }
+        Ok(removed)
+    }
+
+    /// `$XDG_CACHE_HOME/dependency-scanner`, falling back to `~/.cache` and then the temp dir
+    pub fn default_dir() -> PathBuf {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("dependency-scanner")
+    }
+
+    fn path(&self, namespace: &str, key: &str) -> PathBuf {
+        self.dir.join(namespace).join(format!("{}.json", encode_key(key)))
+    }
+
+    /// Look up an entry. Unreadable, corrupted and expired entries all count as a miss.
+    pub fn get(&self, namespace: &str, key: &str) -> Cached {
+        if self.disabled {
+            return Cached::Miss;
+        }
+        let Ok(content) = fs::read_to_string(self.path(namespace, key)) else {
+            return Cached::Miss;
+        };
+        let Ok(entry) = serde_json::from_str::<Entry>(&content) else {
+            return Cached::Miss;
+        };
+        let ttl = if entry.negative { self.negative_ttl } else { self.ttl };
+        let age = now_secs().saturating_sub(entry.stored_at);
+        if age >= ttl.as_secs() {
+            return Cached::Miss;
+        }
+        self.verbosity.detail(format!("Cache hit{}: {} {}", if entry.negative { " (negative)" } else { "" }, namespace, key));
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        if entry.negative {
+            Cached::NegativeHit
+        } else {
+            Cached::Hit(entry.value)
+        }
+    }
+
+    /// Store a result, replacing whatever was there (including a negative entry)
+    pub fn put(&self, namespace: &str, key: &str, value: &Value) {
+        self.write(namespace, key, false, value.clone());
+    }
+
+    pub fn put_negative(&self, namespace: &str, key: &str) {
+        self.write(namespace, key, true, Value::Null);
+    }
+
+    fn write(&self, namespace: &str, key: &str, negative: bool, value: Value) {
+        if self.disabled {
+            return;
+        }
+        let entry = Entry {
+            stored_at: now_secs(),
+            negative,
+            value,
+        };
+        let path = self.path(namespace, key);
+        let temporary = path.with_extension(format!(
+            "json.{}-{}.tmp",
+            std::process::id(),
+            self.writes.fetch_add(1, Ordering::Relaxed)
+        ));
+        // The cache is an optimisation only; failing to write it must never fail a scan
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temporary, serde_json::to_vec(&entry).unwrap_or_default()))
+            .and_then(|_| fs::rename(&temporary, &path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        if let Err(e) = result {
+            let warning = format!("Warning: could not write cache entry {}: {}", path.display(), e);
+            self.warnings.lock().unwrap_or_else(|e| e.into_inner()).push(warning);
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Turn a key like `actions/checkout@v4` into a safe, collision-free file name
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'-' || byte == b'_' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Parse a duration such as `90s`, `30m`, `6h` or `7d`; a bare number is seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("invalid duration: {}", value))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit in {} (use s, m, h or d)", value)),
+    };
+    Ok(Duration::from_secs(number * multiplier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::Scratch;
+    use serde_json::json;
+
+    fn scratch(name: &str) -> Scratch {
+        Scratch::new(&format!("cache-{}", name))
+    }
+
+    fn cache(dir: &Path) -> DiskCache {
+        DiskCache::new(dir.to_path_buf(), Duration::from_secs(3600), Duration::from_secs(3600))
+    }
+
+    #[test]
+    fn concurrent_writers_and_readers_never_see_a_torn_entry() {
+        let dir = scratch("stress");
+        // Two caches on one directory stand for two scans sharing it
+        let caches = [cache(&dir).locked().unwrap(), cache(&dir).locked().unwrap()];
+        let keys = ["actions/checkout@v4", "actions/cache@v4", "owner/repo@main"];
+        std::thread::scope(|scope| {
+            for writer in 0..8 {
+                let cache = &caches[writer % 2];
+                scope.spawn(move || {
+                    for round in 0..200 {
+                        let key = keys[round % keys.len()];
+                        if round % 5 == 0 {
+                            cache.put_negative("stress", key);
+                        } else {
+                            // Large enough that a torn write would not parse
+                            cache.put("stress", key, &json!({ "writer": writer, "round": round, "pad": "x".repeat(4096) }));
+                        }
+                    }
+                });
+            }
+            for reader in 0..4 {
+                let cache = &caches[reader % 2];
+                scope.spawn(move || {
+                    for round in 0..400 {
+                        match cache.get("stress", keys[round % keys.len()]) {
+                            Cached::Hit(value) => assert!(value["writer"].is_u64() && value["pad"].as_str().unwrap().len() == 4096),
+                            Cached::NegativeHit | Cached::Miss => {}
+                        }
+                    }
+                });
+            }
+        });
+        for key in keys {
+            assert!(!matches!(caches[0].get("stress", key), Cached::Miss), "{} was lost", key);
+        }
+        let leftovers: Vec<_> = fs::read_dir(dir.join("stress"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_tasks_share_one_cache() {
+        let dir = scratch("tasks");
+        let cache = std::sync::Arc::new(cache(&dir).locked().unwrap());
+        let tasks: Vec<_> = (0..16)
+            .map(|task| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    for round in 0..50 {
+                        cache.put("tasks", "shared", &json!({ "task": task, "round": round }));
+                        assert!(!matches!(cache.get("tasks", "shared"), Cached::NegativeHit));
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert!(matches!(cache.get("tasks", "shared"), Cached::Hit(_)));
+    }
+
+    #[test]
+    fn locking_fails_while_the_cache_is_cleared() {
+        let dir = scratch("clearing");
+        let clearing = lock_file(&dir).unwrap();
+        clearing.lock().unwrap();
+        assert!(cache(&dir).locked().is_err());
+        drop(clearing);
+        assert!(!cache(&dir).locked().unwrap().disabled);
+    }
+
+    #[test]
+    fn a_failed_write_is_kept_for_the_scan_to_report() {
+        let dir = scratch("unwritable");
+        // A file where the namespace directory should be
+        fs::write(dir.join("ns"), "").unwrap();
+        let cache = cache(&dir);
+        cache.put("ns", "key", &json!(1));
+        let warnings = cache.take_warnings();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with("Warning: could not write cache entry"), "{}", warnings[0]);
+        assert!(cache.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn clear_waits_for_scans_holding_the_cache() {
+        let dir = scratch("wait");
+        let scan = cache(&dir).locked().unwrap();
+        scan.put("ns", "key", &json!(1));
+        let released = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let clearing = scope.spawn(|| {
+                let removed = cache(&dir).clear().unwrap();
+                assert!(released.load(Ordering::SeqCst), "clear ran while a scan held the cache");
+                removed
+            });
+            std::thread::sleep(Duration::from_millis(200));
+            released.store(true, Ordering::SeqCst);
+            drop(scan);
+            assert_eq!(clearing.join().unwrap(), 1);
+        });
+    }
+
+    /// Store an entry as if it had been written `age` seconds ago
+    fn store_aged(cache: &DiskCache, key: &str, negative: bool, age: u64) {
+        let entry = Entry { stored_at: now_secs() - age, negative, value: if negative { Value::Null } else { json!(age) } };
+        let path = cache.path("ttl", key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, serde_json::to_vec(&entry).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn negative_entries_expire_at_their_own_ttl() {
+        let dir = scratch("negative-ttl");
+        let cache = DiskCache::new(dir.to_path_buf(), Duration::from_secs(24 * 3600), Duration::from_secs(6 * 3600));
+        let six_hours = 6 * 3600;
+        store_aged(&cache, "fresh", true, six_hours - 60);
+        store_aged(&cache, "expired", true, six_hours);
+        store_aged(&cache, "stale", true, six_hours + 60);
+        assert!(matches!(cache.get("ttl", "fresh"), Cached::NegativeHit));
+        assert!(matches!(cache.get("ttl", "expired"), Cached::Miss), "an entry as old as the TTL has expired");
+        assert!(matches!(cache.get("ttl", "stale"), Cached::Miss));
+    }
+
+    #[test]
+    fn positive_entries_outlive_the_negative_ttl() {
+        let dir = scratch("positive-ttl");
+        let cache = DiskCache::new(dir.to_path_buf(), Duration::from_secs(24 * 3600), Duration::from_secs(6 * 3600));
+        let day = 24 * 3600;
+        store_aged(&cache, "past-negative-ttl", false, 12 * 3600);
+        store_aged(&cache, "fresh", false, day - 60);
+        store_aged(&cache, "expired", false, day);
+        assert!(matches!(cache.get("ttl", "past-negative-ttl"), Cached::Hit(_)));
+        assert!(matches!(cache.get("ttl", "fresh"), Cached::Hit(value) if value == json!(day - 60)));
+        assert!(matches!(cache.get("ttl", "expired"), Cached::Miss));
+    }
+
+    #[test]
+    fn a_zero_negative_ttl_never_answers_from_a_negative_entry() {
+        let dir = scratch("zero-negative-ttl");
+        let cache = DiskCache::new(dir.to_path_buf(), Duration::from_secs(3600), Duration::ZERO);
+        cache.put_negative("ns", "none");
+        cache.put("ns", "some", &json!([]));
+        assert!(matches!(cache.get("ns", "none"), Cached::Miss));
+        assert!(matches!(cache.get("ns", "some"), Cached::Hit(_)));
+    }
+
+    #[test]
+    fn a_positive_result_replaces_a_negative_one_at_once() {
+        let dir = scratch("overwrite");
+        let cache = cache(&dir);
+        cache.put_negative("ns", "acme/deploy@v1");
+        assert!(matches!(cache.get("ns", "acme/deploy@v1"), Cached::NegativeHit));
+        cache.put("ns", "acme/deploy@v1", &json!({ "sha": "abc" }));
+        assert!(matches!(cache.get("ns", "acme/deploy@v1"), Cached::Hit(value) if value["sha"] == "abc"));
+        cache.put_negative("ns", "acme/deploy@v1");
+        assert!(matches!(cache.get("ns", "acme/deploy@v1"), Cached::NegativeHit), "and a later negative replaces it back");
+    }
+
+    #[test]
+    fn corrupted_entries_are_misses() {
+        let dir = scratch("corrupted");
+        let cache = cache(&dir);
+        cache.put("ns", "key", &json!(1));
+        fs::write(cache.path("ns", "key"), "{\"stored_at\": ").unwrap();
+        assert!(matches!(cache.get("ns", "key"), Cached::Miss));
+    }
+
+    #[test]
+    fn durations_take_a_unit() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_duration(" 6h "), Ok(Duration::from_secs(6 * 3600)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 24 * 3600)));
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_duration("6w"), Err("invalid duration unit in 6w (use s, m, h or d)".to_string()));
+        assert_eq!(parse_duration("h"), Err("invalid duration: h".to_string()));
+    }
+
+    #[test]
+    fn clear_removes_only_entries_the_cache_wrote() {
+        let dir = scratch("clear");
+        let cache = cache(&dir);
+        cache.put("ns", "a", &json!(1));
+        cache.put_negative("ns", "b");
+        fs::write(dir.join("ns/notes.txt"), "keep").unwrap();
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert!(dir.join("ns/notes.txt").exists());
+    }
+}
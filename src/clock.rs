@@ -0,0 +1,101 @@
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use std::time::{Duration, Instant};
+
+/// The single source of "now" for everything the scanner emits.
+///
+/// Reports must take their timestamps from here rather than the system clock so that pinned
+/// builds (`--timestamp`, `SOURCE_DATE_EPOCH`) produce byte-identical artifacts.
+#[derive(Debug, Clone, Copy)]
+pub enum Clock {
+    System,
+    Fixed(DateTime<Utc>),
+}
+
+impl Clock {
+    /// Build the clock from `--timestamp`, falling back to `SOURCE_DATE_EPOCH`, then the system clock
+    pub fn from_options(timestamp: Option<DateTime<Utc>>) -> Result<Self, String> {
+        if let Some(timestamp) = timestamp {
+            return Ok(Clock::Fixed(timestamp));
+        }
+        match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(epoch) => {
+                let seconds: i64 = epoch
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("SOURCE_DATE_EPOCH is not a number of seconds: {}", epoch))?;
+                Utc.timestamp_opt(seconds, 0)
+                    .single()
+                    .map(Clock::Fixed)
+                    .ok_or_else(|| format!("SOURCE_DATE_EPOCH is out of range: {}", epoch))
+            }
+            Err(_) => Ok(Clock::System),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Fixed(time) => *time,
+        }
+    }
+
+    /// Time taken since `started`; zero under a pinned clock, whose reports must not vary with
+    /// how fast the machine is
+    pub fn elapsed(&self, started: Instant) -> Duration {
+        match self {
+            Clock::System => started.elapsed(),
+            Clock::Fixed(_) => Duration::ZERO,
+        }
+    }
+
+    /// Current time as an RFC 3339 string with second precision
+    pub fn rfc3339(&self) -> String {
+        self.now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+/// Parse a `--timestamp` value
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+    DateTime::parse_from_rfc3339(value).map(|t| t.with_timezone(&Utc))
+}
+
+/// Parse an RFC 3339 time, or a plain `YYYY-MM-DD` date read as its midnight in UTC
+pub fn parse_date_or_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = parse_timestamp(value) {
+        return Ok(time);
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc())
+        .map_err(|_| format!("{} is neither a date (2024-01-01) nor an RFC 3339 time (2024-01-01T00:00:00Z)", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        parse_timestamp(value).unwrap()
+    }
+
+    #[test]
+    fn a_plain_date_is_its_midnight_in_utc() {
+        assert_eq!(parse_date_or_time("2024-01-01"), Ok(utc("2024-01-01T00:00:00Z")));
+        assert_eq!(parse_date_or_time("2024-02-29"), Ok(utc("2024-02-29T00:00:00Z")));
+    }
+
+    #[test]
+    fn an_rfc_3339_time_keeps_its_offset() {
+        // One minute after midnight in UTC+02:00 is still the previous day in UTC
+        assert_eq!(parse_date_or_time("2024-01-01T00:01:00+02:00"), Ok(utc("2023-12-31T22:01:00Z")));
+        assert_eq!(parse_date_or_time("2023-12-31T23:30:00-01:00"), Ok(utc("2024-01-01T00:30:00Z")));
+        assert_eq!(parse_date_or_time("2024-01-01T00:00:00.5Z").unwrap().timestamp_subsec_millis(), 500);
+    }
+
+    #[test]
+    fn anything_else_is_rejected() {
+        for value in ["2024-13-01", "2023-02-29", "01/02/2024", "2024-01-01T00:00:00", "yesterday", ""] {
+            let error = parse_date_or_time(value).unwrap_err();
+            assert!(error.starts_with(&format!("{} is neither a date", value)), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::decision::LevelCounts;
+use crate::report::ScanReport;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Group name for files no CODEOWNERS rule assigns
+pub const UNOWNED: &str = "(unowned)";
+
+/// Where GitHub looks for the file, in the order it looks
+const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+struct OwnerRule {
+    regex: Regex,
+    /// Empty for a pattern listed without owners, which leaves matching files unowned
+    owners: Vec<String>,
+}
+
+pub struct CodeOwners {
+    pub path: PathBuf,
+    rules: Vec<OwnerRule>,
+}
+
+/// Translate one CODEOWNERS pattern to a regex over repository-relative paths.
+///
+/// The syntax is gitignore's with GitHub's deviations: a pattern containing a `/` (other than a
+/// trailing one) is anchored at the root, otherwise it matches at any depth; a trailing `/` or a
+/// plain name also owns everything beneath; `*` stays within a path segment, so `docs/*` owns
+/// `docs/a.md` but not `docs/sub/b.md`, while `**` spans segments.
+fn pattern_regex(pattern: &str) -> Result<Regex, String> {
+    let directory = pattern.ends_with('/');
+    let body = pattern.trim_start_matches('/').trim_end_matches('/');
+    if body.is_empty() {
+        return Err(format!("pattern {} matches nothing", pattern));
+    }
+    let anchored = pattern.starts_with('/') || body.contains('/');
+    let last = body.rsplit('/').next().unwrap_or(body);
+    let owns_beneath = directory || last == "**" || !last.contains(['*', '?']);
+
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let chars: Vec<char> = body.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push_str(if owns_beneath { "(?:/.*)?$" } else { "$" });
+    Regex::new(&regex).map_err(|e| format!("pattern {}: {}", pattern, e))
+}
+
+impl CodeOwners {
+    /// Parse a CODEOWNERS file. Lines GitHub would ignore are skipped with a warning for each.
+    pub fn parse(path: PathBuf, source: &str) -> (Self, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut warnings = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // Owners end at an unescaped `#`; patterns escape a literal one as `\#`
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else { continue };
+            let owners: Vec<String> = fields.take_while(|f| !f.starts_with('#')).map(str::to_string).collect();
+            let location = format!("{}:{}", path.display(), index + 1);
+            // GitHub does not support negation; such a line changes no ownership
+            if pattern.starts_with('!') {
+                warnings.push(format!("{}: negated pattern {} is not supported by GitHub and is ignored", location, pattern));
+                continue;
+            }
+            match pattern_regex(pattern) {
+                Ok(regex) => rules.push(OwnerRule { regex, owners }),
+                Err(e) => warnings.push(format!("{}: {}, ignored", location, e)),
+            }
+        }
+        (CodeOwners { path, rules }, warnings)
+    }
+
+    /// Read the first CODEOWNERS file GitHub would use under the repository root
+    pub fn discover(root: &Path) -> Result<(Self, Vec<String>), String> {
+        for location in LOCATIONS {
+            let path = root.join(location);
+            if let Ok(source) = fs::read_to_string(&path) {
+                return Ok(CodeOwners::parse(path, &source));
+            }
+        }
+        Err(format!("--codeowners: no CODEOWNERS file in {} (tried {})", root.display(), LOCATIONS.join(", ")))
+    }
+
+    /// Owners of a repository-relative path: the last matching rule wins
+    pub fn owners(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.regex.is_match(path))
+            .map_or(&[], |rule| rule.owners.as_slice())
+    }
+}
+
+/// A workflow path as CODEOWNERS sees it: relative to the repository root, without `./`
+fn relative_path(root: &Path, path: &str) -> String {
+    let path = Path::new(path);
+    let relative = match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path.strip_prefix(&root).map(Path::to_path_buf).unwrap_or(path),
+        _ => path.to_path_buf(),
+    };
+    relative.to_string_lossy().trim_start_matches("./").to_string()
+}
+
+/// Record each finding's owners: those of its workflow file, or for per-action findings, of every
+/// workflow that uses the action. Scan-level findings belong to no one.
+pub fn assign(report: &mut ScanReport, codeowners: &CodeOwners, root: &Path) {
+    let file_owners: BTreeMap<String, Vec<String>> = report
+        .workflow_actions
+        .keys()
+        .map(|file| {
+            let owners = codeowners.owners(&relative_path(root, file));
+            let owners = if owners.is_empty() { vec![UNOWNED.to_string()] } else { owners.to_vec() };
+            (file.clone(), owners)
+        })
+        .collect();
+    for finding in &mut report.findings {
+        let owners: BTreeSet<String> = match &finding.file {
+            Some(file) => file_owners.get(file).cloned().unwrap_or_default().into_iter().collect(),
+            None => report
+                .workflow_actions
+                .iter()
+                .filter(|(_, actions)| actions.contains(&finding.action))
+                .flat_map(|(file, _)| file_owners[file].iter().cloned())
+                .collect(),
+        };
+        finding.owners = owners.into_iter().collect();
+    }
+}
+
+/// Print the findings grouped by owner, with the workflow files each owner is responsible for
+pub fn print_groups(out: &mut dyn Write, report: &ScanReport, codeowners: &CodeOwners, root: &Path) -> io::Result<()> {
+    let mut groups: BTreeMap<&str, (LevelCounts, BTreeSet<&str>)> = BTreeMap::new();
+    for file in report.workflow_actions.keys() {
+        let owners = codeowners.owners(&relative_path(root, file));
+        if owners.is_empty() {
+            groups.entry(UNOWNED).or_default().1.insert(file);
+        }
+        for owner in owners {
+            groups.entry(owner).or_default().1.insert(file);
+        }
+    }
+    for finding in &report.findings {
+        for owner in &finding.owners {
+            groups.entry(owner).or_default().0.add(finding.level);
+        }
+    }
+    if groups.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nFindings by owner ({}):", codeowners.path.display())?;
+    for (owner, (counts, files)) in &groups {
+        writeln!(out, "- {}: {} error, {} warning, {} note", owner, counts.error, counts.warning, counts.note)?;
+        for file in files {
+            writeln!(out, "  - {}", file)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codeowners(source: &str) -> (CodeOwners, Vec<String>) {
+        CodeOwners::parse(PathBuf::from("CODEOWNERS"), source)
+    }
+
+    fn owners(codeowners: &CodeOwners, path: &str) -> Vec<String> {
+        codeowners.owners(path).to_vec()
+    }
+
+    #[test]
+    fn the_last_matching_rule_wins() {
+        let (codeowners, warnings) = codeowners(
+            "* @org/everyone
+.github/ @org/platform
+.github/workflows/release.yml @org/release @alice
+.github/workflows/unowned.yml
+",
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(owners(&codeowners, "README.md"), ["@org/everyone"]);
+        assert_eq!(owners(&codeowners, ".github/workflows/ci.yml"), ["@org/platform"]);
+        assert_eq!(owners(&codeowners, ".github/workflows/release.yml"), ["@org/release", "@alice"]);
+        // A later pattern without owners takes ownership away again
+        assert!(owners(&codeowners, ".github/workflows/unowned.yml").is_empty());
+    }
+
+    #[test]
+    fn an_earlier_rule_does_not_override_a_later_one() {
+        let (codeowners, _) = codeowners(".github/workflows/ci.yml @org/ci\n* @org/everyone\n");
+        assert_eq!(owners(&codeowners, ".github/workflows/ci.yml"), ["@org/everyone"]);
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored_at_the_root() {
+        let (codeowners, _) = codeowners(
+            "ci.yml @any-depth
+/release.yml @root-only
+workflows/deploy.yml @anchored
+",
+        );
+        assert_eq!(owners(&codeowners, "ci.yml"), ["@any-depth"]);
+        assert_eq!(owners(&codeowners, ".github/workflows/ci.yml"), ["@any-depth"]);
+        assert_eq!(owners(&codeowners, "release.yml"), ["@root-only"]);
+        assert!(owners(&codeowners, ".github/workflows/release.yml").is_empty());
+        assert_eq!(owners(&codeowners, "workflows/deploy.yml"), ["@anchored"]);
+        assert!(owners(&codeowners, ".github/workflows/deploy.yml").is_empty());
+    }
+
+    #[test]
+    fn directories_own_everything_beneath_them() {
+        let (codeowners, _) = codeowners(
+            ".github/workflows/ @workflows
+apps @apps
+",
+        );
+        assert_eq!(owners(&codeowners, ".github/workflows/ci.yml"), ["@workflows"]);
+        assert_eq!(owners(&codeowners, ".github/workflows/nested/ci.yml"), ["@workflows"]);
+        assert!(owners(&codeowners, "other/.github/workflows/ci.yml").is_empty());
+        assert_eq!(owners(&codeowners, "apps/web/.github/workflows/ci.yml"), ["@apps"]);
+        assert_eq!(owners(&codeowners, "services/apps/ci.yml"), ["@apps"]);
+        assert!(owners(&codeowners, "applications/ci.yml").is_empty());
+    }
+
+    #[test]
+    fn a_star_stays_within_a_segment_and_a_double_star_spans_them() {
+        let (codeowners, _) = codeowners(
+            ".github/workflows/*.yml @one-level
+docs/** @docs
+**/deploy-*.yaml @deploy
+",
+        );
+        assert_eq!(owners(&codeowners, ".github/workflows/ci.yml"), ["@one-level"]);
+        assert!(owners(&codeowners, ".github/workflows/nested/ci.yml").is_empty());
+        assert!(owners(&codeowners, ".github/workflows/ci.yaml").is_empty());
+        assert_eq!(owners(&codeowners, "docs/a/b/c.md"), ["@docs"]);
+        assert_eq!(owners(&codeowners, "deploy-prod.yaml"), ["@deploy"]);
+        assert_eq!(owners(&codeowners, "a/b/deploy-prod.yaml"), ["@deploy"]);
+    }
+
+    #[test]
+    fn a_negated_pattern_is_warned_about_and_changes_nothing() {
+        let (codeowners, warnings) = codeowners(
+            ".github/ @org/platform
+!.github/workflows/ci.yml @org/ci
+",
+        );
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with("CODEOWNERS:2: negated pattern !.github/workflows/ci.yml"), "{}", warnings[0]);
+        assert!(warnings[0].ends_with("is ignored"), "{}", warnings[0]);
+        assert_eq!(owners(&codeowners, ".github/workflows/ci.yml"), ["@org/platform"]);
+    }
+
+    #[test]
+    fn comments_end_the_owners_and_an_escaped_hash_is_part_of_the_pattern() {
+        let (codeowners, warnings) = codeowners(
+            "# a comment line
+ci.yml @org/ci # trailing comment @not-an-owner
+\\#hash.yml @org/hash
+",
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(owners(&codeowners, "ci.yml"), ["@org/ci"]);
+        assert_eq!(owners(&codeowners, "#hash.yml"), ["@org/hash"]);
+    }
+
+    #[test]
+    fn a_pattern_matching_nothing_is_warned_about() {
+        let (codeowners, warnings) = codeowners("/ @org/root\nci.yml @org/ci\n");
+        assert_eq!(warnings, ["CODEOWNERS:1: pattern / matches nothing, ignored"]);
+        assert_eq!(owners(&codeowners, "ci.yml"), ["@org/ci"]);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::report::{Rule, ScanReport};
+use std::collections::BTreeSet;
+
+/// Another scanner whose rule ids `--rule-id-compat` reports under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Zizmor,
+    Pinact,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 2] = [Tool::Zizmor, Tool::Pinact];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Zizmor => "zizmor",
+            Tool::Pinact => "pinact",
+        }
+    }
+
+    /// The tool's id for a rule, if it has an equivalent
+    fn id(self, rule: Rule) -> Option<&'static str> {
+        match self {
+            Tool::Zizmor => zizmor(rule),
+            Tool::Pinact => pinact(rule),
+        }
+    }
+}
+
+impl std::str::FromStr for Tool {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "zizmor" => Ok(Tool::Zizmor),
+            "pinact" => Ok(Tool::Pinact),
+            _ => Err(format!("unknown tool {} (supported: zizmor, pinact)", value)),
+        }
+    }
+}
+
+/// The closest zizmor audit. The match has no wildcard arm so that a new rule does not compile
+/// until someone decides whether it has an equivalent.
+fn zizmor(rule: Rule) -> Option<&'static str> {
+    match rule {
+        Rule::UnpinnedDependency
+        | Rule::UnstableReference
+        | Rule::TagPinnedCritical
+        | Rule::AbbreviatedSha
+        | Rule::UntrustedOwnerNoSha => Some("unpinned-uses"),
+        Rule::UntrustedOwner => Some("forbidden-uses"),
+        Rule::KnownVulnerability | Rule::CompromisedVersion => Some("known-vulnerable-actions"),
+        Rule::SelfHostedPullRequest => Some("self-hosted-runner"),
+        Rule::PinTagMismatch => Some("ref-version-mismatch"),
+        Rule::UnreleasedCommit => Some("stale-action-refs"),
+        Rule::DockerTagPinned => Some("unpinned-images"),
+        Rule::UnreachableCommit => Some("impostor-commit"),
+        Rule::CachePoisoning => Some("cache-poisoning"),
+        Rule::BlockedDependency => Some("forbidden-uses"),
+        Rule::RepositoryArchived => Some("archived-uses"),
+        Rule::MalformedReference
+        | Rule::OidcUntrustedAction
+        | Rule::WorkflowShape
+        | Rule::TooManyUntrustedOwners
+        | Rule::VendoredOutdated
+        | Rule::InvalidVendoredMarker
+        | Rule::SecretEnvExposure
+        | Rule::DynamicReference
+        | Rule::AdvisoryHistory
+        | Rule::UnpinnedDispatch
+        | Rule::InsecureDownload
+        | Rule::PinDowngrade
+        | Rule::UnusedConfigEntry
+        | Rule::GithubScript
+        | Rule::DockerArgsInjection
+        | Rule::RepositoryNotFound
+        | Rule::RepositoryDisabled
+        | Rule::RepositoryMoved
+        | Rule::DeprecatedRuntime
+        | Rule::PinPolicy
+        | Rule::DependabotCoverage
+        | Rule::ParseLimit
+        | Rule::PinComment
+        | Rule::PinCommentMismatch
+        | Rule::CheckoutOverlay
+        | Rule::InvalidWorkflow
+        | Rule::DuplicateKey
+        | Rule::DependencyDepth
+        | Rule::InputDrift
+        | Rule::NewDependency
+        | Rule::MissingLocalAction
+        | Rule::ImmutableRelease
+        | Rule::MissingCommit
+        | Rule::Outdated
+        | Rule::UnguardedCommentTrigger => None,
+    }
+}
+
+/// The pinact check that fails on the same thing. pinact has no rule ids of its own, so lists
+/// written for it name the check: `unpinned` for `pinact run --check`, `verify` for `--verify`
+/// and `update` for `--update`. As above, there is no wildcard arm.
+fn pinact(rule: Rule) -> Option<&'static str> {
+    match rule {
+        Rule::UnpinnedDependency
+        | Rule::UnstableReference
+        | Rule::TagPinnedCritical
+        | Rule::AbbreviatedSha
+        | Rule::UntrustedOwnerNoSha
+        | Rule::PinComment => Some("unpinned"),
+        Rule::PinTagMismatch | Rule::PinCommentMismatch => Some("verify"),
+        Rule::Outdated => Some("update"),
+        Rule::UntrustedOwner
+        | Rule::KnownVulnerability
+        | Rule::CompromisedVersion
+        | Rule::SelfHostedPullRequest
+        | Rule::UnreleasedCommit
+        | Rule::DockerTagPinned
+        | Rule::UnreachableCommit
+        | Rule::CachePoisoning
+        | Rule::BlockedDependency
+        | Rule::RepositoryArchived
+        | Rule::MalformedReference
+        | Rule::OidcUntrustedAction
+        | Rule::WorkflowShape
+        | Rule::TooManyUntrustedOwners
+        | Rule::VendoredOutdated
+        | Rule::InvalidVendoredMarker
+        | Rule::SecretEnvExposure
+        | Rule::DynamicReference
+        | Rule::AdvisoryHistory
+        | Rule::UnpinnedDispatch
+        | Rule::InsecureDownload
+        | Rule::PinDowngrade
+        | Rule::UnusedConfigEntry
+        | Rule::GithubScript
+        | Rule::DockerArgsInjection
+        | Rule::RepositoryNotFound
+        | Rule::RepositoryDisabled
+        | Rule::RepositoryMoved
+        | Rule::DeprecatedRuntime
+        | Rule::PinPolicy
+        | Rule::DependabotCoverage
+        | Rule::ParseLimit
+        | Rule::CheckoutOverlay
+        | Rule::InvalidWorkflow
+        | Rule::DuplicateKey
+        | Rule::DependencyDepth
+        | Rule::InputDrift
+        | Rule::NewDependency
+        | Rule::MissingLocalAction
+        | Rule::ImmutableRelease
+        | Rule::MissingCommit
+        | Rule::UnguardedCommentTrigger => None,
+    }
+}
+
+/// The id outputs use for a rule: the compat tool's, else the scanner's own
+pub fn rule_id(tool: Option<Tool>, rule: Rule) -> &'static str {
+    tool.and_then(|tool| tool.id(rule)).unwrap_or(rule.id())
+}
+
+/// Whether a suppression's rule id names the rule: by its own id, or under `--rule-id-compat` by
+/// the tool's, so that lists written for the tool keep working. One of the tool's ids may stand
+/// for several rules.
+pub fn names_rule(tool: Option<Tool>, id: &str, rule: Rule) -> bool {
+    id == rule.id() || tool.and_then(|tool| tool.id(rule)) == Some(id)
+}
+
+/// The warning about the rules in the report that kept their own ids for want of an equivalent
+pub fn unmapped_warning(report: &ScanReport) -> Option<String> {
+    let tool = report.rule_ids?;
+    let unmapped: BTreeSet<&str> = report
+        .findings
+        .iter()
+        .filter(|f| tool.id(f.rule).is_none())
+        .map(|f| f.rule.id())
+        .collect();
+    if unmapped.is_empty() {
+        return None;
+    }
+    let names: Vec<&str> = unmapped.into_iter().collect();
+    Some(format!("Warning: no {} equivalent for {}; reported under their own ids", tool.name(), names.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{Finding, Level};
+
+    /// Every audit id zizmor has that a rule maps to
+    const ZIZMOR_AUDITS: [&str; 10] = [
+        "archived-uses",
+        "cache-poisoning",
+        "forbidden-uses",
+        "impostor-commit",
+        "known-vulnerable-actions",
+        "ref-version-mismatch",
+        "self-hosted-runner",
+        "stale-action-refs",
+        "unpinned-images",
+        "unpinned-uses",
+    ];
+
+    /// Every check pinact suppression lists name that a rule maps to
+    const PINACT_CHECKS: [&str; 3] = ["unpinned", "update", "verify"];
+
+    fn known_ids(tool: Tool) -> &'static [&'static str] {
+        match tool {
+            Tool::Zizmor => &ZIZMOR_AUDITS,
+            Tool::Pinact => &PINACT_CHECKS,
+        }
+    }
+
+    #[test]
+    fn every_rule_keeps_or_maps_its_id() {
+        for rule in Rule::ALL {
+            assert_eq!(rule_id(None, rule), rule.id());
+            for tool in Tool::ALL {
+                let compat = rule_id(Some(tool), rule);
+                match tool.id(rule) {
+                    Some(id) => {
+                        assert_eq!(compat, id);
+                        assert!(known_ids(tool).contains(&id), "{} maps to {}, which {} does not have", rule.id(), id, tool.name());
+                    }
+                    None => assert_eq!(compat, rule.id()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_registry_lists_each_rule_once() {
+        let ids: BTreeSet<&str> = Rule::ALL.iter().map(|rule| rule.id()).collect();
+        assert_eq!(ids.len(), Rule::ALL.len());
+        // An id of our own never collides with an id another rule is reported under
+        for tool in Tool::ALL {
+            for rule in Rule::ALL.into_iter().filter(|rule| tool.id(*rule).is_none()) {
+                assert!(!known_ids(tool).contains(&rule.id()), "{} under {}", rule.id(), tool.name());
+            }
+        }
+    }
+
+    #[test]
+    fn pinning_rules_share_zizmors_unpinned_uses() {
+        for rule in [Rule::UnpinnedDependency, Rule::UnstableReference, Rule::TagPinnedCritical, Rule::AbbreviatedSha, Rule::UntrustedOwnerNoSha] {
+            assert_eq!(rule_id(Some(Tool::Zizmor), rule), "unpinned-uses", "{}", rule.id());
+        }
+        assert_eq!(rule_id(Some(Tool::Zizmor), Rule::KnownVulnerability), "known-vulnerable-actions");
+        assert_eq!(rule_id(Some(Tool::Zizmor), Rule::DockerTagPinned), "unpinned-images");
+        assert_eq!(rule_id(Some(Tool::Zizmor), Rule::GithubScript), "github-script");
+    }
+
+    #[test]
+    fn pinact_checks_cover_pinning_comments_and_updates() {
+        assert_eq!(rule_id(Some(Tool::Pinact), Rule::UntrustedOwnerNoSha), "unpinned");
+        assert_eq!(rule_id(Some(Tool::Pinact), Rule::PinComment), "unpinned");
+        assert_eq!(rule_id(Some(Tool::Pinact), Rule::PinCommentMismatch), "verify");
+        assert_eq!(rule_id(Some(Tool::Pinact), Rule::Outdated), "update");
+        assert_eq!(rule_id(Some(Tool::Pinact), Rule::KnownVulnerability), "known-vulnerability");
+    }
+
+    #[test]
+    fn tools_are_named_on_the_command_line() {
+        assert_eq!("zizmor".parse::<Tool>(), Ok(Tool::Zizmor));
+        assert_eq!("pinact".parse::<Tool>(), Ok(Tool::Pinact));
+        for tool in Tool::ALL {
+            assert_eq!(tool.name().parse::<Tool>(), Ok(tool));
+        }
+        assert_eq!("semgrep".parse::<Tool>(), Err("unknown tool semgrep (supported: zizmor, pinact)".to_string()));
+    }
+
+    #[test]
+    fn suppressions_name_rules_by_either_id() {
+        assert!(names_rule(None, "untrusted-owner-no-sha", Rule::UntrustedOwnerNoSha));
+        assert!(!names_rule(None, "unpinned-uses", Rule::UntrustedOwnerNoSha));
+        assert!(names_rule(Some(Tool::Zizmor), "unpinned-uses", Rule::UntrustedOwnerNoSha));
+        assert!(names_rule(Some(Tool::Zizmor), "unpinned-uses", Rule::TagPinnedCritical));
+        assert!(names_rule(Some(Tool::Zizmor), "untrusted-owner-no-sha", Rule::UntrustedOwnerNoSha));
+        assert!(!names_rule(Some(Tool::Zizmor), "unpinned-uses", Rule::UntrustedOwner));
+        assert!(names_rule(Some(Tool::Pinact), "unpinned", Rule::UnpinnedDependency));
+        assert!(!names_rule(Some(Tool::Zizmor), "unpinned", Rule::UnpinnedDependency));
+    }
+
+    #[test]
+    fn the_report_shows_the_compat_ids() {
+        let finding = Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, "acme/tool@v1", "unpinned".to_string());
+        let mut report = ScanReport { findings: vec![finding], ..Default::default() };
+        assert_eq!(report.rule_id(Rule::UntrustedOwnerNoSha), "untrusted-owner-no-sha");
+        report.rule_ids = Some(Tool::Zizmor);
+        assert_eq!(report.rule_id(Rule::UntrustedOwnerNoSha), "unpinned-uses");
+    }
+
+    #[test]
+    fn rules_without_an_equivalent_are_warned_about() {
+        let finding = |rule| Finding::new(rule, Level::Error, "acme/tool@v1", "finding".to_string());
+        let mut report = ScanReport { findings: vec![finding(Rule::UntrustedOwnerNoSha)], ..Default::default() };
+        assert_eq!(unmapped_warning(&report), None);
+        report.rule_ids = Some(Tool::Zizmor);
+        assert_eq!(unmapped_warning(&report), None);
+        let unmapped = Rule::ALL.into_iter().find(|rule| zizmor(*rule).is_none()).unwrap();
+        report.findings.push(finding(unmapped));
+        assert_eq!(
+            unmapped_warning(&report),
+            Some(format!("Warning: no zizmor equivalent for {}; reported under their own ids", unmapped.id()))
+        );
+    }
+}
@@ -0,0 +1,881 @@
+use crate::compat::{self, Tool};
+use crate::config_lint::{self, Lint};
+use crate::error::ScanError;
+use crate::profile::Profile;
+use crate::report::{Category, Finding, Level, Suppression};
+use crate::severity::SeverityMapping;
+use crate::surface::{Surface, TriggerSurface};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the directory-scoped config files discovered next to workflows
+pub const SCOPED_CONFIG_FILE: &str = "dependency-scanner.yaml";
+
+/// A rule's level where a finding's workflow and action match the selectors. An `action` selector
+/// outranks a `workflow` selector, which outranks a `trigger_surface` one, and entries with more
+/// selectors beat those with fewer of the same rank; among entries equally specific, the later
+/// one wins.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RuleOverride {
+    /// Rule id, e.g. unstable-reference
+    pub rule: String,
+    /// Glob on the workflow path, or on its file name when the glob has no `/` (e.g. `deploy-*`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<String>,
+    /// Glob on the action, or on its name without the ref when the glob has no `@`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Trigger surface of the finding's workflow: anyone, contributors, maintainers or internal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger_surface: Option<TriggerSurface>,
+    pub level: Level,
+}
+
+impl RuleOverride {
+    /// How specific the entry is, or `None` when it does not apply to the site. A workflow
+    /// selector never applies to findings that are not in a workflow file.
+    fn specificity(&self, rule: &str, workflow: Option<&str>, action: &str, surface: Option<TriggerSurface>) -> Option<u8> {
+        if self.rule != rule {
+            return None;
+        }
+        let matches = |pattern: &str, value: &str| glob::Pattern::new(pattern).is_ok_and(|p| p.matches(value));
+        let workflow_matches = match (&self.workflow, workflow) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(pattern), Some(path)) => {
+                let name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or(path);
+                let stem = Path::new(name).file_stem().and_then(|n| n.to_str()).unwrap_or(name);
+                if pattern.contains('/') { matches(pattern, path) } else { matches(pattern, name) || matches(pattern, stem) }
+            }
+        };
+        let action_matches = match &self.action {
+            None => true,
+            Some(pattern) if pattern.contains('@') => matches(pattern, action),
+            Some(pattern) => matches(pattern, action.split_once('@').map_or(action, |(name, _)| name)),
+        };
+        let surface_matches = self.trigger_surface.is_none() || self.trigger_surface == surface;
+        let rank = |selector: bool, weight: u8| if selector { weight } else { 0 };
+        (workflow_matches && action_matches && surface_matches)
+            .then_some(rank(self.action.is_some(), 4) + rank(self.workflow.is_some(), 2) + rank(self.trigger_surface.is_some(), 1))
+    }
+}
+
+impl Config {
+    /// The `rule_overrides` entry that decides a rule's level at a finding site, with its index
+    pub fn rule_override(
+        &self,
+        rule: &str,
+        workflow: Option<&str>,
+        action: &str,
+        surface: Option<TriggerSurface>,
+    ) -> Option<(usize, &RuleOverride)> {
+        self.rule_overrides
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((entry.specificity(rule, workflow, action, surface)?, index, entry)))
+            .max_by_key(|(specificity, index, _)| (*specificity, *index))
+            .map(|(_, index, entry)| (index, entry))
+    }
+
+    /// The `pin_policies` entry for an action, as its pattern and policy. A pattern matches the
+    /// action's `owner/repo`, or its `owner/repo/path` for an action in a subdirectory. A pattern
+    /// without wildcards beats every glob, and among globs the one with more literal characters
+    /// wins, so `acme/deploy-*` beats `acme/*`.
+    pub fn pin_policy(&self, action: &str) -> Option<(&str, &PinPolicy)> {
+        let name = action.split_once('@').map_or(action, |(name, _)| name);
+        let repo = name.splitn(3, '/').take(2).collect::<Vec<_>>().join("/");
+        let options = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+        self.pin_policies
+            .iter()
+            .flatten()
+            .filter(|(pattern, _)| {
+                glob::Pattern::new(pattern).is_ok_and(|p| p.matches_with(name, options) || p.matches_with(&repo, options))
+            })
+            .max_by_key(|(pattern, _)| {
+                let literal = pattern.chars().filter(|c| !matches!(c, '*' | '?' | '[' | ']')).count();
+                (!pattern.contains(['*', '?', '[']), literal)
+            })
+            .map(|(pattern, policy)| (pattern.as_str(), policy))
+    }
+
+    /// `pin_policies` patterns must compile and name `owner/repo`; levels are checked as the
+    /// config is read
+    fn check_pin_policies(&self) -> Result<(), String> {
+        for pattern in self.pin_policies.iter().flat_map(|p| p.keys()) {
+            glob::Pattern::new(pattern).map_err(|e| format!("pin_policies entry {}: {}", pattern, e))?;
+            if pattern.split('/').count() < 2 || pattern.contains('@') {
+                return Err(format!("pin_policies entry {} is not an owner/repo pattern", pattern));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject `rule_overrides` entries with unknown rule ids or globs that do not parse
+    fn check_rule_overrides(&self) -> Result<(), String> {
+        for (index, entry) in self.rule_overrides.iter().flatten().enumerate() {
+            if !crate::report::Rule::ALL.iter().any(|r| r.id() == entry.rule) {
+                return Err(format!("rule_overrides entry {}: unknown rule {}", index + 1, entry.rule));
+            }
+            for pattern in entry.workflow.iter().chain(&entry.action) {
+                glob::Pattern::new(pattern).map_err(|e| format!("rule_overrides entry {}: {}: {}", index + 1, pattern, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Glob entries of `trusted_owners` and `trusted_repos` must compile; a typo would otherwise
+    /// quietly trust nothing
+    fn check_trust_patterns(&self) -> Result<(), String> {
+        let entries = [("trusted_owners", &self.trusted_owners), ("trusted_repos", &self.trusted_repos)];
+        for (key, entry) in entries.iter().flat_map(|(key, entries)| entries.iter().flatten().map(move |e| (key, e))) {
+            if entry.contains(['*', '?', '[']) {
+                glob::Pattern::new(entry).map_err(|e| format!("{} entry {}: {}", key, entry, e))?;
+            }
+        }
+        if let Some(entry) = self.trusted_repos.iter().flatten().find(|e| e.split('/').count() != 2) {
+            return Err(format!("trusted_repos entry {} is not an owner/repo name", entry));
+        }
+        Ok(())
+    }
+
+    /// `blocked_actions` entries are `owner/repo` or `owner/repo@ref`, `blocked_owners` entries
+    /// bare owners
+    fn check_blocklists(&self) -> Result<(), String> {
+        for entry in self.blocked_actions.iter().flatten() {
+            let (name, version) = entry.name().split_once('@').unwrap_or((entry.name(), "-"));
+            let parts: Vec<&str> = name.split('/').collect();
+            if parts.len() != 2 || parts.iter().any(|p| p.is_empty()) || version.is_empty() {
+                return Err(format!("blocked_actions entry {} is not owner/repo or owner/repo@ref", entry.name()));
+            }
+        }
+        if let Some(entry) = self.blocked_owners.iter().flatten().find(|e| e.name().is_empty() || e.name().contains(['/', '@'])) {
+            return Err(format!("blocked_owners entry {} is not an owner name", entry.name()));
+        }
+        Ok(())
+    }
+}
+
+/// Apply `rule_overrides` to every finding, under the config governing the finding's workflow
+/// (`governing`, by path) or the root config, and the workflow's trigger surface (`surfaces`).
+/// Returns each change as (finding index, old level).
+pub fn apply_rule_overrides(
+    findings: &mut [Finding],
+    root: &Config,
+    governing: &BTreeMap<String, Config>,
+    surfaces: &BTreeMap<String, Surface>,
+) -> Vec<(usize, Level)> {
+    let mut changed = Vec::new();
+    for (index, finding) in findings.iter_mut().enumerate() {
+        let config = finding.file.as_ref().and_then(|f| governing.get(f)).unwrap_or(root);
+        let surface = finding.file.as_ref().and_then(|f| surfaces.get(f)).map(|s| s.surface);
+        if let Some((_, entry)) = config.rule_override(finding.rule.id(), finding.file.as_deref(), &finding.action, surface)
+            && entry.level != finding.level
+        {
+            changed.push((index, finding.level));
+            finding.level = entry.level;
+        }
+    }
+    changed
+}
+
+/// A `blocked_actions` or `blocked_owners` entry: the name alone, or with the reason its
+/// findings give
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum BlockedEntry {
+    Name(String),
+    WithReason {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+impl BlockedEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            BlockedEntry::Name(name) | BlockedEntry::WithReason { name, .. } => name,
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            BlockedEntry::WithReason { reason: Some(reason), .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+/// How an action must be pinned under a `pin_policies` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PinLevel {
+    /// A full commit SHA
+    Sha,
+    /// A tag or a full commit SHA
+    Tag,
+    /// Any ref, branches included
+    BranchAllowed,
+    /// Nothing; the action may be unpinned
+    Any,
+}
+
+impl PinLevel {
+    pub fn describe(self) -> &'static str {
+        match self {
+            PinLevel::Sha => "a full commit SHA",
+            PinLevel::Tag => "a tag or commit SHA",
+            PinLevel::BranchAllowed => "a ref",
+            PinLevel::Any => "nothing",
+        }
+    }
+}
+
+/// The pin a `pin_policies` entry requires of the actions it matches
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PinPolicy {
+    /// sha, tag, branch-allowed or any
+    pub pin: PinLevel,
+    /// Refs accepted whatever `pin` requires, e.g. main for an internal action that tracks it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_refs: Option<Vec<String>>,
+}
+
+/// Ignore file read from the working directory when --ignore-file does not name one
+pub const DEFAULT_IGNORE_FILE: &str = ".dependency-scanner-ignore.yaml";
+
+/// The scanner config file. The JSON Schema for it is printed by `schema config`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Optional definition of critical dependencies. These require version pinning.
+    pub critical_dependencies: Option<Vec<String>>,
+    /// Trusted GitHub owners/organizations, or globs of them such as acme-*. These do not require version pinning. Unset, actions and github are trusted.
+    pub trusted_owners: Option<BTreeSet<String>>,
+    /// owner/repo repositories (or globs) trusted even when their owner is not.
+    pub trusted_repos: Option<BTreeSet<String>>,
+    /// owner/repo actions, or owner/repo@ref releases of them, whose use always fails the scan.
+    pub blocked_actions: Option<Vec<BlockedEntry>>,
+    /// Owners whose actions always fail the scan.
+    pub blocked_owners: Option<Vec<BlockedEntry>>,
+    /// Maximum number of distinct non-trusted owners the whole scan may depend on.
+    pub max_untrusted_owners: Option<usize>,
+    /// Local action path -> upstream owner/repo@ref it was vendored from.
+    pub vendored_actions: Option<BTreeMap<String, String>>,
+    /// Keys of the root config that directory-scoped configs may not override.
+    pub enforced: Option<Vec<String>>,
+    /// Rule id -> level for visibility-sensitive rules in private repos.
+    pub private_repo_levels: Option<BTreeMap<String, Level>>,
+    /// Extra regexes (named groups repo, ref, optional workflow) for --scan-run-steps.
+    pub run_step_patterns: Option<Vec<String>>,
+    /// Pattern id -> regex for insecure downloads in run steps; a built-in id replaces it.
+    pub insecure_download_patterns: Option<BTreeMap<String, String>>,
+    /// Insecure-download pattern ids to turn off.
+    pub disabled_insecure_downloads: Option<Vec<String>>,
+    /// Severity -> CVSS score band, used instead of the provider's label.
+    #[schemars(with = "Option<BTreeMap<String, String>>")]
+    pub severity_mapping: Option<SeverityMapping>,
+    /// Downgrade advisories without a patched version to warnings, after severity mapping.
+    pub fail_only_if_fix_available: Option<bool>,
+    /// Days after publication during which an advisory is reported as a warning with a due date.
+    pub grace_period_days: Option<u32>,
+    /// Level of findings for SHA pins that no release contains (default warning).
+    pub unreleased_commit_level: Option<Level>,
+    /// Longest inline actions/github-script script --scan-github-script accepts (default 50 lines).
+    pub github_script_max_lines: Option<usize>,
+    /// Refs treated as branch pins when the default branch cannot be looked up (default main, master, latest); refs with a / always are.
+    pub unstable_ref_names: Option<Vec<String>>,
+    /// Workflow files larger than this many bytes are refused rather than parsed (default 1 MiB).
+    pub max_workflow_bytes: Option<usize>,
+    /// Deepest nesting a workflow may have (default 64).
+    pub max_workflow_depth: Option<usize>,
+    /// Most YAML alias references a workflow may contain (default 100).
+    pub max_workflow_aliases: Option<usize>,
+    /// Deadline for parsing one workflow file, e.g. 5s (the default).
+    pub workflow_parse_timeout: Option<String>,
+    /// Regex with a named version group for the comment after a SHA pin (default accepts
+    /// `v4`, `tag=v4` and `renovate: tag=v4`).
+    pub pin_comment_pattern: Option<String>,
+    /// Template for comments written by fix, e.g. "tag={version}" (default "{version}").
+    pub pin_comment_format: Option<String>,
+    /// owner/repo actions security has signed off on; review-bump fails on other newly added dependencies.
+    pub approved_new_dependencies: Option<Vec<String>>,
+    /// Accept tag pins whose release GitHub marks immutable in place of SHA pins (default false).
+    pub exempt_immutable_releases: Option<bool>,
+    /// Docker registries, or registry/namespace prefixes, whose images may be pinned by tag (default docker.io).
+    pub trusted_registries: Option<BTreeSet<String>>,
+    /// Trust ghcr.io images whose namespace is a trusted owner (default false).
+    pub trust_ghcr_owners: Option<bool>,
+    /// Rule levels for findings in matching workflows and/or actions; the most specific entry wins.
+    pub rule_overrides: Option<Vec<RuleOverride>>,
+    /// owner/repo pattern (or glob) -> the pin its actions need, used instead of critical_dependencies and --strict; the most specific pattern wins.
+    pub pin_policies: Option<BTreeMap<String, PinPolicy>>,
+    /// Level of findings for branch pins (unstable_ref_names) of non-critical actions; unset, only --strict reports them.
+    pub unstable_ref_level: Option<Level>,
+    /// Treat every action as listed in critical_dependencies (default false).
+    pub critical_by_default: Option<bool>,
+    /// Fail when an action could not be checked against the advisory database, as --fail-on-unchecked does.
+    pub fail_on_unchecked: Option<bool>,
+    /// Oldest scanner version that may apply this config, e.g. "0.9.0"; older ones refuse to run.
+    pub minimum_scanner_version: Option<String>,
+}
+
+/// Root config file used when neither --config nor the environment names one
+pub const DEFAULT_CONFIG_FILE: &str = "critical_dependencies.yaml";
+
+/// Environment variable naming the root config when --config is not given
+pub const CONFIG_ENV: &str = "DEPENDENCY_SCANNER_CONFIG";
+
+/// The root config file and who chose it
+pub struct ConfigPath {
+    pub path: String,
+    /// `--config` or the environment variable; `None` for the default file
+    pub source: Option<&'static str>,
+}
+
+impl ConfigPath {
+    /// `--config`, else `$DEPENDENCY_SCANNER_CONFIG`, else `./critical_dependencies.yaml`
+    pub fn resolve(flag: Option<&str>) -> ConfigPath {
+        if let Some(path) = flag {
+            return ConfigPath { path: path.to_string(), source: Some("--config") };
+        }
+        match std::env::var(CONFIG_ENV).ok().filter(|p| !p.is_empty()) {
+            Some(path) => ConfigPath { path, source: Some("$DEPENDENCY_SCANNER_CONFIG") },
+            None => ConfigPath { path: DEFAULT_CONFIG_FILE.to_string(), source: None },
+        }
+    }
+}
+
+/// Load the raw YAML of the root config. Only the default file may be missing, in which case the
+/// built-in defaults apply; a file someone named must exist, so a typo cannot turn the policy off.
+fn load_config_value(config: &ConfigPath, warnings: &mut Vec<String>) -> Result<(String, Value), ScanError> {
+    match fs::read_to_string(&config.path) {
+        Ok(content) => {
+            let value: Value = serde_yaml::from_str(&content).map_err(|e| ScanError::yaml(&config.path, &e))?;
+            // An empty file is a valid (empty) config
+            Ok((content, if value.is_null() { Value::Mapping(Mapping::new()) } else { value }))
+        },
+        Err(e) => {
+            if let Some(source) = config.source {
+                return Err(ScanError::io(&format!("config file {} (from {})", config.path, source), e));
+            }
+            warnings.push(format!("Warning: Could not load config file {}: {}", config.path, e));
+            let defaults = Config {
+                critical_dependencies: Some(Vec::new()),
+                ..Default::default()
+            };
+            let value = serde_yaml::to_value(defaults).map_err(|e| ScanError::config(&config.path, e))?;
+            Ok((String::new(), value))
+        }
+    }
+}
+
+/// Keep the warnings of a config file's lint, and fail with its errors
+fn apply_lint(path: &str, lint: Lint, warnings: &mut Vec<String>) -> Result<(), ScanError> {
+    warnings.extend(lint.warnings.iter().map(|warning| format!("Warning: {}: {}", path, warning)));
+    match lint.errors.as_slice() {
+        [] => Ok(()),
+        [only] => Err(ScanError::config_at(path, only.line, &only.message)),
+        errors => {
+            let listed: Vec<String> = errors.iter().map(|e| format!("\n  {}", e)).collect();
+            Err(ScanError::config(path, format!("{} problems:{}", errors.len(), listed.concat())))
+        }
+    }
+}
+
+/// Deserialize a checked config document. The lint has caught unknown keys, so what fails here
+/// is a key of the wrong type, which the schema names by key where serde, working from the
+/// parsed document, could only say what it expected.
+fn deserialize(path: &str, source: &str, document: Value) -> Result<Config, ScanError> {
+    serde_yaml::from_value(document.clone()).map_err(|e| match crate::schema::validate_config(&document) {
+        Err(message) => ScanError::config_at(path, config_lint::schema_error_line(source, &message), message),
+        Ok(()) => ScanError::config(path, e),
+    })
+}
+
+/// One entry of the ignore file: findings for an action, an advisory, a rule, or any combination
+/// of them, accepted for a stated reason until an optional expiry date
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreEntry {
+    /// `owner/repo` for every version, or `owner/repo@ref` for one
+    pub action: Option<String>,
+    /// Advisory id, e.g. GHSA-xxxx-xxxx-xxxx
+    pub advisory: Option<String>,
+    /// Rule id, e.g. untrusted-owner-no-sha, or under `--rule-id-compat` the tool's id for it
+    pub rule: Option<String>,
+    pub reason: String,
+    /// Last day (YYYY-MM-DD) the entry applies; after it the findings fail again
+    pub expires: Option<chrono::NaiveDate>,
+}
+
+impl IgnoreEntry {
+    /// Short description for messages: the rule, action and advisory it names
+    pub fn describe(&self) -> String {
+        let target = match (&self.action, &self.advisory) {
+            (Some(action), Some(advisory)) => format!("{} in {}", advisory, action),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => String::new(),
+        };
+        match &self.rule {
+            Some(rule) if target.is_empty() => rule.clone(),
+            Some(rule) => format!("{} for {}", rule, target),
+            None => target,
+        }
+    }
+
+    fn matches(&self, finding: &Finding, tool: Option<Tool>) -> bool {
+        let action_matches = self.action.as_ref().is_none_or(|action| {
+            let name = finding.action.split_once('@').map_or(finding.action.as_str(), |(name, _)| name);
+            if action.contains('@') { *action == finding.action } else { action.eq_ignore_ascii_case(name) }
+        });
+        let advisory_matches = self
+            .advisory
+            .as_ref()
+            .is_none_or(|id| finding.advisory.as_ref().is_some_and(|a| a.id.eq_ignore_ascii_case(id)));
+        let rule_matches = self.rule.as_ref().is_none_or(|id| compat::names_rule(tool, id, finding.rule));
+        action_matches && advisory_matches && rule_matches && !finding.action.is_empty()
+    }
+}
+
+/// Load the ignore file. A missing default file means nothing is ignored; a file named with
+/// `--ignore-file` must exist. Every entry needs an action, an advisory or a rule, and a reason.
+pub fn load_ignore_file(flag: Option<&str>) -> Result<Vec<IgnoreEntry>, ScanError> {
+    let path = flag.unwrap_or(DEFAULT_IGNORE_FILE);
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if flag.is_some() => return Err(ScanError::io(&format!("ignore file {} (from --ignore-file)", path), e)),
+        Err(_) => return Ok(Vec::new()),
+    };
+    let entries: Option<Vec<IgnoreEntry>> = serde_yaml::from_str(&content).map_err(|e| ScanError::yaml(path, &e))?;
+    let entries = entries.unwrap_or_default();
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.action.is_none() && entry.advisory.is_none() && entry.rule.is_none() {
+            return Err(ScanError::config(path, format!("entry {} names no action, advisory or rule", index + 1)));
+        }
+        if entry.reason.trim().is_empty() {
+            return Err(ScanError::config(path, format!("entry {} ({}) has an empty reason", index + 1, entry.describe())));
+        }
+    }
+    Ok(entries)
+}
+
+/// Mark the findings the ignore file covers as suppressed. A finding whose entry expired before
+/// `today` is marked too, as expired, and still counts. Rules are named by their own ids or, with
+/// a `--rule-id-compat` tool, by its ids. Returns the entries that matched nothing.
+pub fn apply_ignore_file<'a>(
+    findings: &mut [Finding],
+    entries: &'a [IgnoreEntry],
+    tool: Option<Tool>,
+    today: chrono::NaiveDate,
+) -> Vec<&'a IgnoreEntry> {
+    let mut used = vec![false; entries.len()];
+    // A ban is the config's own decision; the ignore file does not lift it
+    for finding in findings.iter_mut().filter(|f| f.rule.category() != Category::Blocked) {
+        let Some(index) = entries.iter().position(|entry| entry.matches(finding, tool)) else {
+            continue;
+        };
+        used[index] = true;
+        let entry = &entries[index];
+        finding.suppression = Some(Suppression {
+            reason: entry.reason.clone(),
+            expires: entry.expires.map(|d| d.to_string()),
+            expired: entry.expires.is_some_and(|d| d < today),
+            baseline: false,
+            unchanged: false,
+        });
+    }
+    entries.iter().zip(used).filter(|(_, used)| !used).map(|(entry, _)| entry).collect()
+}
+
+/// The root config plus the directory-scoped configs that cascade over it
+pub struct ConfigSet {
+    root_path: String,
+    root: Value,
+    root_config: Config,
+    validate: bool,
+    profile: Option<Profile>,
+    /// Scoped config files already linted, so a directory's warnings are kept once
+    linted: Mutex<BTreeSet<PathBuf>>,
+    /// Warnings of the config files loaded so far, until the scan reports them
+    warnings: Mutex<Vec<String>>,
+}
+
+/// A config built in code rather than loaded, such as a library caller's. Directory-scoped
+/// configs still cascade over it.
+impl From<Config> for ConfigSet {
+    fn from(config: Config) -> Self {
+        ConfigSet {
+            root_path: "<config>".to_string(),
+            root: serde_yaml::to_value(&config).unwrap_or_else(|_| Value::Mapping(Mapping::new())),
+            root_config: config,
+            validate: false,
+            profile: None,
+            linted: Mutex::new(BTreeSet::new()),
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// The config that applies to one workflow file, and where it came from
+pub struct EffectiveConfig {
+    pub config: Config,
+    /// The nearest directory-scoped config, if one was found
+    pub scoped: Option<PathBuf>,
+    /// Keys the scoped config tried to set but the root config enforces
+    pub overridden_enforced: Vec<String>,
+}
+
+impl EffectiveConfig {
+    /// Human-readable description of which config files governed a workflow
+    pub fn describe(&self, root_path: &str) -> String {
+        match &self.scoped {
+            Some(scoped) => format!("{} over {}", scoped.display(), root_path),
+            None => root_path.to_string(),
+        }
+    }
+}
+
+impl ConfigSet {
+    /// Load the root config, merged over the `profile`'s defaults. With `validate`, every config
+    /// document is first checked against the config schema so structural mistakes are reported by
+    /// key rather than by serde position.
+    pub fn load(config: &ConfigPath, validate: bool, profile: Option<Profile>) -> Result<Self, ScanError> {
+        let root_path = config.path.as_str();
+        let mut warnings = Vec::new();
+        let (source, root) = load_config_value(config, &mut warnings)?;
+        apply_lint(root_path, config_lint::check(&source, &root, &crate::schema::config_keys()), &mut warnings)?;
+        if validate {
+            crate::schema::validate_config(&root)
+                .map_err(|e| ScanError::config_at(root_path, config_lint::schema_error_line(&source, &e), e))?;
+        }
+        let root = match profile {
+            Some(profile) => crate::profile::merge_under(profile, root).map_err(|e| ScanError::config(root_path, e))?,
+            None => root,
+        };
+        let root_config = deserialize(root_path, &source, root.clone())?;
+        crate::pincomment::CommentStyle::from_config(&root_config).map_err(|e| ScanError::config(root_path, e))?;
+        root_config.check_rule_overrides().map_err(|e| ScanError::config(root_path, e))?;
+        root_config.check_trust_patterns().map_err(|e| ScanError::config(root_path, e))?;
+        root_config.check_blocklists().map_err(|e| ScanError::config(root_path, e))?;
+        root_config.check_pin_policies().map_err(|e| ScanError::config(root_path, e))?;
+        Ok(ConfigSet {
+            root_path: root_path.to_string(),
+            root,
+            root_config,
+            validate,
+            profile,
+            linted: Mutex::new(BTreeSet::new()),
+            warnings: Mutex::new(warnings),
+        })
+    }
+
+    pub fn root(&self) -> &Config {
+        &self.root_config
+    }
+
+    /// `--no-default-trust`: a root config without `trusted_owners` trusts no owner instead of
+    /// the built-in defaults, and so do the scoped configs over it that set none either
+    pub fn without_default_trust(mut self) -> Self {
+        if self.root_config.trusted_owners.is_none() {
+            self.root_config.trusted_owners = Some(BTreeSet::new());
+            if !self.root.is_mapping() {
+                self.root = Value::Mapping(Mapping::new());
+            }
+            if let Some(root) = self.root.as_mapping_mut() {
+                root.insert(Value::from("trusted_owners"), Value::Sequence(Vec::new()));
+            }
+        }
+        self
+    }
+
+    /// The warnings of the config files loaded since the last call, such as a missing default
+    /// file or a lint warning, for the caller to report
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn root_path(&self) -> &str {
+        &self.root_path
+    }
+
+    /// The root config file, and the profile under it when one is set
+    pub fn describe_root(&self) -> String {
+        match self.profile {
+            Some(profile) => format!("{} over the {} profile", self.root_path, profile.name()),
+            None => self.root_path.clone(),
+        }
+    }
+
+    /// The effective config for a workflow: the nearest `dependency-scanner.yaml` in its parent
+    /// directories merged over the root config, with the root's `enforced` keys always winning
+    pub fn for_workflow(&self, workflow: &Path) -> Result<EffectiveConfig, ScanError> {
+        let Some(scoped) = find_scoped_config(workflow) else {
+            return Ok(EffectiveConfig {
+                config: self.root_config.clone(),
+                scoped: None,
+                overridden_enforced: Vec::new(),
+            });
+        };
+
+        let scoped_path = scoped.display().to_string();
+        let content = fs::read_to_string(&scoped).map_err(|e| ScanError::io(&scoped_path, e))?;
+        let overlay: Value = serde_yaml::from_str(&content).map_err(|e| ScanError::yaml(&scoped_path, &e))?;
+        let overlay = if overlay.is_null() { Value::Mapping(Mapping::new()) } else { overlay };
+        // Workflows of one directory share its config; its warnings are kept once
+        let mut lint = config_lint::check(&content, &overlay, &crate::schema::config_keys());
+        if !self.linted.lock().unwrap_or_else(|e| e.into_inner()).insert(scoped.clone()) {
+            lint.warnings.clear();
+        }
+        apply_lint(&scoped_path, lint, &mut self.warnings.lock().unwrap_or_else(|e| e.into_inner()))?;
+        let enforced = self.root_config.enforced.clone().unwrap_or_default();
+
+        let mut merged = self.root.as_mapping().cloned().unwrap_or_default();
+        let mut overridden_enforced = Vec::new();
+        if let Some(overlay) = overlay.as_mapping() {
+            for (key, value) in overlay {
+                let name = key.as_str().unwrap_or("");
+                // Only the root config decides what is enforced
+                if name == "enforced" {
+                    continue;
+                }
+                if enforced.iter().any(|k| k == name) && merged.contains_key(key) {
+                    overridden_enforced.push(name.to_string());
+                    continue;
+                }
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+
+        let merged = Value::Mapping(merged);
+        if self.validate {
+            crate::schema::validate_config(&merged)
+                .map_err(|e| ScanError::config_at(&scoped_path, config_lint::schema_error_line(&content, &e), e))?;
+        }
+        let config = deserialize(&scoped_path, &content, merged)?;
+        config.check_rule_overrides().map_err(|e| ScanError::config(&scoped_path, e))?;
+        config.check_trust_patterns().map_err(|e| ScanError::config(&scoped_path, e))?;
+        config.check_blocklists().map_err(|e| ScanError::config(&scoped_path, e))?;
+        config.check_pin_policies().map_err(|e| ScanError::config(&scoped_path, e))?;
+        Ok(EffectiveConfig {
+            config,
+            scoped: Some(scoped),
+            overridden_enforced,
+        })
+    }
+}
+
+/// Walk up from a workflow's directory to the repository root looking for the nearest scoped config
+fn find_scoped_config(workflow: &Path) -> Option<PathBuf> {
+    let workflow = workflow.canonicalize().ok()?;
+    let stop = crate::repo_root().canonicalize().ok();
+    let mut dir = workflow.parent();
+    while let Some(current) = dir {
+        let candidate = current.join(SCOPED_CONFIG_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        // Never look above the repository the workflow lives in
+        if stop.as_deref() == Some(current) || current.join(".git").exists() {
+            return None;
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Rule;
+
+    /// A config of `rule_overrides` entries given as YAML flow mappings
+    fn overrides(entries: &[&str]) -> Config {
+        let yaml = format!("rule_overrides:\n{}", entries.iter().map(|e| format!("  - {}\n", e)).collect::<String>());
+        let config: Config = serde_yaml::from_str(&yaml).unwrap();
+        config.check_rule_overrides().unwrap();
+        config
+    }
+
+    /// The 1-based entry and level deciding the rule at the site, if any
+    fn resolved(config: &Config, rule: &str, workflow: Option<&str>, action: &str, surface: Option<TriggerSurface>) -> Option<(usize, Level)> {
+        config.rule_override(rule, workflow, action, surface).map(|(index, entry)| (index + 1, entry.level))
+    }
+
+    #[test]
+    fn the_most_specific_override_wins() {
+        let config = overrides(&[
+            "{rule: unstable-reference, level: warning}",
+            "{rule: unstable-reference, workflow: deploy-*, level: error}",
+            "{rule: unstable-reference, action: acme/*, level: note}",
+            "{rule: unstable-reference, workflow: deploy-*, action: acme/*, level: warning}",
+            "{rule: unstable-reference, trigger_surface: anyone, level: error}",
+            "{rule: unstable-reference, workflow: .github/workflows/release/*.yml, level: note}",
+            "{rule: unpinned-dependency, action: 'acme/tool@v1', level: note}",
+        ]);
+        let ci = Some(".github/workflows/ci.yml");
+        let deploy = Some(".github/workflows/deploy-prod.yml");
+        let anyone = Some(TriggerSurface::Anyone);
+        for (rule, workflow, action, surface, expected) in [
+            ("unstable-reference", ci, "other/tool@main", None, Some((1, Level::Warning))),
+            ("unstable-reference", deploy, "other/tool@main", None, Some((2, Level::Error))),
+            ("unstable-reference", ci, "acme/tool@main", None, Some((3, Level::Note))),
+            ("unstable-reference", deploy, "acme/tool@main", None, Some((4, Level::Warning))),
+            ("unstable-reference", ci, "other/tool@main", anyone, Some((5, Level::Error))),
+            // A workflow selector outranks a trigger surface, an action one outranks both
+            ("unstable-reference", deploy, "other/tool@main", anyone, Some((2, Level::Error))),
+            ("unstable-reference", ci, "acme/tool@main", anyone, Some((3, Level::Note))),
+            // A glob with a `/` matches the path rather than the file name
+            ("unstable-reference", Some(".github/workflows/release/nightly.yml"), "other/tool@main", None, Some((6, Level::Note))),
+            // Scan-level findings are in no workflow, so workflow selectors skip them
+            ("unstable-reference", None, "other/tool@main", None, Some((1, Level::Warning))),
+            // An action glob with an `@` matches the ref too
+            ("unpinned-dependency", ci, "acme/tool@v1", None, Some((7, Level::Note))),
+            ("unpinned-dependency", ci, "acme/tool@v2", None, None),
+            ("untrusted-owner", deploy, "acme/tool@main", anyone, None),
+        ] {
+            assert_eq!(resolved(&config, rule, workflow, action, surface), expected, "{} {:?} {} {:?}", rule, workflow, action, surface);
+        }
+    }
+
+    #[test]
+    fn among_equally_specific_entries_the_later_one_wins() {
+        let config = overrides(&["{rule: untrusted-owner, action: acme/*, level: error}", "{rule: untrusted-owner, action: 'acme/t*', level: note}"]);
+        assert_eq!(resolved(&config, "untrusted-owner", None, "acme/tool@v1", None), Some((2, Level::Note)));
+        assert_eq!(resolved(&config, "untrusted-owner", None, "acme/deploy@v1", None), Some((1, Level::Error)));
+    }
+
+    #[test]
+    fn the_workflow_glob_matches_the_file_name_with_or_without_extension() {
+        for pattern in ["deploy-*", "deploy-prod.yml", "deploy-prod"] {
+            let config = overrides(&[&format!("{{rule: untrusted-owner, workflow: '{}', level: note}}", pattern)]);
+            assert!(resolved(&config, "untrusted-owner", Some(".github/workflows/deploy-prod.yml"), "a/b@v1", None).is_some(), "{}", pattern);
+            assert!(resolved(&config, "untrusted-owner", Some(".github/workflows/ci.yml"), "a/b@v1", None).is_none(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn unknown_rules_and_bad_globs_are_rejected() {
+        let parse = |entry: &str| serde_yaml::from_str::<Config>(&format!("rule_overrides:\n  - {}\n", entry)).unwrap().check_rule_overrides();
+        assert_eq!(parse("{rule: no-such-rule, level: note}"), Err("rule_overrides entry 1: unknown rule no-such-rule".to_string()));
+        assert!(parse("{rule: untrusted-owner, action: 'acme/[', level: note}").unwrap_err().starts_with("rule_overrides entry 1: acme/[:"));
+        assert!(serde_yaml::from_str::<Config>("rule_overrides:\n  - {rule: untrusted-owner, level: note, scope: all}\n").is_err());
+    }
+
+    #[test]
+    fn overrides_apply_under_the_config_governing_each_finding() {
+        let finding = |file: &str| {
+            Finding::new(Rule::UntrustedOwner, Level::Warning, "acme/tool@v1", "untrusted".to_string()).at(file, Some(3))
+        };
+        let root = overrides(&["{rule: untrusted-owner, level: error}"]);
+        let scoped = overrides(&["{rule: untrusted-owner, level: note}"]);
+        let governing: BTreeMap<String, Config> = [("scoped/ci.yml".to_string(), scoped)].into();
+        let mut findings = vec![finding("ci.yml"), finding("scoped/ci.yml")];
+        let changed = apply_rule_overrides(&mut findings, &root, &governing, &BTreeMap::new());
+        assert_eq!(changed, [(0, Level::Warning), (1, Level::Warning)]);
+        assert_eq!((findings[0].level, findings[1].level), (Level::Error, Level::Note));
+        assert!(apply_rule_overrides(&mut findings, &root, &governing, &BTreeMap::new()).is_empty(), "nothing changes twice");
+    }
+
+    fn trust_patterns(yaml: &str) -> Result<(), String> {
+        serde_yaml::from_str::<Config>(yaml).unwrap().check_trust_patterns()
+    }
+
+    #[test]
+    fn trust_entries_must_be_valid_globs_and_repos_owner_slash_repo() {
+        assert_eq!(trust_patterns("trusted_owners: [acme-*, actions]\ntrusted_repos: [docker/build-*]\n"), Ok(()));
+        let bad_owner = trust_patterns("trusted_owners: ['acme-[']\n").unwrap_err();
+        assert!(bad_owner.starts_with("trusted_owners entry acme-[: "), "{}", bad_owner);
+        let bad_repo = trust_patterns("trusted_repos: ['docker/[x']\n").unwrap_err();
+        assert!(bad_repo.starts_with("trusted_repos entry docker/[x: "), "{}", bad_repo);
+        for entry in ["docker", "docker/build-push-action/sub"] {
+            let message = trust_patterns(&format!("trusted_repos: [{}]\n", entry)).unwrap_err();
+            assert_eq!(message, format!("trusted_repos entry {} is not an owner/repo name", entry));
+        }
+    }
+
+    fn pin_policies(yaml: &str) -> Config {
+        let config: Config = serde_yaml::from_str(&format!("pin_policies:\n{}", yaml)).unwrap();
+        config.check_pin_policies().unwrap();
+        config
+    }
+
+    #[test]
+    fn the_most_specific_pin_policy_wins() {
+        let config = pin_policies(
+            "  '*/*': {pin: tag}\n  acme/*: {pin: sha}\n  acme/deploy-*: {pin: any}\n  acme/deploy-prod: {pin: sha}\n  acme/?ool: {pin: branch-allowed}\n",
+        );
+        for (action, expected) in [
+            ("actions/checkout@v4", Some(("*/*", PinLevel::Tag))),
+            ("acme/build@v1", Some(("acme/*", PinLevel::Sha))),
+            ("acme/deploy-staging@v1", Some(("acme/deploy-*", PinLevel::Any))),
+            // An exact name beats every glob, however many literals they hold
+            ("acme/deploy-prod@v1", Some(("acme/deploy-prod", PinLevel::Sha))),
+            ("acme/tool@main", Some(("acme/?ool", PinLevel::BranchAllowed))),
+            // Owner and repo match case-insensitively, and a path falls back to its repo
+            ("Acme/Deploy-Prod/sub@v1", Some(("acme/deploy-prod", PinLevel::Sha))),
+            ("acme/build/nested/path@v1", Some(("acme/*", PinLevel::Sha))),
+            ("acme", None),
+        ] {
+            assert_eq!(config.pin_policy(action).map(|(pattern, policy)| (pattern, policy.pin)), expected, "{}", action);
+        }
+        assert!(pin_policies("  acme/*: {pin: sha}\n").pin_policy("other/tool@v1").is_none());
+    }
+
+    #[test]
+    fn a_pattern_with_a_path_wins_for_actions_under_it() {
+        let config = pin_policies("  acme/monorepo: {pin: tag}\n  acme/monorepo/deploy: {pin: sha}\n");
+        assert_eq!(config.pin_policy("acme/monorepo/deploy@v1").map(|(p, _)| p), Some("acme/monorepo/deploy"));
+        assert_eq!(config.pin_policy("acme/monorepo/lint@v1").map(|(p, _)| p), Some("acme/monorepo"));
+    }
+
+    #[test]
+    fn pin_policies_reject_unknown_levels_keys_and_patterns() {
+        let parse = |yaml: &str| serde_yaml::from_str::<Config>(&format!("pin_policies:\n{}", yaml));
+        let level = parse("  acme/*: {pin: always}\n").unwrap_err().to_string();
+        assert!(level.contains("unknown variant `always`"), "{}", level);
+        assert!(parse("  acme/*: {pin: sha, scope: all}\n").is_err());
+        assert!(parse("  acme/*: {allowed_refs: [main]}\n").is_err(), "pin is required");
+        assert_eq!(pin_policies("  acme/*: {pin: sha, allowed_refs: [main]}\n").pin_policies.unwrap()["acme/*"].allowed_refs, Some(vec!["main".to_string()]));
+        for pattern in ["acme", "acme/tool@v1"] {
+            let message = parse(&format!("  {}: {{pin: sha}}\n", pattern)).unwrap().check_pin_policies().unwrap_err();
+            assert_eq!(message, format!("pin_policies entry {} is not an owner/repo pattern", pattern));
+        }
+        let glob = parse("  'acme/[': {pin: sha}\n").unwrap().check_pin_policies().unwrap_err();
+        assert!(glob.starts_with("pin_policies entry acme/[: "), "{}", glob);
+    }
+
+    #[test]
+    fn ignore_file_errors_tell_a_bad_entry_from_a_missing_file() {
+        let dir = crate::scratch::Scratch::new("ignore-errors");
+        let missing = dir.join("missing.yaml").display().to_string();
+        assert!(matches!(load_ignore_file(Some(&missing)), Err(ScanError::Io { .. })));
+        let bad = dir.join("bad.yaml").display().to_string();
+        fs::write(&bad, "- reason: accepted\n").unwrap();
+        match load_ignore_file(Some(&bad)) {
+            Err(ScanError::ConfigInvalid { path, message, .. }) => {
+                assert_eq!((path.as_str(), message.as_str()), (bad.as_str(), "entry 1 names no action, advisory or rule"));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_missing_default_config_is_a_warning_kept_for_the_scan() {
+        let dir = crate::scratch::Scratch::new("config-warnings");
+        let path = dir.join("critical_dependencies.yaml").display().to_string();
+        let configs = ConfigSet::load(&ConfigPath { path: path.clone(), source: None }, false, None).unwrap();
+        let warnings = configs.take_warnings();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with(&format!("Warning: Could not load config file {}", path)), "{}", warnings[0]);
+        assert!(configs.take_warnings().is_empty());
+    }
+}
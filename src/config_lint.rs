@@ -0,0 +1,273 @@
+use crate::dispatch::source_line;
+use regex::Regex;
+use serde_yaml::Value;
+use std::fmt;
+
+/// One problem with a config file, at the 1-based line it is on when that can be found
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// What checking one config file found; errors stop the config from loading
+#[derive(Debug, Default)]
+pub struct Lint {
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+}
+
+/// The forms an entry naming actions can take
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    /// An owner, or a glob of owners
+    Owner,
+    /// `owner/repo`, or a glob of them
+    Repo,
+    /// `owner/repo`, optionally `@ref`
+    Release,
+    /// A `uses:` value, `owner/repo[/path][@ref]`
+    Action,
+    /// A `uses:` value with its ref, `owner/repo[/path]@ref`
+    Pin,
+}
+
+impl Shape {
+    fn describe(self) -> &'static str {
+        match self {
+            Shape::Owner => "an owner name",
+            Shape::Repo => "owner/repo",
+            Shape::Release => "owner/repo or owner/repo@ref",
+            Shape::Action => "owner/repo[/path][@ref]",
+            Shape::Pin => "owner/repo[/path]@ref",
+        }
+    }
+
+    fn matches(self, entry: &str) -> bool {
+        // Names as GitHub allows them, plus the glob characters the trusted lists accept
+        let name = r"[A-Za-z0-9_.*?\[\]-]+";
+        let pattern = match self {
+            Shape::Owner => format!("^{}$", name),
+            Shape::Repo => format!("^{0}/{0}$", name),
+            Shape::Release => format!(r"^{0}/{0}(@\S+)?$", name),
+            Shape::Action => format!(r"^{0}/{0}(/[^@\s]+)?(@\S+)?$", name),
+            Shape::Pin => format!(r"^{0}/{0}(/[^@\s]+)?@\S+$", name),
+        };
+        Regex::new(&pattern).unwrap().is_match(entry)
+    }
+}
+
+/// The list keys whose entries name actions, and the shape of each entry
+const LISTS: [(&str, Shape); 6] = [
+    ("critical_dependencies", Shape::Action),
+    ("trusted_owners", Shape::Owner),
+    ("trusted_repos", Shape::Repo),
+    ("approved_new_dependencies", Shape::Repo),
+    ("blocked_actions", Shape::Release),
+    ("blocked_owners", Shape::Owner),
+];
+
+/// Check a config document, parsed from `source`, for what deserializing it would miss or
+/// report by position only: keys the config does not have, each with the key it was probably
+/// meant to be, entries that do not name actions the way their key expects, and entries listed
+/// twice (a warning). `known` are the config's keys.
+pub fn check(source: &str, document: &Value, known: &[String]) -> Lint {
+    let mut lint = Lint::default();
+    let Some(mapping) = document.as_mapping() else {
+        lint.errors.push(Problem { line: None, message: "the config must be a mapping of keys such as trusted_owners".to_string() });
+        return lint;
+    };
+    for (key, value) in mapping {
+        let Some(key) = key.as_str() else {
+            lint.errors.push(Problem { line: None, message: format!("config keys are strings, not {:?}", key) });
+            continue;
+        };
+        if known.iter().any(|k| k == key) {
+            continue;
+        }
+        let mut message = format!("unknown key {}", key);
+        if let Some(hint) = suggestion(key, value, known) {
+            message.push_str(&format!("; {}", hint));
+        }
+        lint.errors.push(Problem { line: key_line(source, key), message });
+    }
+
+    for (key, shape) in LISTS {
+        let Some(entries) = mapping.get(key).and_then(Value::as_sequence) else { continue };
+        let key_at = key_line(source, key);
+        let mut seen: Vec<String> = Vec::new();
+        for entry in entries {
+            // Blocklist entries may be maps with the name under `name`
+            let Some(name) = entry.as_str().or_else(|| entry.get("name").and_then(Value::as_str)) else { continue };
+            let lines = entry_lines(source, name, key_at);
+            if !shape.matches(name) {
+                let message = format!("{} entry {} is not {}", key, name, shape.describe());
+                lint.errors.push(Problem { line: lines.first().copied().or(key_at), message });
+            }
+            if seen.iter().any(|s| s.eq_ignore_ascii_case(name)) {
+                let line = lines.get(1).or(lines.first()).copied().or(key_at);
+                lint.warnings.push(Problem { line, message: format!("{} lists {} more than once", key, name) });
+            } else {
+                seen.push(name.to_string());
+            }
+        }
+    }
+
+    if let Some(vendored) = mapping.get("vendored_actions").and_then(Value::as_mapping) {
+        let key_at = key_line(source, "vendored_actions");
+        for (local, upstream) in vendored {
+            let Some(upstream) = upstream.as_str() else { continue };
+            if !Shape::Pin.matches(upstream) {
+                let local = local.as_str().unwrap_or("");
+                let message = format!("vendored_actions entry {}: {} is not {}", local, upstream, Shape::Pin.describe());
+                lint.errors.push(Problem { line: source_line(source, upstream, key_at).or(key_at), message });
+            }
+        }
+    }
+    lint
+}
+
+/// The 1-based line of a top-level key, unindented as top-level keys are
+pub fn key_line(source: &str, key: &str) -> Option<usize> {
+    let index = source.lines().position(|line| {
+        let Some((name, _)) = line.split_once(':') else { return false };
+        !line.starts_with([' ', '\t', '#', '-']) && name.trim().trim_matches(|c| c == '"' || c == '\'') == key
+    })?;
+    Some(index + 1)
+}
+
+/// The line of the top-level key a schema error such as `trusted_owners[2]: expected string`
+/// is about
+pub fn schema_error_line(source: &str, message: &str) -> Option<usize> {
+    key_line(source, message.split(['.', '[', ':']).next().unwrap_or(""))
+}
+
+/// Lines from the key's own on that hold the entry, for pointing at a bad or repeated one. The
+/// entry must stand alone, so `acme` is not found in `acme/tool`.
+fn entry_lines(source: &str, entry: &str, from: Option<usize>) -> Vec<usize> {
+    let Some(from) = from else { return Vec::new() };
+    let part_of_name = |c: char| c.is_ascii_alphanumeric() || "_.-/@*?[]".contains(c);
+    let stands_alone = |line: &str| {
+        line.match_indices(entry).any(|(start, _)| {
+            let before = line[..start].chars().next_back();
+            let after = line[start + entry.len()..].chars().next();
+            !before.is_some_and(part_of_name) && !after.is_some_and(part_of_name)
+        })
+    };
+    source
+        .lines()
+        .enumerate()
+        .skip(from - 1)
+        .filter(|(_, line)| stands_alone(line.split('#').next().unwrap_or("")))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// The key an unknown one was probably meant to be: the same name spelled with `-` or in
+/// another case, a mapping that holds top-level keys one level too deep, or a near miss
+fn suggestion(key: &str, value: &Value, known: &[String]) -> Option<String> {
+    let normalized = key.to_ascii_lowercase().replace(['-', ' ', '.'], "_");
+    if let Some(found) = known.iter().find(|k| **k == normalized) {
+        return Some(format!("did you mean {}?", found));
+    }
+    if let Some(nested) = value.as_mapping()
+        && let Some(inner) = nested.keys().filter_map(Value::as_str).find(|k| known.iter().any(|known| known == k))
+    {
+        return Some(format!("it holds {}, which is a top-level key; is it indented one level too deep?", inner));
+    }
+    known
+        .iter()
+        .map(|k| (edit_distance(&normalized, k), k))
+        .filter(|(distance, k)| *distance <= 2.max(k.len() / 5))
+        .min()
+        .map(|(_, k)| format!("did you mean {}?", k))
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(source: &str) -> Lint {
+        let known: Vec<String> = ["trusted_owners", "trusted_repos", "critical_dependencies", "blocked_actions", "vendored_actions"]
+            .map(String::from)
+            .to_vec();
+        check(source, &serde_yaml::from_str(source).unwrap(), &known)
+    }
+
+    fn messages(problems: &[Problem]) -> Vec<String> {
+        problems.iter().map(Problem::to_string).collect()
+    }
+
+    #[test]
+    fn unknown_keys_name_the_key_they_were_meant_to_be() {
+        let source = "# policy\nTrusted-Owners: [actions]\ntrusted_repo: [a/b]\nsettings:\n  trusted_owners: [a]\nfrobnicate: 1\n";
+        assert_eq!(
+            messages(&lint(source).errors),
+            [
+                "line 2: unknown key Trusted-Owners; did you mean trusted_owners?",
+                "line 3: unknown key trusted_repo; did you mean trusted_repos?",
+                "line 4: unknown key settings; it holds trusted_owners, which is a top-level key; is it indented one level too deep?",
+                "line 6: unknown key frobnicate",
+            ]
+        );
+    }
+
+    #[test]
+    fn entries_must_have_the_shape_their_key_expects() {
+        let source = "trusted_owners: [actions, acme/tool, \"acme-*\"]\ntrusted_repos:\n  - acme/tool\n  - acme\ncritical_dependencies: [actions/checkout@v4, actions/cache/save, \"not an action\"]\nblocked_actions: [evil/tool@v1, evil]\nvendored_actions:\n  ./vendor/x: acme/x\n";
+        assert_eq!(
+            messages(&lint(source).errors),
+            [
+                "line 5: critical_dependencies entry not an action is not owner/repo[/path][@ref]",
+                "line 1: trusted_owners entry acme/tool is not an owner name",
+                // Not line 3, whose acme/tool only starts with the entry
+                "line 4: trusted_repos entry acme is not owner/repo",
+                "line 6: blocked_actions entry evil is not owner/repo or owner/repo@ref",
+                "line 8: vendored_actions entry ./vendor/x: acme/x is not owner/repo[/path]@ref",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_repeated_entry_is_a_warning_at_its_second_line() {
+        let lint = lint("trusted_owners:\n  - actions\n  - acme\n  - Actions\n");
+        assert!(lint.errors.is_empty(), "{:?}", lint.errors);
+        assert_eq!(messages(&lint.warnings), ["line 4: trusted_owners lists Actions more than once"]);
+    }
+
+    #[test]
+    fn a_document_that_is_not_a_mapping_is_one_error() {
+        assert_eq!(messages(&lint("- actions\n").errors), ["the config must be a mapping of keys such as trusted_owners"]);
+    }
+
+    #[test]
+    fn schema_errors_point_at_their_top_level_key() {
+        let source = "trusted_owners: [a]\ncritical_dependencies:\n  - 3\n";
+        assert_eq!(schema_error_line(source, "critical_dependencies[0]: expected string"), Some(2));
+        assert_eq!(schema_error_line(source, "missing: expected string"), None);
+        assert_eq!(key_line("  trusted_owners: [a]\n\"trusted_owners\": []\n", "trusted_owners"), Some(2));
+    }
+}
@@ -0,0 +1,804 @@
+use crate::render::{self, Marker};
+use crate::report::{Category, Finding, Level, Rule, ScanReport};
+use crate::severity::Severity;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// The thresholds that turn findings into a failing exit status
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitPolicy {
+    /// Findings at or above this level fail the scan
+    pub fail_on: Level,
+    /// Fail when any action could not be checked against the advisory database
+    pub fail_on_unchecked: bool,
+    /// `--require-complete`: the same, for incomplete coverage as the summary reports it
+    pub require_complete: bool,
+    /// Fail when any trusted-owner, critical-dependency or vendoring entry matched nothing
+    pub fail_on_unused_config: bool,
+    /// Fail when the scan would otherwise pass with warnings
+    pub fail_on_warnings: bool,
+    /// Exit 0 whatever the verdict (`--exit-zero`); the report keeps the code it would have been
+    pub exit_zero: bool,
+    /// Advisories below this severity only warn (`--fail-on-severity`); low, the default, fails on all
+    pub fail_on_severity: Severity,
+    /// Severity assumed for advisories that give none (`--unknown-severity`)
+    pub unknown_severity: Severity,
+}
+
+impl Default for ExitPolicy {
+    fn default() -> Self {
+        ExitPolicy {
+            fail_on: Level::Error,
+            fail_on_unchecked: false,
+            require_complete: false,
+            fail_on_unused_config: false,
+            fail_on_warnings: false,
+            exit_zero: false,
+            fail_on_severity: Severity::Low,
+            unknown_severity: Severity::Critical,
+        }
+    }
+}
+
+impl ExitPolicy {
+    /// The flag that fails the scan on unchecked actions, as the decision chain names it
+    fn unchecked_flag(&self) -> &'static str {
+        if self.require_complete && !self.fail_on_unchecked { "--require-complete" } else { "--fail-on-unchecked" }
+    }
+}
+
+/// Every exit status the scanner uses. Both the process exit and the `exit` object in reports are
+/// derived from one of these, so the two cannot disagree.
+///
+/// A failed scan says why: vulnerabilities alongside policy violations (3), vulnerabilities alone
+/// (1), an incomplete scan (10), such as one whose lookups failed, then policy violations alone
+/// (2), so CI can page on 1 and 3 and only block the merge on 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Pass,
+    Vulnerabilities,
+    PolicyViolations,
+    VulnerabilitiesAndPolicyViolations,
+    UnsupportedPorcelain,
+    ArtifactFailure,
+    UsageError,
+    OperationalError,
+}
+
+impl ExitCode {
+    pub const ALL: [ExitCode; 8] = [
+        ExitCode::Pass,
+        ExitCode::Vulnerabilities,
+        ExitCode::PolicyViolations,
+        ExitCode::VulnerabilitiesAndPolicyViolations,
+        ExitCode::UnsupportedPorcelain,
+        ExitCode::ArtifactFailure,
+        ExitCode::UsageError,
+        ExitCode::OperationalError,
+    ];
+
+    pub fn code(self) -> i32 {
+        match self {
+            ExitCode::Pass => 0,
+            ExitCode::Vulnerabilities => 1,
+            ExitCode::PolicyViolations => 2,
+            ExitCode::VulnerabilitiesAndPolicyViolations => 3,
+            ExitCode::ArtifactFailure => 4,
+            ExitCode::UsageError => 5,
+            ExitCode::UnsupportedPorcelain => 6,
+            ExitCode::OperationalError => 10,
+        }
+    }
+
+    /// Stable name for scripts to match on instead of the number
+    pub fn name(self) -> &'static str {
+        match self {
+            ExitCode::Pass => "pass",
+            ExitCode::Vulnerabilities => "vulnerabilities",
+            ExitCode::PolicyViolations => "policy-violations",
+            ExitCode::VulnerabilitiesAndPolicyViolations => "vulnerabilities-and-policy-violations",
+            ExitCode::UnsupportedPorcelain => "unsupported-porcelain",
+            ExitCode::ArtifactFailure => "artifact-failure",
+            ExitCode::UsageError => "usage-error",
+            ExitCode::OperationalError => "operational-error",
+        }
+    }
+
+    pub fn meaning(self) -> &'static str {
+        match self {
+            ExitCode::Pass => "no findings at or above the failure threshold and no opt-in failure condition",
+            ExitCode::Vulnerabilities => "blocking vulnerabilities and no blocking policy violations, whatever else was found",
+            ExitCode::PolicyViolations => {
+                "blocking policy violations such as insecure pinning, uses of blocked actions, or \
+                 --fail-on-unused-config or --fail-on-warnings, and no vulnerabilities; from `fix`, \
+                 findings remain that could not be fixed automatically"
+            }
+            ExitCode::VulnerabilitiesAndPolicyViolations => {
+                "blocking vulnerabilities and blocking policy violations such as insecure pinning or uses \
+                 of blocked actions, whatever else was found"
+            }
+            ExitCode::OperationalError => {
+                "the scan could not run, could not read a workflow, or had a lookup fail and could not \
+                 check an action; no vulnerabilities were found in what it did check"
+            }
+            ExitCode::UnsupportedPorcelain => "the --porcelain version asked for is not one this build can write; nothing was scanned",
+            ExitCode::UsageError => {
+                "the arguments were rejected or conflict, the config requires a newer scanner than this \
+                 one (minimum_scanner_version), or --stdin-filelist got no paths or a path that does not \
+                 exist; nothing was scanned"
+            }
+            ExitCode::ArtifactFailure => {
+                "the findings pass, but an artifact requested with --output-dir could not be written; \
+                 a failed scan keeps its own code regardless"
+            }
+        }
+    }
+
+    pub fn from_code(code: i32) -> Option<ExitCode> {
+        ExitCode::ALL.into_iter().find(|exit| exit.code() == code)
+    }
+
+    pub fn status(self, reason: String) -> ExitStatus {
+        ExitStatus {
+            code: self.code(),
+            name: self.name(),
+            reason,
+            process_code: None,
+        }
+    }
+}
+
+/// How a run ended, for the final banner, the `outcome` of reports and check-run conclusions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// Passed without a single warning or error
+    Clean,
+    /// Passed, but with findings that did not block it
+    PassedWithWarnings,
+    Failed,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Clean => "clean",
+            Outcome::PassedWithWarnings => "passed-with-warnings",
+            Outcome::Failed => "failed",
+        }
+    }
+
+    /// The GitHub check-run conclusion for the outcome
+    pub fn conclusion(self) -> &'static str {
+        match self {
+            Outcome::Clean => "success",
+            Outcome::PassedWithWarnings => "neutral",
+            Outcome::Failed => "failure",
+        }
+    }
+}
+
+/// The `exit` object of reports: what the process exits with, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitStatus {
+    pub code: i32,
+    pub name: &'static str,
+    pub reason: String,
+    /// What the process exits with instead of `code`, under `--exit-zero`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process_code: Option<i32>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct LevelCounts {
+    pub error: usize,
+    pub warning: usize,
+    pub note: usize,
+}
+
+impl LevelCounts {
+    pub fn add(&mut self, level: Level) {
+        match level {
+            Level::Error => self.error += 1,
+            Level::Warning => self.warning += 1,
+            Level::Note => self.note += 1,
+        }
+    }
+}
+
+/// Pass/fail verdict for a single workflow file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileVerdict {
+    pub file: String,
+    pub passed: bool,
+    /// Most serious level among the file's findings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worst: Option<Level>,
+    pub counts: LevelCounts,
+}
+
+/// The full chain of reasoning behind the exit status of a run
+#[derive(Debug, Clone, Serialize)]
+pub struct Decision {
+    pub counts: LevelCounts,
+    pub policy: ExitPolicy,
+    pub blocking_vulnerabilities: usize,
+    pub blocking_policy_violations: usize,
+    /// Uses of actions the config bans, which block at any level
+    pub blocked_dependencies: usize,
+    /// Workflow files that could not be read or parsed
+    pub invalid_workflows: usize,
+    /// Lookups that failed, which fail the scan whatever the thresholds
+    pub errors: Vec<String>,
+    pub unchecked: Vec<String>,
+    pub unchecked_applied: bool,
+    /// Checks `--offline` skipped, which `--require-complete` fails on
+    pub skipped_checks: Vec<String>,
+    pub skipped_applied: bool,
+    /// Config entries that matched no action
+    pub unused_config: usize,
+    pub unused_config_applied: bool,
+    /// Warnings and errors that did not block the run
+    pub warnings: usize,
+    /// Advisories that did not block because their severity is below `--fail-on-severity`
+    pub below_severity: usize,
+    /// Findings the ignore file suppresses, which neither block nor count as warnings
+    pub suppressed: usize,
+    /// Findings the `--baseline` covers, which do not block either
+    pub baselined: usize,
+    /// Findings about entries unchanged since the `--diff` or `--diff-base` base, not blocking
+    pub unchanged: usize,
+    pub warnings_applied: bool,
+    pub exit: ExitStatus,
+    pub outcome: Outcome,
+    /// Per-workflow verdicts against the same thresholds
+    pub files: Vec<FileVerdict>,
+}
+
+/// Whether a finding counts against the exit status under a policy
+pub fn is_blocking(finding: &Finding, policy: &ExitPolicy) -> bool {
+    match finding.rule.category() {
+        Category::Blocked => true,
+        Category::Informational => false,
+        _ => finding.level >= policy.fail_on && !finding.is_suppressed(),
+    }
+}
+
+/// Verdict per workflow file. A file owns the findings located in it, plus per-action findings
+/// (advisories, vendoring) for the actions it uses; scan-level findings belong to no file.
+fn file_verdicts(report: &ScanReport, policy: &ExitPolicy) -> Vec<FileVerdict> {
+    report
+        .workflow_actions
+        .iter()
+        .map(|(file, actions)| {
+            let mut counts = LevelCounts::default();
+            let mut worst = None;
+            let mut passed = true;
+            let owned = report.findings.iter().filter(|f| match &f.file {
+                Some(location) => location == file,
+                None => !f.action.is_empty() && actions.contains(&f.action),
+            });
+            for finding in owned {
+                counts.add(finding.level);
+                worst = worst.max(Some(finding.level));
+                passed &= !is_blocking(finding, policy);
+            }
+            FileVerdict {
+                file: file.clone(),
+                passed,
+                worst,
+                counts,
+            }
+        })
+        .collect()
+}
+
+/// Decide the exit status for a report. This is the only place exit codes are computed.
+pub fn decide(report: &ScanReport, policy: &ExitPolicy) -> Decision {
+    let mut counts = LevelCounts::default();
+    let mut blocking_vulnerabilities = 0;
+    let mut blocking_policy_violations = 0;
+    let mut blocked_dependencies = 0;
+    let mut invalid_workflows = 0;
+    let mut warnings = 0;
+    let mut suppressed = 0;
+    let mut baselined = 0;
+    let mut unchanged = 0;
+
+    for finding in &report.findings {
+        counts.add(finding.level);
+        if finding.is_suppressed() && finding.suppression.as_ref().is_some_and(|s| s.baseline) {
+            baselined += 1;
+        } else if finding.is_suppressed() && finding.suppression.as_ref().is_some_and(|s| s.unchanged) {
+            unchanged += 1;
+        } else if finding.is_suppressed() {
+            suppressed += 1;
+        } else if !is_blocking(finding, policy) {
+            warnings += usize::from(finding.level >= Level::Warning);
+        } else {
+            match finding.rule.category() {
+                _ if finding.rule == Rule::InvalidWorkflow => invalid_workflows += 1,
+                Category::Vulnerability => blocking_vulnerabilities += 1,
+                Category::Policy => blocking_policy_violations += 1,
+                Category::Blocked => blocked_dependencies += 1,
+                Category::Informational => {}
+            }
+        }
+    }
+
+    let unchecked_applied = (policy.fail_on_unchecked || policy.require_complete) && !report.unchecked.is_empty();
+    let skipped_applied = policy.require_complete && !report.skipped_checks.is_empty();
+    let unused_config = report.findings.iter().filter(|f| f.rule == Rule::UnusedConfigEntry).count();
+    let unused_config_applied = policy.fail_on_unused_config && unused_config > 0;
+
+    let warnings_applied = policy.fail_on_warnings && warnings > 0;
+    let below_severity = report
+        .findings
+        .iter()
+        .filter(|f| f.rule == Rule::KnownVulnerability && f.severity.unwrap_or(policy.unknown_severity) < policy.fail_on_severity)
+        .count();
+
+    let mut causes = Vec::new();
+    if blocking_vulnerabilities > 0 {
+        causes.push(plural(blocking_vulnerabilities, "vulnerability", "vulnerabilities"));
+    }
+    if blocking_policy_violations > 0 {
+        causes.push(plural(blocking_policy_violations, "policy violation", "policy violations"));
+    }
+    if invalid_workflows > 0 {
+        causes.push(plural(invalid_workflows, "unreadable workflow", "unreadable workflows"));
+    }
+
+    // Failure causes that are not findings over the level threshold
+    let mut extra = Vec::new();
+    if !report.errors.is_empty() {
+        extra.push(plural(report.errors.len(), "lookup error", "lookup errors"));
+    }
+    if blocked_dependencies > 0 {
+        extra.push(format!(
+            "{} (blocked_actions, blocked_owners)",
+            plural(blocked_dependencies, "blocked dependency", "blocked dependencies")
+        ));
+    }
+    if unchecked_applied {
+        extra.push(format!(
+            "{} ({})",
+            plural(report.unchecked.len(), "unchecked action", "unchecked actions"),
+            policy.unchecked_flag()
+        ));
+    }
+    if skipped_applied {
+        extra.push(format!(
+            "{} (--offline, --require-complete)",
+            plural(report.skipped_checks.len(), "skipped check", "skipped checks")
+        ));
+    }
+    if unused_config_applied {
+        extra.push(format!(
+            "{} (--fail-on-unused-config)",
+            plural(unused_config, "unused config entry", "unused config entries")
+        ));
+    }
+    if warnings_applied {
+        extra.push(format!("{} (--fail-on-warnings)", plural(warnings, "warning", "warnings")));
+    }
+
+    let code = if blocking_vulnerabilities > 0 && (blocking_policy_violations > 0 || blocked_dependencies > 0) {
+        ExitCode::VulnerabilitiesAndPolicyViolations
+    } else if blocking_vulnerabilities > 0 {
+        ExitCode::Vulnerabilities
+    } else if invalid_workflows > 0 || !report.errors.is_empty() || unchecked_applied || skipped_applied {
+        ExitCode::OperationalError
+    } else {
+        ExitCode::PolicyViolations
+    };
+    let mut exit = if !causes.is_empty() {
+        let mut reason = format!("{} {} {} threshold", causes.join(" and "), render::at_least(), policy.fail_on);
+        for cause in &extra {
+            reason.push_str(&format!(", {}", cause));
+        }
+        code.status(reason)
+    } else if !extra.is_empty() {
+        code.status(extra.join(", "))
+    } else {
+        ExitCode::Pass.status(format!("no findings {} {} threshold", render::at_least(), policy.fail_on))
+    };
+    if policy.exit_zero {
+        exit.process_code = Some(0);
+    }
+    let outcome = match (exit.code, warnings) {
+        (0, 0) => Outcome::Clean,
+        (0, _) => Outcome::PassedWithWarnings,
+        _ => Outcome::Failed,
+    };
+
+    Decision {
+        counts,
+        policy: policy.clone(),
+        blocking_vulnerabilities,
+        blocking_policy_violations,
+        blocked_dependencies,
+        invalid_workflows,
+        errors: report.errors.clone(),
+        unchecked: report.unchecked.clone(),
+        unchecked_applied,
+        skipped_checks: report.skipped_checks.clone(),
+        skipped_applied,
+        unused_config,
+        unused_config_applied,
+        warnings,
+        warnings_applied,
+        below_severity,
+        suppressed,
+        baselined,
+        unchanged,
+        exit,
+        outcome,
+        files: file_verdicts(report, policy),
+    }
+}
+
+impl Decision {
+    /// Print each workflow file's verdict under its own header line
+    pub fn print_files(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.files.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "\nWorkflow verdicts:")?;
+        for verdict in &self.files {
+            let marker = if verdict.passed { Marker::Ok } else { Marker::Fail };
+            writeln!(
+                out,
+                "{} {}: {} ({} error, {} warning, {} note)",
+                marker,
+                verdict.file,
+                if verdict.passed { "pass" } else { "fail" },
+                verdict.counts.error,
+                verdict.counts.warning,
+                verdict.counts.note
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Print the decision chain so a run's exit status can be explained after the fact
+    pub fn print(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\nDecision chain:")?;
+        writeln!(
+            out,
+            "- findings: {} error, {} warning, {} note",
+            self.counts.error, self.counts.warning, self.counts.note
+        )?;
+        writeln!(out, "- threshold: fail on {} and above", self.policy.fail_on)?;
+        if !self.errors.is_empty() {
+            writeln!(out, "- lookup errors: {}, failing the scan", self.errors.len())?;
+        }
+        if self.unchecked.is_empty() {
+            writeln!(out, "- unchecked actions: none")?;
+        } else {
+            writeln!(
+                out,
+                "- unchecked actions: {} ({}), {} {}",
+                self.unchecked.len(),
+                self.unchecked.join(", "),
+                self.policy.unchecked_flag(),
+                if self.policy.fail_on_unchecked || self.policy.require_complete { "applied" } else { "not set" }
+            )?;
+        }
+        if !self.skipped_checks.is_empty() {
+            writeln!(
+                out,
+                "- skipped checks: {} (--offline), --require-complete {}",
+                self.skipped_checks.len(),
+                if self.policy.require_complete { "applied" } else { "not set" }
+            )?;
+        }
+        if self.unused_config > 0 {
+            writeln!(
+                out,
+                "- unused config entries: {}, --fail-on-unused-config {}",
+                self.unused_config,
+                if self.policy.fail_on_unused_config { "applied" } else { "not set" }
+            )?;
+        }
+        if self.warnings > 0 {
+            writeln!(
+                out,
+                "- non-blocking warnings: {}, --fail-on-warnings {}",
+                self.warnings,
+                if self.policy.fail_on_warnings { "applied" } else { "not set" }
+            )?;
+        }
+        if self.policy.fail_on_severity > Severity::Low {
+            writeln!(
+                out,
+                "- advisories below --fail-on-severity {}: {}, not blocking",
+                self.policy.fail_on_severity, self.below_severity
+            )?;
+        }
+        if self.suppressed > 0 {
+            writeln!(out, "- suppressed by the ignore file: {}, not blocking", self.suppressed)?;
+        }
+        if self.baselined > 0 {
+            writeln!(out, "- in the baseline: {}, not blocking", self.baselined)?;
+        }
+        if self.unchanged > 0 {
+            writeln!(out, "- unchanged since the --diff base: {}, not blocking", self.unchanged)?;
+        }
+        writeln!(out, "- outcome: {} (check-run conclusion {})", self.outcome.name(), self.outcome.conclusion())?;
+        writeln!(out, "- exit {} ({}): {}", self.exit.code, self.exit.name, self.exit.reason)?;
+        if let Some(code) = self.exit.process_code {
+            writeln!(out, "- exit {} instead: --exit-zero", code)?;
+        }
+        Ok(())
+    }
+
+    /// The code the process exits with, which `--exit-zero` overrides
+    pub fn process_code(&self) -> i32 {
+        self.exit.process_code.unwrap_or(self.exit.code)
+    }
+
+    /// The last line of a run that was not failed. Unchecked actions are named there, so a green
+    /// run never reads as if it had covered them.
+    pub fn print_banner(&self, out: &mut dyn Write) -> io::Result<()> {
+        if !self.skipped_checks.is_empty() {
+            writeln!(out, "\n{} No local check failed; network checks were skipped (--offline)", Marker::Warn)?;
+            return Ok(());
+        }
+        if !self.unchecked.is_empty() {
+            writeln!(
+                out,
+                "\n{} No checked action failed, but {} could not be checked",
+                Marker::Warn,
+                plural(self.unchecked.len(), "action", "actions")
+            )?;
+            return Ok(());
+        }
+        match self.outcome {
+            Outcome::PassedWithWarnings => writeln!(
+                out,
+                "\n{} All actions passed security checks, with {}",
+                Marker::Warn,
+                plural(self.warnings, "warning", "warnings")
+            )?,
+            _ => writeln!(out, "\n{} All actions passed security checks!", Marker::Ok)?,
+        }
+        Ok(())
+    }
+}
+
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Suppression;
+
+    fn finding(rule: Rule, level: Level) -> Finding {
+        let mut finding = Finding::new(rule, level, "acme/deploy@v1", format!("{} finding", rule.id()));
+        finding.file = Some(".github/workflows/ci.yml".to_string());
+        finding
+    }
+
+    fn vulnerability() -> Finding {
+        finding(Rule::KnownVulnerability, Level::Error)
+    }
+
+    fn violation() -> Finding {
+        finding(Rule::UntrustedOwnerNoSha, Level::Error)
+    }
+
+    fn warning() -> Finding {
+        finding(Rule::UntrustedOwner, Level::Warning)
+    }
+
+    /// The finding accepted by the ignore file, the baseline or the `--diff` base
+    fn accepted(mut finding: Finding, baseline: bool, unchanged: bool, expired: bool) -> Finding {
+        finding.suppression = Some(Suppression { reason: "accepted".to_string(), expires: None, expired, baseline, unchanged });
+        finding
+    }
+
+    fn report(findings: Vec<Finding>) -> ScanReport {
+        ScanReport { findings, ..Default::default() }
+    }
+
+    fn unchecked(report: ScanReport) -> ScanReport {
+        ScanReport { unchecked: vec!["acme/down@v1".to_string()], ..report }
+    }
+
+    fn skipped(report: ScanReport) -> ScanReport {
+        ScanReport { skipped_checks: vec!["GitHub and OSV advisory lookups".to_string()], ..report }
+    }
+
+    fn failed_lookup(report: ScanReport) -> ScanReport {
+        ScanReport { errors: vec!["acme/down@v1: advisory database unreachable".to_string()], ..report }
+    }
+
+    #[test]
+    fn exit_codes_over_finding_sets_and_flags() {
+        let default = ExitPolicy::default();
+        let fail_on_warning = ExitPolicy { fail_on: Level::Warning, ..Default::default() };
+        let fail_on_warnings = ExitPolicy { fail_on_warnings: true, ..Default::default() };
+        let fail_on_unchecked = ExitPolicy { fail_on_unchecked: true, ..Default::default() };
+        let require_complete = ExitPolicy { require_complete: true, ..Default::default() };
+        let fail_on_unused = ExitPolicy { fail_on_unused_config: true, ..Default::default() };
+        let (clean, warned, failed) = (Outcome::Clean, Outcome::PassedWithWarnings, Outcome::Failed);
+
+        let matrix: Vec<(&str, ScanReport, &ExitPolicy, i32, Outcome)> = vec![
+            ("nothing found", report(vec![]), &default, 0, clean),
+            ("a warning", report(vec![warning()]), &default, 0, warned),
+            ("a warning at --fail-on warning", report(vec![warning()]), &fail_on_warning, 2, failed),
+            ("a warning with --fail-on-warnings", report(vec![warning()]), &fail_on_warnings, 2, failed),
+            ("a note at --fail-on warning", report(vec![finding(Rule::UntrustedOwner, Level::Note)]), &fail_on_warning, 0, clean),
+            ("a policy violation", report(vec![violation()]), &default, 2, failed),
+            ("a vulnerability", report(vec![vulnerability()]), &default, 1, failed),
+            ("a vulnerability and a violation", report(vec![vulnerability(), violation()]), &default, 3, failed),
+            ("a vulnerability and a blocked dependency", report(vec![vulnerability(), finding(Rule::BlockedDependency, Level::Error)]), &default, 3, failed),
+            ("a vulnerability and a warning with --fail-on-warnings", report(vec![vulnerability(), warning()]), &fail_on_warnings, 1, failed),
+            ("a vulnerability, a violation and a failed lookup", failed_lookup(report(vec![vulnerability(), violation()])), &default, 3, failed),
+            ("a violation and an unreadable workflow", report(vec![violation(), finding(Rule::InvalidWorkflow, Level::Error)]), &default, 10, failed),
+            ("a vulnerability and an unreadable workflow", report(vec![vulnerability(), finding(Rule::InvalidWorkflow, Level::Error)]), &default, 1, failed),
+            ("a blocked dependency at warning", report(vec![finding(Rule::BlockedDependency, Level::Warning)]), &default, 2, failed),
+            ("an ignored violation", report(vec![accepted(violation(), false, false, false)]), &default, 0, clean),
+            ("a baselined vulnerability", report(vec![accepted(vulnerability(), true, false, false)]), &default, 0, clean),
+            ("an unchanged violation", report(vec![accepted(violation(), false, true, false)]), &default, 0, clean),
+            ("an expired suppression", report(vec![accepted(violation(), false, false, true)]), &default, 2, failed),
+            ("an ignored blocked dependency", report(vec![accepted(finding(Rule::BlockedDependency, Level::Error), false, false, false)]), &default, 0, clean),
+            ("an unchecked action", unchecked(report(vec![])), &default, 0, clean),
+            ("an unchecked action with --fail-on-unchecked", unchecked(report(vec![])), &fail_on_unchecked, 10, failed),
+            ("an unchecked action with --require-complete", unchecked(report(vec![])), &require_complete, 10, failed),
+            ("an unchecked action and a vulnerability", unchecked(report(vec![vulnerability()])), &fail_on_unchecked, 1, failed),
+            ("an unchecked action and a violation", unchecked(report(vec![violation()])), &fail_on_unchecked, 10, failed),
+            ("a skipped check", skipped(report(vec![])), &default, 0, clean),
+            ("a skipped check with --fail-on-unchecked", skipped(report(vec![])), &fail_on_unchecked, 0, clean),
+            ("a skipped check with --require-complete", skipped(report(vec![])), &require_complete, 10, failed),
+            ("a failed lookup", failed_lookup(report(vec![])), &default, 10, failed),
+            ("a failed lookup and a vulnerability", failed_lookup(report(vec![vulnerability()])), &default, 1, failed),
+            ("an unused config entry", report(vec![finding(Rule::UnusedConfigEntry, Level::Note)]), &default, 0, clean),
+            ("an unused config entry at --fail-on warning", report(vec![finding(Rule::UnusedConfigEntry, Level::Warning)]), &fail_on_warning, 0, warned),
+            ("an unused config entry with --fail-on-unused-config", report(vec![finding(Rule::UnusedConfigEntry, Level::Note)]), &fail_on_unused, 2, failed),
+        ];
+        for (case, report, policy, code, outcome) in matrix {
+            let decision = decide(&report, policy);
+            assert_eq!(decision.exit.code, code, "{}: {}", case, decision.exit.reason);
+            assert_eq!(decision.exit.name, ExitCode::from_code(code).unwrap().name(), "{}", case);
+            assert_eq!(decision.outcome, outcome, "{}: {}", case, decision.exit.reason);
+            assert_eq!(decision.process_code(), code, "{}", case);
+        }
+    }
+
+    #[test]
+    fn exit_zero_keeps_the_code_it_would_have_been() {
+        let policy = ExitPolicy { exit_zero: true, ..Default::default() };
+        let decision = decide(&report(vec![vulnerability()]), &policy);
+        assert_eq!((decision.exit.code, decision.process_code()), (1, 0));
+        assert_eq!(decision.outcome, Outcome::Failed);
+        let decision = decide(&report(vec![]), &policy);
+        assert_eq!((decision.exit.code, decision.process_code()), (0, 0));
+    }
+
+    #[test]
+    fn accepted_findings_are_counted_apart() {
+        let findings = vec![
+            accepted(violation(), false, false, false),
+            accepted(violation(), true, false, false),
+            accepted(vulnerability(), true, false, false),
+            accepted(warning(), false, true, false),
+            warning(),
+            finding(Rule::UntrustedOwner, Level::Note),
+        ];
+        let decision = decide(&report(findings), &ExitPolicy::default());
+        assert_eq!((decision.suppressed, decision.baselined, decision.unchanged), (1, 2, 1));
+        assert_eq!(decision.warnings, 1, "only the unaccepted warning counts; notes never do");
+        assert_eq!((decision.counts.error, decision.counts.warning, decision.counts.note), (3, 2, 1));
+        assert_eq!(decision.blocking_vulnerabilities + decision.blocking_policy_violations, 0);
+    }
+
+    #[test]
+    fn the_reason_names_every_cause() {
+        let at_least = render::at_least();
+        let decision = decide(&unchecked(report(vec![violation(), violation()])), &ExitPolicy { fail_on_unchecked: true, ..Default::default() });
+        assert_eq!(decision.exit.reason, format!("2 policy violations {} error threshold, 1 unchecked action (--fail-on-unchecked)", at_least));
+
+        let decision = decide(&unchecked(report(vec![])), &ExitPolicy { require_complete: true, ..Default::default() });
+        assert_eq!(decision.exit.reason, "1 unchecked action (--require-complete)");
+
+        let policy = ExitPolicy { fail_on_warnings: true, ..Default::default() };
+        let decision = decide(&failed_lookup(report(vec![warning(), finding(Rule::BlockedDependency, Level::Error)])), &policy);
+        assert_eq!(
+            decision.exit.reason,
+            "1 lookup error, 1 blocked dependency (blocked_actions, blocked_owners), 1 warning (--fail-on-warnings)"
+        );
+
+        let decision = decide(&report(vec![warning()]), &ExitPolicy::default());
+        assert_eq!(decision.exit.reason, format!("no findings {} error threshold", at_least));
+    }
+
+    #[test]
+    fn the_chain_ends_with_the_exit_and_its_reason() {
+        let policy = ExitPolicy { exit_zero: true, fail_on_unchecked: true, ..Default::default() };
+        let decision = decide(&unchecked(report(vec![vulnerability(), accepted(violation(), true, false, false)])), &policy);
+        let mut out = Vec::new();
+        decision.print(&mut out).unwrap();
+        let chain = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = chain.lines().collect();
+        assert!(lines.contains(&"- findings: 2 error, 0 warning, 0 note"), "{}", chain);
+        assert!(lines.contains(&"- unchecked actions: 1 (acme/down@v1), --fail-on-unchecked applied"), "{}", chain);
+        assert!(lines.contains(&"- in the baseline: 1, not blocking"), "{}", chain);
+        let reason = format!("- exit 1 (vulnerabilities): {}", decision.exit.reason);
+        assert_eq!(lines[lines.len() - 2..], [reason.as_str(), "- exit 0 instead: --exit-zero"], "{}", chain);
+    }
+
+    #[test]
+    fn every_exit_code_has_one_number_and_name() {
+        for exit in ExitCode::ALL {
+            assert_eq!(ExitCode::from_code(exit.code()), Some(exit));
+            let status = exit.status("because".to_string());
+            assert_eq!((status.code, status.name, status.process_code), (exit.code(), exit.name(), None));
+            assert!(!exit.meaning().is_empty(), "{}", exit.name());
+            let same_code = ExitCode::ALL.iter().filter(|other| other.code() == exit.code()).count();
+            let same_name = ExitCode::ALL.iter().filter(|other| other.name() == exit.name()).count();
+            assert_eq!((same_code, same_name), (1, 1), "{}", exit.name());
+        }
+        assert_eq!(ExitCode::from_code(7), None);
+    }
+
+    #[test]
+    fn the_exit_object_is_the_process_code_and_its_name() {
+        let exit_zero = ExitPolicy { exit_zero: true, ..Default::default() };
+        for (report, policy) in [
+            (report(vec![]), &ExitPolicy::default()),
+            (report(vec![vulnerability()]), &ExitPolicy::default()),
+            (report(vec![violation()]), &ExitPolicy::default()),
+            (failed_lookup(report(vec![])), &ExitPolicy::default()),
+            (report(vec![vulnerability()]), &exit_zero),
+        ] {
+            let decision = decide(&report, policy);
+            let code = ExitCode::from_code(decision.exit.code).unwrap();
+            let object = serde_json::to_value(&decision.exit).unwrap();
+            assert_eq!((object["code"].as_i64(), object["name"].as_str()), (Some(code.code() as i64), Some(code.name())));
+            assert_eq!(object["reason"], decision.exit.reason);
+            let process = object.get("process_code").and_then(|c| c.as_i64()).unwrap_or(code.code() as i64);
+            assert_eq!(process, decision.process_code() as i64, "{}", object);
+        }
+    }
+
+    fn banner(report: ScanReport, policy: &ExitPolicy) -> String {
+        let mut out = Vec::new();
+        decide(&report, policy).print_banner(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn each_passing_state_has_its_own_banner() {
+        let policy = ExitPolicy::default();
+        assert_eq!(banner(report(vec![]), &policy), format!("\n{} All actions passed security checks!\n", Marker::Ok));
+        assert_eq!(
+            banner(report(vec![finding(Rule::UntrustedOwner, Level::Note)]), &policy),
+            format!("\n{} All actions passed security checks!\n", Marker::Ok),
+            "notes do not make a warning"
+        );
+        assert_eq!(banner(report(vec![warning()]), &policy), format!("\n{} All actions passed security checks, with 1 warning\n", Marker::Warn));
+        assert_eq!(
+            banner(report(vec![warning(), warning()]), &policy),
+            format!("\n{} All actions passed security checks, with 2 warnings\n", Marker::Warn)
+        );
+        assert_eq!(
+            banner(skipped(report(vec![warning()])), &policy),
+            format!("\n{} No local check failed; network checks were skipped (--offline)\n", Marker::Warn)
+        );
+        assert_eq!(
+            banner(unchecked(report(vec![])), &policy),
+            format!("\n{} No checked action failed, but 1 action could not be checked\n", Marker::Warn)
+        );
+    }
+
+    #[test]
+    fn outcomes_map_to_check_run_conclusions() {
+        let conclusions: Vec<(&str, &str)> = [Outcome::Clean, Outcome::PassedWithWarnings, Outcome::Failed].iter().map(|o| (o.name(), o.conclusion())).collect();
+        assert_eq!(conclusions, [("clean", "success"), ("passed-with-warnings", "neutral"), ("failed", "failure")]);
+        assert_eq!(serde_json::to_value(Outcome::PassedWithWarnings).unwrap(), "passed-with-warnings");
+    }
+}
@@ -0,0 +1,250 @@
+use crate::report::{Finding, Level, Rule};
+use crate::rules::uses_value;
+use crate::{Config, ScanError, Workflow, is_unstable_ref_name};
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Component, Path};
+
+/// Where GitHub looks for the file, relative to the repository root, in the order it looks
+const LOCATIONS: [&str; 2] = [".github/dependabot.yml", ".github/dependabot.yaml"];
+
+/// The ecosystem whose updates bump `uses:` pins
+const ECOSYSTEM: &str = "github-actions";
+
+/// The `directory` and `directories` of the file's github-actions updates, each without its
+/// leading and trailing `/`, so the repository root is the empty string
+struct Coverage {
+    path: String,
+    directories: Vec<glob::Pattern>,
+}
+
+impl Coverage {
+    fn load(path: &Path) -> Result<Self, ScanError> {
+        let shown = path.display().to_string();
+        let invalid = |e: String| ScanError::config(&shown, e);
+        let source = fs::read_to_string(path).map_err(|e| ScanError::io(&shown, e))?;
+        let document: Value = serde_yaml::from_str(&source).map_err(|e| ScanError::yaml(&shown, &e))?;
+        let updates = match document.get("updates") {
+            Some(Value::Sequence(updates)) => updates.as_slice(),
+            None | Some(Value::Null) => &[],
+            Some(_) => return Err(invalid("updates is not a list".to_string())),
+        };
+        let mut directories = Vec::new();
+        for update in updates.iter().filter(|u| u.get("package-ecosystem").and_then(Value::as_str) == Some(ECOSYSTEM)) {
+            let listed = update.get("directories").and_then(Value::as_sequence).map(Vec::as_slice).unwrap_or_default();
+            for directory in update.get("directory").into_iter().chain(listed).filter_map(Value::as_str) {
+                let pattern = glob::Pattern::new(&normalize(directory))
+                    .map_err(|e| invalid(format!("github-actions directory {} is not a valid glob: {}", directory, e)))?;
+                directories.push(pattern);
+            }
+        }
+        Ok(Coverage { path: display(path), directories })
+    }
+
+    fn covers(&self, directory: &str) -> bool {
+        self.directories.iter().any(|pattern| pattern.matches(directory))
+    }
+}
+
+/// A path as the user would type it, without the `./` of the default root
+fn display(path: &Path) -> String {
+    path.strip_prefix(".").unwrap_or(path).display().to_string()
+}
+
+/// A `directory` as Dependabot reads it: `/` is the root, whose `.github/workflows` it scans, and
+/// a directory naming `.github/workflows` itself means the same as its parent
+fn normalize(directory: &str) -> String {
+    let directory = directory.trim().trim_matches('/');
+    let directory = directory.strip_suffix(".github/workflows").unwrap_or(directory);
+    directory.trim_end_matches('/').to_string()
+}
+
+/// The directory whose `.github/workflows` holds the workflow, relative to the repository root,
+/// as a Dependabot `directory` names it; `None` for a file outside any `.github/workflows`
+fn workflow_directory(root: &Path, file: &str) -> Option<String> {
+    let path = Path::new(file);
+    let relative = match (fs::canonicalize(path), fs::canonicalize(root)) {
+        (Ok(path), Ok(root)) => path.strip_prefix(&root).map(Path::to_path_buf).unwrap_or(path),
+        _ => path.to_path_buf(),
+    };
+    let parts: Vec<&str> = relative
+        .parent()?
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    let (directory, workflows) = parts.split_at(parts.len().checked_sub(2)?);
+    (workflows == [".github", "workflows"]).then(|| directory.join("/"))
+}
+
+/// Actions Dependabot would bump: pinned to a commit SHA or a tag, and not local, `docker://` or
+/// on a branch as `unstable_ref_names` guesses them
+fn pinned(action: &str, config: &Config) -> bool {
+    action.contains('@') && !action.starts_with("./") && !action.starts_with("docker://") && !is_unstable_ref_name(action, config)
+}
+
+fn use_line(source: &str, action: &str) -> Option<usize> {
+    source.lines().position(|line| uses_value(line) == Some(action)).map(|index| index + 1)
+}
+
+/// `--check-dependabot`: flag SHA- and tag-pinned actions of workflows that no github-actions
+/// entry of the repository's dependabot.yml covers, since nothing would update their pins. The
+/// file is `path` when given, else GitHub's own location under `root`; without it, one
+/// scan-level finding covers every pin. Findings warn, or fail with `strict`. Reads only local
+/// files.
+pub fn check(
+    workflows: &[&Workflow],
+    root: &Path,
+    path: Option<&Path>,
+    config: &Config,
+    strict: bool,
+) -> Result<Vec<Finding>, ScanError> {
+    let level = if strict { Level::Error } else { Level::Warning };
+    let found = match path {
+        Some(path) if !path.exists() => {
+            return Err(ScanError::Options(format!("--dependabot-config {} does not exist", path.display())));
+        }
+        Some(path) => Some(path.to_path_buf()),
+        None => LOCATIONS.iter().map(|location| root.join(location)).find(|p| p.exists()),
+    };
+    let pins = workflows.iter().flat_map(|w| w.actions().into_iter().filter(|a| pinned(a, config)).map(move |a| (*w, a)));
+
+    let Some(found) = found else {
+        let mut actions: Vec<String> = pins.map(|(_, action)| action).collect();
+        actions.sort();
+        actions.dedup();
+        let Some(first) = actions.first() else { return Ok(Vec::new()) };
+        let others = match actions.len() - 1 {
+            0 => String::new(),
+            1 => " and 1 other pinned action".to_string(),
+            n => format!(" and {} other pinned actions", n),
+        };
+        let message = format!(
+            "The workflows pin {}{}, but {} does not exist, so nothing updates the pins; add an updates entry with package-ecosystem: {}",
+            first,
+            others,
+            display(&root.join(LOCATIONS[0])),
+            ECOSYSTEM
+        );
+        return Ok(vec![Finding::new(Rule::DependabotCoverage, level, "", message)]);
+    };
+
+    let coverage = Coverage::load(&found)?;
+    let mut findings = Vec::new();
+    for (workflow, action) in pins {
+        let reason = match workflow_directory(root, &workflow.path) {
+            Some(directory) if coverage.covers(&directory) => continue,
+            Some(directory) => format!(
+                "no {} updates entry in {} covers directory /{}",
+                ECOSYSTEM,
+                coverage.path,
+                directory
+            ),
+            None => "the file is not under a .github/workflows directory, where Dependabot looks".to_string(),
+        };
+        let message = format!("Dependency {} is pinned, but {}, so nothing updates the pin", action, reason);
+        let line = use_line(&workflow.source, &action);
+        findings.push(Finding::new(Rule::DependabotCoverage, level, &action, message).at(&workflow.path, line));
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::Scratch;
+
+    const PINNED: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/tool@main\n      - uses: ./.github/actions/local\n";
+
+    /// A repository with `ci.yml` at the root's and at `services/api`'s `.github/workflows`, and
+    /// `dependabot` if given
+    fn repository(name: &str, dependabot: Option<&str>) -> (Scratch, Vec<Workflow>) {
+        let root = Scratch::new(&format!("dependabot-{}", name));
+        let mut workflows = Vec::new();
+        for dir in [".github/workflows", "services/api/.github/workflows"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            let path = root.join(dir).join("ci.yml");
+            fs::write(&path, PINNED).unwrap();
+            workflows.push(Workflow::parse(&path.to_string_lossy(), PINNED).unwrap());
+        }
+        if let Some(dependabot) = dependabot {
+            fs::write(root.join(".github/dependabot.yml"), dependabot).unwrap();
+        }
+        (root, workflows)
+    }
+
+    fn uncovered(root: &Path, workflows: &[Workflow], strict: bool) -> Vec<(String, Level)> {
+        let workflows: Vec<&Workflow> = workflows.iter().collect();
+        let findings = check(&workflows, root, None, &Config::default(), strict).unwrap();
+        findings.into_iter().map(|f| (f.message, f.level)).collect()
+    }
+
+    fn update(directories: &str) -> String {
+        format!("version: 2\nupdates:\n  - package-ecosystem: npm\n    directory: \"/services/api\"\n  - package-ecosystem: github-actions\n{}", directories)
+    }
+
+    #[test]
+    fn every_directory_an_entry_lists_is_covered() {
+        for directories in [
+            "    directories: [\"/\", \"/services/api\"]\n",
+            "    directories: [\"/\", \"/services/*\"]\n",
+            "    directory: \"/\"\n  - package-ecosystem: github-actions\n    directory: /services/api/.github/workflows\n",
+        ] {
+            let (root, workflows) = repository("covered", Some(&update(directories)));
+            assert_eq!(uncovered(&root, &workflows, false), [], "{}", directories);
+        }
+    }
+
+    #[test]
+    fn a_pin_outside_every_github_actions_entry_is_flagged_at_its_line() {
+        let (root, workflows) = repository("uncovered", Some(&update("    directory: \"/\"\n")));
+        let workflows: Vec<&Workflow> = workflows.iter().collect();
+        let findings = check(&workflows, &root, None, &Config::default(), false).unwrap();
+        // The npm entry covers services/api for npm only; the branch and local uses are not pins
+        assert_eq!(findings.len(), 1, "{:?}", findings);
+        assert_eq!(findings[0].action, "actions/checkout@v4");
+        assert_eq!(findings[0].line, Some(6));
+        let expected = format!(
+            "Dependency actions/checkout@v4 is pinned, but no github-actions updates entry in {} covers directory /services/api, \
+             so nothing updates the pin",
+            display(&root.join(".github/dependabot.yml"))
+        );
+        assert_eq!(findings[0].message, expected);
+    }
+
+    #[test]
+    fn without_the_file_one_finding_covers_every_pin() {
+        let (root, workflows) = repository("missing", None);
+        let found = uncovered(&root, &workflows, true);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert!(found[0].0.starts_with("The workflows pin actions/checkout@v4, but "), "{}", found[0].0);
+        assert!(found[0].0.ends_with("add an updates entry with package-ecosystem: github-actions"), "{}", found[0].0);
+        assert_eq!(found[0].1, Level::Error);
+    }
+
+    #[test]
+    fn a_named_config_must_exist_and_parse() {
+        let (root, workflows) = repository("named", None);
+        let workflows: Vec<&Workflow> = workflows.iter().collect();
+        let missing = check(&workflows, &root, Some(&root.join("nope.yml")), &Config::default(), false).unwrap_err();
+        assert!(missing.to_string().ends_with("nope.yml does not exist"), "{}", missing);
+        fs::write(root.join("bad.yml"), "updates: {}\n").unwrap();
+        let invalid = check(&workflows, &root, Some(&root.join("bad.yml")), &Config::default(), false).unwrap_err();
+        assert!(invalid.to_string().ends_with("updates is not a list"), "{}", invalid);
+    }
+
+    #[test]
+    fn directories_normalize_as_dependabot_reads_them() {
+        assert_eq!(normalize("/"), "");
+        assert_eq!(normalize("/.github/workflows"), "");
+        assert_eq!(normalize("services/api/"), "services/api");
+        assert_eq!(normalize("/services/api/.github/workflows/"), "services/api");
+        let config = Config::default();
+        assert!(pinned("actions/checkout@v4", &config) && pinned("actions/checkout@8ade135a41bc03ea155e62e844d188df1ea18608", &config));
+        for unpinned in ["actions/checkout@main", "acme/tool@releases/v1", "actions/checkout", "./local", "docker://alpine:3"] {
+            assert!(!pinned(unpinned, &config), "{}", unpinned);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::ActionRef;
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::report::ScanReport;
+use crate::{outdated, shas, unreleased};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// How many of the newest tags a commit on no tag is compared against to find its nearest one
+const TAGS_COMPARED: usize = 20;
+
+/// Where a SHA pin's version came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinVersion {
+    /// A tag of the commit, or the nearest tag it descends from with `+N` commits after it
+    Tag(String),
+    /// The version comment next to the pin, which nothing checked
+    Comment(String),
+    Unknown,
+}
+
+impl PinVersion {
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            PinVersion::Tag(version) | PinVersion::Comment(version) => Some(version),
+            PinVersion::Unknown => None,
+        }
+    }
+}
+
+/// `--describe-pins`: the version of every SHA pin in the scan, keyed by `uses:` value; empty
+/// without the flag. Shared, since every partial report of the scan carries it.
+#[derive(Debug, Clone, Default)]
+pub struct Pins(Arc<BTreeMap<String, PinVersion>>);
+
+impl Pins {
+    pub fn new(pins: BTreeMap<String, PinVersion>) -> Self {
+        Pins(Arc::new(pins))
+    }
+
+    /// A SHA pin as text output shows it, e.g. `actions/checkout@11bd719… (v4.2.2)`; anything
+    /// else, or any action without the flag, unchanged
+    pub fn display(&self, action: &str) -> String {
+        let Some(pin) = self.0.get(action) else {
+            return action.to_string();
+        };
+        let Some((name, sha)) = action.split_once('@') else {
+            return action.to_string();
+        };
+        let version = match pin {
+            PinVersion::Tag(version) => version.clone(),
+            PinVersion::Comment(version) => format!("{} per its comment", version),
+            PinVersion::Unknown => "unknown version".to_string(),
+        };
+        format!("{}@{} ({})", name, short_sha(sha), version)
+    }
+
+    /// Text with every SHA pin in it shown as [`Pins::display`] shows it
+    pub fn annotate(&self, text: &str) -> String {
+        self.0.keys().filter(|action| text.contains(action.as_str())).fold(text.to_string(), |text, action| {
+            text.replace(action.as_str(), &self.display(action))
+        })
+    }
+
+    /// The resolved version of a SHA pin, for the machine-readable outputs
+    pub fn version(&self, action: &str) -> Option<&str> {
+        self.0.get(action)?.version()
+    }
+}
+
+/// The exact tag of the commit, else the nearest tag it descends from as `git describe` writes it
+/// (`v4.2.2+3`), else `None`. Resolutions are cached like the lookups they are made from.
+pub async fn resolve(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<String>, std::io::Error> {
+    let key = format!("{}@{}", action_ref.full_name(), action_ref.version);
+    match cache.get("pin-versions", &key) {
+        Cached::Hit(value) => return Ok(value.as_str().map(str::to_string)),
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {}
+    }
+    let described = match outdated::tag_of_commit(api, cache, action_ref).await? {
+        Some(tag) => Some(tag),
+        None => nearest_tag(api, cache, action_ref).await?,
+    };
+    match &described {
+        Some(version) => cache.put("pin-versions", &key, &Value::from(version.as_str())),
+        None => cache.put_negative("pin-versions", &key),
+    }
+    Ok(described)
+}
+
+/// The newest-listed tag the commit is the fewest commits past
+async fn nearest_tag(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<String>, std::io::Error> {
+    let tags = shas::refs(api, cache, action_ref, "tags").await?;
+    let mut nearest: Option<(u64, &str)> = None;
+    for (tag, _) in tags.iter().take(TAGS_COMPARED) {
+        if let Some(ahead) = unreleased::compare(api, cache, action_ref, tag).await?.and_then(|c| c.ahead())
+            && nearest.is_none_or(|(best, _)| ahead < best)
+        {
+            nearest = Some((ahead, tag));
+        }
+    }
+    Ok(nearest.map(|(ahead, tag)| format!("{}+{}", tag, ahead)))
+}
+
+/// The commit as the display shows it: seven characters and an ellipsis
+fn short_sha(sha: &str) -> String {
+    format!("{}…", &sha[..sha.len().min(7)])
+}
+
+/// Record on each finding about a SHA pin the version it resolved to
+pub fn attach(report: &mut ScanReport) {
+    for finding in &mut report.findings {
+        finding.pin_version = report.pins.version(&finding.action).map(str::to_string);
+    }
+}
+
+/// The action's reference, when it is pinned to a commit and so `--describe-pins` resolves it
+pub fn sha_pin(action: &str) -> Option<ActionRef> {
+    ActionRef::from_action_string(action).filter(|action_ref| action_ref.is_commit_sha())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIN: &str = "actions/checkout@11bd71901bbe5b1630ceea73d27597364c9af683";
+
+    fn pins(version: PinVersion) -> Pins {
+        Pins::new(BTreeMap::from([(PIN.to_string(), version)]))
+    }
+
+    #[test]
+    fn a_pin_is_shown_with_where_its_version_came_from() {
+        assert_eq!(pins(PinVersion::Tag("v4.2.2".to_string())).display(PIN), "actions/checkout@11bd719… (v4.2.2)");
+        assert_eq!(pins(PinVersion::Comment("v4".to_string())).display(PIN), "actions/checkout@11bd719… (v4 per its comment)");
+        assert_eq!(pins(PinVersion::Unknown).display(PIN), "actions/checkout@11bd719… (unknown version)");
+        assert_eq!(pins(PinVersion::Unknown).display("actions/cache@v4"), "actions/cache@v4");
+        assert_eq!(Pins::default().display(PIN), PIN, "without --describe-pins");
+    }
+
+    #[test]
+    fn each_scan_keeps_its_own_pins() {
+        let (first, second) = (pins(PinVersion::Tag("v4.2.2".to_string())), pins(PinVersion::Tag("v4.2.1".to_string())));
+        assert_eq!(first.version(PIN), Some("v4.2.2"));
+        assert_eq!(second.version(PIN), Some("v4.2.1"));
+        let message = format!("{} has no version comment", PIN);
+        assert_eq!(second.annotate(&message), "actions/checkout@11bd719… (v4.2.1) has no version comment");
+    }
+}
@@ -0,0 +1,201 @@
+//! `--diff` and `--diff-base`: fail only on the `uses:` entries a change adds or modifies. Each
+//! scanned workflow is compared with its version in the base, an older copy of the workflows or a
+//! git revision; an action whose ref changed counts as changed, and one that only moved does not.
+//! Unchanged actions are not looked up, and findings about them are listed without failing.
+
+use crate::report::{Category, DiffScope, ScanReport, Suppression, canonical_action};
+use crate::{ScanError, Workflow, lenient};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What the scanned workflows are compared against
+#[derive(Debug, Clone)]
+pub enum Base {
+    /// `--diff`: an older workflow file, or a directory laid out as the scanned one
+    Path(PathBuf),
+    /// `--diff-base`: a revision of the git checkout the scan runs in
+    Revision(String),
+}
+
+impl Base {
+    fn describe(&self) -> String {
+        match self {
+            Base::Path(path) => path.display().to_string(),
+            Base::Revision(revision) => revision.clone(),
+        }
+    }
+}
+
+/// The scanned workflows next to their base versions
+pub struct Diff {
+    base: String,
+    /// The `uses:` values of each scanned file's base version, in canonical form; `None` for a
+    /// file the base lacks
+    previous: BTreeMap<String, Option<BTreeSet<String>>>,
+    /// Files whose source is the same as in the base
+    identical: BTreeSet<String>,
+    /// Workflow files the base has and the scan does not
+    deleted: Vec<String>,
+}
+
+fn git(args: &[&str]) -> Result<String, std::io::Error> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn is_workflow_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "yml" || extension == "yaml")
+}
+
+/// A path as `git show <revision>:<path>` takes it: relative to the working directory, which the
+/// `./` prefix tells git
+fn revision_path(path: &str) -> String {
+    let path = Path::new(path);
+    let relative = std::env::current_dir().ok().and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf));
+    format!("./{}", relative.as_deref().unwrap_or(path).display())
+}
+
+/// The `uses:` values of a workflow's source, with matrix references expanded, in canonical form
+/// so a change of case alone is no change. Lines are read as well as the parsed YAML, so a base
+/// that no longer parses still counts.
+fn uses(path: &str, source: &str) -> BTreeSet<String> {
+    let mut values: BTreeSet<String> = lenient::uses_lines(source).into_iter().map(|u| u.value).collect();
+    if let Ok(workflow) = Workflow::parse(path, source) {
+        values.extend(crate::extract_actions(&workflow.value));
+        values.extend(workflow.actions());
+    }
+    values.iter().map(|value| canonical_action(value)).collect()
+}
+
+impl Diff {
+    /// Read the base version of every scanned workflow. `scan_paths` are the paths the scan was
+    /// given, which a `--diff` directory mirrors; a `--diff` file stands for the one file scanned.
+    pub fn load(base: &Base, scan_paths: &[PathBuf], workflows: &[&Workflow]) -> Result<Diff, ScanError> {
+        let mut sources: BTreeMap<String, Option<String>> = BTreeMap::new();
+        let mut deleted = Vec::new();
+        match base {
+            Base::Path(base) if !base.exists() => return Err(ScanError::Options(format!("--diff {} does not exist", base.display()))),
+            Base::Path(base) if base.is_file() => {
+                let [workflow] = workflows else {
+                    return Err(ScanError::Options(format!("--diff {} is a file, so the scan must be of one workflow file; give a directory to compare directories", base.display())));
+                };
+                let source = fs::read_to_string(base).map_err(|e| ScanError::io(&format!("--diff {}", base.display()), e))?;
+                sources.insert(workflow.path.clone(), Some(source));
+            }
+            Base::Path(base) => {
+                for workflow in workflows {
+                    let path = Path::new(&workflow.path);
+                    let relative = scan_paths.iter().find_map(|scan_path| match path.strip_prefix(scan_path) {
+                        Ok(relative) if !relative.as_os_str().is_empty() => Some(relative.to_path_buf()),
+                        _ => path.file_name().filter(|_| path == scan_path).map(PathBuf::from),
+                    });
+                    let source = relative.and_then(|relative| fs::read_to_string(base.join(relative)).ok());
+                    sources.insert(workflow.path.clone(), source);
+                }
+                // Only a single scanned directory says which of the base's files should still exist
+                if let [scan_path] = scan_paths
+                    && scan_path.is_dir()
+                {
+                    let entries = fs::read_dir(base).map_err(|e| ScanError::io(&format!("--diff {}", base.display()), e))?;
+                    for entry in entries.flatten().map(|e| e.path()).filter(|p| p.is_file() && is_workflow_file(p)) {
+                        let Some(name) = entry.file_name() else { continue };
+                        if !scan_path.join(name).exists() {
+                            deleted.push(entry.display().to_string());
+                        }
+                    }
+                }
+            }
+            Base::Revision(revision) => {
+                git(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", revision)])
+                    .map_err(|_| ScanError::Options(format!("--diff-base {} is not a commit of this checkout", revision)))?;
+                for workflow in workflows {
+                    let source = git(&["show", &format!("{}:{}", revision, revision_path(&workflow.path))]).ok();
+                    sources.insert(workflow.path.clone(), source);
+                }
+                let mut args = vec!["diff", "--name-only", "--relative", "--diff-filter=D", revision.as_str(), "--"];
+                let paths: Vec<String> = scan_paths.iter().map(|p| p.display().to_string()).collect();
+                args.extend(paths.iter().map(String::as_str));
+                deleted.extend(git(&args).map_err(|e| ScanError::io("git diff", e))?.lines().filter(|p| is_workflow_file(Path::new(p))).map(str::to_string));
+            }
+        }
+        deleted.sort();
+        deleted.dedup();
+
+        let mut previous = BTreeMap::new();
+        let mut identical = BTreeSet::new();
+        for workflow in workflows {
+            let source = sources.remove(&workflow.path).flatten();
+            if source.as_deref().is_some_and(|s| s.strip_prefix('\u{feff}').unwrap_or(s) == workflow.source) {
+                identical.insert(workflow.path.clone());
+            }
+            previous.insert(workflow.path.clone(), source.map(|source| uses(&workflow.path, &source)));
+        }
+        Ok(Diff { base: base.describe(), previous, identical, deleted })
+    }
+
+    /// Whether the file's use of the action is new since the base: the file is new, or its base
+    /// version did not use the action with this ref
+    pub fn is_changed(&self, file: &str, action: &str) -> bool {
+        match self.previous.get(file) {
+            Some(Some(previous)) => !previous.contains(&canonical_action(action)),
+            _ => true,
+        }
+    }
+
+    /// The actions every scanned file that uses them already used in the base
+    pub fn unchanged_actions(&self, workflows: &[&Workflow]) -> BTreeSet<String> {
+        let mut used: BTreeMap<String, bool> = BTreeMap::new();
+        for workflow in workflows {
+            for action in workflow.actions() {
+                let changed = self.is_changed(&workflow.path, &action);
+                *used.entry(action).or_default() |= changed;
+            }
+        }
+        used.into_iter().filter(|(_, changed)| !changed).map(|(action, _)| action).collect()
+    }
+
+    /// The report's account of the comparison
+    pub fn scope(&self, unchanged_actions: &BTreeSet<String>) -> DiffScope {
+        DiffScope {
+            base: self.base.clone(),
+            unchanged_actions: unchanged_actions.iter().cloned().collect(),
+            added_workflows: self.previous.iter().filter(|(_, p)| p.is_none()).map(|(file, _)| file.clone()).collect(),
+            deleted_workflows: self.deleted.clone(),
+        }
+    }
+
+    /// Mark the findings about unchanged entries as unchanged: still reported, but not failing.
+    /// Findings about no action count as unchanged when the whole file is. Bans apply whatever
+    /// changed, as they do under the baseline.
+    pub fn apply(&self, report: &mut ScanReport) {
+        let unchanged_actions: BTreeSet<&String> = report.diff.iter().flat_map(|scope| &scope.unchanged_actions).collect();
+        for finding in report.findings.iter_mut() {
+            if finding.is_suppressed() || matches!(finding.rule.category(), Category::Blocked | Category::Informational) {
+                continue;
+            }
+            let unchanged = match &finding.file {
+                Some(file) if finding.action.is_empty() => self.identical.contains(file),
+                Some(file) => !self.is_changed(file, &finding.action),
+                None => unchanged_actions.contains(&finding.action),
+            };
+            if unchanged {
+                finding.suppression = Some(Suppression {
+                    reason: format!("unchanged since {}", self.base),
+                    expires: None,
+                    expired: false,
+                    baseline: false,
+                    unchanged: true,
+                });
+            }
+        }
+    }
+}
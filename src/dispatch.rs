@@ -0,0 +1,272 @@
+use crate::{RefKind, Workflow};
+use crate::config::Config;
+use crate::report::{Finding, Level, Rule};
+use crate::rules::{key_line, step_label};
+use regex::Regex;
+
+/// Where a command can start: the beginning of the line or of a compound command, after any
+/// `VAR=value` assignments. Keeps `echo "gh workflow run ..."` and the like from counting.
+const COMMAND_START: &str = r"(?:^|[;&|(`]|\b(?:then|do|else|sudo|exec)\s)\s*(?:\w+=\S*\s+)*";
+
+/// A workflow dispatch found in a `run:` script
+struct Dispatch {
+    repo: String,
+    workflow: Option<String>,
+    reference: String,
+}
+
+/// Heuristic `--scan-run-steps` rule: find `gh workflow run` and `gh api .../dispatches` calls in
+/// `run:` scripts that trigger another repository's workflow at a ref that is not a commit SHA.
+///
+/// Only dispatches whose repository and ref are both literal are reported; anything built from
+/// variables or left to the target's default branch is skipped rather than guessed at.
+pub fn check_run_steps(workflow: &Workflow, config: &Config) -> Result<Vec<Finding>, String> {
+    let extra = config
+        .run_step_patterns
+        .iter()
+        .flatten()
+        .map(|pattern| Regex::new(pattern).map_err(|e| format!("invalid run_step_patterns entry {}: {}", pattern, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut findings = Vec::new();
+    let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else {
+        return Ok(findings);
+    };
+    for (job_id, job) in jobs {
+        let job_id = job_id.as_str().unwrap_or("<unnamed>");
+        let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else { continue };
+        let job_line = key_line(&workflow.source, &["jobs", job_id]);
+        for (index, step) in steps.iter().enumerate() {
+            let Some(script) = step.get("run").and_then(|v| v.as_str()) else { continue };
+            for (first, line) in logical_lines(script) {
+                let Some(dispatch) = parse_line(&line, &extra) else { continue };
+                if is_commit_sha(&dispatch.reference) {
+                    continue;
+                }
+                let target = match &dispatch.workflow {
+                    Some(name) => format!("{} in {}", name, dispatch.repo),
+                    None => dispatch.repo.clone(),
+                };
+                let message = format!(
+                    "Job {}, {}, dispatches workflow {} at {}, which is not a commit SHA; the triggered code is an untracked dependency",
+                    job_id,
+                    step_label(step, index),
+                    target,
+                    dispatch.reference
+                );
+                let dependency = format!("{}@{}", dispatch.repo, dispatch.reference);
+                let line_number = source_line(&workflow.source, &first, job_line).or(job_line);
+                findings.push(
+                    Finding::new(Rule::UnpinnedDispatch, Level::Warning, &dependency, message)
+                        .with_job(job_id)
+                        .at(&workflow.path, line_number),
+                );
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// Join backslash-continued lines so each command is matched as a whole. Returns each command's
+/// first physical line alongside the joined command.
+pub fn logical_lines(script: &str) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+    let mut first: Option<String> = None;
+    let mut current = String::new();
+    for line in script.lines() {
+        first.get_or_insert_with(|| line.to_string());
+        match line.trim_end().strip_suffix('\\') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                lines.push((first.take().unwrap_or_default(), std::mem::take(&mut current)));
+            }
+        }
+    }
+    if let Some(first) = first {
+        lines.push((first, current));
+    }
+    lines
+}
+
+fn parse_line(line: &str, extra: &[Regex]) -> Option<Dispatch> {
+    let line = line.split('#').next().unwrap_or("");
+    parse_workflow_run(line)
+        .or_else(|| parse_api_dispatch(line))
+        .or_else(|| extra.iter().find_map(|pattern| parse_custom(line, pattern)))
+        .filter(|d| !d.repo.contains('$') && !d.reference.contains('$'))
+}
+
+/// `gh workflow run <workflow> --repo owner/repo --ref <ref>`, flags in any order
+fn parse_workflow_run(line: &str) -> Option<Dispatch> {
+    let command = Regex::new(&format!(r"{}gh\s+workflow\s+run\s+([^;&|]*)", COMMAND_START)).unwrap();
+    let args = command.captures(line)?.get(1)?.as_str();
+    let workflow = args.split_whitespace().next().filter(|w| !w.starts_with('-')).map(unquote);
+    Some(Dispatch {
+        repo: flag(args, r"-R|--repo")?,
+        workflow,
+        reference: flag(args, r"-r|--ref")?,
+    })
+}
+
+/// `gh api repos/owner/repo/actions/workflows/<workflow>/dispatches -f ref=<ref>`
+fn parse_api_dispatch(line: &str) -> Option<Dispatch> {
+    let command = Regex::new(&format!(r"{}gh\s+api\s+([^;&|]*)", COMMAND_START)).unwrap();
+    let args = command.captures(line)?.get(1)?.as_str();
+    let path = Regex::new(r"/?repos/([\w.-]+/[\w.-]+)/actions/workflows/([^/\s'\x22]+)/dispatches").unwrap();
+    let path = path.captures(args)?;
+    let reference = Regex::new(r"(?:-f|-F|--field|--raw-field)(?:=|\s+)['\x22]?ref=([^\s'\x22]+)").unwrap();
+    Some(Dispatch {
+        repo: path[1].to_string(),
+        workflow: Some(path[2].to_string()),
+        reference: reference.captures(args)?[1].to_string(),
+    })
+}
+
+/// A `run_step_patterns` regex with named `repo` and `ref` groups, and optionally `workflow`
+fn parse_custom(line: &str, pattern: &Regex) -> Option<Dispatch> {
+    let captures = pattern.captures(line)?;
+    Some(Dispatch {
+        repo: captures.name("repo")?.as_str().to_string(),
+        workflow: captures.name("workflow").map(|w| w.as_str().to_string()),
+        reference: captures.name("ref")?.as_str().to_string(),
+    })
+}
+
+/// The value of a command-line flag given as `--flag value` or `--flag=value`
+fn flag(args: &str, names: &str) -> Option<String> {
+    let pattern = Regex::new(&format!(r"(?:^|\s)(?:{})(?:=|\s+)(\S+)", names)).unwrap();
+    Some(unquote(&pattern.captures(args)?[1]))
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+fn is_commit_sha(reference: &str) -> bool {
+    RefKind::classify(reference) == RefKind::FullSha
+}
+
+/// Line in the workflow source holding the start of a command, searching from the job's line
+pub fn source_line(source: &str, first_line: &str, from: Option<usize>) -> Option<usize> {
+    let needle = first_line.trim();
+    if needle.is_empty() {
+        return None;
+    }
+    let skip = from.unwrap_or(1) - 1;
+    source
+        .lines()
+        .enumerate()
+        .skip(skip)
+        .find(|(_, line)| line.contains(needle))
+        .map(|(index, _)| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA: &str = "8f4b7f84864484a7bf31766abe9204da3cbe65b3";
+
+    /// A workflow of one `release` job whose `trigger` step runs `run`, given as the YAML after
+    /// `run:`
+    fn workflow(run: &str) -> Workflow {
+        let source = format!("on: push\njobs:\n  release:\n    runs-on: ubuntu-latest\n    steps:\n      - name: trigger\n        run: {}\n", run);
+        Workflow::parse("ci.yml", &source).unwrap()
+    }
+
+    /// The dependency and line of each finding
+    fn dispatched(run: &str, config: &Config) -> Vec<(String, Option<usize>)> {
+        let findings = check_run_steps(&workflow(run), config).unwrap();
+        findings.into_iter().map(|f| (f.action, f.line)).collect()
+    }
+
+    fn dependencies(run: &str) -> Vec<String> {
+        dispatched(run, &Config::default()).into_iter().map(|(dependency, _)| dependency).collect()
+    }
+
+    #[test]
+    fn a_dispatch_at_a_branch_or_tag_is_flagged() {
+        assert_eq!(dependencies("gh workflow run deploy.yml --repo acme/infra --ref main"), ["acme/infra@main"]);
+        assert_eq!(dependencies("gh workflow run deploy.yml -R acme/infra -r v2 -f env=prod"), ["acme/infra@v2"]);
+        assert_eq!(dependencies("gh workflow run --ref=main --repo=acme/infra deploy.yml"), ["acme/infra@main"]);
+        assert_eq!(dependencies("GH_TOKEN=x gh workflow run deploy.yml --repo 'acme/infra' --ref \"main\""), ["acme/infra@main"]);
+        assert_eq!(dependencies("make build && gh workflow run deploy.yml --repo acme/infra --ref main"), ["acme/infra@main"]);
+        assert_eq!(dependencies("gh api repos/acme/infra/actions/workflows/deploy.yml/dispatches -f ref=main"), ["acme/infra@main"]);
+        assert_eq!(dependencies("gh api -X POST /repos/acme/infra/actions/workflows/1234/dispatches --raw-field 'ref=release'"), ["acme/infra@release"]);
+    }
+
+    #[test]
+    fn the_finding_names_the_job_step_workflow_and_ref() {
+        let findings = check_run_steps(&workflow("gh workflow run deploy.yml --repo acme/infra --ref main"), &Config::default()).unwrap();
+        let [finding] = findings.as_slice() else { panic!("{:?}", findings) };
+        assert_eq!((finding.rule, finding.level), (Rule::UnpinnedDispatch, Level::Warning));
+        assert_eq!((finding.job.as_deref(), finding.file.as_deref(), finding.line), (Some("release"), Some("ci.yml"), Some(7)));
+        assert_eq!(
+            finding.message,
+            "Job release, \"trigger\", dispatches workflow deploy.yml in acme/infra at main, which is not a commit SHA; the triggered code is an untracked dependency"
+        );
+    }
+
+    #[test]
+    fn pinned_variable_and_default_branch_dispatches_are_skipped() {
+        for run in [
+            format!("gh workflow run deploy.yml --repo acme/infra --ref {}", SHA),
+            "gh workflow run deploy.yml --repo acme/infra --ref $REF".to_string(),
+            "gh workflow run deploy.yml --repo $GITHUB_REPOSITORY --ref main".to_string(),
+            "gh workflow run deploy.yml --repo acme/infra".to_string(),
+            "gh workflow run deploy.yml --ref main".to_string(),
+        ] {
+            assert_eq!(dependencies(&run), Vec::<String>::new(), "{}", run);
+        }
+    }
+
+    #[test]
+    fn look_alikes_that_do_not_run_gh_are_not_dispatches() {
+        for run in [
+            "echo gh workflow run deploy.yml --repo acme/infra --ref main",
+            "echo \"gh workflow run deploy.yml --repo acme/infra --ref main\"",
+            "echo 'to deploy, gh workflow run deploy.yml --repo acme/infra --ref main'",
+            "# gh workflow run deploy.yml --repo acme/infra --ref main",
+            "make deploy # then gh workflow run deploy.yml --repo acme/infra --ref main",
+            "printf '%s' 'gh api repos/acme/infra/actions/workflows/deploy.yml/dispatches -f ref=main'",
+            "gh workflow list --repo acme/infra",
+        ] {
+            assert_eq!(dependencies(run), Vec::<String>::new(), "{}", run);
+        }
+    }
+
+    #[test]
+    fn a_continued_dispatch_is_reported_at_its_first_line() {
+        let run = "|\n          echo start\n          gh workflow run deploy.yml \\\n            --repo acme/infra \\\n            --ref main";
+        assert_eq!(dispatched(run, &Config::default()), [("acme/infra@main".to_string(), Some(9))]);
+    }
+
+    #[test]
+    fn run_step_patterns_add_dispatch_commands() {
+        let config = Config { run_step_patterns: Some(vec![r"trigger\.sh (?P<repo>\S+) (?P<ref>\S+)".to_string()]), ..Default::default() };
+        assert_eq!(dispatched("./trigger.sh acme/infra main", &config), [("acme/infra@main".to_string(), Some(7))]);
+        let invalid = Config { run_step_patterns: Some(vec!["(".to_string()]), ..Default::default() };
+        assert!(check_run_steps(&workflow("true"), &invalid).unwrap_err().starts_with("invalid run_step_patterns entry ("));
+    }
+
+    #[test]
+    fn source_line_finds_the_command_from_the_job_on() {
+        let source = "jobs:\n  a:\n    steps:\n      - run: make\n  b:\n    steps:\n      - run: make\n";
+        assert_eq!(source_line(source, "  make  ", Some(2)), Some(4));
+        assert_eq!(source_line(source, "make", Some(5)), Some(7), "the first match at or after the job's line");
+        assert_eq!(source_line(source, "make", None), Some(4));
+        assert_eq!(source_line(source, "deploy", Some(1)), None);
+        assert_eq!(source_line(source, "   ", Some(1)), None, "a blank line matches nothing");
+    }
+
+    #[test]
+    fn logical_lines_join_continuations_and_keep_the_first_line() {
+        let lines = logical_lines("a \\\n  b\nc\nd \\");
+        let expected = [("a \\", "a    b"), ("c", "c"), ("d \\", "d  ")];
+        assert_eq!(lines, expected.map(|(first, line)| (first.to_string(), line.to_string())));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::Workflow;
+use crate::dispatch::source_line;
+use crate::github_script::dangerous_expression;
+use crate::report::{Finding, Level, Rule};
+use crate::rules::{key_line, step_label};
+use regex::Regex;
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+
+/// Inputs of a Docker action step that become the container's command line
+const COMMAND_INPUTS: [&str; 2] = ["args", "entrypoint"];
+
+/// Whether the step runs a container: a `docker://` image, or a local action whose metadata
+/// says `runs.using: docker`
+fn runs_container(action: &str, root: &Path) -> bool {
+    if action.starts_with("docker://") {
+        return true;
+    }
+    let Some(path) = action.strip_prefix("./") else {
+        return false;
+    };
+    ["action.yml", "action.yaml"].iter().any(|file| {
+        let Ok(content) = fs::read_to_string(root.join(path).join(file)) else { return false };
+        let Ok(metadata) = serde_yaml::from_str::<Value>(&content) else { return false };
+        metadata.get("runs").and_then(|r| r.get("using")).and_then(Value::as_str) == Some("docker")
+    })
+}
+
+/// Flag Docker action steps whose `with: args` or `with: entrypoint` interpolate
+/// attacker-controlled expressions. These reach the container's command line as the attacker
+/// wrote them, just as they would in a `run:` step. Under `--strict`, any other expression there
+/// is a note. Remote actions are recognized only by `docker://`, since their metadata is not
+/// read.
+pub fn check(workflow: &Workflow, root: &Path, strict: bool) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else {
+        return findings;
+    };
+    let expression = Regex::new(r"\$\{\{.*?\}\}").unwrap();
+    let dangerous = dangerous_expression();
+    for (job_id, job) in jobs {
+        let job_id = job_id.as_str().unwrap_or("<unnamed>");
+        let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else { continue };
+        let job_line = key_line(&workflow.source, &["jobs", job_id]);
+        for (index, step) in steps.iter().enumerate() {
+            let Some(action) = step.get("uses").and_then(|v| v.as_str()) else { continue };
+            let Some(with) = step.get("with") else { continue };
+            if !COMMAND_INPUTS.iter().any(|input| with.get(input).is_some()) || !runs_container(action, root) {
+                continue;
+            }
+            for input in COMMAND_INPUTS {
+                let Some(value) = with.get(input).and_then(Value::as_str) else { continue };
+                let (level, found, what) = match expression.find_iter(value).find(|m| dangerous.is_match(m.as_str())) {
+                    Some(found) => (Level::Warning, found.as_str(), "attacker-controlled event data"),
+                    None => match expression.find(value) {
+                        Some(found) if strict => (Level::Note, found.as_str(), "an expression (reported under --strict)"),
+                        _ => continue,
+                    },
+                };
+                let label = step_label(step, index);
+                let message = format!(
+                    "Job {}, {}, interpolates {} into the container's {}: `{}` reaches its command line as written, \
+                     where it can inject arguments or commands; pass it through env instead",
+                    job_id, label, what, input, found
+                );
+                let line = source_line(&workflow.source, found, job_line).or(job_line);
+                findings.push(
+                    Finding::new(Rule::DockerArgsInjection, level, action, message)
+                        .with_job(job_id)
+                        .with_step(&label)
+                        .at(&workflow.path, line),
+                );
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::Scratch;
+
+    /// A workflow on `issue_comment` whose `run` job has the step `step`, given as the YAML lines
+    /// after `- ` at step indentation
+    fn workflow(step: &str) -> Workflow {
+        let source = format!("on: issue_comment\njobs:\n  run:\n    runs-on: ubuntu-latest\n    steps:\n      - {}\n", step);
+        Workflow::parse("ci.yml", &source).unwrap()
+    }
+
+    fn flagged(step: &str, root: &Path, strict: bool) -> Vec<(Level, Option<usize>, String)> {
+        check(&workflow(step), root, strict).into_iter().map(|f| (f.level, f.line, f.message)).collect()
+    }
+
+    #[test]
+    fn a_docker_image_interpolating_event_data_into_args_or_entrypoint_is_flagged() {
+        let root = Scratch::new("docker-args-image");
+        for input in COMMAND_INPUTS {
+            let step = format!("uses: docker://alpine:3.20\n        with:\n          {}: echo ${{{{ github.event.comment.body }}}}", input);
+            let flagged = flagged(&step, &root, false);
+            let [(level, line, message)] = flagged.as_slice() else { panic!("{}: {:?}", input, flagged) };
+            assert_eq!((*level, *line), (Level::Warning, Some(8)), "{}", input);
+            assert!(
+                message.starts_with(&format!(
+                    "Job run, step 1, interpolates attacker-controlled event data into the container's {}: `${{{{ github.event.comment.body }}}}`",
+                    input
+                )),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn other_expressions_are_notes_only_under_strict() {
+        let root = Scratch::new("docker-args-strict");
+        let step = "uses: docker://alpine:3.20\n        with:\n          args: build ${{ inputs.target }}";
+        assert_eq!(flagged(step, &root, false), []);
+        let flagged = flagged(step, &root, true);
+        assert_eq!(flagged.iter().map(|(level, line, _)| (*level, *line)).collect::<Vec<_>>(), [(Level::Note, Some(8))]);
+    }
+
+    #[test]
+    fn a_local_docker_action_taking_interpolated_inputs_is_flagged() {
+        let root = Scratch::new("docker-args-local");
+        for (dir, using) in [("container", "docker"), ("script", "node20")] {
+            fs::create_dir_all(root.join(".github/actions").join(dir)).unwrap();
+            let metadata = format!("name: {}\nruns:\n  using: {}\n  image: Dockerfile\n  main: index.js\n", dir, using);
+            fs::write(root.join(".github/actions").join(dir).join("action.yml"), metadata).unwrap();
+        }
+        let step = |dir: &str| format!("uses: ./.github/actions/{}\n        with:\n          entrypoint: ${{{{ github.head_ref }}}}", dir);
+        let flagged_container = flagged(&step("container"), &root, false);
+        assert_eq!(flagged_container.iter().map(|(level, line, _)| (*level, *line)).collect::<Vec<_>>(), [(Level::Warning, Some(8))]);
+        assert_eq!(flagged(&step("script"), &root, false), [], "a JavaScript action's inputs are not its command line");
+        assert_eq!(flagged(&step("missing"), &root, false), [], "an action with no metadata on disk is not known to be Docker");
+    }
+
+    #[test]
+    fn other_inputs_and_plain_arguments_are_left_alone() {
+        let root = Scratch::new("docker-args-plain");
+        for step in [
+            "uses: docker://alpine:3.20\n        with:\n          args: make test",
+            "uses: docker://alpine:3.20\n        with:\n          token: ${{ github.event.comment.body }}",
+            "uses: acme/action@v1\n        with:\n          args: ${{ github.event.comment.body }}",
+        ] {
+            assert_eq!(flagged(step, &root, true), [], "{}", step);
+        }
+    }
+}
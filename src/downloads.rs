@@ -0,0 +1,231 @@
+use crate::Workflow;
+use crate::config::Config;
+use crate::dispatch::{logical_lines, source_line};
+use crate::report::{Finding, Level, Rule};
+use crate::rules::{key_line, step_label};
+use regex::Regex;
+
+/// Built-in insecure-download idioms: id, pattern, remediation hint. Deliberately conservative;
+/// each is matched against one command at a time.
+const DEFAULT_PATTERNS: [(&str, &str, &str); 4] = [
+    (
+        "curl-insecure",
+        r"\bcurl\b[^|;&]*\s(?:--insecure|-[A-Za-z]*k[A-Za-z]*)(?:\s|$)",
+        "drop -k/--insecure and fix the certificate chain instead",
+    ),
+    (
+        "wget-no-check-certificate",
+        r"\bwget\b[^|;&]*\s--no-check-certificate\b",
+        "drop --no-check-certificate and fix the certificate chain instead",
+    ),
+    (
+        "http-download",
+        r#"\b(?:curl|wget)\b[^|;&]*\s['"]?http://\S+"#,
+        "download over https:// and verify a checksum",
+    ),
+    (
+        "pip-trusted-host",
+        r"\bpip3?\b[^|;&]*\s--trusted-host\b",
+        "remove --trusted-host and serve the index over valid TLS",
+    ),
+];
+
+/// Hosts where plain HTTP never leaves the runner
+const LOCAL_HOSTS: [&str; 3] = ["http://localhost", "http://127.", "http://0.0.0.0"];
+
+struct Pattern {
+    id: String,
+    regex: Regex,
+    hint: String,
+}
+
+/// The default patterns plus `insecure_download_patterns`, minus `disabled_insecure_downloads`
+fn patterns(config: &Config) -> Result<Vec<Pattern>, String> {
+    let disabled = config.disabled_insecure_downloads.clone().unwrap_or_default();
+    let mut patterns: Vec<Pattern> = DEFAULT_PATTERNS
+        .iter()
+        .map(|(id, regex, hint)| Pattern {
+            id: id.to_string(),
+            regex: Regex::new(regex).unwrap(),
+            hint: hint.to_string(),
+        })
+        .collect();
+    for (id, regex) in config.insecure_download_patterns.iter().flatten() {
+        let regex = Regex::new(regex).map_err(|e| format!("invalid insecure_download_patterns entry {}: {}", id, e))?;
+        // A configured pattern with a built-in id replaces the built-in
+        patterns.retain(|p| &p.id != id);
+        patterns.push(Pattern {
+            id: id.clone(),
+            regex,
+            hint: "download over verified TLS instead".to_string(),
+        });
+    }
+    patterns.retain(|p| !disabled.contains(&p.id));
+    Ok(patterns)
+}
+
+/// `--scan-run-steps` rule family for `run:` scripts that fetch code over plain HTTP or with TLS
+/// verification turned off
+pub fn check_run_steps(workflow: &Workflow, config: &Config) -> Result<Vec<Finding>, String> {
+    let patterns = patterns(config)?;
+    let mut findings = Vec::new();
+    let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else {
+        return Ok(findings);
+    };
+    for (job_id, job) in jobs {
+        let job_id = job_id.as_str().unwrap_or("<unnamed>");
+        let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else { continue };
+        let job_line = key_line(&workflow.source, &["jobs", job_id]);
+        for (index, step) in steps.iter().enumerate() {
+            let Some(script) = step.get("run").and_then(|v| v.as_str()) else { continue };
+            for (first, line) in logical_lines(script) {
+                let command = line.split(" #").next().unwrap_or("").trim_start();
+                if command.starts_with('#') {
+                    continue;
+                }
+                for pattern in &patterns {
+                    let Some(matched) = pattern.regex.find(command) else { continue };
+                    let snippet = snippet(matched.as_str());
+                    if pattern.id == "http-download" && LOCAL_HOSTS.iter().any(|h| snippet.contains(h)) {
+                        continue;
+                    }
+                    let message = format!(
+                        "Job {}, {}, downloads insecurely ({}): `{}`; {}",
+                        job_id,
+                        step_label(step, index),
+                        pattern.id,
+                        snippet,
+                        pattern.hint
+                    );
+                    let line_number = source_line(&workflow.source, &first, job_line).or(job_line);
+                    findings.push(
+                        Finding::new(Rule::InsecureDownload, Level::Warning, "", message)
+                            .with_job(job_id)
+                            .at(&workflow.path, line_number),
+                    );
+                }
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// The matched command with whitespace collapsed, cut short enough to read in a log line
+fn snippet(matched: &str) -> String {
+    let collapsed = matched.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(80) {
+        Some((end, _)) => format!("{}...", &collapsed[..end]),
+        None => collapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workflow of one `build` job whose only step runs `run`, given as the YAML after `run:`
+    fn workflow(run: &str) -> Workflow {
+        let source = format!("on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - name: fetch\n        run: {}\n", run);
+        Workflow::parse("ci.yml", &source).unwrap()
+    }
+
+    /// The pattern id and line of each finding
+    fn flagged(run: &str, config: &Config) -> Vec<(String, Option<usize>)> {
+        let findings = check_run_steps(&workflow(run), config).unwrap();
+        findings
+            .into_iter()
+            .map(|f| {
+                let id = f.message.split(" (").nth(1).and_then(|m| m.split(')').next()).unwrap().to_string();
+                (id, f.line)
+            })
+            .collect()
+    }
+
+    fn ids(run: &str) -> Vec<String> {
+        flagged(run, &Config::default()).into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn each_default_pattern_is_flagged() {
+        assert_eq!(ids("curl -k https://example.com/install.sh | sh"), ["curl-insecure"]);
+        assert_eq!(ids("curl -sSkL https://example.com/x"), ["curl-insecure"]);
+        assert_eq!(ids("curl --insecure -o x https://example.com/x"), ["curl-insecure"]);
+        assert_eq!(ids("wget --no-check-certificate https://example.com/x"), ["wget-no-check-certificate"]);
+        assert_eq!(ids("wget -q http://example.com/x.tgz"), ["http-download"]);
+        assert_eq!(ids("pip install --trusted-host pypi.example.com foo"), ["pip-trusted-host"]);
+        assert_eq!(ids("pip3 install --trusted-host pypi.example.com foo"), ["pip-trusted-host"]);
+    }
+
+    #[test]
+    fn the_defaults_are_conservative() {
+        for run in [
+            "curl -sSL https://example.com/install.sh | sh",
+            "curl -X POST https://example.com/hook",
+            "curl http://localhost:8080/health",
+            "wget http://127.0.0.1:3000/ready",
+            "echo http://example.com",
+            "pip install foo",
+            "# curl -k https://example.com",
+            "make build # curl -k https://example.com",
+        ] {
+            assert!(ids(run).is_empty(), "{} was flagged", run);
+        }
+    }
+
+    #[test]
+    fn the_finding_names_the_job_step_snippet_and_hint() {
+        let findings = check_run_steps(&workflow("curl   --insecure  https://example.com/x"), &Config::default()).unwrap();
+        let [finding] = findings.as_slice() else { panic!("{:?}", findings) };
+        assert_eq!((finding.rule, finding.level), (Rule::InsecureDownload, Level::Warning));
+        assert_eq!((finding.job.as_deref(), finding.file.as_deref(), finding.line), (Some("build"), Some("ci.yml"), Some(7)));
+        assert!(finding.message.starts_with("Job build, \"fetch\", downloads insecurely (curl-insecure): `curl --insecure`;"), "{}", finding.message);
+        assert!(finding.message.ends_with("drop -k/--insecure and fix the certificate chain instead"), "{}", finding.message);
+    }
+
+    #[test]
+    fn literal_block_lines_are_checked_one_command_at_a_time() {
+        let run = "|\n          set -e\n          curl -fsSL https://example.com/a\n          wget --no-check-certificate https://example.com/b\n          pip install --trusted-host idx foo";
+        let flagged = flagged(run, &Config::default());
+        let expected = [("wget-no-check-certificate", Some(10)), ("pip-trusted-host", Some(11))];
+        assert_eq!(flagged, expected.map(|(id, line)| (id.to_string(), line)));
+    }
+
+    #[test]
+    fn a_continued_command_is_reported_at_its_first_line() {
+        let run = "|\n          echo start\n          curl -sS \\\n            --insecure \\\n            https://example.com/x";
+        assert_eq!(flagged(run, &Config::default()), [("curl-insecure".to_string(), Some(9))]);
+    }
+
+    #[test]
+    fn a_folded_block_is_one_command() {
+        let run = ">\n          curl\n          -k\n          https://example.com/x";
+        assert_eq!(ids(run), ["curl-insecure"]);
+    }
+
+    #[test]
+    fn patterns_can_be_disabled_replaced_and_added() {
+        let run = "|\n          curl -k https://example.com/a\n          npm config set strict-ssl false";
+        let config = Config {
+            disabled_insecure_downloads: Some(vec!["curl-insecure".to_string()]),
+            insecure_download_patterns: Some([("npm-strict-ssl".to_string(), r"strict-ssl\s+false".to_string())].into()),
+            ..Config::default()
+        };
+        assert_eq!(flagged(run, &config).into_iter().map(|(id, _)| id).collect::<Vec<_>>(), ["npm-strict-ssl"]);
+
+        let config = Config {
+            insecure_download_patterns: Some([("curl-insecure".to_string(), r"curl -k https://example\.com/b".to_string())].into()),
+            ..Config::default()
+        };
+        assert!(flagged(run, &config).is_empty());
+    }
+
+    #[test]
+    fn an_invalid_pattern_is_an_error() {
+        let config = Config {
+            insecure_download_patterns: Some([("broken".to_string(), "(".to_string())].into()),
+            ..Config::default()
+        };
+        let error = check_run_steps(&workflow("true"), &config).unwrap_err();
+        assert!(error.starts_with("invalid insecure_download_patterns entry broken: "), "{}", error);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::token;
+use std::fmt;
+use std::sync::Arc;
+
+/// Why loading or looking something up failed, with the file, URL or reference it was about.
+/// Errors are cloned into the advisory memo, so I/O errors are shared rather than owned.
+#[derive(Debug, Clone)]
+pub enum ScanError {
+    /// Reading a file, or sending a request, failed; `context` is the path or URL
+    Io { context: String, source: Arc<std::io::Error> },
+    /// A file is not valid YAML, at the 1-based line and column when the parser gave them
+    YamlParse { path: String, line: Option<usize>, column: Option<usize>, message: String },
+    /// An API answered with a status the scan cannot use
+    Http { status: u16, url: String },
+    /// An API still refused for rate limiting after the scan had waited and retried
+    RateLimited { url: String, retry_after: Option<u64> },
+    /// A response body that is not what the API documents
+    InvalidResponse { url: String, message: String },
+    /// A config file that parsed but does not hold a valid config, at the 1-based line of the
+    /// bad key or entry when it is known
+    ConfigInvalid { path: String, line: Option<usize>, message: String },
+    /// Not an `owner/repo[/path]@ref` reference
+    MalformedActionRef(String),
+    /// Scan options the scan cannot carry out, such as a `--diff` base that does not exist
+    Options(String),
+}
+
+impl ScanError {
+    pub fn io(context: &str, error: std::io::Error) -> Self {
+        ScanError::Io { context: context.to_string(), source: Arc::new(error) }
+    }
+
+    /// The parse error of `path`, with the location taken out of the message and kept apart
+    pub fn yaml(path: &str, error: &serde_yaml::Error) -> Self {
+        let message = error.to_string();
+        let Some(location) = error.location() else {
+            return ScanError::YamlParse { path: path.to_string(), line: None, column: None, message };
+        };
+        let (line, column) = (location.line(), location.column());
+        ScanError::YamlParse {
+            path: path.to_string(),
+            line: Some(line),
+            column: Some(column),
+            message: message.replace(&format!(" at line {} column {}", line, column), ""),
+        }
+    }
+
+    pub fn config(path: &str, message: impl fmt::Display) -> Self {
+        ScanError::ConfigInvalid { path: path.to_string(), line: None, message: message.to_string() }
+    }
+
+    pub fn config_at(path: &str, line: Option<usize>, message: impl fmt::Display) -> Self {
+        ScanError::ConfigInvalid { path: path.to_string(), line, message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Io { context, source } => write!(f, "{}: {}", token::without_userinfo(context), source),
+            ScanError::YamlParse { path, line: Some(line), column: Some(column), message } => {
+                write!(f, "{}: line {}, column {}: {}", path, line, column, message)
+            }
+            ScanError::YamlParse { path, message, .. } => write!(f, "{}: {}", path, message),
+            ScanError::Http { status, url } => write!(f, "{}: unexpected HTTP status {}", token::without_userinfo(url), status),
+            ScanError::RateLimited { url, retry_after: Some(seconds) } => {
+                write!(f, "{}: rate limited (retry after {}s)", token::without_userinfo(url), seconds)
+            }
+            ScanError::RateLimited { url, retry_after: None } => write!(f, "{}: rate limited", token::without_userinfo(url)),
+            ScanError::InvalidResponse { url, message } => write!(f, "{}: invalid response: {}", token::without_userinfo(url), message),
+            ScanError::ConfigInvalid { path, line: Some(line), message } => write!(f, "{}: line {}: {}", path, line, message),
+            ScanError::ConfigInvalid { path, message, .. } => write!(f, "{}: {}", path, message),
+            ScanError::MalformedActionRef(action) => write!(f, "{} is not an owner/repo[/path]@ref reference", action),
+            ScanError::Options(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScanError::Io { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
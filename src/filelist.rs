@@ -0,0 +1,78 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Paths as `--stdin-filelist` takes them: one per line, or NUL-separated with `-z` as
+/// `git ls-files -z` and `find -print0` write them. Blank entries are skipped, and in line mode
+/// so is the `\r` of CRLF endings; otherwise paths are taken byte for byte.
+pub fn parse(input: &[u8], nul: bool) -> Vec<PathBuf> {
+    let separator = if nul { b'\0' } else { b'\n' };
+    input
+        .split(|b| *b == separator)
+        .map(|entry| if nul { entry } else { entry.strip_suffix(b"\r").unwrap_or(entry) })
+        .filter(|entry| !entry.iter().all(u8::is_ascii_whitespace))
+        .map(path_of)
+        .collect()
+}
+
+/// A listed path, byte for byte where paths are bytes
+#[cfg(unix)]
+fn path_of(entry: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(entry))
+}
+
+/// A listed path; elsewhere paths are Unicode, so the list must be UTF-8
+#[cfg(not(unix))]
+fn path_of(entry: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(entry).into_owned())
+}
+
+/// The listed paths that do not exist, for the error
+fn missing(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter().filter(|p| !p.exists()).map(|p| p.display().to_string()).collect()
+}
+
+/// Read the path list from stdin. Every path must exist; the error lists each one that does not.
+/// Duplicates are left for the scan, which scans each file once.
+pub fn read_stdin(nul: bool) -> Result<Vec<PathBuf>, String> {
+    let mut input = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut input)
+        .map_err(|e| format!("could not read the file list from stdin: {}", e))?;
+    let paths = parse(&input, nul);
+    let missing = missing(&paths);
+    if !missing.is_empty() {
+        return Err(format!("--stdin-filelist names files that do not exist: {}", missing.join(", ")));
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_mode_skips_blank_entries_and_crlf() {
+        let paths = parse(b"a.yml\r\n\n  \nb dir/c.yml\n", false);
+        assert_eq!(paths, [PathBuf::from("a.yml"), PathBuf::from("b dir/c.yml")]);
+    }
+
+    #[test]
+    fn nul_mode_keeps_newlines_and_carriage_returns_in_paths() {
+        let paths = parse(b"a\nb.yml\0c.yml\r\0\0", true);
+        assert_eq!(paths, [PathBuf::from("a\nb.yml"), PathBuf::from("c.yml\r")]);
+    }
+
+    #[test]
+    fn duplicates_are_kept_for_the_scan() {
+        let paths = parse(b"a.yml\na.yml\n", false);
+        assert_eq!(paths.len(), 2);
+    }
+
+    #[test]
+    fn missing_lists_each_path_that_does_not_exist() {
+        let here = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let paths = [here.clone(), here.join("no-such-file.yml"), PathBuf::from("also-missing.yml")];
+        assert_eq!(missing(&paths), [here.join("no-such-file.yml").display().to_string(), "also-missing.yml".to_string()]);
+    }
+}
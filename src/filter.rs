@@ -0,0 +1,53 @@
+use crate::ActionRef;
+use glob::Pattern;
+
+/// Restricts a scan to a subset of actions, matched on `owner/repo`
+#[derive(Debug, Clone, Default)]
+pub struct ActionFilter {
+    pub only: Vec<Pattern>,
+    pub skip: Vec<Pattern>,
+}
+
+impl ActionFilter {
+    /// Whether an action should be checked. `--skip` wins over `--only`.
+    pub fn allows(&self, action: &str) -> bool {
+        let name = ActionRef::from_action_string(action).map(|r| r.full_name());
+        let matches = |patterns: &[Pattern]| {
+            name.as_deref().is_some_and(|name| patterns.iter().any(|p| p.matches(name)))
+        };
+        if matches(&self.skip) {
+            return false;
+        }
+        self.only.is_empty() || matches(&self.only)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.only.is_empty() || !self.skip.is_empty()
+    }
+}
+
+pub fn parse_pattern(pattern: &str) -> Result<Pattern, glob::PatternError> {
+    Pattern::new(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(only: &[&str], skip: &[&str]) -> ActionFilter {
+        let patterns = |p: &[&str]| p.iter().map(|p| parse_pattern(p).unwrap()).collect();
+        ActionFilter { only: patterns(only), skip: patterns(skip) }
+    }
+
+    #[test]
+    fn skip_wins_over_only_and_subpaths_match_their_repository() {
+        let filter = filter(&["acme/*"], &["acme/internal"]);
+        assert!(filter.allows("acme/deploy@v1"));
+        assert!(filter.allows("acme/cache/save@v2"));
+        assert!(!filter.allows("acme/internal@v1"));
+        assert!(!filter.allows("actions/checkout@v4"));
+        assert!(!filter.allows("./local-action"));
+        assert!(filter.is_active());
+        assert!(ActionFilter::default().allows("./local-action"));
+    }
+}
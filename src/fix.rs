@@ -0,0 +1,345 @@
+use crate::artifacts;
+use crate::cache::{Cached, DiskCache};
+use crate::config::Config;
+use crate::decision::ExitCode;
+use crate::github::GitHubApi;
+use crate::pincomment::CommentStyle;
+use crate::pr::{self, FilePins, PrOptions, PrOutcome};
+use crate::report::{Finding, Level, Rule};
+use crate::scanner::Scanner;
+use crate::{ActionRef, extract_actions_from_workflow, is_unstable_ref_name, patch};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What the `fix` subcommand fixes, and where the fixes go
+pub struct FixOptions {
+    pub workflow_paths: Vec<PathBuf>,
+    /// Commit the fixes to a branch and open (or update) a pull request instead of writing files
+    pub create_pr: bool,
+    /// Repository to open the pull request in, as owner/repo
+    pub repo: Option<String>,
+    /// Branch to open the pull request against; the repository's default branch when unset
+    pub base: Option<String>,
+    /// Branch the fixes are committed to
+    pub branch: String,
+    pub pr_labels: Vec<String>,
+    pub draft: bool,
+    /// Print the changes as a diff instead of writing the files
+    pub dry_run: bool,
+    /// Write the changes to this file as a patch instead of writing the workflows
+    pub emit_patch: Option<PathBuf>,
+    /// Repository root that workflow paths are made relative to
+    pub workspace: PathBuf,
+    /// Token that pushes the branch and opens the pull request
+    pub token: Option<String>,
+}
+
+/// One `uses:` line rewritten from a tag to the commit the tag points at
+#[derive(Debug, Clone)]
+pub struct PinChange {
+    pub line: usize,
+    pub from: String,
+    pub to: String,
+    pub tag: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// Actions whose findings can be fixed by pinning the tag to its commit SHA. A ref built from an
+/// expression names no tag until the workflow runs, and a branch, as the config's
+/// `unstable_ref_names` tell them apart, is meant to move, so both are left for a human.
+pub fn fix_candidates(findings: &[Finding], config: &Config) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for finding in findings {
+        if matches!(finding.rule, Rule::TagPinnedCritical | Rule::UntrustedOwnerNoSha)
+            && !finding.action.contains("${{")
+            && !candidates.contains(&finding.action)
+            && ActionRef::from_action_string(&finding.action).is_some()
+            && !is_unstable_ref_name(&finding.action, config)
+        {
+            candidates.push(finding.action.clone());
+        }
+    }
+    candidates
+}
+
+/// Resolve a tag to its commit SHA, remembering both hits and missing tags in the cache
+pub async fn resolve_tag_sha_cached(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    action_ref: &ActionRef,
+) -> Result<Option<String>, std::io::Error> {
+    let key = format!("{}@{}", action_ref.full_name(), action_ref.version);
+    match cache.get("tag-refs", &key) {
+        Cached::NegativeHit => return Ok(None),
+        Cached::Hit(value) => {
+            if let Some(sha) = value.as_str() {
+                return Ok(Some(sha.to_string()));
+            }
+        }
+        Cached::Miss => {}
+    }
+    let resolved = resolve_tag_sha(api, action_ref).await?;
+    match &resolved {
+        Some(sha) => cache.put("tag-refs", &key, &serde_json::Value::from(sha.as_str())),
+        None => cache.put_negative("tag-refs", &key),
+    }
+    Ok(resolved)
+}
+
+/// Resolve a tag to the commit it points at, dereferencing annotated tags. The tag and the tag
+/// object's SHA are encoded, so a `#` or `?` in either cannot change which resource is asked for.
+pub async fn resolve_tag_sha(api: &GitHubApi<'_>, action_ref: &ActionRef) -> Result<Option<String>, std::io::Error> {
+    let path = format!("/repos/{}/git/ref/tags/{}", action_ref.full_name(), pr::segment(&action_ref.version));
+    let Some(reference) = api.get(&path).await? else {
+        return Ok(None);
+    };
+
+    let object_type = reference["object"]["type"].as_str().unwrap_or("");
+    let sha = reference["object"]["sha"].as_str().unwrap_or("").to_string();
+    if object_type != "tag" {
+        return Ok(Some(sha).filter(|s| !s.is_empty()));
+    }
+
+    // Annotated tags point at a tag object, which in turn points at the commit
+    let tag_path = format!("/repos/{}/git/tags/{}", action_ref.full_name(), pr::segment(&sha));
+    let tag = api.get(&tag_path).await?;
+    Ok(tag.and_then(|t| t["object"]["sha"].as_str().map(str::to_string)))
+}
+
+/// Rewrite every `uses:` line whose value has a resolved SHA, leaving everything else untouched.
+///
+/// The edit is line-based rather than a YAML round-trip so comments, quoting and indentation survive.
+/// Lines holding a `${{ }}` expression are never touched, since what they resolve to is unknown.
+pub fn rewrite_workflow(content: &str, pins: &BTreeMap<String, String>, style: &CommentStyle) -> (String, Vec<PinChange>) {
+    let uses_regex = Regex::new(r#"^(\s*(?:-\s+)?uses:\s*)(["']?)([^\s"'#]+)(["']?)(?:\s+#(.*))?\s*$"#).unwrap();
+    let mut output = String::with_capacity(content.len());
+    let mut changes = Vec::new();
+
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let (line, ending) = split_line_ending(raw_line);
+        let rewritten = uses_regex.captures(line).and_then(|caps| {
+            let value = &caps[3];
+            if line.contains("${{") {
+                return None;
+            }
+            let sha = pins.get(value)?;
+            let (name, tag) = value.split_once('@')?;
+            let pinned = format!("{}@{}", name, sha);
+            let comment = style.rewrite(caps.get(5).map(|m| m.as_str()), tag);
+            let rewritten = format!("{}{}{}{} # {}", &caps[1], &caps[2], pinned, &caps[4], comment);
+            changes.push(PinChange {
+                line: index + 1,
+                from: value.to_string(),
+                to: pinned,
+                tag: tag.to_string(),
+                before: line.to_string(),
+                after: rewritten.clone(),
+            });
+            Some(rewritten)
+        });
+        output.push_str(rewritten.as_deref().unwrap_or(line));
+        output.push_str(ending);
+    }
+
+    (output, changes)
+}
+
+pub fn split_line_ending(line: &str) -> (&str, &str) {
+    if let Some(stripped) = line.strip_suffix("\r\n") {
+        (stripped, "\r\n")
+    } else if let Some(stripped) = line.strip_suffix('\n') {
+        (stripped, "\n")
+    } else {
+        (line, "")
+    }
+}
+
+/// Run the `fix` subcommand. Returns the exit code: policy violations while unfixable findings remain.
+pub async fn run(scanner: &Scanner, opt: &FixOptions) -> Result<i32, Box<dyn std::error::Error>> {
+    let api = GitHubApi::new(scanner.client(), scanner.endpoints(), opt.token.as_deref());
+    let mut files = Vec::new();
+    let mut unfixable = Vec::new();
+
+    for workflow_path in &opt.workflow_paths {
+        let config = scanner.configs().for_workflow(workflow_path)?.config;
+        let style = CommentStyle::from_config(&config)?;
+        let mut findings = Vec::new();
+        for action in extract_actions_from_workflow(workflow_path)? {
+            findings.extend(scanner.check_action_policy(&action, &config));
+        }
+
+        let mut pins = BTreeMap::new();
+        for action in fix_candidates(&findings, &config) {
+            let Some(action_ref) = ActionRef::from_action_string(&action) else { continue };
+            match resolve_tag_sha_cached(&api, scanner.cache(), &action_ref).await {
+                Ok(Some(sha)) => {
+                    pins.insert(action, sha);
+                }
+                Ok(None) => {
+                    scanner.events().warning(&format!("Warning: could not resolve {}: tag {} not found", action, action_ref.version));
+                    unfixable.push(action);
+                }
+                Err(e) => {
+                    scanner.events().warning(&format!("Warning: could not resolve {}: {}", action, e));
+                    unfixable.push(action);
+                }
+            }
+        }
+        // Anything flagged that is not a resolvable tag pin (branches, malformed refs) needs a human
+        for finding in &findings {
+            if finding.level == Level::Error
+                && !pins.contains_key(&finding.action)
+                && !unfixable.contains(&finding.action)
+            {
+                unfixable.push(finding.action.clone());
+            }
+        }
+
+        files.push((workflow_path.as_path(), pins, style));
+    }
+
+    if opt.create_pr {
+        let repo = opt.repo.clone().ok_or("--create-pr needs --repo or GITHUB_REPOSITORY")?;
+        // The PR body is the markdown report of the workflows as they are before the fixes
+        let scanned = scanner.scan(&opt.workflow_paths).await?;
+        let report = artifacts::markdown(&scanned.report, &scanned.decision);
+        let file_pins = files
+            .iter()
+            .map(|(path, pins, style)| {
+                Ok(FilePins {
+                    path: repo_relative(&opt.workspace, path)?,
+                    pins: pins.clone(),
+                    comment_style: style.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        let options = PrOptions {
+            repo,
+            base: opt.base.clone(),
+            branch: opt.branch.clone(),
+            labels: opt.pr_labels.clone(),
+            draft: opt.draft,
+        };
+        match pr::create_or_update(&api, scanner.events(), &options, &file_pins, &report).await? {
+            PrOutcome::Opened(number, url) => println!("Opened pull request #{}: {}", number, url),
+            PrOutcome::Updated(number, url) => println!("Updated pull request #{}: {}", number, url),
+            PrOutcome::NoChanges { closed: Some(number) } => {
+                println!("Nothing left to fix, closed stale pull request #{}", number)
+            }
+            PrOutcome::NoChanges { closed: None } => println!("Nothing to fix"),
+        }
+    } else if let Some(patch_path) = &opt.emit_patch {
+        let mut diffs = Vec::new();
+        for (path, pins, style) in &files {
+            let content = fs::read_to_string(path)?;
+            let (updated, changes) = rewrite_workflow(&content, pins, style);
+            if !changes.is_empty() {
+                diffs.push(patch::file_diff(&repo_relative(&opt.workspace, path)?, &content, &updated));
+            }
+        }
+        fs::write(patch_path, patch::render(&diffs, &unfixable))
+            .map_err(|e| std::io::Error::other(format!("Failed to write patch {}: {}", patch_path.display(), e)))?;
+        match diffs.len() {
+            0 => println!("Nothing to fix; wrote {} with no changes", patch_path.display()),
+            count => println!("Wrote fixes for {} file(s) to {}; apply them with git apply", count, patch_path.display()),
+        }
+    } else {
+        for (path, pins, style) in &files {
+            let content = fs::read_to_string(path)?;
+            let (updated, changes) = rewrite_workflow(&content, pins, style);
+            if changes.is_empty() {
+                continue;
+            }
+            if opt.dry_run {
+                print_diff(path, &changes);
+                continue;
+            }
+            fs::write(path, updated)?;
+            for change in &changes {
+                println!("{}:{}: {} -> {}", path.display(), change.line, change.from, change.to);
+            }
+        }
+    }
+
+    if unfixable.is_empty() {
+        Ok(ExitCode::Pass.code())
+    } else {
+        println!("\nFindings that could not be fixed automatically:");
+        for action in &unfixable {
+            println!("- {}", action);
+        }
+        Ok(ExitCode::PolicyViolations.code())
+    }
+}
+
+/// The changes to one file as a unified diff with no context lines, one hunk per line rewritten
+fn print_diff(path: &Path, changes: &[PinChange]) {
+    println!("--- a/{}", path.display());
+    println!("+++ b/{}", path.display());
+    for change in changes {
+        println!("@@ -{} +{} @@", change.line, change.line);
+        println!("-{}", change.before);
+        println!("+{}", change.after);
+    }
+}
+
+/// Turn a local workflow path into the path GitHub knows it by
+fn repo_relative(workspace: &Path, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let root = workspace.canonicalize()?;
+    let full = path.canonicalize()?;
+    let relative = full
+        .strip_prefix(&root)
+        .map_err(|_| format!("{} is outside the workspace {}", path.display(), workspace.display()))?;
+    Ok(relative.to_string_lossy().replace('\\', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::Endpoints;
+    use crate::http::{HttpClient, HttpRequest, HttpResponse, ResponseFuture};
+    use serde_json::{Value, json};
+    use std::sync::Mutex;
+
+    /// Answers every request with one annotated tag object and keeps the URLs it was sent
+    struct AnnotatedTag {
+        tag: Value,
+        urls: Mutex<Vec<String>>,
+    }
+
+    impl HttpClient for AnnotatedTag {
+        fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+            self.urls.lock().unwrap().push(request.url);
+            let response = HttpResponse { status: 200, headers: BTreeMap::new(), body: self.tag.to_string() };
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[test]
+    fn branches_are_told_from_tags_by_the_configs_unstable_ref_names() {
+        let unpinned = |action| Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, action, "unpinned".to_string());
+        let findings = [unpinned("acme/tool@v1"), unpinned("acme/tool@main"), unpinned("acme/tool@stable"), unpinned("acme/tool@releases/v1")];
+        assert_eq!(fix_candidates(&findings, &Config::default()), ["acme/tool@v1", "acme/tool@stable"]);
+        let named = Config { unstable_ref_names: Some(vec!["stable".to_string()]), ..Config::default() };
+        assert_eq!(fix_candidates(&findings, &named), ["acme/tool@v1", "acme/tool@main"]);
+    }
+
+    #[tokio::test]
+    async fn the_tag_and_the_tag_object_are_encoded_in_paths() {
+        let client = AnnotatedTag { tag: json!({ "object": { "type": "tag", "sha": "a#b" } }), urls: Mutex::new(Vec::new()) };
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&client, &endpoints, None);
+        let action_ref = ActionRef::from_action_string("acme/tool@v1 #2?x%").unwrap();
+        assert_eq!(resolve_tag_sha(&api, &action_ref).await.unwrap().as_deref(), Some("a#b"));
+        assert_eq!(
+            client.urls.into_inner().unwrap(),
+            [
+                "https://api.github.com/repos/acme/tool/git/ref/tags/v1%20%232%3Fx%25",
+                "https://api.github.com/repos/acme/tool/git/tags/a%23b",
+            ]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Fixture directories answer the scanner's network lookups from disk, for hermetic pipelines.
+//!
+//! Every GitHub lookup maps to one JSON file per repository and lookup type:
+//!
+//! ```text
+//! <dir>/<owner>/<repo>/advisories.json          advisories affecting the repository's action
+//! <dir>/<owner>/<repo>/osv.json                 OSV vulnerabilities of the repository (POST query)
+//! <dir>/<owner>/<repo>/repo.json                repository metadata (visibility, --check-repo-status)
+//! <dir>/<owner>/<repo>/git/ref/tags/<tag>.json  tag refs (fix, review-bump)
+//! <dir>/<owner>/<repo>/git/tags/<sha>.json      annotated tag objects
+//! <dir>/<owner>/<repo>/releases/latest.json     latest release (vendored actions)
+//! <dir>/<owner>/<repo>/contents/<path>.json     file contents, e.g. contents/action.yml.json
+//! <dir>/<owner>/user.json                       account (config check --online)
+//! ```
+//!
+//! Any other `/repos/<owner>/<repo>/<path>` lookup uses `<path>.json` the same way, with the query
+//! string ignored. Each file holds `{"status": 200, "body": <response JSON>}`, plus optional
+//! `"headers"` and the `"scanner_version"` that recorded it; a `"status": 404` file records that
+//! something does not exist. `fixtures record` writes this layout from a real scan.
+
+use crate::github::Endpoints;
+use crate::http::{HttpClient, HttpRequest, HttpResponse, ResponseFuture};
+use crate::osv;
+use crate::version::{NewerFormats, SCANNER_VERSION};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One fixture file
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scanner_version: Option<String>,
+    status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Value,
+}
+
+impl FixtureFile {
+    fn from_response(response: &HttpResponse) -> Self {
+        // Bodies are stored as JSON so fixtures stay readable; anything else is kept verbatim
+        let body = if response.body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&response.body).unwrap_or_else(|_| Value::String(response.body.clone()))
+        };
+        FixtureFile {
+            scanner_version: Some(SCANNER_VERSION.to_string()),
+            status: response.status,
+            headers: response.headers.clone(),
+            body,
+        }
+    }
+
+    fn into_response(self) -> HttpResponse {
+        let body = match self.body {
+            Value::Null => String::new(),
+            Value::String(raw) => raw,
+            body => body.to_string(),
+        };
+        HttpResponse {
+            status: self.status,
+            headers: self.headers,
+            body,
+        }
+    }
+}
+
+/// Where a lookup's fixture lives, relative to the fixture directory
+pub fn fixture_path(url: &str, endpoints: &Endpoints) -> Option<PathBuf> {
+    let path = [&endpoints.api, &endpoints.advisories].into_iter().find_map(|base| url.strip_prefix(base.as_str()))?;
+    let (relative, file) = if let Some(query) = path.strip_prefix("/advisories?") {
+        let repository = query.split('&').find_map(|term| term.strip_prefix("affects="))?;
+        (repository.to_string(), "advisories".to_string())
+    } else if let Some(account) = path.strip_prefix("/users/") {
+        (account.split('?').next()?.to_string(), "user".to_string())
+    } else {
+        let rest = path.strip_prefix("/repos/")?.split('?').next()?;
+        let mut parts = rest.splitn(3, '/');
+        let repository = format!("{}/{}", parts.next()?, parts.next()?);
+        (repository, parts.next().unwrap_or("repo").to_string())
+    };
+    let segments: Vec<&str> = relative.split('/').chain(file.split('/')).collect();
+    // Lookups name repositories and refs; never let one escape the fixture directory
+    if segments.iter().any(|s| s.is_empty() || *s == "." || *s == "..") {
+        return None;
+    }
+    let mut fixture: PathBuf = segments.iter().collect();
+    fixture.set_extension(match fixture.extension() {
+        Some(ext) => format!("{}.json", ext.to_string_lossy()),
+        None => "json".to_string(),
+    });
+    Some(fixture)
+}
+
+/// Where a request's fixture lives: GET lookups by URL, and OSV queries by the package they ask
+/// about. Other requests are not recorded.
+fn fixture_location(request: &HttpRequest, endpoints: &Endpoints) -> Option<PathBuf> {
+    if request.method == Method::GET {
+        return fixture_path(&request.url, endpoints);
+    }
+    if request.method == Method::POST && request.url == endpoints.osv_query_url() {
+        let package = osv::queried_package(request.body.as_ref()?)?;
+        let mut parts = package.split('/');
+        let (owner, repo) = (parts.next()?, parts.next()?);
+        if parts.next().is_some() || [owner, repo].iter().any(|s| s.is_empty() || *s == "." || *s == "..") {
+            return None;
+        }
+        return Some([owner, repo, "osv.json"].iter().collect());
+    }
+    None
+}
+
+/// Answers GET lookups and OSV queries from a fixture directory. Lookups without a fixture go to `fallback`, or
+/// fail when there is none (`--frozen`).
+pub struct FixtureDir {
+    dir: PathBuf,
+    endpoints: Endpoints,
+    fallback: Option<Box<dyn HttpClient>>,
+    /// Checks the version each fixture records, for the scan to report
+    formats: Arc<NewerFormats>,
+}
+
+impl FixtureDir {
+    pub fn new(dir: PathBuf, endpoints: Endpoints, fallback: Option<Box<dyn HttpClient>>, formats: Arc<NewerFormats>) -> Self {
+        FixtureDir { dir, endpoints, fallback, formats }
+    }
+}
+
+impl HttpClient for FixtureDir {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let fixture = fixture_location(&request, &self.endpoints).map(|relative| self.dir.join(relative));
+            if let Some(path) = &fixture
+                && let Ok(content) = fs::read_to_string(path)
+            {
+                let file: FixtureFile = serde_json::from_str(&content)
+                    .map_err(|e| std::io::Error::other(format!("{}: {}", path.display(), e)))?;
+                self.formats.check(&path.display().to_string(), file.scanner_version.as_deref());
+                return Ok(file.into_response());
+            }
+            match &self.fallback {
+                Some(fallback) => fallback.send(request).await,
+                None => Err(std::io::Error::other(match fixture {
+                    Some(path) => format!("no fixture {} for {} {} (--frozen)", path.display(), request.method, request.url),
+                    None => format!("{} {} has no fixture location (--frozen)", request.method, request.url),
+                })),
+            }
+        })
+    }
+}
+
+/// Passes requests through to another client and writes each response that has a fixture location
+/// into the fixture layout
+pub struct DirRecorder {
+    inner: Box<dyn HttpClient>,
+    dir: PathBuf,
+    endpoints: Endpoints,
+}
+
+impl DirRecorder {
+    pub fn new(inner: Box<dyn HttpClient>, dir: PathBuf, endpoints: Endpoints) -> Self {
+        DirRecorder { inner, dir, endpoints }
+    }
+
+    fn write(&self, path: &Path, response: &HttpResponse) -> Result<(), std::io::Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&FixtureFile::from_response(response)).map_err(std::io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+impl HttpClient for DirRecorder {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let relative = fixture_location(&request, &self.endpoints);
+            let response = self.inner.send(request).await?;
+            match relative {
+                // Rate-limit and server errors are not worth replaying
+                Some(_) if response.status().is_server_error() || response.status().as_u16() == 429 => {}
+                Some(relative) => {
+                    let path = self.dir.join(relative);
+                    if let Err(e) = self.write(&path, &response) {
+                        eprintln!("Warning: could not write fixture {}: {}", path.display(), e);
+                    }
+                }
+                None => {}
+            }
+            Ok(response)
+        })
+    }
+}
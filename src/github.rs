@@ -0,0 +1,176 @@
+use crate::http::{HttpClient, HttpRequest};
+use crate::osv;
+use crate::token;
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+
+pub const API_BASE: &str = "https://api.github.com";
+
+/// Environment variable GitHub Actions sets to the API of the server running the workflow
+pub const API_URL_ENV: &str = "GITHUB_API_URL";
+
+/// Every base URL the scan sends lookups to, as `--api-url`, `--advisory-api-url` and
+/// `--osv-api-url` chose them; tests point them all at a local server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The REST API every lookup goes to
+    pub api: String,
+    /// The API GitHub advisory lookups go to
+    pub advisories: String,
+    /// OSV's API
+    pub osv: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints { api: API_BASE.to_string(), advisories: API_BASE.to_string(), osv: osv::API_BASE.to_string() }
+    }
+}
+
+/// An API base such as `https://ghe.example.com/api/v3`, without its trailing slashes
+pub fn parse_api_url(url: &str) -> Result<String, String> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let host = trimmed.strip_prefix("https://").or_else(|| trimmed.strip_prefix("http://"));
+    match host {
+        Some(host) if !host.is_empty() && !host.contains(['?', '#']) => Ok(trimmed.to_string()),
+        _ => Err(format!("{} is not an http(s) API URL such as https://ghe.example.com/api/v3", url)),
+    }
+}
+
+impl Endpoints {
+    /// The API base from `--api-url`, else `$GITHUB_API_URL`, else github.com, the base of GitHub
+    /// advisory lookups from `--advisory-api-url`, else the API base, and OSV's from
+    /// `--osv-api-url`, else osv.dev
+    pub fn from_options(api: Option<&str>, advisories: Option<&str>, osv: Option<&str>) -> Result<Self, String> {
+        let from_env = std::env::var(API_URL_ENV).ok().filter(|url| !url.is_empty());
+        let api = match api.or(from_env.as_deref()) {
+            Some(url) => parse_api_url(url)?,
+            None => API_BASE.to_string(),
+        };
+        let advisories = match advisories {
+            Some(url) => parse_api_url(url)?,
+            None => api.clone(),
+        };
+        let osv = match osv {
+            Some(url) => parse_api_url(url)?,
+            None => osv::API_BASE.to_string(),
+        };
+        Ok(Endpoints { api, advisories, osv })
+    }
+
+    /// Where OSV queries are sent
+    pub fn osv_query_url(&self) -> String {
+        format!("{}/v1/query", self.osv)
+    }
+}
+
+/// Minimal authenticated access to the GitHub REST API
+pub struct GitHubApi<'a> {
+    client: &'a dyn HttpClient,
+    /// The API base every path is appended to
+    base: &'a str,
+    token: Option<&'a str>,
+}
+
+impl<'a> GitHubApi<'a> {
+    pub fn new(client: &'a dyn HttpClient, endpoints: &'a Endpoints, token: Option<&'a str>) -> Self {
+        GitHubApi { client, base: &endpoints.api, token }
+    }
+
+    async fn request(&self, method: Method, path: &str, body: Option<&Value>) -> Result<(StatusCode, Value), std::io::Error> {
+        let url = format!("{}{}", self.base, path);
+        let mut request = HttpRequest::new(method.clone(), &url)
+            .header("Accept", "application/vnd.github+json");
+        if let Some(token) = self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body.filter(|b| !b.is_null()) {
+            request = request.json(body);
+        }
+
+        // Errors deliberately mention only the method and path so credentials never end up in logs
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| std::io::Error::other(format!("{} {} failed: {}", method, path, e)))?;
+        let value = if response.body.is_empty() {
+            Value::Null
+        } else {
+            response
+                .json()
+                .map_err(|e| std::io::Error::other(format!("{} {}: JSON parsing failed: {}", method, path, e)))?
+        };
+        Ok((response.status(), value))
+    }
+
+    /// GET a resource, returning `None` when it does not exist
+    pub async fn get(&self, path: &str) -> Result<Option<Value>, std::io::Error> {
+        let response = match self.request(Method::GET, path, None).await? {
+            // A renamed or transferred repository answers with where it went, which clients that
+            // do not follow redirects themselves pass through
+            (status, value) if status.is_redirection() => match value["url"].as_str().and_then(|url| url.strip_prefix(self.base)) {
+                Some(moved) => self.request(Method::GET, moved, None).await?,
+                None => (status, value),
+            },
+            response => response,
+        };
+        match response {
+            (StatusCode::NOT_FOUND, _) => Ok(None),
+            (status, value) if status.is_success() => Ok(Some(value)),
+            (status, value) => Err(api_error(&Method::GET, path, status, &value)),
+        }
+    }
+
+    /// Send a request with a JSON body, failing on any non-success status
+    pub async fn send(&self, method: Method, path: &str, body: &Value) -> Result<Value, std::io::Error> {
+        match self.request(method.clone(), path, Some(body)).await? {
+            (status, value) if status.is_success() => Ok(value),
+            (status, value) => Err(api_error(&method, path, status, &value)),
+        }
+    }
+
+    /// POST a JSON body and return the status with the response, for callers that expect some
+    /// failures and handle them themselves
+    pub async fn post(&self, path: &str, body: &Value) -> Result<(StatusCode, Value), std::io::Error> {
+        self.request(Method::POST, path, Some(body)).await
+    }
+}
+
+pub fn api_error(method: &Method, path: &str, status: StatusCode, body: &Value) -> std::io::Error {
+    // Servers have been known to echo the credentials they refused
+    let detail = token::scrub(body.get("message").and_then(|m| m.as_str()).unwrap_or(""), &[]);
+    std::io::Error::other(format!("{} {} returned {} {}", method, path, status, detail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_urls_lose_their_trailing_slashes() {
+        for (url, parsed) in [
+            ("https://ghe.example.com/api/v3", "https://ghe.example.com/api/v3"),
+            ("https://ghe.example.com/api/v3/", "https://ghe.example.com/api/v3"),
+            ("https://api.github.com//", "https://api.github.com"),
+            (" http://127.0.0.1:8080 ", "http://127.0.0.1:8080"),
+        ] {
+            assert_eq!(parse_api_url(url).as_deref(), Ok(parsed), "{}", url);
+        }
+        for url in ["ghe.example.com/api/v3", "ftp://ghe.example.com", "https://", "https://ghe.example.com/api?v=3", ""] {
+            assert!(parse_api_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn advisory_lookups_follow_the_api_url_unless_set_apart() {
+        let ghes = Endpoints::from_options(Some("https://ghe.example.com/api/v3/"), None, None).unwrap();
+        assert_eq!(ghes, Endpoints { api: "https://ghe.example.com/api/v3".to_string(), advisories: "https://ghe.example.com/api/v3".to_string(), osv: osv::API_BASE.to_string() });
+
+        let split = Endpoints::from_options(Some("https://ghe.example.com/api/v3"), Some("https://api.github.com/"), Some("http://127.0.0.1:9000/osv")).unwrap();
+        assert_eq!((split.api.as_str(), split.advisories.as_str()), ("https://ghe.example.com/api/v3", "https://api.github.com"));
+        assert_eq!(split.osv_query_url(), "http://127.0.0.1:9000/osv/v1/query");
+
+        assert!(Endpoints::from_options(Some("https://ghe.example.com"), Some("nope"), None).is_err());
+    }
+}
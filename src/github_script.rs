@@ -0,0 +1,253 @@
+use crate::Workflow;
+use crate::config::Config;
+use crate::dispatch::source_line;
+use crate::report::{Finding, Level, Rule};
+use crate::rules::{key_line, step_label};
+use regex::Regex;
+use serde_yaml::Value;
+
+/// Inline scripts longer than this are flagged unless `github_script_max_lines` says otherwise
+pub const DEFAULT_MAX_LINES: usize = 50;
+
+/// Events whose workflows run with a write token and secrets while handling other people's input
+const PRIVILEGED_EVENTS: [&str; 6] = [
+    "pull_request_target",
+    "workflow_run",
+    "issue_comment",
+    "issues",
+    "discussion",
+    "discussion_comment",
+];
+
+/// Expressions that expand attacker-controlled text into the script source before it runs.
+/// Reading the same values through `context.payload` at runtime is safe and not matched.
+pub fn dangerous_expression() -> Regex {
+    Regex::new(concat!(
+        r"\$\{\{[^}]*\bgithub\.(?:head_ref|event\.(?:",
+        r"(?:issue|pull_request|discussion)\.(?:title|body)",
+        r"|(?:comment|review|review_comment)\.body",
+        r"|pull_request\.head\.(?:ref|label)",
+        r"|head_commit\.(?:message|author\.(?:name|email))",
+        r"|commits\b[^}]*\.(?:message|author)",
+        r"|workflow_run\.(?:head_branch|display_title|head_commit\.message)",
+        r"))"
+    ))
+    .unwrap()
+}
+
+/// `require()` of a path in the checkout rather than a module shipped with the action
+fn workspace_require() -> Regex {
+    Regex::new(r#"require\(\s*(?:['"`]\.{0,2}/|`\$\{\s*process\.env\.GITHUB_WORKSPACE|process\.env\.GITHUB_WORKSPACE|['"`]\$\{\{\s*github\.workspace)"#).unwrap()
+}
+
+fn triggers(workflow: &Value) -> Vec<&str> {
+    let events: Vec<&str> = match workflow.get("on") {
+        Some(Value::String(event)) => vec![event.as_str()],
+        Some(Value::Sequence(events)) => events.iter().filter_map(|e| e.as_str()).collect(),
+        Some(Value::Mapping(events)) => events.keys().filter_map(|e| e.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    events.into_iter().filter(|e| PRIVILEGED_EVENTS.contains(e)).collect()
+}
+
+/// Whether the job checks out the pull request's head, which puts PR-controlled files on disk
+fn checks_out_pull_request(job: &Value) -> bool {
+    let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else {
+        return false;
+    };
+    steps.iter().any(|step| {
+        let is_checkout = step.get("uses").and_then(|v| v.as_str()).is_some_and(|u| u.starts_with("actions/checkout@"));
+        let reference = step.get("with").and_then(|w| w.get("ref")).and_then(|v| v.as_str()).unwrap_or("");
+        is_checkout && (reference.contains("pull_request.head") || reference.contains("github.head_ref"))
+    })
+}
+
+/// Opt-in `--scan-github-script` rule: in workflows triggered by privileged events, flag
+/// `actions/github-script` steps that run long inline scripts, interpolate attacker-controlled
+/// expressions into the script, or (under `pull_request_target`) `require()` files from a checkout
+/// of the pull request. Short scripts that read the event through `context` are left alone.
+pub fn check(workflow: &Workflow, config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let events = triggers(&workflow.value);
+    if events.is_empty() {
+        return findings;
+    }
+    let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else {
+        return findings;
+    };
+    let max_lines = config.github_script_max_lines.unwrap_or(DEFAULT_MAX_LINES);
+    let dangerous = dangerous_expression();
+    let requires = workspace_require();
+    let trigger = events.join(" and ");
+
+    for (job_id, job) in jobs {
+        let job_id = job_id.as_str().unwrap_or("<unnamed>");
+        let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else { continue };
+        let job_line = key_line(&workflow.source, &["jobs", job_id]);
+        let pull_request_checkout = events.contains(&"pull_request_target") && checks_out_pull_request(job);
+        for (index, step) in steps.iter().enumerate() {
+            let Some(action) = step.get("uses").and_then(|v| v.as_str()) else { continue };
+            if !action.starts_with("actions/github-script@") {
+                continue;
+            }
+            let Some(script) = step.get("with").and_then(|w| w.get("script")).and_then(|v| v.as_str()) else { continue };
+            let lines: Vec<&str> = script.lines().filter(|l| !l.trim().is_empty()).collect();
+
+            let mut problems: Vec<(Level, String, &str)> = Vec::new();
+            if let Some(line) = lines.iter().find(|l| dangerous.is_match(l)) {
+                problems.push((Level::Error, "interpolates attacker-controlled event data into the script".to_string(), line));
+            }
+            if pull_request_checkout && let Some(line) = lines.iter().find(|l| requires.is_match(l)) {
+                problems.push((Level::Error, "requires a file from the checked-out pull request".to_string(), line));
+            }
+            if lines.len() > max_lines {
+                let problem = format!("runs a {}-line inline script (github_script_max_lines is {})", lines.len(), max_lines);
+                problems.push((Level::Warning, problem, lines[0]));
+            }
+
+            for (level, problem, line) in problems {
+                let message = format!(
+                    "Job {}, {}, {} in a workflow triggered by {}, with full token access: `{}`",
+                    job_id,
+                    step_label(step, index),
+                    problem,
+                    trigger,
+                    line.trim()
+                );
+                let line_number = source_line(&workflow.source, line, job_line).or(job_line);
+                findings.push(
+                    Finding::new(Rule::GithubScript, level, action, message)
+                        .with_job(job_id)
+                        .at(&workflow.path, line_number),
+                );
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workflow triggered by `on` whose `build` job runs `steps`, given as YAML list items
+    fn workflow(on: &str, steps: &str) -> Workflow {
+        let source = format!("on: {}\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n{}", on, steps);
+        Workflow::parse("ci.yml", &source).unwrap()
+    }
+
+    /// A github-script step running `script`, given as the lines of a literal block
+    fn script_step(script: &[&str]) -> String {
+        let body: String = script.iter().map(|line| format!("            {}\n", line)).collect();
+        format!("      - name: triage\n        uses: actions/github-script@v7\n        with:\n          script: |\n{}", body)
+    }
+
+    const PR_CHECKOUT: &str = "      - uses: actions/checkout@v4\n        with:\n          ref: ${{ github.event.pull_request.head.sha }}\n";
+
+    /// The level, line and quoted script line of each finding
+    fn flagged(workflow: &Workflow, config: &Config) -> Vec<(Level, Option<usize>, String)> {
+        check(workflow, config)
+            .into_iter()
+            .map(|f| (f.level, f.line, f.message.rsplit_once(": `").unwrap().1.strip_suffix('`').unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn benign_one_liners_are_not_flagged() {
+        for script in [
+            "core.setOutput('title', context.payload.pull_request.title)",
+            "await github.rest.issues.addLabels({ ...context.repo, issue_number: context.issue.number, labels: ['triage'] })",
+            "const sha = '${{ github.event.pull_request.head.sha }}'",
+            "console.log('${{ github.event.pull_request.number }}')",
+            "const { run } = require('@actions/core')",
+        ] {
+            let workflow = workflow("pull_request_target", &script_step(&[script]));
+            assert!(flagged(&workflow, &Config::default()).is_empty(), "{} was flagged", script);
+        }
+    }
+
+    #[test]
+    fn dangerous_expressions_are_flagged_with_the_offending_line() {
+        for expression in [
+            "github.event.pull_request.title",
+            "github.event.issue.body",
+            "github.event.comment.body",
+            "github.event.review.body",
+            "github.event.pull_request.head.ref",
+            "github.head_ref",
+            "github.event.head_commit.message",
+            "github.event.workflow_run.head_branch",
+        ] {
+            let line = format!("const text = `${{{{ {} }}}}`", expression);
+            let workflow = workflow("pull_request_target", &script_step(&["const labels = []", &line]));
+            assert_eq!(flagged(&workflow, &Config::default()), [(Level::Error, Some(11), line.clone())], "{}", expression);
+        }
+    }
+
+    #[test]
+    fn only_privileged_triggers_are_checked() {
+        let steps = script_step(&["const title = '${{ github.event.issue.title }}'"]);
+        for on in ["pull_request_target", "[push, issue_comment]", "{issues: {types: [opened]}}", "workflow_run"] {
+            assert_eq!(flagged(&workflow(on, &steps), &Config::default()).len(), 1, "{}", on);
+        }
+        for on in ["push", "pull_request", "[pull_request, workflow_dispatch]"] {
+            assert!(flagged(&workflow(on, &steps), &Config::default()).is_empty(), "{}", on);
+        }
+    }
+
+    #[test]
+    fn requiring_checkout_files_is_only_flagged_after_a_pull_request_checkout_under_pull_request_target() {
+        let require = script_step(&["const script = require('./.github/scripts/triage.js')", "await script({ github, context })"]);
+        let with_checkout = format!("{}{}", PR_CHECKOUT, require);
+        let found = flagged(&workflow("pull_request_target", &with_checkout), &Config::default());
+        assert_eq!(found, [(Level::Error, Some(13), "const script = require('./.github/scripts/triage.js')".to_string())]);
+        for line in [
+            "const script = require(`${process.env.GITHUB_WORKSPACE}/triage.js`)",
+            "const script = require(process.env.GITHUB_WORKSPACE + '/triage.js')",
+            "const script = require('${{ github.workspace }}/triage.js')",
+        ] {
+            let steps = format!("{}{}", PR_CHECKOUT, script_step(&[line]));
+            assert_eq!(flagged(&workflow("pull_request_target", &steps), &Config::default()).len(), 1, "{}", line);
+        }
+
+        // The base branch's own files, or a workflow run that checks nothing out, are not PR-controlled
+        let base_checkout = format!("      - uses: actions/checkout@v4\n{}", require);
+        assert!(flagged(&workflow("pull_request_target", &base_checkout), &Config::default()).is_empty());
+        assert!(flagged(&workflow("pull_request_target", &require), &Config::default()).is_empty());
+        assert!(flagged(&workflow("issue_comment", &with_checkout), &Config::default()).is_empty());
+    }
+
+    #[test]
+    fn scripts_over_the_limit_warn_at_their_first_line() {
+        let lines: Vec<String> = (0..DEFAULT_MAX_LINES).map(|n| format!("core.info('{}')", n)).collect();
+        let mut script: Vec<&str> = lines.iter().map(String::as_str).collect();
+        script.insert(1, "");
+        let at_limit = workflow("pull_request_target", &script_step(&script));
+        assert!(flagged(&at_limit, &Config::default()).is_empty(), "blank lines do not count");
+
+        script.push("core.info('one more')");
+        let over = workflow("pull_request_target", &script_step(&script));
+        let found = check(&over, &Config::default());
+        let [finding] = found.as_slice() else { panic!("{:?}", found) };
+        assert_eq!((finding.rule, finding.level, finding.line), (Rule::GithubScript, Level::Warning, Some(10)));
+        assert_eq!(
+            finding.message,
+            "Job build, \"triage\", runs a 51-line inline script (github_script_max_lines is 50) in a workflow triggered by pull_request_target, with full token access: `core.info('0')`"
+        );
+
+        let config = Config { github_script_max_lines: Some(2), ..Config::default() };
+        let short = workflow("pull_request_target", &script_step(&["core.info('a')", "core.info('b')", "core.info('c')"]));
+        assert_eq!(flagged(&short, &config), [(Level::Warning, Some(10), "core.info('a')".to_string())]);
+        let config = Config { github_script_max_lines: Some(3), ..Config::default() };
+        assert!(flagged(&short, &config).is_empty());
+    }
+
+    #[test]
+    fn each_problem_in_one_script_is_its_own_finding() {
+        let script = script_step(&["const body = '${{ github.event.issue.body }}'", "require('./triage.js')(body)"]);
+        let steps = format!("{}{}", PR_CHECKOUT, script);
+        let config = Config { github_script_max_lines: Some(1), ..Config::default() };
+        let levels: Vec<Level> = flagged(&workflow("pull_request_target", &steps), &config).into_iter().map(|(level, _, _)| level).collect();
+        assert_eq!(levels, [Level::Error, Level::Error, Level::Warning]);
+    }
+}
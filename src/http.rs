@@ -0,0 +1,368 @@
+use crate::version::{self, NewerFormats};
+use reqwest::{Client, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Response headers worth keeping in fixtures; everything else is noise or sensitive
+const RECORDED_HEADERS: [&str; 4] = ["retry-after", "link", "x-ratelimit-remaining", "x-ratelimit-reset"];
+
+pub type ResponseFuture<'a> = Pin<Box<dyn Future<Output = Result<HttpResponse, std::io::Error>> + Send + 'a>>;
+
+/// A request to send through an `HttpClient`
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// Kept apart from `headers` so tokens never reach a fixture file
+    pub bearer: Option<String>,
+    pub body: Option<Value>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: &str) -> Self {
+        HttpRequest {
+            method,
+            url: url.to_string(),
+            headers: Vec::new(),
+            bearer: None,
+            body: None,
+        }
+    }
+
+    pub fn get(url: &str) -> Self {
+        HttpRequest::new(Method::GET, url)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn bearer_auth(mut self, token: &str) -> Self {
+        self.bearer = Some(token.to_string());
+        self
+    }
+
+    pub fn json(mut self, body: &Value) -> Self {
+        self.body = Some(body.clone());
+        self
+    }
+}
+
+impl std::fmt::Debug for HttpRequest {
+    /// Shows whether a token is attached, never the token
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &self.headers)
+            .field("bearer", &self.bearer.as_ref().map(|_| "<redacted>"))
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// A fully read response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// A response header, looked up case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.body)
+    }
+}
+
+/// Everything the scanner sends over HTTP goes through this, so networked behavior can be
+/// recorded once and replayed without network access
+pub trait HttpClient: Send + Sync {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_>;
+}
+
+/// The production client
+pub struct ReqwestClient(pub Client);
+
+impl HttpClient for ReqwestClient {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let mut builder = self.0.request(request.method, &request.url);
+            for (name, value) in &request.headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            if let Some(token) = &request.bearer {
+                builder = builder.bearer_auth(token);
+            }
+            if let Some(body) = &request.body {
+                builder = builder.json(body);
+            }
+            // Errors never include the URL, which may carry query parameters worth keeping out of logs
+            let response = builder
+                .send()
+                .await
+                .map_err(|e| std::io::Error::other(e.without_url().to_string()))?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter(|(name, _)| RECORDED_HEADERS.contains(&name.as_str()))
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect();
+            let body = response
+                .text()
+                .await
+                .map_err(|e| std::io::Error::other(e.without_url().to_string()))?;
+            Ok(HttpResponse { status, headers, body })
+        })
+    }
+}
+
+/// `--offline`: the network is never reached; a request that gets here fails at once rather than
+/// waiting on a connection an air-gapped runner cannot make
+pub struct Offline;
+
+impl HttpClient for Offline {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move { Err(std::io::Error::other(format!("{} not sent: --offline disables network access", request.url))) })
+    }
+}
+
+/// Attaches the `--token` to GitHub API requests that do not carry a token of their own. Without
+/// one, warns the first time the API is used that the unauthenticated rate limit applies.
+pub struct GitHubAuth {
+    inner: Box<dyn HttpClient>,
+    token: Option<String>,
+    /// The API base the token is sent to
+    api: String,
+    warned: AtomicBool,
+}
+
+impl GitHubAuth {
+    pub fn new(inner: Box<dyn HttpClient>, token: Option<String>, api: &str) -> Self {
+        GitHubAuth {
+            inner,
+            token: token.filter(|t| !t.is_empty()),
+            api: api.to_string(),
+            warned: AtomicBool::new(false),
+        }
+    }
+}
+
+impl HttpClient for GitHubAuth {
+    fn send(&self, mut request: HttpRequest) -> ResponseFuture<'_> {
+        // The token is for the server --api-url names, not for a separate advisory API
+        if request.bearer.is_none() && request.url.starts_with(&self.api) {
+            match &self.token {
+                Some(token) => request.bearer = Some(token.clone()),
+                None if !self.warned.swap(true, Ordering::Relaxed) => eprintln!(
+                    "Warning: no GitHub token (--token, --token-file or GITHUB_TOKEN); unauthenticated API requests are limited to 60 an hour"
+                ),
+                None => {}
+            }
+        }
+        self.inner.send(request)
+    }
+}
+
+/// One request/response pair in a fixture file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+    response: HttpResponse,
+}
+
+impl Interaction {
+    fn matches(&self, request: &HttpRequest) -> bool {
+        self.method == request.method.as_str() && self.url == request.url && self.body == request.body
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Fixture {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scanner_version: Option<String>,
+    interactions: Vec<Interaction>,
+}
+
+/// Passes requests through to another client and appends every exchange to a fixture file
+pub struct Recorder {
+    inner: Box<dyn HttpClient>,
+    path: PathBuf,
+    fixture: Mutex<Fixture>,
+}
+
+impl Recorder {
+    pub fn new(inner: Box<dyn HttpClient>, path: PathBuf) -> Self {
+        Recorder {
+            inner,
+            path,
+            fixture: Mutex::new(Fixture {
+                scanner_version: Some(version::SCANNER_VERSION.to_string()),
+                interactions: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl HttpClient for Recorder {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let response = self.inner.send(request.clone()).await?;
+            let mut fixture = self.fixture.lock().unwrap_or_else(|e| e.into_inner());
+            fixture.interactions.push(Interaction {
+                method: request.method.to_string(),
+                url: request.url,
+                body: request.body,
+                response: response.clone(),
+            });
+            // Rewritten after every exchange so an interrupted run still leaves a usable fixture
+            let json = serde_json::to_string_pretty(&*fixture).map_err(std::io::Error::other)?;
+            fs::write(&self.path, json + "\n")?;
+            Ok(response)
+        })
+    }
+}
+
+/// Answers requests from a fixture file and never touches the network.
+///
+/// Identical requests are answered in recorded order; once those run out the last recorded
+/// answer is repeated.
+pub struct Replayer {
+    interactions: Vec<Interaction>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl Replayer {
+    /// Load a fixture file, checking the version it records against `formats`
+    pub fn load(path: &PathBuf, formats: &NewerFormats) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let fixture: Fixture = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+        formats.check(&path.display().to_string(), fixture.scanner_version.as_deref());
+        let used = vec![false; fixture.interactions.len()];
+        Ok(Replayer {
+            interactions: fixture.interactions,
+            used: Mutex::new(used),
+        })
+    }
+}
+
+impl HttpClient for Replayer {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+            let matching: Vec<usize> = (0..self.interactions.len())
+                .filter(|&i| self.interactions[i].matches(&request))
+                .collect();
+            let index = matching
+                .iter()
+                .copied()
+                .find(|&i| !used[i])
+                .or_else(|| matching.last().copied())
+                .ok_or_else(|| std::io::Error::other(format!("no recorded response for {} {}", request.method, request.url)))?;
+            used[index] = true;
+            Ok(self.interactions[index].response.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A replayer of `interactions`, loaded from a fixture file that is removed once read
+    fn replayer(name: &str, interactions: Value) -> Replayer {
+        let path = std::env::temp_dir().join(format!("scanner-http-{}-{}.json", std::process::id(), name));
+        fs::write(&path, json!({ "interactions": interactions }).to_string()).unwrap();
+        let replayer = Replayer::load(&path, &NewerFormats::default()).unwrap();
+        fs::remove_file(path).unwrap();
+        replayer
+    }
+
+    fn answer(url: &str, body: &str) -> Value {
+        json!({ "method": "GET", "url": url, "response": { "status": 200, "body": body } })
+    }
+
+    async fn body(client: &dyn HttpClient, request: HttpRequest) -> String {
+        client.send(request).await.unwrap().body
+    }
+
+    #[tokio::test]
+    async fn identical_requests_are_answered_in_order_then_the_last_repeats() {
+        let replayer = replayer("order", json!([answer("https://x/a", "1"), answer("https://x/b", "b"), answer("https://x/a", "2")]));
+        let answers = [
+            body(&replayer, HttpRequest::get("https://x/a")).await,
+            body(&replayer, HttpRequest::get("https://x/a")).await,
+            body(&replayer, HttpRequest::get("https://x/a")).await,
+            body(&replayer, HttpRequest::get("https://x/b")).await,
+        ];
+        assert_eq!(answers, ["1", "2", "2", "b"]);
+    }
+
+    #[tokio::test]
+    async fn requests_match_on_method_url_and_body() {
+        let post = json!({ "method": "POST", "url": "https://x/q", "body": { "v": 1 }, "response": { "status": 404 } });
+        let replayer = replayer("match", json!([post]));
+        let request = HttpRequest::new(Method::POST, "https://x/q");
+        assert_eq!(replayer.send(request.clone().json(&json!({ "v": 1 }))).await.unwrap().status, 404);
+        for unmatched in [request.clone().json(&json!({ "v": 2 })), request, HttpRequest::get("https://x/q")] {
+            let error = replayer.send(unmatched).await.unwrap_err().to_string();
+            assert!(error.starts_with("no recorded response for "), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn one_replayer_answers_concurrent_requests() {
+        let interactions: Vec<Value> = (0..16).map(|i| answer(&format!("https://x/{}", i), &i.to_string())).collect();
+        let replayer = std::sync::Arc::new(replayer("concurrent", Value::from(interactions)));
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let replayer = replayer.clone();
+                tokio::spawn(async move { body(&*replayer, HttpRequest::get(&format!("https://x/{}", i))).await })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await.unwrap(), i.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn a_recording_replays_and_keeps_no_token() {
+        let source = replayer("source", json!([answer("https://x/a", "[1]")]));
+        let recorded = std::env::temp_dir().join(format!("scanner-http-{}-recorded.json", std::process::id()));
+        let recorder = Recorder::new(Box::new(source), recorded.clone());
+        assert_eq!(body(&recorder, HttpRequest::get("https://x/a").bearer_auth("secret-token")).await, "[1]");
+
+        let content = fs::read_to_string(&recorded).unwrap();
+        assert!(!content.contains("secret-token"), "{}", content);
+        assert!(content.contains(version::SCANNER_VERSION), "{}", content);
+        let replayer = Replayer::load(&recorded, &NewerFormats::default()).unwrap();
+        fs::remove_file(&recorded).unwrap();
+        assert_eq!(replayer.send(HttpRequest::get("https://x/a")).await.unwrap().json::<Vec<u8>>().unwrap(), [1]);
+    }
+}
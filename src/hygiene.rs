@@ -0,0 +1,71 @@
+use crate::{ActionRef, matches_trust_entry};
+use crate::config::{Config, EffectiveConfig};
+use crate::report::{Finding, Level, Rule};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The actions used under one config, and the config itself
+struct Governed<'a> {
+    config: &'a Config,
+    actions: BTreeSet<&'a str>,
+}
+
+fn owner(action: &str) -> Option<&str> {
+    if action.starts_with("./") || action.starts_with("docker://") {
+        return None;
+    }
+    action.split('/').next().filter(|owner| !owner.is_empty())
+}
+
+/// Config entries that matched no action in the workflows they govern.
+///
+/// Workflows are grouped by the config that governs them (the root config, or a scoped config
+/// over it), so an entry counts as used if any workflow under that config needed it.
+pub fn unused_entries<'a>(
+    governed: impl IntoIterator<Item = (&'a EffectiveConfig, String, &'a BTreeSet<String>)>,
+) -> Vec<Finding> {
+    let mut groups: BTreeMap<String, Governed> = BTreeMap::new();
+    for (effective, description, actions) in governed {
+        let group = groups.entry(description).or_insert_with(|| Governed {
+            config: &effective.config,
+            actions: BTreeSet::new(),
+        });
+        group.actions.extend(actions.iter().map(String::as_str));
+    }
+
+    let mut findings = Vec::new();
+    for (description, group) in &groups {
+        let mut unused = |key: &str, entry: &str| {
+            let message = format!("{} entry {} in {} matched no action", key, entry, description);
+            findings.push(Finding::scan_level(Rule::UnusedConfigEntry, Level::Note, message));
+        };
+        let owners: BTreeSet<&str> = group.actions.iter().filter_map(|a| owner(a)).collect();
+        for trusted in group.config.trusted_owners.iter().flatten() {
+            if !owners.iter().any(|owner| matches_trust_entry(trusted, owner)) {
+                unused("trusted_owners", trusted);
+            }
+        }
+        let repositories: BTreeSet<String> = group.actions.iter().filter_map(|a| ActionRef::from_action_string(a)).map(|r| r.full_name()).collect();
+        for trusted in group.config.trusted_repos.iter().flatten() {
+            if !repositories.iter().any(|repository| matches_trust_entry(trusted, repository)) {
+                unused("trusted_repos", trusted);
+            }
+        }
+        for critical in group.config.critical_dependencies.iter().flatten() {
+            if !group.actions.contains(critical.as_str()) {
+                unused("critical_dependencies", critical);
+            }
+        }
+        // A pattern every action of which a more specific one takes is unused too
+        for pattern in group.config.pin_policies.iter().flat_map(|p| p.keys()) {
+            if !group.actions.iter().any(|action| group.config.pin_policy(action).is_some_and(|(p, _)| p == pattern)) {
+                unused("pin_policies", pattern);
+            }
+        }
+        for local in group.config.vendored_actions.iter().flat_map(|m| m.keys()) {
+            if !group.actions.contains(local.as_str()) {
+                unused("vendored_actions", local);
+            }
+        }
+    }
+    findings
+}
@@ -0,0 +1,56 @@
+use crate::ActionRef;
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::report::{Finding, Level, Rule};
+use crate::scanner::ScanEvents;
+use serde_json::json;
+
+/// Findings that ask for a SHA pin, which an immutable release makes unnecessary
+const SHA_REQUIREMENTS: [Rule; 2] = [Rule::UntrustedOwnerNoSha, Rule::TagPinnedCritical];
+
+/// Whether the release for the pinned tag is immutable, through the cache. `None` when the tag has
+/// no release, or the API does not say: floating tags such as `v4` are not releases, so they never
+/// qualify.
+async fn is_immutable(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<bool>, std::io::Error> {
+    let key = format!("{}@{}", action_ref.full_name(), action_ref.version);
+    let value = match cache.get("immutable-releases", &key) {
+        Cached::Hit(value) => value,
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {
+            let path = format!("/repos/{}/releases/tags/{}", action_ref.full_name(), action_ref.version);
+            let Some(release) = api.get(&path).await? else {
+                cache.put_negative("immutable-releases", &key);
+                return Ok(None);
+            };
+            let value = json!({ "immutable": release["immutable"] });
+            cache.put("immutable-releases", &key, &value);
+            value
+        }
+    };
+    Ok(value["immutable"].as_bool())
+}
+
+/// `exempt_immutable_releases`: the note that replaces the SHA-pin findings among `findings` for a
+/// tag whose release is immutable, for `drop_exempted` to act on. Anything short of a definite
+/// answer keeps the findings, and a failed lookup is a warning through `events`.
+pub async fn exempt(api: &GitHubApi<'_>, cache: &DiskCache, events: &dyn ScanEvents, action: &str, action_ref: &ActionRef, findings: &[Finding]) -> Option<Finding> {
+    if action_ref.is_commit_sha() || !findings.iter().any(|f| f.action == action && SHA_REQUIREMENTS.contains(&f.rule)) {
+        return None;
+    }
+    match is_immutable(api, cache, action_ref).await {
+        Ok(Some(true)) => {
+            let message = format!("{} needs no SHA pin: tag is immutable ({} release)", action, action_ref.version);
+            Some(Finding::new(Rule::ImmutableRelease, Level::Note, action, message))
+        }
+        Ok(_) => None,
+        Err(e) => {
+            events.warning(&format!("Warning: could not check whether {} is an immutable release: {}", action, e));
+            None
+        }
+    }
+}
+
+/// Drop the SHA-pin findings of an action `exempt` found to be an immutable release
+pub fn drop_exempted(findings: &mut Vec<Finding>, action: &str) {
+    findings.retain(|f| f.action != action || !SHA_REQUIREMENTS.contains(&f.rule));
+}
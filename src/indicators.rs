@@ -0,0 +1,107 @@
+use crate::ActionRef;
+use crate::report::{Finding, Level, Rule};
+use crate::severity::Severity;
+use serde::Deserialize;
+
+/// Known-compromised action versions shipped with the scanner
+const BUILTIN: &str = include_str!("../data/compromised-actions.yaml");
+
+/// One compromised action and the refs that were malicious
+#[derive(Debug, Deserialize)]
+pub struct Indicator {
+    /// `owner/repo`
+    pub action: String,
+    /// Commit SHAs or refs published while the action was compromised
+    pub refs: Vec<String>,
+    pub summary: String,
+    pub reference: String,
+}
+
+/// Parse the compiled-in indicator table
+pub fn builtin() -> Result<Vec<Indicator>, serde_yaml::Error> {
+    serde_yaml::from_str(BUILTIN)
+}
+
+impl Indicator {
+    /// Owner/repo and refs are compared case-insensitively, as GitHub resolves them that way
+    fn matches(&self, action_ref: &ActionRef) -> bool {
+        self.action.eq_ignore_ascii_case(&action_ref.full_name())
+            && self.refs.iter().any(|r| r.eq_ignore_ascii_case(&action_ref.version))
+    }
+}
+
+/// Report `action`, at critical severity, if it is one of the known-compromised versions
+pub fn check(action: &str, indicators: &[Indicator]) -> Option<Finding> {
+    let action_ref = ActionRef::from_action_string(action)?;
+    let indicator = indicators.iter().find(|i| i.matches(&action_ref))?;
+    let finding = Finding::new(
+        Rule::CompromisedVersion,
+        Level::Error,
+        action,
+        format!(
+            "Action {} is a known-compromised version: {} ({})",
+            action, indicator.summary, indicator.reference
+        ),
+    );
+    Some(finding.with_severity(Some(Severity::Critical)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGED_FILES: &str = "0e58ed8671d6b60d0890c21b07f8835ace038e67";
+
+    #[test]
+    fn the_builtin_list_parses_and_every_entry_is_complete() {
+        let indicators = builtin().unwrap();
+        assert!(!indicators.is_empty());
+        for indicator in &indicators {
+            let name = &indicator.action;
+            assert!(
+                ActionRef::from_action_string(&format!("{}@v1", name)).is_some_and(|r| r.full_name() == *name && r.path.is_none()),
+                "{} is not owner/repo",
+                name
+            );
+            assert!(!indicator.refs.is_empty(), "{} lists no refs", name);
+            for reference in &indicator.refs {
+                assert!(!reference.is_empty() && !reference.contains(char::is_whitespace), "{}: ref {:?}", name, reference);
+            }
+            assert!(!indicator.summary.trim().is_empty(), "{} has no summary", name);
+            assert!(indicator.reference.starts_with("https://"), "{}: reference {}", name, indicator.reference);
+        }
+        let mut names: Vec<String> = indicators.iter().map(|i| i.action.to_ascii_lowercase()).collect();
+        names.sort();
+        let count = names.len();
+        names.dedup();
+        assert_eq!(names.len(), count, "an action is listed twice");
+    }
+
+    #[test]
+    fn a_listed_ref_is_reported_at_critical_severity_with_its_reference() {
+        let indicators = builtin().unwrap();
+        let action = format!("tj-actions/changed-files@{}", CHANGED_FILES);
+        let finding = check(&action, &indicators).unwrap();
+        assert_eq!((finding.rule, finding.level, finding.severity), (Rule::CompromisedVersion, Level::Error, Some(Severity::Critical)));
+        assert_eq!(finding.action, action);
+        assert!(finding.message.contains("CVE-2025-30066"), "{}", finding.message);
+        assert!(finding.message.ends_with("(https://github.com/advisories/GHSA-mrrh-fwg8-r2c3)"), "{}", finding.message);
+    }
+
+    #[test]
+    fn matching_ignores_case_and_needs_a_listed_ref() {
+        let indicators = builtin().unwrap();
+        let upper = format!("TJ-Actions/Changed-Files@{}", CHANGED_FILES.to_ascii_uppercase());
+        assert!(check(&upper, &indicators).is_some());
+        assert!(check(&format!("tj-actions/changed-files/sub@{}", CHANGED_FILES), &indicators).is_some(), "a path within the repository is the same code");
+        assert!(check("tj-actions/changed-files@v46.0.1", &indicators).is_none());
+        assert!(check(&format!("tj-actions/other@{}", CHANGED_FILES), &indicators).is_none());
+        assert!(check("./.github/actions/changed-files", &indicators).is_none());
+        assert!(check("docker://alpine:3.19", &indicators).is_none());
+    }
+
+    #[test]
+    fn an_empty_list_matches_nothing() {
+        assert!(check(&format!("tj-actions/changed-files@{}", CHANGED_FILES), &[]).is_none());
+    }
+}
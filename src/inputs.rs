@@ -0,0 +1,186 @@
+use crate::ActionRef;
+use crate::bump::PinBump;
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::pr::decode_content;
+use crate::report::{Finding, Level, Rule};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+/// Declared inputs of an action: name -> whether it is required
+type Inputs = BTreeMap<String, bool>;
+
+/// The `inputs` of the action's metadata file at a ref, through the cache. `None` when the
+/// action has no metadata file there.
+async fn inputs_at(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef, path: &str, reference: &str) -> Result<Option<Inputs>, std::io::Error> {
+    let key = format!("{}/{}@{}", action_ref.full_name(), path, reference);
+    let value = match cache.get("action-inputs", &key) {
+        Cached::Hit(value) => value,
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {
+            let mut found = None;
+            for file in ["action.yml", "action.yaml"] {
+                let file = if path.is_empty() { file.to_string() } else { format!("{}/{}", path, file) };
+                let url = format!("/repos/{}/contents/{}?ref={}", action_ref.full_name(), file, reference);
+                if let Some(contents) = api.get(&url).await? {
+                    found = Some(contents);
+                    break;
+                }
+            }
+            let Some(contents) = found else {
+                cache.put_negative("action-inputs", &key);
+                return Ok(None);
+            };
+            let inputs = declared(&decode_content(&contents)?)
+                .map_err(|e| std::io::Error::other(format!("{} metadata at {}: {}", action_ref.full_name(), reference, e)))?;
+            let value = Value::Object(inputs.into_iter().map(|(name, required)| (name, json!(required))).collect());
+            cache.put("action-inputs", &key, &value);
+            value
+        }
+    };
+    Ok(Some(
+        value
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, required)| (name.clone(), required.as_bool().unwrap_or(false)))
+            .collect(),
+    ))
+}
+
+/// The inputs an `action.yml` body declares. `required` counts as the boolean or the string
+/// `"true"`, as the runner reads it.
+fn declared(metadata: &str) -> Result<Inputs, serde_yaml::Error> {
+    let metadata: serde_yaml::Value = serde_yaml::from_str(metadata)?;
+    Ok(metadata
+        .get("inputs")
+        .and_then(|v| v.as_mapping())
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, input)| {
+            let required = input.get("required").is_some_and(|r| r.as_bool() == Some(true) || r.as_str() == Some("true"));
+            Some((name.as_str()?.to_string(), required))
+        })
+        .collect())
+}
+
+/// Added, removed and required-changed inputs, in that order
+fn diff(old: &Inputs, new: &Inputs) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (name, required) in new {
+        if !old.contains_key(name) {
+            changes.push(format!("{} added{}", name, if *required { " (required)" } else { "" }));
+        }
+    }
+    for name in old.keys().filter(|name| !new.contains_key(*name)) {
+        changes.push(format!("{} removed", name));
+    }
+    for (name, required) in new {
+        match old.get(name) {
+            Some(false) if *required => changes.push(format!("{} is now required", name)),
+            Some(true) if !*required => changes.push(format!("{} is no longer required", name)),
+            _ => {}
+        }
+    }
+    changes
+}
+
+/// Compare the inputs a bumped action declares at each base version with those at its new ref, so
+/// a bump of a critical dependency says which `with:` keys need attention. Informational: drift
+/// never fails a review, and a lookup that fails only skips the comparison; the failures come
+/// back, after the findings, for the caller to warn about.
+pub async fn check_drift(api: &GitHubApi<'_>, cache: &DiskCache, bump: &PinBump) -> (Vec<Finding>, Vec<String>) {
+    let Some(action_ref) = ActionRef::from_action_string(&bump.to) else {
+        return (Vec::new(), Vec::new());
+    };
+    // `owner/repo/path` actions keep their metadata under the path
+    let path = action_ref.path.as_deref().unwrap_or("");
+    let new = match inputs_at(api, cache, &action_ref, path, &action_ref.version).await {
+        Ok(Some(inputs)) => inputs,
+        Ok(None) => return (Vec::new(), Vec::new()),
+        Err(e) => return (Vec::new(), vec![format!("Failed to read the inputs of {}: {}", bump.to, e)]),
+    };
+    let mut findings = Vec::new();
+    let mut errors = Vec::new();
+    for from in &bump.from {
+        let old = match inputs_at(api, cache, &action_ref, path, from).await {
+            Ok(Some(inputs)) => inputs,
+            Ok(None) => continue,
+            Err(e) => {
+                errors.push(format!("Failed to read the inputs of {}@{}: {}", bump.name, from, e));
+                continue;
+            }
+        };
+        let changes = diff(&old, &new);
+        if !changes.is_empty() {
+            let message = format!("{} inputs changed since {}: {}", bump.to, from, changes.join(", "));
+            findings.push(Finding::new(Rule::InputDrift, Level::Note, &bump.to, message));
+        }
+    }
+    (findings, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = "name: Deploy
+inputs:
+  token:
+    required: true
+  environment:
+    description: Where to deploy
+  dry-run:
+    required: false
+runs:
+  using: node20
+  main: index.js
+";
+
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        diff(&declared(old).unwrap(), &declared(new).unwrap())
+    }
+
+    #[test]
+    fn required_reads_booleans_and_the_string_true() {
+        let inputs = declared(&V1.replace("required: true", "required: 'true'")).unwrap();
+        let expected = [("dry-run", false), ("environment", false), ("token", true)];
+        assert_eq!(inputs, expected.map(|(name, required)| (name.to_string(), required)).into());
+        assert_eq!(declared("name: Bare\nruns:\n  using: node20\n").unwrap(), Inputs::new());
+    }
+
+    #[test]
+    fn an_added_input_is_named_with_whether_it_is_required() {
+        let v2 = V1.replace("  dry-run:\n", "  region:\n    required: true\n  cache:\n    default: on\n  dry-run:\n");
+        assert_eq!(changes(V1, &v2), ["cache added", "region added (required)"]);
+    }
+
+    #[test]
+    fn a_removed_input_is_named() {
+        let v2 = V1.replace("  environment:\n    description: Where to deploy\n", "");
+        assert_eq!(changes(V1, &v2), ["environment removed"]);
+    }
+
+    #[test]
+    fn an_input_changed_to_required_or_optional_is_named() {
+        let v2 = V1.replace("  environment:\n", "  environment:\n    required: true\n").replace("required: true\n  environment", "required: false\n  environment");
+        assert_eq!(changes(V1, &v2), ["environment is now required", "token is no longer required"]);
+    }
+
+    #[test]
+    fn the_same_inputs_are_no_change() {
+        let reordered = V1.replace("  dry-run:\n    required: false\n", "").replace("inputs:\n", "inputs:\n  dry-run:\n    required: false\n");
+        assert_eq!(changes(V1, &reordered), Vec::<String>::new());
+    }
+
+    #[test]
+    fn every_kind_of_change_is_listed_added_removed_then_required() {
+        let v2 = V1.replace("  environment:\n    description: Where to deploy\n", "  region: {}\n").replace("required: false", "required: true");
+        assert_eq!(changes(V1, &v2), ["region added", "environment removed", "dry-run is now required"]);
+    }
+
+    #[test]
+    fn invalid_metadata_is_an_error() {
+        assert!(declared("inputs: [unclosed").is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use regex::Regex;
+
+/// A `uses:` value found by scanning lines rather than parsing YAML
+#[derive(Debug, Clone)]
+pub struct LenientUse {
+    pub line: usize,
+    pub value: String,
+    /// The value contains a template placeholder, so the reference cannot be resolved
+    pub templated: bool,
+}
+
+/// Template placeholders: Helm/Go (`{{ .Values.foo }}`), Actions expressions (`${{ ... }}`) and
+/// shell-style variables (`${VAR}`)
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\$?\{\{.*?\}\}|\$\{[A-Za-z_][A-Za-z0-9_]*\}").unwrap()
+}
+
+/// Whether a reference contains a template placeholder
+pub fn has_placeholder(value: &str) -> bool {
+    placeholder_regex().is_match(value)
+}
+
+/// Extract `uses:` values line by line, for workflows whose placeholders break YAML parsing.
+///
+/// Values that are nothing but a placeholder are skipped: there is no reference to check.
+pub fn uses_lines(source: &str) -> Vec<LenientUse> {
+    let uses_regex = Regex::new(r"^\s*(?:-\s+)?uses:\s*(.*?)\s*$").unwrap();
+    let placeholders = placeholder_regex();
+    let mut found = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let Some(caps) = uses_regex.captures(line) else { continue };
+        // A comment starts at a `#` after whitespace; placeholders never contain one
+        let value = caps[1].split(" #").next().unwrap_or("").trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        if value.is_empty() {
+            continue;
+        }
+        let concrete = placeholders.replace_all(value, "");
+        if concrete.trim().is_empty() {
+            continue;
+        }
+        found.push(LenientUse {
+            line: index + 1,
+            value: value.to_string(),
+            templated: concrete.len() != value.len(),
+        });
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder_only_values_are_skipped_and_partial_ones_marked() {
+        let source = "jobs:\n  build:\n    runs-on: {{ .Values.runner }}\n    steps:\n      - uses: {{ .Values.checkout }}\n      - uses: \"acme/tool@v1\" # pinned later\n      - name: Setup\n        uses: acme/setup@${SETUP_VERSION}\n";
+        let uses = uses_lines(source);
+        let found: Vec<(usize, &str, bool)> = uses.iter().map(|u| (u.line, u.value.as_str(), u.templated)).collect();
+        assert_eq!(found, [(6, "acme/tool@v1", false), (8, "acme/setup@${SETUP_VERSION}", true)]);
+    }
+}
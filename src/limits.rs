@@ -0,0 +1,283 @@
+use crate::cache::parse_duration;
+use crate::config::Config;
+use crate::multidoc;
+use regex::Regex;
+use serde_yaml::Value;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_MAX_BYTES: usize = 1024 * 1024;
+const DEFAULT_MAX_DEPTH: usize = 64;
+const DEFAULT_MAX_ALIASES: usize = 100;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bounds on the work one workflow file may cost, for YAML authored by people we do not trust
+#[derive(Debug, Clone)]
+pub struct ParseLimits {
+    pub max_bytes: usize,
+    pub max_depth: usize,
+    /// Alias references (`*name`) in the source; each can expand a whole anchored subtree
+    pub max_aliases: usize,
+    pub timeout: Duration,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_aliases: DEFAULT_MAX_ALIASES,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl ParseLimits {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let timeout = match &config.workflow_parse_timeout {
+            Some(timeout) => parse_duration(timeout).map_err(|e| format!("workflow_parse_timeout: {}", e))?,
+            None => DEFAULT_TIMEOUT,
+        };
+        Ok(ParseLimits {
+            max_bytes: config.max_workflow_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+            max_depth: config.max_workflow_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            max_aliases: config.max_workflow_aliases.unwrap_or(DEFAULT_MAX_ALIASES),
+            timeout,
+        })
+    }
+}
+
+/// What parsing under limits came to
+pub enum Parsed {
+    Value(Value),
+    /// Not valid YAML
+    Invalid(serde_yaml::Error),
+    /// A limit was hit; the reason names which
+    Refused(String),
+}
+
+/// Alias references outside quoted scalars and comments: a `*name` starting a node, at the start
+/// of a line or after `:`, `-`, `[`, `{` or `,`, not one inside a plain scalar such as `ls *.txt`.
+/// This is a lexical count, which is all that is needed to refuse a file before the parser starts
+/// expanding anything.
+fn alias_count(source: &str) -> usize {
+    let alias = Regex::new(r#"(?:^\s*|[:-]\s+|[\[{,]\s*)\*[^\s\[\]{},]+"#).unwrap();
+    let quoted = Regex::new(r#""(?:[^"\\]|\\.)*"|'[^']*'"#).unwrap();
+    source
+        .lines()
+        .map(|line| {
+            let line = line.split(" #").next().unwrap_or("");
+            if line.trim_start().starts_with('#') {
+                return 0;
+            }
+            // Drop quoted scalars so "*.js" and similar globs do not count
+            let unquoted = quoted.replace_all(line, "\"\"");
+            alias.find_iter(&unquoted).count()
+        })
+        .sum()
+}
+
+/// The nesting depth the source will parse to, read from its indentation, `- ` markers and flow
+/// brackets, so a deep document is refused before the parser builds it. Block scalar bodies are
+/// skipped, and the count errs low (a sequence at its key's indentation is not counted), so it
+/// never refuses a document the parsed depth would pass.
+fn lexical_depth(source: &str) -> usize {
+    let block_scalar = Regex::new(r"[|>][-+0-9]*$").unwrap();
+    let quoted = Regex::new(r#""(?:[^"\\]|\\.)*"|'[^']*'"#).unwrap();
+    let (mut deepest, mut columns, mut flow) = (0, Vec::<usize>::new(), 0usize);
+    let mut scalar_below: Option<usize> = None;
+    for line in source.lines() {
+        let content = line.trim_start();
+        let mut column = line.len() - content.len();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        match scalar_below {
+            Some(key) if column > key => continue,
+            _ => scalar_below = None,
+        }
+        let unquoted = quoted.replace_all(content, "\"\"");
+        let code = unquoted.split(" #").next().unwrap_or("").trim_end();
+        if flow == 0 {
+            if code.starts_with("---") || code.starts_with("...") {
+                columns.clear();
+                continue;
+            }
+            while columns.last().is_some_and(|&top| top > column) {
+                columns.pop();
+            }
+            let mut rest = code;
+            let opens = |columns: &mut Vec<usize>, column: usize| {
+                if columns.last() != Some(&column) {
+                    columns.push(column);
+                }
+            };
+            let item = |r: &str| r.strip_prefix('-').filter(|r| r.is_empty() || r.starts_with(' ')).map(str::to_string);
+            let key = |r: &str| r.contains(": ") || r.ends_with(':');
+            // Scalars, such as a plain one running on from the line above, open nothing
+            if item(rest).is_some() || key(rest) {
+                opens(&mut columns, column);
+            }
+            while let Some(after_dash) = item(rest) {
+                let after = after_dash.trim_start();
+                column += 1 + after_dash.len() - after.len();
+                rest = &rest[rest.len() - after.len()..];
+                if item(rest).is_some() || key(rest) {
+                    opens(&mut columns, column);
+                }
+            }
+            if block_scalar.is_match(code) {
+                scalar_below = Some(line.len() - content.len());
+            }
+        }
+        let mut open = flow;
+        for c in code.chars() {
+            match c {
+                '[' | '{' => {
+                    open += 1;
+                    deepest = deepest.max(columns.len() + open);
+                }
+                ']' | '}' => open = open.saturating_sub(1),
+                _ => {}
+            }
+        }
+        flow = open;
+        deepest = deepest.max(columns.len() + flow);
+    }
+    deepest
+}
+
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Sequence(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        Value::Mapping(entries) => 1 + entries.iter().map(|(k, v)| depth(k).max(depth(v))).max().unwrap_or(0),
+        Value::Tagged(tagged) => depth(&tagged.value),
+        _ => 0,
+    }
+}
+
+/// Parse YAML under the limits. The size, alias and depth limits are checked on the source before
+/// parsing starts, and the depth again on the parsed value. The parser runs in a worker thread so
+/// a pathological document cannot stall the scan past the deadline. A worker that misses it is
+/// abandoned, not killed: it keeps a CPU busy until its parse ends, which the limits checked
+/// beforehand bound to the work of parsing `max_bytes` of YAML.
+pub fn parse(source: &str, limits: &ParseLimits) -> Parsed {
+    if source.len() > limits.max_bytes {
+        return Parsed::Refused(format!("{} bytes exceeds the {} byte limit", source.len(), limits.max_bytes));
+    }
+    let aliases = alias_count(source);
+    if aliases > limits.max_aliases {
+        return Parsed::Refused(format!("{} alias references exceed the limit of {}", aliases, limits.max_aliases));
+    }
+    let depth_estimate = lexical_depth(source);
+    if depth_estimate > limits.max_depth {
+        return Parsed::Refused(format!("nesting depth {} exceeds the limit of {}", depth_estimate, limits.max_depth));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let owned = source.to_string();
+    thread::spawn(move || {
+        let _ = sender.send(multidoc::parse(&owned).map(multidoc::combine));
+    });
+    let value = match receiver.recv_timeout(limits.timeout) {
+        Ok(Ok(value)) => value,
+        // serde_yaml's own recursion and alias-repetition guards are resource limits too
+        Ok(Err(e)) if e.to_string().contains("limit exceeded") => return Parsed::Refused(e.to_string()),
+        Ok(Err(e)) => return Parsed::Invalid(e),
+        Err(_) => return Parsed::Refused(format!("parsing took longer than {}s", limits.timeout.as_secs_f64())),
+    };
+    let depth = depth(&value);
+    if depth > limits.max_depth {
+        return Parsed::Refused(format!("nesting depth {} exceeds the limit of {}", depth, limits.max_depth));
+    }
+    Parsed::Value(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = "on:\n  push:\n    branches: [main]\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n        with:\n          fetch-depth: 0\n      - run: |\n          if true; then\n            for x in a b; do\n              echo \"$x: done\"\n            done\n          fi\n    services:\n    - name: db\n      ports: [{host: 1}]\n";
+
+    fn refused(source: &str, limits: &ParseLimits) -> String {
+        match parse(source, limits) {
+            Parsed::Refused(reason) => reason,
+            Parsed::Value(_) => panic!("parsed"),
+            Parsed::Invalid(e) => panic!("invalid: {}", e),
+        }
+    }
+
+    fn nested(levels: usize) -> String {
+        (0..levels).map(|level| format!("{}k{}:\n", "  ".repeat(level), level)).collect::<String>() + &"  ".repeat(levels) + "leaf: 1\n"
+    }
+
+    #[test]
+    fn a_workflow_within_every_limit_parses() {
+        let Parsed::Value(value) = parse(WORKFLOW, &ParseLimits::default()) else { panic!("not parsed") };
+        assert_eq!(value["jobs"]["build"]["steps"][0]["uses"], "actions/checkout@v4");
+    }
+
+    #[test]
+    fn a_file_over_the_size_limit_is_refused_unread() {
+        let limits = ParseLimits { max_bytes: 64, ..Default::default() };
+        // Not even valid YAML: the size is checked before the parser sees it
+        let source = format!("on: push\n{}: [", "x".repeat(64));
+        assert_eq!(refused(&source, &limits), format!("{} bytes exceeds the 64 byte limit", source.len()));
+    }
+
+    #[test]
+    fn an_alias_bomb_is_refused_before_it_expands() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m"];
+        let mut bomb = "a: &a [x, x, x, x, x, x, x, x, x]\n".to_string();
+        for pair in names.windows(2) {
+            bomb += &format!("{}: &{} [{}]\n", pair[1], pair[1], vec![format!("*{}", pair[0]); 9].join(", "));
+        }
+        assert_eq!(refused(&bomb, &ParseLimits::default()), "108 alias references exceed the limit of 100");
+        // Past a raised limit, serde_yaml's own repetition guard still refuses the expansion
+        assert_eq!(refused(&bomb, &ParseLimits { max_aliases: 1000, ..Default::default() }), "repetition limit exceeded");
+        // Globs and comments are not aliases
+        assert_eq!(alias_count("paths: ['*.js', \"src/**\"]\n# *note\nrun: ls *.txt # *x\n"), 0);
+        assert_eq!(alias_count("defaults: &d {a: 1}\njob: *d\nlist: [*d, *d]\n- *d\n"), 4);
+    }
+
+    #[test]
+    fn a_deep_file_is_refused_before_it_is_parsed() {
+        let limits = ParseLimits { max_depth: 8, ..Default::default() };
+        // Invalid after the nesting, so a refusal rather than a parse error shows the parser never
+        // got that far
+        let deep = nested(9) + "broken: [\n";
+        assert_eq!(refused(&deep, &limits), "nesting depth 10 exceeds the limit of 8");
+        assert_eq!(refused(&format!("a: {}{}\n", "[".repeat(9), "]".repeat(9)), &limits), "nesting depth 10 exceeds the limit of 8");
+        assert!(matches!(parse(&nested(7), &limits), Parsed::Value(_)));
+    }
+
+    #[test]
+    fn the_lexical_depth_never_exceeds_the_parsed_one() {
+        for source in [WORKFLOW.to_string(), nested(5), "- on\n- jobs\n".to_string(), "- - - a\n".to_string(), "just text\n".to_string(), "a: 1\n---\nb:\n  c: [1, {d: [2]}]\n".to_string()] {
+            let Parsed::Value(value) = parse(&source, &ParseLimits::default()) else { panic!("{}", source) };
+            assert!(lexical_depth(&source) <= depth(&value), "{}: {} > {}", source, lexical_depth(&source), depth(&value));
+        }
+        // services' items sit at its own indentation, which the lexical count leaves out
+        assert_eq!(lexical_depth(WORKFLOW), 6);
+        assert_eq!(depth(&serde_yaml::from_str(WORKFLOW).unwrap()), 7);
+    }
+
+    #[test]
+    fn a_parse_past_the_deadline_is_given_up() {
+        let steps = "      - uses: actions/checkout@v4\n        with: {fetch-depth: 0, path: x}\n".repeat(30_000);
+        let source = format!("on: push\njobs:\n  build:\n    steps:\n{}", steps);
+        let limits = ParseLimits { max_bytes: usize::MAX, timeout: Duration::from_millis(1), ..Default::default() };
+        assert_eq!(refused(&source, &limits), "parsing took longer than 0.001s");
+    }
+
+    #[test]
+    fn limits_come_from_the_config() {
+        let config: Config =
+            serde_yaml::from_str("max_workflow_bytes: 10\nmax_workflow_depth: 3\nmax_workflow_aliases: 0\nworkflow_parse_timeout: 2s\n").unwrap();
+        let limits = ParseLimits::from_config(&config).unwrap();
+        assert_eq!((limits.max_bytes, limits.max_depth, limits.max_aliases, limits.timeout), (10, 3, 0, Duration::from_secs(2)));
+        let bad: Config = serde_yaml::from_str("workflow_parse_timeout: soon\n").unwrap();
+        assert!(ParseLimits::from_config(&bad).unwrap_err().starts_with("workflow_parse_timeout: "));
+    }
+}
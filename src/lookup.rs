@@ -0,0 +1,343 @@
+use crate::cache::{Cached, DiskCache};
+use crate::config::Config;
+use crate::decision::{self, ExitPolicy, ExitStatus};
+use crate::fix::resolve_tag_sha_cached;
+use crate::github::GitHubApi;
+use crate::indicators;
+use crate::render::Marker;
+use crate::report::{Finding, ScanReport};
+use crate::scanner::Scanner;
+use crate::{ActionRef, AdvisoryPolicy, action_policy, advisory_finding, is_trusted_owner};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize)]
+pub struct AdvisorySummary {
+    pub open: usize,
+    pub total: usize,
+    /// Publication date of the most recent advisory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Release {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepoHealth {
+    pub stars: u64,
+    pub forks: u64,
+    pub open_issues: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pushed_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    pub archived: bool,
+    /// Archived, or described by its maintainers as deprecated
+    pub deprecated: bool,
+}
+
+/// Everything the scanner knows or can fetch about one action, for `vendor <action>`
+#[derive(Debug, Serialize)]
+pub struct ActionSummary {
+    pub action: String,
+    /// The reference policy was evaluated at: the one given, else the latest release
+    pub evaluated: String,
+    pub trusted_owner: bool,
+    pub critical: bool,
+    /// Whether the repository appears in the built-in compromised-action list at all
+    pub indicator_listed: bool,
+    pub advisories: Option<AdvisorySummary>,
+    /// `None` with no `unknown` entry means the repository has no releases
+    pub latest_release: Option<Release>,
+    pub repository: Option<RepoHealth>,
+    pub findings: Vec<Finding>,
+    pub passes_policy: bool,
+    /// What `vendor` exits with for this summary
+    pub exit: ExitStatus,
+    /// Lookups that could not be answered, with the reason
+    pub unknown: BTreeMap<String, String>,
+}
+
+/// Repository metadata through the cache
+pub async fn repository(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<Value>, std::io::Error> {
+    let key = action_ref.full_name();
+    match cache.get("repos", &key) {
+        Cached::Hit(value) => return Ok(Some(value)),
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {}
+    }
+    let repo = api.get(&format!("/repos/{}", action_ref.full_name())).await?;
+    match &repo {
+        Some(repo) => cache.put("repos", &key, repo),
+        None => cache.put_negative("repos", &key),
+    }
+    Ok(repo)
+}
+
+/// The latest release through the cache
+async fn latest_release(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<Value>, std::io::Error> {
+    let key = action_ref.full_name();
+    match cache.get("latest-release", &key) {
+        Cached::Hit(value) => return Ok(Some(value)),
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {}
+    }
+    let release = api.get(&format!("/repos/{}/releases/latest", action_ref.full_name())).await?;
+    match &release {
+        Some(release) => cache.put("latest-release", &key, release),
+        None => cache.put_negative("latest-release", &key),
+    }
+    Ok(release)
+}
+
+fn health(repo: &Value) -> RepoHealth {
+    let text = |key: &str| repo[key].as_str().map(str::to_string);
+    let archived = repo["archived"].as_bool().unwrap_or(false);
+    let described_deprecated = repo["description"]
+        .as_str()
+        .is_some_and(|d| d.to_ascii_lowercase().contains("deprecated"));
+    RepoHealth {
+        stars: repo["stargazers_count"].as_u64().unwrap_or(0),
+        forks: repo["forks_count"].as_u64().unwrap_or(0),
+        open_issues: repo["open_issues_count"].as_u64().unwrap_or(0),
+        pushed_at: text("pushed_at"),
+        license: repo["license"]["spdx_id"].as_str().map(str::to_string),
+        archived,
+        deprecated: archived || described_deprecated,
+    }
+}
+
+/// Gather the summary. Lookups that fail are recorded in `unknown` rather than failing the command.
+pub async fn summarize(scanner: &Scanner, config: &Config, action: &str) -> Result<ActionSummary, Box<dyn std::error::Error>> {
+    let cache = scanner.cache();
+    let name = action.split_once('@').map_or(action, |(name, _)| name);
+    let mut parts = name.splitn(2, '/');
+    let (Some(owner), Some(repo)) = (parts.next(), parts.next()) else {
+        return Err(format!("{} is not an owner/repo[@ref] reference", action).into());
+    };
+    let base_ref = ActionRef::from_action_string(&format!("{}/{}@HEAD", owner, repo))
+        .ok_or_else(|| format!("{} is not an owner/repo[@ref] reference", action))?;
+    let api = scanner.api();
+    let mut unknown = BTreeMap::new();
+
+    let latest_release = match latest_release(&api, cache, &base_ref).await {
+        Ok(Some(release)) => match release["tag_name"].as_str() {
+            Some(tag) => {
+                let tag_ref = ActionRef { version: tag.to_string(), ..base_ref.clone() };
+                let sha = match resolve_tag_sha_cached(&api, cache, &tag_ref).await {
+                    Ok(sha) => sha,
+                    Err(e) => {
+                        unknown.insert("latest_release.sha".to_string(), e.to_string());
+                        None
+                    }
+                };
+                Some(Release {
+                    tag: tag.to_string(),
+                    sha,
+                    published_at: release["published_at"].as_str().map(str::to_string),
+                })
+            }
+            None => None,
+        },
+        Ok(None) => None,
+        Err(e) => {
+            unknown.insert("latest_release".to_string(), e.to_string());
+            None
+        }
+    };
+
+    // A bare owner/repo is judged as it would be pinned to its latest release
+    let evaluated = match (action.contains('@'), &latest_release) {
+        (false, Some(release)) => format!("{}@{}", name, release.tag),
+        _ => action.to_string(),
+    };
+
+    let mut findings = action_policy(&evaluated, config, scanner.options().strict);
+    let builtin = indicators::builtin().map_err(|e| format!("built-in compromised action list is invalid: {}", e))?;
+    let indicator_listed = builtin.iter().any(|i| i.action.eq_ignore_ascii_case(name));
+    findings.extend(indicators::check(&evaluated, &builtin));
+
+    let advisories = match scanner.advisories(&base_ref, false).await {
+        Ok((advisories, _)) => {
+            let policy = AdvisoryPolicy::new(config, scanner.clock());
+            // Judged at the evaluated ref, as the scan would judge the pin
+            let pinned = ActionRef::from_action_string(&evaluated).unwrap_or_else(|| base_ref.clone());
+            let open: Vec<_> = advisories.iter().filter(|a| a.is_open() && a.affects(&pinned) != Some(false)).collect();
+            findings.extend(open.iter().map(|a| advisory_finding(a, &policy, &evaluated)));
+            Some(AdvisorySummary {
+                open: open.len(),
+                total: advisories.len(),
+                latest: advisories.iter().filter_map(|a| a.created_at.clone()).max(),
+            })
+        }
+        Err(e) => {
+            unknown.insert("advisories".to_string(), e.to_string());
+            None
+        }
+    };
+
+    let repository = match repository(&api, cache, &base_ref).await {
+        Ok(repo) => repo.as_ref().map(health),
+        Err(e) => {
+            unknown.insert("repository".to_string(), e.to_string());
+            None
+        }
+    };
+
+    let report = ScanReport {
+        findings: findings.clone(),
+        unchecked: if advisories.is_none() { vec![evaluated.clone()] } else { Vec::new() },
+        ..Default::default()
+    };
+    let exit = decision::decide(&report, &ExitPolicy::default()).exit;
+
+    Ok(ActionSummary {
+        action: action.to_string(),
+        evaluated,
+        trusted_owner: is_trusted_owner(owner, config),
+        critical: config.critical_dependencies.as_ref().is_some_and(|deps| deps.iter().any(|d| d == action)),
+        indicator_listed,
+        advisories,
+        latest_release,
+        repository,
+        findings,
+        passes_policy: exit.code == 0,
+        exit,
+        unknown,
+    })
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+impl ActionSummary {
+    /// "unknown (offline)" for a lookup that failed, else the given text
+    fn or_unknown(&self, key: &str, known: impl FnOnce() -> String) -> String {
+        if self.unknown.contains_key(key) {
+            "unknown (offline)".to_string()
+        } else {
+            known()
+        }
+    }
+
+    /// One line for a finding: owner trust, advisories, latest release and repository health
+    pub fn brief(&self) -> String {
+        let known = |key: &str, label: &str, text: String| {
+            if self.unknown.contains_key(key) { format!("{} unknown", label) } else { text }
+        };
+        let advisories = match &self.advisories {
+            Some(a) => format!("{} open of {} advisories", a.open, a.total),
+            None => "no advisories".to_string(),
+        };
+        let release = match &self.latest_release {
+            Some(Release { tag, published_at: Some(date), .. }) => format!("latest release {} ({})", tag, date),
+            Some(release) => format!("latest release {}", release.tag),
+            None => "no releases".to_string(),
+        };
+        let repository = match &self.repository {
+            Some(r) => format!(
+                "{} stars, last push {}, license {}{}",
+                r.stars,
+                r.pushed_at.as_deref().unwrap_or("unknown"),
+                r.license.as_deref().unwrap_or("none"),
+                if r.archived { ", archived" } else if r.deprecated { ", deprecated" } else { "" }
+            ),
+            None => "repository not found".to_string(),
+        };
+        [
+            if self.trusted_owner { "trusted owner" } else { "untrusted owner" }.to_string(),
+            known("advisories", "advisories", advisories),
+            known("latest_release", "latest release", release),
+            known("repository", "repository health", repository),
+        ]
+        .join("; ")
+    }
+
+    /// The `vendor` subcommand's report of the action
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("{}\n", self.action));
+        if self.evaluated != self.action {
+            out.push_str(&format!("  Evaluated as:     {}\n", self.evaluated));
+        }
+        out.push_str(&format!("  Trusted owner:    {}\n", yes_no(self.trusted_owner)));
+        out.push_str(&format!("  Critical:         {}\n", yes_no(self.critical)));
+        let compromised = self.findings.iter().any(|f| f.rule == crate::report::Rule::CompromisedVersion);
+        out.push_str(&format!(
+            "  Indicator list:   {}\n",
+            match (self.indicator_listed, compromised) {
+                (_, true) => "this ref is a known-compromised version",
+                (true, false) => "listed, but not at this ref",
+                (false, false) => "not listed",
+            }
+        ));
+        out.push_str(&format!(
+            "  Advisories:       {}\n",
+            self.or_unknown("advisories", || match &self.advisories {
+                Some(a) => match &a.latest {
+                    Some(latest) => format!("{} open, {} total, latest {}", a.open, a.total, latest),
+                    None => format!("{} open, {} total", a.open, a.total),
+                },
+                None => "none".to_string(),
+            })
+        ));
+        out.push_str(&format!(
+            "  Latest release:   {}\n",
+            self.or_unknown("latest_release", || match &self.latest_release {
+                Some(release) => {
+                    let sha = self.or_unknown("latest_release.sha", || release.sha.clone().unwrap_or_else(|| "tag not found".to_string()));
+                    match &release.published_at {
+                        Some(date) => format!("{} ({}), published {}", release.tag, sha, date),
+                        None => format!("{} ({})", release.tag, sha),
+                    }
+                }
+                None => "none".to_string(),
+            })
+        ));
+        let repo = self.repository.as_ref();
+        out.push_str(&format!(
+            "  Repository:       {}\n",
+            self.or_unknown("repository", || match repo {
+                Some(r) => format!(
+                    "{} stars, {} forks, {} open issues, last push {}",
+                    r.stars,
+                    r.forks,
+                    r.open_issues,
+                    r.pushed_at.as_deref().unwrap_or("unknown")
+                ),
+                None => "not found".to_string(),
+            })
+        ));
+        out.push_str(&format!(
+            "  License:          {}\n",
+            self.or_unknown("repository", || repo.and_then(|r| r.license.clone()).unwrap_or_else(|| "none".to_string()))
+        ));
+        out.push_str(&format!(
+            "  Deprecated:       {}\n",
+            self.or_unknown("repository", || match repo {
+                Some(r) if r.archived => "yes (archived)".to_string(),
+                Some(r) => yes_no(r.deprecated).to_string(),
+                None => "unknown".to_string(),
+            })
+        ));
+        for finding in &self.findings {
+            out.push_str(&format!("  {} {}\n", finding.level.marker(), finding.message));
+        }
+        let marker = if self.passes_policy { Marker::Ok } else { Marker::Fail };
+        out.push_str(&format!(
+            "  Policy:           {} {}\n",
+            marker,
+            if self.passes_policy { "passes the current policy" } else { "fails the current policy" }
+        ));
+        out
+    }
+}
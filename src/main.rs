@@ -1,301 +1,1679 @@
-use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
-use std::collections::HashSet;
+use reqwest::Client;
+use std::collections::BTreeSet;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use structopt::StructOpt;
-use tokio::time::sleep;
 use std::time::Duration;
-use regex::Regex;
+
+use gh_action_security_scanner::*;
+use cache::DiskCache;
+use clock::Clock;
+use config::{Config, ConfigPath, ConfigSet};
+use decision::{Decision, ExitCode, ExitPolicy};
+use filter::ActionFilter;
+use github::GitHubApi;
+use http::HttpClient;
+use render::Marker;
+use severity::Severity;
+use report::{Category, Finding, Level, OutputFormat, Rule, ScanReport};
+use version::NewerFormats;
 
 #[derive(StructOpt)]
 struct Opt {
+    /// Workflow files or directories of workflows to scan; - reads one workflow from stdin
     #[structopt(parse(from_os_str))]
-    workflow_path: PathBuf,
+    workflow_paths: Vec<PathBuf>,
+
+    /// Name the workflow read from stdin (-) is reported under, such as the file a pre-commit hook
+    /// passes; its config is looked up as for that path (default <stdin>)
+    #[structopt(long)]
+    filename: Option<String>,
     
+    /// Also scan workflows in subdirectories of the directories scanned
     #[structopt(long)]
+    recursive: bool,
+
+    /// Directory to discover workflows in instead of .github/workflows (repeatable)
+    #[structopt(long = "workflows-dir", number_of_values = 1, parse(from_os_str))]
+    workflows_dirs: Vec<PathBuf>,
+
+    /// Scan only the workflow that is running, as named by GITHUB_WORKFLOW_REF
+    #[structopt(long = "self", conflicts_with_all = &["workflow-paths", "workflows-dir"])]
+    self_workflow: bool,
+
+    /// Scan the paths read from stdin, one per line (NUL-separated with -z), e.g. from
+    /// `git ls-files '.github/**/*.yml'`. An empty list is a usage error (exit 5)
+    #[structopt(long, conflicts_with_all = &["workflow-paths", "workflows-dir", "self"])]
+    stdin_filelist: bool,
+
+    /// With --stdin-filelist, accept an empty list and report an empty scan
+    #[structopt(long, requires = "stdin-filelist")]
+    allow_empty: bool,
+
+    #[structopt(long, global = true)]
     strict: bool,
     
-    #[structopt(long, default_value = "critical_dependencies.yaml")]
-    config: String,
+    /// Root config file (default: $DEPENDENCY_SCANNER_CONFIG, else ./critical_dependencies.yaml).
+    /// A file named here or in the environment must exist.
+    #[structopt(long, global = true)]
+    config: Option<String>,
+
+    /// Findings to accept, by action, advisory id or rule id, each with a reason and an optional expiry
+    /// (default ./.dependency-scanner-ignore.yaml, if it exists)
+    #[structopt(long)]
+    ignore_file: Option<String>,
+
+    /// Findings accepted when the scanner was adopted (a --write-baseline file): still reported,
+    /// but only findings not in it fail the scan
+    #[structopt(long, conflicts_with = "write-baseline")]
+    baseline: Option<String>,
+
+    /// Record this scan's findings as a baseline file, which then covers them
+    #[structopt(long)]
+    write_baseline: Option<String>,
+
+    /// Fail only on `uses:` entries added or changed since an older copy of the workflows: a file,
+    /// or a directory laid out as the scanned one. Findings of unchanged entries are listed, not
+    /// failing, and unchanged actions are not looked up
+    #[structopt(long, parse(from_os_str), conflicts_with = "diff-base")]
+    diff: Option<PathBuf>,
+
+    /// As --diff, against the workflows at a git revision of this checkout, such as origin/main
+    #[structopt(long)]
+    diff_base: Option<String>,
+
+    /// Rewrite the --baseline file without the entries whose findings are gone
+    #[structopt(long, requires = "baseline")]
+    update_baseline: bool,
+
+    /// Check config files against the config schema before loading them
+    #[structopt(long, global = true)]
+    validate_config: bool,
+
+    /// Curated defaults merged under the config, which always wins: baseline, strict or paranoid
+    /// (help lists what each enables)
+    #[structopt(long, global = true)]
+    profile: Option<String>,
+
+    /// Fail when an action could not be checked against the advisory database. A lookup that
+    /// fails already fails the scan with operational-error (10), so this only names the flag in
+    /// the decision chain.
+    #[structopt(long)]
+    fail_on_unchecked: bool,
+
+    /// Fail when the summary reports incomplete coverage: any action whose network checks could
+    /// not complete fails the scan, as with --fail-on-unchecked
+    #[structopt(long)]
+    require_complete: bool,
+
+    /// Fall back to line-by-line extraction of `uses:` values for workflows whose template
+    /// placeholders (`{{ .Values.x }}`, `${VAR}`) are not valid YAML
+    #[structopt(long)]
+    lenient_templates: bool,
+
+    /// Warn about SHA pins without a version comment in the pin_comment_pattern style
+    #[structopt(long)]
+    require_pinning_comment_style: bool,
+
+    /// Fail SHA pins whose version comment (`# v4.2.2`) names a tag that does not resolve to the
+    /// pinned commit; pins without a comment are left alone
+    #[structopt(long)]
+    verify_pin_comments: bool,
+
+    /// Fail when a trusted_owners, trusted_repos, critical_dependencies or vendored_actions entry matched nothing
+    #[structopt(long)]
+    fail_on_unused_config: bool,
+
+    /// Trust no owner by default, not even actions and github, when the config sets no trusted_owners
+    #[structopt(long, global = true)]
+    no_default_trust: bool,
+
+    /// Fail when the scan would pass with warnings
+    #[structopt(long)]
+    fail_on_warnings: bool,
+
+    /// Fail only on advisories of this severity or above (low, moderate, high or critical); those
+    /// below are reported as warnings. The default, low, fails on every advisory.
+    #[structopt(long, default_value = "low")]
+    fail_on_severity: Severity,
+
+    /// Severity assumed for advisories that give none, when --fail-on-severity is above low
+    #[structopt(long, default_value = "critical")]
+    unknown_severity: Severity,
+
+    /// Leave advisories published before this date (2024-01-01, in UTC, or an RFC 3339 time) out
+    /// of the report; those that fail the scan still show and fail it
+    #[structopt(long, parse(try_from_str = clock::parse_date_or_time))]
+    advisories_since: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// With --advisories-since, let only recent advisories fail the scan
+    #[structopt(long, requires = "advisories-since")]
+    fail_only_recent: bool,
+
+    /// Run every check and write every output, then exit 0 whatever was found; the JSON `exit`
+    /// object keeps the code the scan would have exited with. Usage and config errors still fail.
+    #[structopt(long)]
+    exit_zero: bool,
+
+    /// Run only the local checks (pinning, trusted owners, blocklists and the workflow rules) and
+    /// send no request; the report lists every network-backed check as skipped
+    #[structopt(long, conflicts_with_all = &["submit-dependency-snapshot", "pushgateway-url", "record-http"])]
+    offline: bool,
+
+    /// Check that every SHA pin's commit exists in the action's repository and is on one of its
+    /// branches or recent tags, not only in a fork
+    #[structopt(long)]
+    verify_shas: bool,
+
+    /// Show each SHA pin with the tag it resolves to (or the nearest tag it descends from, as
+    /// v4.2.2+3), falling back to its version comment; JSON gets the version as pin_version
+    #[structopt(long)]
+    describe_pins: bool,
+
+    /// Warn about pins behind the latest release of their action
+    #[structopt(long)]
+    check_outdated: bool,
+
+    /// Make outdated pins errors (implies --check-outdated)
+    #[structopt(long)]
+    fail_on_outdated: bool,
+
+    /// Flag actions whose repository is archived (a warning, an error for critical dependencies),
+    /// disabled, does not exist, or moved to another name, whose owner the trust checks then see
+    #[structopt(long)]
+    check_repo_status: bool,
+
+    /// Flag actions whose action.yml, at their ref, runs on a deprecated Node.js runtime such as
+    /// node16, or one runners do not support (a warning, an error with --strict)
+    #[structopt(long)]
+    check_runtime: bool,
+
+    /// Retries of a request refused for rate limiting, with backoff, before the action it was for
+    /// is left unchecked
+    #[structopt(long, default_value = "3")]
+    max_retries: u32,
+
+    /// Actions whose network checks run at the same time (at least 1)
+    #[structopt(long, default_value = "5")]
+    concurrency: usize,
+
+    /// Also check the actions composite actions use, read from each action's action.yml at its
+    /// ref; findings name the chain of actions that leads to them
+    #[structopt(long)]
+    transitive: bool,
+
+    /// Levels of composite actions below the workflow --transitive follows (default 1)
+    #[structopt(long, requires = "transitive")]
+    transitive_depth: Option<usize>,
+
+    /// Fail on actions more than N levels of composite actions below the workflow. Only chains
+    /// --transitive-depth follows are seen, so it needs a depth above N.
+    #[structopt(long, requires = "transitive")]
+    max_depth_fail: Option<usize>,
+
+    /// Submit the workflows' actions to the repository's dependency graph (needs GITHUB_TOKEN with
+    /// contents: write, and the GitHub Actions run environment)
+    #[structopt(long)]
+    submit_dependency_snapshot: bool,
+
+    /// Group the final report by the CODEOWNERS owners of each workflow file
+    #[structopt(long)]
+    codeowners: bool,
+
+    /// Show the commit, author and date that introduced each finding's line (runs git blame, which
+    /// is slow on very large repositories). Without git on PATH the scan warns and goes on
+    /// without blame.
+    #[structopt(long)]
+    blame: bool,
+
+    /// Write each finding as a `# dependency-scanner:` comment above its step, into a
+    /// `<workflow>.annotated` copy of each workflow (re-running replaces the earlier comments)
+    #[structopt(long)]
+    annotate_workflow: bool,
+
+    /// With --annotate-workflow, annotate the workflow files themselves instead of copies
+    #[structopt(long, requires = "annotate-workflow")]
+    in_place: bool,
+
+    /// Only check actions whose owner/repo matches one of these glob patterns
+    #[structopt(long, number_of_values = 1, parse(try_from_str = filter::parse_pattern))]
+    only: Vec<glob::Pattern>,
+
+    /// Skip actions whose owner/repo matches one of these glob patterns (wins over --only)
+    #[structopt(long, number_of_values = 1, parse(try_from_str = filter::parse_pattern))]
+    skip: Vec<glob::Pattern>,
+
+    /// Pin every emitted timestamp to this RFC 3339 time, and report the duration as zero
+    /// (overrides SOURCE_DATE_EPOCH)
+    #[structopt(long, parse(try_from_str = clock::parse_timestamp))]
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Directory for cached API results (defaults to the platform cache directory)
+    #[structopt(long, global = true, parse(from_os_str))]
+    cache_dir: Option<PathBuf>,
+
+    /// How long cached API results stay valid
+    #[structopt(long, global = true, default_value = "24h", parse(try_from_str = cache::parse_duration))]
+    cache_ttl: Duration,
+
+    /// How long cached negative results (no advisories, missing refs) stay valid
+    #[structopt(long, global = true, default_value = "6h", parse(try_from_str = cache::parse_duration))]
+    negative_cache_ttl: Duration,
+
+    /// Neither read nor write cached API results; each action is still looked up once per run
+    #[structopt(long, global = true)]
+    no_cache: bool,
+
+    /// Remove every cached API result before running
+    #[structopt(long, global = true)]
+    cache_clear: bool,
+
+    /// Plain ASCII output without emoji (automatic when TERM=dumb or the locale isn't UTF-8)
+    #[structopt(long, global = true)]
+    ascii: bool,
+
+    /// No emoji or other styling (also with $NO_COLOR, or when stdout is not a terminal)
+    #[structopt(long, global = true)]
+    no_color: bool,
+
+    /// Print only the findings and the summary; no progress on stderr
+    #[structopt(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print each API request, cache hit and rate limit on stderr
+    #[structopt(short, long, global = true)]
+    verbose: bool,
+
+    /// Don't check actions against the built-in list of known-compromised versions
+    #[structopt(long)]
+    no_builtin_indicators: bool,
+
+    /// Also list actions with any advisory published in the last <months> months, even if the pinned
+    /// version is patched (informational, never affects the exit code)
+    #[structopt(long)]
+    advisory_history: Option<u32>,
+
+    /// Heuristically scan run: scripts for workflows dispatched in other repositories at unpinned refs
+    /// and for downloads over plain HTTP or without TLS verification
+    #[structopt(long)]
+    scan_run_steps: bool,
+
+    /// Flag actions/github-script steps in privileged workflows that run long inline scripts, expand
+    /// attacker-controlled expressions, or require files from a pull request checkout
+    #[structopt(long)]
+    scan_github_script: bool,
+
+    /// Flag privileged workflows that run files restored from caches or artifacts that a workflow
+    /// any GitHub user can trigger also writes, across all the workflows scanned
+    #[structopt(long)]
+    scan_cache_poisoning: bool,
+
+    /// Flag SHA- and tag-pinned actions in workflows that no github-actions entry of
+    /// .github/dependabot.yml covers, since nothing would update their pins (a warning, an error
+    /// with --strict)
+    #[structopt(long)]
+    check_dependabot: bool,
+
+    /// Dependabot config to read instead of .github/dependabot.yml (implies --check-dependabot)
+    #[structopt(long, parse(from_os_str))]
+    dependabot_config: Option<PathBuf>,
+
+    /// Repository visibility for visibility-sensitive rules: public, private, or auto to look it up
+    /// (needs a token and GITHUB_REPOSITORY)
+    #[structopt(long, default_value = "auto")]
+    repo_visibility: visibility::VisibilityOption,
+
+    /// Render at most this many findings in text and markdown output, highest severity first; the
+    /// rest are counted in an overflow line
+    #[structopt(long)]
+    max_findings: Option<usize>,
+
+    /// Apply --max-findings to JSON, SARIF, TeamCity service messages and workflow commands too (by
+    /// default they list every finding); the JSON report counts the rest in truncated_findings
+    #[structopt(long, requires = "max-findings")]
+    truncate_structured: bool,
+
+    /// Output format: text, teamcity or github to also emit TeamCity service messages or GitHub
+    /// Actions annotations, or json or sarif for that report on stdout (text goes to stderr).
+    /// Defaults to github inside GitHub Actions ($GITHUB_ACTIONS=true), else text.
+    #[structopt(long)]
+    format: Option<OutputFormat>,
+
+    /// Write report artifacts into this directory (all of them unless --emit picks some)
+    #[structopt(long, parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+
+    /// Artifacts to write with --output-dir: json, sarif, markdown, cyclonedx (repeatable or
+    /// comma-separated)
+    #[structopt(long, use_delimiter = true, requires = "output-dir")]
+    emit: Vec<artifacts::Artifact>,
+
+    /// Write one report to this file, in the format --report-format names
+    #[structopt(long, parse(from_os_str))]
+    report: Option<PathBuf>,
+
+    /// Format of the --report file: markdown (a PR comment, and the default), json, sarif or
+    /// cyclonedx
+    #[structopt(long, requires = "report")]
+    report_format: Option<artifacts::Artifact>,
+
+    /// Write the stable machine-readable report (format version, e.g. v1) to stdout; everything else
+    /// goes to stderr
+    #[structopt(long, conflicts_with = "format")]
+    porcelain: Option<String>,
+
+    /// With --porcelain, terminate fields with NUL instead of escaping them; with --stdin-filelist,
+    /// read NUL-separated paths, as `git ls-files -z` writes them
+    #[structopt(short = "z")]
+    nul_terminated: bool,
+
+    /// Export scan spans and metrics to this OTLP/HTTP collector, e.g. http://localhost:4318
+    #[cfg(feature = "otlp")]
+    #[structopt(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Upper bound for each OTLP export request
+    #[cfg(feature = "otlp")]
+    #[structopt(long, default_value = "5s", parse(try_from_str = cache::parse_duration))]
+    otlp_timeout: Duration,
+
+    /// Push the scan's metrics to this Prometheus Pushgateway when it ends, e.g.
+    /// http://pushgateway:9091; for scheduled scans nothing scrapes. A failed push only warns
+    #[structopt(long)]
+    pushgateway_url: Option<String>,
+
+    /// Job label of the pushed metrics (default dependency-scanner)
+    #[structopt(long, requires = "pushgateway-url")]
+    pushgateway_job: Option<String>,
+
+    /// Instance label of the pushed metrics (default $GITHUB_REPOSITORY)
+    #[structopt(long, requires = "pushgateway-url")]
+    pushgateway_instance: Option<String>,
+
+    /// Vulnerability databases to query: github, osv or all
+    #[structopt(long, global = true, default_value = "all")]
+    source: AdvisorySources,
+
+    /// Base URL of the GitHub API, e.g. https://ghe.example.com/api/v3 for GitHub Enterprise Server
+    /// (default: $GITHUB_API_URL, else https://api.github.com)
+    #[structopt(long, global = true, parse(try_from_str = github::parse_api_url))]
+    api_url: Option<String>,
+
+    /// Base URL for GitHub advisory lookups, for servers that do not mirror the advisory database
+    /// (default: the API URL). The token is only sent to the API URL.
+    #[structopt(long, global = true, parse(try_from_str = github::parse_api_url))]
+    advisory_api_url: Option<String>,
+
+    /// Base URL of the OSV API, for mirrors and test servers (default: https://api.osv.dev)
+    #[structopt(long, global = true, parse(try_from_str = github::parse_api_url))]
+    osv_api_url: Option<String>,
+
+    /// Report rule ids as another scanner names them, for suppression lists and dashboards keyed
+    /// on its ids: zizmor or pinact. Rules without an equivalent keep their own ids.
+    #[structopt(long)]
+    rule_id_compat: Option<compat::Tool>,
+
+    /// Token for the GitHub API, or - to read it from stdin. Without one, requests are limited to
+    /// 60 an hour, which a workflow with more than a handful of actions exhausts. Prefer
+    /// --token-file or $GITHUB_TOKEN, which keep it out of the process list; either flag takes
+    /// precedence over the variable
+    #[structopt(long)]
+    token: Option<String>,
+
+    /// File holding the token for the GitHub API, e.g. a mounted secret
+    #[structopt(long, parse(from_os_str))]
+    token_file: Option<PathBuf>,
+
+    /// Write a zip for troubleshooting to this path: the effective config, an environment summary,
+    /// every request with its status and timing (no bodies or credentials), the rate limit over
+    /// the run, and the report as far as the run got. Tokens are scrubbed from all of it.
+    #[structopt(long, global = true, parse(from_os_str))]
+    debug_bundle: Option<PathBuf>,
+
+    /// Record every HTTP exchange to this fixture file
+    #[structopt(long, global = true, hidden = true, parse(from_os_str))]
+    record_http: Option<PathBuf>,
+
+    /// Answer HTTP requests from a fixture file recorded with --record-http instead of the network
+    #[structopt(long, global = true, hidden = true, parse(from_os_str), conflicts_with = "record-http")]
+    replay_http: Option<PathBuf>,
+
+    /// Answer network lookups from a fixture directory (see `fixtures record`), falling back to
+    /// the network for lookups it has no file for
+    #[structopt(long, global = true, parse(from_os_str), conflicts_with_all = &["record-http", "replay-http"])]
+    fixtures: Option<PathBuf>,
+
+    /// With --fixtures, fail lookups that have no fixture instead of going to the network
+    #[structopt(long, global = true, requires = "fixtures")]
+    frozen: bool,
+
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    critical_dependencies: Option<Vec<String>>, // Optional definition of critical dependencies. These require version pinning.
-    trusted_owners: Option<HashSet<String>>,    // Trusted GitHub owners/organizations. These do not require version pinning.
+#[derive(StructOpt)]
+enum Command {
+    /// Pin tag references that should be commit SHAs, locally or through a pull request
+    Fix(FixOpt),
+    /// Inspect configuration
+    Config(ConfigCommand),
+    /// Print the JSON Schema of a file format
+    Schema(SchemaCommand),
+    /// Check only the action pins a pull request changes, such as a Dependabot bump, and write a
+    /// markdown verdict for a PR comment
+    ReviewBump(ReviewBumpOpt),
+    /// Manage fixture directories for offline scans
+    Fixtures(FixturesCommand),
+    /// Summarize what is known about one action before approving it
+    Vendor {
+        /// The action, as owner/repo or owner/repo@ref
+        action: String,
+
+        /// Print the summary as JSON
+        #[structopt(long)]
+        json: bool,
+    },
+    /// Print what an exit code of the scanner means
+    ExplainExitCode { code: i32 },
 }
 
-/// Load configuration from YAML file
-fn load_config(config_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
-    match fs::read_to_string(config_path) {
-        Ok(content) => {
-            let config: Config = serde_yaml::from_str(&content)?;
-            Ok(config)
-        },
-        Err(e) => {
-            eprintln!("Warning: Could not load config file: {}", e);
-            // Return default config if file not found
-            Ok(Config {
-                critical_dependencies: Some(Vec::new()),
-                trusted_owners: Some(HashSet::new()),
-            })
-        }
-    }
-}
-
-/// GitHub advisory API response
-#[derive(Debug, Serialize, Deserialize)]
-struct GitHubAdvisoryResponse {
-    items: Vec<GitHubAdvisory>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct GitHubAdvisory {
-    id: String,
-    number: i64,
-    title: String,
-    state: String,
-    labels: Vec<Label>,
-    severity: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Label {
-    name: String,
-}
-
-/// Structure to store parsed action information
-#[derive(Debug, Clone)]
-struct ActionRef {
-    owner: String,
-    repo: String,
-    version: String,
-    is_commit_sha: bool,
-}
-
-impl ActionRef {
-    fn from_action_string(action: &str) -> Option<Self> {
-        // Skip docker actions
-        if action.starts_with("docker://") {
-            return None;
-        }
-        
-        // Parse action in format: owner/repo@version
-        let parts: Vec<&str> = action.split('@').collect();
-        if parts.len() != 2 {
-            return None;
-        }
-        
-        let repo_parts: Vec<&str> = parts[0].split('/').collect();
-        if repo_parts.len() != 2 {
-            return None;
-        }
-        
-        // Check if version is a commit SHA (40 hex characters)
-        let commit_sha_regex = Regex::new(r"^[0-9a-f]{40}$").unwrap();
-        let is_commit_sha = commit_sha_regex.is_match(parts[1]);
-        
-        Some(ActionRef {
-            owner: repo_parts[0].to_string(),
-            repo: repo_parts[1].to_string(),
-            version: parts[1].to_string(),
-            is_commit_sha,
-        })
+#[derive(StructOpt)]
+struct FixOpt {
+    #[structopt(parse(from_os_str), required = true)]
+    workflow_paths: Vec<PathBuf>,
+
+    /// Commit the fixes to a branch and open (or update) a pull request instead of writing files
+    #[structopt(long)]
+    create_pr: bool,
+
+    /// Repository to open the pull request in, as owner/repo
+    #[structopt(long, env = "GITHUB_REPOSITORY")]
+    repo: Option<String>,
+
+    /// Branch to open the pull request against (defaults to the repository's default branch)
+    #[structopt(long)]
+    base: Option<String>,
+
+    /// Branch the fixes are committed to
+    #[structopt(long, default_value = pr::DEFAULT_BRANCH)]
+    branch: String,
+
+    /// Labels to add to the pull request
+    #[structopt(long, use_delimiter = true)]
+    pr_labels: Vec<String>,
+
+    /// Open the pull request as a draft
+    #[structopt(long)]
+    draft: bool,
+
+    /// Print the changes as a diff instead of writing the files
+    #[structopt(long, conflicts_with = "create-pr")]
+    dry_run: bool,
+
+    /// Write the changes to this file as a patch for `git apply`, with what could not be fixed
+    /// listed at its end, instead of writing the workflows
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["create-pr", "dry-run"])]
+    emit_patch: Option<PathBuf>,
+
+    /// Repository root that workflow paths are made relative to
+    #[structopt(long, parse(from_os_str), default_value = ".")]
+    workspace: PathBuf,
+
+    /// Token that pushes the branch and opens the pull request, or - to read it from stdin
+    /// (default $GITHUB_TOKEN)
+    #[structopt(long)]
+    token: Option<String>,
+
+    /// File holding the token that pushes the branch and opens the pull request
+    #[structopt(long, parse(from_os_str))]
+    token_file: Option<PathBuf>,
+}
+
+#[derive(StructOpt)]
+struct ReviewBumpOpt {
+    /// Base revision of the change
+    #[structopt(long)]
+    base: Option<String>,
+
+    /// Head revision of the change
+    #[structopt(long)]
+    head: Option<String>,
+
+    /// Pull request event payload to take the base and head from when they are not given
+    #[structopt(long, env = "GITHUB_EVENT_PATH", parse(from_os_str))]
+    event_path: Option<PathBuf>,
+
+    /// Repository directory to look for changed workflows in (repeatable, default .github/workflows)
+    #[structopt(long = "workflows-dir", number_of_values = 1)]
+    workflows_dirs: Vec<String>,
+
+    /// Write the markdown verdict to a file instead of stdout
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Fail when a bumped action could not be checked
+    #[structopt(long)]
+    fail_on_unchecked: bool,
+
+    /// List at most this many findings in the verdict, highest severity first
+    #[structopt(long)]
+    max_findings: Option<usize>,
+}
+
+impl FixOpt {
+    fn options(&self) -> fix::FixOptions {
+        fix::FixOptions {
+            workflow_paths: self.workflow_paths.clone(),
+            create_pr: self.create_pr,
+            repo: self.repo.clone(),
+            base: self.base.clone(),
+            branch: self.branch.clone(),
+            pr_labels: self.pr_labels.clone(),
+            draft: self.draft,
+            dry_run: self.dry_run,
+            emit_patch: self.emit_patch.clone(),
+            workspace: self.workspace.clone(),
+            token: self.token.clone(),
+        }
     }
-    
-    fn full_name(&self) -> String {
-        format!("{}/{}", self.owner, self.repo)
+}
+
+impl ReviewBumpOpt {
+    fn options(&self) -> bump::ReviewOptions {
+        bump::ReviewOptions {
+            base: self.base.clone(),
+            head: self.head.clone(),
+            event_path: self.event_path.clone(),
+            workflows_dirs: self.workflows_dirs.clone(),
+            output: self.output.clone(),
+            fail_on_unchecked: self.fail_on_unchecked,
+            max_findings: self.max_findings,
+        }
+    }
+}
+
+#[derive(StructOpt)]
+enum FixturesCommand {
+    /// Run the scan as usual, writing every network lookup into a fixture directory for --fixtures
+    Record {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+    },
+}
+
+#[derive(StructOpt)]
+enum SchemaCommand {
+    /// Schema of the YAML config file
+    Config,
+    /// Schema of the JSON scan report
+    Report,
+}
+
+#[derive(StructOpt)]
+enum ConfigCommand {
+    /// Print the effective config, optionally for a given workflow file
+    Check {
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
+
+        /// Also check that each trusted owner is a real GitHub account (uses the API)
+        #[structopt(long)]
+        online: bool,
+
+        /// Print the rule levels rule_overrides resolve to for this action in the workflow
+        #[structopt(long)]
+        action: Option<String>,
+    },
+    /// Check a config file without scanning, e.g. to lint config changes in CI: unknown keys,
+    /// entries that do not name actions, duplicates (warnings) and the config schema
+    Validate {
+        /// Config file to check (default: the root config)
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
+    },
+}
+
+/// Why a run stopped before its findings could decide the exit, by the code each exits with
+#[derive(Debug)]
+enum RunError {
+    /// Arguments clap rejected: usage-error
+    Arguments(structopt::clap::Error),
+    /// Flags that cannot work together, or a value the scanner cannot use: usage-error
+    Usage(String),
+    /// A `--porcelain` version this build cannot write: unsupported-porcelain
+    UnsupportedPorcelain(String),
+    /// A config, workflow or lookup that failed, or output that could not be written:
+    /// operational-error, so it cannot be mistaken for findings
+    Operational(Box<dyn std::error::Error>),
+}
+
+impl RunError {
+    fn exit(&self) -> ExitCode {
+        match self {
+            RunError::Arguments(_) | RunError::Usage(_) => ExitCode::UsageError,
+            RunError::UnsupportedPorcelain(_) => ExitCode::UnsupportedPorcelain,
+            RunError::Operational(_) => ExitCode::OperationalError,
+        }
+    }
+
+    fn usage(message: impl Into<String>) -> Self {
+        RunError::Usage(message.into())
+    }
+
+    fn arguments(message: &str, kind: structopt::clap::ErrorKind) -> Self {
+        RunError::Arguments(structopt::clap::Error::with_description(message, kind))
+    }
+}
+
+/// Clap's message comes with its own `error:` prefix and the usage line
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Arguments(e) => write!(f, "{}", e.message),
+            RunError::Usage(message) | RunError::UnsupportedPorcelain(message) => write!(f, "Error: {}", message),
+            RunError::Operational(e) => write!(f, "Error: {}", e),
+        }
+    }
+}
+
+impl<E: Into<Box<dyn std::error::Error>>> From<E> for RunError {
+    fn from(error: E) -> Self {
+        RunError::Operational(error.into())
     }
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let opt = Opt::from_args();
-    let config = load_config(&opt.config)?;
+async fn main() {
+    let result = match parse_args() {
+        // Everything the run renders, on whichever thread, uses the charset its output allows
+        Ok(opt) => render::in_charset(render::Charset::detect(opt.ascii, opt.no_color), run(opt)).await,
+        Err(e) => Err(e),
+    };
+    // The run has returned, so everything it held, the `--debug-bundle` included, is written
+    // before the process exits
+    let code = match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            e.exit().code()
+        }
+    };
+    if code != 0 {
+        let _ = io::stdout().flush();
+        std::process::exit(code);
+    }
+}
+
+fn parse_args() -> Result<Opt, RunError> {
+    match Opt::from_args_safe() {
+        Ok(opt) => Ok(opt),
+        // --help and --version are printed by clap and exit 0
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => Err(RunError::Arguments(e)),
+    }
+}
+
+/// The scan or subcommand the arguments ask for, returning the code to exit with
+async fn run(mut opt: Opt) -> Result<i32, RunError> {
+    if opt.nul_terminated && opt.porcelain.is_none() && !opt.stdin_filelist {
+        return Err(RunError::arguments("-z needs --porcelain or --stdin-filelist", structopt::clap::ErrorKind::MissingRequiredArgument));
+    }
+    if opt.stdin_filelist && opt.token.as_deref() == Some("-") {
+        return Err(RunError::usage("--stdin-filelist and --token - both read stdin; pass the token with --token-file or $GITHUB_TOKEN"));
+    }
+    let stdin_workflows = opt.workflow_paths.iter().filter(|p| p.as_os_str() == STDIN_PATH).count();
+    if stdin_workflows > 1 {
+        return Err(RunError::usage("- is given more than once; stdin holds one workflow"));
+    }
+    if stdin_workflows == 1 && opt.token.as_deref() == Some("-") {
+        return Err(RunError::usage("- and --token - both read stdin; pass the token with --token-file or $GITHUB_TOKEN"));
+    }
+    if stdin_workflows == 0 && opt.filename.is_some() {
+        return Err(RunError::usage("--filename names the workflow read from stdin; pass - as a workflow path"));
+    }
+    if stdin_workflows == 1 && opt.annotate_workflow && opt.in_place {
+        return Err(RunError::usage("--in-place cannot write the annotated workflow back to stdin; without it a copy is written"));
+    }
+    opt.token = token::resolve(opt.token.as_deref(), opt.token_file.as_deref())?;
+    if let Some(Command::Fix(fix_opt)) = &mut opt.cmd {
+        fix_opt.token = token::resolve(fix_opt.token.as_deref(), fix_opt.token_file.as_deref())?;
+    }
+    let bundle = opt.debug_bundle.clone().map(|path| {
+        let mut secrets: Vec<String> = opt.token.iter().cloned().collect();
+        if let Some(Command::Fix(fix_opt)) = &opt.cmd {
+            secrets.extend(fix_opt.token.clone());
+        }
+        Arc::new(bundle::Bundle::new(path, secrets))
+    });
+    let verbosity = output::Verbosity::from_flags(opt.quiet, opt.verbose);
+    let endpoints = github::Endpoints::from_options(opt.api_url.as_deref(), opt.advisory_api_url.as_deref(), opt.osv_api_url.as_deref())?;
+    // `--max-findings` holds finding lines back for a prioritized list at the end of the run
+    let deferred = opt.max_findings.is_some() || matches!(&opt.cmd, Some(Command::ReviewBump(b)) if b.max_findings.is_some());
+    // Schemas describe the formats themselves and need neither a config nor the network
+    if let Some(Command::Schema(which)) = &opt.cmd {
+        let schema = match which {
+            SchemaCommand::Config => schema::config_schema(),
+            SchemaCommand::Report => schema::report_schema(),
+        };
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(0);
+    }
+    if let Some(Command::Config(ConfigCommand::Validate { path })) = &opt.cmd {
+        let path = path.as_ref().map(|p| p.to_string_lossy().into_owned());
+        let config_path = ConfigPath::resolve(path.as_deref().or(opt.config.as_deref()));
+        // Only a scan may fall back to the defaults for a missing file
+        if !Path::new(&config_path.path).is_file() {
+            return Err(format!("{}: no such config file", config_path.path).into());
+        }
+        let configs = ConfigSet::load(&config_path, true, None)?;
+        for warning in configs.take_warnings() {
+            eprintln!("{}", warning);
+        }
+        println!("{}: valid", config_path.path);
+        return Ok(0);
+    }
+    if let Some(Command::ExplainExitCode { code }) = &opt.cmd {
+        let Some(exit) = ExitCode::from_code(*code) else {
+            let known: Vec<String> = ExitCode::ALL.iter().map(|e| format!("{} {}", e.code(), e.name())).collect();
+            return Err(RunError::usage(format!("exit code {} is not one the scanner uses (known: {})", code, known.join(", "))));
+        };
+        println!("{} {}: {}", exit.code(), exit.name(), exit.meaning());
+        return Ok(0);
+    }
+    if let Some(version) = &opt.porcelain
+        && let Err(e) = porcelain::parse_version(version)
+    {
+        return Err(RunError::UnsupportedPorcelain(e));
+    }
+    let format = match opt.format {
+        Some(format) => format,
+        None if opt.porcelain.is_none() && workflow_commands::in_actions() => OutputFormat::Github,
+        None => OutputFormat::Text,
+    };
+    // Machine-readable reports own stdout; everything else printed goes to stderr
+    let machine = opt.porcelain.is_some() || matches!(format, OutputFormat::Json | OutputFormat::Sarif);
+    if machine && !matches!(&opt.cmd, None | Some(Command::Fixtures(_))) {
+        return Err(RunError::usage("--porcelain and --format json or sarif apply to scans, not to subcommands"));
+    }
+    let profile = match opt.profile.as_deref() {
+        Some("help") => {
+            write!(human_output(machine), "{}", profile::help())?;
+            return Ok(0);
+        }
+        Some(name) => Some(name.parse::<profile::Profile>().map_err(RunError::usage)?),
+        None => None,
+    };
+    let mut configs = ConfigSet::load(&ConfigPath::resolve(opt.config.as_deref()), opt.validate_config, profile)?;
+    if opt.no_default_trust {
+        configs = configs.without_default_trust();
+    }
+    let config = configs.root();
+    if let Some(minimum) = &config.minimum_scanner_version
+        && let Err(e) = version::check_minimum(minimum)
+    {
+        return Err(RunError::usage(format!("{}: {}", configs.root_path(), e)));
+    }
+    if uses_default_trust(config) {
+        verbosity.progress(format!(
+            "{} Using built-in default trust: {} (set trusted_owners to replace it, or pass --no-default-trust)",
+            Marker::Info,
+            DEFAULT_TRUSTED_OWNERS.join(", ")
+        ));
+    }
+    if let Some(bundle) = &bundle {
+        bundle.record_config(config);
+    }
+    let ignore = config::load_ignore_file(opt.ignore_file.as_deref())?;
+    let baseline = match &opt.baseline {
+        Some(path) => Some((path.clone(), baseline::load(path)?)),
+        None => None,
+    };
+    let clock = Clock::from_options(opt.timestamp)?;
     
-    let client = Client::builder()
+    let reqwest_client = Client::builder()
         .user_agent("github-action-security-scanner")
         .build()
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to build HTTP client: {}", e)))?;
-        
-    let actions = extract_actions_from_workflow(opt.workflow_path.to_str().unwrap())?;
+        .map_err(|e| std::io::Error::other(format!("Failed to build HTTP client: {}", e)))?;
+    let network = || {
+        let authed = Box::new(http::GitHubAuth::new(Box::new(http::ReqwestClient(reqwest_client)), opt.token.clone(), &endpoints.api));
+        Box::new(ratelimit::RateLimitRetry::new(authed, opt.max_retries, verbosity))
+    };
+    // The fixtures read and the baseline are checked for a newer format; the scan reports it
+    let formats = Arc::new(NewerFormats::default());
+    let client: Box<dyn HttpClient> = match (&opt.replay_http, &opt.record_http) {
+        (Some(fixture), _) => Box::new(http::Replayer::load(fixture, &formats)?),
+        (None, Some(fixture)) => Box::new(http::Recorder::new(network(), fixture.clone())),
+        (None, None) if opt.offline => Box::new(http::Offline),
+        (None, None) => network(),
+    };
+    let client: Box<dyn HttpClient> = match (&opt.fixtures, &opt.cmd) {
+        (Some(dir), _) => Box::new(fixtures::FixtureDir::new(dir.clone(), endpoints.clone(), (!opt.frozen).then_some(client), formats.clone())),
+        (None, Some(Command::Fixtures(FixturesCommand::Record { dir }))) => Box::new(fixtures::DirRecorder::new(client, dir.clone(), endpoints.clone())),
+        (None, _) => client,
+    };
+    let client: Box<dyn HttpClient> = match verbosity {
+        output::Verbosity::Verbose => Box::new(output::RequestDetails { inner: client }),
+        _ => client,
+    };
+    let client: Box<dyn HttpClient> = match &bundle {
+        Some(bundle) => Box::new(bundle::RequestLog { inner: client, bundle: bundle.clone() }),
+        None => client,
+    };
+    #[cfg(feature = "otlp")]
+    let telemetry = opt.otlp_endpoint.as_deref().map(|endpoint| telemetry::Telemetry::new(endpoint, opt.otlp_timeout));
+    #[cfg(feature = "otlp")]
+    let client: Box<dyn HttpClient> = match &telemetry {
+        Some(telemetry) => Box::new(telemetry::TracingClient { inner: client, telemetry: telemetry.clone() }),
+        None => client,
+    };
+    let pushgateway = opt
+        .pushgateway_url
+        .as_deref()
+        .map(|url| pushgateway::Pushgateway::new(url, opt.pushgateway_job.as_deref(), opt.pushgateway_instance.as_deref()));
+    let client: Box<dyn HttpClient> = match &pushgateway {
+        Some(gateway) => Box::new(pushgateway::RateLimitWatch { inner: client, gateway: gateway.clone() }),
+        None => client,
+    };
 
-    let mut vulnerable_actions = Vec::new();
-    let mut insecure_pinning = Vec::new();
-
-    println!("Scanning {} actions from workflow", actions.len());
-    
-    for action in &actions {
-        println!("Checking {} for vulnerabilities...", action);
-        
-        // Check dependency pinning
-        if !check_dependency_pinning(action, &config, opt.strict) {
-            insecure_pinning.push(action.clone());
-        }
-        
-        if let Some(action_ref) = ActionRef::from_action_string(action) {
-            // Check if owner is trusted (for supply chain attacks)
-            if !is_trusted_owner(&action_ref.owner, &config) {
-                println!("⚠️ Warning: Action {} is from non-trusted owner {}", action, action_ref.owner);
-                
-                // For non-trusted owners, only accept commit SHAs
-                if !action_ref.is_commit_sha {
-                    println!("❌ Non-trusted action {} should use commit SHA instead of tag/branch", action);
-                    insecure_pinning.push(action.clone());
-                }
+    let cache_dir = opt.cache_dir.clone().unwrap_or_else(DiskCache::default_dir);
+    let cache = DiskCache::new(cache_dir.clone(), opt.cache_ttl, opt.negative_cache_ttl);
+    let mut use_cache = !opt.no_cache;
+    if opt.cache_clear {
+        // Waiting for the exclusive lock blocks, which the runtime is told about
+        match tokio::task::block_in_place(|| cache.clear()) {
+            Ok(removed) => eprintln!("Cleared {} cache entries from {}", removed, cache_dir.display()),
+            // Entries that should have been cleared must not be used either
+            Err(e) => {
+                eprintln!("Warning: not using the cache: {}", e);
+                use_cache = false;
             }
-            
-            // Check for known vulnerabilities
-            match get_github_advisories(&client, &action_ref).await {
-                Ok(advisories) if !advisories.is_empty() => {
-                    println!("Vulnerability found in {}!", action);
-                    for advisory in &advisories {
-                        println!("- Advisory ID: {}, Title: {}", advisory.id, advisory.title);
+        }
+    }
+    let cache = match use_cache.then(|| cache.locked()) {
+        Some(Ok(cache)) => cache,
+        Some(Err(e)) => {
+            eprintln!("Warning: not using the cache: {}", e);
+            DiskCache::disabled()
+        }
+        None => DiskCache::disabled(),
+    }
+    .with_verbosity(verbosity);
+
+    let options = ScanOptions {
+        strict: opt.strict,
+        sources: opt.source,
+        endpoints,
+        token: opt.token.clone(),
+        visibility: opt.repo_visibility,
+        offline: opt.offline,
+        recursive: opt.recursive,
+        lenient_templates: opt.lenient_templates,
+        filter: ActionFilter { only: opt.only.clone(), skip: opt.skip.clone() },
+        builtin_indicators: !opt.no_builtin_indicators,
+        diff: match (&opt.diff, &opt.diff_base) {
+            (Some(path), _) => Some(diff::Base::Path(path.clone())),
+            (None, Some(revision)) => Some(diff::Base::Revision(revision.clone())),
+            (None, None) => None,
+        },
+        ignore,
+        baseline,
+        write_baseline: opt.write_baseline.clone(),
+        update_baseline: opt.update_baseline,
+        require_pinning_comment_style: opt.require_pinning_comment_style,
+        verify_pin_comments: opt.verify_pin_comments,
+        describe_pins: opt.describe_pins,
+        verify_shas: opt.verify_shas,
+        outdated: match (opt.check_outdated, opt.fail_on_outdated) {
+            (_, true) => Some(Level::Error),
+            (true, false) => Some(Level::Warning),
+            (false, false) => None,
+        },
+        repo_status: opt.check_repo_status,
+        runtime: opt.check_runtime,
+        advisory_history: opt.advisory_history,
+        transitive: opt.transitive.then(|| opt.transitive_depth.unwrap_or(transitive::DEFAULT_DEPTH)),
+        max_depth_fail: opt.max_depth_fail,
+        concurrency: opt.concurrency,
+        scan_run_steps: opt.scan_run_steps,
+        scan_github_script: opt.scan_github_script,
+        scan_cache_poisoning: opt.scan_cache_poisoning,
+        check_dependabot: opt.check_dependabot,
+        dependabot_config: opt.dependabot_config.clone(),
+        advisories_since: opt.advisories_since,
+        fail_only_recent: opt.fail_only_recent,
+        exit: ExitPolicy {
+            fail_on_unchecked: opt.fail_on_unchecked,
+            require_complete: opt.require_complete,
+            fail_on_unused_config: opt.fail_on_unused_config,
+            fail_on_warnings: opt.fail_on_warnings,
+            exit_zero: opt.exit_zero,
+            fail_on_severity: opt.fail_on_severity,
+            unknown_severity: opt.unknown_severity,
+            ..Default::default()
+        },
+        rule_id_compat: opt.rule_id_compat,
+    };
+    let console = |scanned: &str| Console {
+        out: Mutex::new(human_output(machine)),
+        deferred,
+        scanned: scanned.to_string(),
+        pins: Mutex::default(),
+        verbosity,
+        bundle: bundle.clone(),
+        #[cfg(feature = "otlp")]
+        telemetry: telemetry.clone(),
+    };
+    let scanner = Scanner::new(configs, options)
+        .with_client(client)
+        .with_cache(cache)
+        .with_clock(clock)
+        .with_newer_formats(formats)
+        .with_events(Box::new(console("")));
+    scanner.report_warnings();
+    let configs = scanner.configs();
+    let config = scanner.config();
+
+    match &opt.cmd {
+        Some(Command::Fix(fix_opt)) => {
+            let code = fix::run(&scanner, &fix_opt.options()).await?;
+            scanner.report_warnings();
+            return Ok(code);
+        }
+        Some(Command::Config(ConfigCommand::Check { path, online, action })) => {
+            let checked = match path {
+                Some(path) => {
+                    let effective = configs.for_workflow(path)?;
+                    println!("# Effective config for {} ({})", path.display(), effective.describe(&configs.describe_root()));
+                    for key in &effective.overridden_enforced {
+                        println!("# {} is enforced by {}; the scoped value is ignored", key, configs.root_path());
                     }
-                    vulnerable_actions.push(action.clone());
+                    print!("{}", serde_yaml::to_string(&effective.config)?);
+                    effective.config
                 }
-                Ok(_) => println!("✅ No known vulnerabilities for {}", action),
-                Err(e) => {
-                    eprintln!("Failed to check {}: {}", action, e);
+                None => {
+                    println!("# Effective config ({})", configs.describe_root());
+                    print!("{}", serde_yaml::to_string(config)?);
+                    config.clone()
                 }
+            };
+            if let Some(action) = action {
+                let surface = match path {
+                    Some(path) => match load_workflow(&path.to_string_lossy()) {
+                        Ok(workflow) => {
+                            let (visibility, _) = scanner.visibility().await;
+                            Some(surface::classify(&workflow.value, visibility))
+                        }
+                        Err(_) => None,
+                    },
+                    None => None,
+                };
+                print_rule_overrides(&checked, path.as_deref(), action, surface.as_ref());
             }
-        } else {
-            println!("Skipping malformed action reference: {}", action);
+            if *online {
+                for warning in accounts::check_trusted_owners(&scanner.api(), scanner.cache(), &checked, &known_owners(&checked)).await {
+                    eprintln!("Warning: {}", warning);
+                }
+            }
+            scanner.report_warnings();
+            return Ok(0);
+        }
+        Some(Command::ReviewBump(bump_opt)) => {
+            let code = bump::run(&scanner, &bump_opt.options()).await?;
+            scanner.report_warnings();
+            return Ok(code);
+        }
+        Some(Command::Vendor { action, json }) => {
+            let summary = lookup::summarize(&scanner, config, action).await?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                print!("{}", summary.render());
+            }
+            scanner.report_warnings();
+            return Ok(summary.exit.code);
         }
-        
-        // Add a small delay to avoid rate limiting
-        sleep(Duration::from_millis(100)).await;
+        // Recording is a normal scan through the recording client
+        Some(
+            Command::Schema(_)
+            | Command::Config(ConfigCommand::Validate { .. })
+            | Command::Fixtures(_)
+            | Command::ExplainExitCode { .. },
+        )
+        | None => {}
     }
 
-    // Create a final report
-    if !vulnerable_actions.is_empty() || !insecure_pinning.is_empty() {
-        println!("\n⛔ Security scan failed!");
-        
-        if !vulnerable_actions.is_empty() {
-            println!("\nVulnerable actions found:");
-            for action in &vulnerable_actions {
-                println!("- {}", action);
+    // Explicit paths always win over --workflows-dir, which wins over the workspace defaults
+    let (scan_paths, default_source) = match opt.workflow_paths.as_slice() {
+        [] if opt.stdin_filelist => {
+            let paths = filelist::read_stdin(opt.nul_terminated).map_err(|e| RunError::usage(e.to_string()))?;
+            if paths.is_empty() && !opt.allow_empty {
+                return Err(RunError::usage("--stdin-filelist read no paths from stdin (pass --allow-empty to accept an empty list)"));
+            }
+            (paths, Some("--stdin-filelist"))
+        }
+        [] if opt.self_workflow => (vec![self_workflow()?], Some("--self")),
+        [_, ..] => (opt.workflow_paths.clone(), None),
+        [] if !opt.workflows_dirs.is_empty() => (opt.workflows_dirs.clone(), Some("--workflows-dir")),
+        [] => match default_workflow_dir() {
+            Ok((path, source)) => (vec![path], Some(source)),
+            Err(tried) => {
+                return Err(RunError::arguments(
+                    &format!("no workflow path given and no default workflow directory found; tried:\n{}", tried.join("\n")),
+                    structopt::clap::ErrorKind::MissingRequiredArgument,
+                ));
             }
+        },
+    };
+    let stdin_name = opt.filename.as_deref().unwrap_or(STDIN_NAME);
+    let listed = scan_paths
+        .iter()
+        .map(|p| match p.as_os_str() == STDIN_PATH {
+            true => format!("{} (stdin)", stdin_name),
+            false => p.display().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let scanned_note = match default_source {
+        Some("--stdin-filelist") if scan_paths.is_empty() => "no paths (--stdin-filelist)".to_string(),
+        Some(flag @ ("--workflows-dir" | "--self" | "--stdin-filelist")) => format!("{} ({})", listed, flag),
+        Some(source) => format!("{} (default from {})", listed, source),
+        None => listed,
+    };
+
+    let scanner = scanner.with_events(Box::new(console(&scanned_note)));
+    let scanner = match scan_paths.iter().any(|p| p.as_os_str() == STDIN_PATH) {
+        true => {
+            let mut source = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut source).map_err(|e| ScanError::io(STDIN_NAME, e))?;
+            scanner.with_stdin_workflow(stdin_name, source)
+        }
+        false => scanner,
+    };
+
+    // Check the run environment before scanning rather than after
+    let snapshot_context = if opt.submit_dependency_snapshot {
+        let token = opt
+            .token
+            .clone()
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| RunError::usage("--submit-dependency-snapshot needs --token, --token-file or GITHUB_TOKEN"))?;
+        Some((snapshot::RunContext::from_env()?, token))
+    } else {
+        None
+    };
+
+    let blame = opt.blame && blame::check_checkout()?;
+    if opt.blame && !blame {
+        scanner.events().warning(blame::GIT_MISSING);
+    }
+
+    let codeowners = if opt.codeowners {
+        let (codeowners, warnings) = codeowners::CodeOwners::discover(&repo_root())?;
+        for warning in warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        Some(codeowners)
+    } else {
+        None
+    };
+
+    let ScanOutcome { mut report, decision, overridden, unused, kept_older, inventory } = scanner.scan(&scan_paths).await?;
+    let mut out = human_output(machine);
+    if !overridden.is_empty() {
+        writeln!(out, "\nRule overrides applied:")?;
+        for (finding, was) in &overridden {
+            writeln!(out, "{} {} ({}): {} -> {}", finding.level.marker(), finding.message, finding.rule.id(), was, finding.level)?;
+        }
+    }
+    print_suppressions(&mut out, &report)?;
+    if !unused.is_empty() && !deferred {
+        writeln!(out, "\nConfig hygiene:")?;
+        for finding in &unused {
+            render_finding(&mut out, finding, &report.pins)?;
         }
-        
-        if !insecure_pinning.is_empty() {
-            println!("\nActions with insecure version pinning:");
-            for action in &insecure_pinning {
-                println!("- {}", action);
+    }
+
+    // The inventory is submitted whole, whatever --only/--skip narrowed the checks to
+    if let Some((context, token)) = &snapshot_context {
+        let payload = snapshot::payload(context, scanner.clock(), &inventory);
+        let api = GitHubApi::new(scanner.client(), scanner.endpoints(), Some(token));
+        match snapshot::submit(&api, context, &payload).await {
+            Ok(snapshot::Submission::Accepted(id, result)) => {
+                verbosity.progress(format!("\n{} Dependency snapshot {} submitted to {} ({})", Marker::Ok, id, context.repo, result))
             }
+            Ok(snapshot::Submission::Unavailable(reason)) => verbosity.progress(format!(
+                "\n{} Dependency snapshot not submitted: {} does not accept submissions ({})",
+                Marker::Warn,
+                context.repo,
+                reason
+            )),
+            Err(e) => eprintln!("Warning: could not submit dependency snapshot: {}", e),
+        }
+    }
+
+    if let Some(since) = opt.advisories_since
+        && !kept_older.is_empty()
+        && !deferred
+    {
+        writeln!(out, "\nAdvisories published before {} that still fail the scan:", since.format("%Y-%m-%d"))?;
+        for finding in &kept_older {
+            render_finding(&mut out, finding, &report.pins)?;
+        }
+    }
+    if let Some(codeowners) = &codeowners {
+        codeowners::assign(&mut report, codeowners, &repo_root());
+    }
+    let (blame_lines, blame_notes) = if blame { blame::assign(&mut report) } else { Default::default() };
+    #[cfg(feature = "otlp")]
+    if let Some(telemetry) = &telemetry {
+        telemetry.finish(&report, scanner.events()).await;
+    }
+    if let Some(gateway) = &pushgateway {
+        gateway.push(&report, decision.exit.code).await;
+    }
+    if opt.max_findings.is_some() {
+        print_prioritized(&mut out, &report, opt.max_findings)?;
+    }
+    let structured_max = opt.max_findings.filter(|_| opt.truncate_structured);
+    let service_messages = match format {
+        OutputFormat::Teamcity => teamcity::render(&report, &decision, structured_max),
+        OutputFormat::Github => workflow_commands::render(&report, &decision, structured_max),
+        OutputFormat::Text | OutputFormat::Json | OutputFormat::Sarif => Vec::new(),
+    };
+    // JSON and SARIF, on stdout or written out, list everything unless --truncate-structured
+    let truncated = structured_max.map(|max| report.truncated(max));
+    let structured = truncated.as_ref().unwrap_or(&report);
+    let mut written = match &opt.output_dir {
+        Some(dir) => {
+            let requested = if opt.emit.is_empty() { artifacts::Artifact::ALL.to_vec() } else { opt.emit.clone() };
+            artifacts::write_all(dir, &requested, structured, &decision)
+        }
+        None => Vec::new(),
+    };
+    if let Some(path) = &opt.report {
+        let format = opt.report_format.unwrap_or(artifacts::Artifact::Markdown);
+        written.push(artifacts::write_report(path, format, structured, &decision));
+    }
+    let artifact_failed = written.iter().any(|w| w.result.is_err());
+    let annotated = if opt.annotate_workflow { annotate::write_all(&report, opt.in_place)? } else { Vec::new() };
+    if machine {
+        let mut stdout = io::stdout().lock();
+        if opt.porcelain.is_some() {
+            stdout.write_all(&porcelain::render(&report, &decision, opt.nul_terminated))?;
+        } else {
+            let value = match format {
+                OutputFormat::Sarif => artifacts::sarif(structured, &decision),
+                _ => artifacts::json_report(structured, &decision)?,
+            };
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&value)?)?;
+        }
+        stdout.flush()?;
+    }
+
+    // Create a final report
+    if decision.exit.code != 0 {
+        writeln!(out, "\n{} Security scan failed!", Marker::Stop)?;
+        print_failures(&mut out, &report, &decision)?;
+    } else {
+        decision.print_banner(&mut out)?;
+    }
+    decision.print_files(&mut out)?;
+    if let Some(codeowners) = &codeowners {
+        codeowners::print_groups(&mut out, &report, codeowners, &repo_root())?;
+    }
+    if blame {
+        blame::print(&mut out, &blame_lines, &blame_notes)?;
+    }
+    print_scan_scope(&mut out, &scanned_note, &report, &scanner.options().filter)?;
+    if let Some(summary) = &report.summary {
+        summary.print(&mut out)?;
+    }
+    if !written.is_empty() {
+        artifacts::print_index(&mut out, &written)?;
+    }
+    annotate::print(&mut out, &annotated)?;
+    decision.print(&mut out)?;
+    // The findings decide the exit; a failed artifact only fails a run they pass
+    let mut code = decision.process_code();
+    if artifact_failed && !opt.exit_zero {
+        if decision.exit.code == 0 {
+            let exit = ExitCode::ArtifactFailure;
+            writeln!(out, "- exit {} ({}) instead: some artifacts could not be written", exit.code(), exit.name())?;
+            code = exit.code();
+        } else {
+            writeln!(out, "- some artifacts could not be written either")?;
+        }
+    }
+    print_service_messages(&mut out, &service_messages)?;
+    out.flush()?;
+    Ok(code)
+}
+
+/// What failed the scan: blocked dependencies, the failing sites of each category, scan-level
+/// violations, failed lookups, and what the policy flags made fail
+fn print_failures(out: &mut dyn Write, report: &ScanReport, decision: &Decision) -> io::Result<()> {
+    let blocked: BTreeSet<&str> =
+        report.findings.iter().filter(|f| f.rule == Rule::BlockedDependency).map(|f| f.message.as_str()).collect();
+    if !blocked.is_empty() {
+        writeln!(out, "\nBlocked dependencies:")?;
+        for message in blocked {
+            writeln!(out, "- {}", message)?;
+        }
+    }
+
+    print_failing_sites(out, report, Category::Vulnerability, "Vulnerable actions found:")?;
+    print_failing_sites(out, report, Category::Policy, "Actions with insecure version pinning:")?;
+
+    let scan_level: Vec<&Finding> = report
+        .findings
+        .iter()
+        .filter(|f| f.is_scan_level() && f.level >= decision.policy.fail_on)
+        .collect();
+    if !scan_level.is_empty() {
+        writeln!(out, "\nScan-level policy violations:")?;
+        for finding in scan_level {
+            writeln!(out, "- {}", finding.message)?;
+        }
+    }
+
+    if !decision.errors.is_empty() {
+        writeln!(out, "\nLookups that failed:")?;
+        for error in &decision.errors {
+            writeln!(out, "- {}", error)?;
+        }
+    }
+
+    if decision.unchecked_applied {
+        writeln!(out, "\nActions that could not be checked:")?;
+        for action in &report.unchecked {
+            writeln!(out, "- {}", report.pins.display(action))?;
         }
-        
-        std::process::exit(1);
     }
 
-    println!("\n✅ All actions passed security checks!");
+    if decision.unused_config_applied {
+        writeln!(out, "\nUnused config entries:")?;
+        for finding in report.findings.iter().filter(|f| f.rule == Rule::UnusedConfigEntry) {
+            writeln!(out, "- {}", finding.message)?;
+        }
+    }
     Ok(())
 }
 
-/// Check if an owner is in the trusted owners list
-fn is_trusted_owner(owner: &str, config: &Config) -> bool {
-    config.trusted_owners.as_ref().map_or(false, |owners| owners.contains(owner))
+/// The findings held back by `--max-findings`, highest severity first, then the overflow count
+fn print_prioritized(out: &mut dyn Write, report: &ScanReport, max_findings: Option<usize>) -> io::Result<()> {
+    let (findings, hidden) = report.prioritized(max_findings);
+    if findings.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nFindings (highest severity first):")?;
+    for finding in findings {
+        render_finding(out, finding, &report.pins)?;
+    }
+    if hidden > 0 {
+        writeln!(out, "{}", render::overflow_line(hidden, "see JSON report"))?;
+    }
+    Ok(())
 }
 
-fn extract_actions_from_workflow(workflow_path: &str) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(workflow_path)?;
-    let workflow: Value = serde_yaml::from_str(&content)?;
+/// Service messages go last so TeamCity's status text reflects the complete run
+fn print_service_messages(out: &mut dyn Write, lines: &[String]) -> io::Result<()> {
+    if !lines.is_empty() {
+        writeln!(out)?;
+    }
+    for line in lines {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
 
-    let mut actions = HashSet::new();
-    if let Some(jobs) = workflow.get("jobs").and_then(|v| v.as_mapping()) {
-        for job in jobs.values() {
-            if let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) {
-                for step in steps {
-                    if let Some(action) = step.get("uses").and_then(|v| v.as_str()) {
-                        actions.insert(action.to_string());
-                    }
-                }
+/// `config check --action`: which rule_overrides entry, if any, decides each rule's level
+fn print_rule_overrides(config: &Config, workflow: Option<&Path>, action: &str, surface: Option<&surface::Surface>) {
+    let workflow = workflow.map(|p| p.to_string_lossy().into_owned());
+    let site = match &workflow {
+        Some(workflow) => format!("{} in {}", action, workflow),
+        None => action.to_string(),
+    };
+    println!("# Rule overrides for {}", site);
+    if let Some(surface) = surface {
+        println!("# trigger surface: {}", surface.describe());
+    }
+    let mut any = false;
+    for rule in Rule::ALL {
+        if let Some((index, entry)) = config.rule_override(rule.id(), workflow.as_deref(), action, surface.map(|s| s.surface)) {
+            let trigger_surface = entry.trigger_surface.map(|s| s.to_string());
+            let selectors: Vec<String> = [("workflow", &entry.workflow), ("action", &entry.action), ("trigger_surface", &trigger_surface)]
+                .into_iter()
+                .filter_map(|(key, value)| Some(format!("{} {}", key, value.as_ref()?)))
+                .collect();
+            match selectors.is_empty() {
+                true => println!("# {}: {} (entry {}, unscoped)", rule.id(), entry.level, index + 1),
+                false => println!("# {}: {} (entry {}: {})", rule.id(), entry.level, index + 1, selectors.join(", ")),
+            }
+            any = true;
+        }
+    }
+    if !any {
+        println!("# none apply; every rule keeps its built-in level");
+    }
+}
+
+/// Findings the ignore file covers, and those whose suppression has run out; baselined findings
+/// and those `--diff` found unchanged are listed apart
+fn print_suppressions(out: &mut dyn Write, report: &ScanReport) -> io::Result<()> {
+    let baselined: Vec<&Finding> = report.findings.iter().filter(|f| f.suppression.as_ref().is_some_and(|s| s.baseline)).collect();
+    if !baselined.is_empty() {
+        writeln!(out, "\nIn the baseline, so not failing the scan:")?;
+        for finding in baselined {
+            match finding.location() {
+                Some(location) => writeln!(out, "{} {}: {}", Marker::Info, location, report.pins.annotate(&finding.message))?,
+                None => writeln!(out, "{} {}: {}", Marker::Info, finding.action, report.pins.annotate(&finding.message))?,
+            }
+        }
+    }
+    let unchanged: Vec<&Finding> = report.findings.iter().filter(|f| f.suppression.as_ref().is_some_and(|s| s.unchanged)).collect();
+    if let (false, Some(diff)) = (unchanged.is_empty(), &report.diff) {
+        writeln!(out, "\nUnchanged since {}, so not failing the scan:", diff.base)?;
+        for finding in unchanged {
+            match finding.location() {
+                Some(location) => writeln!(out, "{} {}: {}", Marker::Info, location, report.pins.annotate(&finding.message))?,
+                None => writeln!(out, "{} {}: {}", Marker::Info, finding.action, report.pins.annotate(&finding.message))?,
             }
         }
     }
-    Ok(actions)
+    let covered: Vec<&Finding> =
+        report.findings.iter().filter(|f| f.suppression.as_ref().is_some_and(|s| !s.baseline && !s.unchanged)).collect();
+    if covered.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nSuppressed by the ignore file:")?;
+    for finding in covered {
+        let Some(suppression) = &finding.suppression else { continue };
+        // Advisory messages do not name the action they were found in
+        let what = if finding.message.contains(&finding.action) {
+            finding.message.clone()
+        } else {
+            format!("{}: {}", finding.action, finding.message)
+        };
+        match (&suppression.expires, suppression.expired) {
+            (Some(expires), true) => writeln!(
+                out,
+                "{} {}: suppression expired on {} and it counts again (was: {})",
+                Marker::Fail,
+                what,
+                expires,
+                suppression.reason
+            )?,
+            (Some(expires), false) => writeln!(out, "{} {}: suppressed until {} ({})", Marker::Info, what, expires, suppression.reason)?,
+            (None, _) => writeln!(out, "{} {}: suppressed ({})", Marker::Info, what, suppression.reason)?,
+        }
+    }
+    Ok(())
+}
+
+/// Each failing finding of the category at the file, job and step it applies to, such as
+/// `ci.yml › deploy › "Post release notes": actions/github-script@main — unstable reference`
+fn print_failing_sites(out: &mut dyn Write, report: &ScanReport, category: Category, header: &str) -> io::Result<()> {
+    let failing = report.failing_sites(category);
+    if failing.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n{}", header)?;
+    for (site, action, rule) in failing {
+        let what = format!("{}{}{}", report.pins.display(action), render::dash(), rule.id().replace('-', " "));
+        match site {
+            Some(site) => writeln!(out, "- {}: {}", site, what)?,
+            None => writeln!(out, "- {}", what)?,
+        }
+    }
+    Ok(())
+}
+
+fn render_finding(out: &mut dyn Write, finding: &Finding, pins: &describe::Pins) -> io::Result<()> {
+    let message = pins.annotate(&finding.message);
+    match finding.location() {
+        Some(location) => writeln!(out, "{} {}: {}", finding.level.marker(), location, message)?,
+        None => writeln!(out, "{} {}", finding.level.marker(), message)?,
+    }
+    if let Some(blame) = &finding.blame {
+        writeln!(out, "  {}", blame.describe())?;
+    }
+
+    Ok(())
+
+}
+
+/// Where the human-readable output goes: stdout, unless a machine-readable report owns it
+fn human_output(machine: bool) -> Box<dyn Write + Send> {
+    match machine {
+        true => Box::new(io::stderr()),
+        false => Box::new(io::stdout()),
+    }
+}
+
+/// The scan's events as the CLI prints them: findings to the human-readable output as they are
+/// found, unless `--max-findings` holds them for the end of the run, and progress to stderr
+struct Console {
+    out: Mutex<Box<dyn Write + Send>>,
+    deferred: bool,
+    /// What was scanned, for the line announcing the scan
+    scanned: String,
+    /// The versions `--describe-pins` resolved, once the scan has them
+    pins: Mutex<describe::Pins>,
+    verbosity: output::Verbosity,
+    /// `--debug-bundle`, which keeps the report as it stands
+    bundle: Option<Arc<bundle::Bundle>>,
+    #[cfg(feature = "otlp")]
+    telemetry: Option<std::sync::Arc<telemetry::Telemetry>>,
+}
+
+impl Console {
+    /// Write to the human-readable output. Events cannot fail the scan, so a write that fails is
+    /// dropped.
+    fn print(&self, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) {
+        let mut out = self.out.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = write(out.as_mut());
+    }
+
+    fn resolved_pins(&self) -> describe::Pins {
+        self.pins.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
 }
 
-// Check if a dependency is in the critical_dependencies.yml config
-fn check_dependency_pinning(action: &str, config: &Config, strict: bool) -> bool {
-    let is_critical = config.critical_dependencies.as_ref().map_or(false, |deps| deps.contains(&action.to_string()));
+impl ScanEvents for Console {
+    fn scanning(&self, actions: usize, workflows: usize) {
+        self.verbosity.progress(format!("Scanning {} actions from {} workflow file(s) in {}", actions, workflows, self.scanned));
+    }
 
-    // Skip Docker actions for pinning checks
-    if action.starts_with("docker://") {
-        return true;
+    fn progress(&self, message: &str) {
+        self.verbosity.progress(message);
     }
 
-    if is_critical {
-        if !action.contains('@') {
-            println!("❌ Critical dependency {} is not pinned at all!", action);
-            return false;
-        } else if action.contains("@main") || action.contains("@master") || action.contains("@latest") {
-            println!("❌ Critical dependency {} is using an unstable reference!", action);
-            return false;
+    fn warning(&self, message: &str) {
+        eprintln!("{}", message);
+    }
+
+    fn pins(&self, pins: &describe::Pins) {
+        *self.pins.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = pins.clone();
+    }
+
+    fn finding(&self, finding: &Finding) {
+        if !self.deferred {
+            let pins = self.resolved_pins();
+            self.print(|out| render_finding(out, finding, &pins));
+        }
+    }
+
+    fn policy_finding(&self, finding: &Finding) {
+        if !self.deferred {
+            // The untrusted-owner line has always carried a "Warning:" prefix
+            let prefix = if finding.rule == Rule::UntrustedOwner { "Warning: " } else { "" };
+            let message = self.resolved_pins().annotate(&finding.message);
+            self.print(|out| writeln!(out, "{} {}{}", finding.level.marker(), prefix, message));
         }
-        
-        // For critical dependencies, prefer commit SHAs
-        if let Some(action_ref) = ActionRef::from_action_string(action) {
-            if !action_ref.is_commit_sha {
-                println!("⚠️ Critical dependency {} is pinned to a tag, not a commit SHA", action);
-                // This is just a warning, not a failure
+    }
+
+    fn vulnerable(&self, action: &str, cached: bool) {
+        self.print(|out| writeln!(out, "Vulnerability found in {}!{}", action, if cached { " (cached)" } else { "" }));
+    }
+
+    fn advisory(&self, advisory: &GitHubAdvisory, finding: &Finding) {
+        if !self.deferred {
+            self.print(|out| {
+                writeln!(
+                    out,
+                    "- Advisory ID: {} ({}), Title: {}, Severity: {}, {}{}",
+                    advisory.id,
+                    advisory.sources.join(", "),
+                    advisory.title,
+                    finding.severity.map_or("unknown", |s| s.name()),
+                    advisory.fix().note(),
+                    finding.due_by.as_ref().map(|due| format!(", due by {}", due)).unwrap_or_default()
+                )
+            });
+        }
+    }
+
+    #[cfg(feature = "otlp")]
+    fn phase(&self, phase: &str) {
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.phase(phase);
+        }
+    }
+
+    fn report(&self, report: &ScanReport) {
+        if let Some(bundle) = &self.bundle {
+            bundle.record_report(report);
+        }
+    }
+}
+
+/// Say what was scanned, so a green run over a default path or with filters is obviously partial
+fn print_scan_scope(out: &mut dyn Write, scanned_note: &str, report: &ScanReport, filter: &ActionFilter) -> io::Result<()> {
+    writeln!(out, "\nScanned: {} at {}", scanned_note, report.generated_at)?;
+    writeln!(out, "Repository visibility: {}", report.visibility)?;
+    if report.configs.values().any(|c| c.contains(" over ")) {
+        writeln!(out, "Configs:")?;
+        for (file, config) in &report.configs {
+            writeln!(out, "- {}: {}", file, config)?;
+        }
+    }
+    if !report.lenient.is_empty() {
+        writeln!(out, "Parsed leniently (--lenient-templates; workflow-level rules skipped):")?;
+        for file in &report.lenient {
+            writeln!(out, "- {}", file)?;
+        }
+    }
+    if !report.vendored.is_empty() {
+        writeln!(out, "Vendored actions:")?;
+        for vendored in &report.vendored {
+            writeln!(out, "- {} from {} ({})", vendored.local, vendored.upstream, vendored.source)?;
+        }
+    }
+    if filter.is_active() {
+        writeln!(
+            out,
+            "Partial scan: {} action(s) filtered out by --only/--skip, {} checked",
+            report.filtered_out, report.actions_scanned
+        )?;
+    }
+    if let Some(since) = &report.advisories_since {
+        let kept = match since.kept {
+            0 => String::new(),
+            kept => format!("; {} older still shown because they fail the scan (--fail-only-recent drops them)", kept),
+        };
+        writeln!(out, "Advisories published before {}: {} left out (--advisories-since){}", since.since, since.filtered, kept)?;
+    }
+    Ok(())
+}
+
+/// Owners the config and the workspace's workflows refer to, as corrections for misspelt trusted
+/// owners
+fn known_owners(config: &Config) -> BTreeSet<String> {
+    let mut actions: BTreeSet<String> = config.critical_dependencies.iter().flatten().cloned().collect();
+    actions.extend(config.vendored_actions.iter().flat_map(|m| m.values().cloned()));
+    if let Ok((dir, _)) = default_workflow_dir() {
+        for file in scanner::workflow_files(&dir, false, &scanner::Silent).unwrap_or_default() {
+            if let Ok(used) = extract_actions_from_workflow(&file) {
+                actions.extend(used);
             }
         }
-    } else if strict {
-        if !action.contains('@') {
-            println!("❌ Dependency {} is not pinned (failing due to --strict)", action);
-            return false;
-        } else if action.contains("@main") || action.contains("@master") || action.contains("@latest") {
-            println!("❌ Dependency {} is using an unstable reference (failing due to --strict)", action);
-            return false;
+    }
+    actions.iter().filter_map(|a| ActionRef::from_action_string(a)).map(|a| a.owner).collect()
+}
+
+/// Find the workflow directory to scan when no path is given.
+///
+/// Returns the directory and the name of the default that produced it, or every location tried.
+fn default_workflow_dir() -> Result<(PathBuf, &'static str), Vec<String>> {
+    let mut tried = Vec::new();
+    match std::env::var_os("GITHUB_WORKSPACE") {
+        Some(workspace) => {
+            let dir = PathBuf::from(workspace).join(".github/workflows");
+            if dir.is_dir() {
+                return Ok((dir, "GITHUB_WORKSPACE"));
+            }
+            tried.push(format!("  {} (from GITHUB_WORKSPACE, not a directory)", dir.display()));
         }
+        None => tried.push("  $GITHUB_WORKSPACE/.github/workflows (GITHUB_WORKSPACE is not set)".to_string()),
     }
 
-    true
+    let dir = PathBuf::from("./.github/workflows");
+    if dir.is_dir() {
+        return Ok((dir, "the current directory"));
+    }
+    tried.push(format!("  {} (not a directory)", dir.display()));
+    Err(tried)
 }
 
-/// Query GitHub's advisory database for vulnerabilities in a given GitHub Action
-async fn get_github_advisories(client: &Client, action_ref: &ActionRef) -> Result<Vec<GitHubAdvisory>, std::io::Error> {
-    let url = format!(
-        "https://api.github.com/search/issues?q=repo:github/advisory-database+is:issue+is:open+label:{}/{}",
-        action_ref.owner, action_ref.repo
-    );
-    
-    let response = client.get(&url)
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("HTTP request failed: {}", e)))?;
-
-    match response.status() {
-        StatusCode::OK => {
-            let response_data = response.json::<GitHubAdvisoryResponse>()
-                .await
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("JSON parsing failed: {}", e)))?;
-            Ok(response_data.items)
-        },
-        
-        StatusCode::TOO_MANY_REQUESTS => {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|val| val.to_str().ok())
-                .and_then(|val| val.parse::<u64>().ok())
-                .unwrap_or(60);
-                
-            println!("Rate limited. Retrying after {} seconds...", retry_after);
-            sleep(Duration::from_secs(retry_after)).await;
-            
-            Box::pin(get_github_advisories(client, action_ref)).await
-        },
+/// Split a workflow ref such as `owner/repo/.github/workflows/ci.yml@refs/heads/main` into the
+/// repository, the workflow's path within it and the git ref
+fn parse_workflow_ref(workflow_ref: &str) -> Option<(String, String, String)> {
+    let (location, git_ref) = workflow_ref.rsplit_once('@')?;
+    let mut parts = location.splitn(3, '/');
+    let (owner, repo, path) = (parts.next()?, parts.next()?, parts.next()?);
+    if owner.is_empty() || repo.is_empty() || path.is_empty() || !git_ref.starts_with("refs/") {
+        return None;
+    }
+    Some((format!("{}/{}", owner, repo), path.to_string(), git_ref.to_string()))
+}
 
-        status => {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("Unexpected HTTP status: {}", status)
-            ))
+/// The running workflow's file in the workspace, for `--self`. `GITHUB_WORKFLOW_REF` names it;
+/// `workflow_dispatch` payloads in `GITHUB_EVENT_PATH` carry the path as a fallback.
+fn self_workflow() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (repository, path) = match std::env::var("GITHUB_WORKFLOW_REF") {
+        Ok(workflow_ref) => {
+            let (repository, path, _) = parse_workflow_ref(&workflow_ref).ok_or_else(|| {
+                format!("GITHUB_WORKFLOW_REF is not of the form owner/repo/path@refs/...: {}", workflow_ref)
+            })?;
+            (Some(repository), path)
         }
+        Err(_) => {
+            let event_path = std::env::var("GITHUB_EVENT_PATH")
+                .map_err(|_| "--self needs GITHUB_WORKFLOW_REF or GITHUB_EVENT_PATH; is this running in GitHub Actions?")?;
+            let content = fs::read_to_string(&event_path).map_err(|e| format!("{}: {}", event_path, e))?;
+            let payload: serde_json::Value = serde_json::from_str(&content).map_err(|e| format!("{}: {}", event_path, e))?;
+            let path = payload["workflow"]
+                .as_str()
+                .ok_or_else(|| format!("{}: event payload does not name the workflow; set GITHUB_WORKFLOW_REF", event_path))?;
+            (None, path.to_string())
+        }
+    };
+
+    let file = repo_root().join(&path);
+    if !file.is_file() {
+        // Reusable workflows run under their caller's ref, and sparse checkouts may omit the file
+        let from = repository.map(|r| format!(" from {}", r)).unwrap_or_default();
+        return Err(format!(
+            "--self: workflow {}{} is not in the checkout at {}; check out its repository first \
+             (a called reusable workflow is not part of the caller's checkout)",
+            path,
+            from,
+            repo_root().display()
+        )
+        .into());
     }
-}
\ No newline at end of file
+    Ok(file)
+}
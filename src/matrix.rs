@@ -0,0 +1,279 @@
+use crate::rules::step_label;
+use regex::Regex;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+
+/// One combination of a job's `strategy.matrix`: matrix key -> value
+pub type Combination = BTreeMap<String, Value>;
+
+/// A step whose `uses:` is templated with `${{ ... }}`
+pub struct Templated {
+    pub job: String,
+    pub step: String,
+    pub uses: String,
+    /// Each distinct concrete reference with the matrix entry that produced it (e.g.
+    /// `checkout=v3`), or why the reference can't be resolved statically
+    pub expansions: Result<Vec<(String, String)>, String>,
+}
+
+/// Find templated step references in a workflow and expand them through their job's matrix
+pub fn templated_uses(workflow: &Value) -> Vec<Templated> {
+    let mut templated = Vec::new();
+    let Some(jobs) = workflow.get("jobs").and_then(|v| v.as_mapping()) else {
+        return templated;
+    };
+    for (job_id, job) in jobs {
+        let job_id = job_id.as_str().unwrap_or("<unnamed>");
+        let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else { continue };
+        // Expanded lazily: most jobs never template a `uses:`
+        let mut combinations = None;
+        for (index, step) in steps.iter().enumerate() {
+            let Some(uses) = step.get("uses").and_then(|v| v.as_str()) else { continue };
+            if !uses.contains("${{") {
+                continue;
+            }
+            let combinations = combinations.get_or_insert_with(|| match job.get("strategy").and_then(|s| s.get("matrix")) {
+                Some(matrix) => expand(matrix),
+                None => Err("the job has no strategy.matrix".to_string()),
+            });
+            let expansions = combinations
+                .as_ref()
+                .map_err(Clone::clone)
+                .and_then(|combinations| expand_uses(uses, combinations));
+            templated.push(Templated {
+                job: job_id.to_string(),
+                step: step_label(step, index),
+                uses: uses.to_string(),
+                expansions,
+            });
+        }
+    }
+    templated
+}
+
+/// Substitute every combination into a `uses:` value, keeping one entry per distinct result
+fn expand_uses(uses: &str, combinations: &[Combination]) -> Result<Vec<(String, String)>, String> {
+    let mut expansions: Vec<(String, String)> = Vec::new();
+    for combination in combinations {
+        let expansion = substitute(uses, combination)?;
+        if !expansions.contains(&expansion) {
+            expansions.push(expansion);
+        }
+    }
+    if expansions.is_empty() {
+        return Err("the matrix has no combinations".to_string());
+    }
+    Ok(expansions)
+}
+
+/// Replace `${{ matrix.<key> }}` with the combination's literal values. Returns the concrete
+/// reference and the `key=value` pairs that were used.
+fn substitute(uses: &str, combination: &Combination) -> Result<(String, String), String> {
+    let expression = Regex::new(r"\$\{\{\s*matrix\.([A-Za-z0-9_-]+)\s*\}\}").unwrap();
+    let mut used = Vec::new();
+    let mut missing = None;
+    let concrete = expression.replace_all(uses, |captures: &regex::Captures| {
+        let key = &captures[1];
+        match combination.get(key).and_then(scalar) {
+            Some(value) => {
+                used.push(format!("{}={}", key, value));
+                value
+            }
+            None => {
+                missing.get_or_insert_with(|| key.to_string());
+                String::new()
+            }
+        }
+    });
+    if let Some(key) = missing {
+        return Err(format!("matrix.{} is not a literal in every combination", key));
+    }
+    if concrete.contains("${{") {
+        return Err("it uses an expression other than a matrix value".to_string());
+    }
+    used.dedup();
+    Ok((concrete.into_owned(), used.join(", ")))
+}
+
+/// The string form of a scalar matrix value
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn is_expression(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.contains("${{"),
+        Value::Sequence(items) => items.iter().any(is_expression),
+        Value::Mapping(map) => map.values().any(is_expression),
+        _ => false,
+    }
+}
+
+/// Expand a `strategy.matrix` into its combinations with GitHub's semantics: the cross product of
+/// the axes, minus `exclude` entries (matched on the keys they list), then each `include` entry is
+/// merged into every combination whose original values it doesn't contradict, or appended as a
+/// combination of its own when it matches none.
+pub fn expand(matrix: &Value) -> Result<Vec<Combination>, String> {
+    if is_expression(matrix) {
+        return Err("the matrix is built from an expression".to_string());
+    }
+    let Some(matrix) = matrix.as_mapping() else {
+        return Err("the matrix is not a mapping".to_string());
+    };
+
+    let mut combinations = vec![Combination::new()];
+    let mut axes = Vec::new();
+    for (key, values) in matrix {
+        let Some(key) = key.as_str() else { continue };
+        if key == "include" || key == "exclude" {
+            continue;
+        }
+        let values = values
+            .as_sequence()
+            .ok_or_else(|| format!("matrix axis {} is not a list", key))?;
+        axes.push(key.to_string());
+        combinations = combinations
+            .iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.insert(key.to_string(), value.clone());
+                    combination
+                })
+            })
+            .collect();
+    }
+    if axes.is_empty() {
+        combinations.clear();
+    }
+
+    if let Some(exclude) = matrix.get("exclude") {
+        let exclude = entries(exclude, "exclude")?;
+        combinations.retain(|combination| {
+            !exclude
+                .iter()
+                .any(|entry| entry.iter().all(|(key, value)| combination.get(key) == Some(value)))
+        });
+    }
+
+    if let Some(include) = matrix.get("include") {
+        let original = combinations.len();
+        for entry in entries(include, "include")? {
+            let mut matched = false;
+            for combination in &mut combinations[..original] {
+                let compatible = entry
+                    .iter()
+                    .filter(|(key, _)| axes.contains(key))
+                    .all(|(key, value)| combination.get(key) == Some(value));
+                if compatible {
+                    combination.extend(entry.clone());
+                    matched = true;
+                }
+            }
+            if !matched {
+                combinations.push(entry);
+            }
+        }
+    }
+    Ok(combinations)
+}
+
+/// The mappings of an `include`/`exclude` list
+fn entries(list: &Value, name: &str) -> Result<Vec<Combination>, String> {
+    let list = list
+        .as_sequence()
+        .ok_or_else(|| format!("matrix {} is not a list", name))?;
+    list.iter()
+        .map(|entry| {
+            let entry = entry
+                .as_mapping()
+                .ok_or_else(|| format!("matrix {} entry is not a mapping", name))?;
+            Ok(entry
+                .iter()
+                .filter_map(|(key, value)| Some((key.as_str()?.to_string(), value.clone())))
+                .collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workflow of one `build` job with `strategy.matrix` given as a YAML flow mapping, whose
+    /// only step uses `uses`
+    fn workflow(matrix: &str, uses: &str) -> Value {
+        let source = format!(
+            "jobs:\n  build:\n    strategy:\n      matrix: {}\n    runs-on: ubuntu-latest\n    steps:\n      - uses: {}\n",
+            matrix, uses
+        );
+        serde_yaml::from_str(&source).unwrap()
+    }
+
+    fn expansions(matrix: &str, uses: &str) -> Result<Vec<(String, String)>, String> {
+        let templated = templated_uses(&workflow(matrix, uses));
+        let [templated] = templated.as_slice() else { panic!("{} templated steps", templated.len()) };
+        assert_eq!((templated.job.as_str(), templated.step.as_str(), templated.uses.as_str()), ("build", "step 1", uses));
+        templated.expansions.clone()
+    }
+
+    fn references(matrix: &str, uses: &str) -> Vec<String> {
+        expansions(matrix, uses).unwrap().into_iter().map(|(reference, _)| reference).collect()
+    }
+
+    #[test]
+    fn each_distinct_reference_of_the_cross_product_is_kept_with_its_entry() {
+        let found = expansions("{checkout: [v3, v4], os: [ubuntu, windows]}", "actions/checkout@${{ matrix.checkout }}").unwrap();
+        let expected = [("actions/checkout@v3", "checkout=v3"), ("actions/checkout@v4", "checkout=v4")];
+        assert_eq!(found, expected.map(|(r, e)| (r.to_string(), e.to_string())));
+        assert_eq!(references("{owner: [acme], v: [1, 2]}", "${{ matrix.owner }}/tool@v${{ matrix.v }}"), ["acme/tool@v1", "acme/tool@v2"]);
+    }
+
+    #[test]
+    fn exclude_removes_the_combinations_it_matches() {
+        let matrix = "{os: [ubuntu, windows], cache: [v3, v4], exclude: [{os: windows, cache: v3}]}";
+        assert_eq!(expand(&serde_yaml::from_str(matrix).unwrap()).unwrap().len(), 3);
+        let matrix = "{cache: [v3, v4], os: [ubuntu], exclude: [{cache: v3}]}";
+        assert_eq!(references(matrix, "actions/cache@${{ matrix.cache }}"), ["actions/cache@v4"]);
+    }
+
+    #[test]
+    fn include_extends_matching_combinations_or_adds_its_own() {
+        let matrix = "{os: [ubuntu, windows], include: [{os: windows, node: v4}, {os: macos, node: v5}]}";
+        let combinations = expand(&serde_yaml::from_str(matrix).unwrap()).unwrap();
+        let nodes: Vec<Option<&Value>> = combinations.iter().map(|c| c.get("node")).collect();
+        assert_eq!(nodes, [None, Some(&Value::from("v4")), Some(&Value::from("v5"))]);
+
+        let matrix = "{include: [{setup: actions/setup-node@v4}, {setup: actions/setup-python@v5}]}";
+        assert_eq!(references(matrix, "${{ matrix.setup }}"), ["actions/setup-node@v4", "actions/setup-python@v5"]);
+    }
+
+    #[test]
+    fn an_expansion_that_cannot_be_resolved_says_why() {
+        for (matrix, uses, error) in [
+            ("{os: [ubuntu]}", "actions/checkout@${{ matrix.checkout }}", "matrix.checkout is not a literal in every combination"),
+            ("{os: [ubuntu], include: [{os: ubuntu, v: v4}, {os: macos}]}", "a/b@${{ matrix.v }}", "matrix.v is not a literal in every combination"),
+            ("{v: [v4]}", "a/b@${{ inputs.ref }}", "it uses an expression other than a matrix value"),
+            ("${{ fromJSON(needs.plan.outputs.matrix) }}", "a/b@${{ matrix.v }}", "the matrix is built from an expression"),
+            ("{v: v4}", "a/b@${{ matrix.v }}", "matrix axis v is not a list"),
+            ("{v: [v4], exclude: {v: v4}}", "a/b@${{ matrix.v }}", "matrix exclude is not a list"),
+            ("{v: [v4], exclude: [{v: v4}]}", "a/b@${{ matrix.v }}", "the matrix has no combinations"),
+        ] {
+            assert_eq!(expansions(matrix, uses), Err(error.to_string()), "{}", matrix);
+        }
+    }
+
+    #[test]
+    fn a_templated_step_without_a_matrix_says_so_and_literal_steps_are_skipped() {
+        let value: Value = serde_yaml::from_str("jobs:\n  a:\n    steps:\n      - uses: a/b@${{ env.REF }}\n      - uses: a/c@v1\n").unwrap();
+        let templated = templated_uses(&value);
+        let [templated] = templated.as_slice() else { panic!("{} templated steps", templated.len()) };
+        assert_eq!(templated.uses, "a/b@${{ env.REF }}");
+        assert_eq!(templated.expansions, Err("the job has no strategy.matrix".to_string()));
+    }
+}
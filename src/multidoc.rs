@@ -0,0 +1,150 @@
+use serde_yaml::{Mapping, Value};
+use std::ops::Range;
+
+/// Whether the line is a document marker: `---` starting a document or `...` ending one
+fn is_marker(line: &str) -> bool {
+    ["---", "..."].iter().any(|marker| line.strip_prefix(marker).is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t'])))
+}
+
+/// Whether the line holds anything of a document: not blank, a comment or a `%` directive
+fn has_content(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    !trimmed.is_empty() && !trimmed.starts_with('#') && !line.starts_with('%')
+}
+
+/// The 0-based line ranges of the source's documents, markers left out. Documents with nothing
+/// but comments are skipped, as the parser reads them as null; a file without markers is one
+/// document.
+pub fn ranges(source: &str) -> Vec<Range<usize>> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for index in 0..=lines.len() {
+        if index < lines.len() && !is_marker(lines[index]) {
+            continue;
+        }
+        if lines[start..index].iter().any(|line| has_content(line)) {
+            ranges.push(start..index);
+        }
+        start = index + 1;
+    }
+    ranges
+}
+
+/// The document at `range` as the whole source with every other line blanked, so the parser's
+/// line numbers and the line lookups stay those of the file
+fn blanked(source: &str, range: &Range<usize>) -> String {
+    let lines = source.lines().enumerate().map(|(index, line)| if range.contains(&index) { line } else { "" });
+    lines.collect::<Vec<_>>().join("\n")
+}
+
+/// Parse every document of the source, with `<<:` merge keys applied. A file of one document is
+/// parsed as a whole; a stream of several is split at its markers and each document parsed on
+/// its own, since serde_yaml's document iterator loses error locations and panics on an alias to
+/// another document's anchor.
+pub fn parse(source: &str) -> Result<Vec<Value>, serde_yaml::Error> {
+    let documents = match serde_yaml::from_str::<Value>(source) {
+        Ok(value) => vec![value],
+        Err(e) if e.to_string().contains("more than one document") => {
+            let documents = ranges(source).into_iter().map(|range| serde_yaml::from_str::<Value>(&blanked(source, &range)));
+            documents.collect::<Result<Vec<_>, _>>()?
+        }
+        Err(e) => return Err(e),
+    };
+    documents
+        .into_iter()
+        .map(|mut document| {
+            document.apply_merge()?;
+            Ok(document)
+        })
+        .collect()
+}
+
+/// The key of a job from document `document` (1-based) whose id an earlier document already has
+fn job_key(id: &str, document: usize) -> String {
+    format!("{} (document {})", id, document)
+}
+
+/// A job key as [`combine`] writes it: the id in the source, and its document when it is not the
+/// first to have that id
+pub fn parse_job_key(key: &str) -> (&str, Option<usize>) {
+    let Some((id, document)) = key.strip_suffix(')').and_then(|key| key.rsplit_once(" (document ")) else {
+        return (key, None);
+    };
+    match document.parse() {
+        Ok(document) => (id, Some(document)),
+        Err(_) => (key, None),
+    }
+}
+
+/// The documents of a file as one workflow, so every check sees every job. The jobs of all
+/// documents are gathered under `jobs`, a job whose id an earlier document has already taking
+/// ` (document N)` after it; other top-level keys, such as `on` and `permissions`, come from the
+/// first document that has them.
+pub fn combine(documents: Vec<Value>) -> Value {
+    let mut documents = documents.into_iter().enumerate();
+    let Some((_, first)) = documents.next() else {
+        return Value::Null;
+    };
+    let Value::Mapping(mut combined) = first else {
+        return first;
+    };
+    for (index, document) in documents {
+        let Value::Mapping(document) = document else { continue };
+        for (key, value) in document {
+            let is_jobs = key.as_str() == Some("jobs");
+            match value {
+                Value::Mapping(jobs) if is_jobs => {
+                    let entry = combined.entry(key).or_insert_with(|| Value::Mapping(Mapping::new()));
+                    let Some(all) = entry.as_mapping_mut() else { continue };
+                    for (id, job) in jobs {
+                        let id = match id.as_str() {
+                            Some(name) if all.contains_key(name) => Value::from(job_key(name, index + 1)),
+                            _ => id,
+                        };
+                        all.insert(id, job);
+                    }
+                }
+                value => {
+                    combined.entry(key).or_insert(value);
+                }
+            }
+        }
+    }
+    Value::Mapping(combined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_leave_out_markers_and_comment_only_documents() {
+        assert_eq!(ranges("on: push\n"), vec![0..1]);
+        assert_eq!(ranges("# header\n---\na: 1\n...\n---\n# notes\n---\nb: 2\n"), [2..3, 7..8]);
+        assert_eq!(ranges("a: 1\n---   # second\nb: 2\n"), [0..1, 2..3]);
+        assert_eq!(ranges("a: '---'\nb: --- no\n"), vec![0..2], "only a line starting with a marker splits");
+    }
+
+    #[test]
+    fn documents_parse_with_merges_and_the_file_error_line() {
+        let documents = parse("base: &base {x: 1}\nmerged:\n  <<: *base\n  y: 2\n---\nc: 3\n").unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0]["merged"]["x"], Value::from(1));
+        assert_eq!(documents[1]["c"], Value::from(3));
+        let error = parse("a: 1\n---\nb: [\n").unwrap_err();
+        assert_eq!(error.location().map(|l| l.line()), Some(4), "{}", error);
+    }
+
+    #[test]
+    fn combined_jobs_of_a_repeated_id_name_their_document() {
+        let documents = parse("on: push\njobs:\n  build: {}\n---\non: pull_request\njobs:\n  build: {}\n  lint: {}\n").unwrap();
+        let combined = combine(documents);
+        let jobs: Vec<&str> = combined["jobs"].as_mapping().unwrap().keys().filter_map(Value::as_str).collect();
+        assert_eq!(jobs, ["build", "build (document 2)", "lint"]);
+        assert_eq!(combined["on"], Value::from("push"), "the first document's trigger wins");
+        assert_eq!(parse_job_key("build (document 2)"), ("build", Some(2)));
+        assert_eq!(parse_job_key("build (x)"), ("build (x)", None));
+        assert_eq!(parse_job_key("build (document two)"), ("build (document two)", None));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::github::Endpoints;
+use crate::http::{HttpClient, HttpRequest};
+use crate::{ActionRef, AdvisoryPackage, AdvisoryVulnerability, GitHubAdvisory, ScanError, ratelimit};
+use reqwest::{Method, StatusCode};
+use serde_json::{Value, json};
+
+/// OSV's API, unless `--osv-api-url` names another
+pub const API_BASE: &str = "https://api.osv.dev";
+
+/// OSV's name for the ecosystem
+const ECOSYSTEM: &str = "GitHub Actions";
+
+/// The `owner/repo` an OSV query asks about, for fixture files
+pub fn queried_package(body: &Value) -> Option<&str> {
+    body["package"]["name"].as_str()
+}
+
+/// Vulnerabilities OSV lists for the action. A tag pin is sent as the version, so OSV leaves out
+/// the ones fixed before it; anything else asks for every vulnerability of the repository.
+pub async fn query(client: &dyn HttpClient, endpoints: &Endpoints, action_ref: &ActionRef) -> Result<Vec<GitHubAdvisory>, ScanError> {
+    let mut body = json!({ "package": { "ecosystem": ECOSYSTEM, "name": action_ref.full_name() } });
+    if !action_ref.is_commit_sha() {
+        body["version"] = json!(action_ref.version);
+    }
+    let url = endpoints.osv_query_url();
+    let request = HttpRequest::new(Method::POST, &url).json(&body);
+    let response = client.send(request).await.map_err(|e| ScanError::io(&url, e))?;
+    if ratelimit::is_rate_limited(&response) {
+        return Err(ScanError::RateLimited { retry_after: ratelimit::retry_after(&response), url });
+    }
+    if response.status() != StatusCode::OK {
+        return Err(ScanError::Http { status: response.status().as_u16(), url });
+    }
+    let value: Value = response
+        .json()
+        .map_err(|e| ScanError::InvalidResponse { url: url.clone(), message: e.to_string() })?;
+    Ok(value["vulns"].as_array().into_iter().flatten().filter_map(|v| advisory(v, action_ref)).collect())
+}
+
+/// An OSV range's events as GitHub-style ranges (`>= 1.0.0, < 1.2.3`) with the version that ends
+/// each one, if it was fixed
+fn spans(events: &[Value]) -> Vec<(String, Option<String>)> {
+    let mut spans = Vec::new();
+    let mut introduced: Option<&str> = None;
+    for event in events {
+        if let Some(version) = event["introduced"].as_str() {
+            introduced = Some(version);
+            continue;
+        }
+        let (op, end) = match (event["fixed"].as_str(), event["last_affected"].as_str()) {
+            (Some(fixed), _) => ("<", fixed),
+            (None, Some(last)) => ("<=", last),
+            (None, None) => continue,
+        };
+        let start = introduced.take().filter(|v| *v != "0").map(|v| format!(">= {}, ", v)).unwrap_or_default();
+        let fixed = (op == "<").then(|| end.to_string());
+        spans.push((format!("{}{} {}", start, op, end), fixed));
+    }
+    if let Some(start) = introduced {
+        // Introduced and never fixed
+        spans.push((format!(">= {}", if start == "0" { "0.0.0" } else { start }), None));
+    }
+    spans
+}
+
+/// An OSV vulnerability in the advisory shape, identified by its GHSA id when it has one so it
+/// merges with the GitHub record of the same advisory
+fn advisory(vuln: &Value, action_ref: &ActionRef) -> Option<GitHubAdvisory> {
+    let id = vuln["id"].as_str()?;
+    let ghsa = std::iter::once(id)
+        .chain(vuln["aliases"].as_array().into_iter().flatten().filter_map(Value::as_str))
+        .find(|alias| alias.starts_with("GHSA-"));
+    let title = vuln["summary"]
+        .as_str()
+        .or_else(|| vuln["details"].as_str().and_then(|d| d.lines().next()))
+        .unwrap_or(id);
+    let vulnerabilities = vuln["affected"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|a| a["package"]["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(&action_ref.full_name())))
+        .flat_map(|affected| {
+            let name = affected["package"]["name"].as_str().unwrap_or_default();
+            affected["ranges"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|range| spans(range["events"].as_array().map(Vec::as_slice).unwrap_or_default()))
+                .map(move |(range, fixed)| AdvisoryVulnerability {
+                    package: Some(AdvisoryPackage { name: name.to_string() }),
+                    vulnerable_version_range: Some(range),
+                    first_patched_version: fixed,
+                })
+        })
+        .collect();
+    Some(GitHubAdvisory {
+        id: ghsa.unwrap_or(id).to_string(),
+        title: title.to_string(),
+        severity: vuln["database_specific"]["severity"].as_str().map(str::to_ascii_lowercase),
+        created_at: vuln["published"].as_str().map(str::to_string),
+        withdrawn_at: vuln["withdrawn"].as_str().map(str::to_string),
+        cvss: None,
+        vulnerabilities: Some(vulnerabilities),
+        sources: vec!["osv".to_string()],
+    })
+}
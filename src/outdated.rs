@@ -0,0 +1,110 @@
+use crate::ActionRef;
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::report::{Finding, Level, Rule, UpdateRef};
+use crate::{shas, unreleased, vendor};
+use serde_json::Value;
+
+/// Numeric components of a tag as written, so `v4` has one and `v4.1.2` three
+fn components(tag: &str) -> Option<Vec<u64>> {
+    tag.strip_prefix('v').unwrap_or(tag).split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Whether `latest` is newer than `pinned` at the precision of the pin: a major tag such as `v4`
+/// follows every 4.x release, so only a v5 makes it outdated. `None` for tags that are not
+/// version numbers.
+fn is_behind(pinned: &str, latest: &str) -> Option<bool> {
+    let pinned = components(pinned)?;
+    let mut latest = components(latest)?;
+    latest.resize(pinned.len(), 0);
+    Some(latest > pinned)
+}
+
+/// The latest release's tag, else the highest version among the tags, through the cache
+async fn latest_version(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<String>, std::io::Error> {
+    let key = action_ref.full_name();
+    match cache.get("latest-version", &key) {
+        Cached::Hit(value) => return Ok(value.as_str().map(str::to_string)),
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {}
+    }
+    let latest = match vendor::latest_release(api, action_ref).await? {
+        Some(tag) => Some(tag),
+        None => shas::refs(api, cache, action_ref, "tags")
+            .await?
+            .into_iter()
+            .filter_map(|(tag, _)| components(&tag).map(|key| (key, tag)))
+            .max()
+            .map(|(_, tag)| tag),
+    };
+    match &latest {
+        Some(tag) => cache.put("latest-version", &key, &Value::from(tag.as_str())),
+        None => cache.put_negative("latest-version", &key),
+    }
+    Ok(latest)
+}
+
+/// The most specific tag pointing at a pinned commit, so `v4.1.2` wins over `v4`
+pub async fn tag_of_commit(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<String>, std::io::Error> {
+    let tags = shas::refs(api, cache, action_ref, "tags").await?;
+    Ok(tags
+        .into_iter()
+        .filter(|(_, sha)| sha.eq_ignore_ascii_case(&action_ref.version))
+        .max_by_key(|(tag, _)| components(tag).map_or(0, |c| c.len()))
+        .map(|(tag, _)| tag))
+}
+
+/// `--check-outdated`: flag a pin behind the repository's latest version. Version tags are
+/// compared by number; anything else, such as a SHA on no tag, counts as outdated when it is an
+/// ancestor of the latest version's commit.
+pub async fn check(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    action: &str,
+    action_ref: &ActionRef,
+    level: Level,
+) -> Result<Option<Finding>, std::io::Error> {
+    let Some(latest) = latest_version(api, cache, action_ref).await? else {
+        return Ok(None);
+    };
+    let current = if action_ref.is_commit_sha() {
+        tag_of_commit(api, cache, action_ref).await?
+    } else {
+        Some(action_ref.version.clone())
+    };
+    let behind = match current.as_deref().and_then(|current| is_behind(current, &latest)) {
+        Some(behind) => behind,
+        None => unreleased::compare(api, cache, action_ref, &latest).await?.is_some_and(|c| c.behind),
+    };
+    if !behind {
+        return Ok(None);
+    }
+    let current = current.unwrap_or_else(|| action_ref.version.clone());
+    let message = format!("{} is outdated: pinned to {}, latest is {}", action, current, latest);
+    let mut finding = Finding::new(Rule::Outdated, level, action, message);
+    finding.update = Some(UpdateRef { current, latest });
+    Ok(Some(finding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_at_the_precision_of_the_pin() {
+        assert_eq!(is_behind("v3", "v4.2.2"), Some(true));
+        assert_eq!(is_behind("v4", "v4.2.2"), Some(false));
+        assert_eq!(is_behind("v4.1", "v4.2.0"), Some(true));
+        assert_eq!(is_behind("v4.1.2", "v4.1.10"), Some(true));
+        assert_eq!(is_behind("4.1.2", "v4.1.2"), Some(false));
+        assert_eq!(is_behind("v5", "v4.2.2"), Some(false));
+    }
+
+    #[test]
+    fn tags_that_are_not_versions_are_not_compared() {
+        assert_eq!(is_behind("main", "v4.2.2"), None);
+        assert_eq!(is_behind("v4", "nightly"), None);
+        assert_eq!(is_behind("v4.1-beta", "v4.2"), None);
+        assert_eq!(components("v4.1.2"), Some(vec![4, 1, 2]));
+    }
+}
@@ -0,0 +1,71 @@
+//! How much the scan says about its own progress. Progress and diagnostics go to stderr, so that
+//! stdout holds the report alone: findings and the summary in text output, or the `--format`
+//! document.
+
+use crate::http::{HttpClient, HttpRequest, ResponseFuture};
+use crate::token;
+use std::fmt::Display;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// `--quiet`: findings and the summary only
+    Quiet,
+    #[default]
+    Normal,
+    /// `--verbose`: also each request, cache hit and rate limit
+    Verbose,
+}
+
+impl Verbosity {
+    /// The verbosity `--quiet` and `--verbose` ask for; `--quiet` wins when both are given
+    pub fn from_flags(quiet: bool, verbose: bool) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, true) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        }
+    }
+
+    /// A step of the scan, such as the action being checked; not with `--quiet`
+    pub fn progress(self, message: impl Display) {
+        if self >= Verbosity::Normal {
+            eprintln!("{}", message);
+        }
+    }
+
+    /// Details of how the scan got its answers; with `--verbose` only
+    pub fn detail(self, message: impl Display) {
+        if self >= Verbosity::Verbose {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+/// `--verbose`: each request with its status, time and the rate limit it reports
+pub struct RequestDetails {
+    pub inner: Box<dyn HttpClient>,
+}
+
+impl HttpClient for RequestDetails {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let line = format!("{} {}", request.method, token::without_userinfo(&request.url));
+            let sent = Instant::now();
+            let result = self.inner.send(request).await;
+            let took = sent.elapsed().as_millis();
+            match &result {
+                Ok(response) => {
+                    let remaining = match (response.header("x-ratelimit-remaining"), response.header("x-ratelimit-limit")) {
+                        (Some(remaining), Some(limit)) => format!(", rate limit {}/{} left", remaining, limit),
+                        (Some(remaining), None) => format!(", rate limit {} left", remaining),
+                        _ => String::new(),
+                    };
+                    eprintln!("{} -> {} in {} ms{}", line, response.status, took, remaining);
+                }
+                Err(e) => eprintln!("{} failed after {} ms: {}", line, took, e),
+            }
+            result
+        })
+    }
+}
@@ -0,0 +1,154 @@
+use crate::fix::split_line_ending;
+
+/// Lines of unchanged context around each change, as `git diff` shows
+const CONTEXT: usize = 3;
+
+/// `old` and `new` of one file as a `git apply`-able unified diff, for rewrites that replace lines
+/// one for one, as the fixes do, so line `n` of one is line `n` of the other. `path` is relative
+/// to the repository root. Empty when nothing changed.
+pub fn file_diff(path: &str, old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let changed: Vec<usize> = (0..old.len().max(new.len())).filter(|&i| old.get(i) != new.get(i)).collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Changes whose context touches or overlaps form one hunk, as (first, last) line indexes
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changed {
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + CONTEXT).min(old.len().max(new.len()) - 1);
+        match hunks.last_mut() {
+            Some((_, last)) if start <= *last + 1 => *last = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", path);
+    for (start, end) in hunks {
+        let old_count = end.min(old.len().saturating_sub(1)) + 1 - start;
+        let new_count = end.min(new.len().saturating_sub(1)) + 1 - start;
+        out.push_str(&format!("@@ -{} +{} @@\n", range(start, old_count), range(start, new_count)));
+        for index in start..=end {
+            match (old.get(index), new.get(index)) {
+                (Some(before), Some(after)) if before == after => push_line(&mut out, ' ', before),
+                (before, after) => {
+                    if let Some(before) = before {
+                        push_line(&mut out, '-', before);
+                    }
+                    if let Some(after) = after {
+                        push_line(&mut out, '+', after);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// A hunk header range: 1-based start and count, with the count left out when it is 1
+fn range(start: usize, count: usize) -> String {
+    match count {
+        1 => format!("{}", start + 1),
+        0 => format!("{},0", start),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// One diff line; a last line without a newline gets git's marker for it
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    let (text, ending) = split_line_ending(line);
+    out.push(prefix);
+    out.push_str(text);
+    if ending == "\r\n" {
+        out.push('\r');
+    }
+    out.push('\n');
+    if ending.is_empty() {
+        out.push_str("\\ No newline at end of file\n");
+    }
+}
+
+/// The patch `fix --emit-patch` writes: every file's diff, then what could not be fixed as
+/// comment lines, which `git apply` skips
+pub fn render(diffs: &[String], unfixable: &[String]) -> String {
+    let mut out = diffs.concat();
+    if !unfixable.is_empty() {
+        out.push_str("\n# Findings that could not be fixed automatically:\n");
+        for action in unfixable {
+            out.push_str(&format!("# - {}\n", action));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` numbered lines, `uses: acme/tool@v1` at the 1-based `pinned` ones
+    fn lines(count: usize, pinned: &[usize], uses: &str) -> String {
+        (1..=count).map(|n| if pinned.contains(&n) { format!("  uses: {}\n", uses) } else { format!("line {}\n", n) }).collect()
+    }
+
+    #[test]
+    fn one_change_gets_three_lines_of_context() {
+        let diff = file_diff(".github/workflows/ci.yml", &lines(10, &[5], "a@v1"), &lines(10, &[5], "a@sha"));
+        let expected = "\
+diff --git a/.github/workflows/ci.yml b/.github/workflows/ci.yml
+--- a/.github/workflows/ci.yml
++++ b/.github/workflows/ci.yml
+@@ -2,7 +2,7 @@
+ line 2
+ line 3
+ line 4
+-  uses: a@v1
++  uses: a@sha
+ line 6
+ line 7
+ line 8
+";
+        assert_eq!(diff, expected);
+    }
+
+    #[test]
+    fn context_is_cut_at_the_ends_of_the_file() {
+        let diff = file_diff("ci.yml", &lines(3, &[1], "a@v1"), &lines(3, &[1], "a@sha"));
+        assert!(diff.ends_with("@@ -1,3 +1,3 @@\n-  uses: a@v1\n+  uses: a@sha\n line 2\n line 3\n"), "{}", diff);
+        let diff = file_diff("ci.yml", "  uses: a@v1\n", "  uses: a@sha\n");
+        assert!(diff.ends_with("@@ -1 +1 @@\n-  uses: a@v1\n+  uses: a@sha\n"), "{}", diff);
+    }
+
+    #[test]
+    fn distant_changes_are_separate_hunks_and_near_ones_merge() {
+        let diff = file_diff("ci.yml", &lines(20, &[2, 18], "a@v1"), &lines(20, &[2, 18], "a@sha"));
+        let headers: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(headers, ["@@ -1,5 +1,5 @@", "@@ -15,6 +15,6 @@"]);
+        // Six lines apart, the contexts touch
+        let diff = file_diff("ci.yml", &lines(20, &[5, 12], "a@v1"), &lines(20, &[5, 12], "a@sha"));
+        let headers: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(headers, ["@@ -2,14 +2,14 @@"]);
+    }
+
+    #[test]
+    fn a_last_line_without_a_newline_gets_git_s_marker() {
+        let diff = file_diff("ci.yml", "line 1\n  uses: a@v1", "line 1\n  uses: a@sha");
+        assert!(diff.ends_with(" line 1\n-  uses: a@v1\n\\ No newline at end of file\n+  uses: a@sha\n\\ No newline at end of file\n"), "{}", diff);
+        let diff = file_diff("ci.yml", "  uses: a@v1\r\nline 2\r\n", "  uses: a@sha\r\nline 2\r\n");
+        assert!(diff.ends_with("-  uses: a@v1\r\n+  uses: a@sha\r\n line 2\r\n"), "{:?}", diff);
+    }
+
+    #[test]
+    fn an_unchanged_file_has_no_diff() {
+        assert_eq!(file_diff("ci.yml", "line 1\n", "line 1\n"), "");
+    }
+
+    #[test]
+    fn what_could_not_be_fixed_trails_the_diffs_as_comments() {
+        let diff = file_diff("ci.yml", "  uses: a@v1\n", "  uses: a@sha\n");
+        let patch = render(std::slice::from_ref(&diff), &["acme/gone@v3".to_string(), "acme/tool@main".to_string()]);
+        assert_eq!(patch, format!("{}\n# Findings that could not be fixed automatically:\n# - acme/gone@v3\n# - acme/tool@main\n", diff));
+        assert_eq!(render(std::slice::from_ref(&diff), &[]), diff);
+    }
+}
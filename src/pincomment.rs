@@ -0,0 +1,170 @@
+use crate::{ActionRef, RefKind};
+use crate::cache::DiskCache;
+use crate::config::Config;
+use crate::github::GitHubApi;
+use crate::{fix, outdated};
+use crate::report::{Finding, Level, Rule};
+use regex::Regex;
+
+/// Accepts `# v4`, `# tag=v4.2.2` and `# renovate: tag=v4.2.2`
+pub const DEFAULT_PATTERN: &str = r"^(?:renovate:\s*)?(?:tag=)?(?P<version>\S+)$";
+
+/// How an organisation writes the version comment next to a SHA pin: a regex with a named
+/// `version` group to read it, and a `{version}` template to write new ones
+#[derive(Debug, Clone)]
+pub struct CommentStyle {
+    pattern: Regex,
+    format: String,
+}
+
+impl Default for CommentStyle {
+    fn default() -> Self {
+        CommentStyle {
+            pattern: Regex::new(DEFAULT_PATTERN).unwrap(),
+            format: "{version}".to_string(),
+        }
+    }
+}
+
+impl CommentStyle {
+    /// The style from `pin_comment_pattern` and `pin_comment_format`, rejecting a pattern without
+    /// a `version` group (it could never read a version) and a format without `{version}`
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut style = CommentStyle::default();
+        if let Some(pattern) = &config.pin_comment_pattern {
+            let regex = Regex::new(pattern).map_err(|e| format!("invalid pin_comment_pattern {}: {}", pattern, e))?;
+            if !regex.capture_names().flatten().any(|name| name == "version") {
+                return Err(format!("pin_comment_pattern {} has no named version group, e.g. (?P<version>\\S+)", pattern));
+            }
+            style.pattern = regex;
+        }
+        if let Some(format) = &config.pin_comment_format {
+            if !format.contains("{version}") {
+                return Err(format!("pin_comment_format {} does not contain {{version}}", format));
+            }
+            style.format = format.clone();
+        }
+        Ok(style)
+    }
+
+    /// The version a comment (the text after `#`) names, if it is in this style
+    pub fn version<'a>(&self, comment: &'a str) -> Option<&'a str> {
+        let caps = self.pattern.captures(comment.trim())?;
+        caps.name("version").map(|m| m.as_str())
+    }
+
+    /// The comment text for a new pin
+    pub fn render(&self, version: &str) -> String {
+        self.format.replace("{version}", version)
+    }
+
+    /// Point an existing comment at a new version in its own style, or write a fresh one
+    pub fn rewrite(&self, comment: Option<&str>, version: &str) -> String {
+        let Some(comment) = comment.map(str::trim) else {
+            return self.render(version);
+        };
+        match self.pattern.captures(comment).and_then(|caps| caps.name("version")) {
+            Some(found) => format!("{}{}{}", &comment[..found.start()], version, &comment[found.end()..]),
+            None => self.render(version),
+        }
+    }
+}
+
+/// The SHA pins of a workflow's source: 0-based line, `uses:` value and the comment after it
+fn sha_pins(source: &str) -> Vec<(usize, &str, Option<&str>)> {
+    let uses_regex = Regex::new(r#"^\s*(?:-\s+)?uses:\s*["']?([^\s"'#]+@[0-9a-fA-F]{40}(?:[0-9a-fA-F]{24})?)["']?\s*(?:#\s*(.*))?$"#).unwrap();
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let caps = uses_regex.captures(line)?;
+            Some((index, caps.get(1)?.as_str(), caps.get(2).map(|m| m.as_str())))
+        })
+        .collect()
+}
+
+/// The SHA pins whose comment names a version in the style: 1-based line, `uses:` value and
+/// the version
+pub fn commented_pins<'a>(source: &'a str, style: &CommentStyle) -> Vec<(usize, &'a str, &'a str)> {
+    sha_pins(source)
+        .into_iter()
+        .filter_map(|(index, action, comment)| Some((index + 1, action, style.version(comment?)?)))
+        .collect()
+}
+
+/// The version each SHA pin's comment names, where it has one in the style
+pub fn versions<'a>(source: &'a str, style: &CommentStyle) -> Vec<(&'a str, &'a str)> {
+    commented_pins(source, style).into_iter().map(|(_, action, version)| (action, version)).collect()
+}
+
+/// `--verify-pin-comments`: whether the tag a pin's comment names resolves to the pinned commit,
+/// annotated tags dereferenced; `None` when it does, else what is wrong, naming the tag the
+/// commit really carries when it has one
+pub async fn verify(api: &GitHubApi<'_>, cache: &DiskCache, action: &str, version: &str) -> Result<Option<String>, std::io::Error> {
+    let Some(pinned) = ActionRef::from_action_string(action) else {
+        return Ok(None);
+    };
+    let commented = ActionRef { version: version.to_string(), kind: RefKind::classify(version), ..pinned.clone() };
+    let problem = match fix::resolve_tag_sha_cached(api, cache, &commented).await? {
+        Some(sha) if sha.eq_ignore_ascii_case(&pinned.version) => return Ok(None),
+        Some(sha) => format!("but {} is commit {}", version, sha),
+        None => format!("but {} has no tag {}", pinned.full_name(), version),
+    };
+    let actual = match outdated::tag_of_commit(api, cache, &pinned).await? {
+        Some(tag) => format!("the pinned commit is {}", tag),
+        None => "no tag points at the pinned commit".to_string(),
+    };
+    Ok(Some(format!("Pin comment mismatch: {} is commented # {}, {}; {}", action, version, problem, actual)))
+}
+
+/// `--require-pinning-comment-style`: every SHA pin must carry a version comment in the house style
+pub fn check(path: &str, source: &str, style: &CommentStyle) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (index, action, comment) in sha_pins(source) {
+        let problem = match comment {
+            None => "has no version comment".to_string(),
+            Some(comment) if style.version(comment).is_none() => format!("has a comment not in the pin comment style: # {}", comment.trim()),
+            Some(_) => continue,
+        };
+        let message = format!("SHA pin {} {}", action, problem);
+        findings.push(Finding::new(Rule::PinComment, Level::Warning, action, message).at(path, Some(index + 1)));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(pattern: &str, format: &str) -> CommentStyle {
+        let config = Config { pin_comment_pattern: Some(pattern.to_string()), pin_comment_format: Some(format.to_string()), ..Default::default() };
+        CommentStyle::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn comments_are_rewritten_in_their_own_style() {
+        let default = CommentStyle::default();
+        assert_eq!(default.rewrite(Some(" tag=v4.1.0 "), "v4.2.2"), "tag=v4.2.2");
+        assert_eq!(default.rewrite(Some("renovate: tag=v4.1.0"), "v4.2.2"), "renovate: tag=v4.2.2");
+        assert_eq!(default.rewrite(None, "v4.2.2"), "v4.2.2");
+        let renovate = style(DEFAULT_PATTERN, "renovate: tag={version}");
+        assert_eq!(renovate.rewrite(None, "v4.2.2"), "renovate: tag=v4.2.2");
+        assert_eq!(renovate.rewrite(Some("see the changelog"), "v4.2.2"), "renovate: tag=v4.2.2", "a comment not in the style is replaced");
+    }
+
+    #[test]
+    fn a_format_without_its_placeholder_is_rejected() {
+        let config = Config { pin_comment_format: Some("tag=".to_string()), ..Default::default() };
+        assert_eq!(CommentStyle::from_config(&config).unwrap_err(), "pin_comment_format tag= does not contain {version}");
+    }
+
+    #[test]
+    fn only_sha_pins_are_read_with_their_comment_version() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        let source = format!("steps:\n  - uses: a/b@{} # tag=v1\n  - uses: a/c@v2 # v2\n  - uses: \"a/d@{}\"\n", sha, sha);
+        let (b, d) = (format!("a/b@{}", sha), format!("a/d@{}", sha));
+        assert_eq!(commented_pins(&source, &CommentStyle::default()), [(2, b.as_str(), "v1")]);
+        let lines: Vec<Option<usize>> = check("ci.yml", &source, &CommentStyle::default()).into_iter().map(|f| f.line).collect();
+        assert_eq!(lines, [Some(4)], "{} has no comment", d);
+    }
+}
@@ -0,0 +1,326 @@
+use crate::Workflow;
+use crate::dispatch::source_line;
+use crate::report::{Finding, Level, Rule};
+use crate::rules::{key_line, step_label};
+use crate::surface::{self, TriggerSurface};
+use crate::visibility::Visibility;
+use regex::Regex;
+use serde_yaml::Value;
+
+/// Interpreters whose first path argument is the file they run
+const INTERPRETERS: [&str; 12] = ["bash", "sh", "zsh", "source", ".", "node", "python", "python3", "ruby", "perl", "pwsh", "deno"];
+
+/// Events whose runs get no secrets and a read-only token when they come from a fork; every other
+/// trigger runs with the repository's credentials
+const UNPRIVILEGED_EVENTS: [&str; 3] = ["pull_request", "pull_request_review", "pull_request_review_comment"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Store {
+    Cache,
+    Artifact,
+}
+
+/// A cache or artifact a job of an untrusted workflow writes
+struct Write<'w> {
+    store: Store,
+    /// The key or name with each expression replaced by `*`
+    name: String,
+    workflow: &'w str,
+    trigger: String,
+}
+
+/// A step of a privileged job that restores a cache or another workflow's artifact
+struct Restore {
+    store: Store,
+    action: String,
+    label: String,
+    /// Keys or names it accepts, each with whether it is a prefix (`restore-keys`); empty for a
+    /// download of every artifact
+    accepts: Vec<(String, bool)>,
+    /// Directories it fills, relative to the workspace; the empty string is the workspace itself
+    paths: Vec<String>,
+}
+
+/// The key or name as it can be compared across workflows, with expressions as `*`
+fn skeleton(value: &str) -> String {
+    let expression = Regex::new(r"\$\{\{.*?\}\}").unwrap();
+    expression.replace_all(value.trim(), "*").into_owned()
+}
+
+fn input<'v>(step: &'v Value, key: &str) -> Option<&'v str> {
+    step.get("with").and_then(|w| w.get(key)).and_then(|v| v.as_str())
+}
+
+/// A directory as run steps refer to it: no `./`, no trailing `/`, glob patterns cut at the
+/// first wildcard. `None` for a glob with no directory before its wildcard.
+fn normalize_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    let path = path.strip_prefix("${{ github.workspace }}").unwrap_or(path);
+    let path = path.trim_start_matches('/').trim_start_matches("./");
+    match path.find('*') {
+        Some(0) => None,
+        Some(at) => Some(path[..at].trim_end_matches('/').to_string()),
+        None => Some(if path == "." { String::new() } else { path.trim_end_matches('/').to_string() }),
+    }
+}
+
+/// Multi-line `path:` inputs list one path per line
+fn paths(value: Option<&str>, default: &str) -> Vec<String> {
+    value.unwrap_or(default).lines().filter(|l| !l.trim().is_empty()).filter_map(normalize_path).collect()
+}
+
+fn uses(step: &Value) -> &str {
+    step.get("uses").and_then(|v| v.as_str()).unwrap_or("")
+}
+
+fn writes(step: &Value) -> Option<(Store, String)> {
+    let action = uses(step);
+    if action.starts_with("actions/cache@") || action.starts_with("actions/cache/save@") {
+        return Some((Store::Cache, skeleton(input(step, "key")?)));
+    }
+    if action.starts_with("actions/upload-artifact@") {
+        return Some((Store::Artifact, skeleton(input(step, "name").unwrap_or("artifact"))));
+    }
+    None
+}
+
+fn restores(step: &Value, index: usize) -> Option<Restore> {
+    let action = uses(step);
+    let restore = |store, accepts, paths| Restore { store, action: action.to_string(), label: step_label(step, index), accepts, paths };
+    if action.starts_with("actions/cache@") || action.starts_with("actions/cache/restore@") {
+        let mut accepts = vec![(skeleton(input(step, "key")?), false)];
+        let prefixes = input(step, "restore-keys").unwrap_or("");
+        accepts.extend(prefixes.lines().filter(|l| !l.trim().is_empty()).map(|l| (skeleton(l), true)));
+        return Some(restore(Store::Cache, accepts, paths(input(step, "path"), "")));
+    }
+    // download-artifact only reaches another workflow's artifacts when given a run id
+    let cross_workflow = (action.starts_with("actions/download-artifact@") && input(step, "run-id").is_some())
+        || action.starts_with("dawidd6/action-download-artifact@");
+    if cross_workflow {
+        let accepts = input(step, "name").map(|name| vec![(skeleton(name), false)]).unwrap_or_default();
+        return Some(restore(Store::Artifact, accepts, paths(input(step, "path"), ".")));
+    }
+    None
+}
+
+impl Restore {
+    /// Whether the restore can take what `write` stored
+    fn accepts(&self, write: &Write) -> bool {
+        if self.store != write.store {
+            return false;
+        }
+        if self.accepts.is_empty() {
+            return true;
+        }
+        self.accepts.iter().any(|(key, prefix)| {
+            *key == write.name || (*prefix && !key.trim_end_matches('*').is_empty() && write.name.starts_with(key.trim_end_matches('*')))
+        })
+    }
+
+    fn describe(&self) -> String {
+        match (self.store, self.accepts.first()) {
+            (Store::Cache, Some((key, _))) => format!("the cache key `{}`", key),
+            (Store::Artifact, Some((name, _))) => format!("artifact `{}`", name),
+            (_, None) => "every artifact of the run".to_string(),
+        }
+    }
+}
+
+/// The file a shell command runs: its first word when that is a path, or the first path argument
+/// of an interpreter
+fn executed_file(command: &str) -> Option<&str> {
+    let mut words = command.split_whitespace().skip_while(|w| *w == "sudo" || w.contains('='));
+    let first = words.next()?;
+    if INTERPRETERS.contains(&first) {
+        return words.find(|w| !w.starts_with('-'));
+    }
+    first.contains('/').then_some(first)
+}
+
+/// Whether `file` lies in the restored directory `path`
+fn under(file: &str, path: &str) -> bool {
+    let file = file.trim_matches(|c| c == '"' || c == '\'');
+    let file = ["$GITHUB_WORKSPACE/", "${GITHUB_WORKSPACE}/", "${{ github.workspace }}/"]
+        .iter()
+        .find_map(|prefix| file.strip_prefix(prefix))
+        .unwrap_or(file);
+    let file = file.strip_prefix("./").unwrap_or(file);
+    if path.is_empty() {
+        return !file.starts_with(['/', '~', '$']);
+    }
+    file == path || file.starts_with(&format!("{}/", path))
+}
+
+/// The first line of a later step that runs a file from the restored paths, or the step's
+/// `uses:` when it is a local action there
+fn executes<'s>(step: &'s Value, restore: &Restore) -> Option<&'s str> {
+    let action = uses(step);
+    if let Some(local) = action.strip_prefix("./")
+        && restore.paths.iter().any(|p| under(local, p))
+    {
+        return Some(action);
+    }
+    let script = step.get("run").and_then(|v| v.as_str())?;
+    let separators = Regex::new(r"&&|\|\||;|\|").unwrap();
+    script.lines().find(|line| {
+        separators
+            .split(line)
+            .filter_map(executed_file)
+            .any(|file| restore.paths.iter().any(|p| under(file, p)))
+    })
+}
+
+/// The events of the workflow that run with the repository's secrets and a write token
+fn privileged_events(workflow: &Value) -> Vec<&str> {
+    let events: Vec<&str> = match workflow.get("on") {
+        Some(Value::String(event)) => vec![event.as_str()],
+        Some(Value::Sequence(events)) => events.iter().filter_map(|e| e.as_str()).collect(),
+        Some(Value::Mapping(events)) => events.keys().filter_map(|e| e.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    events.into_iter().filter(|e| !UNPRIVILEGED_EVENTS.contains(e)).collect()
+}
+
+fn steps(job: &Value) -> &[Value] {
+    job.get("steps").and_then(|v| v.as_sequence()).map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Opt-in `--scan-cache-poisoning` rule, across every workflow of the scan: flag steps of
+/// privileged workflows that run files restored from a cache, or downloaded from another
+/// workflow's artifacts, which a workflow any GitHub user can trigger also writes under a
+/// matching key or name. Keys are compared with their expressions as wildcards, and a
+/// `restore-keys` prefix matches every key it starts. Restores whose files nothing runs are
+/// left alone.
+pub fn check(workflows: &[&Workflow], visibility: Visibility) -> Vec<Finding> {
+    let mut written = Vec::new();
+    for workflow in workflows {
+        let surface = surface::classify(&workflow.value, visibility);
+        if surface.surface != TriggerSurface::Anyone {
+            continue;
+        }
+        let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else { continue };
+        for step in jobs.values().flat_map(steps) {
+            if let Some((store, name)) = writes(step) {
+                written.push(Write { store, name, workflow: &workflow.path, trigger: surface.describe() });
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    for workflow in workflows {
+        let events = privileged_events(&workflow.value);
+        if events.is_empty() {
+            continue;
+        }
+        let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else { continue };
+        for (job_id, job) in jobs {
+            let job_id = job_id.as_str().unwrap_or("<unnamed>");
+            let job_line = key_line(&workflow.source, &["jobs", job_id]);
+            let steps = steps(job);
+            for (index, step) in steps.iter().enumerate() {
+                let Some(restore) = restores(step, index) else { continue };
+                let Some(write) = written.iter().find(|w| w.workflow != workflow.path && restore.accepts(w)) else { continue };
+                for (later, next) in steps.iter().enumerate().skip(index + 1) {
+                    let Some(line) = executes(next, &restore) else { continue };
+                    let message = format!(
+                        "Job {}, {}, runs `{}` from files {} restores from {}, which {} (triggered by {}) also writes; \
+                         a fork's run can plant them for this workflow, triggered by {}",
+                        job_id,
+                        step_label(next, later),
+                        line.trim(),
+                        restore.label,
+                        restore.describe(),
+                        write.workflow,
+                        write.trigger,
+                        events.join(", ")
+                    );
+                    let line_number = source_line(&workflow.source, line, job_line).or(job_line);
+                    findings.push(
+                        Finding::new(Rule::CachePoisoning, Level::Warning, &restore.action, message)
+                            .with_job(job_id)
+                            .with_step(&step_label(next, later))
+                            .at(&workflow.path, line_number),
+                    );
+                }
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fork's pull request fills the dependency cache; its only job runs the untrusted build
+    const WRITER: &str = "on: pull_request_target
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/cache@v4
+        with:
+          path: tools
+          key: tools-${{ hashFiles('tools.lock') }}
+      - run: make test
+";
+
+    /// A release on push restores the same cache and runs a script out of it
+    const RESTORER: &str = "on: push
+jobs:
+  release:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/cache/restore@v4
+        with:
+          path: tools
+          key: tools-${{ hashFiles('tools.lock') }}
+      - name: publish
+        run: ./tools/publish.sh
+";
+
+    fn workflow(path: &str, source: &str) -> Workflow {
+        Workflow::parse(path, source).unwrap()
+    }
+
+    fn findings(workflows: &[Workflow]) -> Vec<Finding> {
+        check(&workflows.iter().collect::<Vec<_>>(), Visibility::Public)
+    }
+
+    #[test]
+    fn a_privileged_job_running_a_cache_an_untrusted_trigger_writes_is_flagged() {
+        let findings = findings(&[workflow("pr.yml", WRITER), workflow("release.yml", RESTORER)]);
+        let [finding] = findings.as_slice() else { panic!("{:?}", findings) };
+        assert_eq!((finding.rule, finding.level), (Rule::CachePoisoning, Level::Warning));
+        assert_eq!((finding.job.as_deref(), finding.file.as_deref(), finding.line), (Some("release"), Some("release.yml"), Some(11)));
+        assert!(finding.message.contains("runs `./tools/publish.sh`"), "{}", finding.message);
+        assert!(finding.message.contains("the cache key `tools-*`, which pr.yml"), "{}", finding.message);
+        assert!(finding.message.ends_with("triggered by push"), "{}", finding.message);
+    }
+
+    #[test]
+    fn a_restore_keys_prefix_takes_the_untrusted_cache() {
+        let restorer = RESTORER.replace("          key: tools-${{ hashFiles('tools.lock') }}\n", "          key: release-tools\n          restore-keys: |\n            tools-\n");
+        assert_eq!(findings(&[workflow("pr.yml", WRITER), workflow("release.yml", &restorer)]).len(), 1);
+    }
+
+    #[test]
+    fn a_cache_shared_by_workflows_of_the_same_trust_is_not_flagged() {
+        let writer = WRITER.replace("on: pull_request_target", "on: push");
+        assert!(findings(&[workflow("build.yml", &writer), workflow("release.yml", RESTORER)]).is_empty());
+        let unprivileged = RESTORER.replace("on: push", "on: pull_request");
+        assert!(findings(&[workflow("pr.yml", WRITER), workflow("preview.yml", &unprivileged)]).is_empty());
+    }
+
+    #[test]
+    fn a_restore_whose_files_nothing_runs_is_left_alone() {
+        let restorer = RESTORER.replace("run: ./tools/publish.sh", "run: make publish");
+        assert!(findings(&[workflow("pr.yml", WRITER), workflow("release.yml", &restorer)]).is_empty());
+    }
+
+    #[test]
+    fn a_different_key_is_not_the_written_cache() {
+        let restorer = RESTORER.replace("key: tools-", "key: release-");
+        assert!(findings(&[workflow("pr.yml", WRITER), workflow("release.yml", &restorer)]).is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+//! `--porcelain=v1`: a line-oriented report for tools that wrap the scanner.
+//!
+//! The format below is a contract: within a major version of the scanner, v1 records keep their
+//! names, field order and field count. New information gets a new record type, which readers
+//! must ignore when they do not know it, or a new porcelain version.
+//!
+//! ```text
+//! porcelain   v1
+//! finding     <level> <rule> <file> <line> <action> <message>
+//! unchecked   <action>
+//! verdict     <file> <pass|fail> <errors> <warnings> <notes>
+//! exit        <code> <name> <reason>
+//! ```
+//!
+//! Fields are separated by a tab and records end with a newline. Absent values (a finding without
+//! a file, line or action) are written as `-`. A backslash, tab, carriage return or newline inside
+//! a field is escaped as `\\`, `\t`, `\r` or `\n`.
+//!
+//! With `-z` nothing is escaped: every field, the last one included, is terminated by a NUL, and
+//! since each record type has a fixed number of fields no record separator is needed.
+//!
+//! The `porcelain` record always comes first and `exit` always last; a run that ends without an
+//! `exit` record failed before it could scan. Human-readable output goes to stderr.
+
+use crate::decision::Decision;
+use crate::report::ScanReport;
+
+/// Porcelain versions this build can write
+pub const VERSIONS: [&str; 1] = ["v1"];
+
+/// Validate a requested `--porcelain` version
+pub fn parse_version(value: &str) -> Result<String, String> {
+    if VERSIONS.contains(&value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("unsupported porcelain version {} (supported: {})", value, VERSIONS.join(", ")))
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn record(out: &mut Vec<u8>, fields: &[&str], nul: bool) {
+    if nul {
+        for field in fields {
+            out.extend_from_slice(field.as_bytes());
+            out.push(0);
+        }
+    } else {
+        let fields: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+        out.extend_from_slice(fields.join("\t").as_bytes());
+        out.push(b'\n');
+    }
+}
+
+fn or_dash(value: Option<&str>) -> &str {
+    value.filter(|v| !v.is_empty()).unwrap_or("-")
+}
+
+/// Render the report in porcelain v1
+pub fn render(report: &ScanReport, decision: &Decision, nul: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    record(&mut out, &["porcelain", "v1"], nul);
+    for finding in &report.findings {
+        let level = finding.level.to_string();
+        let line = finding.line.map(|l| l.to_string());
+        record(
+            &mut out,
+            &[
+                "finding",
+                &level,
+                report.rule_id(finding.rule),
+                or_dash(finding.file.as_deref()),
+                or_dash(line.as_deref()),
+                or_dash(Some(&finding.action)),
+                &finding.message,
+            ],
+            nul,
+        );
+    }
+    for action in &report.unchecked {
+        record(&mut out, &["unchecked", action], nul);
+    }
+    for file in &decision.files {
+        let counts = [file.counts.error, file.counts.warning, file.counts.note].map(|c| c.to_string());
+        let verdict = if file.passed { "pass" } else { "fail" };
+        record(&mut out, &["verdict", &file.file, verdict, &counts[0], &counts[1], &counts[2]], nul);
+    }
+    let code = decision.exit.code.to_string();
+    record(&mut out, &["exit", &code, decision.exit.name, &decision.exit.reason], nul);
+    out
+}
@@ -0,0 +1,468 @@
+use crate::fix::{self, PinChange};
+use crate::github::GitHubApi;
+use crate::pincomment::CommentStyle;
+use crate::scanner::ScanEvents;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use reqwest::Method;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+/// Branch used for every scanner PR, which is what makes re-runs update rather than duplicate
+pub const DEFAULT_BRANCH: &str = "dependency-scanner/pin-actions";
+
+const PR_TITLE: &str = "Pin GitHub Actions to commit SHAs";
+
+/// Trailer on every commit the scanner pushes, which marks a branch as one it may reset
+pub const COMMIT_MARKER: &str = "Pushed-by: dependency-scanner";
+
+/// Last line of the PR body, hidden when GitHub renders it, marking the PR as the one each run
+/// updates
+pub const BODY_MARKER: &str = "<!-- dependency-scanner: pin-actions -->";
+
+pub struct PrOptions {
+    /// Repository in `owner/repo` form
+    pub repo: String,
+    /// Branch to open the PR against; defaults to the repository's default branch
+    pub base: Option<String>,
+    pub branch: String,
+    pub labels: Vec<String>,
+    pub draft: bool,
+}
+
+/// The pins to apply to one workflow file, keyed by the original `uses:` value
+pub struct FilePins {
+    /// Path of the workflow relative to the repository root
+    pub path: String,
+    pub pins: BTreeMap<String, String>,
+    /// How the version comments next to new pins are written, as the workflow's config says
+    pub comment_style: CommentStyle,
+}
+
+pub enum PrOutcome {
+    Opened(u64, String),
+    Updated(u64, String),
+    NoChanges { closed: Option<u64> },
+}
+
+/// Commit the rewritten workflows to the scanner branch and open or update its PR, with the
+/// markdown `report` of the scan as its body, telling `events` about the files it skips
+pub async fn create_or_update(
+    api: &GitHubApi<'_>,
+    events: &dyn ScanEvents,
+    options: &PrOptions,
+    files: &[FilePins],
+    report: &str,
+) -> Result<PrOutcome, std::io::Error> {
+    let Some((owner, name)) = options.repo.split_once('/') else {
+        return Err(std::io::Error::other(format!("Repository {} is not in owner/repo form", options.repo)));
+    };
+    let repo = &format!("{}/{}", segment(owner), segment(name));
+    let branch = &segment(&options.branch);
+    let repo_info = api
+        .get(&format!("/repos/{}", repo))
+        .await?
+        .ok_or_else(|| std::io::Error::other(format!("Repository {} not found", options.repo)))?;
+    let default_branch = repo_info["default_branch"].as_str().unwrap_or("main").to_string();
+
+    // Never write to the default branch, no matter what the caller asked for
+    if options.branch == default_branch {
+        return Err(std::io::Error::other(format!(
+            "Refusing to push fixes directly to the default branch {}",
+            default_branch
+        )));
+    }
+    let base = options.base.clone().unwrap_or(default_branch);
+    // Resetting the branch onto the base would leave nothing to compare it with
+    if options.branch == base {
+        return Err(std::io::Error::other(format!(
+            "The fix branch {} is the base branch; pass --branch to commit the fixes elsewhere",
+            base
+        )));
+    }
+
+    let existing = api
+        .get(&format!("/repos/{}/pulls?state=open&head={}:{}", repo, segment(owner), branch))
+        .await?
+        .and_then(|prs| prs.as_array().and_then(|prs| prs.first().cloned()));
+    let existing_number = existing.as_ref().and_then(|pr| pr["number"].as_u64());
+
+    // Rewrite the files as they are on the base branch, not as they happen to be locally
+    let mut rewritten = Vec::new();
+    for file in files {
+        let Some(remote) = api.get(&format!("/repos/{}/contents/{}?ref={}", repo, path_segments(&file.path), segment(&base))).await? else {
+            events.warning(&format!("Warning: {} does not exist on {}, skipping", file.path, base));
+            continue;
+        };
+        let content = decode_content(&remote)?;
+        let (updated, changes) = fix::rewrite_workflow(&content, &file.pins, &file.comment_style);
+        if !changes.is_empty() {
+            rewritten.push((file.path.as_str(), updated, changes));
+        }
+    }
+
+    if rewritten.is_empty() {
+        let closed = match existing_number {
+            Some(number) => {
+                ensure_own_branch(api, repo, &options.branch, None).await?;
+                close_stale(api, repo, number, branch).await?;
+                Some(number)
+            }
+            None => None,
+        };
+        return Ok(PrOutcome::NoChanges { closed });
+    }
+
+    // Reset the scanner branch onto the base so each run produces a fresh, minimal set of commits
+    let base_ref = api
+        .get(&format!("/repos/{}/git/ref/heads/{}", repo, segment(&base)))
+        .await?
+        .ok_or_else(|| std::io::Error::other(format!("Base branch {} not found", base)))?;
+    let base_sha = base_ref["object"]["sha"].as_str().unwrap_or("").to_string();
+    if ensure_own_branch(api, repo, &options.branch, Some(&base_sha)).await? {
+        api.send(
+            Method::PATCH,
+            &format!("/repos/{}/git/refs/heads/{}", repo, branch),
+            &json!({ "sha": base_sha, "force": true }),
+        )
+        .await?;
+    } else {
+        api.send(
+            Method::POST,
+            &format!("/repos/{}/git/refs", repo),
+            &json!({ "ref": format!("refs/heads/{}", options.branch), "sha": base_sha }),
+        )
+        .await?;
+    }
+
+    for (path, content, changes) in &rewritten {
+        let current = api
+            .get(&format!("/repos/{}/contents/{}?ref={}", repo, path_segments(path), branch))
+            .await?;
+        let mut body = json!({
+            "message": commit_message(path, changes),
+            "content": BASE64.encode(content),
+            "branch": options.branch,
+        });
+        if let Some(sha) = current.as_ref().and_then(|c| c["sha"].as_str()) {
+            body["sha"] = json!(sha);
+        }
+        api.send(Method::PUT, &format!("/repos/{}/contents/{}", repo, path_segments(path)), &body).await?;
+    }
+
+    let body = pr_body(report);
+    let outcome = match existing {
+        Some(pr) => {
+            let number = pr["number"].as_u64().unwrap_or_default();
+            api.send(
+                Method::PATCH,
+                &format!("/repos/{}/pulls/{}", repo, number),
+                &json!({ "title": PR_TITLE, "body": body }),
+            )
+            .await?;
+            PrOutcome::Updated(number, pr["html_url"].as_str().unwrap_or("").to_string())
+        }
+        None => {
+            let pr = api
+                .send(
+                    Method::POST,
+                    &format!("/repos/{}/pulls", repo),
+                    &json!({
+                        "title": PR_TITLE,
+                        "head": options.branch,
+                        "base": base,
+                        "body": body,
+                        "draft": options.draft,
+                    }),
+                )
+                .await?;
+            PrOutcome::Opened(
+                pr["number"].as_u64().unwrap_or_default(),
+                pr["html_url"].as_str().unwrap_or("").to_string(),
+            )
+        }
+    };
+
+    let number = match &outcome {
+        PrOutcome::Opened(number, _) | PrOutcome::Updated(number, _) => *number,
+        PrOutcome::NoChanges { .. } => unreachable!(),
+    };
+    if !options.labels.is_empty() {
+        api.send(
+            Method::POST,
+            &format!("/repos/{}/issues/{}/labels", repo, number),
+            &json!({ "labels": options.labels }),
+        )
+        .await?;
+    }
+
+    Ok(outcome)
+}
+
+/// Percent-encode a URL path segment, leaving only unreserved characters as they are, so a `/`
+/// or `#` in a branch name cannot change which resource a request names
+pub fn segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// A repository file path with each of its segments encoded
+fn path_segments(path: &str) -> String {
+    path.split('/').map(segment).collect::<Vec<_>>().join("/")
+}
+
+/// Whether the fix branch exists, failing when it does and is not the scanner's: its head commit
+/// must carry [`COMMIT_MARKER`], or be the base commit a run that stopped early reset it to.
+/// Resetting any other branch would throw away someone's work.
+async fn ensure_own_branch(
+    api: &GitHubApi<'_>,
+    repo: &str,
+    branch: &str,
+    base_sha: Option<&str>,
+) -> Result<bool, std::io::Error> {
+    let Some(head) = api.get(&format!("/repos/{}/git/ref/heads/{}", repo, segment(branch))).await? else {
+        return Ok(false);
+    };
+    let sha = head["object"]["sha"].as_str().unwrap_or("");
+    if base_sha == Some(sha) {
+        return Ok(true);
+    }
+    let commit = api.get(&format!("/repos/{}/git/commits/{}", repo, segment(sha))).await?;
+    let message = commit.as_ref().and_then(|c| c["message"].as_str()).unwrap_or("");
+    if message.lines().any(|line| line.trim() == COMMIT_MARKER) {
+        return Ok(true);
+    }
+    Err(std::io::Error::other(format!(
+        "Branch {} exists and its last commit was not made by dependency-scanner; refusing to reset it (pass --branch to use another branch)",
+        branch
+    )))
+}
+
+/// Close a scanner PR whose fixes have all landed (or disappeared) and remove its branch
+async fn close_stale(api: &GitHubApi<'_>, repo: &str, number: u64, branch: &str) -> Result<(), std::io::Error> {
+    api.send(
+        Method::POST,
+        &format!("/repos/{}/issues/{}/comments", repo, number),
+        &json!({ "body": "All actions are pinned now, closing this PR." }),
+    )
+    .await?;
+    api.send(Method::PATCH, &format!("/repos/{}/pulls/{}", repo, number), &json!({ "state": "closed" }))
+        .await?;
+    api.send(Method::DELETE, &format!("/repos/{}/git/refs/heads/{}", repo, branch), &Value::Null)
+        .await?;
+    Ok(())
+}
+
+pub fn decode_content(file: &Value) -> Result<String, std::io::Error> {
+    let encoded: String = file["content"]
+        .as_str()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| std::io::Error::other(format!("Could not decode file contents: {}", e)))?;
+    String::from_utf8(bytes).map_err(|e| std::io::Error::other(format!("File is not valid UTF-8: {}", e)))
+}
+
+fn commit_message(path: &str, changes: &[PinChange]) -> String {
+    let mut message = format!(
+        "Pin {} in {} to commit SHAs\n",
+        if changes.len() == 1 { "1 action".to_string() } else { format!("{} actions", changes.len()) },
+        path
+    );
+    for change in changes {
+        message.push_str(&format!("\n- line {}: {} -> {}", change.line, change.from, change.to));
+    }
+    message.push_str(&format!("\n\n{}", COMMIT_MARKER));
+    message
+}
+
+/// The markdown report, as `--report` writes it, then [`BODY_MARKER`]. The pins themselves are
+/// in the commit messages.
+fn pr_body(report: &str) -> String {
+    format!("{}\n{}\n", report.trim_end(), BODY_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::Endpoints;
+    use crate::http::{HttpClient, HttpRequest, HttpResponse, ResponseFuture};
+    use crate::scanner::Silent;
+    use std::sync::Mutex;
+
+    const BASE_SHA: &str = "1111111111111111111111111111111111111111";
+    const HEAD_SHA: &str = "2222222222222222222222222222222222222222";
+    const PIN: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    /// A GitHub answering GETs from `resources`, by path, with 404 for anything else, and every
+    /// other request with a bare pull request; it records what it was sent
+    struct FakeGitHub {
+        resources: BTreeMap<String, Value>,
+        sent: Mutex<Vec<(Method, String)>>,
+        bodies: Mutex<Vec<Value>>,
+    }
+
+    impl FakeGitHub {
+        fn new(resources: &[(&str, Value)]) -> Self {
+            let resources = resources.iter().map(|(path, value)| (path.to_string(), value.clone())).collect();
+            FakeGitHub { resources, sent: Mutex::new(Vec::new()), bodies: Mutex::new(Vec::new()) }
+        }
+
+        fn writes(&self) -> Vec<(Method, String)> {
+            self.sent.lock().unwrap().iter().filter(|(method, _)| method != Method::GET).cloned().collect()
+        }
+
+        fn paths(&self) -> Vec<String> {
+            self.sent.lock().unwrap().iter().map(|(_, path)| path.clone()).collect()
+        }
+    }
+
+    impl HttpClient for FakeGitHub {
+        fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+            let path = request.url.strip_prefix(crate::github::API_BASE).unwrap_or(&request.url).to_string();
+            self.sent.lock().unwrap().push((request.method.clone(), path.clone()));
+            self.bodies.lock().unwrap().extend(request.body.clone());
+            let (status, body) = match (&request.method, self.resources.get(&path)) {
+                (&Method::GET, Some(value)) => (200, value.to_string()),
+                (&Method::GET, None) => (404, String::new()),
+                _ => (200, json!({ "number": 7, "html_url": "https://github.com/o/r/pull/7" }).to_string()),
+            };
+            Box::pin(async move { Ok(HttpResponse { status, headers: BTreeMap::new(), body }) })
+        }
+    }
+
+    fn options(branch: &str, base: Option<&str>) -> PrOptions {
+        PrOptions {
+            repo: "o/r".to_string(),
+            base: base.map(str::to_string),
+            branch: branch.to_string(),
+            labels: Vec::new(),
+            draft: false,
+        }
+    }
+
+    fn files() -> Vec<FilePins> {
+        let pins = BTreeMap::from([("actions/checkout@v4".to_string(), PIN.to_string())]);
+        vec![FilePins { path: ".github/workflows/ci.yml".to_string(), pins, comment_style: CommentStyle::default() }]
+    }
+
+    fn workflow() -> Value {
+        json!({ "content": BASE64.encode("steps:\n  - uses: actions/checkout@v4\n") })
+    }
+
+    /// The repository with default branch `main` at [`BASE_SHA`]; `branch` and `head` describe
+    /// the fix branch when it already exists
+    fn github(branch: &str, head: Option<&str>) -> FakeGitHub {
+        let mut resources = vec![
+            ("/repos/o/r", json!({ "default_branch": "main" })),
+            ("/repos/o/r/git/ref/heads/main", json!({ "object": { "sha": BASE_SHA } })),
+            ("/repos/o/r/contents/.github/workflows/ci.yml?ref=main", workflow()),
+        ];
+        let ref_path = format!("/repos/o/r/git/ref/heads/{}", segment(branch));
+        let commit_path = format!("/repos/o/r/git/commits/{}", HEAD_SHA);
+        if let Some(message) = head {
+            resources.push((&ref_path, json!({ "object": { "sha": HEAD_SHA } })));
+            resources.push((&commit_path, json!({ "message": message })));
+        }
+        FakeGitHub::new(&resources)
+    }
+
+    #[tokio::test]
+    async fn fix_branch_equal_to_the_base_is_refused() {
+        let github = github("release", None);
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        let error = create_or_update(&api, &Silent, &options("release", Some("release")), &files(), "").await.err().unwrap();
+        assert!(error.to_string().contains("is the base branch"), "{}", error);
+        assert!(github.writes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn default_branch_is_refused() {
+        let github = github("main", None);
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        assert!(create_or_update(&api, &Silent, &options("main", None), &files(), "").await.is_err());
+        assert!(github.writes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn branch_without_the_marker_is_not_reset() {
+        let github = github(DEFAULT_BRANCH, Some("Someone's own work"));
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        let error = create_or_update(&api, &Silent, &options(DEFAULT_BRANCH, None), &files(), "").await.err().unwrap();
+        assert!(error.to_string().contains("refusing to reset it"), "{}", error);
+        assert!(github.writes().is_empty(), "{:?}", github.writes());
+    }
+
+    #[tokio::test]
+    async fn branch_with_the_marker_is_reset_onto_the_base() {
+        let message = format!("Pin 1 action in ci.yml to commit SHAs\n\n{}", COMMIT_MARKER);
+        let github = github(DEFAULT_BRANCH, Some(&message));
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        let outcome = create_or_update(&api, &Silent, &options(DEFAULT_BRANCH, None), &files(), "").await.unwrap();
+        assert!(matches!(outcome, PrOutcome::Opened(7, _)));
+        let reset = (Method::PATCH, "/repos/o/r/git/refs/heads/dependency-scanner%2Fpin-actions".to_string());
+        assert_eq!(github.writes().first(), Some(&reset));
+    }
+
+    #[tokio::test]
+    async fn new_branch_is_created() {
+        let github = github(DEFAULT_BRANCH, None);
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        create_or_update(&api, &Silent, &options(DEFAULT_BRANCH, None), &files(), "").await.unwrap();
+        assert_eq!(github.writes().first(), Some(&(Method::POST, "/repos/o/r/git/refs".to_string())));
+    }
+
+    #[tokio::test]
+    async fn branch_and_repository_are_encoded_in_paths() {
+        let branch = "fix/pins #1?x";
+        let github = github(branch, None);
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        create_or_update(&api, &Silent, &options(branch, None), &files(), "").await.unwrap();
+        let paths = github.paths();
+        assert!(paths.contains(&"/repos/o/r/pulls?state=open&head=o:fix%2Fpins%20%231%3Fx".to_string()), "{:?}", paths);
+        assert!(paths.contains(&"/repos/o/r/git/ref/heads/fix%2Fpins%20%231%3Fx".to_string()), "{:?}", paths);
+        assert!(paths.contains(&"/repos/o/r/contents/.github/workflows/ci.yml?ref=fix%2Fpins%20%231%3Fx".to_string()), "{:?}", paths);
+        assert!(paths.iter().all(|path| !path.contains(' ') && !path.contains('#')), "{:?}", paths);
+    }
+
+    #[tokio::test]
+    async fn the_body_is_the_markdown_report_with_the_marker() {
+        let github = github(DEFAULT_BRANCH, None);
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&github, &endpoints, None);
+        let report = "### Action security scan\n\n**Verdict: fail**\n";
+        create_or_update(&api, &Silent, &options(DEFAULT_BRANCH, None), &files(), report).await.unwrap();
+        let bodies = github.bodies.lock().unwrap();
+        let opened = bodies.iter().find(|body| body.get("head").is_some()).unwrap();
+        assert_eq!(opened["body"], format!("{}\n{}\n", report.trim_end(), BODY_MARKER));
+    }
+
+    #[test]
+    fn segment_keeps_only_unreserved_characters() {
+        assert_eq!(segment("a-b_c.d~e"), "a-b_c.d~e");
+        assert_eq!(segment("a/b c#d?e%"), "a%2Fb%20c%23d%3Fe%25");
+        assert_eq!(segment("é"), "%C3%A9");
+        assert_eq!(path_segments(".github/workflows/c i.yml"), ".github/workflows/c%20i.yml");
+    }
+
+    #[test]
+    fn commit_messages_carry_the_marker() {
+        let (_, changes) = fix::rewrite_workflow("- uses: actions/checkout@v4\n", &files()[0].pins, &CommentStyle::default());
+        assert!(commit_message("ci.yml", &changes).ends_with(&format!("\n\n{}", COMMIT_MARKER)));
+    }
+}
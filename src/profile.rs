@@ -0,0 +1,194 @@
+use serde_yaml::{Mapping, Value};
+
+/// A curated set of config defaults `--profile` merges under the user's config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Baseline,
+    Strict,
+    Paranoid,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 3] = [Profile::Baseline, Profile::Strict, Profile::Paranoid];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Baseline => "baseline",
+            Profile::Strict => "strict",
+            Profile::Paranoid => "paranoid",
+        }
+    }
+
+    /// What the profile turns on, one line each, for `--profile help`
+    pub fn enables(self) -> &'static [&'static str] {
+        match self {
+            Profile::Baseline => &[
+                "branch pins such as @main warn (unstable_ref_level: warning)",
+                "advisories fail: high and critical are errors, moderate warns",
+            ],
+            Profile::Strict => &[
+                "everything in baseline, with branch pins failing (unstable_ref_level: error)",
+                "actions from owners outside trusted_owners must be pinned to a commit SHA",
+                "permissions checks fail: id-token: write reaching untrusted actions, and secrets in job or workflow env",
+            ],
+            Profile::Paranoid => &[
+                "everything in strict",
+                "allowlist trust: an action from an owner outside trusted_owners fails",
+                "a commit SHA is required everywhere; tag pins fail (critical_by_default: true)",
+                "an action that could not be checked fails the scan (fail_on_unchecked: true)",
+            ],
+        }
+    }
+
+    /// The profile as the config fragment it is
+    pub fn fragment(self) -> &'static str {
+        match self {
+            Profile::Baseline => BASELINE,
+            Profile::Strict => STRICT,
+            Profile::Paranoid => PARANOID,
+        }
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Profile::ALL.into_iter().find(|p| p.name() == value).ok_or_else(|| {
+            let names: Vec<&str> = Profile::ALL.iter().map(|p| p.name()).collect();
+            format!("unknown profile {} (expected one of {}, or help)", value, names.join(", "))
+        })
+    }
+}
+
+const BASELINE: &str = "\
+unstable_ref_level: warning
+";
+
+const STRICT: &str = "\
+unstable_ref_level: error
+rule_overrides:
+  - rule: untrusted-owner-no-sha
+    level: error
+  - rule: oidc-untrusted-action
+    level: error
+  - rule: secret-env-exposure
+    level: error
+";
+
+const PARANOID: &str = "\
+unstable_ref_level: error
+critical_by_default: true
+fail_on_unchecked: true
+rule_overrides:
+  - rule: untrusted-owner-no-sha
+    level: error
+  - rule: oidc-untrusted-action
+    level: error
+  - rule: secret-env-exposure
+    level: error
+  - rule: untrusted-owner
+    level: error
+  - rule: tag-pinned-critical
+    level: error
+";
+
+/// `--profile help`: each profile and what it turns on
+pub fn help() -> String {
+    let mut out = String::from("Profiles set defaults under the config; a key the config sets always wins.\n");
+    for profile in Profile::ALL {
+        out.push_str(&format!("\n{}:\n", profile.name()));
+        for line in profile.enables() {
+            out.push_str(&format!("  - {}\n", line));
+        }
+    }
+    out
+}
+
+/// The user's config merged over the profile's fragment. Keys the user sets replace the
+/// profile's, except `rule_overrides`, where the user's entries are appended after the profile's
+/// and so win among equally specific entries.
+pub fn merge_under(profile: Profile, user: Value) -> Result<Value, serde_yaml::Error> {
+    let fragment: Value = serde_yaml::from_str(profile.fragment())?;
+    let mut merged = fragment.as_mapping().cloned().unwrap_or_default();
+    for (key, value) in user.as_mapping().cloned().unwrap_or_else(Mapping::new) {
+        let combined = match (key.as_str(), merged.get(&key), &value) {
+            (Some("rule_overrides"), Some(Value::Sequence(base)), Value::Sequence(extra)) => {
+                Value::Sequence(base.iter().chain(extra).cloned().collect())
+            }
+            _ => value,
+        };
+        merged.insert(key, combined);
+    }
+    Ok(Value::Mapping(merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::report::Level;
+
+    fn config(profile: Profile, user: &str) -> Config {
+        serde_yaml::from_value(merge_under(profile, serde_yaml::from_str(user).unwrap()).unwrap()).unwrap()
+    }
+
+    /// The level each of the profile's rule_overrides gives, by rule id
+    fn levels(config: &Config) -> Vec<(&str, Level)> {
+        config.rule_overrides.iter().flatten().map(|o| (o.rule.as_str(), o.level)).collect()
+    }
+
+    #[test]
+    fn each_profile_sets_what_it_documents() {
+        let baseline = config(Profile::Baseline, "{}");
+        assert_eq!(baseline.unstable_ref_level, Some(Level::Warning));
+        assert_eq!((baseline.critical_by_default, baseline.fail_on_unchecked), (None, None));
+        assert!(levels(&baseline).is_empty());
+
+        let strict = config(Profile::Strict, "{}");
+        assert_eq!(strict.unstable_ref_level, Some(Level::Error));
+        assert_eq!((strict.critical_by_default, strict.fail_on_unchecked), (None, None));
+        let permissions = [("untrusted-owner-no-sha", Level::Error), ("oidc-untrusted-action", Level::Error), ("secret-env-exposure", Level::Error)];
+        assert_eq!(levels(&strict), permissions);
+
+        let paranoid = config(Profile::Paranoid, "{}");
+        assert_eq!(paranoid.unstable_ref_level, Some(Level::Error));
+        assert_eq!((paranoid.critical_by_default, paranoid.fail_on_unchecked), (Some(true), Some(true)));
+        let mut expected = permissions.to_vec();
+        expected.extend([("untrusted-owner", Level::Error), ("tag-pinned-critical", Level::Error)]);
+        assert_eq!(levels(&paranoid), expected);
+    }
+
+    #[test]
+    fn every_profile_is_a_valid_config() {
+        for profile in Profile::ALL {
+            let fragment: Value = serde_yaml::from_str(profile.fragment()).unwrap();
+            crate::schema::validate_config(&fragment).unwrap_or_else(|e| panic!("{}: {}", profile.name(), e));
+            for (rule, _) in levels(&config(profile, "{}")) {
+                assert!(crate::report::Rule::ALL.iter().any(|r| r.id() == rule), "{}: unknown rule {}", profile.name(), rule);
+            }
+        }
+    }
+
+    #[test]
+    fn the_user_config_wins_over_the_profile() {
+        let merged = config(Profile::Paranoid, "unstable_ref_level: note\nfail_on_unchecked: false\ntrusted_owners: [acme]\n");
+        assert_eq!((merged.unstable_ref_level, merged.fail_on_unchecked, merged.critical_by_default), (Some(Level::Note), Some(false), Some(true)));
+        assert_eq!(merged.trusted_owners, Some(["acme".to_string()].into()));
+
+        // The user's rule_overrides come after the profile's, so they win a tie
+        let merged = config(Profile::Strict, "rule_overrides:\n  - {rule: secret-env-exposure, level: note}\n");
+        assert_eq!(levels(&merged).last(), Some(&("secret-env-exposure", Level::Note)));
+        assert_eq!(merged.rule_override("secret-env-exposure", None, "acme/tool@v1", None).map(|(_, o)| o.level), Some(Level::Note));
+        assert_eq!(levels(&merged).len(), 4);
+    }
+
+    #[test]
+    fn names_parse_and_help_lists_every_profile() {
+        for profile in Profile::ALL {
+            assert_eq!(profile.name().parse::<Profile>(), Ok(profile));
+            assert!(help().contains(&format!("\n{}:\n", profile.name())));
+        }
+        assert_eq!("lax".parse::<Profile>().unwrap_err(), "unknown profile lax (expected one of baseline, strict, paranoid, or help)");
+    }
+}
@@ -0,0 +1,240 @@
+//! Push of a scan's metrics to a Prometheus Pushgateway with `--pushgateway-url`, for scheduled
+//! scans that exit before anything could scrape them.
+//!
+//! The metric names, types and labels below are stable; dashboards and alerts may rely on them.
+//! Every run replaces the metrics of its group (job and instance), so each is a gauge of the
+//! latest scan.
+//!
+//! | metric | labels | value |
+//! |---|---|---|
+//! | `dependency_scanner_findings` | `level` | findings at each level, error, warning and note |
+//! | `dependency_scanner_vulnerabilities` | `severity` | advisory findings at each severity |
+//! | `dependency_scanner_actions_scanned` | | distinct actions checked |
+//! | `dependency_scanner_unchecked_actions` | | actions that could not be checked |
+//! | `dependency_scanner_scan_duration_seconds` | | wall time of the scan |
+//! | `dependency_scanner_rate_limit_remaining` | | the API's last `x-ratelimit-remaining`, when it sent one |
+//! | `dependency_scanner_exit_code` | | the exit code the scan decided on |
+//! | `dependency_scanner_last_run_timestamp_seconds` | | when the scan finished, in Unix time |
+
+use crate::http::{HttpClient, HttpRequest, ResponseFuture};
+use crate::report::{Level, Rule, ScanReport};
+use crate::severity::Severity;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Job label used when `--pushgateway-job` is not given
+pub const DEFAULT_JOB: &str = "dependency-scanner";
+
+/// Upper bound for the push request
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Each metric as (name, help); all are gauges
+const METRICS: [(&str, &str); 8] = [
+    ("dependency_scanner_findings", "Findings of the latest scan by level"),
+    ("dependency_scanner_vulnerabilities", "Advisory findings of the latest scan by severity"),
+    ("dependency_scanner_actions_scanned", "Distinct actions the latest scan checked"),
+    ("dependency_scanner_unchecked_actions", "Actions the latest scan could not check against the advisory databases"),
+    ("dependency_scanner_scan_duration_seconds", "Wall time of the latest scan"),
+    ("dependency_scanner_rate_limit_remaining", "GitHub API requests left in the rate limit window after the latest scan"),
+    ("dependency_scanner_exit_code", "Exit code of the latest scan"),
+    ("dependency_scanner_last_run_timestamp_seconds", "Unix time the latest scan finished"),
+];
+
+/// Where the metrics go and what the scan saw on the way
+pub struct Pushgateway {
+    url: String,
+    job: String,
+    instance: Option<String>,
+    start: Instant,
+    rate_limit_remaining: Mutex<Option<u64>>,
+}
+
+impl Pushgateway {
+    /// `instance` defaults to `$GITHUB_REPOSITORY`; without either the group has no instance label
+    pub fn new(url: &str, job: Option<&str>, instance: Option<&str>) -> Arc<Self> {
+        let instance = instance.map(str::to_string).or_else(|| std::env::var("GITHUB_REPOSITORY").ok().filter(|r| !r.is_empty()));
+        Arc::new(Pushgateway {
+            url: url.trim_end_matches('/').to_string(),
+            job: job.unwrap_or(DEFAULT_JOB).to_string(),
+            instance,
+            start: Instant::now(),
+            rate_limit_remaining: Mutex::new(None),
+        })
+    }
+
+    /// The grouping key as a URL path. Label values go base64-encoded, which the Pushgateway
+    /// accepts for values with `/` in them, such as `owner/repo`.
+    fn group_url(&self) -> String {
+        let mut url = format!("{}/metrics/job@base64/{}", self.url, URL_SAFE.encode(&self.job));
+        if let Some(instance) = &self.instance {
+            url.push_str(&format!("/instance@base64/{}", URL_SAFE.encode(instance)));
+        }
+        url
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self, report: &ScanReport, exit_code: i32) -> String {
+        let mut out = String::new();
+        let mut gauge = |index: usize, samples: Vec<(String, f64)>| {
+            let (name, help) = METRICS[index];
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+            for (labels, value) in samples {
+                out.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+        let levels = [Level::Error, Level::Warning, Level::Note];
+        let by_level = levels.iter().map(|level| {
+            let count = report.findings.iter().filter(|f| f.level == *level).count();
+            (format!("{{level=\"{}\"}}", level), count as f64)
+        });
+        gauge(0, by_level.collect());
+        let severities = [Severity::Critical, Severity::High, Severity::Medium, Severity::Low];
+        let by_severity = severities.iter().map(|severity| {
+            let count = report.findings.iter().filter(|f| f.rule == Rule::KnownVulnerability && f.severity == Some(*severity)).count();
+            (format!("{{severity=\"{}\"}}", severity), count as f64)
+        });
+        gauge(1, by_severity.collect());
+        gauge(2, vec![(String::new(), report.actions_scanned as f64)]);
+        gauge(3, vec![(String::new(), report.unchecked.len() as f64)]);
+        gauge(4, vec![(String::new(), self.start.elapsed().as_secs_f64())]);
+        let remaining = *self.rate_limit_remaining.lock().unwrap_or_else(|e| e.into_inner());
+        gauge(5, remaining.map(|r| (String::new(), r as f64)).into_iter().collect());
+        gauge(6, vec![(String::new(), f64::from(exit_code))]);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        gauge(7, vec![(String::new(), now as f64)]);
+        out
+    }
+
+    /// Replace the group's metrics with this scan's. Failures are warnings; the scan's exit code
+    /// never depends on the push.
+    pub async fn push(&self, report: &ScanReport, exit_code: i32) {
+        let client = match reqwest::Client::builder().timeout(PUSH_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Warning: could not push metrics: {}", e);
+                return;
+            }
+        };
+        let body = self.render(report, exit_code);
+        let result = client.put(self.group_url()).header("content-type", "text/plain; version=0.0.4").body(body).send().await;
+        match result {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => eprintln!("Warning: Pushgateway push to {} returned {}", self.url, response.status()),
+            Err(e) => eprintln!("Warning: Pushgateway push to {} failed: {}", self.url, e.without_url()),
+        }
+    }
+}
+
+/// Notes the rate limit each response reports, for `dependency_scanner_rate_limit_remaining`
+pub struct RateLimitWatch {
+    pub inner: Box<dyn HttpClient>,
+    pub gateway: Arc<Pushgateway>,
+}
+
+impl HttpClient for RateLimitWatch {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let result = self.inner.send(request).await;
+            if let Some(remaining) = result.as_ref().ok().and_then(|r| r.header("x-ratelimit-remaining")).and_then(|v| v.parse().ok()) {
+                *self.gateway.rate_limit_remaining.lock().unwrap_or_else(|e| e.into_inner()) = Some(remaining);
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Finding;
+
+    fn report() -> ScanReport {
+        let finding = |rule: Rule, level: Level| Finding::new(rule, level, "acme/tool@v1", "found".to_string());
+        ScanReport {
+            actions_scanned: 3,
+            unchecked: vec!["acme/other@v2".to_string()],
+            findings: vec![
+                finding(Rule::KnownVulnerability, Level::Error).with_severity(Some(Severity::High)),
+                finding(Rule::KnownVulnerability, Level::Error).with_severity(Some(Severity::Critical)),
+                finding(Rule::UntrustedOwner, Level::Warning),
+            ],
+            ..ScanReport::default()
+        }
+    }
+
+    /// Each sample of the exposition as (metric and labels, value); every line must be a well
+    /// formed `# HELP`, `# TYPE ... gauge` or sample of a documented metric
+    fn samples(text: &str) -> Vec<(String, String)> {
+        let names: Vec<&str> = METRICS.iter().map(|(name, _)| *name).collect();
+        let mut samples = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let (name, help) = rest.split_once(' ').unwrap();
+                assert!(names.contains(&name) && !help.is_empty(), "{}", line);
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(names.contains(&name) && kind == "gauge", "{}", line);
+            } else {
+                let (series, value) = line.rsplit_once(' ').unwrap();
+                let name = series.split('{').next().unwrap();
+                assert!(names.contains(&name), "{}", line);
+                if let Some(labels) = series.strip_prefix(name).filter(|l| !l.is_empty()) {
+                    let labels = labels.strip_prefix('{').and_then(|l| l.strip_suffix('}')).unwrap();
+                    for label in labels.split(',') {
+                        let (key, quoted) = label.split_once('=').unwrap();
+                        assert!(key.chars().all(|c| c.is_ascii_lowercase() || c == '_') && quoted.starts_with('"') && quoted.ends_with('"'), "{}", line);
+                    }
+                }
+                value.parse::<f64>().unwrap_or_else(|e| panic!("{}: {}", line, e));
+                samples.push((series.to_string(), value.to_string()));
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn the_metrics_are_valid_text_exposition() {
+        let gateway = Pushgateway::new("http://127.0.0.1:9091/", Some("nightly"), Some("acme/repo"));
+        *gateway.rate_limit_remaining.lock().unwrap() = Some(4321);
+        let rendered = gateway.render(&report(), 1);
+        let stable: Vec<(String, String)> = samples(&rendered).into_iter().filter(|(series, _)| !series.contains("duration") && !series.contains("timestamp")).collect();
+        let expected = [
+            ("dependency_scanner_findings{level=\"error\"}", "2"),
+            ("dependency_scanner_findings{level=\"warning\"}", "1"),
+            ("dependency_scanner_findings{level=\"note\"}", "0"),
+            ("dependency_scanner_vulnerabilities{severity=\"critical\"}", "1"),
+            ("dependency_scanner_vulnerabilities{severity=\"high\"}", "1"),
+            ("dependency_scanner_vulnerabilities{severity=\"medium\"}", "0"),
+            ("dependency_scanner_vulnerabilities{severity=\"low\"}", "0"),
+            ("dependency_scanner_actions_scanned", "3"),
+            ("dependency_scanner_unchecked_actions", "1"),
+            ("dependency_scanner_rate_limit_remaining", "4321"),
+            ("dependency_scanner_exit_code", "1"),
+        ];
+        assert_eq!(stable, expected.map(|(series, value)| (series.to_string(), value.to_string())));
+        // Every documented metric has its HELP and TYPE lines, in order
+        let typed: Vec<&str> = rendered.lines().filter_map(|l| l.strip_prefix("# TYPE ")?.split(' ').next()).collect();
+        assert_eq!(typed, METRICS.map(|(name, _)| name));
+    }
+
+    #[test]
+    fn a_gauge_without_a_value_has_no_sample() {
+        let gateway = Pushgateway::new("http://127.0.0.1:9091", None, Some("acme/repo"));
+        let rendered = gateway.render(&ScanReport::default(), 0);
+        assert!(!samples(&rendered).iter().any(|(series, _)| series == "dependency_scanner_rate_limit_remaining"), "{}", rendered);
+        assert!(rendered.contains("# TYPE dependency_scanner_rate_limit_remaining gauge\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn the_group_is_job_and_instance_in_base64() {
+        let gateway = Pushgateway::new("http://127.0.0.1:9091/", Some("nightly"), Some("acme/repo"));
+        assert_eq!(gateway.group_url(), "http://127.0.0.1:9091/metrics/job@base64/bmlnaHRseQ==/instance@base64/YWNtZS9yZXBv");
+        let gateway = Pushgateway {
+            instance: None,
+            ..Arc::into_inner(Pushgateway::new("http://127.0.0.1:9091", None, None)).unwrap()
+        };
+        assert_eq!(gateway.group_url(), format!("http://127.0.0.1:9091/metrics/job@base64/{}", URL_SAFE.encode(DEFAULT_JOB)));
+    }
+}
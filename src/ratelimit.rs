@@ -0,0 +1,235 @@
+use crate::http::{HttpClient, HttpRequest, HttpResponse, ResponseFuture};
+use crate::output::Verbosity;
+use reqwest::StatusCode;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+
+/// Retries of a rate-limited request when `--max-retries` is not given
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// First wait of the exponential backoff, for limits that say nothing of when they lift
+const BACKOFF_BASE: Duration = Duration::from_secs(2);
+
+/// Longest single wait; a limit that lifts later than this is given up on at once, since waiting
+/// out an hour-long primary window would stall the scan far longer than a rerun
+const MAX_WAIT: Duration = Duration::from_secs(300);
+
+/// Below this many requests left in the window, requests are spread over the rest of it
+const PACE_BELOW: u64 = 50;
+
+/// Longest pause pacing puts before one request
+const MAX_PACE: Duration = Duration::from_secs(10);
+
+/// Whether the response refuses the request for rate limiting: a 429, or a 403 that GitHub sends
+/// for an exhausted primary limit (`x-ratelimit-remaining: 0`) or a secondary limit
+/// (`retry-after`)
+pub fn is_rate_limited(response: &HttpResponse) -> bool {
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::FORBIDDEN => response.header("x-ratelimit-remaining") == Some("0") || response.header("retry-after").is_some(),
+        _ => false,
+    }
+}
+
+/// `Retry-After` in seconds, when the response gives it
+pub fn retry_after(response: &HttpResponse) -> Option<u64> {
+    response.header("retry-after").and_then(|value| value.trim().parse().ok())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Up to a quarter of `wait` more, so scans that were limited together do not retry together.
+/// The clock's nanoseconds are random enough for that.
+fn jitter(wait: Duration) -> Duration {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    wait + wait.mul_f64(f64::from(nanos % 1000) / 4000.0)
+}
+
+/// How long to wait before retry `attempt` (0-based): what `Retry-After` says, else until the
+/// primary window resets, else an exponential backoff
+fn wait_before(response: &HttpResponse, attempt: u32) -> Duration {
+    if let Some(seconds) = retry_after(response) {
+        return Duration::from_secs(seconds);
+    }
+    let reset = response.header("x-ratelimit-reset").and_then(|v| v.parse::<u64>().ok());
+    if let (Some("0"), Some(reset)) = (response.header("x-ratelimit-remaining"), reset) {
+        return Duration::from_secs(reset.saturating_sub(now()) + 1);
+    }
+    jitter(BACKOFF_BASE * 2u32.saturating_pow(attempt))
+}
+
+/// The window the last response reported: requests left, and when it resets in Unix time
+#[derive(Clone, Copy)]
+struct Window {
+    remaining: u64,
+    reset: u64,
+}
+
+/// Retries rate-limited requests with backoff, `max_retries` times at most, and paces requests
+/// when the window is nearly spent. A request still limited after its last retry returns the
+/// refusal, which callers report as rate limited and leave the action unchecked.
+pub struct RateLimitRetry {
+    pub inner: Box<dyn HttpClient>,
+    pub max_retries: u32,
+    /// Retries are progress; pauses for pacing are details
+    pub verbosity: Verbosity,
+    window: Mutex<Option<Window>>,
+}
+
+impl RateLimitRetry {
+    pub fn new(inner: Box<dyn HttpClient>, max_retries: u32, verbosity: Verbosity) -> Self {
+        RateLimitRetry { inner, max_retries, verbosity, window: Mutex::new(None) }
+    }
+
+    /// The pause that spreads the requests left over the rest of the window
+    fn pace(&self) -> Option<Duration> {
+        let window = (*self.window.lock().unwrap_or_else(|e| e.into_inner()))?;
+        if window.remaining >= PACE_BELOW {
+            return None;
+        }
+        let left = window.reset.saturating_sub(now());
+        Some(Duration::from_secs(left / (window.remaining + 1)).min(MAX_PACE)).filter(|d| !d.is_zero())
+    }
+
+    fn observe(&self, response: &HttpResponse) {
+        let remaining = response.header("x-ratelimit-remaining").and_then(|v| v.parse().ok());
+        let reset = response.header("x-ratelimit-reset").and_then(|v| v.parse().ok());
+        if let (Some(remaining), Some(reset)) = (remaining, reset) {
+            *self.window.lock().unwrap_or_else(|e| e.into_inner()) = Some(Window { remaining, reset });
+        }
+    }
+}
+
+impl HttpClient for RateLimitRetry {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let mut attempt = 0;
+            loop {
+                if let Some(pause) = self.pace() {
+                    self.verbosity.detail(format!("Rate limit nearly spent; pausing {}s before {}", pause.as_secs(), request.url));
+                    sleep(pause).await;
+                }
+                let response = self.inner.send(request.clone()).await?;
+                self.observe(&response);
+                if !is_rate_limited(&response) || attempt >= self.max_retries {
+                    return Ok(response);
+                }
+                let wait = wait_before(&response, attempt);
+                if wait > MAX_WAIT {
+                    self.verbosity.progress(format!("Rate limited; the limit lifts in {}s, too long to wait", wait.as_secs()));
+                    return Ok(response);
+                }
+                attempt += 1;
+                self.verbosity.progress(format!(
+                    "Rate limited. Retrying after {} seconds ({} of {})...",
+                    wait.as_secs(),
+                    attempt,
+                    self.max_retries
+                ));
+                sleep(wait).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Method;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn response(status: u16, headers: &[(&str, &str)]) -> HttpResponse {
+        let headers = headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        HttpResponse { status, headers, body: String::new() }
+    }
+
+    #[test]
+    fn a_429_or_a_403_with_limit_headers_is_rate_limited() {
+        for (status, headers, limited) in [
+            (429, &[][..], true),
+            (403, &[("x-ratelimit-remaining", "0")][..], true),
+            (403, &[("retry-after", "30")][..], true),
+            (403, &[("x-ratelimit-remaining", "12")][..], false),
+            (403, &[][..], false),
+            (200, &[("x-ratelimit-remaining", "0")][..], false),
+        ] {
+            assert_eq!(is_rate_limited(&response(status, headers)), limited, "{} {:?}", status, headers);
+        }
+    }
+
+    #[test]
+    fn the_wait_is_retry_after_then_the_reset_then_a_backoff() {
+        let reset = (now() + 60).to_string();
+        let both = response(403, &[("retry-after", "7"), ("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", &reset)]);
+        assert_eq!(wait_before(&both, 0), Duration::from_secs(7));
+        let until_reset = wait_before(&response(403, &[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", &reset)]), 0);
+        assert!((Duration::from_secs(60)..=Duration::from_secs(61)).contains(&until_reset), "{:?}", until_reset);
+        for (attempt, base) in [(0, 2), (1, 4), (3, 16)] {
+            let wait = wait_before(&response(429, &[]), attempt);
+            let base = Duration::from_secs(base);
+            assert!(wait >= base && wait <= base.mul_f64(1.25), "attempt {}: {:?}", attempt, wait);
+        }
+    }
+
+    /// Answers with the scripted responses in turn, the last one from then on
+    struct Script {
+        responses: Vec<HttpResponse>,
+        sent: Arc<AtomicUsize>,
+    }
+
+    impl HttpClient for Script {
+        fn send(&self, _: HttpRequest) -> ResponseFuture<'_> {
+            let sent = self.sent.fetch_add(1, Ordering::SeqCst);
+            let response = self.responses[sent.min(self.responses.len() - 1)].clone();
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    /// The status finally returned and how many requests it took
+    async fn sent(responses: Vec<HttpResponse>, max_retries: u32) -> (u16, usize) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let retry = RateLimitRetry::new(Box::new(Script { responses, sent: count.clone() }), max_retries, Verbosity::Quiet);
+        let response = retry.send(HttpRequest::new(Method::GET, "https://api.github.com/advisories")).await.unwrap();
+        (response.status, count.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn retries_stop_at_success_or_after_max_retries() {
+        let limited = response(403, &[("x-ratelimit-remaining", "0"), ("retry-after", "0")]);
+        assert_eq!(sent(vec![limited.clone(), limited.clone(), response(200, &[])], 3).await, (200, 3));
+        assert_eq!(sent(vec![limited.clone()], 2).await, (403, 3));
+        assert_eq!(sent(vec![limited.clone()], 0).await, (403, 1));
+        assert_eq!(sent(vec![response(429, &[("retry-after", "0")])], 1).await, (429, 2));
+        // Other failures are the caller's to report
+        assert_eq!(sent(vec![response(500, &[]), response(200, &[])], 3).await, (500, 1));
+    }
+
+    #[tokio::test]
+    async fn a_limit_lifting_later_than_the_longest_wait_is_given_up_at_once() {
+        let later = (now() + 3600).to_string();
+        let spent = response(403, &[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", &later)]);
+        assert_eq!(sent(vec![spent, response(200, &[])], 3).await, (403, 1));
+        assert_eq!(sent(vec![response(429, &[("retry-after", "301")]), response(200, &[])], 3).await, (429, 1));
+    }
+
+    #[test]
+    fn requests_are_paced_only_when_the_window_is_nearly_spent() {
+        let retry = RateLimitRetry::new(Box::new(Script { responses: vec![], sent: Arc::default() }), 3, Verbosity::Quiet);
+        assert_eq!(retry.pace(), None, "no window seen yet");
+        let window = |remaining: &str, reset_in: u64| response(200, &[("x-ratelimit-remaining", remaining), ("x-ratelimit-reset", &(now() + reset_in).to_string())]);
+        retry.observe(&window("50", 100));
+        assert_eq!(retry.pace(), None);
+        retry.observe(&window("9", 50));
+        let pause = retry.pace().unwrap();
+        assert!((Duration::from_secs(4)..=Duration::from_secs(5)).contains(&pause), "{:?}", pause);
+        retry.observe(&window("0", 3000));
+        assert_eq!(retry.pace(), Some(MAX_PACE));
+        // A response without both headers leaves the window as it was
+        retry.observe(&response(200, &[("x-ratelimit-remaining", "4000")]));
+        assert_eq!(retry.pace(), Some(MAX_PACE));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::io::IsTerminal;
+
+/// Which characters the text report may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Unicode,
+    /// Plain ASCII markers, for log viewers and terminals that garble emoji
+    Ascii,
+}
+
+tokio::task_local! {
+    /// The charset of what is being rendered, set by [`in_charset`] and [`with_charset`]
+    static CHARSET: Charset;
+}
+
+impl Charset {
+    /// The charset for a run's output: ASCII when asked for with `--ascii` or `--no-color`, when
+    /// `NO_COLOR` is set, when stdout is not a terminal, or when the terminal or locale can't be
+    /// trusted with UTF-8
+    pub fn detect(ascii: bool, no_color: bool) -> Self {
+        let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        if ascii || no_color || !std::io::stdout().is_terminal() || !environment_supports_utf8() {
+            Charset::Ascii
+        } else {
+            Charset::Unicode
+        }
+    }
+}
+
+/// The charset rendering happens in; Unicode outside [`in_charset`] and [`with_charset`]
+pub fn charset() -> Charset {
+    CHARSET.try_with(|charset| *charset).unwrap_or(Charset::Unicode)
+}
+
+/// Run `future` rendering in `charset`, on whichever thread it is polled
+pub async fn in_charset<F: Future>(charset: Charset, future: F) -> F::Output {
+    CHARSET.scope(charset, future).await
+}
+
+/// Render with `charset` whatever the enclosing scope chose, for a report meant for somewhere
+/// other than the terminal the run writes to
+pub fn with_charset<T>(charset: Charset, render: impl FnOnce() -> T) -> T {
+    CHARSET.sync_scope(charset, render)
+}
+
+/// A count with thousands separators, e.g. 1,234
+pub fn grouped(count: usize) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// The line closing a capped finding list
+pub fn overflow_line(hidden: usize, structured: &str) -> String {
+    let noun = if hidden == 1 { "finding" } else { "findings" };
+    format!("...and {} more {} ({})", grouped(hidden), noun, structured)
+}
+
+/// `TERM=dumb`, or a locale (`LC_ALL`, then `LC_CTYPE`, then `LANG`) that isn't UTF-8
+fn environment_supports_utf8() -> bool {
+    if std::env::var("TERM").is_ok_and(|term| term == "dumb") {
+        return false;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+/// Status markers printed at the start of report lines
+#[derive(Debug, Clone, Copy)]
+pub enum Marker {
+    Ok,
+    Info,
+    Warn,
+    Fail,
+    /// Headline of a failed scan
+    Stop,
+    Vendored,
+}
+
+impl Marker {
+    pub fn as_str(self) -> &'static str {
+        match (charset(), self) {
+            (Charset::Unicode, Marker::Ok) => "✅",
+            (Charset::Unicode, Marker::Info) => "ℹ️",
+            (Charset::Unicode, Marker::Warn) => "⚠️",
+            (Charset::Unicode, Marker::Fail) => "❌",
+            (Charset::Unicode, Marker::Stop) => "⛔",
+            (Charset::Unicode, Marker::Vendored) => "📦",
+            (Charset::Ascii, Marker::Ok) => "[OK]",
+            (Charset::Ascii, Marker::Info) => "[INFO]",
+            (Charset::Ascii, Marker::Warn) => "[WARN]",
+            (Charset::Ascii, Marker::Fail | Marker::Stop) => "[FAIL]",
+            (Charset::Ascii, Marker::Vendored) => "[VENDORED]",
+        }
+    }
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The "at or above" sign used in threshold explanations
+pub fn at_least() -> &'static str {
+    match charset() {
+        Charset::Unicode => "≥",
+        Charset::Ascii => ">=",
+    }
+}
+
+/// Between the file, job and step of a finding's site
+pub fn site_separator() -> &'static str {
+    match charset() {
+        Charset::Unicode => " › ",
+        Charset::Ascii => " > ",
+    }
+}
+
+/// Between an attributed action and what is wrong with it
+pub fn dash() -> &'static str {
+    match charset() {
+        Charset::Unicode => " — ",
+        Charset::Ascii => " - ",
+    }
+}
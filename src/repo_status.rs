@@ -0,0 +1,151 @@
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::report::{Finding, Level, Rule};
+use crate::{ActionRef, Config, is_critical, is_trusted};
+use serde_json::{Value, json};
+
+/// The parts of the repository the check reads, through the cache. `None` when the API has no
+/// such repository.
+async fn repository(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<Value>, std::io::Error> {
+    let key = action_ref.full_name();
+    match cache.get("repo-status", &key) {
+        Cached::Hit(value) => return Ok(Some(value)),
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {}
+    }
+    let Some(repo) = api.get(&format!("/repos/{}", key)).await? else {
+        cache.put_negative("repo-status", &key);
+        return Ok(None);
+    };
+    let value = json!({
+        "full_name": repo["full_name"],
+        "archived": repo["archived"],
+        "archived_at": repo["archived_at"],
+        "disabled": repo["disabled"],
+        "pushed_at": repo["pushed_at"],
+    });
+    cache.put("repo-status", &key, &value);
+    Ok(Some(value))
+}
+
+/// A repository that was renamed or transferred: who controls the code may have changed while the
+/// old name keeps working. The trust checks are repeated against the new name, since trust given
+/// to the old owner does not carry over. A change of case only is the same repository.
+fn moved(action: &str, action_ref: &ActionRef, canonical: &str, config: &Config, strict: bool) -> Vec<Finding> {
+    let name = action_ref.full_name();
+    let Some((owner, repo)) = canonical.split_once('/').filter(|_| !canonical.eq_ignore_ascii_case(&name)) else {
+        return Vec::new();
+    };
+    let moved_ref = ActionRef { owner: owner.to_string(), repo: repo.to_string(), ..action_ref.clone() };
+    let path = action_ref.path.as_ref().map(|p| format!("/{}", p)).unwrap_or_default();
+    let (level, reason) = match is_critical(action, config) && strict {
+        true => (Level::Error, " (a critical dependency, failing due to --strict)"),
+        false => (Level::Warning, ""),
+    };
+    let message = format!(
+        "Action {} comes from {}, which is now {}: whoever controls {} controls the code; update the workflow to {}{}@{}{}",
+        action, name, canonical, owner, canonical, path, action_ref.version, reason
+    );
+    let mut findings = vec![Finding::new(Rule::RepositoryMoved, level, action, message)];
+    if is_trusted(action_ref, config) && !is_trusted(&moved_ref, config) {
+        let message = format!("Action {} is trusted by its old name only; {} now belongs to non-trusted owner {}", action, canonical, owner);
+        findings.push(Finding::new(Rule::UntrustedOwner, Level::Warning, action, message));
+        if !action_ref.is_commit_sha() {
+            let message = format!("Non-trusted action {} (now {}) should use commit SHA instead of tag/branch", action, canonical);
+            findings.push(Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, action, message));
+        }
+    }
+    findings
+}
+
+/// `--check-repo-status`: flag an action whose repository is gone, which no workflow can run and
+/// whose name someone else may register, as well as one that GitHub disabled, one that moved to
+/// another name, and one that is archived and so gets no more fixes. An archived repository
+/// warns unless the action is a critical dependency.
+pub async fn check(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    action: &str,
+    action_ref: &ActionRef,
+    config: &Config,
+    strict: bool,
+) -> Result<Vec<Finding>, std::io::Error> {
+    let name = action_ref.full_name();
+    let Some(repo) = repository(api, cache, action_ref).await? else {
+        let message = format!(
+            "Action {} comes from {}, which does not exist (deleted, or private to this token); workflows cannot run it, \
+             and the name can be registered by someone else",
+            action, name
+        );
+        return Ok(vec![Finding::new(Rule::RepositoryNotFound, Level::Error, action, message)]);
+    };
+    if repo["disabled"].as_bool() == Some(true) {
+        let message = format!("Action {} comes from {}, which GitHub has disabled; workflows cannot fetch it", action, name);
+        return Ok(vec![Finding::new(Rule::RepositoryDisabled, Level::Error, action, message)]);
+    }
+    let mut findings = repo["full_name"].as_str().map(|canonical| moved(action, action_ref, canonical, config, strict)).unwrap_or_default();
+    if repo["archived"].as_bool() != Some(true) {
+        return Ok(findings);
+    }
+    let mut dates = Vec::new();
+    if let Some(at) = repo["archived_at"].as_str() {
+        dates.push(format!("archived {}", at));
+    }
+    if let Some(at) = repo["pushed_at"].as_str() {
+        dates.push(format!("last pushed {}", at));
+    }
+    let dates = if dates.is_empty() { String::new() } else { format!(" ({})", dates.join(", ")) };
+    let (level, reason) = if is_critical(action, config) { (Level::Error, ", and it is a critical dependency") } else { (Level::Warning, "") };
+    let message = format!("Action {} comes from {}, which is archived{}: it gets no more security fixes{}", action, name, dates, reason);
+    findings.push(Finding::new(Rule::RepositoryArchived, level, action, message));
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(action: &str, canonical: &str, config: &Config, strict: bool) -> Vec<(Rule, Level)> {
+        let action_ref = ActionRef::from_action_string(action).unwrap();
+        moved(action, &action_ref, canonical, config, strict).into_iter().map(|f| (f.rule, f.level)).collect()
+    }
+
+    fn trusting(owners: &[&str]) -> Config {
+        Config { trusted_owners: Some(owners.iter().map(|o| o.to_string()).collect()), ..Default::default() }
+    }
+
+    #[test]
+    fn a_rename_that_only_changes_case_is_not_a_move() {
+        assert_eq!(found("Actions/Checkout@v4", "actions/checkout", &trusting(&["actions"]), true), []);
+        assert_eq!(found("actions/checkout@v4", "actions/checkout", &Config::default(), true), []);
+    }
+
+    #[test]
+    fn a_transfer_is_a_warning_naming_the_new_repository() {
+        let action_ref = ActionRef::from_action_string("acme/tool/setup@v1").unwrap();
+        let findings = moved("acme/tool/setup@v1", &action_ref, "widgets/tool", &Config::default(), false);
+        let [finding] = findings.as_slice() else { panic!("{:?}", findings) };
+        assert_eq!((finding.rule, finding.level), (Rule::RepositoryMoved, Level::Warning));
+        assert_eq!(
+            finding.message,
+            "Action acme/tool/setup@v1 comes from acme/tool, which is now widgets/tool: whoever controls widgets controls the code; \
+             update the workflow to widgets/tool/setup@v1"
+        );
+    }
+
+    #[test]
+    fn a_moved_critical_dependency_fails_under_strict() {
+        let config = Config { critical_dependencies: Some(vec!["acme/tool@v1".to_string()]), ..Default::default() };
+        assert_eq!(found("acme/tool@v1", "widgets/tool", &config, false), [(Rule::RepositoryMoved, Level::Warning)]);
+        assert_eq!(found("acme/tool@v1", "widgets/tool", &config, true), [(Rule::RepositoryMoved, Level::Error)]);
+    }
+
+    #[test]
+    fn trust_in_the_old_owner_does_not_carry_over() {
+        let expected = [(Rule::RepositoryMoved, Level::Warning), (Rule::UntrustedOwner, Level::Warning), (Rule::UntrustedOwnerNoSha, Level::Error)];
+        assert_eq!(found("acme/tool@v1", "widgets/tool", &trusting(&["acme"]), false), expected);
+        let pinned = format!("acme/tool@{}", "a".repeat(40));
+        assert_eq!(found(&pinned, "widgets/tool", &trusting(&["acme"]), false), expected[..2]);
+        assert_eq!(found("acme/tool@v1", "widgets/tool", &trusting(&["acme", "widgets"]), false), expected[..1]);
+    }
+}
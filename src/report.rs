@@ -0,0 +1,827 @@
+use crate::blame::Blame;
+use crate::{ActionRef, DockerRef};
+use crate::render::Marker;
+use crate::severity::Severity;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How serious a finding is. Ordered so that thresholds can be compared with `>=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Level {
+    /// Marker printed in front of findings of this level in the text output
+    pub fn marker(&self) -> &'static str {
+        let marker = match self {
+            Level::Note => Marker::Info,
+            Level::Warning => Marker::Warn,
+            Level::Error => Marker::Fail,
+        };
+        marker.as_str()
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Note => "note",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Broad grouping of rules, used by the final report and the exit decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Vulnerability,
+    Policy,
+    /// Actions the config bans, which always block a scan
+    Blocked,
+    /// Context for reviewers that never blocks a scan
+    Informational,
+}
+
+/// Every check the scanner can report on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    UnpinnedDependency,
+    UnstableReference,
+    TagPinnedCritical,
+    UntrustedOwner,
+    UntrustedOwnerNoSha,
+    KnownVulnerability,
+    MalformedReference,
+    OidcUntrustedAction,
+    WorkflowShape,
+    TooManyUntrustedOwners,
+    VendoredOutdated,
+    InvalidVendoredMarker,
+    SecretEnvExposure,
+    CompromisedVersion,
+    DynamicReference,
+    SelfHostedPullRequest,
+    AdvisoryHistory,
+    UnpinnedDispatch,
+    InsecureDownload,
+    PinDowngrade,
+    PinTagMismatch,
+    UnreleasedCommit,
+    UnusedConfigEntry,
+    GithubScript,
+    ParseLimit,
+    PinComment,
+    CheckoutOverlay,
+    InvalidWorkflow,
+    InputDrift,
+    NewDependency,
+    DockerTagPinned,
+    MissingLocalAction,
+    ImmutableRelease,
+    MissingCommit,
+    UnreachableCommit,
+    Outdated,
+    UnguardedCommentTrigger,
+    CachePoisoning,
+    BlockedDependency,
+    AbbreviatedSha,
+    DockerArgsInjection,
+    RepositoryArchived,
+    RepositoryNotFound,
+    RepositoryDisabled,
+    RepositoryMoved,
+    DeprecatedRuntime,
+    PinPolicy,
+    DependabotCoverage,
+    PinCommentMismatch,
+    DuplicateKey,
+    DependencyDepth,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 51] = [
+        Rule::UnpinnedDependency,
+        Rule::UnstableReference,
+        Rule::TagPinnedCritical,
+        Rule::UntrustedOwner,
+        Rule::UntrustedOwnerNoSha,
+        Rule::KnownVulnerability,
+        Rule::MalformedReference,
+        Rule::OidcUntrustedAction,
+        Rule::WorkflowShape,
+        Rule::TooManyUntrustedOwners,
+        Rule::VendoredOutdated,
+        Rule::InvalidVendoredMarker,
+        Rule::SecretEnvExposure,
+        Rule::CompromisedVersion,
+        Rule::DynamicReference,
+        Rule::SelfHostedPullRequest,
+        Rule::AdvisoryHistory,
+        Rule::UnpinnedDispatch,
+        Rule::InsecureDownload,
+        Rule::PinDowngrade,
+        Rule::PinTagMismatch,
+        Rule::UnreleasedCommit,
+        Rule::UnusedConfigEntry,
+        Rule::GithubScript,
+        Rule::ParseLimit,
+        Rule::PinComment,
+        Rule::CheckoutOverlay,
+        Rule::InvalidWorkflow,
+        Rule::InputDrift,
+        Rule::NewDependency,
+        Rule::DockerTagPinned,
+        Rule::MissingLocalAction,
+        Rule::ImmutableRelease,
+        Rule::MissingCommit,
+        Rule::UnreachableCommit,
+        Rule::Outdated,
+        Rule::UnguardedCommentTrigger,
+        Rule::CachePoisoning,
+        Rule::BlockedDependency,
+        Rule::AbbreviatedSha,
+        Rule::DockerArgsInjection,
+        Rule::RepositoryArchived,
+        Rule::RepositoryNotFound,
+        Rule::RepositoryDisabled,
+        Rule::RepositoryMoved,
+        Rule::DeprecatedRuntime,
+        Rule::PinPolicy,
+        Rule::DependabotCoverage,
+        Rule::PinCommentMismatch,
+        Rule::DuplicateKey,
+        Rule::DependencyDepth,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::UnpinnedDependency => "unpinned-dependency",
+            Rule::UnstableReference => "unstable-reference",
+            Rule::TagPinnedCritical => "tag-pinned-critical",
+            Rule::UntrustedOwner => "untrusted-owner",
+            Rule::UntrustedOwnerNoSha => "untrusted-owner-no-sha",
+            Rule::KnownVulnerability => "known-vulnerability",
+            Rule::MalformedReference => "malformed-reference",
+            Rule::OidcUntrustedAction => "oidc-untrusted-action",
+            Rule::WorkflowShape => "workflow-shape",
+            Rule::TooManyUntrustedOwners => "too-many-untrusted-owners",
+            Rule::VendoredOutdated => "vendored-outdated",
+            Rule::InvalidVendoredMarker => "invalid-vendored-marker",
+            Rule::SecretEnvExposure => "secret-env-exposure",
+            Rule::CompromisedVersion => "compromised-version",
+            Rule::DynamicReference => "dynamic-reference",
+            Rule::SelfHostedPullRequest => "self-hosted-pull-request",
+            Rule::AdvisoryHistory => "advisory-history",
+            Rule::UnpinnedDispatch => "unpinned-dispatch",
+            Rule::InsecureDownload => "insecure-download",
+            Rule::PinDowngrade => "pin-downgrade",
+            Rule::PinTagMismatch => "pin-tag-mismatch",
+            Rule::UnreleasedCommit => "unreleased-commit",
+            Rule::UnusedConfigEntry => "unused-config-entry",
+            Rule::GithubScript => "github-script",
+            Rule::ParseLimit => "parse-limit",
+            Rule::PinComment => "pin-comment",
+            Rule::CheckoutOverlay => "checkout-overlay",
+            Rule::InvalidWorkflow => "invalid-workflow",
+            Rule::InputDrift => "input-drift",
+            Rule::NewDependency => "new-dependency",
+            Rule::DockerTagPinned => "docker-tag-pinned",
+            Rule::MissingLocalAction => "missing-local-action",
+            Rule::ImmutableRelease => "immutable-release",
+            Rule::MissingCommit => "missing-commit",
+            Rule::UnreachableCommit => "unreachable-commit",
+            Rule::Outdated => "outdated",
+            Rule::UnguardedCommentTrigger => "unguarded-comment-trigger",
+            Rule::CachePoisoning => "cache-poisoning",
+            Rule::BlockedDependency => "blocked-dependency",
+            Rule::AbbreviatedSha => "abbreviated-sha",
+            Rule::DockerArgsInjection => "docker-args-injection",
+            Rule::RepositoryArchived => "repository-archived",
+            Rule::RepositoryNotFound => "repository-not-found",
+            Rule::RepositoryDisabled => "repository-disabled",
+            Rule::RepositoryMoved => "repository-moved",
+            Rule::DeprecatedRuntime => "deprecated-runtime",
+            Rule::PinPolicy => "pin-policy",
+            Rule::DependabotCoverage => "dependabot-coverage",
+            Rule::PinCommentMismatch => "pin-comment-mismatch",
+            Rule::DuplicateKey => "duplicate-key",
+            Rule::DependencyDepth => "dependency-depth",
+        }
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Rule::KnownVulnerability | Rule::CompromisedVersion => Category::Vulnerability,
+            Rule::BlockedDependency => Category::Blocked,
+            Rule::AdvisoryHistory | Rule::UnusedConfigEntry | Rule::InputDrift | Rule::ImmutableRelease => Category::Informational,
+            _ => Category::Policy,
+        }
+    }
+}
+
+impl JsonSchema for Rule {
+    fn schema_name() -> String {
+        "Rule".to_string()
+    }
+
+    /// Rules serialize as their id strings
+    fn json_schema(_: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            enum_values: Some(Rule::ALL.iter().map(|rule| rule.id().into()).collect()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl Serialize for Rule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+/// A single result produced by one of the checks
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Finding {
+    pub rule: Rule,
+    pub level: Level,
+    pub action: String,
+    pub message: String,
+    /// Job the finding applies to, for checks that look at a whole job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+    /// Workflow file the finding was raised in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// 1-based line in `file` the finding points at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Step the finding applies to, for findings about one `uses:` of a step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+    /// Matrix entry (`key=value`) that produced the reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<String>,
+    /// Advisory severity, for vulnerability findings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Date (YYYY-MM-DD) by which an advisory inside its grace period must be fixed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_by: Option<String>,
+    /// CODEOWNERS owners responsible for the finding (`--codeowners`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// Commit that last changed the finding's line (`--blame`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blame: Option<Blame>,
+    /// The advisory behind a vulnerability finding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advisory: Option<AdvisoryRef>,
+    /// The pinned and latest versions, for outdated findings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateRef>,
+    /// The ignore-file entry covering the finding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppression: Option<Suppression>,
+    /// The version the finding's SHA pin resolved to (`--describe-pins`), e.g. `v4.2.2+3`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin_version: Option<String>,
+    /// For `--transitive`: the actions, from the workflow's own on, through which it uses this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chain: Vec<String>,
+    /// Identity of the finding that survives steps moving within a file; see `Finding::fingerprint`
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub fingerprint: String,
+}
+
+/// Why a finding is suppressed, from the ignore file, the baseline or `--diff`
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Suppression {
+    pub reason: String,
+    /// Last day (YYYY-MM-DD) the suppression applies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// The expiry has passed, so the finding counts again
+    pub expired: bool,
+    /// Accepted by `--baseline` rather than by the ignore file
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub baseline: bool,
+    /// About an entry unchanged since the `--diff` or `--diff-base` base
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unchanged: bool,
+}
+
+impl Finding {
+    /// Suppressed by an ignore-file entry that has not expired, or by the baseline
+    pub fn is_suppressed(&self) -> bool {
+        self.suppression.as_ref().is_some_and(|s| !s.expired)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct UpdateRef {
+    /// The version pinned, or the tag a pinned commit is at (the SHA when it is on none)
+    pub current: String,
+    pub latest: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AdvisoryRef {
+    pub id: String,
+    pub title: String,
+    /// The databases that list the advisory: github, osv
+    pub sources: Vec<String>,
+    /// When the advisory was published (RFC 3339), when the database says
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+}
+
+/// What `--advisories-since` left out of the report
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AdvisoriesSince {
+    /// Advisories published before this time (RFC 3339) are left out
+    pub since: String,
+    /// Older advisories left out
+    pub filtered: usize,
+    /// Older advisories kept because they still fail the scan, without `--fail-only-recent`
+    pub kept: usize,
+}
+
+impl Finding {
+    pub fn new(rule: Rule, level: Level, action: &str, message: String) -> Self {
+        Finding {
+            rule,
+            level,
+            action: action.to_string(),
+            message,
+            job: None,
+            file: None,
+            line: None,
+            step: None,
+            matrix: None,
+            severity: None,
+            due_by: None,
+            owners: Vec::new(),
+            blame: None,
+            advisory: None,
+            update: None,
+            suppression: None,
+            pin_version: None,
+            chain: Vec::new(),
+            fingerprint: String::new(),
+        }
+    }
+
+    /// Attribute the finding to an action reached through `chain`, which the message then starts with
+    pub fn via(mut self, chain: &[String]) -> Self {
+        if chain.is_empty() {
+            return self;
+        }
+        self.message = format!("{} -> {}: {}", chain.join(" -> "), self.action, self.message);
+        self.chain = chain.to_vec();
+        self
+    }
+
+    /// Scan-level findings describe the scan as a whole and have no action or location
+    pub fn scan_level(rule: Rule, level: Level, message: String) -> Self {
+        Finding::new(rule, level, "", message)
+    }
+
+    pub fn is_scan_level(&self) -> bool {
+        self.action.is_empty() && self.job.is_none() && self.file.is_none()
+    }
+
+    /// `file:line` (or just `file`) for findings that have a location
+    pub fn location(&self) -> Option<String> {
+        let file = self.file.as_ref()?;
+        Some(match self.line {
+            Some(line) => format!("{}:{}", file, line),
+            None => file.clone(),
+        })
+    }
+
+    pub fn at(mut self, file: &str, line: Option<usize>) -> Self {
+        self.file = Some(file.to_string());
+        self.line = line;
+        self
+    }
+
+    pub fn with_job(mut self, job: &str) -> Self {
+        self.job = Some(job.to_string());
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Option<Severity>) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_step(mut self, step: &str) -> Self {
+        self.step = Some(step.to_string());
+        self
+    }
+
+    pub fn with_matrix(mut self, step: &str, entry: &str) -> Self {
+        self.step = Some(step.to_string());
+        self.matrix = Some(entry.to_string());
+        self
+    }
+
+    /// A hash of what the finding is about rather than where: the rule, the canonical action, the
+    /// file, job, step and matrix entry, and the advisory. The line only counts for findings with
+    /// no action, job or step to name them, so reordering steps keeps every fingerprint while
+    /// renaming a step may change one.
+    pub fn fingerprint(&self) -> String {
+        let line = match (&self.job, &self.step) {
+            (None, None) if self.action.is_empty() => self.line.map(|l| l.to_string()).unwrap_or_default(),
+            _ => String::new(),
+        };
+        let key = [
+            self.rule.id().to_string(),
+            canonical_action(&self.action),
+            self.file.clone().unwrap_or_default(),
+            self.job.clone().unwrap_or_default(),
+            self.step.clone().unwrap_or_default(),
+            self.matrix.clone().unwrap_or_default(),
+            self.advisory.as_ref().map(|a| a.id.clone()).unwrap_or_default(),
+            line,
+        ]
+        .join("\0");
+        format!("{:016x}", fnv1a(&key))
+    }
+}
+
+/// The action as GitHub resolves it: owner and repository are case-insensitive, and a Docker Hub
+/// image is the same with or without `docker.io/library/`
+pub fn canonical_action(action: &str) -> String {
+    if let Some(parsed) = ActionRef::from_action_string(action) {
+        let path = parsed.path.as_ref().map(|p| format!("/{}", p)).unwrap_or_default();
+        format!("{}{}@{}", parsed.full_name().to_ascii_lowercase(), path, parsed.version)
+    } else if let Some(image) = DockerRef::parse(action) {
+        let namespace = image.namespace.map(|n| format!("{}/", n)).unwrap_or_default();
+        let tag = image.tag.map(|t| format!(":{}", t)).unwrap_or_default();
+        let digest = image.digest.map(|d| format!("@{}", d)).unwrap_or_default();
+        format!("docker://{}/{}{}{}{}", image.registry, namespace, image.name, tag, digest)
+    } else {
+        action.to_string()
+    }
+}
+
+/// 64-bit FNV-1a: short, stable across builds and platforms, and enough to tell findings apart
+pub fn fnv1a(key: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Everything a scan found, accumulated before any exit decision is made. The JSON Schema for it
+/// is printed by `schema report`.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct ScanReport {
+    /// RFC 3339 time the report was produced, taken from the injected `Clock`
+    pub generated_at: String,
+    pub actions_scanned: usize,
+    /// Actions excluded by `--only`/`--skip`
+    pub filtered_out: usize,
+    pub findings: Vec<Finding>,
+    /// Actions whose network checks could not be completed
+    pub unchecked: Vec<String>,
+    /// Lookups that failed, such as an advisory database that could not be reached; any of them
+    /// fails the scan as an operational error
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Repository visibility the visibility-sensitive rules assumed, and why
+    pub visibility: String,
+    /// Actions each workflow file uses, for attributing per-action findings to files
+    #[serde(skip)]
+    pub workflow_actions: std::collections::BTreeMap<String, std::collections::BTreeSet<String>>,
+    /// Config file(s) that governed each workflow file
+    pub configs: std::collections::BTreeMap<String, String>,
+    /// Local actions recognised as vendored copies of an upstream action
+    pub vendored: Vec<crate::vendor::Vendored>,
+    /// Workflow files read line by line because their templates are not valid YAML
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lenient: Vec<String>,
+    /// Who can trigger each workflow file, from its `on:` block
+    pub trigger_surfaces: std::collections::BTreeMap<String, crate::surface::Surface>,
+    /// Set by `--advisories-since`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advisories_since: Option<AdvisoriesSince>,
+    /// Network-backed checks `--offline` skipped, which the scan says nothing about
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_checks: Vec<String>,
+    /// Each workflow file's `uses:` occurrences, with their jobs and steps, for attributing
+    /// per-action findings to the steps that use the action
+    #[serde(skip)]
+    pub occurrences: std::collections::BTreeMap<String, Vec<crate::Occurrence>>,
+    /// Set by `--diff` and `--diff-base`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<DiffScope>,
+    /// Set by `--transitive`: every action composite actions use, with the chain leading to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transitive: Option<Vec<crate::transitive::Nested>>,
+    /// Counts, coverage and timing of the run, set once the scan is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<crate::summary::Summary>,
+    /// Set by `--truncate-structured`: how many findings beyond `--max-findings` were left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated_findings: Option<usize>,
+    /// `--rule-id-compat`: the tool whose rule ids outputs show
+    #[serde(skip)]
+    pub rule_ids: Option<crate::compat::Tool>,
+    /// The workflow read from stdin, by the name it is reported under, with its source
+    #[serde(skip)]
+    pub stdin: Option<(String, String)>,
+    /// Set by `--describe-pins`: the versions the SHA pins resolved to, for the text outputs
+    #[serde(skip)]
+    pub pins: crate::describe::Pins,
+}
+
+/// What `--diff` or `--diff-base` compared the scan against, and what that left out
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct DiffScope {
+    /// The `--diff` path or `--diff-base` revision
+    pub base: String,
+    /// Actions every file that uses them already used in the base, which were not looked up
+    pub unchanged_actions: Vec<String>,
+    /// Scanned workflow files the base does not have, all of whose actions are new
+    pub added_workflows: Vec<String>,
+    /// Workflow files the base has and the scan does not
+    pub deleted_workflows: Vec<String>,
+}
+
+impl ScanReport {
+    /// The id outputs show for a rule, which `--rule-id-compat` may replace with another tool's
+    pub fn rule_id(&self, rule: Rule) -> &'static str {
+        crate::compat::rule_id(self.rule_ids, rule)
+    }
+
+    /// A scanned workflow's source: what stdin held for the stdin workflow, else the file. Steps
+    /// that go back to a workflow after the scan, to find lines or annotate it, read it here.
+    pub fn workflow_source(&self, file: &str) -> Result<String, std::io::Error> {
+        match &self.stdin {
+            Some((name, source)) if name == file => Ok(source.clone()),
+            _ => std::fs::read_to_string(file),
+        }
+    }
+
+    /// Put findings, unchecked actions and errors into their canonical order so every output is
+    /// reproducible.
+    ///
+    /// Findings are ordered by file, line, rule id, action, then message. Findings without a
+    /// location (scan-level and per-action checks) sort first.
+    pub fn sort(&mut self) {
+        self.findings.sort_by(|a, b| {
+            (&a.file, a.line, a.rule.id(), &a.action, &a.message)
+                .cmp(&(&b.file, b.line, b.rule.id(), &b.action, &b.message))
+        });
+        self.unchecked.sort();
+        self.unchecked.dedup();
+        self.errors.sort();
+        self.errors.dedup();
+    }
+
+    /// Record each finding's fingerprint, once nothing more about it will change
+    pub fn fingerprint(&mut self) {
+        for finding in &mut self.findings {
+            finding.fingerprint = finding.fingerprint();
+        }
+    }
+
+    /// The findings to render when at most `max` may be shown: highest level first, then by rule
+    /// id, in canonical order within a rule. Returns them with the number left out.
+    pub fn prioritized(&self, max: Option<usize>) -> (Vec<&Finding>, usize) {
+        let mut findings: Vec<&Finding> = self.findings.iter().collect();
+        let Some(max) = max else {
+            return (findings, 0);
+        };
+        findings.sort_by(|a, b| b.level.cmp(&a.level).then_with(|| a.rule.id().cmp(b.rule.id())));
+        let hidden = findings.len().saturating_sub(max);
+        findings.truncate(max);
+        (findings, hidden)
+    }
+
+    /// `--truncate-structured`: the report with only the findings [`ScanReport::prioritized`]
+    /// keeps, in that order, counting the rest in `truncated_findings`. The summary still counts
+    /// everything.
+    pub fn truncated(&self, max: usize) -> ScanReport {
+        let (findings, hidden) = self.prioritized(Some(max));
+        let findings = findings.into_iter().cloned().collect();
+        ScanReport { findings, truncated_findings: Some(hidden), ..self.clone() }
+    }
+
+    /// The steps a finding applies to: its own, when it was raised at one, else every step of the
+    /// scanned workflows that uses its action. A step of a matrix job is one site, however many
+    /// combinations run it.
+    pub fn sites(&self, finding: &Finding) -> Vec<Site> {
+        if let (Some(file), Some(job)) = (&finding.file, &finding.job) {
+            return vec![Site { file: file.clone(), job: job.clone(), step: finding.step.clone() }];
+        }
+        if finding.file.is_some() || finding.action.is_empty() {
+            return Vec::new();
+        }
+        let mut sites: Vec<Site> = self
+            .occurrences
+            .iter()
+            .flat_map(|(file, occurrences)| {
+                occurrences
+                    .iter()
+                    .filter(|o| o.action == finding.action)
+                    .map(|o| Site { file: file.clone(), job: o.job.clone(), step: o.step.clone() })
+            })
+            .collect();
+        sites.sort();
+        sites.dedup();
+        sites
+    }
+
+    /// Each failing finding of the category at each of its sites, as (site, action, rule), once
+    /// each; a finding no step can be found for comes once without a site
+    pub fn failing_sites(&self, category: Category) -> Vec<(Option<Site>, &str, Rule)> {
+        let mut failing: Vec<(Option<Site>, &str, Rule)> = Vec::new();
+        for finding in &self.findings {
+            if finding.level != Level::Error || finding.is_suppressed() || finding.action.is_empty() || finding.rule.category() != category {
+                continue;
+            }
+            let sites = self.sites(finding);
+            let sites = if sites.is_empty() { vec![None] } else { sites.into_iter().map(Some).collect() };
+            failing.extend(sites.into_iter().map(|site| (site, finding.action.as_str(), finding.rule)));
+        }
+        failing.sort_by(|a, b| (&a.0, a.1, a.2.id()).cmp(&(&b.0, b.1, b.2.id())));
+        failing.dedup();
+        failing
+    }
+
+    /// Unique action strings with at least one finding of the given category at `Level::Error`
+    pub fn failing_actions(&self, category: Category) -> Vec<&str> {
+        let mut actions: Vec<&str> = Vec::new();
+        for finding in &self.findings {
+            if finding.level == Level::Error
+                && !finding.is_suppressed()
+                && !finding.action.is_empty()
+                && finding.rule.category() == category
+                && !actions.contains(&finding.action.as_str())
+            {
+                actions.push(&finding.action);
+            }
+        }
+        actions.sort_unstable();
+        actions
+    }
+}
+
+/// A step of a workflow file that a finding applies to; `step` is `None` for a job that calls a
+/// reusable workflow
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Site {
+    pub file: String,
+    pub job: String,
+    pub step: Option<String>,
+}
+
+impl fmt::Display for Site {
+    /// `ci.yml › deploy › "Post release notes"`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = crate::render::site_separator();
+        write!(f, "{}{}{}", self.file, separator, self.job)?;
+        match &self.step {
+            Some(step) => write!(f, "{}{}", separator, step),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How the scan results are emitted on stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// Human-readable text followed by TeamCity service messages
+    Teamcity,
+    /// Human-readable text followed by GitHub Actions workflow commands, which annotate the lines
+    /// findings point to
+    Github,
+    /// The JSON report on stdout, with the human-readable text on stderr
+    Json,
+    /// SARIF 2.1.0 on stdout for code scanning, with the human-readable text on stderr
+    Sarif,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "teamcity" => Ok(OutputFormat::Teamcity),
+            "github" => Ok(OutputFormat::Github),
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            _ => Err(format!("unknown format {} (expected text, teamcity, github, json or sarif)", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn located(step: &str, line: usize) -> Finding {
+        Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, "acme/deploy@v1", "unpinned".to_string())
+            .with_job("build")
+            .with_step(step)
+            .at(".github/workflows/ci.yml", Some(line))
+    }
+
+    #[test]
+    fn the_fingerprint_ignores_the_line_of_a_named_finding() {
+        assert_eq!(located("deploy", 7).fingerprint(), located("deploy", 42).fingerprint());
+        assert_ne!(located("deploy", 7).fingerprint(), located("release", 7).fingerprint(), "renaming the step may change it");
+        assert_eq!(located("deploy", 7).fingerprint().len(), 16);
+    }
+
+    #[test]
+    fn the_fingerprint_names_what_the_finding_is_about() {
+        let base = located("deploy", 7);
+        let respelled = Finding { action: "ACME/Deploy@v1".to_string(), ..base.clone() };
+        assert_eq!(respelled.fingerprint(), base.fingerprint(), "the action is compared as GitHub resolves it");
+        for other in [
+            Finding { rule: Rule::UntrustedOwner, ..base.clone() },
+            Finding { action: "acme/deploy@v2".to_string(), ..base.clone() },
+            Finding { file: Some(".github/workflows/release.yml".to_string()), ..base.clone() },
+            Finding { job: Some("test".to_string()), ..base.clone() },
+            base.clone().with_matrix("deploy", "os=linux"),
+        ] {
+            assert_ne!(other.fingerprint(), base.fingerprint(), "{:?}", other);
+        }
+    }
+
+    #[test]
+    fn a_finding_with_nothing_else_to_name_it_keeps_its_line() {
+        let at = |line| Finding::new(Rule::InvalidWorkflow, Level::Error, "", "bad".to_string()).at("ci.yml", Some(line));
+        assert_ne!(at(3).fingerprint(), at(4).fingerprint());
+        let job_level = |line| at(line).with_job("build");
+        assert_eq!(job_level(3).fingerprint(), job_level(4).fingerprint());
+    }
+
+    #[test]
+    fn the_report_sets_every_fingerprint() {
+        let mut report = ScanReport { findings: vec![located("deploy", 7), located("release", 9)], ..Default::default() };
+        report.fingerprint();
+        let fingerprints: Vec<&str> = report.findings.iter().map(|f| f.fingerprint.as_str()).collect();
+        assert_eq!(fingerprints, [located("deploy", 1).fingerprint(), located("release", 1).fingerprint()]);
+    }
+
+    /// 3,000 findings cycling through levels and rules, in the order a scan might find them
+    fn large_report() -> ScanReport {
+        let levels = [Level::Note, Level::Warning, Level::Error];
+        let rules = [Rule::UntrustedOwnerNoSha, Rule::KnownVulnerability, Rule::UntrustedOwner, Rule::PinCommentMismatch];
+        let findings = (0..3000)
+            .map(|i| Finding::new(rules[i % rules.len()], levels[i % levels.len()], &format!("acme/tool{}@v1", i), format!("finding {}", i)))
+            .collect();
+        ScanReport { findings, ..Default::default() }
+    }
+
+    #[test]
+    fn prioritized_keeps_the_highest_levels_then_orders_by_rule() {
+        let report = large_report();
+        let (kept, hidden) = report.prioritized(Some(1500));
+        assert_eq!((kept.len(), hidden), (1500, 1500));
+        assert!(kept[..1000].iter().all(|f| f.level == Level::Error), "all 1,000 errors make the cut");
+        assert!(kept[1000..].iter().all(|f| f.level == Level::Warning), "then warnings, and no note");
+        for level in kept.chunk_by(|a, b| a.level == b.level) {
+            let ids: Vec<&str> = level.iter().map(|f| f.rule.id()).collect();
+            assert!(ids.is_sorted(), "by rule id within a level");
+        }
+        let first = kept.iter().find(|f| f.rule == Rule::KnownVulnerability).unwrap();
+        assert_eq!(first.message, "finding 5", "a rule keeps the order the scan found it in");
+
+        let (all, hidden) = report.prioritized(None);
+        assert_eq!((all.len(), hidden), (3000, 0));
+        assert_eq!(all[1].message, "finding 1", "no limit, no reordering");
+    }
+
+    #[test]
+    fn a_truncated_report_counts_what_it_left_out() {
+        let report = large_report();
+        let truncated = report.truncated(10);
+        assert_eq!(truncated.findings.len(), 10);
+        assert_eq!(truncated.truncated_findings, Some(2990));
+        assert!(truncated.findings.iter().all(|f| f.level == Level::Error));
+        let value = serde_json::to_value(&truncated).unwrap();
+        assert_eq!(value["truncated_findings"], 2990);
+        assert!(serde_json::to_value(&report).unwrap().get("truncated_findings").is_none());
+    }
+}
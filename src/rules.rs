@@ -0,0 +1,541 @@
+use crate::dispatch::source_line;
+use crate::filter::ActionFilter;
+use crate::multidoc;
+use crate::report::{Finding, Level, Rule};
+use crate::config::Config;
+use crate::visibility::{self, Visibility};
+use crate::{ActionRef, Workflow, is_trusted};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What every workflow-level rule gets to look at
+struct Context<'a> {
+    workflow: &'a Workflow,
+    config: &'a Config,
+    filter: &'a ActionFilter,
+    visibility: Visibility,
+}
+
+impl Context<'_> {
+    /// Attach the workflow file and the line of the given key path to a finding
+    fn locate(&self, finding: Finding, path: &[&str]) -> Finding {
+        finding.at(&self.workflow.path, key_line(&self.workflow.source, path))
+    }
+
+    /// Steps of a job that run actions from non-trusted owners or without a SHA pin
+    fn risky_steps<'v>(&self, job: &'v Value) -> Vec<(String, &'v str)> {
+        let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else {
+            return Vec::new();
+        };
+        let mut risky = Vec::new();
+        for (index, step) in steps.iter().enumerate() {
+            let Some(action) = step.get("uses").and_then(|v| v.as_str()).filter(|a| self.filter.allows(a)) else { continue };
+            let Some(action_ref) = ActionRef::from_action_string(action) else { continue };
+            if !is_trusted(&action_ref, self.config) || !action_ref.is_commit_sha() {
+                risky.push((step_label(step, index), action));
+            }
+        }
+        risky
+    }
+}
+
+/// Run the checks that need the whole workflow rather than a single `uses:` reference
+pub fn check_workflow(workflow: &Workflow, config: &Config, filter: &ActionFilter, visibility: Visibility) -> Vec<Finding> {
+    let ctx = Context { workflow, config, filter, visibility };
+    let mut findings = Vec::new();
+    let Some(jobs) = workflow.value.get("jobs").and_then(|v| v.as_mapping()) else {
+        return findings;
+    };
+
+    let mut any_risky = false;
+    for (job_id, job) in jobs {
+        let job_id = job_id.as_str().unwrap_or("<unnamed>");
+        if let Some(finding) = check_job_shape(job_id, job) {
+            findings.push(ctx.locate(finding, &["jobs", job_id]));
+            continue;
+        }
+        any_risky |= !ctx.risky_steps(job).is_empty();
+        findings.extend(check_oidc_exposure(&ctx, job_id, job));
+        findings.extend(check_secret_env(&ctx, Some((job_id, job))));
+        findings.extend(check_self_hosted_pull_request(&ctx, job_id, job));
+        findings.extend(check_checkout_overlay(&ctx, job_id, job));
+        findings.extend(check_comment_trigger(&ctx, jobs, job_id, job));
+    }
+    if any_risky {
+        findings.extend(check_secret_env(&ctx, None));
+    }
+    findings
+}
+
+/// Find the 1-based line of a nested mapping key such as `["jobs", "build", "env"]`.
+///
+/// This is an indentation-based scan rather than a YAML parse, which is enough to point
+/// findings at the right line in ordinary block-style workflows. In a file of several documents
+/// each is searched in turn, and a job key `multidoc::combine` gave a ` (document N)` suffix is
+/// looked up in its own document.
+pub fn key_line(source: &str, path: &[&str]) -> Option<usize> {
+    let mut path = path.to_vec();
+    let mut document = None;
+    if let ["jobs", job, ..] = path[..] {
+        let (id, from) = multidoc::parse_job_key(job);
+        path[1] = id;
+        document = from;
+    }
+    let lines: Vec<&str> = source.lines().collect();
+    multidoc::ranges(source)
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| document.is_none_or(|document| document == index + 1))
+        .find_map(|(_, range)| {
+            let start = range.start;
+            key_line_in(lines[range].iter().copied().enumerate().map(|(index, line)| (start + index, line)), &path)
+        })
+}
+
+fn key_line_in<'a>(lines: impl Iterator<Item = (usize, &'a str)>, path: &[&str]) -> Option<usize> {
+    let mut depth = 0;
+    let mut parent_indent: Option<usize> = None;
+    for (index, line) in lines {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if parent_indent.is_some_and(|parent| indent <= parent) {
+            // Left the block of the last matched key without finding the next one
+            return None;
+        }
+        let Some((key, _)) = trimmed.split_once(':') else { continue };
+        if key.trim().trim_matches(|c| c == '"' || c == '\'') == path[depth] {
+            if depth + 1 == path.len() {
+                return Some(index + 1);
+            }
+            depth += 1;
+            parent_indent = Some(indent);
+        }
+    }
+    None
+}
+
+/// The value of a `uses:` line, unquoted and without a trailing comment
+pub fn uses_value(line: &str) -> Option<&str> {
+    let line = line.trim_start().trim_start_matches("- ").trim_start();
+    let value = line.strip_prefix("uses:")?;
+    Some(value.split(" #").next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\''))
+}
+
+/// The first line at or after `from` (1-based) that uses the action
+pub fn uses_line(source: &str, action: &str, from: usize) -> Option<usize> {
+    source
+        .lines()
+        .enumerate()
+        .skip(from.saturating_sub(1))
+        .find(|(_, line)| uses_value(line) == Some(action))
+        .map(|(index, _)| index + 1)
+}
+
+/// Enforce `max_untrusted_owners` over every action in the scan
+pub fn check_owner_budget(actions: &BTreeSet<String>, config: &Config) -> Option<Finding> {
+    let limit = config.max_untrusted_owners?;
+    let mut owners: BTreeMap<String, usize> = BTreeMap::new();
+    for action in actions {
+        if let Some(action_ref) = ActionRef::from_action_string(action)
+            && !is_trusted(&action_ref, config)
+        {
+            *owners.entry(action_ref.owner).or_default() += 1;
+        }
+    }
+    if owners.len() <= limit {
+        return None;
+    }
+
+    // Owners contributing the most actions first, so teams know where consolidating pays off
+    let mut owners: Vec<(String, usize)> = owners.into_iter().collect();
+    owners.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let listed = owners
+        .iter()
+        .map(|(owner, count)| format!("{} ({} action{})", owner, count, if *count == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = format!(
+        "{} distinct non-trusted owners exceed max_untrusted_owners of {}: {}",
+        owners.len(),
+        limit,
+        listed
+    );
+    Some(Finding::scan_level(Rule::TooManyUntrustedOwners, Level::Error, message))
+}
+
+/// Report jobs the extractor cannot see into: neither a `steps` list nor a reusable workflow `uses`
+fn check_job_shape(job_id: &str, job: &Value) -> Option<Finding> {
+    let problem = match job {
+        Value::Mapping(_) => {
+            let has_uses = job.get("uses").is_some_and(|v| v.is_string());
+            let steps = job.get("steps");
+            if has_uses || steps.is_some_and(|v| v.is_sequence()) {
+                return None;
+            }
+            match steps {
+                Some(Value::Null) => "has a null steps list",
+                Some(_) => "has a steps value that is not a list",
+                None => "has neither steps nor uses",
+            }
+        }
+        Value::Null => "is empty",
+        _ => "is not a mapping",
+    };
+    let message = format!("Job {} {}; no actions could be extracted from it", job_id, problem);
+    Some(Finding::new(Rule::WorkflowShape, Level::Warning, "", message).with_job(job_id))
+}
+
+/// Human-readable label for a step: its `name`, or its position when unnamed
+pub fn step_label(step: &Value, index: usize) -> String {
+    match step.get("name").and_then(|v| v.as_str()) {
+        Some(name) => format!("\"{}\"", name),
+        None => format!("step {}", index + 1),
+    }
+}
+
+/// Whether a `permissions` block grants the given scope write access
+fn grants_write(permissions: &Value, scope: &str) -> bool {
+    match permissions {
+        Value::String(s) => s == "write-all",
+        Value::Mapping(_) => permissions.get(scope).and_then(|v| v.as_str()) == Some("write"),
+        _ => false,
+    }
+}
+
+/// Flag jobs that can mint OIDC tokens while running third-party code that could exfiltrate them
+fn check_oidc_exposure(ctx: &Context, job_id: &str, job: &Value) -> Option<Finding> {
+    // Job-level permissions replace the workflow-level block entirely
+    let (permissions, scope) = match job.get("permissions") {
+        Some(permissions) => (permissions, "job"),
+        None => (ctx.workflow.value.get("permissions")?, "workflow"),
+    };
+    if !grants_write(permissions, "id-token") {
+        return None;
+    }
+
+    let offending = ctx.risky_steps(job);
+    if offending.is_empty() {
+        return None;
+    }
+
+    let steps = offending
+        .iter()
+        .map(|(label, action)| format!("{} ({})", label, action))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let actions = offending.iter().map(|(_, action)| *action).collect::<Vec<_>>().join(", ");
+    let message = format!(
+        "Job {} grants id-token: write ({}-level) and runs untrusted or unpinned actions: {}. Move the OIDC token exchange into its own job.",
+        job_id, scope, steps
+    );
+    let finding = Finding::new(Rule::OidcUntrustedAction, Level::Error, &actions, message).with_job(job_id);
+    Some(ctx.locate(finding, &["jobs", job_id]))
+}
+
+/// Whether an env value interpolates a secret
+fn is_secret_value(value: &Value) -> bool {
+    value.as_str().is_some_and(|v| v.contains("secrets.") || v.contains("secrets["))
+}
+
+/// Flag secrets put into workflow- or job-level `env`, where every step (including third-party
+/// actions) can read them. `job` is `None` for the workflow-level block, which is only checked
+/// when some job runs risky actions; step-level env is deliberately never flagged.
+fn check_secret_env(ctx: &Context, job: Option<(&str, &Value)>) -> Vec<Finding> {
+    let (scope, env, path) = match job {
+        Some((job_id, job)) => {
+            if ctx.risky_steps(job).is_empty() {
+                return Vec::new();
+            }
+            (format!("job {}", job_id), job.get("env"), vec!["jobs", job_id, "env"])
+        }
+        None => ("the workflow".to_string(), ctx.workflow.value.get("env"), vec!["env"]),
+    };
+    let Some(env) = env.and_then(|v| v.as_mapping()) else {
+        return Vec::new();
+    };
+
+    let mut findings = Vec::new();
+    for (name, value) in env {
+        let Some(name) = name.as_str() else { continue };
+        if !is_secret_value(value) {
+            continue;
+        }
+        let message = format!(
+            "Secret-valued env {} is declared for {}, exposing it to untrusted or unpinned actions; scope it to the step that needs it",
+            name, scope
+        );
+        let mut finding = Finding::new(Rule::SecretEnvExposure, Level::Warning, "", message);
+        if let Some((job_id, _)) = job {
+            finding = finding.with_job(job_id);
+        }
+        let mut key_path = path.clone();
+        key_path.push(name);
+        findings.push(ctx.locate(finding, &key_path));
+    }
+    findings
+}
+
+/// The workflow's trigger events that are among `wanted`
+fn triggers<'v>(workflow: &'v Value, wanted: &[&str]) -> Vec<&'v str> {
+    let events: Vec<&str> = match workflow.get("on") {
+        Some(Value::String(event)) => vec![event.as_str()],
+        Some(Value::Sequence(events)) => events.iter().filter_map(|e| e.as_str()).collect(),
+        Some(Value::Mapping(events)) => events.keys().filter_map(|e| e.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    events.into_iter().filter(|e| wanted.contains(e)).collect()
+}
+
+/// Pull request events, which let outside contributors run code in public repositories
+fn pull_request_triggers(workflow: &Value) -> Vec<&str> {
+    triggers(workflow, &["pull_request", "pull_request_target"])
+}
+
+fn runs_on_self_hosted(job: &Value) -> bool {
+    let is_self_hosted = |label: &Value| label.as_str() == Some("self-hosted");
+    match job.get("runs-on") {
+        Some(label @ Value::String(_)) => is_self_hosted(label),
+        Some(Value::Sequence(labels)) => labels.iter().any(is_self_hosted),
+        Some(runner @ Value::Mapping(_)) => runner
+            .get("labels")
+            .is_some_and(|labels| is_self_hosted(labels) || labels.as_sequence().is_some_and(|l| l.iter().any(is_self_hosted))),
+        _ => false,
+    }
+}
+
+/// Flag self-hosted runners in workflows that pull requests can trigger: in a public repository
+/// anyone can open one and run code on the runner. Downgraded for private repositories.
+fn check_self_hosted_pull_request(ctx: &Context, job_id: &str, job: &Value) -> Option<Finding> {
+    if !runs_on_self_hosted(job) {
+        return None;
+    }
+    let events = pull_request_triggers(&ctx.workflow.value);
+    if events.is_empty() {
+        return None;
+    }
+    let message = format!(
+        "Job {} runs on a self-hosted runner and the workflow is triggered by {}; pull requests can run arbitrary code on the runner",
+        job_id,
+        events.join(" and ")
+    );
+    let finding = Finding::new(Rule::SelfHostedPullRequest, Level::Error, "", message).with_job(job_id);
+    let finding = visibility::adjust(finding, ctx.visibility, ctx.config.private_repo_levels.as_ref());
+    Some(ctx.locate(finding, &["jobs", job_id, "runs-on"]))
+}
+
+/// Expressions in an `if:` that restrict who the run acts for
+const COMMENTER_GUARDS: [&str; 5] = [
+    "author_association",
+    "github.event.comment.user.login",
+    "github.event.sender.login",
+    "github.actor",
+    "github.triggering_actor",
+];
+
+/// Actions that fail their job unless the commenter has write access or belongs to a team
+const PERMISSION_CHECK_ACTIONS: [&str; 8] = [
+    "actions-cool/check-user-permission",
+    "prince-chrismc/check-actor-permissions-action",
+    "sushichop/action-repository-permission",
+    "lannonbr/repo-permission-check-action",
+    "peter-evans/slash-command-dispatch",
+    "xt0rted/slash-command-action",
+    "tspascoal/get-user-teams-membership",
+    "theModdingInquisition/actions-team-membership",
+];
+
+/// Permission API calls that a github-script step can make to guard the job
+const PERMISSION_CHECK_CALLS: [&str; 3] = ["getCollaboratorPermissionLevel", "checkMembershipForUser", "checkCollaborator"];
+
+/// Names of the secrets a value mentions anywhere in it, and `inherit` for `secrets: inherit`
+fn secrets_used(value: &Value, found: &mut BTreeSet<String>) {
+    match value {
+        Value::String(s) => {
+            let mut rest = s.as_str();
+            while let Some(index) = rest.find("secrets.") {
+                rest = &rest[index + "secrets.".len()..];
+                let name: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+                if !name.is_empty() {
+                    found.insert(name);
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter().for_each(|item| secrets_used(item, found)),
+        Value::Mapping(mapping) => {
+            for (key, item) in mapping {
+                if key.as_str() == Some("secrets") && item.as_str() == Some("inherit") {
+                    found.insert("inherit".to_string());
+                }
+                secrets_used(item, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether a job checks who it runs for: in its `if:`, with a permission-check step, or by
+/// needing a job that does
+fn is_commenter_guarded(jobs: &serde_yaml::Mapping, job: &Value, depth: usize) -> bool {
+    let condition = job.get("if").and_then(|v| v.as_str()).unwrap_or("");
+    if COMMENTER_GUARDS.iter().any(|guard| condition.contains(guard)) {
+        return true;
+    }
+    let steps = job.get("steps").and_then(|v| v.as_sequence()).into_iter().flatten();
+    let checks_permission = steps.clone().any(|step| {
+        let uses = step.get("uses").and_then(|v| v.as_str()).unwrap_or("");
+        let name = uses.split_once('@').map_or(uses, |(name, _)| name);
+        PERMISSION_CHECK_ACTIONS.iter().any(|a| a.eq_ignore_ascii_case(name))
+            || step
+                .get("with")
+                .and_then(|w| w.get("script"))
+                .and_then(|s| s.as_str())
+                .is_some_and(|script| PERMISSION_CHECK_CALLS.iter().any(|call| script.contains(call)))
+    });
+    if checks_permission || depth > jobs.len() {
+        return checks_permission;
+    }
+    let needs: Vec<&str> = match job.get("needs") {
+        Some(Value::String(need)) => vec![need.as_str()],
+        Some(Value::Sequence(needs)) => needs.iter().filter_map(|n| n.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    needs
+        .into_iter()
+        .filter_map(|need| jobs.get(need))
+        .any(|needed| is_commenter_guarded(jobs, needed, depth + 1))
+}
+
+/// Flag jobs that a comment can trigger and that use secrets or critical actions without checking
+/// who commented: on a public repository anyone can comment, so an unguarded `/deploy` deploys for
+/// anyone. Downgraded for private repositories.
+fn check_comment_trigger(ctx: &Context, jobs: &serde_yaml::Mapping, job_id: &str, job: &Value) -> Option<Finding> {
+    let events = triggers(&ctx.workflow.value, &["issue_comment", "discussion_comment"]);
+    if events.is_empty() || is_commenter_guarded(jobs, job, 0) {
+        return None;
+    }
+
+    let mut secrets = BTreeSet::new();
+    secrets_used(job, &mut secrets);
+    if let Some(env) = ctx.workflow.value.get("env") {
+        secrets_used(env, &mut secrets);
+    }
+    let critical: Vec<&str> = job
+        .get("steps")
+        .and_then(|v| v.as_sequence())
+        .into_iter()
+        .flatten()
+        .filter_map(|step| step.get("uses").and_then(|v| v.as_str()))
+        .chain(job.get("uses").and_then(|v| v.as_str()))
+        .filter(|action| ctx.config.critical_dependencies.iter().flatten().any(|d| d == action))
+        .collect();
+    if secrets.is_empty() && critical.is_empty() {
+        return None;
+    }
+
+    let mut uses = Vec::new();
+    if !secrets.is_empty() {
+        let names: Vec<String> = secrets.iter().map(|s| if s == "inherit" { "secrets: inherit".to_string() } else { format!("secrets.{}", s) }).collect();
+        uses.push(names.join(", "));
+    }
+    if !critical.is_empty() {
+        uses.push(format!("critical actions {}", critical.join(", ")));
+    }
+    let message = format!(
+        "Job {} runs on {} and uses {}, but nothing checks who commented; add a guard such as \
+         contains(fromJSON('[\"OWNER\", \"MEMBER\", \"COLLABORATOR\"]'), github.event.comment.author_association) to its if:",
+        job_id,
+        events.join(" and "),
+        uses.join(" and ")
+    );
+    let finding = Finding::new(Rule::UnguardedCommentTrigger, Level::Error, &critical.join(", "), message).with_job(job_id);
+    let finding = visibility::adjust(finding, ctx.visibility, ctx.config.private_repo_levels.as_ref());
+    let path: &[&str] = if job.get("if").is_some() { &["jobs", job_id, "if"] } else { &["jobs", job_id] };
+    Some(ctx.locate(finding, path))
+}
+
+/// One `actions/checkout` step of a job
+struct Checkout<'v> {
+    label: String,
+    action: &'v str,
+    line: Option<usize>,
+    /// `None` for the repository the workflow runs in
+    repository: Option<&'v str>,
+    reference: &'v str,
+    /// Normalized `path:`; empty for the workspace root
+    path: String,
+}
+
+/// Refs that name code from a pull request or another fork rather than the base repository
+fn is_untrusted_ref(reference: &str) -> bool {
+    ["pull_request.head", "github.head_ref", "workflow_run.head_", "refs/pull/"]
+        .iter()
+        .any(|marker| reference.contains(marker))
+}
+
+fn checkouts<'v>(ctx: &Context, job_id: &str, job: &'v Value) -> Vec<Checkout<'v>> {
+    let Some(steps) = job.get("steps").and_then(|v| v.as_sequence()) else {
+        return Vec::new();
+    };
+    let mut line = key_line(&ctx.workflow.source, &["jobs", job_id]);
+    let mut found = Vec::new();
+    for (index, step) in steps.iter().enumerate() {
+        let Some(action) = step.get("uses").and_then(|v| v.as_str()) else { continue };
+        let name = action.split('@').next().unwrap_or(action);
+        if !name.eq_ignore_ascii_case("actions/checkout") {
+            continue;
+        }
+        // Identical `uses:` lines are told apart by searching on from the previous checkout
+        let from = line.map(|l| l + usize::from(!found.is_empty()));
+        let step_line = source_line(&ctx.workflow.source, action, from);
+        line = step_line.or(line);
+        let with = |key: &str| step.get("with").and_then(|w| w.get(key)).and_then(|v| v.as_str());
+        let repository = with("repository").filter(|r| !r.replace(' ', "").contains("github.repository}}"));
+        let path = with("path").unwrap_or("").trim_start_matches("./").trim_end_matches('/');
+        found.push(Checkout {
+            label: step_label(step, index),
+            action,
+            line: step_line,
+            repository,
+            reference: with("ref").unwrap_or(""),
+            path: if path == "." { String::new() } else { path.to_string() },
+        });
+    }
+    found
+}
+
+/// Flag a checkout that lands on top of an earlier one in the same directory with code from a
+/// pull request or another repository: steps after it run whatever the overlay brought in, while
+/// the job looks like it works on the trusted checkout. Checkouts into distinct `path:` values
+/// are the normal multi-repository setup and are left alone.
+fn check_checkout_overlay(ctx: &Context, job_id: &str, job: &Value) -> Vec<Finding> {
+    let checkouts = checkouts(ctx, job_id, job);
+    let mut findings = Vec::new();
+    for (index, later) in checkouts.iter().enumerate() {
+        let Some(earlier) = checkouts[..index].iter().rev().find(|c| c.path == later.path) else { continue };
+        let (level, problem) = if is_untrusted_ref(later.reference) && !is_untrusted_ref(earlier.reference) {
+            (Level::Error, format!("untrusted ref {}", later.reference))
+        } else if later.repository != earlier.repository {
+            (Level::Warning, format!("repository {}", later.repository.unwrap_or("of the workflow")))
+        } else {
+            continue;
+        };
+        let at_line = |c: &Checkout| c.line.map(|l| format!(" (line {})", l)).unwrap_or_default();
+        let directory = if later.path.is_empty() { "the workspace".to_string() } else { later.path.clone() };
+        let message = format!(
+            "Job {}, {}{} checks out {} into {} on top of {}{}; later steps run the overlaid files, so give it its own `path`",
+            job_id,
+            later.label,
+            at_line(later),
+            problem,
+            directory,
+            earlier.label,
+            at_line(earlier)
+        );
+        let finding = Finding::new(Rule::CheckoutOverlay, level, later.action, message).with_job(job_id);
+        findings.push(finding.at(&ctx.workflow.path, later.line));
+    }
+    findings
+}
@@ -0,0 +1,50 @@
+use crate::ActionRef;
+use crate::action_metadata;
+use crate::cache::DiskCache;
+use crate::github::GitHubApi;
+use crate::report::{Finding, Level, Rule};
+
+/// Node.js runtimes GitHub has retired from its runners
+const DEPRECATED: [&str; 2] = ["node12", "node16"];
+
+/// `runs.using` values the runners support
+const SUPPORTED: [&str; 4] = ["node20", "node24", "composite", "docker"];
+
+/// What `--check-runtime` found for one action
+pub enum Runtime {
+    /// A Node.js runtime the runners support; nothing to say
+    Supported,
+    /// `composite` or `docker`, which set no Node.js version of their own
+    Other(String),
+    Flagged(Box<Finding>),
+    /// No `action.yml` at the ref
+    NoMetadata,
+}
+
+/// `--check-runtime`: the `runs.using` of the action's metadata at its ref. `node12` and `node16`,
+/// and any value the runners do not know, warn, or fail with `strict`.
+pub async fn check(api: &GitHubApi<'_>, cache: &DiskCache, action: &str, action_ref: &ActionRef, strict: bool) -> Result<Runtime, std::io::Error> {
+    let Some(metadata) = action_metadata::fetch(api, cache, action_ref).await? else {
+        return Ok(Runtime::NoMetadata);
+    };
+    let using = action_metadata::runs_using(&metadata).unwrap_or("");
+    if using == "composite" || using == "docker" {
+        return Ok(Runtime::Other(using.to_string()));
+    }
+    if SUPPORTED.contains(&using) {
+        return Ok(Runtime::Supported);
+    }
+    let problem = if DEPRECATED.contains(&using) {
+        format!("runs on {}, a Node.js runtime GitHub has deprecated; runners warn about it or refuse it", using)
+    } else if using.is_empty() {
+        "sets no runs.using, so runners cannot tell how to run it".to_string()
+    } else {
+        format!("runs on {}, which is not a runtime GitHub runners support ({})", using, SUPPORTED.join(", "))
+    };
+    let (level, reason) = match strict {
+        true => (Level::Error, " (failing due to --strict)"),
+        false => (Level::Warning, ""),
+    };
+    let message = format!("Action {} {}; update it to a release on a supported runtime{}", action, problem, reason);
+    Ok(Runtime::Flagged(Box::new(Finding::new(Rule::DeprecatedRuntime, level, action, message))))
+}
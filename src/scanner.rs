@@ -0,0 +1,1340 @@
+use crate::baseline::{self, Baseline};
+use crate::cache::{Cached, DiskCache};
+use crate::clock::Clock;
+use crate::compat;
+use crate::config::{self, Config, ConfigSet, EffectiveConfig, IgnoreEntry};
+use crate::decision::{self, Decision, ExitPolicy};
+use crate::diff;
+use crate::filter::ActionFilter;
+use crate::github::{Endpoints, GitHubApi};
+use crate::http::{GitHubAuth, HttpClient, ReqwestClient};
+use crate::report::{Finding, Level, Rule, ScanReport};
+use crate::render::Marker;
+use crate::visibility::{self, Visibility, VisibilityOption};
+use crate::{ActionKind, ActionRef, AdvisoryMemo, AdvisoryPolicy, AdvisorySources, GitHubAdvisory, Occurrence, STDIN_PATH, ScanError, Workflow};
+use crate::{action_policy, advisory_finding, check_default_branch_pin, check_local_action, dynamic_reference_level};
+use crate::{extract_occurrences, filter_advisories_since, parse_workflow, query_github_advisories, repo_root};
+use crate::{blocklist, describe, dispatch, docker_args, dependabot, downloads, github_script, hygiene, immutable, indicators};
+use crate::{limits, matrix, osv, outdated, pincomment, poisoning, repo_status, rules, runtime, shas, summary, surface};
+use crate::{transitive, unreleased, vendor, version};
+use crate::version::NewerFormats;
+use crate::summary::CountRequests;
+use futures_util::{StreamExt, stream};
+use reqwest::Client;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub type AdvisoryFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<GitHubAdvisory>, ScanError>> + Send + 'a>>;
+
+/// Where a [`Scanner`] gets the advisories affecting an action, in place of the cache and the
+/// databases `--source` selects
+pub trait AdvisorySource: Send + Sync {
+    /// Every advisory of the action's repository, or with `open_only` those still open that may
+    /// affect its ref
+    fn advisories<'a>(&'a self, action_ref: &'a ActionRef, open_only: bool) -> AdvisoryFuture<'a>;
+}
+
+/// What a scan reports as it goes, for a caller that shows it; each event does nothing unless
+/// implemented. Findings reach the report whatever is done with them here.
+pub trait ScanEvents: Send + Sync {
+    /// The scan is about to check `actions` unique actions from `workflows` workflow files
+    fn scanning(&self, _actions: usize, _workflows: usize) {}
+    /// A step of the scan, such as the workflow being checked or a lookup that found nothing
+    fn progress(&self, _message: &str) {}
+    /// A problem that does not stop the scan, such as a lookup that failed
+    fn warning(&self, _message: &str) {}
+    /// The versions `--describe-pins` resolved, before any finding those pins have is reported
+    fn pins(&self, _pins: &describe::Pins) {}
+    /// A finding, as it is found
+    fn finding(&self, _finding: &Finding) {}
+    /// A pinning or trust finding of one action, before it is placed in its workflow
+    fn policy_finding(&self, finding: &Finding) {
+        self.finding(finding)
+    }
+    /// Advisories were found for the action, from the cache when `cached`
+    fn vulnerable(&self, _action: &str, _cached: bool) {}
+    /// One advisory of a vulnerable action, with its finding
+    fn advisory(&self, _advisory: &GitHubAdvisory, _finding: &Finding) {}
+    /// The scan moved on to a phase: load, policy, network or decide
+    fn phase(&self, _phase: &str) {}
+    /// The report as far as the scan got: before the network checks, and once it is complete
+    fn report(&self, _report: &ScanReport) {}
+}
+
+/// Events nobody listens to
+pub struct Silent;
+
+impl ScanEvents for Silent {}
+
+/// What the CLI takes as flags rather than from the config
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// `--strict`: tags and branches are errors, not warnings
+    pub strict: bool,
+    pub sources: AdvisorySources,
+    pub endpoints: Endpoints,
+    /// Sent to the API by the default client, and used to look up the repository's visibility
+    pub token: Option<String>,
+    /// The visibility the visibility-sensitive rules and trigger surfaces assume
+    pub visibility: VisibilityOption,
+    /// `--offline`: only the local checks run, and the network-backed ones are listed as skipped
+    pub offline: bool,
+    /// Also scan workflows in subdirectories of the directories scanned
+    pub recursive: bool,
+    pub lenient_templates: bool,
+    /// `--only` and `--skip`
+    pub filter: ActionFilter,
+    /// Check actions against the built-in list of known-compromised versions
+    pub builtin_indicators: bool,
+    /// `--diff` or `--diff-base`
+    pub diff: Option<diff::Base>,
+    /// The ignore file's entries
+    pub ignore: Vec<IgnoreEntry>,
+    /// `--baseline`: its path and the findings it accepts
+    pub baseline: Option<(String, Baseline)>,
+    /// `--write-baseline`
+    pub write_baseline: Option<String>,
+    /// `--update-baseline`
+    pub update_baseline: bool,
+    pub require_pinning_comment_style: bool,
+    pub verify_pin_comments: bool,
+    pub describe_pins: bool,
+    pub verify_shas: bool,
+    /// Level of outdated-pin findings, when `--check-outdated` looks for them
+    pub outdated: Option<Level>,
+    /// `--check-repo-status`
+    pub repo_status: bool,
+    /// `--check-runtime`
+    pub runtime: bool,
+    /// `--advisory-history`, in months
+    pub advisory_history: Option<u32>,
+    /// `--transitive`, with the levels of composite actions it follows
+    pub transitive: Option<usize>,
+    /// `--max-depth-fail`: dependency chains deeper than this fail the scan
+    pub max_depth_fail: Option<usize>,
+    /// Actions whose network checks run at the same time
+    pub concurrency: usize,
+    pub scan_run_steps: bool,
+    pub scan_github_script: bool,
+    pub scan_cache_poisoning: bool,
+    pub check_dependabot: bool,
+    /// Dependabot config to read instead of .github/dependabot.yml (implies `check_dependabot`)
+    pub dependabot_config: Option<PathBuf>,
+    /// `--advisories-since`
+    pub advisories_since: Option<chrono::DateTime<chrono::Utc>>,
+    pub fail_only_recent: bool,
+    /// The thresholds of the exit decision; the config's `fail_on_unchecked` adds to them
+    pub exit: ExitPolicy,
+    /// `--rule-id-compat`
+    pub rule_id_compat: Option<compat::Tool>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            strict: false,
+            sources: AdvisorySources::default(),
+            endpoints: Endpoints::default(),
+            token: None,
+            visibility: VisibilityOption::Public,
+            offline: false,
+            recursive: false,
+            lenient_templates: false,
+            filter: ActionFilter::default(),
+            builtin_indicators: true,
+            diff: None,
+            ignore: Vec::new(),
+            baseline: None,
+            write_baseline: None,
+            update_baseline: false,
+            require_pinning_comment_style: false,
+            verify_pin_comments: false,
+            describe_pins: false,
+            verify_shas: false,
+            outdated: None,
+            repo_status: false,
+            runtime: false,
+            advisory_history: None,
+            transitive: None,
+            max_depth_fail: None,
+            concurrency: 5,
+            scan_run_steps: false,
+            scan_github_script: false,
+            scan_cache_poisoning: false,
+            check_dependabot: false,
+            dependabot_config: None,
+            advisories_since: None,
+            fail_only_recent: false,
+            exit: ExitPolicy::default(),
+            rule_id_compat: None,
+        }
+    }
+}
+
+/// The result of [`Scanner::scan`]: the report and its exit decision, with what the CLI prints
+/// after the scan
+pub struct ScanOutcome {
+    pub report: ScanReport,
+    pub decision: Decision,
+    /// Findings whose level `rule_overrides` changed, each with the level it had before
+    pub overridden: Vec<(Finding, Level)>,
+    /// Config, ignore file and baseline entries that matched nothing, which are in the report too
+    pub unused: Vec<Finding>,
+    /// Advisories published before `--advisories-since` kept because they fail the scan
+    pub kept_older: Vec<Finding>,
+    /// The actions each scanned workflow uses, whatever the filter checked
+    pub inventory: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Every check of a scan, without the CLI around it: the caller gets the report and decides what
+/// to print, through [`ScanEvents`], and what to do with the exit decision
+pub struct Scanner {
+    configs: ConfigSet,
+    options: ScanOptions,
+    clock: Clock,
+    /// Counts the requests of each scan for its summary
+    client: CountRequests,
+    cache: DiskCache,
+    /// The GitHub advisories of each repository, looked up once per scanner
+    memo: AdvisoryMemo,
+    source: Option<Box<dyn AdvisorySource>>,
+    events: Box<dyn ScanEvents>,
+    /// The workflow read from stdin, with the name it is reported under
+    stdin: Option<(String, String)>,
+    /// Files of this run written by a newer scanner, shared with the clients that read fixtures
+    formats: Arc<NewerFormats>,
+}
+
+impl Scanner {
+    /// A scanner sending its lookups over HTTP to `options.endpoints`, without a cache, that
+    /// reports nothing as it goes
+    pub fn new(configs: impl Into<ConfigSet>, options: ScanOptions) -> Self {
+        let client = GitHubAuth::new(Box::new(ReqwestClient(Client::new())), options.token.clone(), &options.endpoints.api);
+        Scanner {
+            configs: configs.into(),
+            options,
+            clock: Clock::System,
+            client: CountRequests::new(Box::new(client)),
+            cache: DiskCache::disabled(),
+            memo: AdvisoryMemo::default(),
+            source: None,
+            events: Box::new(Silent),
+            stdin: None,
+            formats: Arc::default(),
+        }
+    }
+
+    /// Send every lookup through `client` instead
+    pub fn with_client(mut self, client: Box<dyn HttpClient>) -> Self {
+        self.client = CountRequests::new(client);
+        self
+    }
+
+    /// Cache lookups in `cache`
+    pub fn with_cache(mut self, cache: DiskCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Date reports and advisory grace periods by `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Tell `events` what the scan finds as it goes
+    pub fn with_events(mut self, events: Box<dyn ScanEvents>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Take advisories from `source` instead of the cache and the advisory databases
+    pub fn with_advisory_source(mut self, source: Box<dyn AdvisorySource>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Scan `source`, read from stdin, as the workflow named `name`: the path `-` stands for it,
+    /// and so does `name` itself
+    pub fn with_stdin_workflow(mut self, name: &str, source: String) -> Self {
+        self.stdin = Some((name.to_string(), source));
+        self
+    }
+
+    /// Report the files of a newer scanner `formats` has checked, such as the fixtures of the
+    /// client, with the scan's own warnings
+    pub fn with_newer_formats(mut self, formats: Arc<NewerFormats>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    pub fn configs(&self) -> &ConfigSet {
+        &self.configs
+    }
+
+    /// The root config
+    pub fn config(&self) -> &Config {
+        self.configs.root()
+    }
+
+    pub fn options(&self) -> &ScanOptions {
+        &self.options
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn client(&self) -> &dyn HttpClient {
+        &self.client
+    }
+
+    pub fn cache(&self) -> &DiskCache {
+        &self.cache
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.options.endpoints
+    }
+
+    pub fn events(&self) -> &dyn ScanEvents {
+        self.events.as_ref()
+    }
+
+    /// The REST API through the scanner's client, which adds the token
+    pub fn api(&self) -> GitHubApi<'_> {
+        GitHubApi::new(self.client(), self.endpoints(), None)
+    }
+
+    /// The visibility the scan assumes, with a note explaining it for the summary
+    pub async fn visibility(&self) -> (Visibility, String) {
+        visibility::resolve(self.options.visibility, self.client(), self.endpoints(), self.options.token.as_deref()).await
+    }
+
+    /// The advisories of the action's repository, or with `open_only` those still open that may
+    /// affect its ref, and whether they came from the cache
+    pub async fn advisories(&self, action_ref: &ActionRef, open_only: bool) -> Result<(Vec<GitHubAdvisory>, bool), ScanError> {
+        match &self.source {
+            Some(source) => Ok((source.advisories(action_ref, open_only).await?, false)),
+            None => {
+                let http = HttpAdvisories {
+                    client: self.client(),
+                    cache: &self.cache,
+                    sources: self.options.sources,
+                    endpoints: self.endpoints(),
+                    memo: &self.memo,
+                    events: self.events(),
+                };
+                http.lookup(action_ref, open_only).await
+            }
+        }
+    }
+
+    /// The offline pinning and trust checks of one action under `config`, and for a local action
+    /// that `vendored_actions` does not map that its directory holds one, each finding told to
+    /// the events
+    pub fn check_action_policy(&self, action: &str, config: &Config) -> Vec<Finding> {
+        let mut findings = action_policy(action, config, self.options.strict);
+        let mapped = config.vendored_actions.as_ref().is_some_and(|mapping| mapping.contains_key(action));
+        if let Some(ActionKind::Local(path)) = ActionKind::parse(action)
+            && !mapped
+            && let Some(finding) = check_local_action(action, &path, &repo_root())
+        {
+            findings.push(finding);
+        }
+        for finding in &findings {
+            self.events.policy_finding(finding);
+        }
+        findings
+    }
+
+    /// A lookup that failed: warned about, and recorded with the action left unchecked. The error
+    /// fails the scan as an operational error unless vulnerabilities fail it first.
+    fn lookup_failed(&self, report: &mut ScanReport, action: &str, error: String) {
+        self.events.warning(&error);
+        report.unchecked.push(action.to_string());
+        report.errors.push(error);
+    }
+
+    /// Look up known vulnerabilities for an action, recording findings against the `uses:` value.
+    /// An action whose lookup fails is recorded as unchecked, with the error.
+    pub async fn check_advisories(&self, policy: &AdvisoryPolicy, action: &str, action_ref: &ActionRef, report: &mut ScanReport) {
+        match self.advisories(action_ref, true).await {
+            Ok((advisories, cached)) if !advisories.is_empty() => {
+                self.events.vulnerable(action, cached);
+                for advisory in &advisories {
+                    let finding = advisory_finding(advisory, policy, action);
+                    if !policy.predates_since(advisory) {
+                        self.events.advisory(advisory, &finding);
+                    }
+                    report.findings.push(finding);
+                }
+            }
+            Ok(_) => self.events.progress(&format!("{} No known vulnerabilities for {}", Marker::Ok, report.pins.display(action))),
+            Err(e) => {
+                let error = format!("Failed to check {}: {}", report.pins.display(action), e);
+                self.lookup_failed(report, action, error)
+            }
+        }
+    }
+
+    /// Every check of one workflow file, as [`Scanner::scan`] runs them
+    pub async fn scan_workflow(&self, path: &Path) -> Result<ScanReport, ScanError> {
+        Ok(self.scan(&[path.to_path_buf()]).await?.report)
+    }
+
+    /// Tell the events the warnings held back so far: config files that were missing or linted
+    /// with warnings, files written by a newer scanner, and cache entries that could not be
+    /// written. The scan reports them as it goes; a subcommand using the scanner calls this itself.
+    pub fn report_warnings(&self) {
+        if let Some((path, baseline)) = &self.options.baseline {
+            self.formats.check(path, baseline.scanner_version.as_deref());
+        }
+        let warnings = [self.configs.take_warnings(), self.formats.take_warnings(), self.cache.take_warnings()];
+        for warning in warnings.iter().flatten() {
+            self.events.warning(warning);
+        }
+    }
+
+    /// The indicator, policy and network checks of each action, outside any workflow
+    pub async fn scan_actions(&self, actions: &[ActionRef]) -> Result<ScanReport, ScanError> {
+        let indicators = self.indicators()?;
+        let (_, visibility_note) = self.visibility().await;
+        let mut report = ScanReport { generated_at: self.clock.rfc3339(), visibility: visibility_note, ..Default::default() };
+        let actions: BTreeSet<String> = actions.iter().map(uses_value).collect();
+        for action in &actions {
+            report.findings.extend(indicators::check(action, &indicators));
+            report.findings.extend(self.check_action_policy(action, self.config()));
+        }
+        if self.options.offline {
+            report.skipped_checks = self.offline_skipped();
+        } else {
+            let policy = self.advisory_policy();
+            for action in &actions {
+                let checked = self.check_online(action, &policy, &report.findings, &report.pins).await;
+                report.findings.extend(checked.findings);
+                report.unchecked.extend(checked.unchecked);
+                report.errors.extend(checked.errors);
+            }
+        }
+        self.report_warnings();
+        report.actions_scanned = actions.len();
+        report.rule_ids = self.options.rule_id_compat;
+        report.sort();
+        report.fingerprint();
+        Ok(report)
+    }
+
+    /// Scan workflow files and directories of workflows: every `uses:` against the policy of
+    /// the config governing its file, the workflow rules, and the network checks of each action,
+    /// then overrides, suppressions and the exit decision
+    pub async fn scan(&self, paths: &[PathBuf]) -> Result<ScanOutcome, ScanError> {
+        let started = Instant::now();
+        // The scanner's counts run on across scans; the summary has this scan's share
+        let (requests_before, hits_before) = (self.client.requests(), self.cache.hits());
+        let config = self.config();
+        let options = &self.options;
+        let filter = &options.filter;
+        self.events.phase("load");
+        self.report_warnings();
+        let indicators = self.indicators()?;
+
+        let mut workflows = Vec::new();
+        let mut actions = BTreeSet::new();
+        let mut report_lenient = Vec::new();
+        let mut files = Vec::new();
+        for scan_path in paths {
+            // The stdin workflow is scanned under its name; a file of that name, also given, is the same workflow
+            if scan_path.as_os_str() == STDIN_PATH {
+                let (name, _) = self.stdin.as_ref().ok_or_else(|| ScanError::Options("- scans the workflow on stdin, but none was read".to_string()))?;
+                files.push(PathBuf::from(name));
+                continue;
+            }
+            files.extend(workflow_files(scan_path, options.recursive, self.events())?);
+        }
+        files.sort();
+        files.dedup();
+        let invalid_root = |e: String| ScanError::config(self.configs.root_path(), e);
+        let parse_limits = limits::ParseLimits::from_config(config).map_err(invalid_root)?;
+        let comment_style = pincomment::CommentStyle::from_config(config).map_err(invalid_root)?;
+        for workflow_file in files {
+            let workflow = self.load(&workflow_file, &parse_limits).unwrap_or_else(|e| Workflow::invalid(&workflow_file.to_string_lossy(), e));
+            let effective = self.configs.for_workflow(&workflow_file)?;
+            actions.extend(workflow.actions());
+            if workflow.lenient.is_some() {
+                report_lenient.push(workflow.path.clone());
+            }
+            workflows.push((workflow, effective));
+        }
+        let total_actions = actions.len();
+        actions.retain(|action| filter.allows(action));
+        let diff = match &options.diff {
+            Some(base) => {
+                let parsed: Vec<&Workflow> = workflows.iter().map(|(workflow, _)| workflow).collect();
+                let diff = diff::Diff::load(base, paths, &parsed)?;
+                let unchanged: BTreeSet<String> = diff.unchanged_actions(&parsed).into_iter().filter(|a| actions.contains(a)).collect();
+                actions.retain(|action| !unchanged.contains(action));
+                Some((diff.scope(&unchanged), diff))
+            }
+            None => None,
+        };
+
+        let advisory_policy = self.advisory_policy();
+        let (visibility, visibility_note) = self.visibility().await;
+
+        let mut report = ScanReport {
+            generated_at: self.clock.rfc3339(),
+            visibility: visibility_note,
+            actions_scanned: actions.len(),
+            filtered_out: total_actions - actions.len() - diff.as_ref().map_or(0, |(scope, _)| scope.unchanged_actions.len()),
+            lenient: report_lenient,
+            diff: diff.as_ref().map(|(scope, _)| scope.clone()),
+            rule_ids: options.rule_id_compat,
+            stdin: self.stdin.clone(),
+            ..Default::default()
+        };
+
+        self.events.scanning(actions.len(), workflows.len());
+        if filter.is_active() {
+            self.events.progress(&format!("Filtered out {} of {} actions with --only/--skip", report.filtered_out, total_actions));
+        }
+        if let Some((scope, _)) = &diff {
+            self.events.progress(&format!(
+                "Comparing with {}: {} actions unchanged and not looked up, {} new workflow file(s), {} deleted",
+                scope.base,
+                scope.unchanged_actions.len(),
+                scope.added_workflows.len(),
+                scope.deleted_workflows.len()
+            ));
+            for deleted in &scope.deleted_workflows {
+                self.events.progress(&format!("{} {} was deleted; nothing to check", Marker::Info, deleted));
+            }
+        }
+
+        if options.describe_pins && !options.offline {
+            report.pins = self.describe_pins(&actions, &workflows, &comment_style).await;
+            self.events.pins(&report.pins);
+        }
+        let comment_mismatches = if options.verify_pin_comments && !options.offline {
+            self.verify_pin_comments(&workflows, &comment_style).await
+        } else {
+            Vec::new()
+        };
+
+        self.events.phase("policy");
+        // Policy checks run per workflow file, under whichever config governs that file
+        for (workflow, effective) in &workflows {
+            let governing = effective.describe(self.configs.root_path());
+            self.events.progress(&format!("\nWorkflow {} (config: {})", workflow.path, governing));
+            for key in &effective.overridden_enforced {
+                self.events.progress(&format!(
+                    "{} {} is enforced by {}; the scoped value is ignored",
+                    Marker::Warn,
+                    key,
+                    self.configs.root_path()
+                ));
+            }
+            if let Some((message, line)) = workflow.load_error() {
+                let finding = Finding::new(Rule::InvalidWorkflow, Level::Error, "", message).at(&workflow.path, line);
+                self.found(&mut report, finding);
+                report.configs.insert(workflow.path.clone(), governing);
+                report.workflow_actions.insert(workflow.path.clone(), BTreeSet::new());
+                continue;
+            }
+            if let Some(reason) = &workflow.refused {
+                let message = format!("Refused to parse {} (resource limits): {}", workflow.path, reason);
+                let finding = Finding::new(Rule::ParseLimit, Level::Error, "", message).at(&workflow.path, None);
+                self.found(&mut report, finding);
+                report.configs.insert(workflow.path.clone(), governing);
+                report.workflow_actions.insert(workflow.path.clone(), BTreeSet::new());
+                continue;
+            }
+            // Every value of a repeated key is scanned, but what the file means is unclear
+            for duplicate in &workflow.duplicates {
+                let (level, reason) = if options.strict { (Level::Error, " (failing due to --strict)") } else { (Level::Warning, "") };
+                let message = format!("{}; YAML parsers keep only one of the values{}", duplicate.describe(), reason);
+                let finding = Finding::new(Rule::DuplicateKey, level, "", message).at(&workflow.path, Some(duplicate.second));
+                self.found(&mut report, finding);
+            }
+            if workflow.is_empty() {
+                self.events.progress(&format!("{} {} is empty or only comments; no actions to check", Marker::Info, workflow.path));
+            } else if workflow.is_not_mapping() {
+                self.events.progress(&format!(
+                    "{} {} is not a mapping of keys such as on and jobs; no actions to check",
+                    Marker::Info,
+                    workflow.path
+                ));
+            }
+            if workflow.lenient.is_none() {
+                let surface = surface::classify(&workflow.value, visibility);
+                self.events.progress(&format!("{} Trigger surface: {}", Marker::Info, surface.describe()));
+                report.trigger_surfaces.insert(workflow.path.clone(), surface);
+            }
+            if let Some(uses) = &workflow.lenient {
+                // Without the YAML structure only the per-reference checks can run
+                self.events.progress(&format!("{} {} was parsed leniently; workflow-level rules were skipped", Marker::Warn, workflow.path));
+                for found in uses.iter().filter(|u| filter.allows(&u.value)) {
+                    let finding = if found.templated {
+                        let message = format!("{} uses {}, which contains a template placeholder", workflow.path, found.value);
+                        Finding::new(Rule::DynamicReference, Level::Warning, &found.value, message)
+                    } else {
+                        let mut findings = Vec::from_iter(indicators::check(&found.value, &indicators));
+                        findings.extend(self.check_action_policy(&found.value, &effective.config));
+                        report.findings.extend(findings.into_iter().map(|f| f.at(&workflow.path, Some(found.line))));
+                        continue;
+                    };
+                    self.found(&mut report, finding.at(&workflow.path, Some(found.line)));
+                }
+                report.configs.insert(workflow.path.clone(), governing);
+                let used = workflow.actions().into_iter().filter(|a| filter.allows(a)).collect();
+                report.workflow_actions.insert(workflow.path.clone(), used);
+                continue;
+            }
+            // Each occurrence gets its own findings, so a reference repeated across jobs is reported
+            // at every job and line that has to change
+            let occurrences: Vec<Occurrence> =
+                extract_occurrences(&workflow.value, &workflow.source).into_iter().filter(|o| filter.allows(&o.action)).collect();
+            for occurrence in &occurrences {
+                let action = &occurrence.action;
+                let locate = |finding: Finding| occurrence.locate(finding, &workflow.path);
+                // Templated references are checked below, per matrix expansion
+                if action.contains("${{") {
+                    continue;
+                }
+                if workflow.is_templated(action) {
+                    let message = format!("{} uses {}, which contains a template placeholder", workflow.path, action);
+                    self.found(&mut report, locate(Finding::new(Rule::DynamicReference, Level::Warning, action, message)));
+                    continue;
+                }
+                // Known compromises are reported whatever the config trusts
+                if let Some(finding) = indicators::check(action, &indicators) {
+                    self.found(&mut report, locate(finding));
+                }
+                let findings = self.check_action_policy(action, &effective.config);
+                report.findings.extend(findings.into_iter().map(locate));
+            }
+            report.occurrences.insert(workflow.path.clone(), occurrences);
+            for templated in matrix::templated_uses(&workflow.value).iter().filter(|t| filter.allows(&t.uses)) {
+                let line = rules::key_line(&workflow.source, &["jobs", &templated.job]);
+                let expansions = match &templated.expansions {
+                    Ok(expansions) => expansions,
+                    Err(reason) => {
+                        let level = dynamic_reference_level(&templated.uses, &effective.config, options.strict);
+                        let why = match level {
+                            Level::Error if options.strict => " (failing due to --strict)",
+                            Level::Error => " (a critical dependency)",
+                            _ => "",
+                        };
+                        let message = format!(
+                            "Job {}, {}, uses {}, which cannot be resolved statically because {}; the action that runs is only known at run time{}",
+                            templated.job, templated.step, templated.uses, reason, why
+                        );
+                        let uses_line = dispatch::source_line(&workflow.source, &templated.uses, line).or(line);
+                        let finding = Finding::new(Rule::DynamicReference, level, &templated.uses, message)
+                            .with_job(&templated.job)
+                            .with_step(&templated.step)
+                            .at(&workflow.path, uses_line);
+                        self.found(&mut report, finding);
+                        continue;
+                    }
+                };
+                for (concrete, entry) in expansions.iter().filter(|(concrete, _)| filter.allows(concrete)) {
+                    self.events.progress(&format!("Job {}, {}, expands to {} for matrix {}", templated.job, templated.step, concrete, entry));
+                    let mut findings = Vec::from_iter(indicators::check(concrete, &indicators));
+                    findings.extend(self.check_action_policy(concrete, &effective.config));
+                    report.findings.extend(findings.into_iter().map(|f| {
+                        f.with_job(&templated.job)
+                            .with_matrix(&templated.step, entry)
+                            .at(&workflow.path, line)
+                    }));
+                }
+            }
+            for finding in rules::check_workflow(workflow, &effective.config, filter, visibility) {
+                self.found(&mut report, finding);
+            }
+            for finding in docker_args::check(workflow, &repo_root(), options.strict).into_iter().filter(|f| filter.allows(&f.action)) {
+                self.found(&mut report, finding);
+            }
+            if options.require_pinning_comment_style {
+                let findings = pincomment::check(&workflow.path, &workflow.source, &comment_style);
+                for finding in findings.into_iter().filter(|f| filter.allows(&f.action)) {
+                    self.found(&mut report, finding);
+                }
+            }
+            for finding in comment_mismatches.iter().filter(|f| f.file.as_deref() == Some(workflow.path.as_str()) && filter.allows(&f.action)) {
+                self.found(&mut report, finding.clone());
+            }
+            if options.scan_github_script {
+                for finding in github_script::check(workflow, &effective.config).into_iter().filter(|f| filter.allows(&f.action)) {
+                    self.found(&mut report, finding);
+                }
+            }
+            if options.scan_run_steps {
+                let invalid = |e: String| ScanError::config(&effective.describe(self.configs.root_path()), e);
+                let dispatches = dispatch::check_run_steps(workflow, &effective.config).map_err(invalid)?;
+                let downloads = downloads::check_run_steps(workflow, &effective.config).map_err(invalid)?;
+                for finding in dispatches.into_iter().filter(|f| filter.allows(&f.action)).chain(downloads) {
+                    self.found(&mut report, finding);
+                }
+            }
+            report.configs.insert(workflow.path.clone(), governing);
+            let used = workflow.actions().into_iter().filter(|a| filter.allows(a)).collect();
+            report.workflow_actions.insert(workflow.path.clone(), used);
+        }
+        if options.scan_cache_poisoning {
+            let parsed: Vec<&Workflow> = workflows.iter().map(|(workflow, _)| workflow).collect();
+            for finding in poisoning::check(&parsed, visibility).into_iter().filter(|f| filter.allows(&f.action)) {
+                self.found(&mut report, finding);
+            }
+        }
+        if options.check_dependabot || options.dependabot_config.is_some() {
+            let parsed: Vec<&Workflow> = workflows.iter().map(|(workflow, _)| workflow).collect();
+            let found = dependabot::check(&parsed, &repo_root(), options.dependabot_config.as_deref(), config, options.strict)?;
+            for finding in found.into_iter().filter(|f| f.action.is_empty() || filter.allows(&f.action)) {
+                self.found(&mut report, finding);
+            }
+        }
+        // Actions only composite actions use get the same checks, attributed through their chain
+        let mut chains: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if let Some(depth) = options.transitive.filter(|_| !options.offline) {
+            let (nested, failed) = transitive::expand(&self.api(), &self.cache, &actions, depth, options.concurrency).await;
+            // What a composite whose metadata could not be read uses went unchecked
+            for (action, error) in failed {
+                self.lookup_failed(&mut report, &action, error);
+            }
+            let nested: Vec<transitive::Nested> = nested.into_iter().filter(|d| filter.allows(&d.action)).collect();
+            report.transitive = Some(nested.clone());
+            for dependency in nested {
+                self.events.progress(&format!("{} is used by {}", dependency.action, dependency.chain.join(" -> ")));
+                // Each file that uses the action leading to it checks it under its own config, at
+                // the line of its first use of that action
+                let root = &dependency.chain[0];
+                for (workflow, effective) in &workflows {
+                    if !report.workflow_actions.get(&workflow.path).is_some_and(|used| used.contains(root)) {
+                        continue;
+                    }
+                    let occurrences = report.occurrences.get(&workflow.path).into_iter().flatten();
+                    let line = occurrences.filter(|o| &o.action == root).find_map(|o| o.line);
+                    let mut findings = action_policy(&dependency.action, &effective.config, options.strict);
+                    if let Some(max) = options.max_depth_fail.filter(|max| dependency.depth() > *max) {
+                        let message = format!("dependency chain of depth {} exceeds --max-depth-fail {}", dependency.depth(), max);
+                        findings.push(Finding::new(Rule::DependencyDepth, Level::Error, &dependency.action, message));
+                    }
+                    for finding in findings {
+                        self.found(&mut report, finding.via(&dependency.chain).at(&workflow.path, line));
+                    }
+                }
+                // Its findings count for every file that uses the action leading to it
+                for used in report.workflow_actions.values_mut() {
+                    if used.contains(&dependency.chain[0]) {
+                        used.insert(dependency.action.clone());
+                    }
+                }
+                actions.insert(dependency.action.clone());
+                chains.insert(dependency.action, dependency.chain);
+            }
+            report.actions_scanned = actions.len();
+        }
+        self.events.progress("");
+
+        self.events.phase("network");
+        self.events.report(&report);
+        // Network checks run once per unique action, several at a time; results are merged in the
+        // order of the actions so the report does not depend on which lookup finished first
+        let checked: Vec<ScanReport> = if options.offline {
+            report.skipped_checks = self.offline_skipped();
+            self.events.progress(&format!("{} Offline: skipped {}", Marker::Warn, report.skipped_checks.join(", ")));
+            Vec::new()
+        } else {
+            let (policy_findings, pins) = (&report.findings, &report.pins);
+            stream::iter(&actions)
+                .map(|action| self.check_online(action, &advisory_policy, policy_findings, pins))
+                .buffered(options.concurrency.max(1))
+                .collect()
+                .await
+        };
+        for (action, checked) in actions.iter().zip(checked) {
+            if checked.findings.iter().any(|f| f.rule == Rule::ImmutableRelease) {
+                immutable::drop_exempted(&mut report.findings, action);
+            }
+            let chain = chains.get(action).map(Vec::as_slice).unwrap_or_default();
+            report.findings.extend(checked.findings.into_iter().map(|finding| finding.via(chain)));
+            report.unchecked.extend(checked.unchecked);
+            report.errors.extend(checked.errors);
+            report.vendored.extend(checked.vendored);
+        }
+
+        if let Some(finding) = rules::check_owner_budget(&actions, config) {
+            self.found(&mut report, finding);
+        }
+
+        let governing: BTreeMap<String, Config> = workflows.iter().map(|(w, e)| (w.path.clone(), e.config.clone())).collect();
+        let overridden = config::apply_rule_overrides(&mut report.findings, config, &governing, &report.trigger_surfaces)
+            .into_iter()
+            .map(|(index, was)| (report.findings[index].clone(), was))
+            .collect();
+        let unused_ignores = config::apply_ignore_file(&mut report.findings, &options.ignore, options.rule_id_compat, self.clock.now().date_naive());
+        let unused_baseline = match (&options.write_baseline, &options.baseline) {
+            (Some(path), _) => {
+                let snapshot = baseline::snapshot(&report);
+                baseline::write(path, &snapshot)?;
+                self.events.progress(&format!("\n{} Wrote baseline {} with {} entries", Marker::Ok, path, snapshot.entries.len()));
+                baseline::apply(&mut report, &snapshot, path)
+            }
+            (None, Some((path, accepted))) => baseline::apply(&mut report, accepted, path),
+            _ => Vec::new(),
+        };
+        if let Some((_, diff)) = &diff {
+            diff.apply(&mut report);
+        }
+        if options.update_baseline
+            && let Some((path, accepted)) = &options.baseline
+        {
+            if filter.is_active() {
+                self.events.warning("Warning: --update-baseline ignored: --only and --skip hide findings the baseline still needs");
+            } else if !unused_baseline.is_empty() {
+                let entries = accepted.entries.iter().filter(|entry| !unused_baseline.contains(entry)).cloned().collect();
+                let updated = Baseline { scanner_version: Some(version::SCANNER_VERSION.to_string()), entries };
+                baseline::write(path, &updated)?;
+                self.events.progress(&format!("{} Removed {} resolved entries from baseline {}", Marker::Ok, unused_baseline.len(), path));
+            }
+        }
+
+        // A filtered scan sees only some actions, so every entry outside the filter would look unused
+        let mut unused = Vec::new();
+        if !filter.is_active() {
+            let used: Vec<BTreeSet<String>> = workflows.iter().map(|(workflow, _)| workflow.actions()).collect();
+            let governed = workflows
+                .iter()
+                .zip(&used)
+                .map(|((_, effective), actions)| (effective, effective.describe(self.configs.root_path()), actions));
+            unused = hygiene::unused_entries(governed);
+            unused.extend(unused_ignores.into_iter().map(unused_ignore_finding));
+            if !options.update_baseline {
+                unused.extend(unused_baseline.iter().map(baseline::unused_finding));
+            }
+            report.findings.extend(unused.iter().cloned());
+        }
+
+        let policy = ExitPolicy {
+            fail_on_unchecked: options.exit.fail_on_unchecked || config.fail_on_unchecked == Some(true),
+            ..options.exit.clone()
+        };
+        let kept_older = match options.advisories_since {
+            Some(since) => filter_advisories_since(&mut report, since, options.fail_only_recent, &policy),
+            None => Vec::new(),
+        };
+        report.sort();
+        report.fingerprint();
+        report.summary = Some(summary::Summary {
+            api_requests: self.client.requests() - requests_before,
+            cache_hits: self.cache.hits() - hits_before,
+            ..summary::Summary::new(&report, workflows.len(), self.clock.elapsed(started))
+        });
+        describe::attach(&mut report);
+        if let Some(warning) = compat::unmapped_warning(&report) {
+            self.events.warning(&warning);
+        }
+        self.report_warnings();
+        self.events.report(&report);
+        self.events.phase("decide");
+        let decision = decision::decide(&report, &policy);
+        let inventory = workflows.iter().map(|(workflow, _)| (workflow.path.clone(), workflow.actions())).collect();
+        Ok(ScanOutcome { report, decision, overridden, unused, kept_older, inventory })
+    }
+
+    /// A workflow file, or the stdin workflow when `file` is its name. With `--lenient-templates`
+    /// a workflow that does not parse is warned about and read line by line instead.
+    fn load(&self, file: &Path, limits: &limits::ParseLimits) -> Result<Workflow, ScanError> {
+        let lenient = self.options.lenient_templates;
+        let (name, source) = match &self.stdin {
+            Some((name, source)) if Path::new(name) == file => (Cow::from(name.as_str()), Cow::from(source.as_str())),
+            _ => {
+                let name = file.to_string_lossy();
+                let source = fs::read_to_string(file).map_err(|e| ScanError::io(&name, e))?;
+                (name, Cow::from(source))
+            }
+        };
+        match parse_workflow(&name, &source, lenient, limits) {
+            Err(e) if lenient => {
+                self.events.warning(&format!("Warning: {}; parsing leniently (--lenient-templates)", e));
+                Ok(Workflow::lenient(&name, &source))
+            }
+            parsed => parsed,
+        }
+    }
+
+    /// A finding the scan made itself, told to the events and added to the report
+    fn found(&self, report: &mut ScanReport, finding: Finding) {
+        self.events.finding(&finding);
+        report.findings.push(finding);
+    }
+
+    fn indicators(&self) -> Result<Vec<indicators::Indicator>, ScanError> {
+        if !self.options.builtin_indicators {
+            return Ok(Vec::new());
+        }
+        indicators::builtin().map_err(|e| ScanError::yaml("built-in compromised action list", &e))
+    }
+
+    fn advisory_policy(&self) -> AdvisoryPolicy {
+        AdvisoryPolicy {
+            fail_on_severity: self.options.exit.fail_on_severity,
+            unknown_severity: self.options.exit.unknown_severity,
+            since: self.options.advisories_since,
+            ..AdvisoryPolicy::new(self.config(), &self.clock)
+        }
+    }
+
+    /// The network-backed checks `--offline` skips: the ones every scan runs, and those the options
+    /// asked for
+    fn offline_skipped(&self) -> Vec<String> {
+        let options = &self.options;
+        let mut skipped = vec![
+            match options.sources {
+                AdvisorySources::Github => "GitHub advisory lookups".to_string(),
+                AdvisorySources::Osv => "OSV advisory lookups".to_string(),
+                AdvisorySources::All => "GitHub and OSV advisory lookups".to_string(),
+            },
+            "default-branch and release lookups of pins".to_string(),
+            "vendored upstream checks".to_string(),
+        ];
+        let requested = [
+            (options.verify_shas, "commit verification (--verify-shas)"),
+            (options.repo_status, "repository status (--check-repo-status)"),
+            (options.runtime, "runtime (--check-runtime)"),
+            (options.outdated.is_some(), "outdated pins (--check-outdated)"),
+            (options.advisory_history.is_some(), "advisory history (--advisory-history)"),
+            (options.transitive.is_some(), "composite action dependencies (--transitive)"),
+            (options.describe_pins, "pin versions (--describe-pins)"),
+            (options.verify_pin_comments, "pin comment verification (--verify-pin-comments)"),
+        ];
+        skipped.extend(requested.into_iter().filter(|(asked, _)| *asked).map(|(_, check)| check.to_string()));
+        skipped
+    }
+
+    /// `--describe-pins`: resolve every SHA pin to a version before anything prints it. A pin the API
+    /// cannot place falls back to the version comment of its first use.
+    async fn describe_pins(
+        &self,
+        actions: &BTreeSet<String>,
+        workflows: &[(Workflow, EffectiveConfig)],
+        style: &pincomment::CommentStyle,
+    ) -> describe::Pins {
+        let mut comments: BTreeMap<&str, &str> = BTreeMap::new();
+        for (workflow, _) in workflows {
+            for (action, version) in pincomment::versions(&workflow.source, style) {
+                comments.entry(action).or_insert(version);
+            }
+        }
+        let pins: Vec<(&String, ActionRef)> = actions.iter().filter_map(|action| Some((action, describe::sha_pin(action)?))).collect();
+        let api = self.api();
+        let resolved: Vec<Option<String>> = stream::iter(&pins)
+            .map(|(action, action_ref)| {
+                let api = &api;
+                async move {
+                    describe::resolve(api, &self.cache, action_ref).await.unwrap_or_else(|e| {
+                        self.events.warning(&format!("Could not resolve the version of {}: {}", action, e));
+                        None
+                    })
+                }
+            })
+            .buffered(self.options.concurrency.max(1))
+            .collect()
+            .await;
+        let versions = pins
+            .iter()
+            .zip(resolved)
+            .map(|((action, _), resolved)| {
+                let version = match (resolved, comments.get(action.as_str())) {
+                    (Some(tag), _) => describe::PinVersion::Tag(tag),
+                    (None, Some(comment)) => describe::PinVersion::Comment(comment.to_string()),
+                    (None, None) => describe::PinVersion::Unknown,
+                };
+                (action.to_string(), version)
+            })
+            .collect();
+        describe::Pins::new(versions)
+    }
+
+    /// `--verify-pin-comments`: check each SHA pin's version comment against the tag it names, once
+    /// per pin and version however many lines carry them, and locate a mismatch at every such line.
+    /// A pin that cannot be looked up is reported and left unverified.
+    async fn verify_pin_comments(&self, workflows: &[(Workflow, EffectiveConfig)], style: &pincomment::CommentStyle) -> Vec<Finding> {
+        let mut lines: BTreeMap<(&str, &str), Vec<(&str, usize)>> = BTreeMap::new();
+        for (workflow, _) in workflows {
+            for (line, action, version) in pincomment::commented_pins(&workflow.source, style) {
+                lines.entry((action, version)).or_default().push((&workflow.path, line));
+            }
+        }
+        let api = self.api();
+        let verified: Vec<Option<String>> = stream::iter(lines.keys())
+            .map(|(action, version)| {
+                let api = &api;
+                async move {
+                    pincomment::verify(api, &self.cache, action, version).await.unwrap_or_else(|e| {
+                        self.events.warning(&format!("Could not verify the pin comment of {}: {}", action, e));
+                        None
+                    })
+                }
+            })
+            .buffered(self.options.concurrency.max(1))
+            .collect()
+            .await;
+        let mut findings = Vec::new();
+        for (((action, _), uses), message) in lines.iter().zip(verified) {
+            let Some(message) = message else { continue };
+            for (path, line) in uses {
+                findings.push(Finding::new(Rule::PinCommentMismatch, Level::Error, action, message.clone()).at(path, Some(*line)));
+            }
+        }
+        findings
+    }
+
+    /// The network checks of one action, as a partial report of its findings, whether it could be
+    /// checked, and its vendoring. `policy_findings` are those of every workflow, which an
+    /// immutable release can exempt the action from.
+    async fn check_online(&self, action: &str, advisory_policy: &AdvisoryPolicy, policy_findings: &[Finding], pins: &describe::Pins) -> ScanReport {
+        let config = self.config();
+        let options = &self.options;
+        let mut checked = ScanReport { pins: pins.clone(), ..Default::default() };
+        // The ref, maybe the action, is only known at run time; the policy checks reported it
+        if action.contains("${{") {
+            self.events.progress(&format!("Skipping dynamic reference {}", action));
+            return checked;
+        }
+        // A blocked action fails already; its lookups would spend API calls on nothing
+        if let Some(ActionKind::GitHub(action_ref)) = ActionKind::parse(action)
+            && blocklist::is_blocked(&action_ref, config)
+        {
+            self.events.progress(&format!("Skipping lookups for blocked action {}", pins.display(action)));
+            return checked;
+        }
+        self.events.progress(&format!("Checking {} for vulnerabilities...", pins.display(action)));
+
+        let api = self.api();
+        let kind = ActionKind::parse(action);
+        let vendored = if matches!(kind, Some(ActionKind::Local(_))) {
+            match vendor::provenance(action, config.vendored_actions.as_ref(), &repo_root()) {
+                Ok(vendored) => vendored,
+                Err(e) => {
+                    let finding = Finding::new(
+                        Rule::InvalidVendoredMarker,
+                        Level::Warning,
+                        action,
+                        format!("Invalid vendoring provenance for {}: {}", action, e),
+                    );
+                    self.found(&mut checked, finding);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(vendored) = vendored {
+            // Vendored copies inherit the upstream's advisories and fall behind its releases
+            self.events.progress(&format!("{} {} is vendored from {} ({})", Marker::Vendored, action, vendored.upstream, vendored.source));
+            self.check_advisories(advisory_policy, action, &vendored.upstream_ref, &mut checked).await;
+            match vendor::latest_release(&api, &vendored.upstream_ref).await {
+                Ok(Some(latest)) if latest != vendored.upstream_ref.version => {
+                    let message = format!(
+                        "Vendored action {} is at {} but upstream {} has released {}",
+                        action,
+                        vendored.upstream_ref.version,
+                        vendored.upstream_ref.full_name(),
+                        latest
+                    );
+                    self.found(&mut checked, Finding::new(Rule::VendoredOutdated, Level::Warning, action, message));
+                }
+                Ok(_) => {}
+                Err(e) => self.events.warning(&format!("Failed to check releases of {}: {}", vendored.upstream, e)),
+            }
+            checked.vendored.push(vendored);
+        } else if let Some(ActionKind::GitHub(action_ref)) = &kind {
+            self.check_advisories(advisory_policy, action, action_ref, &mut checked).await;
+            if options.repo_status {
+                match repo_status::check(&api, &self.cache, action, action_ref, config, options.strict).await {
+                    Ok(findings) => {
+                        for finding in findings {
+                            self.found(&mut checked, finding);
+                        }
+                    }
+                    Err(e) => self.lookup_failed(
+                        &mut checked,
+                        action,
+                        format!("Could not check the repository status of {}: {}", action_ref.full_name(), e),
+                    ),
+                }
+            }
+            if options.runtime {
+                match runtime::check(&api, &self.cache, action, action_ref, options.strict).await {
+                    Ok(runtime::Runtime::Flagged(finding)) => self.found(&mut checked, *finding),
+                    Ok(runtime::Runtime::Other(using)) => {
+                        self.events.progress(&format!("{} runs as a {} action, with no Node.js runtime of its own", action, using))
+                    }
+                    Ok(runtime::Runtime::NoMetadata) => self.events.warning(&format!(
+                        "Could not check the runtime of {}: it has no action.yml at {}",
+                        action, action_ref.version
+                    )),
+                    Ok(runtime::Runtime::Supported) => {}
+                    Err(e) => self.lookup_failed(&mut checked, action, format!("Could not check the runtime of {}: {}", action, e)),
+                }
+            }
+            if config.exempt_immutable_releases == Some(true)
+                && let Some(finding) = immutable::exempt(&api, &self.cache, self.events(), action, action_ref, policy_findings).await
+            {
+                self.found(&mut checked, finding);
+            }
+            match check_default_branch_pin(&api, &self.cache, action, action_ref, config, options.strict).await {
+                Ok(Some(finding)) => self.found(&mut checked, finding),
+                Ok(None) => {}
+                Err(e) => self.events.warning(&format!("Could not look up the default branch of {}: {}", action_ref.full_name(), e)),
+            }
+            if action_ref.is_commit_sha() {
+                let level = config.unreleased_commit_level.unwrap_or(Level::Warning);
+                match unreleased::check(&api, &self.cache, action, action_ref, level).await {
+                    Ok(Some(finding)) => self.found(&mut checked, finding),
+                    Ok(None) => {}
+                    // Release checks are advisory; without the API they are skipped, not failed
+                    Err(e) => self.events.warning(&format!("Could not check whether {} is released: {}", action, e)),
+                }
+            }
+            if let Some(level) = options.outdated {
+                match outdated::check(&api, &self.cache, action, action_ref, level).await {
+                    Ok(Some(finding)) => self.found(&mut checked, finding),
+                    Ok(None) => {}
+                    Err(e) => self.events.warning(&format!("Could not check whether {} is outdated: {}", action, e)),
+                }
+            }
+            if action_ref.is_commit_sha() && options.verify_shas {
+                match shas::verify(&api, &self.cache, action, action_ref).await {
+                    Ok(Some(finding)) => self.found(&mut checked, finding),
+                    Ok(None) => {}
+                    Err(e) => self.lookup_failed(&mut checked, action, format!("Could not verify the commit {} is pinned to: {}", action, e)),
+                }
+            }
+            if let Some(months) = options.advisory_history {
+                self.check_advisory_history(action, action_ref, months, &mut checked).await;
+            }
+        } else if let Some(ActionKind::Local(_) | ActionKind::Docker(_)) = &kind {
+            // Local actions and images have no advisories to look up; they are checked with the policy
+        } else {
+            // Reported with the policy findings, at each place the reference is used
+            self.events.progress(&format!("Skipping malformed action reference: {}", action));
+        }
+        checked
+    }
+
+    /// Informational `--advisory-history` check: count every advisory for the repository, open or
+    /// closed, published within the window. Kept apart from `check_advisories`, which alone decides
+    /// whether an action is vulnerable.
+    async fn check_advisory_history(&self, action: &str, action_ref: &ActionRef, months: u32, report: &mut ScanReport) {
+        let advisories = match self.advisories(action_ref, false).await {
+            Ok((advisories, _)) => advisories,
+            Err(e) => {
+                self.events.warning(&format!("Failed to check advisory history of {}: {}", action, e));
+                return;
+            }
+        };
+
+        let Some(since) = self.clock.now().checked_sub_months(chrono::Months::new(months)) else { return };
+        let recent: Vec<chrono::DateTime<chrono::Utc>> = advisories
+            .iter()
+            .filter_map(|a| a.created_at.as_deref())
+            .filter_map(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&chrono::Utc))
+            .filter(|date| *date >= since)
+            .collect();
+        let Some(latest) = recent.iter().max() else { return };
+        let message = format!(
+            "{} has a history of advisories: {} in the last {} months, most recent {}",
+            action_ref.full_name(),
+            recent.len(),
+            months,
+            latest.format("%Y-%m-%d")
+        );
+        self.found(report, Finding::new(Rule::AdvisoryHistory, Level::Note, action, message));
+    }
+}
+
+/// The GitHub advisory database and OSV, as `--source` selects them, through the cache
+struct HttpAdvisories<'a> {
+    client: &'a dyn HttpClient,
+    cache: &'a DiskCache,
+    sources: AdvisorySources,
+    endpoints: &'a Endpoints,
+    memo: &'a AdvisoryMemo,
+    events: &'a dyn ScanEvents,
+}
+
+impl HttpAdvisories<'_> {
+    /// The advisories from the cache, else from the databases, and whether they were cached
+    async fn lookup(&self, action_ref: &ActionRef, open_only: bool) -> Result<(Vec<GitHubAdvisory>, bool), ScanError> {
+        let (namespace, key) = match open_only {
+            true => ("advisories", format!("{}/{}@{}", self.sources.name(), action_ref.full_name(), action_ref.version)),
+            false => ("advisory-history", format!("{}/{}", self.sources.name(), action_ref.full_name())),
+        };
+        match self.cache.get(namespace, &key) {
+            Cached::Hit(value) => {
+                if let Ok(advisories) = serde_json::from_value(value) {
+                    return Ok((advisories, true));
+                }
+            }
+            Cached::NegativeHit => return Ok((Vec::new(), true)),
+            Cached::Miss => {}
+        }
+        let (advisories, complete) = self.fetch(action_ref, open_only).await?;
+        // A lookup missing a source is not cached, so the next run asks again
+        if complete && advisories.is_empty() {
+            self.cache.put_negative(namespace, &key);
+        } else if complete {
+            self.cache.put(namespace, &key, &serde_json::to_value(&advisories).unwrap_or_default());
+        }
+        Ok((advisories, false))
+    }
+
+    /// Advisories from every database of `sources`, merged by GHSA id, and whether every source
+    /// answered. A source that fails is skipped with a warning; only when all of them fail is the
+    /// lookup an error, the first source's, with the others warned about.
+    async fn fetch(&self, action_ref: &ActionRef, open_only: bool) -> Result<(Vec<GitHubAdvisory>, bool), ScanError> {
+        let mut advisories = Vec::new();
+        let mut failures = Vec::new();
+        if self.sources != AdvisorySources::Osv {
+            match self.github(action_ref, open_only).await {
+                Ok(found) => advisories = found,
+                Err(e) => failures.push(("GitHub advisories", e)),
+            }
+        }
+        if self.sources != AdvisorySources::Github {
+            match osv::query(self.client, self.endpoints, action_ref).await {
+                Ok(found) => {
+                    let found = found.into_iter().filter(|a| !open_only || (a.is_open() && a.affects(action_ref) != Some(false)));
+                    for advisory in found {
+                        match advisories.iter_mut().find(|a: &&mut GitHubAdvisory| a.id == advisory.id) {
+                            Some(known) => known.sources.push("osv".to_string()),
+                            None => advisories.push(advisory),
+                        }
+                    }
+                }
+                Err(e) => failures.push(("OSV", e)),
+            }
+        }
+        let queried = if self.sources == AdvisorySources::All { 2 } else { 1 };
+        if failures.len() == queried {
+            let (_, first) = failures.remove(0);
+            for (source, e) in &failures {
+                self.events.warning(&format!("Warning: {} {}: {}", action_ref.full_name(), source, e));
+            }
+            return Err(first);
+        }
+        for (source, e) in &failures {
+            self.events.warning(&format!("Warning: {} {}: {} (using the other source)", action_ref.full_name(), source, e));
+        }
+        Ok((advisories, failures.is_empty()))
+    }
+
+    /// GitHub's advisories for the repository, queried once per scanner however many of its pins
+    /// are looked up
+    async fn github(&self, action_ref: &ActionRef, open_only: bool) -> Result<Vec<GitHubAdvisory>, ScanError> {
+        let cell = {
+            let mut memo = self.memo.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            memo.entry(action_ref.full_name().to_ascii_lowercase()).or_default().clone()
+        };
+        let advisories = cell
+            .get_or_init(|| query_github_advisories(self.client, &self.endpoints.advisories, action_ref.full_name()))
+            .await
+            .clone()?;
+        // A pin outside every vulnerable range is patched; one that cannot be compared is not
+        Ok(advisories
+            .into_iter()
+            .filter(|a| !open_only || (a.is_open() && a.affects(action_ref) != Some(false)))
+            .collect())
+    }
+}
+
+fn unused_ignore_finding(entry: &IgnoreEntry) -> Finding {
+    let message = format!("ignore file entry {} matched no finding; remove it if the finding is gone", entry.describe());
+    Finding::scan_level(Rule::UnusedConfigEntry, Level::Warning, message)
+}
+
+/// The `uses:` value that names the action, `owner/repo[/path]@version`
+fn uses_value(action_ref: &ActionRef) -> String {
+    match &action_ref.path {
+        Some(path) => format!("{}/{}@{}", action_ref.full_name(), path, action_ref.version),
+        None => format!("{}@{}", action_ref.full_name(), action_ref.version),
+    }
+}
+
+/// Expand a scan path into workflow files: a file is scanned as-is, a directory yields its
+/// `*.yml`/`*.yaml` entries in sorted order. Symlinked subdirectories are followed, so workflows
+/// linked in from templates are found, and with `recursive` so are all other subdirectories;
+/// every file is reported by its resolved path. A symlink cycle is reported to `events` and not
+/// followed.
+pub fn workflow_files(path: &Path, recursive: bool, events: &dyn ScanEvents) -> Result<Vec<PathBuf>, ScanError> {
+    if !path.is_dir() {
+        return Ok(vec![resolved_path(path)]);
+    }
+    let mut files = Vec::new();
+    collect_workflow_files(path, recursive, events, &mut Vec::new(), &mut BTreeSet::new(), &mut files)?;
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn collect_workflow_files(
+    dir: &Path,
+    recursive: bool,
+    events: &dyn ScanEvents,
+    ancestors: &mut Vec<PathBuf>,
+    visited: &mut BTreeSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), ScanError> {
+    let unreadable = |e| ScanError::io(&dir.display().to_string(), e);
+    let real = dir.canonicalize().map_err(unreadable)?;
+    if ancestors.contains(&real) {
+        events.warning(&format!("Warning: {} links back to {}; not following the symlink cycle", dir.display(), real.display()));
+        return Ok(());
+    }
+    // Reached again through another link: already scanned
+    if !visited.insert(real.clone()) {
+        return Ok(());
+    }
+    ancestors.push(real);
+    for entry in fs::read_dir(dir).map_err(unreadable)? {
+        let entry = entry.map_err(unreadable)?;
+        let file = entry.path();
+        if file.is_dir() {
+            if recursive || entry.file_type().map_err(unreadable)?.is_symlink() {
+                collect_workflow_files(&file, recursive, events, ancestors, visited, files)?;
+            }
+            continue;
+        }
+        let is_yaml = file.extension().is_some_and(|ext| ext == "yml" || ext == "yaml");
+        // Directory-scoped configs can sit next to the workflows they govern
+        let is_config = file.file_name().is_some_and(|name| name == config::SCOPED_CONFIG_FILE);
+        if is_yaml && !is_config && file.is_file() {
+            files.push(resolved_path(&file));
+        }
+    }
+    ancestors.pop();
+    Ok(())
+}
+
+/// The real location of a path with symlinks resolved, relative to the current directory when
+/// it lies below it
+fn resolved_path(path: &Path) -> PathBuf {
+    let Ok(real) = path.canonicalize() else {
+        return path.to_path_buf();
+    };
+    match std::env::current_dir().and_then(|cwd| cwd.canonicalize()) {
+        Ok(cwd) => real.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(real),
+        Err(_) => real,
+    }
+}
@@ -0,0 +1,154 @@
+use crate::config::Config;
+use crate::report::ScanReport;
+use schemars::schema::RootSchema;
+use serde_json::{Map, Value};
+
+/// JSON Schema for the YAML config file
+pub fn config_schema() -> RootSchema {
+    schemars::schema_for!(Config)
+}
+
+/// The top-level keys of the config file
+pub fn config_keys() -> Vec<String> {
+    let schema = config_schema();
+    schema.schema.object.map(|object| object.properties.into_keys().collect()).unwrap_or_default()
+}
+
+/// JSON Schema for the JSON scan report
+pub fn report_schema() -> RootSchema {
+    schemars::schema_for!(ScanReport)
+}
+
+/// Check a config document against the config schema before it is deserialized, so structural
+/// mistakes are reported by key path (`trusted_owners: expected array`) instead of serde's
+/// line/column message
+pub fn validate_config(document: &serde_yaml::Value) -> Result<(), String> {
+    let document = serde_json::to_value(document).map_err(|e| e.to_string())?;
+    check(config_schema(), "config", &document)
+}
+
+/// Check a `--format json` report against the report schema
+pub fn validate_report(report: &Value) -> Result<(), String> {
+    check(report_schema(), "report", report)
+}
+
+fn check(schema: RootSchema, name: &str, document: &Value) -> Result<(), String> {
+    let schema = serde_json::to_value(schema).map_err(|e| e.to_string())?;
+    let mut errors = Vec::new();
+    validate(&schema, name, &schema, document, "", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// A small validator covering the subset of JSON Schema that schemars emits for our types.
+/// Errors name the value by key path, or by `name` for the whole document.
+fn validate(root: &Value, name: &str, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let here = if path.is_empty() { name } else { path };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if let Some(target) = resolve(root, reference) {
+            validate(root, name, target, value, path, errors);
+        }
+        return;
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(key).and_then(Value::as_array) {
+            let matches = options.iter().any(|option| {
+                let mut option_errors = Vec::new();
+                validate(root, name, option, value, path, &mut option_errors);
+                option_errors.is_empty()
+            });
+            if !matches {
+                errors.push(format!("{}: does not match any allowed shape", here));
+            }
+        }
+    }
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for option in all {
+            validate(root, name, option, value, path, errors);
+        }
+    }
+
+    if let Some(types) = schema.get("type") {
+        let allowed: Vec<&str> = match types {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+            errors.push(format!("{}: expected {}", here, allowed.join(" or ")));
+            return;
+        }
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array)
+        && !values.contains(value)
+    {
+        let names: Vec<String> = values.iter().map(Value::to_string).collect();
+        errors.push(format!("{}: expected one of {}", here, names.join(", ")));
+    }
+
+    match value {
+        Value::Object(object) => validate_object(root, name, schema, object, path, errors),
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate(root, name, item_schema, item, &format!("{}[{}]", here, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn validate_object(
+    root: &Value,
+    name: &str,
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let child = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                errors.push(format!("{}: missing", child(key)));
+            }
+        }
+    }
+    for (key, value) in object {
+        match properties.and_then(|p| p.get(key)) {
+            Some(property) => validate(root, name, property, value, &child(key), errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => errors.push(format!("{}: unknown key", child(key))),
+                Some(additional @ Value::Object(_)) => validate(root, name, additional, value, &child(key), errors),
+                _ => {}
+            },
+        }
+    }
+}
+
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
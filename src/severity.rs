@@ -0,0 +1,293 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Advisory severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// A provider's severity label; GitHub calls medium "moderate"
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.to_ascii_lowercase().as_str() {
+            "low" => Some(Severity::Low),
+            "medium" | "moderate" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Severity::from_label(value).ok_or_else(|| format!("unknown severity {} (expected low, moderate, high or critical)", value))
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// CVSS score bands per severity, in tenths of a point so boundaries compare exactly.
+///
+/// Written in config as `severity_mapping: {critical: ">=7.0", high: ">=4.0", ...}`, or with
+/// explicit inclusive ranges such as `high: "4.0-6.9"`. A `>=` bound runs up to the next more
+/// severe band. The bands must cover 0.0 to 10.0 without gaps or overlaps, which is checked when
+/// the config is loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct SeverityMapping {
+    /// `(severity, lowest score, highest score)`, least severe first
+    bands: Vec<(Severity, u8, u8)>,
+    source: BTreeMap<String, String>,
+}
+
+impl Default for SeverityMapping {
+    /// The CVSS v3 qualitative ratings, with 0.0 counted as low
+    fn default() -> Self {
+        let source: BTreeMap<String, String> = [("critical", ">=9.0"), ("high", ">=7.0"), ("medium", ">=4.0"), ("low", ">=0.0")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        SeverityMapping::try_from(source).expect("default severity mapping is valid")
+    }
+}
+
+impl SeverityMapping {
+    /// The severity a CVSS score falls into
+    pub fn classify(&self, score: f64) -> Severity {
+        let tenths = (score.clamp(0.0, 10.0) * 10.0).round() as u8;
+        self.bands
+            .iter()
+            .find(|(_, low, high)| (*low..=*high).contains(&tenths))
+            .map_or(Severity::Critical, |(severity, _, _)| *severity)
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for SeverityMapping {
+    type Error = String;
+
+    fn try_from(source: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        enum Bound {
+            AtLeast(u8),
+            Range(u8, u8),
+        }
+
+        let mut bounds = Vec::new();
+        for (name, spec) in &source {
+            let severity = Severity::from_label(name)
+                .ok_or_else(|| format!("severity_mapping: unknown severity {}", name))?;
+            let spec = spec.trim();
+            let bound = match spec.strip_prefix(">=") {
+                Some(low) => Bound::AtLeast(parse_score(low)?),
+                None => {
+                    let (low, high) = spec
+                        .split_once('-')
+                        .ok_or_else(|| format!("severity_mapping: {} must be \">=X\" or \"X-Y\", got {}", name, spec))?;
+                    let (low, high) = (parse_score(low)?, parse_score(high)?);
+                    if low > high {
+                        return Err(format!("severity_mapping: {} range {} is empty", name, spec));
+                    }
+                    Bound::Range(low, high)
+                }
+            };
+            bounds.push((severity, bound));
+        }
+        bounds.sort_by_key(|(severity, _)| *severity);
+
+        // Resolve `>=` bounds against the next more severe band, most severe first
+        let mut bands: Vec<(Severity, u8, u8)> = Vec::new();
+        for (severity, bound) in bounds.iter().rev() {
+            let (low, high) = match (bound, bands.last()) {
+                (Bound::AtLeast(low), None) => (*low, 100),
+                (Bound::AtLeast(low), Some((above, above_low, _))) => {
+                    if low >= above_low {
+                        return Err(format!(
+                            "severity_mapping: {} starts at {} but {} already covers scores from {}",
+                            severity,
+                            tenths(*low),
+                            above,
+                            tenths(*above_low)
+                        ));
+                    }
+                    (*low, above_low - 1)
+                }
+                (Bound::Range(low, high), _) => (*low, *high),
+            };
+            bands.push((*severity, low, high));
+        }
+        bands.reverse();
+
+        // Least severe first: each band must start right after the one below it ends
+        let mut next = 0;
+        for (severity, low, high) in &bands {
+            if *low > next {
+                return Err(format!(
+                    "severity_mapping: scores {} to {} map to no severity (gap below {})",
+                    tenths(next),
+                    tenths(*low - 1),
+                    severity
+                ));
+            }
+            if *low < next {
+                return Err(format!(
+                    "severity_mapping: {} overlaps the band below it at {}",
+                    severity,
+                    tenths(*low)
+                ));
+            }
+            next = high + 1;
+        }
+        if next <= 100 {
+            return Err(format!("severity_mapping: scores {} to 10.0 map to no severity", tenths(next)));
+        }
+        Ok(SeverityMapping { bands, source })
+    }
+}
+
+impl From<SeverityMapping> for BTreeMap<String, String> {
+    fn from(mapping: SeverityMapping) -> Self {
+        mapping.source
+    }
+}
+
+/// Parse a CVSS score with at most one decimal into tenths
+fn parse_score(value: &str) -> Result<u8, String> {
+    let value = value.trim();
+    let invalid = || format!("severity_mapping: {} is not a CVSS score between 0.0 and 10.0", value);
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, "0"));
+    if fraction.len() != 1 {
+        return Err(invalid());
+    }
+    let whole: u8 = whole.parse().map_err(|_| invalid())?;
+    let fraction: u8 = fraction.parse().map_err(|_| invalid())?;
+    let score = whole.checked_mul(10).and_then(|w| w.checked_add(fraction)).ok_or_else(invalid)?;
+    if score > 100 {
+        return Err(invalid());
+    }
+    Ok(score)
+}
+
+fn tenths(value: u8) -> String {
+    format!("{}.{}", value / 10, value % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(bands: &[(&str, &str)]) -> Result<SeverityMapping, String> {
+        SeverityMapping::try_from(bands.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<_, _>>())
+    }
+
+    #[test]
+    fn the_default_mapping_follows_the_cvss_ratings_at_every_boundary() {
+        let mapping = SeverityMapping::default();
+        for (score, severity) in [
+            (0.0, Severity::Low),
+            (3.9, Severity::Low),
+            (4.0, Severity::Medium),
+            (6.9, Severity::Medium),
+            (7.0, Severity::High),
+            (8.9, Severity::High),
+            (9.0, Severity::Critical),
+            (10.0, Severity::Critical),
+        ] {
+            assert_eq!(mapping.classify(score), severity, "{}", score);
+        }
+    }
+
+    #[test]
+    fn a_stricter_table_moves_the_boundaries() {
+        let mapping = table(&[("critical", ">=7.0"), ("high", ">=4.0"), ("moderate", ">=2.0"), ("low", ">=0.0")]).unwrap();
+        for (score, severity) in [
+            (1.9, Severity::Low),
+            (2.0, Severity::Medium),
+            (3.9, Severity::Medium),
+            (4.0, Severity::High),
+            (6.9, Severity::High),
+            (7.0, Severity::Critical),
+        ] {
+            assert_eq!(mapping.classify(score), severity, "{}", score);
+        }
+    }
+
+    #[test]
+    fn scores_are_compared_in_tenths_and_clamped() {
+        let mapping = SeverityMapping::default();
+        // Off-grid scores round to the nearest tenth before they are placed in a band
+        assert_eq!(mapping.classify(6.94), Severity::Medium);
+        assert_eq!(mapping.classify(6.95), Severity::High);
+        assert_eq!(mapping.classify(-1.0), Severity::Low);
+        assert_eq!(mapping.classify(11.0), Severity::Critical);
+    }
+
+    #[test]
+    fn explicit_ranges_and_missing_severities_are_allowed_when_they_cover_every_score() {
+        let mapping = table(&[("critical", "7.0-10.0"), ("high", "0.0-6.9")]).unwrap();
+        assert_eq!(mapping.classify(6.9), Severity::High);
+        assert_eq!(mapping.classify(7.0), Severity::Critical);
+        let mixed = table(&[("critical", ">=9.5"), ("high", "5.0-9.4"), ("low", ">=0.0")]).unwrap();
+        assert_eq!((mixed.classify(4.9), mixed.classify(5.0), mixed.classify(9.5)), (Severity::Low, Severity::High, Severity::Critical));
+    }
+
+    #[test]
+    fn gaps_and_overlaps_are_rejected() {
+        for (bands, error) in [
+            (&[("critical", ">=9.0"), ("high", ">=7.0")][..], "severity_mapping: scores 0.0 to 6.9 map to no severity (gap below high)"),
+            (&[("critical", "9.0-9.9"), ("low", ">=0.0")][..], "severity_mapping: scores 10.0 to 10.0 map to no severity"),
+            (&[("high", "0.0-6.9"), ("critical", "7.5-10.0")][..], "severity_mapping: scores 7.0 to 7.4 map to no severity (gap below critical)"),
+            (&[("high", "0.0-7.0"), ("critical", "7.0-10.0")][..], "severity_mapping: critical overlaps the band below it at 7.0"),
+            (&[("critical", "5.0-10.0"), ("high", ">=6.0"), ("low", ">=0.0")][..], "severity_mapping: high starts at 6.0 but critical already covers scores from 5.0"),
+            (&[("critical", ">=9.0"), ("high", ">=9.0"), ("low", ">=0.0")][..], "severity_mapping: high starts at 9.0 but critical already covers scores from 9.0"),
+        ] {
+            assert_eq!(table(bands).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        for (bands, error) in [
+            (&[("severe", ">=0.0")][..], "severity_mapping: unknown severity severe"),
+            (&[("low", "~0.0")][..], "severity_mapping: low must be \">=X\" or \"X-Y\", got ~0.0"),
+            (&[("low", "5.0-4.0")][..], "severity_mapping: low range 5.0-4.0 is empty"),
+            (&[("low", ">=10.1")][..], "severity_mapping: 10.1 is not a CVSS score between 0.0 and 10.0"),
+            (&[("low", ">=1.25")][..], "severity_mapping: 1.25 is not a CVSS score between 0.0 and 10.0"),
+            (&[("low", ">=x")][..], "severity_mapping: x is not a CVSS score between 0.0 and 10.0"),
+        ] {
+            assert_eq!(table(bands).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn the_config_form_round_trips() {
+        let parsed: SeverityMapping = serde_yaml::from_str("critical: \">=7.0\"\nhigh: \">=4.0\"\nmedium: \">=0.1\"\nlow: 0.0-0.0\n").unwrap();
+        assert_eq!(parsed.classify(0.0), Severity::Low);
+        assert_eq!(parsed.classify(0.1), Severity::Medium);
+        let written = serde_yaml::to_string(&parsed).unwrap();
+        assert_eq!(serde_yaml::from_str::<SeverityMapping>(&written).unwrap(), parsed);
+        let error = serde_yaml::from_str::<SeverityMapping>("critical: \">=7.0\"\n").unwrap_err().to_string();
+        assert!(error.contains("scores 0.0 to 6.9 map to no severity"), "{}", error);
+    }
+}
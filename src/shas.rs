@@ -0,0 +1,118 @@
+use crate::{ActionRef, RefKind};
+use crate::cache::{Cached, DiskCache};
+use crate::fix;
+use crate::github::GitHubApi;
+use crate::report::{Finding, Level, Rule};
+use crate::unreleased;
+use serde_json::{Value, json};
+
+/// How many tags a commit on no branch is compared against before it counts as unreachable
+const TAGS_COMPARED: usize = 20;
+
+/// Whether the repository has the commit. Commits never change, so a cached answer is final. A
+/// commit pushed to a fork is served through the parent too, so existing does not mean belonging.
+async fn exists(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<bool, std::io::Error> {
+    let key = format!("{}@{}", action_ref.full_name(), action_ref.version);
+    match cache.get("commits", &key) {
+        Cached::Hit(_) => return Ok(true),
+        Cached::NegativeHit => return Ok(false),
+        Cached::Miss => {}
+    }
+    let path = format!("/repos/{}/commits/{}", action_ref.full_name(), action_ref.version);
+    match api.get(&path).await? {
+        Some(commit) => {
+            cache.put("commits", &key, &json!({ "sha": commit["sha"] }));
+            Ok(true)
+        }
+        None => {
+            cache.put_negative("commits", &key);
+            Ok(false)
+        }
+    }
+}
+
+/// Names and head commits of the repository's branches or tags (`kind`), first page only
+pub async fn refs(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef, kind: &str) -> Result<Vec<(String, String)>, std::io::Error> {
+    let key = format!("{}/{}", action_ref.full_name(), kind);
+    let listed = match cache.get("refs", &key) {
+        Cached::Hit(value) => value,
+        Cached::NegativeHit => Value::Null,
+        Cached::Miss => {
+            let listed = api.get(&format!("/repos/{}/{}?per_page=100", action_ref.full_name(), kind)).await?.unwrap_or(Value::Null);
+            if listed.as_array().is_none_or(|l| l.is_empty()) {
+                cache.put_negative("refs", &key);
+            } else {
+                cache.put("refs", &key, &listed);
+            }
+            listed
+        }
+    };
+    Ok(listed
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| Some((r["name"].as_str()?.to_string(), r["commit"]["sha"].as_str()?.to_string())))
+        .collect())
+}
+
+/// Whether a branch or one of the newest tags contains the commit
+async fn reachable(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<bool, std::io::Error> {
+    let branches = refs(api, cache, action_ref, "branches").await?;
+    let tags = refs(api, cache, action_ref, "tags").await?;
+    let sha = action_ref.version.to_ascii_lowercase();
+    if branches.iter().chain(&tags).any(|(_, head)| head.eq_ignore_ascii_case(&sha)) {
+        return Ok(true);
+    }
+    let candidates = branches.iter().chain(tags.iter().take(TAGS_COMPARED));
+    for (name, _) in candidates {
+        if unreleased::compare(api, cache, action_ref, name).await?.is_some_and(|c| c.released) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The fix for a bad SHA pin: the latest release tag and its commit, when there is one
+async fn suggestion(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> String {
+    let generic = "; re-pin to the SHA of the intended release tag".to_string();
+    let Ok(tags) = unreleased::release_tags(api, cache, action_ref).await else {
+        return generic;
+    };
+    let Some(latest) = tags.first() else {
+        return generic;
+    };
+    let tag_ref = ActionRef {
+        version: latest.clone(),
+        kind: RefKind::Tag,
+        ..action_ref.clone()
+    };
+    match fix::resolve_tag_sha_cached(api, cache, &tag_ref).await {
+        Ok(Some(sha)) => format!("{}, e.g. {}@{} # {} for the latest release", generic, action_ref.full_name(), sha, latest),
+        _ => generic,
+    }
+}
+
+/// `--verify-shas`: flag a SHA pin whose commit the repository does not have, or has only through
+/// its fork network, where anyone with a fork can push one
+pub async fn verify(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    action: &str,
+    action_ref: &ActionRef,
+) -> Result<Option<Finding>, std::io::Error> {
+    let (rule, problem) = if !exists(api, cache, action_ref).await? {
+        (Rule::MissingCommit, format!("does not exist in {}", action_ref.full_name()))
+    } else if !reachable(api, cache, action_ref).await? {
+        (Rule::UnreachableCommit, format!("is on no branch or recent tag of {}, as a commit pushed to a fork would be", action_ref.full_name()))
+    } else {
+        return Ok(None);
+    };
+    let message = format!(
+        "{} is pinned to commit {}, which {}{}",
+        action,
+        &action_ref.version[..action_ref.version.len().min(12)],
+        problem,
+        suggestion(api, cache, action_ref).await
+    );
+    Ok(Some(Finding::new(rule, Level::Error, action, message)))
+}
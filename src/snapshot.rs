@@ -0,0 +1,308 @@
+//! Submission of the actions inventory to GitHub's dependency graph, so Dependabot can alert on
+//! actions the way it does for package manifests.
+
+use crate::clock::Clock;
+use crate::github::{GitHubApi, api_error};
+use reqwest::{Method, StatusCode};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The run a snapshot is submitted from, as the Actions runner describes it
+pub struct RunContext {
+    /// Repository in `owner/repo` form
+    pub repo: String,
+    pub sha: String,
+    pub git_ref: String,
+    pub run_id: String,
+    pub job: String,
+}
+
+impl RunContext {
+    /// Read the context from the runner's environment, naming the first variable that is missing
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| format!("--submit-dependency-snapshot needs {} (set by GitHub Actions)", name))
+        };
+        Ok(RunContext {
+            repo: var("GITHUB_REPOSITORY")?,
+            sha: var("GITHUB_SHA")?,
+            git_ref: var("GITHUB_REF")?,
+            run_id: var("GITHUB_RUN_ID")?,
+            job: var("GITHUB_JOB")?,
+        })
+    }
+
+    /// Snapshots with the same correlator replace each other, so each job keeps its own
+    fn correlator(&self) -> String {
+        format!("{}-{}", env!("CARGO_PKG_NAME"), self.job)
+    }
+}
+
+/// Percent-encode a purl version component; branch names may contain `/`
+fn encode_version(version: &str) -> String {
+    let mut encoded = String::with_capacity(version.len());
+    for byte in version.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The package URL for a `uses:` value, with the pinned ref as its version. Local and Docker
+/// actions are not packages the dependency graph knows.
+pub fn package_url(action: &str) -> Option<String> {
+    if action.starts_with("./") || action.starts_with("docker://") {
+        return None;
+    }
+    let (path, version) = action.split_once('@')?;
+    let mut parts = path.splitn(3, '/');
+    let (owner, repo) = (parts.next()?, parts.next()?);
+    if owner.is_empty() || repo.is_empty() || version.is_empty() {
+        return None;
+    }
+    let purl = format!("pkg:githubactions/{}/{}@{}", owner, repo, encode_version(version));
+    Some(match parts.next() {
+        Some(subpath) => format!("{}#{}", purl, subpath),
+        None => purl,
+    })
+}
+
+/// The snapshot payload: one manifest per workflow file, resolving each action it uses
+pub fn payload(context: &RunContext, clock: &Clock, workflows: &BTreeMap<String, BTreeSet<String>>) -> Value {
+    let mut manifests = Map::new();
+    for (path, actions) in workflows {
+        let resolved: Map<String, Value> = actions
+            .iter()
+            .filter_map(|action| package_url(action))
+            .map(|purl| {
+                let package = json!({ "package_url": purl, "relationship": "direct", "scope": "runtime" });
+                (purl, package)
+            })
+            .collect();
+        if resolved.is_empty() {
+            continue;
+        }
+        manifests.insert(
+            path.clone(),
+            json!({ "name": path, "file": { "source_location": path }, "resolved": resolved }),
+        );
+    }
+    json!({
+        "version": 0,
+        "sha": context.sha,
+        "ref": context.git_ref,
+        "job": { "correlator": context.correlator(), "id": context.run_id },
+        "detector": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "url": env!("CARGO_PKG_REPOSITORY"),
+        },
+        "scanned": clock.rfc3339(),
+        "manifests": manifests,
+    })
+}
+
+pub enum Submission {
+    /// The snapshot id and the API's processing result
+    Accepted(u64, String),
+    /// The repository cannot take submissions: the dependency graph is off, or the token lacks
+    /// `contents: write`
+    Unavailable(String),
+}
+
+/// Submit the snapshot through `api`, which needs a token with `contents: write`
+pub async fn submit(api: &GitHubApi<'_>, context: &RunContext, payload: &Value) -> Result<Submission, std::io::Error> {
+    let path = format!("/repos/{}/dependency-graph/snapshots", context.repo);
+    match api.post(&path, payload).await? {
+        (status, value) if status.is_success() => Ok(Submission::Accepted(
+            value["id"].as_u64().unwrap_or(0),
+            value["result"].as_str().unwrap_or("ACCEPTED").to_string(),
+        )),
+        (StatusCode::FORBIDDEN | StatusCode::NOT_FOUND, value) => Ok(Submission::Unavailable(
+            value["message"].as_str().unwrap_or("no message").to_string(),
+        )),
+        (status, value) => Err(api_error(&Method::POST, &path, status, &value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::Endpoints;
+    use crate::http::{HttpClient, HttpRequest, HttpResponse, ResponseFuture};
+    use std::sync::Mutex;
+
+    fn context() -> RunContext {
+        RunContext {
+            repo: "acme/app".to_string(),
+            sha: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            git_ref: "refs/heads/main".to_string(),
+            run_id: "4242".to_string(),
+            job: "scan".to_string(),
+        }
+    }
+
+    fn clock() -> Clock {
+        Clock::Fixed(chrono::DateTime::parse_from_rfc3339("2024-01-02T03:04:05Z").unwrap().with_timezone(&chrono::Utc))
+    }
+
+    fn inventory(workflows: &[(&str, &[&str])]) -> BTreeMap<String, BTreeSet<String>> {
+        workflows
+            .iter()
+            .map(|(path, actions)| (path.to_string(), actions.iter().map(|a| a.to_string()).collect()))
+            .collect()
+    }
+
+    /// What the dependency submission API documents as required of a snapshot, as a list of the
+    /// ways `payload` breaks it
+    fn schema_errors(payload: &Value) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut expect = |ok: bool, what: &str| {
+            if !ok {
+                errors.push(what.to_string());
+            }
+        };
+        expect(payload["version"].is_u64(), "version is an integer");
+        expect(payload["sha"].as_str().is_some_and(|sha| sha.len() == 40), "sha is a 40-character commit");
+        expect(payload["ref"].as_str().is_some_and(|r| r.starts_with("refs/")), "ref is a fully qualified ref");
+        expect(payload["job"]["correlator"].is_string(), "job.correlator is a string");
+        expect(payload["job"]["id"].is_string(), "job.id is a string");
+        for key in ["name", "version", "url"] {
+            expect(payload["detector"][key].is_string(), &format!("detector.{} is a string", key));
+        }
+        expect(
+            payload["scanned"].as_str().is_some_and(|s| chrono::DateTime::parse_from_rfc3339(s).is_ok()),
+            "scanned is an ISO 8601 time",
+        );
+        let manifests = payload["manifests"].as_object();
+        expect(manifests.is_some(), "manifests is an object");
+        for (key, manifest) in manifests.into_iter().flatten() {
+            expect(manifest["name"] == key.as_str(), &format!("manifests.{}.name is its key", key));
+            expect(manifest["file"]["source_location"].is_string(), &format!("manifests.{}.file.source_location is a string", key));
+            let resolved = manifest["resolved"].as_object();
+            expect(resolved.is_some(), &format!("manifests.{}.resolved is an object", key));
+            for (name, package) in resolved.into_iter().flatten() {
+                let what = format!("manifests.{}.resolved.{}", key, name);
+                expect(package["package_url"].as_str().is_some_and(|p| p.starts_with("pkg:")), &format!("{} has a package_url", what));
+                expect(matches!(package["relationship"].as_str(), Some("direct" | "indirect")), &format!("{} has a relationship", what));
+                expect(matches!(package["scope"].as_str(), Some("runtime" | "development")), &format!("{} has a scope", what));
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn package_urls_carry_the_pinned_ref() {
+        for (action, purl) in [
+            ("actions/checkout@v4", Some("pkg:githubactions/actions/checkout@v4")),
+            ("acme/tool@0123456789abcdef0123456789abcdef01234567", Some("pkg:githubactions/acme/tool@0123456789abcdef0123456789abcdef01234567")),
+            ("github/codeql-action/init@v3", Some("pkg:githubactions/github/codeql-action@v3#init")),
+            ("acme/monorepo/actions/deploy@v1", Some("pkg:githubactions/acme/monorepo@v1#actions/deploy")),
+            ("acme/tool@releases/v1", Some("pkg:githubactions/acme/tool@releases%2Fv1")),
+            ("acme/tool@v1+build", Some("pkg:githubactions/acme/tool@v1%2Bbuild")),
+            ("./.github/actions/local", None),
+            ("docker://alpine:3.19", None),
+            ("acme/tool", None),
+            ("acme/tool@", None),
+            ("acme@v1", None),
+        ] {
+            assert_eq!(package_url(action).as_deref(), purl, "{}", action);
+        }
+    }
+
+    #[test]
+    fn the_payload_follows_the_documented_schema() {
+        let workflows = inventory(&[
+            (".github/workflows/ci.yml", &["actions/checkout@v4", "github/codeql-action/init@v3"]),
+            (".github/workflows/release.yml", &["acme/tool@releases/v1", "./.github/actions/local"]),
+        ]);
+        let payload = payload(&context(), &clock(), &workflows);
+        assert_eq!(schema_errors(&payload), Vec::<String>::new(), "{:#}", payload);
+        assert_eq!(payload["sha"], "0123456789abcdef0123456789abcdef01234567");
+        assert_eq!(payload["ref"], "refs/heads/main");
+        assert_eq!(payload["job"], json!({ "correlator": "gh-action-security-scanner-scan", "id": "4242" }));
+        assert_eq!(payload["scanned"], "2024-01-02T03:04:05Z");
+        assert_eq!(payload["detector"]["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn manifests_are_keyed_by_workflow_file_with_one_entry_per_package() {
+        let workflows = inventory(&[
+            (".github/workflows/ci.yml", &["actions/checkout@v4", "actions/checkout@v4", "docker://alpine:3.19"]),
+            (".github/workflows/local.yml", &["./.github/actions/local", "docker://alpine:3.19"]),
+        ]);
+        let manifests = payload(&context(), &clock(), &workflows)["manifests"].clone();
+        // A workflow using no package gets no manifest
+        let expected = json!({
+            ".github/workflows/ci.yml": {
+                "name": ".github/workflows/ci.yml",
+                "file": { "source_location": ".github/workflows/ci.yml" },
+                "resolved": {
+                    "pkg:githubactions/actions/checkout@v4": {
+                        "package_url": "pkg:githubactions/actions/checkout@v4",
+                        "relationship": "direct",
+                        "scope": "runtime",
+                    },
+                },
+            },
+        });
+        assert_eq!(manifests, expected);
+        assert_eq!(payload(&context(), &clock(), &BTreeMap::new())["manifests"], json!({}));
+    }
+
+    /// Answers every request with one response and keeps the requests it was sent
+    struct Answer {
+        status: u16,
+        body: Value,
+        sent: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl HttpClient for Answer {
+        fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+            self.sent.lock().unwrap().push(request);
+            let response = HttpResponse { status: self.status, headers: BTreeMap::new(), body: self.body.to_string() };
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    async fn submitted(status: u16, body: Value) -> (Result<Submission, std::io::Error>, Vec<HttpRequest>) {
+        let client = Answer { status, body, sent: Mutex::new(Vec::new()) };
+        let endpoints = Endpoints::default();
+        let api = GitHubApi::new(&client, &endpoints, Some("token"));
+        let payload = payload(&context(), &clock(), &inventory(&[(".github/workflows/ci.yml", &["actions/checkout@v4"])]));
+        let result = submit(&api, &context(), &payload).await;
+        (result, client.sent.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn an_accepted_snapshot_reports_its_id() {
+        let (result, sent) = submitted(201, json!({ "id": 21, "result": "SUCCESS", "created_at": "2024-01-02T03:04:06Z" })).await;
+        let Ok(Submission::Accepted(id, result)) = result else { panic!("not accepted") };
+        assert_eq!((id, result.as_str()), (21, "SUCCESS"));
+        let [request] = sent.as_slice() else { panic!("{:?}", sent) };
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.url, "https://api.github.com/repos/acme/app/dependency-graph/snapshots");
+        assert_eq!(request.body.as_ref().unwrap()["job"]["correlator"], "gh-action-security-scanner-scan");
+    }
+
+    #[tokio::test]
+    async fn a_repository_without_the_feature_is_not_an_error() {
+        for status in [403, 404] {
+            let (result, _) = submitted(status, json!({ "message": "Dependency graph is disabled" })).await;
+            let Ok(Submission::Unavailable(reason)) = result else { panic!("{} is not unavailable", status) };
+            assert_eq!(reason, "Dependency graph is disabled");
+        }
+    }
+
+    #[tokio::test]
+    async fn other_failures_are_errors() {
+        let (result, _) = submitted(422, json!({ "message": "Invalid request" })).await;
+        let error = result.err().expect("422 is an error").to_string();
+        assert!(error.contains("422"), "{}", error);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::artifacts::PINNING;
+use crate::http::{HttpClient, HttpRequest, ResponseFuture};
+use crate::report::{Category, Rule, ScanReport};
+use crate::transitive::Nested;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Counts every request sent through `inner`, fixture replays included, for the summary
+pub struct CountRequests {
+    inner: Box<dyn HttpClient>,
+    requests: AtomicUsize,
+}
+
+impl CountRequests {
+    pub fn new(inner: Box<dyn HttpClient>) -> Self {
+        CountRequests { inner, requests: AtomicUsize::new(0) }
+    }
+
+    /// Requests sent so far
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+
+impl HttpClient for CountRequests {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.inner.send(request)
+    }
+}
+
+/// Findings by the groups the summary reports, suppressed ones left out
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct FindingCounts {
+    pub vulnerable: usize,
+    pub insecure_pinning: usize,
+    pub untrusted_owner: usize,
+    /// References that are not `owner/repo@ref`, and workflows that could not be read or parsed
+    pub skipped_or_malformed: usize,
+    pub other: usize,
+}
+
+/// The overview at the end of a run, and the `summary` of the JSON and SARIF reports
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Summary {
+    pub workflows_scanned: usize,
+    pub actions_checked: usize,
+    pub findings: FindingCounts,
+    /// Whether every check ran and every action's network checks completed; when not, the scan
+    /// says nothing of the actions in `incomplete` or the checks in `skipped_checks`
+    pub complete: bool,
+    pub incomplete: Vec<String>,
+    pub skipped_checks: Vec<String>,
+    /// `--diff` and `--diff-base`: the base, and the actions skipped as unchanged since it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff_base: Option<String>,
+    pub skipped_unchanged: usize,
+    /// `--transitive`: how far the actions composite actions use reach
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transitive: Option<TransitiveSummary>,
+    pub api_requests: usize,
+    pub cache_hits: usize,
+    pub duration_seconds: f64,
+}
+
+/// Workflow actions named in `top_contributors`, most dependencies first
+const TOP_CONTRIBUTORS: usize = 5;
+
+/// The reach of the composite actions' dependencies
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct TransitiveSummary {
+    /// Actions only composite actions use; `actions_checked` counts them with the workflows' own
+    pub dependencies: usize,
+    /// The most levels of composite actions any dependency is below the workflow
+    pub max_depth: usize,
+    /// The workflows' actions that bring in the most dependencies, with how many
+    pub top_contributors: Vec<Contributor>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Contributor {
+    pub action: String,
+    pub dependencies: usize,
+}
+
+impl TransitiveSummary {
+    pub fn new(nested: &[Nested]) -> Self {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for dependency in nested {
+            *counts.entry(dependency.chain[0].as_str()).or_default() += 1;
+        }
+        let mut top_contributors: Vec<Contributor> =
+            counts.into_iter().map(|(action, dependencies)| Contributor { action: action.to_string(), dependencies }).collect();
+        top_contributors.sort_by(|a, b| b.dependencies.cmp(&a.dependencies).then_with(|| a.action.cmp(&b.action)));
+        top_contributors.truncate(TOP_CONTRIBUTORS);
+        TransitiveSummary {
+            dependencies: nested.len(),
+            max_depth: nested.iter().map(Nested::depth).max().unwrap_or(0),
+            top_contributors,
+        }
+    }
+}
+
+impl Summary {
+    /// The summary of the report, without the request and cache-hit counts only the scanner knows
+    pub fn new(report: &ScanReport, workflows_scanned: usize, duration: Duration) -> Self {
+        let mut findings = FindingCounts::default();
+        for finding in report.findings.iter().filter(|f| !f.is_suppressed()) {
+            let count = match finding.rule {
+                _ if finding.rule.category() == Category::Vulnerability => &mut findings.vulnerable,
+                rule if PINNING.contains(&rule) => &mut findings.insecure_pinning,
+                Rule::UntrustedOwner => &mut findings.untrusted_owner,
+                Rule::MalformedReference | Rule::InvalidWorkflow | Rule::DuplicateKey | Rule::ParseLimit => &mut findings.skipped_or_malformed,
+                _ if finding.rule.category() == Category::Informational => continue,
+                _ => &mut findings.other,
+            };
+            *count += 1;
+        }
+        Summary {
+            workflows_scanned,
+            actions_checked: report.actions_scanned,
+            findings,
+            complete: report.unchecked.is_empty() && report.skipped_checks.is_empty(),
+            incomplete: report.unchecked.clone(),
+            skipped_checks: report.skipped_checks.clone(),
+            diff_base: report.diff.as_ref().map(|diff| diff.base.clone()),
+            skipped_unchanged: report.diff.as_ref().map_or(0, |diff| diff.unchanged_actions.len()),
+            transitive: report.transitive.as_deref().map(TransitiveSummary::new),
+            api_requests: 0,
+            cache_hits: 0,
+            duration_seconds: (duration.as_secs_f64() * 100.0).round() / 100.0,
+        }
+    }
+
+    pub fn print(&self, out: &mut dyn Write) -> io::Result<()> {
+        let counts = &self.findings;
+        writeln!(out, "\nSummary:")?;
+        writeln!(out, "- workflows scanned: {}", self.workflows_scanned)?;
+        writeln!(out, "- actions checked: {}", self.actions_checked)?;
+        writeln!(
+            out,
+            "- findings: {} vulnerable, {} insecure pinning, {} untrusted owner, {} skipped or malformed, {} other",
+            counts.vulnerable, counts.insecure_pinning, counts.untrusted_owner, counts.skipped_or_malformed, counts.other
+        )?;
+        if self.complete {
+            writeln!(out, "- coverage: complete")?;
+        }
+        if !self.skipped_checks.is_empty() {
+            writeln!(out, "- coverage: OFFLINE, local checks only; skipped {}", self.skipped_checks.join(", "))?;
+        }
+        if !self.incomplete.is_empty() {
+            writeln!(
+                out,
+                "- coverage: INCOMPLETE, {} of {} actions could not be checked ({}); the scan says nothing of them",
+                self.incomplete.len(),
+                self.actions_checked,
+                self.incomplete.join(", ")
+            )?;
+        }
+        if let Some(base) = &self.diff_base {
+            writeln!(out, "- skipped as unchanged since {}: {} actions", base, self.skipped_unchanged)?;
+        }
+        if let Some(transitive) = &self.transitive {
+            let top: Vec<String> =
+                transitive.top_contributors.iter().map(|c| format!("{} ({})", c.action, c.dependencies)).collect();
+            writeln!(
+                out,
+                "- composite action dependencies: {}, at most {} below the workflow{}",
+                transitive.dependencies,
+                if transitive.max_depth == 1 { "1 level".to_string() } else { format!("{} levels", transitive.max_depth) },
+                if top.is_empty() { String::new() } else { format!("; most from {}", top.join(", ")) }
+            )?;
+        }
+        writeln!(out, "- API requests: {} sent, {} answered from the cache", self.api_requests, self.cache_hits)?;
+        writeln!(out, "- duration: {:.2}s", self.duration_seconds)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use crate::visibility::Visibility;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+/// Who can start a workflow, from the narrowest to the widest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriggerSurface {
+    /// The schedule and events GitHub raises itself, which no person starts directly
+    Internal,
+    /// People who can push to protected branches or tags, or merge into them
+    Maintainers,
+    /// People with access to the repository: pushing branches, triaging, dispatching
+    Contributors,
+    /// Any GitHub user: pull requests from forks, comments, issues
+    Anyone,
+}
+
+impl TriggerSurface {
+    pub fn name(self) -> &'static str {
+        match self {
+            TriggerSurface::Internal => "internal",
+            TriggerSurface::Maintainers => "maintainers",
+            TriggerSurface::Contributors => "contributors",
+            TriggerSurface::Anyone => "anyone",
+        }
+    }
+}
+
+impl std::fmt::Display for TriggerSurface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A workflow's trigger surface and the events that set it
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Surface {
+    pub surface: TriggerSurface,
+    /// Each trigger with the surface it opens, e.g. `pull_request_target: anyone`
+    pub triggers: Vec<String>,
+}
+
+impl Surface {
+    /// One line for reports, naming the triggers that reach the widest surface
+    pub fn describe(&self) -> String {
+        let widest: Vec<&str> = self
+            .triggers
+            .iter()
+            .filter(|t| t.ends_with(&format!(": {}", self.surface)))
+            .filter_map(|t| t.split(": ").next())
+            .collect();
+        if widest.is_empty() {
+            self.surface.to_string()
+        } else {
+            format!("{} ({})", self.surface, widest.join(", "))
+        }
+    }
+}
+
+/// Activity types only someone with triage access can cause: labels, assignees, milestones, pins
+/// and locks, review requests and the merge queue
+const TRIAGE_TYPES: [&str; 20] = [
+    "labeled",
+    "unlabeled",
+    "assigned",
+    "unassigned",
+    "milestoned",
+    "demilestoned",
+    "pinned",
+    "unpinned",
+    "locked",
+    "unlocked",
+    "transferred",
+    "category_changed",
+    "review_requested",
+    "review_request_removed",
+    "auto_merge_enabled",
+    "auto_merge_disabled",
+    "enqueued",
+    "dequeued",
+    "answered",
+    "unanswered",
+];
+
+/// Events GitHub raises on its own schedule or for its own checks
+const INTERNAL_EVENTS: [&str; 5] = ["schedule", "status", "check_run", "check_suite", "page_build"];
+
+/// Events of other people's activity: forks, comments, issues, reviews and stars
+const PUBLIC_EVENTS: [&str; 8] = [
+    "pull_request",
+    "pull_request_target",
+    "pull_request_review",
+    "pull_request_review_comment",
+    "issue_comment",
+    "issues",
+    "discussion",
+    "discussion_comment",
+];
+
+/// Branch names assumed to be protected, which only maintainers push to
+fn is_protected_branch(pattern: &str) -> bool {
+    matches!(pattern, "main" | "master") || pattern.starts_with("release")
+}
+
+/// The strings of a filter such as `types:` or `branches:`, written as one string or a list
+fn filter<'v>(config: Option<&'v Value>, key: &str) -> Option<Vec<&'v str>> {
+    match config?.get(key)? {
+        Value::String(value) => Some(vec![value.as_str()]),
+        Value::Sequence(values) => Some(values.iter().filter_map(|v| v.as_str()).collect()),
+        _ => None,
+    }
+}
+
+/// The surface one trigger opens, given its config (`None` for the string and list forms of `on:`)
+fn classify_event(event: &str, config: Option<&Value>) -> TriggerSurface {
+    if PUBLIC_EVENTS.contains(&event) {
+        // Branch and path filters select the base branch and the changed files, both of which a
+        // fork pull request chooses, so only `types:` can narrow who triggers these
+        return match filter(config, "types") {
+            Some(types) if !types.is_empty() && types.iter().all(|t| TRIAGE_TYPES.contains(t)) => TriggerSurface::Contributors,
+            _ => TriggerSurface::Anyone,
+        };
+    }
+    if INTERNAL_EVENTS.contains(&event) {
+        return TriggerSurface::Internal;
+    }
+    match event {
+        // Starring and forking need no access at all
+        "fork" | "watch" => TriggerSurface::Anyone,
+        // These run with whatever triggered the workflows they follow or are called from,
+        // which this scan does not trace, so they are assumed reachable from fork pull requests
+        "workflow_run" | "workflow_call" => TriggerSurface::Anyone,
+        "merge_group" => TriggerSurface::Maintainers,
+        "push" => {
+            let branches = filter(config, "branches");
+            let tags = filter(config, "tags");
+            let negated = config.is_some_and(|c| c.get("branches-ignore").is_some() || c.get("tags-ignore").is_some());
+            let protected = match (&branches, &tags) {
+                (Some(branches), _) => !branches.is_empty() && branches.iter().all(|b| is_protected_branch(b)),
+                (None, Some(_)) => true,
+                (None, None) => false,
+            };
+            if protected && !negated { TriggerSurface::Maintainers } else { TriggerSurface::Contributors }
+        }
+        _ => TriggerSurface::Contributors,
+    }
+}
+
+/// Classify the workflow's `on:` block, in its string, list and map forms. In a private
+/// repository only members can fork, comment or open issues, so nothing reaches past
+/// contributors. A workflow with no triggers is internal.
+pub fn classify(workflow: &Value, visibility: Visibility) -> Surface {
+    let events: Vec<(&str, Option<&Value>)> = match workflow.get("on") {
+        Some(Value::String(event)) => vec![(event.as_str(), None)],
+        Some(Value::Sequence(events)) => events.iter().filter_map(|e| Some((e.as_str()?, None))).collect(),
+        Some(Value::Mapping(events)) => events.iter().filter_map(|(e, config)| Some((e.as_str()?, Some(config)))).collect(),
+        _ => Vec::new(),
+    };
+    let triggers: Vec<(&str, TriggerSurface)> = events
+        .into_iter()
+        .map(|(event, config)| {
+            let surface = classify_event(event, config);
+            let surface = if visibility == Visibility::Private { surface.min(TriggerSurface::Contributors) } else { surface };
+            (event, surface)
+        })
+        .collect();
+    Surface {
+        surface: triggers.iter().map(|(_, s)| *s).max().unwrap_or(TriggerSurface::Internal),
+        triggers: triggers.into_iter().map(|(event, surface)| format!("{}: {}", event, surface)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface(on: &str, visibility: Visibility) -> Surface {
+        classify(&serde_yaml::from_str(&format!("on: {}\njobs: {{}}\n", on)).unwrap(), visibility)
+    }
+
+    #[test]
+    fn every_trigger_form_is_classified() {
+        use TriggerSurface::*;
+        for (on, expected) in [
+            // String and list forms
+            ("push", Contributors),
+            ("pull_request", Anyone),
+            ("schedule", Internal),
+            ("[schedule, workflow_dispatch]", Contributors),
+            ("[push, pull_request_target]", Anyone),
+            ("[check_run, status]", Internal),
+            ("[fork]", Anyone),
+            ("workflow_run", Anyone),
+            ("merge_group", Maintainers),
+            // Map form with branch, tag and path filters
+            ("{push: {branches: [main, release/*]}}", Maintainers),
+            ("{push: {branches: main}}", Maintainers),
+            ("{push: {branches: [main, feature/*]}}", Contributors),
+            ("{push: {branches: []}}", Contributors),
+            ("{push: {tags: ['v*']}}", Maintainers),
+            ("{push: {branches: [main], branches-ignore: [main-old]}}", Contributors),
+            ("{push: {tags-ignore: ['v*']}}", Contributors),
+            ("{push: {paths: [src/**]}}", Contributors),
+            ("{push: null}", Contributors),
+            ("{schedule: [{cron: '0 0 * * *'}]}", Internal),
+            // A fork chooses the base branch and the changed files, so those filters don't narrow
+            ("{pull_request: {branches: [main]}}", Anyone),
+            ("{pull_request_target: {paths: [docs/**]}}", Anyone),
+            // Only triage-only `types:` narrow who can trigger an event
+            ("{pull_request_target: {types: [labeled]}}", Contributors),
+            ("{issues: {types: labeled}}", Contributors),
+            ("{pull_request: {types: [labeled, opened]}}", Anyone),
+            ("{pull_request: {types: []}}", Anyone),
+            ("{issue_comment: {types: [created]}}", Anyone),
+            // The widest trigger sets the workflow's surface
+            ("{schedule: [{cron: '0 0 * * *'}], push: {branches: [main]}}", Maintainers),
+            ("{push: {tags: ['v*']}, workflow_dispatch: null}", Contributors),
+            ("{}", Internal),
+        ] {
+            assert_eq!(surface(on, Visibility::Public).surface, expected, "on: {}", on);
+        }
+    }
+
+    #[test]
+    fn a_workflow_without_triggers_is_internal() {
+        let classified = classify(&serde_yaml::from_str("jobs: {}\n").unwrap(), Visibility::Public);
+        assert_eq!((classified.surface, classified.triggers.len()), (TriggerSurface::Internal, 0));
+    }
+
+    #[test]
+    fn nothing_in_a_private_repository_reaches_past_contributors() {
+        for (on, expected) in [
+            ("[pull_request_target, issue_comment]", TriggerSurface::Contributors),
+            ("{push: {branches: [main]}}", TriggerSurface::Maintainers),
+            ("schedule", TriggerSurface::Internal),
+        ] {
+            assert_eq!(surface(on, Visibility::Private).surface, expected, "on: {}", on);
+        }
+    }
+
+    #[test]
+    fn the_description_names_the_triggers_of_the_widest_surface() {
+        let classified = surface("{push: {branches: [main]}, pull_request_target: null, issue_comment: null}", Visibility::Public);
+        assert_eq!(classified.triggers, ["push: maintainers", "pull_request_target: anyone", "issue_comment: anyone"]);
+        assert_eq!(classified.describe(), "anyone (pull_request_target, issue_comment)");
+        assert_eq!(surface("schedule", Visibility::Public).describe(), "internal (schedule)");
+        assert_eq!(surface("{}", Visibility::Public).describe(), "internal");
+    }
+}
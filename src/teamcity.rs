@@ -0,0 +1,221 @@
+use crate::decision::Decision;
+use crate::render;
+use crate::report::{self, Finding, Level, ScanReport};
+use std::collections::BTreeMap;
+
+/// Escape a value for use inside a `##teamcity[...]` service message attribute
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            '\u{0085}' => escaped.push_str("|x"),
+            '\u{2028}' => escaped.push_str("|l"),
+            '\u{2029}' => escaped.push_str("|p"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Format one service message from its name and `key='value'` attributes
+fn message(name: &str, attributes: &[(&str, &str)]) -> String {
+    let mut line = format!("##teamcity[{}", name);
+    for (key, value) in attributes {
+        line.push_str(&format!(" {}='{}'", key, escape(value)));
+    }
+    line.push(']');
+    line
+}
+
+fn severity(level: Level) -> &'static str {
+    match level {
+        Level::Error => "ERROR",
+        Level::Warning => "WARNING",
+        Level::Note => "INFO",
+    }
+}
+
+/// Where TeamCity should file an inspection: the workflow file, else the action, else the scan
+fn inspection_file(finding: &Finding) -> &str {
+    match &finding.file {
+        Some(file) => file,
+        None if !finding.action.is_empty() => &finding.action,
+        None => "(scan)",
+    }
+}
+
+/// Stable identity for a build problem so TeamCity can track and mute it across builds.
+///
+/// TeamCity caps identities at 60 characters, so the finding is hashed (FNV-1a) behind the rule id.
+fn problem_identity(report: &ScanReport, finding: &Finding) -> String {
+    let key = format!("{}\0{}\0{}", inspection_file(finding), finding.action, finding.message);
+    format!("{}-{:016x}", report.rule_id(finding.rule), report::fnv1a(&key))
+}
+
+/// Render the report as TeamCity service messages: an inspection per finding, a build problem per
+/// blocking finding, scan statistics, and a final status text with the counts.
+///
+/// With `max_findings` (`--truncate-structured`) only the prioritized findings get inspections and
+/// build problems; the statistics and status text still count everything.
+pub fn render(report: &ScanReport, decision: &Decision, max_findings: Option<usize>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let (findings, hidden) = report.prioritized(max_findings);
+
+    // TeamCity wants every inspection type declared before it is used
+    let rules: BTreeMap<_, _> = findings.iter().map(|f| (report.rule_id(f.rule), f.rule.category())).collect();
+    for (rule, category) in rules {
+        let category = format!("{:?}", category);
+        lines.push(message(
+            "inspectionType",
+            &[("id", rule), ("name", rule), ("category", &category), ("description", rule)],
+        ));
+    }
+
+    for finding in &findings {
+        let line = finding.line.map(|l| l.to_string());
+        let mut attributes = vec![
+            ("typeId", report.rule_id(finding.rule)),
+            ("message", finding.message.as_str()),
+            ("file", inspection_file(finding)),
+        ];
+        if let Some(line) = &line {
+            attributes.push(("line", line));
+        }
+        attributes.push(("SEVERITY", severity(finding.level)));
+        lines.push(message("inspection", &attributes));
+    }
+
+    for finding in findings.iter().filter(|f| f.level >= decision.policy.fail_on) {
+        let description = match finding.location() {
+            Some(location) => format!("{}: {}", location, finding.message),
+            None => finding.message.clone(),
+        };
+        lines.push(message(
+            "buildProblem",
+            &[("description", &description), ("identity", &problem_identity(report, finding))],
+        ));
+    }
+    if hidden > 0 {
+        let text = render::overflow_line(hidden, "--truncate-structured");
+        lines.push(message("message", &[("text", &text), ("status", "WARNING")]));
+    }
+    if decision.unchecked_applied {
+        let description = format!("Could not check: {}", decision.unchecked.join(", "));
+        lines.push(message(
+            "buildProblem",
+            &[("description", &description), ("identity", "dependency-scanner-unchecked")],
+        ));
+    }
+    if decision.unused_config_applied {
+        let description = format!("{} unused config entries", decision.unused_config);
+        lines.push(message(
+            "buildProblem",
+            &[("description", &description), ("identity", "dependency-scanner-unused-config")],
+        ));
+    }
+
+    let statistics = [
+        ("actionsScanned", report.actions_scanned),
+        ("filteredOut", report.filtered_out),
+        ("errors", decision.counts.error),
+        ("warnings", decision.counts.warning),
+        ("notes", decision.counts.note),
+        ("unchecked", report.unchecked.len()),
+    ];
+    for (key, value) in statistics {
+        let key = format!("dependencyScanner.{}", key);
+        lines.push(message("buildStatisticValue", &[("key", &key), ("value", &value.to_string())]));
+    }
+
+    let text = format!(
+        "{{build.status.text}}; dependency scanner: {} error(s), {} warning(s), {} note(s)",
+        decision.counts.error, decision.counts.warning, decision.counts.note
+    );
+    lines.push(message("buildStatus", &[("text", &text)]));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Undo [`escape`] the way TeamCity reads an attribute value; `None` for a stray `|` or
+    /// an unescaped character TeamCity would end the value or the message at
+    fn unescape(value: &str) -> Option<String> {
+        let mut unescaped = String::new();
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            let c = match c {
+                '|' => match chars.next()? {
+                    '|' => '|',
+                    '\'' => '\'',
+                    'n' => '\n',
+                    'r' => '\r',
+                    '[' => '[',
+                    ']' => ']',
+                    'x' => '\u{0085}',
+                    'l' => '\u{2028}',
+                    'p' => '\u{2029}',
+                    _ => return None,
+                },
+                '\'' | '[' | ']' | '\n' | '\r' | '\u{0085}' | '\u{2028}' | '\u{2029}' => return None,
+                c => c,
+            };
+            unescaped.push(c);
+        }
+        Some(unescaped)
+    }
+
+    #[test]
+    fn every_special_character_is_escaped() {
+        for (raw, escaped) in [
+            ("|", "||"),
+            ("'", "|'"),
+            ("[", "|["),
+            ("]", "|]"),
+            ("\n", "|n"),
+            ("\r", "|r"),
+            ("\u{0085}", "|x"),
+            ("\u{2028}", "|l"),
+            ("\u{2029}", "|p"),
+        ] {
+            assert_eq!(escape(raw), escaped, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn other_text_is_left_alone() {
+        for text in ["", "owner/repo@v1", "docker://alpine:3.19", "ünïcödé ✓", "tab\there", "{build.status.text}"] {
+            assert_eq!(escape(text), text);
+        }
+    }
+
+    #[test]
+    fn escaped_values_read_back() {
+        for text in [
+            "Action 'a/b@v1' [critical] | see\nhttps://example.com\r\n",
+            "||''[[]]",
+            "|n is not a newline",
+            "line\u{0085}next\u{2028}line\u{2029}paragraph",
+            "]",
+        ] {
+            assert_eq!(unescape(&escape(text)).as_deref(), Some(text), "{:?} escaped as {:?}", text, escape(text));
+        }
+    }
+
+    #[test]
+    fn a_message_ends_only_at_its_own_bracket() {
+        let line = message("inspection", &[("typeId", "rule"), ("message", "uses ['x'] |\n]")]);
+        assert_eq!(line, "##teamcity[inspection typeId='rule' message='uses |[|'x|'|] |||n|]']");
+        assert_eq!(line.lines().count(), 1);
+        let body = line.strip_prefix("##teamcity[").and_then(|l| l.strip_suffix(']')).unwrap();
+        let value = body.split_once(" message='").unwrap().1.strip_suffix('\'').unwrap();
+        assert_eq!(unescape(value).as_deref(), Some("uses ['x'] |\n]"));
+    }
+}
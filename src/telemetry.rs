@@ -0,0 +1,365 @@
+//! OTLP/HTTP (JSON) export of scan spans and metrics, compiled in with the `otlp` feature.
+
+use crate::http::{HttpClient, HttpRequest, HttpResponse, ResponseFuture};
+use crate::report::ScanReport;
+use crate::scanner::ScanEvents;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SERVICE_NAME: &str = "dependency-scanner";
+
+struct Span {
+    span_id: String,
+    parent: String,
+    name: String,
+    start: u64,
+    end: u64,
+    attributes: Vec<(String, String)>,
+}
+
+struct State {
+    spans: Vec<Span>,
+    /// The phase currently running, as (span id, name, start)
+    phase: Option<(String, String, u64)>,
+    api_calls: BTreeMap<String, u64>,
+}
+
+/// Collects one trace per scan: a root span, a child span per phase, and a grandchild span per
+/// HTTP request made during the phase
+pub struct Telemetry {
+    endpoint: String,
+    timeout: Duration,
+    trace_id: String,
+    root_id: String,
+    start: u64,
+    state: Mutex<State>,
+}
+
+impl Telemetry {
+    pub fn new(endpoint: &str, timeout: Duration) -> Arc<Self> {
+        Arc::new(Telemetry {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            timeout,
+            trace_id: format!("{:016x}{:016x}", random_u64(), random_u64()),
+            root_id: format!("{:016x}", random_u64()),
+            start: now_nanos(),
+            state: Mutex::new(State {
+                spans: Vec::new(),
+                phase: None,
+                api_calls: BTreeMap::new(),
+            }),
+        })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// End the running phase, if any, and start the next one
+    pub fn phase(&self, name: &str) {
+        let mut state = self.state();
+        close_phase(&mut state, &self.root_id);
+        state.phase = Some((format!("{:016x}", random_u64()), name.to_string(), now_nanos()));
+    }
+
+    fn record_request(&self, request: &HttpRequest, start: u64, status: Option<u16>) {
+        let host_path = request.url.split('?').next().unwrap_or("").to_string();
+        let mut state = self.state();
+        *state.api_calls.entry(host_path.clone()).or_default() += 1;
+        let parent = state.phase.as_ref().map_or(self.root_id.clone(), |(id, _, _)| id.clone());
+        let mut attributes = vec![
+            ("http.request.method".to_string(), request.method.to_string()),
+            ("url.full".to_string(), host_path),
+        ];
+        if let Some(status) = status {
+            attributes.push(("http.response.status_code".to_string(), status.to_string()));
+        }
+        state.spans.push(Span {
+            span_id: format!("{:016x}", random_u64()),
+            parent,
+            name: format!("{} {}", request.method, request.url.split('?').next().unwrap_or("")),
+            start,
+            end: now_nanos(),
+            attributes,
+        });
+    }
+
+    /// Close the trace and export it with the scan's metrics. Export problems are told to
+    /// `events` as warnings and otherwise ignored; each request is bounded by the export timeout.
+    pub async fn finish(&self, report: &ScanReport, events: &dyn ScanEvents) {
+        let (traces, metrics) = self.payloads(report);
+        let client = match reqwest::Client::builder().timeout(self.timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                events.warning(&format!("Warning: could not export telemetry: {}", e));
+                return;
+            }
+        };
+        for (signal, body) in [("traces", traces), ("metrics", metrics)] {
+            let url = format!("{}/v1/{}", self.endpoint, signal);
+            let result = client.post(&url).json(&body).send().await;
+            match result {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => events.warning(&format!("Warning: OTLP {} export returned {}", signal, response.status())),
+                Err(e) => events.warning(&format!("Warning: OTLP {} export failed: {}", signal, e.without_url())),
+            }
+        }
+    }
+
+    /// The OTLP traces and metrics requests, with the running phase closed
+    fn payloads(&self, report: &ScanReport) -> (Value, Value) {
+        let end = now_nanos();
+        let mut state = self.state();
+        close_phase(&mut state, &self.root_id);
+        (self.traces(&state, end), self.metrics(&state, report, end))
+    }
+
+    fn traces(&self, state: &State, end: u64) -> Value {
+        let root = Span {
+            span_id: self.root_id.clone(),
+            parent: String::new(),
+            name: "scan".to_string(),
+            start: self.start,
+            end,
+            attributes: Vec::new(),
+        };
+        let spans: Vec<Value> = std::iter::once(&root)
+            .chain(&state.spans)
+            .map(|span| {
+                json!({
+                    "traceId": self.trace_id,
+                    "spanId": span.span_id,
+                    "parentSpanId": span.parent,
+                    "name": span.name,
+                    "kind": 1,
+                    "startTimeUnixNano": span.start.to_string(),
+                    "endTimeUnixNano": span.end.to_string(),
+                    "attributes": attributes(&span.attributes),
+                })
+            })
+            .collect();
+        json!({
+            "resourceSpans": [{
+                "resource": resource(),
+                "scopeSpans": [{ "scope": { "name": SERVICE_NAME }, "spans": spans }],
+            }]
+        })
+    }
+
+    fn metrics(&self, state: &State, report: &ScanReport, end: u64) -> Value {
+        let mut findings: BTreeMap<Vec<(String, String)>, u64> = BTreeMap::new();
+        for finding in &report.findings {
+            let mut labels = vec![
+                ("rule".to_string(), finding.rule.id().to_string()),
+                ("level".to_string(), finding.level.to_string()),
+            ];
+            if let Some(severity) = finding.severity {
+                labels.push(("severity".to_string(), severity.to_string()));
+            }
+            *findings.entry(labels).or_default() += 1;
+        }
+        let counter = |points: Vec<(Vec<(String, String)>, u64)>| -> Value {
+            let points: Vec<Value> = points
+                .into_iter()
+                .map(|(labels, count)| {
+                    json!({
+                        "asInt": count.to_string(),
+                        "startTimeUnixNano": self.start.to_string(),
+                        "timeUnixNano": end.to_string(),
+                        "attributes": attributes(&labels),
+                    })
+                })
+                .collect();
+            // Cumulative, monotonic sum: one value per scan run
+            json!({ "aggregationTemporality": 2, "isMonotonic": true, "dataPoints": points })
+        };
+        let api_calls = state
+            .api_calls
+            .iter()
+            .map(|(url, count)| (vec![("url.full".to_string(), url.clone())], *count))
+            .collect();
+        let duration = (end.saturating_sub(self.start)) as f64 / 1e9;
+
+        json!({
+            "resourceMetrics": [{
+                "resource": resource(),
+                "scopeMetrics": [{
+                    "scope": { "name": SERVICE_NAME },
+                    "metrics": [
+                        { "name": "scanner.findings", "unit": "1", "sum": counter(findings.into_iter().collect()) },
+                        { "name": "scanner.api_calls", "unit": "1", "sum": counter(api_calls) },
+                        {
+                            "name": "scanner.actions_scanned",
+                            "unit": "1",
+                            "gauge": { "dataPoints": [{ "asInt": report.actions_scanned.to_string(), "timeUnixNano": end.to_string() }] },
+                        },
+                        {
+                            "name": "scanner.duration",
+                            "unit": "s",
+                            "gauge": { "dataPoints": [{ "asDouble": duration, "timeUnixNano": end.to_string() }] },
+                        },
+                    ],
+                }],
+            }]
+        })
+    }
+}
+
+fn close_phase(state: &mut State, root_id: &str) {
+    if let Some((span_id, name, start)) = state.phase.take() {
+        state.spans.push(Span {
+            span_id,
+            parent: root_id.to_string(),
+            name,
+            start,
+            end: now_nanos(),
+            attributes: Vec::new(),
+        });
+    }
+}
+
+fn resource() -> Value {
+    json!({ "attributes": attributes(&[("service.name".to_string(), SERVICE_NAME.to_string())]) })
+}
+
+fn attributes(pairs: &[(String, String)]) -> Vec<Value> {
+    pairs
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+fn now_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Span and trace ids only need to be unique, not unpredictable; the std hasher is randomly keyed
+fn random_u64() -> u64 {
+    RandomState::new().hash_one(now_nanos())
+}
+
+/// Records a span per request made through the wrapped client
+pub struct TracingClient {
+    pub inner: Box<dyn HttpClient>,
+    pub telemetry: Arc<Telemetry>,
+}
+
+impl HttpClient for TracingClient {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        Box::pin(async move {
+            let start = now_nanos();
+            let result: Result<HttpResponse, std::io::Error> = self.inner.send(request.clone()).await;
+            let status = result.as_ref().ok().map(|r| r.status);
+            self.telemetry.record_request(&request, start, status);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{Finding, Level, Rule};
+    use crate::severity::Severity;
+    use reqwest::Method;
+    use std::time::Instant;
+
+    /// The warnings a run gave
+    #[derive(Default)]
+    struct Warnings(Mutex<Vec<String>>);
+
+    impl ScanEvents for Warnings {
+        fn warning(&self, message: &str) {
+            self.0.lock().unwrap().push(message.to_string());
+        }
+    }
+
+    fn spans(traces: &Value) -> Vec<Value> {
+        traces["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap().clone()
+    }
+
+    fn named<'a>(spans: &'a [Value], name: &str) -> &'a Value {
+        spans.iter().find(|span| span["name"] == name).unwrap_or_else(|| panic!("no span {}: {:?}", name, spans))
+    }
+
+    #[test]
+    fn phases_hang_off_the_root_and_requests_off_their_phase() {
+        let telemetry = Telemetry::new("http://127.0.0.1:9", Duration::from_millis(100));
+        let advisories = HttpRequest::new(Method::GET, "https://api.github.com/advisories?affects=acme/tool");
+        telemetry.record_request(&advisories, now_nanos(), Some(200));
+        telemetry.phase("policy");
+        telemetry.phase("network");
+        telemetry.record_request(&advisories, now_nanos(), Some(200));
+        telemetry.record_request(&HttpRequest::new(Method::POST, "https://api.osv.dev/v1/query"), now_nanos(), None);
+        let (traces, _) = telemetry.payloads(&ScanReport::default());
+        let spans = spans(&traces);
+        assert_eq!(spans.len(), 6, "{:?}", spans);
+        assert!(spans.iter().all(|span| span["traceId"] == telemetry.trace_id.as_str()));
+
+        let root = named(&spans, "scan");
+        assert_eq!((root["spanId"].as_str(), root["parentSpanId"].as_str()), (Some(telemetry.root_id.as_str()), Some("")));
+        for phase in ["policy", "network"] {
+            assert_eq!(named(&spans, phase)["parentSpanId"], root["spanId"], "{}", phase);
+        }
+        let network = named(&spans, "network");
+        let lookups: Vec<&Value> = spans.iter().filter(|span| span["parentSpanId"] == network["spanId"]).collect();
+        let names: Vec<&str> = lookups.iter().map(|span| span["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["GET https://api.github.com/advisories", "POST https://api.osv.dev/v1/query"]);
+        let status = &lookups[0]["attributes"][2];
+        assert_eq!((&status["key"], &status["value"]["stringValue"]), (&json!("http.response.status_code"), &json!("200")));
+        // A request made before any phase began belongs to the scan itself
+        let early: Vec<&Value> = spans.iter().filter(|span| span["parentSpanId"] == root["spanId"] && span["name"] == names[0]).collect();
+        assert_eq!(early.len(), 1, "{:?}", spans);
+    }
+
+    #[test]
+    fn findings_are_counted_by_rule_level_and_severity() {
+        let telemetry = Telemetry::new("http://127.0.0.1:9", Duration::from_millis(100));
+        let unpinned = || Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, "acme/tool@v1", "unpinned".to_string());
+        let mut vulnerable = Finding::new(Rule::KnownVulnerability, Level::Error, "acme/tool@v1", "vulnerable".to_string());
+        vulnerable.severity = Some(Severity::High);
+        let report = ScanReport { findings: vec![unpinned(), unpinned(), vulnerable], actions_scanned: 1, ..Default::default() };
+        let (_, metrics) = telemetry.payloads(&report);
+        let metrics = metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"].as_array().unwrap().clone();
+        let findings = metrics.iter().find(|m| m["name"] == "scanner.findings").unwrap();
+        assert_eq!(findings["sum"]["isMonotonic"], true);
+        let points: Vec<(Vec<String>, String)> = findings["sum"]["dataPoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|point| {
+                let labels = point["attributes"].as_array().unwrap().iter().map(|a| format!("{}={}", a["key"].as_str().unwrap(), a["value"]["stringValue"].as_str().unwrap()));
+                (labels.collect(), point["asInt"].as_str().unwrap().to_string())
+            })
+            .collect();
+        assert_eq!(
+            points,
+            [
+                (vec!["rule=known-vulnerability".to_string(), "level=error".to_string(), "severity=high".to_string()], "1".to_string()),
+                (vec!["rule=untrusted-owner-no-sha".to_string(), "level=error".to_string()], "2".to_string()),
+            ]
+        );
+        let scanned = metrics.iter().find(|m| m["name"] == "scanner.actions_scanned").unwrap();
+        assert_eq!(scanned["gauge"]["dataPoints"][0]["asInt"], "1");
+    }
+
+    #[tokio::test]
+    async fn a_collector_that_never_answers_costs_at_most_the_timeout() {
+        // Connections queue in the backlog and are never answered
+        let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", silent.local_addr().unwrap());
+        let telemetry = Telemetry::new(&endpoint, Duration::from_millis(200));
+        let warnings = Warnings::default();
+        let started = Instant::now();
+        telemetry.finish(&ScanReport::default(), &warnings).await;
+        assert!(started.elapsed() < Duration::from_secs(3), "{:?}", started.elapsed());
+        let warnings = warnings.0.into_inner().unwrap();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].starts_with("Warning: OTLP traces export failed: "), "{}", warnings[0]);
+        assert!(warnings[1].starts_with("Warning: OTLP metrics export failed: "), "{}", warnings[1]);
+    }
+}
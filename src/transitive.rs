@@ -0,0 +1,101 @@
+//! `--transitive`: the `uses:` of composite actions, which the workflows run without naming.
+//!
+//! Each GitHub-hosted action's metadata is read at its pinned ref. A composite action's steps
+//! that use other actions are checked like the workflow's own, each with the chain of actions
+//! that leads to it. Every `owner/repo[/path]@ref` is visited once, which also ends cycles.
+
+use crate::action_metadata;
+use crate::cache::DiskCache;
+use crate::github::GitHubApi;
+use crate::{ActionKind, ActionRef};
+use futures_util::stream::{self, StreamExt};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Levels below the workflow that `--transitive` follows unless `--transitive-depth` says
+pub const DEFAULT_DEPTH: usize = 1;
+
+/// An action a composite action uses, and the actions from the workflow's on that lead to it
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Nested {
+    pub action: String,
+    pub chain: Vec<String>,
+}
+
+impl Nested {
+    /// Levels below the workflow: 1 for an action a workflow's own action uses
+    pub fn depth(&self) -> usize {
+        self.chain.len()
+    }
+}
+
+/// The `uses:` of the action's composite steps at its ref; empty for other kinds of action.
+/// `None` when the action has no metadata file there.
+async fn composite_uses(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Option<Vec<String>>, std::io::Error> {
+    let Some(metadata) = action_metadata::fetch(api, cache, action_ref).await? else {
+        return Ok(None);
+    };
+    if action_metadata::runs_using(&metadata) != Some("composite") {
+        return Ok(Some(Vec::new()));
+    }
+    let steps = metadata.get("runs").and_then(|r| r.get("steps")).and_then(|s| s.as_sequence());
+    let uses = steps
+        .into_iter()
+        .flatten()
+        .filter_map(|step| step.get("uses").and_then(|u| u.as_str()))
+        .map(str::to_string)
+        .collect();
+    Ok(Some(uses))
+}
+
+/// The actions that the composite actions among `roots` use, up to `depth` levels below the
+/// workflows, fetching `concurrency` metadata files at a time. Actions already in `roots` are
+/// not repeated. An action whose metadata file cannot be read is not followed; it comes back
+/// with the error, for the scan to record it as unchecked.
+pub async fn expand(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    roots: &BTreeSet<String>,
+    depth: usize,
+    concurrency: usize,
+) -> (Vec<Nested>, Vec<(String, String)>) {
+    let mut visited: BTreeSet<String> = roots.iter().map(|a| a.to_ascii_lowercase()).collect();
+    let mut level: Vec<Nested> = roots.iter().map(|action| Nested { action: action.clone(), chain: Vec::new() }).collect();
+    let mut found = Vec::new();
+    let mut failed = Vec::new();
+    for _ in 0..depth {
+        let lookups = level.iter().filter_map(|parent| match ActionKind::parse(&parent.action) {
+            Some(ActionKind::GitHub(action_ref)) if !parent.action.contains("${{") => Some((parent, action_ref)),
+            _ => None,
+        });
+        let results: Vec<_> = stream::iter(lookups)
+            .map(|(parent, action_ref)| async move { (parent, composite_uses(api, cache, &action_ref).await) })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+        let mut next = Vec::new();
+        for (parent, result) in results {
+            let uses = match result {
+                Ok(Some(uses)) => uses,
+                Ok(None) => continue,
+                Err(e) => {
+                    failed.push((parent.action.clone(), format!("Could not read the action metadata of {}: {}", parent.action, e)));
+                    continue;
+                }
+            };
+            // A composite's local `./` steps are in its own repository, which it pins already
+            for action in uses.into_iter().filter(|a| !a.starts_with("./")) {
+                if !visited.insert(action.to_ascii_lowercase()) {
+                    continue;
+                }
+                let mut chain = parent.chain.clone();
+                chain.push(parent.action.clone());
+                next.push(Nested { action, chain });
+            }
+        }
+        found.extend(next.iter().cloned());
+        level = next;
+    }
+    (found, failed)
+}
@@ -0,0 +1,116 @@
+use crate::ActionRef;
+use crate::cache::{Cached, DiskCache};
+use crate::github::GitHubApi;
+use crate::report::{Finding, Level, Rule};
+use serde_json::{Value, json};
+
+/// How many recent releases a diverged commit is compared against before it counts as unreleased
+const RELEASES_CHECKED: usize = 20;
+
+/// Where a commit stands relative to one release tag
+pub struct Comparison {
+    /// The commit is the tag or one of its ancestors
+    pub released: bool,
+    /// Neither contains the other, as with a commit on a maintenance branch
+    diverged: bool,
+    /// The commit is an ancestor of the tag, and not the tag itself
+    pub behind: bool,
+    /// Commits on the pinned side that the tag does not contain
+    ahead_by: u64,
+}
+
+impl Comparison {
+    /// How many commits the pinned commit is past the tag, when the tag is one of its ancestors
+    pub fn ahead(&self) -> Option<u64> {
+        (!self.released && !self.diverged && self.ahead_by > 0).then_some(self.ahead_by)
+    }
+}
+
+/// Compare a release tag with a pinned commit. Commits never change, so a cached answer is final.
+pub async fn compare(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    action_ref: &ActionRef,
+    tag: &str,
+) -> Result<Option<Comparison>, std::io::Error> {
+    let key = format!("{}/{}...{}", action_ref.full_name(), tag, action_ref.version);
+    let value = match cache.get("compare", &key) {
+        Cached::Hit(value) => value,
+        Cached::NegativeHit => return Ok(None),
+        Cached::Miss => {
+            let path = format!("/repos/{}/compare/{}...{}", action_ref.full_name(), tag, action_ref.version);
+            let Some(comparison) = api.get(&path).await? else {
+                cache.put_negative("compare", &key);
+                return Ok(None);
+            };
+            let value = json!({ "status": comparison["status"], "ahead_by": comparison["ahead_by"] });
+            cache.put("compare", &key, &value);
+            value
+        }
+    };
+    Ok(Some(Comparison {
+        released: matches!(value["status"].as_str(), Some("behind" | "identical")),
+        diverged: value["status"].as_str() == Some("diverged"),
+        behind: value["status"].as_str() == Some("behind"),
+        ahead_by: value["ahead_by"].as_u64().unwrap_or(0),
+    }))
+}
+
+/// Tags of the repository's published releases, newest first
+pub async fn release_tags(api: &GitHubApi<'_>, cache: &DiskCache, action_ref: &ActionRef) -> Result<Vec<String>, std::io::Error> {
+    let key = action_ref.full_name();
+    let releases = match cache.get("releases", &key) {
+        Cached::Hit(value) => value,
+        Cached::NegativeHit => return Ok(Vec::new()),
+        Cached::Miss => {
+            let path = format!("/repos/{}/releases?per_page={}", action_ref.full_name(), RELEASES_CHECKED);
+            let releases = api.get(&path).await?.unwrap_or(Value::Null);
+            if releases.as_array().is_none_or(|r| r.is_empty()) {
+                cache.put_negative("releases", &key);
+                return Ok(Vec::new());
+            }
+            cache.put("releases", &key, &releases);
+            releases
+        }
+    };
+    Ok(releases
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| !r["draft"].as_bool().unwrap_or(false) && !r["prerelease"].as_bool().unwrap_or(false))
+        .filter_map(|r| r["tag_name"].as_str().map(str::to_string))
+        .collect())
+}
+
+/// Flag a SHA pin that no release contains, such as whatever `main` pointed at on the day it was
+/// pinned. Repositories without releases are not judged.
+pub async fn check(
+    api: &GitHubApi<'_>,
+    cache: &DiskCache,
+    action: &str,
+    action_ref: &ActionRef,
+    level: Level,
+) -> Result<Option<Finding>, std::io::Error> {
+    let tags = release_tags(api, cache, action_ref).await?;
+    let Some(latest) = tags.first() else {
+        return Ok(None);
+    };
+    let Some(against_latest) = compare(api, cache, action_ref, latest).await? else {
+        // The commit does not exist upstream; other checks report broken refs
+        return Ok(None);
+    };
+    if against_latest.released {
+        return Ok(None);
+    }
+    // A commit beside the latest release may still be a backport on an older release line
+    for tag in tags.iter().skip(1).filter(|_| against_latest.diverged) {
+        if compare(api, cache, action_ref, tag).await?.is_some_and(|c| c.released) {
+            return Ok(None);
+        }
+    }
+    let message = format!(
+        "{} is pinned to unreleased commit ({} commits ahead of {})",
+        action, against_latest.ahead_by, latest
+    );
+    Ok(Some(Finding::new(Rule::UnreleasedCommit, level, action, message)))
+}
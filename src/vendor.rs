@@ -0,0 +1,146 @@
+use crate::ActionRef;
+use crate::github::GitHubApi;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Marker file placed in a vendored action's directory, naming the upstream it was copied from
+pub const MARKER_FILE: &str = "VENDORED_FROM";
+
+/// A local action that is a frozen copy of an upstream action
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Vendored {
+    /// The `uses:` value in the workflow, e.g. `./.github/actions/vendored-foo`
+    pub local: String,
+    /// The upstream `owner/repo@ref` the copy was taken from
+    pub upstream: String,
+    /// Where the provenance came from: the marker file or the config mapping
+    pub source: String,
+    #[serde(skip)]
+    pub upstream_ref: ActionRef,
+}
+
+/// Parse the contents of a `VENDORED_FROM` marker.
+///
+/// The marker holds a single `owner/repo@ref` line; blank lines and `#` comments are ignored.
+pub fn parse_marker(content: &str) -> Result<String, String> {
+    let mut entries = content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty());
+    let entry = entries.next().ok_or("marker is empty")?;
+    if entries.next().is_some() {
+        return Err("marker must contain a single owner/repo@ref line".to_string());
+    }
+    Ok(entry.to_string())
+}
+
+/// Work out whether a local action is vendored. A config mapping wins over the marker file.
+///
+/// Returns `Ok(None)` for ordinary local actions and `Err` for a marker or mapping that is invalid.
+pub fn provenance(
+    action: &str,
+    mapping: Option<&std::collections::BTreeMap<String, String>>,
+    root: &Path,
+) -> Result<Option<Vendored>, String> {
+    let (upstream, source) = match mapping.and_then(|m| m.get(action)) {
+        Some(upstream) => (upstream.clone(), "config".to_string()),
+        None => {
+            let marker = root.join(action.trim_start_matches("./")).join(MARKER_FILE);
+            match fs::read_to_string(&marker) {
+                Ok(content) => (
+                    parse_marker(&content).map_err(|e| format!("{}: {}", marker.display(), e))?,
+                    marker.display().to_string(),
+                ),
+                Err(_) => return Ok(None),
+            }
+        }
+    };
+
+    let upstream_ref = ActionRef::from_action_string(&upstream)
+        .ok_or_else(|| format!("{}: {} is not an owner/repo@ref reference", source, upstream))?;
+    Ok(Some(Vendored {
+        local: action.to_string(),
+        upstream,
+        source,
+        upstream_ref,
+    }))
+}
+
+/// The tag of the upstream's latest release, if it has one
+pub async fn latest_release(api: &GitHubApi<'_>, action_ref: &ActionRef) -> Result<Option<String>, std::io::Error> {
+    let release = api
+        .get(&format!("/repos/{}/releases/latest", action_ref.full_name()))
+        .await?;
+    Ok(release.and_then(|r| r["tag_name"].as_str().map(str::to_string)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::Scratch;
+    use std::collections::BTreeMap;
+
+    /// A repository root holding `.github/actions/vendored-foo` with the given marker, if any
+    fn repository(name: &str, marker: Option<&str>) -> Scratch {
+        let root = Scratch::new(&format!("vendor-{}", name));
+        let dir = root.join(".github/actions/vendored-foo");
+        fs::create_dir_all(&dir).unwrap();
+        if let Some(marker) = marker {
+            fs::write(dir.join(MARKER_FILE), marker).unwrap();
+        }
+        root
+    }
+
+    const LOCAL: &str = "./.github/actions/vendored-foo";
+
+    #[test]
+    fn a_marker_is_one_reference_among_comments_and_blank_lines() {
+        assert_eq!(parse_marker("acme/foo@v1\n"), Ok("acme/foo@v1".to_string()));
+        assert_eq!(parse_marker("# copied 2024-01-01\n\n  acme/foo@v1  # pinned\n\n"), Ok("acme/foo@v1".to_string()));
+        assert_eq!(parse_marker(""), Err("marker is empty".to_string()));
+        assert_eq!(parse_marker("# nothing but a comment\n\n"), Err("marker is empty".to_string()));
+        assert_eq!(parse_marker("acme/foo@v1\nacme/bar@v2\n"), Err("marker must contain a single owner/repo@ref line".to_string()));
+    }
+
+    #[test]
+    fn a_marker_file_makes_a_local_action_vendored() {
+        let root = repository("marker", Some("acme/foo@v1.2.0\n"));
+        let vendored = provenance(LOCAL, None, &root).unwrap().unwrap();
+        assert_eq!(vendored.local, LOCAL);
+        assert_eq!(vendored.upstream, "acme/foo@v1.2.0");
+        assert!(vendored.source.ends_with(".github/actions/vendored-foo/VENDORED_FROM"), "{}", vendored.source);
+        assert_eq!((vendored.upstream_ref.full_name(), vendored.upstream_ref.version.as_str()), ("acme/foo".to_string(), "v1.2.0"));
+    }
+
+    #[test]
+    fn without_a_marker_or_mapping_a_local_action_is_not_vendored() {
+        let root = repository("plain", None);
+        assert!(provenance(LOCAL, None, &root).unwrap().is_none());
+        let mapping = BTreeMap::from([("./.github/actions/other".to_string(), "acme/other@v1".to_string())]);
+        assert!(provenance(LOCAL, Some(&mapping), &root).unwrap().is_none());
+    }
+
+    #[test]
+    fn the_config_mapping_wins_over_the_marker() {
+        let root = repository("mapping", Some("acme/foo@v1\n"));
+        let mapping = BTreeMap::from([(LOCAL.to_string(), "acme/foo@v2".to_string())]);
+        let vendored = provenance(LOCAL, Some(&mapping), &root).unwrap().unwrap();
+        assert_eq!((vendored.upstream.as_str(), vendored.source.as_str()), ("acme/foo@v2", "config"));
+    }
+
+    #[test]
+    fn an_invalid_marker_or_mapping_is_an_error_naming_where_it_came_from() {
+        let root = repository("invalid", Some("acme/foo@v1\nacme/bar@v1\n"));
+        let error = provenance(LOCAL, None, &root).unwrap_err();
+        assert!(error.ends_with("VENDORED_FROM: marker must contain a single owner/repo@ref line"), "{}", error);
+
+        let bare = repository("not-a-reference", Some("just-a-name\n"));
+        let error = provenance(LOCAL, None, &bare).unwrap_err();
+        assert!(error.ends_with("VENDORED_FROM: just-a-name is not an owner/repo@ref reference"), "{}", error);
+
+        let mapping = BTreeMap::from([(LOCAL.to_string(), "acme/foo".to_string())]);
+        assert_eq!(provenance(LOCAL, Some(&mapping), &bare).unwrap_err(), "config: acme/foo is not an owner/repo@ref reference");
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// This build's version, as the files it writes record it
+pub const SCANNER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// `1.2.3` or `v1.2`, with missing parts as zero
+fn parse(version: &str) -> Option<[u64; 3]> {
+    let mut parts = [0; 3];
+    let numbers: Vec<&str> = version.strip_prefix('v').unwrap_or(version).split('.').collect();
+    if numbers.len() > 3 {
+        return None;
+    }
+    for (part, number) in parts.iter_mut().zip(numbers) {
+        *part = number.parse().ok()?;
+    }
+    Some(parts)
+}
+
+/// The part of a version that changes formats: the major version, or the minor one before 1.0
+fn major(version: [u64; 3]) -> (u64, u64) {
+    if version[0] == 0 { (0, version[1]) } else { (version[0], 0) }
+}
+
+/// `minimum_scanner_version` against this build
+pub fn check_minimum(minimum: &str) -> Result<(), String> {
+    let required = parse(minimum).ok_or_else(|| format!("minimum_scanner_version {} is not a version such as 0.9.0", minimum))?;
+    let own = parse(SCANNER_VERSION).unwrap_or_default();
+    if own < required {
+        return Err(format!(
+            "this config requires gh-action-security-scanner {} or newer, but this is {}; upgrade the scanner",
+            minimum, SCANNER_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// The files of one run written by a newer major version than this build, whose format this
+/// build may misread. One warning covers the run; it is kept until the scan reports it.
+#[derive(Default)]
+pub struct NewerFormats {
+    warned: AtomicBool,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl NewerFormats {
+    /// Check the version `file` records. Files that record no version predate the field and are
+    /// read as they always were.
+    pub fn check(&self, file: &str, recorded: Option<&str>) {
+        let (Some(recorded), Some(own)) = (recorded.and_then(parse), parse(SCANNER_VERSION)) else {
+            return;
+        };
+        if major(recorded) > major(own) && !self.warned.swap(true, Ordering::Relaxed) {
+            self.warnings.lock().unwrap_or_else(|e| e.into_inner()).push(format!(
+                "Warning: {} was written by gh-action-security-scanner {}.{}.{}, a newer major version than this {}; its format may have changed",
+                file, recorded[0], recorded[1], recorded[2], SCANNER_VERSION
+            ));
+        }
+    }
+
+    /// The warning, once, if a newer file was checked since the last call
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_parse_with_missing_parts_as_zero() {
+        assert_eq!(parse("1.2.3"), Some([1, 2, 3]));
+        assert_eq!(parse("v0.9"), Some([0, 9, 0]));
+        assert_eq!(parse("2"), Some([2, 0, 0]));
+        assert_eq!(parse("1.2.3.4"), None);
+        assert_eq!(parse("1.x"), None);
+    }
+
+    #[test]
+    fn before_one_the_minor_version_is_the_major_one() {
+        assert!(major([0, 2, 0]) > major([0, 1, 9]));
+        assert_eq!(major([0, 1, 0]), major([0, 1, 7]));
+        assert_eq!(major([2, 0, 0]), major([2, 5, 1]));
+        assert!(major([1, 0, 0]) > major([0, 99, 0]));
+    }
+
+    #[test]
+    fn one_warning_covers_the_newer_files_of_a_run() {
+        let formats = NewerFormats::default();
+        formats.check("old.json", Some("0.0.1"));
+        formats.check("unversioned.json", None);
+        assert!(formats.take_warnings().is_empty());
+        formats.check("first.json", Some("999.0.0"));
+        formats.check("second.json", Some("999.0.0"));
+        let warnings = formats.take_warnings();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with("Warning: first.json was written by"), "{}", warnings[0]);
+        assert!(formats.take_warnings().is_empty());
+    }
+}
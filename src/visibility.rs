@@ -0,0 +1,78 @@
+use crate::github::{Endpoints, GitHubApi};
+use crate::http::HttpClient;
+use crate::report::{Finding, Level};
+use std::collections::BTreeMap;
+
+/// What `--repo-visibility` asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityOption {
+    Public,
+    Private,
+    /// Ask the repos API, falling back to public when it can't be asked
+    Auto,
+}
+
+impl std::str::FromStr for VisibilityOption {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "public" => Ok(VisibilityOption::Public),
+            "private" => Ok(VisibilityOption::Private),
+            "auto" => Ok(VisibilityOption::Auto),
+            _ => Err(format!("unknown visibility {} (expected public, private or auto)", value)),
+        }
+    }
+}
+
+/// The visibility the visibility-sensitive rules run under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    /// Private and internal repositories, where only members can open pull requests
+    Private,
+}
+
+/// Resolve the option to a visibility, with a note explaining the assumption for the summary
+pub async fn resolve(option: VisibilityOption, client: &dyn HttpClient, endpoints: &Endpoints, token: Option<&str>) -> (Visibility, String) {
+    match option {
+        VisibilityOption::Public => (Visibility::Public, "public (--repo-visibility)".to_string()),
+        VisibilityOption::Private => (Visibility::Private, "private (--repo-visibility)".to_string()),
+        VisibilityOption::Auto => {
+            let token = token.filter(|t| !t.is_empty());
+            let repo = std::env::var("GITHUB_REPOSITORY").ok().filter(|r| !r.is_empty());
+            let (Some(token), Some(repo)) = (token, repo) else {
+                return (
+                    Visibility::Public,
+                    "assumed public (a token and GITHUB_REPOSITORY are needed to look it up)".to_string(),
+                );
+            };
+            let api = GitHubApi::new(client, endpoints, Some(token));
+            match api.get(&format!("/repos/{}", repo)).await {
+                Ok(Some(info)) => match info["visibility"].as_str() {
+                    Some("public") => (Visibility::Public, format!("public ({} via the repos API)", repo)),
+                    Some(visibility) => (Visibility::Private, format!("{} ({} via the repos API)", visibility, repo)),
+                    None => (Visibility::Public, format!("assumed public ({} has no visibility in the API)", repo)),
+                },
+                Ok(None) => (Visibility::Public, format!("assumed public ({} not found)", repo)),
+                Err(e) => (Visibility::Public, format!("assumed public (lookup failed: {})", e)),
+            }
+        }
+    }
+}
+
+/// Apply the visibility to a visibility-sensitive finding. In private repos the finding is kept but
+/// downgraded one level, unless `private_repo_levels` sets the level for its rule.
+pub fn adjust(mut finding: Finding, visibility: Visibility, overrides: Option<&BTreeMap<String, Level>>) -> Finding {
+    if visibility == Visibility::Public {
+        return finding;
+    }
+    finding.level = match overrides.and_then(|o| o.get(finding.rule.id())) {
+        Some(level) => *level,
+        None => match finding.level {
+            Level::Error => Level::Warning,
+            Level::Warning | Level::Note => Level::Note,
+        },
+    };
+    finding
+}
@@ -0,0 +1,140 @@
+use crate::blame;
+use crate::decision::{self, Decision};
+use crate::render;
+use crate::report::{Finding, Level, ScanReport};
+use std::collections::BTreeMap;
+
+/// Environment variable GitHub Actions sets to `true` in every job
+pub const ACTIONS_ENV: &str = "GITHUB_ACTIONS";
+
+/// Whether the scan runs inside a GitHub Actions job
+pub fn in_actions() -> bool {
+    std::env::var(ACTIONS_ENV).is_ok_and(|v| v == "true")
+}
+
+/// A file as annotations name it: relative to `$GITHUB_WORKSPACE`, where the job checks the
+/// repository out, since the scanner is often given absolute paths there
+fn repo_path(file: &str) -> String {
+    match std::env::var("GITHUB_WORKSPACE") {
+        Ok(workspace) if !workspace.is_empty() => {
+            let prefix = format!("{}/", workspace.trim_end_matches('/'));
+            file.strip_prefix(&prefix).unwrap_or(file).to_string()
+        }
+        _ => file.to_string(),
+    }
+}
+
+/// Escape a workflow command's message
+fn escape_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escape a workflow command property, where `:` and `,` also delimit
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Format one workflow command from its name, `key=value` properties and message
+fn command(name: &str, properties: &[(&str, &str)], message: &str) -> String {
+    let properties: Vec<String> = properties.iter().map(|(key, value)| format!("{}={}", key, escape_property(value))).collect();
+    let separator = if properties.is_empty() { "" } else { " " };
+    format!("::{}{}{}::{}", name, separator, properties.join(","), escape_data(message))
+}
+
+/// `error` for a finding that fails the scan, `warning` for one that does not, `notice` for notes
+fn severity(finding: &Finding, decision: &Decision) -> &'static str {
+    if decision::is_blocking(finding, &decision.policy) {
+        "error"
+    } else if finding.level == Level::Note {
+        "notice"
+    } else {
+        "warning"
+    }
+}
+
+/// Render the report as GitHub Actions workflow commands: an annotation per finding at each line
+/// it points to, inside a `::group::` per workflow file. The title is the rule id, so the
+/// annotations can be filtered by check. Suppressed findings get no annotation.
+///
+/// With `max_findings` (`--truncate-structured`) only the prioritized findings are annotated.
+pub fn render(report: &ScanReport, decision: &Decision, max_findings: Option<usize>) -> Vec<String> {
+    let (findings, hidden) = report.prioritized(max_findings);
+    let mut by_file: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut unlocated = Vec::new();
+    for finding in findings.iter().filter(|f| !f.is_suppressed()) {
+        let title = report.rule_id(finding.rule);
+        let message = report.pins.annotate(&finding.message);
+        let name = severity(finding, decision);
+        let sites = blame::finding_sites(report, finding);
+        if !sites.is_empty() {
+            for (file, line) in sites {
+                let file = repo_path(&file);
+                let line = line.to_string();
+                let annotation = command(name, &[("file", &file), ("line", &line), ("title", title)], &message);
+                by_file.entry(file).or_default().push(annotation);
+            }
+        } else if let Some(file) = &finding.file {
+            let file = repo_path(file);
+            let annotation = command(name, &[("file", &file), ("title", title)], &message);
+            by_file.entry(file).or_default().push(annotation);
+        } else {
+            unlocated.push(command(name, &[("title", title)], &message));
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (file, annotations) in by_file {
+        lines.push(command("group", &[], &format!("Action security scan: {}", file)));
+        lines.extend(annotations);
+        lines.push(command("endgroup", &[], ""));
+    }
+    lines.extend(unlocated);
+    if hidden > 0 {
+        lines.push(command("notice", &[("title", "dependency-scanner")], &render::overflow_line(hidden, "--truncate-structured")));
+    }
+    if decision.unchecked_applied {
+        let message = format!("Could not check: {}", decision.unchecked.join(", "));
+        lines.push(command("error", &[("title", "dependency-scanner")], &message));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision::ExitPolicy;
+    use crate::report::Rule;
+
+    fn rendered(findings: Vec<Finding>) -> Vec<String> {
+        let report = ScanReport { findings, ..Default::default() };
+        render(&report, &decision::decide(&report, &ExitPolicy::default()), None)
+    }
+
+    #[test]
+    fn properties_and_messages_are_escaped() {
+        let message = "100% sure:\nline two, really";
+        let finding = Finding::new(Rule::UntrustedOwnerNoSha, Level::Error, "acme/tool@v1", message.to_string()).at("deploy:prod,eu.yml", Some(7));
+        assert_eq!(
+            rendered(vec![finding]),
+            [
+                "::group::Action security scan: deploy:prod,eu.yml",
+                "::error file=deploy%3Aprod%2Ceu.yml,line=7,title=untrusted-owner-no-sha::100%25 sure:%0Aline two, really",
+                "::endgroup::",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_command_follows_whether_the_finding_blocks() {
+        let finding = |level| Finding::new(Rule::UntrustedOwner, level, "acme/tool@v1", "untrusted".to_string());
+        let lines = rendered(vec![finding(Level::Error), finding(Level::Warning), finding(Level::Note)]);
+        assert_eq!(
+            lines,
+            [
+                "::error title=untrusted-owner::untrusted",
+                "::warning title=untrusted-owner::untrusted",
+                "::notice title=untrusted-owner::untrusted",
+            ]
+        );
+    }
+}
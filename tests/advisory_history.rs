@@ -0,0 +1,41 @@
+//! `--advisory-history`: a note for actions with recent advisories even when the pin is patched,
+//! from tests/fixtures/http/vulnerable.json, where acme/tool had an advisory published on
+//! 2024-01-02 and fixed in 1.2.0
+
+mod common;
+
+use common::*;
+
+fn scan(name: &str, args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&["acme/tool@v1.2.0"]))]).config("trusted_owners: [acme]\n");
+    let mut all = vec!["--source", "github", "--format", "json"];
+    all.extend(args);
+    all.push(".github/workflows");
+    run(repo.replaying("vulnerable.json", &all), None)
+}
+
+fn history(output: &std::process::Output) -> Vec<serde_json::Value> {
+    let report = json(output);
+    report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "advisory-history").cloned().collect()
+}
+
+#[test]
+fn a_patched_pin_with_a_recent_advisory_is_noted_without_failing() {
+    let output = scan("history-recent", &["--advisory-history", "12", "--timestamp", "2024-06-01T00:00:00Z", "--strict"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let found = history(&output);
+    assert_eq!(found.len(), 1, "{}", stderr(&output));
+    assert_eq!(found[0]["level"], "note");
+    assert_eq!(found[0]["message"], "acme/tool has a history of advisories: 1 in the last 12 months, most recent 2024-01-02");
+    assert_eq!(json(&output)["summary"]["findings"]["vulnerable"], 0);
+}
+
+#[test]
+fn advisories_outside_the_window_or_without_the_flag_are_not_noted() {
+    let old = scan("history-old", &["--advisory-history", "3", "--timestamp", "2024-06-01T00:00:00Z"]);
+    assert_eq!(code(&old), 0, "{}", stderr(&old));
+    assert!(history(&old).is_empty(), "{}", stdout(&old));
+
+    let off = scan("history-off", &["--timestamp", "2024-06-01T00:00:00Z"]);
+    assert!(history(&off).is_empty(), "{}", stdout(&off));
+}
@@ -0,0 +1,51 @@
+//! `--annotate-workflow`: findings written as comments above their steps, to a copy or in place,
+//! with re-runs replacing rather than stacking them
+
+mod common;
+
+use common::*;
+
+const CI: &str = "# Deploys on push\non: push\njobs:\n  build:\n    runs-on: ubuntu-latest  # pinned runner\n    steps:\n      - uses: actions/checkout@v4\n      - name: deploy\n        uses: acme/deploy@v1\n";
+const MARKERS: &str = "      # dependency-scanner: untrusted-owner Action acme/deploy@v1 is from non-trusted owner acme\n      # dependency-scanner: untrusted-owner-no-sha Non-trusted action acme/deploy@v1 should use commit SHA instead of tag/branch\n";
+
+fn annotated() -> String {
+    CI.replace("      - name: deploy\n", &format!("{}      - name: deploy\n", MARKERS))
+}
+
+#[test]
+fn a_copy_is_written_next_to_the_workflow() {
+    let repo = Repo::new("annotate-copy", &[("ci.yml", CI)]);
+    let output = repo.scan(&["--annotate-workflow"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let copy = repo.workflows().join("ci.yml.annotated");
+    assert_eq!(std::fs::read_to_string(&copy).unwrap(), annotated());
+    assert_eq!(std::fs::read_to_string(repo.workflow("ci.yml")).unwrap(), CI, "the workflow itself is untouched");
+    assert!(stdout(&output).contains("comment(s) in"), "{}", stdout(&output));
+}
+
+#[test]
+fn in_place_re_runs_keep_one_set_of_markers() {
+    let repo = Repo::new("annotate-in-place", &[("ci.yml", CI)]);
+    for _ in 0..2 {
+        let output = repo.scan(&["--annotate-workflow", "--in-place"]);
+        assert_eq!(code(&output), 2, "{}", stderr(&output));
+        assert_eq!(std::fs::read_to_string(repo.workflow("ci.yml")).unwrap(), annotated());
+    }
+    assert!(!repo.workflows().join("ci.yml.annotated").exists());
+}
+
+#[test]
+fn stale_markers_are_removed_once_the_finding_is_fixed() {
+    let repo = Repo::new("annotate-stale", &[("ci.yml", &annotated())]).config("trusted_owners: [actions, acme]\n");
+    let output = repo.scan(&["--annotate-workflow", "--in-place"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(std::fs::read_to_string(repo.workflow("ci.yml")).unwrap(), CI);
+}
+
+#[test]
+fn a_clean_workflow_gets_no_copy() {
+    let repo = Repo::new("annotate-clean", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let output = repo.scan(&["--annotate-workflow"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(!repo.workflows().join("ci.yml.annotated").exists());
+}
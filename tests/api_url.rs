@@ -0,0 +1,121 @@
+//! `--api-url`, `$GITHUB_API_URL`, `--advisory-api-url` and `--osv-api-url` against local
+//! servers answering as the recorded fixtures in tests/fixtures/http do
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@v1.1.0"]))]).config("trusted_owners: [actions, acme]\n")
+}
+
+/// A scan that may reach the network, with GitHub's token set for the server to check
+fn scan(repo: &Repo, args: &[&str]) -> std::process::Output {
+    let mut command = repo.networked(args);
+    command.env("GITHUB_TOKEN", "test-token").arg("--format").arg("json").arg(repo.workflows());
+    run(command, None)
+}
+
+/// The advisory found for acme/tool, which only the mocked lookups can have answered
+fn advisory(output: &std::process::Output) -> serde_json::Value {
+    assert_eq!(code(output), 1, "{}", stderr(output));
+    json(output)["findings"][0]["advisory"].clone()
+}
+
+#[test]
+fn api_url_sends_github_lookups_to_an_enterprise_server() {
+    let server = MockServer::replaying("vulnerable.json");
+    let ghes = format!("{}/api/v3/", server.base);
+    let repo = repo("api-url");
+    let output = scan(&repo, &["--api-url", &ghes, "--source", "github"]);
+    assert_eq!(advisory(&output)["id"], "GHSA-abcd-efgh-ijkl");
+    let requests = server.requests();
+    assert_eq!(requests.iter().filter(|r| r.path.starts_with("/api/v3/advisories?")).count(), 2, "{:?}", requests);
+    assert!(requests.iter().all(|r| r.path.starts_with("/api/v3/") && r.authorized), "{:?}", requests);
+}
+
+#[test]
+fn github_api_url_is_the_default_base() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("api-url-env");
+    let mut command = repo.networked(&["--source", "github", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").env("GITHUB_API_URL", format!("{}/api/v3", server.base)).arg(repo.workflows());
+    let output = run(command, None);
+    assert_eq!(advisory(&output)["id"], "GHSA-abcd-efgh-ijkl");
+    assert!(server.requests().iter().any(|r| r.path.starts_with("/api/v3/advisories?")), "{:?}", server.requests());
+}
+
+#[test]
+fn each_source_can_have_its_own_base() {
+    // An enterprise server that does not mirror the advisory database: OSV answers for
+    // advisories, and only the rest of the API goes to the server, with the token
+    let ghes = MockServer::replaying("clean.json");
+    let osv = MockServer::replaying("vulnerable.json");
+    let repo = repo("api-url-split");
+    let api = format!("{}/api/v3", ghes.base);
+    let osv_base = format!("{}/osv", osv.base);
+    let output = scan(&repo, &["--api-url", &api, "--osv-api-url", &osv_base, "--source", "osv"]);
+    assert_eq!(advisory(&output)["sources"], serde_json::json!(["osv"]));
+    let queries = osv.requests();
+    assert_eq!(queries.iter().filter(|r| r.method == "POST" && r.path == "/osv/v1/query").count(), 2, "{:?}", queries);
+    assert!(queries.iter().all(|r| !r.authorized), "the GitHub token never goes to OSV: {:?}", queries);
+    assert!(ghes.requests().iter().all(|r| !r.path.contains("/advisories")), "{:?}", ghes.requests());
+}
+
+#[test]
+fn advisory_api_url_moves_only_the_advisory_lookups() {
+    let ghes = MockServer::replaying("clean.json");
+    let github = MockServer::replaying("vulnerable.json");
+    let repo = repo("api-url-advisories");
+    let api = format!("{}/api/v3", ghes.base);
+    let output = scan(&repo, &["--api-url", &api, "--advisory-api-url", &github.base, "--source", "github"]);
+    assert_eq!(advisory(&output)["id"], "GHSA-abcd-efgh-ijkl");
+    let lookups = github.requests();
+    assert_eq!(lookups.iter().filter(|r| r.path.starts_with("/advisories?")).count(), 2, "{:?}", lookups);
+    // The enterprise token is not sent to another host
+    assert!(lookups.iter().all(|r| !r.authorized), "{:?}", lookups);
+    assert!(ghes.requests().iter().all(|r| !r.path.contains("/advisories")), "{:?}", ghes.requests());
+}
+
+/// The scan with GitHub's advisories at `github` and OSV at `osv`, from both sources
+fn scan_both(repo: &Repo, github: &MockServer, osv: &MockServer) -> std::process::Output {
+    let (api, osv_base) = (format!("{}/api/v3", github.base), format!("{}/osv", osv.base));
+    scan(repo, &["--api-url", &api, "--osv-api-url", &osv_base, "--source", "all"])
+}
+
+#[test]
+fn both_sources_for_one_ghsa_are_one_finding_and_are_cached() {
+    let (github, osv) = (MockServer::replaying("vulnerable.json"), MockServer::replaying("vulnerable.json"));
+    let repo = repo("api-url-merged");
+    let output = scan_both(&repo, &github, &osv);
+    let report = json(&output);
+    let advisories: Vec<&serde_json::Value> = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "known-vulnerability").collect();
+    let [finding] = advisories.as_slice() else { panic!("{}", report) };
+    assert_eq!(finding["advisory"]["id"], "GHSA-abcd-efgh-ijkl");
+    assert_eq!(finding["advisory"]["sources"], serde_json::json!(["github", "osv"]));
+    assert!(!stderr(&output).contains("using the other source"), "{}", stderr(&output));
+
+    let (github_before, osv_before) = (github.requests().len(), osv.requests().len());
+    assert_eq!(advisory(&scan_both(&repo, &github, &osv))["sources"], serde_json::json!(["github", "osv"]));
+    assert_eq!((github.requests().len(), osv.requests().len()), (github_before, osv_before), "the second scan is answered from the cache");
+}
+
+#[test]
+fn a_failing_source_warns_and_the_other_answers_uncached() {
+    for (failing, answering, source, warning) in [("osv", "github", "github", "acme/tool OSV: "), ("github", "osv", "osv", "acme/tool GitHub advisories: ")] {
+        let (broken, working) = (MockServer::answering(500), MockServer::replaying("vulnerable.json"));
+        let (github, osv) = if failing == "osv" { (&working, &broken) } else { (&broken, &working) };
+        let repo = repo("api-url-one-source");
+        let output = scan_both(&repo, github, osv);
+        assert_eq!(advisory(&output)["sources"], serde_json::json!([source]), "{} failing", failing);
+        let notes = stderr(&output);
+        assert!(notes.contains(&format!("Warning: {}", warning)) && notes.contains("(using the other source)"), "{} failing: {}", failing, notes);
+
+        // Neither source's result was cached, so the next scan asks both again
+        let (asked, retried) = (working.requests().len(), broken.requests().len());
+        let again = scan_both(&repo, github, osv);
+        assert_eq!(advisory(&again)["sources"], serde_json::json!([source]), "{} failing", failing);
+        assert!(working.requests().len() > asked, "{} answered from the cache after {} failed", answering, failing);
+        assert!(broken.requests().len() > retried, "{} was not asked again", failing);
+    }
+}
@@ -0,0 +1,219 @@
+//! `--baseline`, `--write-baseline`, `--update-baseline` and `--diff`: accepted findings stop
+//! failing while new ones still do. Findings matched against an earlier scan compare actions in
+//! their canonical form, so respelling a `uses:` the way GitHub resolves it the same resurfaces
+//! nothing, and reordering steps keeps every finding's fingerprint
+
+mod common;
+
+use common::*;
+
+/// An action as first written, its respelling, and the flags under which it has findings: owner
+/// and repository case, and a Docker Hub image with and without `docker.io/library/`
+const RESPELLED: [(&str, &str, &[&str]); 2] = [
+    ("ACME/Deploy@v1", "acme/deploy@v1", &[]),
+    ("docker://alpine:3.19", "docker://docker.io/library/alpine:3.19", &["--strict"]),
+];
+
+#[test]
+fn baseline_covers_a_respelled_action() {
+    for (before, after, flags) in RESPELLED {
+        let repo = Repo::new("baseline-respelled", &[("ci.yml", &workflow_using(&[before]))]);
+        let baseline = repo.root.join("baseline.json");
+        let mut args = flags.to_vec();
+        args.extend(["--write-baseline", baseline.to_str().unwrap()]);
+        let output = repo.scan(&args);
+        assert_eq!(code(&output), 0, "{}: {}", before, stderr(&output));
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&baseline).unwrap()).unwrap();
+        let entries = written["entries"].as_array().unwrap();
+        assert!(!entries.is_empty(), "{}", before);
+        assert!(entries.iter().all(|entry| entry["action"] == before), "the baseline keeps {} as written: {}", before, written);
+
+        write_files(&repo.workflows(), &[("ci.yml", &workflow_using(&[after]))]);
+        assert_ne!(code(&repo.scan(flags)), 0, "{} fails without the baseline", after);
+        let mut args = flags.to_vec();
+        args.extend(["--format", "json", "--baseline", baseline.to_str().unwrap()]);
+        let output = repo.scan(&args);
+        assert_eq!(code(&output), 0, "{}: {}", after, stderr(&output));
+        let report = json(&output);
+        let findings: Vec<&serde_json::Value> = report["findings"].as_array().unwrap().iter().filter(|f| f["action"] == after).collect();
+        assert!(!findings.is_empty(), "{}: {}", after, report);
+        assert!(findings.iter().all(|f| f["suppression"]["baseline"] == true), "{}: {:?}", after, findings);
+    }
+}
+
+#[test]
+fn diff_counts_a_respelled_action_as_unchanged() {
+    for (before, after, flags) in RESPELLED {
+        let repo = Repo::new("diff-respelled", &[("ci.yml", &workflow_using(&[after]))]);
+        let base = repo.root.join("base");
+        write_files(&base, &[("ci.yml", &workflow_using(&[before]))]);
+        let mut args = flags.to_vec();
+        args.extend(["--format", "json", "--diff", "base", ".github/workflows"]);
+        let output = run(repo.command(&args), None);
+        assert_eq!(code(&output), 0, "{}: {}", after, stderr(&output));
+        assert_eq!(json(&output)["diff"]["unchanged_actions"], serde_json::json!([after]), "{}", after);
+    }
+
+    // A changed ref is a change however the rest is spelled
+    let repo = Repo::new("diff-respelled-ref", &[("ci.yml", &workflow_using(&["acme/deploy@v2"]))]);
+    write_files(&repo.root.join("base"), &[("ci.yml", &workflow_using(&["ACME/Deploy@v1"]))]);
+    let output = run(repo.command(&["--format", "json", "--diff", "base", ".github/workflows"]), None);
+    assert_ne!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(json(&output)["diff"]["unchanged_actions"], serde_json::json!([]));
+}
+
+/// A repository using `uses` with a baseline of its findings written at `baseline.json`
+fn baselined(name: &str, uses: &[&str]) -> Repo {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(uses))]);
+    let output = repo.scan(&["--write-baseline", "baseline.json"]);
+    assert_eq!(code(&output), 0, "writing the baseline passes: {}", stderr(&output));
+    repo
+}
+
+fn entries(repo: &Repo) -> Vec<serde_json::Value> {
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(repo.root.join("baseline.json")).unwrap()).unwrap();
+    written["entries"].as_array().unwrap().clone()
+}
+
+#[test]
+fn baselined_findings_are_reported_but_do_not_fail() {
+    let repo = baselined("baseline-accepted", &["acme/deploy@v1"]);
+    let recorded: Vec<String> = entries(&repo).iter().map(|e| format!("{} {} {}", e["file"], e["action"], e["rule"])).collect();
+    assert_eq!(
+        recorded,
+        [
+            "\".github/workflows/ci.yml\" \"acme/deploy@v1\" \"untrusted-owner\"",
+            "\".github/workflows/ci.yml\" \"acme/deploy@v1\" \"untrusted-owner-no-sha\"",
+        ]
+    );
+    assert_ne!(code(&repo.scan(&[])), 0);
+    let output = repo.scan(&["--baseline", "baseline.json"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(stdout(&output).contains("acme/deploy@v1"), "baselined findings are still printed: {}", stdout(&output));
+}
+
+#[test]
+fn a_new_use_or_a_new_kind_of_finding_still_fails() {
+    let repo = baselined("baseline-new-use", &["acme/deploy@v1"]);
+    write_files(&repo.workflows(), &[("release.yml", &workflow_using(&["acme/deploy@v1"]))]);
+    let report = json(&repo.scan(&["--baseline", "baseline.json", "--format", "json"]));
+    let blocking: Vec<&serde_json::Value> = report["findings"].as_array().unwrap().iter().filter(|f| f["suppression"].is_null() && f["level"] == "error").collect();
+    assert!(!blocking.is_empty() && blocking.iter().all(|f| f["file"] == ".github/workflows/release.yml"), "{}", report["findings"]);
+
+    // Baselined while only mis-pinned, acme/tool@v1.1.0 regresses when its advisory is published
+    let repo = baselined("baseline-regressed", &["acme/tool@v1.1.0"]);
+    let workflows = repo.workflows();
+    let output = run(repo.replaying("vulnerable.json", &["--baseline", "baseline.json", "--format", "json", workflows.to_str().unwrap()]), None);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    let report = json(&output);
+    let vulnerability = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "known-vulnerability").unwrap();
+    assert!(vulnerability["suppression"].is_null(), "{}", vulnerability);
+}
+
+#[test]
+fn a_baseline_keyed_on_zizmor_ids_covers_findings_under_rule_id_compat() {
+    let repo = Repo::new("baseline-compat", &[("ci.yml", &workflow_using(&["acme/deploy@v1"]))]);
+    let entry = |rule: &str| serde_json::json!({"file": ".github/workflows/ci.yml", "action": "acme/deploy@v1", "rule": rule});
+    let migrated = serde_json::json!({"entries": [entry("forbidden-uses"), entry("unpinned-uses")]});
+    write_files(&repo.root, &[("baseline.json", &migrated.to_string())]);
+
+    let output = repo.scan(&["--baseline", "baseline.json"]);
+    assert_ne!(code(&output), 0, "zizmor ids mean nothing without --rule-id-compat: {}", stdout(&output));
+    let output = repo.scan(&["--baseline", "baseline.json", "--rule-id-compat", "zizmor", "--format", "json"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    let findings = report["findings"].as_array().unwrap();
+    assert!(findings.iter().filter(|f| f["action"] == "acme/deploy@v1").all(|f| f["suppression"]["baseline"] == true), "{}", report);
+    let stale = findings.iter().filter(|f| f["message"].as_str().unwrap().starts_with("baseline entry")).count();
+    assert_eq!(stale, 0, "both entries are used: {}", report);
+}
+
+#[test]
+fn resolved_entries_are_reported_and_update_baseline_removes_them() {
+    let repo = baselined("baseline-resolved", &["acme/deploy@v1", "acme/tool@v1.1.0"]);
+    assert_eq!(entries(&repo).len(), 4);
+    write_files(&repo.workflows(), &[("ci.yml", &workflow_using(&["acme/tool@v1.1.0"]))]);
+    let output = repo.scan(&["--baseline", "baseline.json"]);
+    let stale = stdout(&output).matches("matched no finding; --update-baseline removes it").count();
+    assert_eq!(stale, 2, "{}", stdout(&output));
+
+    let output = repo.scan(&["--baseline", "baseline.json", "--update-baseline"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(stderr(&output).contains("Removed 2 resolved entries from baseline baseline.json"), "{}", stderr(&output));
+    assert!(entries(&repo).iter().all(|e| e["action"] == "acme/tool@v1.1.0"), "{:?}", entries(&repo));
+    assert_eq!(entries(&repo).len(), 2);
+    assert!(!stdout(&repo.scan(&["--baseline", "baseline.json"])).contains("matched no finding"));
+}
+
+/// Two named third-party steps after a checkout, in the given order
+fn shuffled(order: &[usize]) -> String {
+    let steps = [
+        "      - uses: actions/checkout@v4\n",
+        "      - name: deploy\n        uses: acme/deploy@v1\n",
+        "      - name: notify\n        uses: acme/notify@main\n",
+    ];
+    let steps: String = order.iter().map(|i| steps[*i]).collect();
+    format!("on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n{}", steps)
+}
+
+fn fingerprints(report: &serde_json::Value) -> Vec<String> {
+    let mut fingerprints: Vec<String> = report["findings"].as_array().unwrap().iter().map(|f| f["fingerprint"].as_str().unwrap().to_string()).collect();
+    fingerprints.sort();
+    fingerprints
+}
+
+#[test]
+fn reordering_steps_keeps_fingerprints_and_the_baseline() {
+    let repo = Repo::new("baseline-shuffle", &[("ci.yml", &shuffled(&[0, 1, 2]))]);
+    let baseline = repo.root.join("baseline.json");
+    let before = json(&repo.scan(&["--format", "json", "--write-baseline", baseline.to_str().unwrap()]));
+    assert!(!fingerprints(&before).is_empty(), "{}", before);
+    let sarif = |repo: &Repo| -> Vec<String> {
+        let log = json(&repo.scan(&["--format", "sarif"]));
+        let mut found: Vec<String> = log["runs"][0]["results"].as_array().unwrap().iter().map(|r| r["partialFingerprints"]["findingFingerprint/v1"].as_str().unwrap().to_string()).collect();
+        found.sort();
+        found
+    };
+    let sarif_before = sarif(&repo);
+    assert_eq!(sarif_before, fingerprints(&before), "SARIF carries the same fingerprints");
+
+    for order in [[2, 1, 0], [1, 0, 2], [0, 2, 1]] {
+        write_files(&repo.workflows(), &[("ci.yml", &shuffled(&order))]);
+        let output = repo.scan(&["--format", "json", "--baseline", baseline.to_str().unwrap()]);
+        assert_eq!(code(&output), 0, "{:?}: {}", order, stderr(&output));
+        let after = json(&output);
+        assert_eq!(fingerprints(&after), fingerprints(&before), "{:?}", order);
+        assert!(after["findings"].as_array().unwrap().iter().all(|f| f["suppression"]["baseline"] == true), "{:?}: {}", order, after);
+        assert_eq!(sarif(&repo), sarif_before, "{:?}", order);
+    }
+}
+
+#[test]
+fn renaming_a_step_may_change_its_fingerprints() {
+    let repo = Repo::new("fingerprint-rename", &[("ci.yml", &shuffled(&[0, 1, 2]))]);
+    let before = fingerprints(&json(&repo.scan(&["--format", "json"])));
+    write_files(&repo.workflows(), &[("ci.yml", &shuffled(&[0, 1, 2]).replace("name: deploy", "name: ship"))]);
+    let after = fingerprints(&json(&repo.scan(&["--format", "json"])));
+    assert_eq!(before.len(), after.len());
+    assert_ne!(before, after);
+}
+
+#[test]
+fn the_baseline_is_subtracted_before_each_file_is_judged() {
+    let repo = Repo::new("baseline-verdicts", &[("ci.yml", &workflow_using(&["acme/deploy@v1"]))]);
+    let baseline = repo.root.join("baseline.json");
+    let baseline = baseline.to_str().unwrap();
+    assert_eq!(code(&repo.scan(&["--write-baseline", baseline])), 0);
+
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let before = json(&output)["files"][0].clone();
+    assert_eq!(before["passed"], false, "{}", before);
+
+    let output = repo.scan(&["--format", "json", "--baseline", baseline]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let after = json(&output)["files"][0].clone();
+    assert_eq!(after["passed"], true, "{}", after);
+    // The baselined findings are still counted, they just no longer fail the file
+    assert_eq!(after["counts"], before["counts"]);
+}
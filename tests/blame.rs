@@ -0,0 +1,84 @@
+//! `--blame`: who committed the line each finding points at, from `git blame` in the checkout
+//! the scan runs in, and the scan without blame where git is not installed
+
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::process::Command;
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .env("GIT_AUTHOR_DATE", "2023-11-02T10:00:00Z")
+        .env("GIT_COMMITTER_DATE", "2023-11-02T10:00:00Z")
+        .args(["-c", "user.name=Jane", "-c", "user.email=jane@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+}
+
+/// A checkout whose one commit adds a workflow using an untrusted action on line 7
+fn committed(name: &str) -> Repo {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@main"]))]);
+    git(&repo.root, &["init", "-q"]);
+    git(&repo.root, &["add", ".github"]);
+    git(&repo.root, &["commit", "-q", "-m", "Add CI"]);
+    repo
+}
+
+#[test]
+fn findings_carry_the_commit_that_introduced_their_line() {
+    let repo = committed("blame");
+    let output = run(repo.command(&["--blame", "--format", "json", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    let findings = report["findings"].as_array().unwrap();
+    assert!(!findings.is_empty(), "{}", report);
+    for finding in findings {
+        assert_eq!(finding["blame"]["author"], "jane@example.com", "{}", finding);
+        assert_eq!(finding["blame"]["date"], "2023-11-02", "{}", finding);
+        assert_eq!(finding["blame"]["sha"].as_str().unwrap().len(), 40, "{}", finding);
+    }
+
+    let text = run(repo.command(&["--blame", ".github/workflows"]), None);
+    let printed = stdout(&text);
+    assert!(printed.contains("\nIntroduced by:\n- .github/workflows/ci.yml:7: introduced by jane@ 2023-11-02 in "), "{}", printed);
+}
+
+#[test]
+fn uncommitted_and_untracked_lines_are_noted_not_blamed() {
+    let repo = committed("blame-uncommitted");
+    let changed = workflow_using(&["actions/checkout@v4", "acme/tool@main", "acme/other@main"]);
+    std::fs::write(repo.workflow("ci.yml"), changed).unwrap();
+    write_files(&repo.root, &[(".github/workflows/new.yml", &workflow_using(&["acme/new@main"]))]);
+    let output = run(repo.command(&["--blame", ".github/workflows"]), None);
+    let printed = stdout(&output);
+    assert!(printed.contains("- blame omitted for .github/workflows/ci.yml: 1 line(s) not committed yet"), "{}", printed);
+    assert!(printed.contains("- blame omitted for .github/workflows/new.yml: not tracked by git"), "{}", printed);
+    assert!(printed.contains("- .github/workflows/ci.yml:7: introduced by jane@"), "{}", printed);
+}
+
+#[test]
+fn blame_outside_a_checkout_is_an_error() {
+    let repo = Repo::new("blame-no-git", &[("ci.yml", &workflow_using(&["acme/tool@main"]))]);
+    let output = run(repo.command(&["--blame", ".github/workflows"]), None);
+    assert_ne!(code(&output), 0);
+    assert!(stderr(&output).contains("--blame needs a git checkout"), "{}", stderr(&output));
+}
+
+#[test]
+fn without_git_on_path_the_scan_warns_and_goes_on_without_blame() {
+    let repo = committed("blame-no-git-binary");
+    let empty = repo.root.join("empty-path");
+    std::fs::create_dir_all(&empty).unwrap();
+    let mut command = repo.command(&["--blame", "--format", "json", ".github/workflows"]);
+    command.env("PATH", &empty);
+    let output = run(command, None);
+    assert_eq!(code(&output), 2, "the findings decide the exit as without --blame: {}", stderr(&output));
+    let err = stderr(&output);
+    assert!(err.contains("Warning: --blame needs the git command, which is not installed or not on PATH; findings are reported without blame"), "{}", err);
+    let report = json(&output);
+    assert!(report["findings"].as_array().unwrap().iter().all(|f| f.get("blame").is_none()), "{}", report);
+}
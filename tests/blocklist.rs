@@ -0,0 +1,54 @@
+//! blocked_actions and blocked_owners: a use of a banned action fails the scan whatever its
+//! pinning, and the report lists it under its own heading with the reason
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str, uses: &[&str], config: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config(&format!("trusted_owners: [actions, tj-actions]\n{}", config))
+}
+
+#[test]
+fn a_blocked_action_fails_even_when_pinned_to_a_sha() {
+    let pinned = format!("tj-actions/changed-files@{}", SHA);
+    let config = "blocked_actions:\n  - name: tj-actions/changed-files\n    reason: compromised in March 2025\n";
+    let output = repo("blocked-action", &[&pinned, &format!("actions/checkout@{}", SHA)], config).scan(&["--ascii"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let out = stdout(&output);
+    let listing = &out[out.find("Blocked dependencies:").unwrap_or_else(|| panic!("{}", out))..];
+    assert!(
+        listing.contains(&format!(
+            "Action {} is blocked by blocked_actions entry tj-actions/changed-files: compromised in March 2025",
+            pinned
+        )),
+        "{}",
+        out
+    );
+    assert!(!listing.contains("actions/checkout"), "{}", out);
+}
+
+#[test]
+fn an_entry_with_a_ref_bans_only_that_release() {
+    let config = "blocked_actions: [tj-actions/changed-files@v45]\n";
+    let banned = repo("blocked-release", &["tj-actions/changed-files@v45"], config).scan(&["--ascii"]);
+    assert_eq!(code(&banned), 2, "{}", stderr(&banned));
+    assert!(stdout(&banned).contains("Blocked dependencies:"), "{}", stdout(&banned));
+
+    let other = repo("blocked-other-release", &["tj-actions/changed-files@v46"], config).scan(&["--ascii"]);
+    assert_eq!(code(&other), 0, "{}", stdout(&other));
+    assert!(!stdout(&other).contains("Blocked dependencies:"), "{}", stdout(&other));
+}
+
+#[test]
+fn a_blocked_owner_bans_its_actions_past_the_ignore_file() {
+    let repo = repo("blocked-owner", &[&format!("evil/tool@{}", SHA)], "blocked_owners: [Evil]\n");
+    write_files(&repo.root, &[("ignore.yaml", "- action: evil/tool\n  reason: accepted\n")]);
+    let output = repo.scan(&["--ascii", "--ignore-file", "ignore.yaml", "--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["exit"]["reason"], "1 blocked dependency (blocked_actions, blocked_owners)", "{}", report);
+    let blocked: Vec<_> = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "blocked-dependency").collect();
+    assert_eq!(blocked.len(), 1, "{}", report);
+    assert!(blocked[0].get("suppression").is_none(), "{}", report);
+}
@@ -0,0 +1,99 @@
+//! A workflow directory mixing a good workflow with broken, empty and non-mapping files: the good
+//! one is fully scanned and the broken one is reported where the parser stopped
+
+mod common;
+
+use common::*;
+
+const GOOD: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/tool@main\n";
+/// An unclosed flow sequence on line 6
+const BROKEN: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: [actions/checkout@v4\n";
+
+fn mixed(name: &str) -> Repo {
+    Repo::new(name, &[("good.yml", GOOD), ("broken.yml", BROKEN), ("empty.yml", ""), ("comment.yml", "# nothing here yet\n"), ("list.yml", "- on\n- jobs\n")])
+}
+
+fn located(report: &serde_json::Value) -> Vec<(&str, &str, Option<u64>)> {
+    let findings = report["findings"].as_array().unwrap().iter();
+    findings.map(|f| (f["rule"].as_str().unwrap(), f["file"].as_str().unwrap_or(""), f["line"].as_u64())).collect()
+}
+
+#[test]
+fn a_broken_workflow_does_not_stop_the_others_from_being_scanned() {
+    let repo = mixed("broken-mixed");
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 10, "an unparsable workflow is an operational error: {}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(
+        located(&report),
+        [
+            ("invalid-workflow", ".github/workflows/broken.yml", Some(7)),
+            ("untrusted-owner", ".github/workflows/good.yml", Some(7)),
+            ("untrusted-owner-no-sha", ".github/workflows/good.yml", Some(7)),
+        ],
+        "{}",
+        report
+    );
+    let invalid = &report["findings"][0]["message"];
+    assert!(invalid.as_str().unwrap().starts_with("Could not parse workflow: 7:1 "), "{}", invalid);
+    assert_eq!(report["summary"]["workflows_scanned"], 5, "{}", report);
+
+    let notes = stderr(&output);
+    for name in ["empty.yml", "comment.yml"] {
+        assert!(notes.contains(&format!(".github/workflows/{} is empty or only comments; no actions to check", name)), "{}", notes);
+    }
+    assert!(notes.contains(".github/workflows/list.yml is not a mapping of keys such as on and jobs; no actions to check"), "{}", notes);
+}
+
+#[test]
+fn without_the_broken_file_the_scan_passes() {
+    let repo = mixed("broken-removed");
+    std::fs::remove_file(repo.workflow("broken.yml")).unwrap();
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert!(!located(&json(&output)).iter().any(|(rule, _, _)| *rule == "invalid-workflow"));
+}
+
+#[cfg(unix)]
+#[test]
+fn a_workflow_at_a_non_utf8_path_is_scanned_under_a_lossy_name() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let repo = Repo::new("broken-non-utf8", &[]);
+    let path = repo.workflows().join(OsStr::from_bytes(b"ci-\xff.yml"));
+    std::fs::create_dir_all(repo.workflows()).unwrap();
+    std::fs::write(&path, GOOD).unwrap();
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    assert!(located(&report).contains(&("untrusted-owner-no-sha", ".github/workflows/ci-\u{fffd}.yml", Some(7))), "{}", report);
+}
+
+#[test]
+fn a_bom_crlf_or_comment_header_leaves_the_uses_lines_where_they_are() {
+    let header = format!("# Build on every push\n#\n{}", GOOD);
+    let crlf = GOOD.replace('\n', "\r\n");
+    let bom_crlf = format!("\u{feff}{}", crlf);
+    for (case, source, line) in [
+        ("a BOM", format!("\u{feff}{}", GOOD), 7),
+        ("a comment header", header, 9),
+        ("CRLF line endings", crlf, 7),
+        ("a BOM and CRLF line endings", bom_crlf, 7),
+    ] {
+        let repo = Repo::new("broken-encoding", &[("ci.yml", &source), ("comment.yml", "# nothing here yet\r\n")]);
+        let output = repo.scan(&["--format", "json"]);
+        assert_eq!(code(&output), 2, "{}: {}", case, stderr(&output));
+        let report = json(&output);
+        assert_eq!(
+            located(&report),
+            [("untrusted-owner", ".github/workflows/ci.yml", Some(line)), ("untrusted-owner-no-sha", ".github/workflows/ci.yml", Some(line))],
+            "{}: {}",
+            case,
+            report
+        );
+        let actions: Vec<&str> = report["actions"].as_array().unwrap().iter().map(|a| a["action"].as_str().unwrap()).collect();
+        assert_eq!(actions, ["acme/tool@main", "actions/checkout@v4"], "{}: no BOM or \\r in the references", case);
+        assert!(stderr(&output).contains(".github/workflows/comment.yml is empty or only comments"), "{}", case);
+    }
+}
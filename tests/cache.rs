@@ -0,0 +1,86 @@
+//! The on-disk cache of API results between scans, `--no-cache`, `--cache-clear`, and one
+//! advisory query per repository within a scan, against a local server
+
+mod common;
+
+use common::*;
+
+/// Lookups answered by a server replaying tests/fixtures/http/vulnerable.json, with GitHub's
+/// advisories only
+fn scan(repo: &Repo, server: &MockServer, args: &[&str]) -> std::process::Output {
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+fn advisory_queries(server: &MockServer) -> usize {
+    server.requests().iter().filter(|r| r.path.contains("/advisories")).count()
+}
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["acme/tool@v1.1.0"]))]).config("trusted_owners: [actions, acme]\n")
+}
+
+#[test]
+fn a_second_scan_is_answered_from_the_cache() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("cache-hit");
+    let first = scan(&repo, &server, &[]);
+    assert_eq!(code(&first), 1, "{}", stderr(&first));
+    assert_eq!(advisory_queries(&server), 1);
+    let second = scan(&repo, &server, &["--verbose"]);
+    assert_eq!(code(&second), 1, "{}", stderr(&second));
+    assert_eq!(advisory_queries(&server), 1, "the cached answer is used");
+    assert!(stderr(&second).contains("Cache hit: "), "{}", stderr(&second));
+    assert_eq!(json(&second)["findings"], json(&first)["findings"]);
+}
+
+#[test]
+fn no_cache_neither_reads_nor_writes_the_cache() {
+    let server = MockServer::replaying("vulnerable.json");
+    let cached = repo("cache-off");
+    scan(&cached, &server, &[]);
+    let output = scan(&cached, &server, &["--no-cache"]);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    assert_eq!(advisory_queries(&server), 2);
+
+    let fresh = repo("cache-off-fresh");
+    scan(&fresh, &server, &["--no-cache"]);
+    assert!(!fresh.root.join("cache").exists(), "--no-cache wrote a cache");
+}
+
+#[test]
+fn cache_clear_removes_the_entries_before_the_scan() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("cache-clear");
+    scan(&repo, &server, &[]);
+    let output = scan(&repo, &server, &["--cache-clear"]);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    assert!(stderr(&output).contains("Cleared "), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("Cleared 0 cache entries"), "{}", stderr(&output));
+    assert_eq!(advisory_queries(&server), 2, "the cleared answer is asked for again");
+}
+
+#[test]
+fn a_corrupted_entry_is_looked_up_again() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("cache-corrupted");
+    scan(&repo, &server, &[]);
+    for entry in std::fs::read_dir(repo.root.join("cache")).unwrap().flat_map(|ns| std::fs::read_dir(ns.unwrap().path()).into_iter().flatten()) {
+        std::fs::write(entry.unwrap().path(), "{\"stored_at\": ").unwrap();
+    }
+    let output = scan(&repo, &server, &[]);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    assert_eq!(advisory_queries(&server), 2);
+}
+
+#[test]
+fn every_pin_of_a_repository_shares_one_advisory_query() {
+    let server = MockServer::replaying("vulnerable.json");
+    let uses = ["acme/tool@v1.1.0", "acme/tool@v1.0.0", "acme/tool@v1.2.0", "acme/tool/sub@v1.1.0"];
+    let repo = Repo::new("cache-memo", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions, acme]\n");
+    let output = scan(&repo, &server, &["--no-cache", "--concurrency", "4"]);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    assert_eq!(advisory_queries(&server), 1);
+}
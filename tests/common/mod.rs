@@ -0,0 +1,323 @@
+//! Helpers for running the scanner binary against workflow fixtures without the network
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A scratch directory under the system temp dir, unique to the test that made it and removed
+/// with everything in it when dropped
+#[derive(Debug)]
+pub struct Scratch(PathBuf);
+
+impl std::ops::Deref for Scratch {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for Scratch {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn scratch(name: &str) -> Scratch {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "scanner-test-{}-{}-{}",
+        std::process::id(),
+        name,
+        NEXT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    Scratch(dir)
+}
+
+/// Write `files` (relative path, content) under `dir`, creating directories as needed
+pub fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+}
+
+/// A repository fixture: a `.github/workflows` directory holding `workflows`, and a config
+/// trusting the `actions` owner unless `config` gives one
+pub struct Repo {
+    pub root: Scratch,
+}
+
+impl Repo {
+    pub fn new(name: &str, workflows: &[(&str, &str)]) -> Repo {
+        let root = scratch(name);
+        for (file, content) in workflows {
+            write_files(&root, &[(&format!(".github/workflows/{}", file), content)]);
+        }
+        write_files(&root, &[("scanner.yml", "trusted_owners: [actions]\n")]);
+        Repo { root }
+    }
+
+    pub fn config(self, config: &str) -> Repo {
+        write_files(&self.root, &[("scanner.yml", config)]);
+        self
+    }
+
+    pub fn workflows(&self) -> PathBuf {
+        self.root.join(".github/workflows")
+    }
+
+    pub fn workflow(&self, file: &str) -> PathBuf {
+        self.workflows().join(file)
+    }
+
+    /// The scanner run offline against the fixture's config and its own cache, with `args` after
+    /// the defaults; no workflow path is added, so `args` names what to scan
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = self.command_without_config(&[]);
+        command.arg("--config").arg(self.root.join("scanner.yml")).args(args);
+        command
+    }
+
+    /// As [`Repo::command`], leaving the config to the scanner's own lookup
+    pub fn command_without_config(&self, args: &[&str]) -> Command {
+        let mut command = self.online();
+        command.arg("--offline").args(args);
+        command
+    }
+
+    /// The scanner run against the fixture's config with every request answered from the
+    /// recorded `fixture` in tests/fixtures/http, so lookups run without network access
+    pub fn replaying(&self, fixture: &str, args: &[&str]) -> Command {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/http").join(fixture);
+        let mut command = self.online();
+        command.arg("--config").arg(self.root.join("scanner.yml")).arg("--replay-http").arg(fixture).args(args);
+        command
+    }
+
+    /// The scanner run against the fixture's config with lookups sent wherever `args` point
+    /// them, such as a local server's `--api-url`
+    pub fn networked(&self, args: &[&str]) -> Command {
+        let mut command = self.online();
+        command.arg("--config").arg(self.root.join("scanner.yml")).args(args);
+        command
+    }
+
+    /// The scanner with the fixture's environment and cache, and the network left to the caller
+    fn online(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_gh-action-security-scanner"));
+        command
+            .current_dir(&self.root)
+            .env_remove("GITHUB_TOKEN")
+            .env_remove("GITHUB_API_URL")
+            .env_remove("GITHUB_WORKSPACE")
+            .env_remove("GITHUB_ACTIONS")
+            .env_remove("GITHUB_EVENT_PATH")
+            .env_remove("GITHUB_STEP_SUMMARY")
+            .env_remove("DEPENDENCY_SCANNER_CONFIG")
+            .env("NO_COLOR", "1")
+            .arg("--cache-dir")
+            .arg(self.root.join("cache"));
+        command
+    }
+
+    /// Scan the fixture's workflow directory
+    pub fn scan(&self, args: &[&str]) -> Output {
+        let workflows = self.workflows();
+        let mut all = args.to_vec();
+        all.push(workflows.to_str().unwrap());
+        run(self.command(&all), None)
+    }
+}
+
+/// Run the command, feeding it `stdin` when given, and wait for it
+pub fn run(mut command: Command, stdin: Option<&[u8]>) -> Output {
+    command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = command.spawn().expect("scanner binary runs");
+    if let Some(input) = stdin {
+        child.stdin.take().unwrap().write_all(input).unwrap();
+    }
+    child.wait_with_output().unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+pub fn code(output: &Output) -> i32 {
+    output.status.code().expect("scanner exits rather than being killed")
+}
+
+/// The `--format json` report on stdout
+pub fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|e| panic!("stdout is not JSON ({}):\n{}\nstderr:\n{}", e, stdout(output), stderr(output)))
+}
+
+/// The rule ids of the report's findings, in report order
+pub fn rules(report: &serde_json::Value) -> Vec<String> {
+    report["findings"].as_array().unwrap().iter().map(|f| f["rule"].as_str().unwrap().to_string()).collect()
+}
+
+/// A workflow of one job whose steps use each of `uses`
+pub fn workflow_using(uses: &[&str]) -> String {
+    let steps: String = uses.iter().map(|u| format!("      - uses: {}\n", u)).collect();
+    format!("on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n{}", steps)
+}
+
+/// A full commit SHA for fixtures
+pub const SHA: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// One request a [`MockServer`] was sent
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub method: String,
+    /// Path and query, as sent
+    pub path: String,
+    /// Whether it carried `Authorization: Bearer test-token`
+    pub authorized: bool,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// An HTTP server on a loopback port, standing in for GitHub, OSV or a Pushgateway, that
+/// records every request it is sent
+pub struct MockServer {
+    /// `http://127.0.0.1:<port>`
+    pub base: String,
+    requests: Arc<Mutex<Vec<Received>>>,
+}
+
+impl MockServer {
+    /// Answer every request the recorded `fixture` in tests/fixtures/http answers, matched on the
+    /// method, the end of the path and the body, wherever the server is mounted; 404 to the rest
+    pub fn replaying(fixture: &str) -> MockServer {
+        let interactions = interactions(fixture);
+        MockServer::start(move |request, _| replayed(&interactions, request))
+    }
+
+    /// Answer every request with `status` and an empty body
+    pub fn answering(status: u16) -> MockServer {
+        MockServer::start(move |_, _| (status, String::new(), String::new()))
+    }
+
+    /// Answer every request with `status` and a GitHub error body whose message is `message`
+    pub fn failing(status: u16, message: &str) -> MockServer {
+        let body = serde_json::json!({ "message": message }).to_string();
+        MockServer::start(move |_, _| (status, String::new(), body.clone()))
+    }
+
+    /// Refuse the first `refusals` requests as GitHub does once the primary rate limit is spent,
+    /// with a 403, `x-ratelimit-remaining: 0` and a `retry-after` of `retry_after` seconds, then
+    /// answer as [`MockServer::replaying`] the `fixture`
+    pub fn rate_limited(refusals: usize, retry_after: u64, fixture: &str) -> MockServer {
+        let interactions = interactions(fixture);
+        MockServer::start(move |request, index| {
+            if index < refusals {
+                let headers = format!("x-ratelimit-remaining: 0\r\nx-ratelimit-reset: 0\r\nretry-after: {}\r\n", retry_after);
+                return (403, headers, "{\"message\": \"API rate limit exceeded\"}".to_string());
+            }
+            replayed(&interactions, request)
+        })
+    }
+
+    /// Answer each request, given with how many came before it, as a status, extra header lines
+    /// and a body
+    fn start(answer: impl Fn(&Received, usize) -> (u16, String, String) + Send + 'static) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let Some(request) = read_request(&mut stream) else { continue };
+                let index = log.lock().unwrap().len();
+                let (status, headers, content) = answer(&request, index);
+                log.lock().unwrap().push(request);
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    content.len(),
+                    content
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        MockServer { base, requests }
+    }
+
+    /// The requests so far, in the order they arrived
+    pub fn requests(&self) -> Vec<Received> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// The interactions recorded in tests/fixtures/http/`fixture`
+fn interactions(fixture: &str) -> Vec<serde_json::Value> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/http").join(fixture);
+    let recorded: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    recorded["interactions"].as_array().unwrap().clone()
+}
+
+/// The recorded answer to the request, matched on the method, the end of the path and the body
+fn replayed(interactions: &[serde_json::Value], request: &Received) -> (u16, String, String) {
+    let body: Option<serde_json::Value> = serde_json::from_str(&request.body).ok();
+    let answer = interactions.iter().find(|i| {
+        let url = i["url"].as_str().unwrap();
+        let host_and_path = &url[url.find("://").unwrap() + 3..];
+        let recorded_path = &host_and_path[host_and_path.find('/').unwrap()..];
+        i["method"] == request.method.as_str() && request.path.ends_with(recorded_path) && (i.get("body").is_none() || i.get("body") == body.as_ref())
+    });
+    match answer {
+        Some(i) => (i["response"]["status"].as_u64().unwrap() as u16, String::new(), i["response"]["body"].as_str().unwrap().to_string()),
+        None => (404, String::new(), "{\"message\": \"Not Found\"}".to_string()),
+    }
+}
+
+/// One HTTP/1.1 request with a `Content-Length` body, or `None` if the client hung up
+fn read_request(stream: &mut std::net::TcpStream) -> Option<Received> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = (parts.next()?.to_string(), parts.next()?.to_string());
+    let (mut length, mut authorized, mut content_type) = (0, false, None);
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => length = value.trim().parse().ok()?,
+            "authorization" => authorized = value.trim() == "Bearer test-token",
+            "content-type" => content_type = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(Received { method, path, authorized, content_type, body: String::from_utf8_lossy(&body).into_owned() })
+}
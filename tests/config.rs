@@ -0,0 +1,381 @@
+//! Where the root config comes from: `--config`, then `$DEPENDENCY_SCANNER_CONFIG`, then
+//! `./critical_dependencies.yaml`, and which of them may be missing
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    write_files(&repo.root, &[("from-flag.yml", "trusted_owners: [actions]\n"), ("from-env.yml", "trusted_owners: [actions]\n")]);
+    repo
+}
+
+fn config_used(output: &std::process::Output) -> String {
+    let report = json(output);
+    report["configs"].as_object().unwrap().values().next().unwrap().as_str().unwrap().to_string()
+}
+
+#[test]
+fn flag_wins_over_environment_and_default() {
+    let repo = repo("config-flag");
+    write_files(&repo.root, &[("critical_dependencies.yaml", "{}\n")]);
+    let mut command = repo.command_without_config(&["--config", "from-flag.yml", "--format", "json", ".github/workflows"]);
+    command.env("DEPENDENCY_SCANNER_CONFIG", "from-env.yml");
+    let output = run(command, None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(config_used(&output), "from-flag.yml");
+}
+
+#[test]
+fn environment_wins_over_default() {
+    let repo = repo("config-env");
+    write_files(&repo.root, &[("critical_dependencies.yaml", "{}\n")]);
+    let mut command = repo.command_without_config(&["--format", "json", ".github/workflows"]);
+    command.env("DEPENDENCY_SCANNER_CONFIG", "from-env.yml");
+    let output = run(command, None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(config_used(&output), "from-env.yml");
+}
+
+#[test]
+fn default_file_is_used_when_nothing_names_one() {
+    let repo = repo("config-default");
+    write_files(&repo.root, &[("critical_dependencies.yaml", "{}\n")]);
+    let output = run(repo.command_without_config(&["--format", "json", ".github/workflows"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(config_used(&output), "critical_dependencies.yaml");
+}
+
+#[test]
+fn missing_default_file_only_warns() {
+    let repo = repo("config-default-missing");
+    let output = run(repo.command_without_config(&["--format", "json", ".github/workflows"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(stderr(&output).contains("Could not load config file critical_dependencies.yaml"), "{}", stderr(&output));
+}
+
+#[test]
+fn missing_file_named_by_flag_is_an_error() {
+    let repo = repo("config-flag-missing");
+    let output = run(repo.command_without_config(&["--config", "typo.yml", ".github/workflows"]), None);
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("typo.yml (from --config)"), "{}", stderr(&output));
+}
+
+#[test]
+fn missing_file_named_by_environment_is_an_error() {
+    let repo = repo("config-env-missing");
+    let mut command = repo.command_without_config(&[".github/workflows"]);
+    command.env("DEPENDENCY_SCANNER_CONFIG", "typo.yml");
+    let output = run(command, None);
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("typo.yml (from $DEPENDENCY_SCANNER_CONFIG)"), "{}", stderr(&output));
+}
+
+#[test]
+fn unparsable_file_named_by_flag_is_an_error() {
+    let repo = repo("config-flag-broken");
+    write_files(&repo.root, &[("broken.yml", "trusted_owners: [actions\n")]);
+    let output = run(repo.command_without_config(&["--config", "broken.yml", ".github/workflows"]), None);
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("broken.yml"), "{}", stderr(&output));
+}
+
+const SCOPED_OVERRIDES: &str = "trusted_owners: [actions]\nrule_overrides:\n  - {rule: untrusted-owner, level: error}\n  - {rule: untrusted-owner, workflow: deploy-*, level: note}\n  - {rule: untrusted-owner-no-sha, action: acme/*, level: warning}\n";
+
+#[test]
+fn rule_overrides_resolve_per_finding_site() {
+    let uses = workflow_using(&["actions/checkout@v4", "acme/tool@v1", "other/tool@v1"]);
+    let repo = Repo::new("rule-overrides", &[("ci.yml", &uses), ("deploy-prod.yml", &uses)]).config(SCOPED_OVERRIDES);
+    let report = json(&repo.scan(&["--format", "json"]));
+    let mut levels: Vec<(String, String, String, String)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| {
+            let file = f["file"].as_str().unwrap().rsplit('/').next().unwrap().to_string();
+            (file, f["action"].as_str().unwrap().to_string(), f["rule"].as_str().unwrap().to_string(), f["level"].as_str().unwrap().to_string())
+        })
+        .collect();
+    levels.sort();
+    let expected: Vec<(String, String, String, String)> = [
+        ("ci.yml", "acme/tool@v1", "untrusted-owner", "error"),
+        ("ci.yml", "acme/tool@v1", "untrusted-owner-no-sha", "warning"),
+        ("ci.yml", "other/tool@v1", "untrusted-owner", "error"),
+        ("ci.yml", "other/tool@v1", "untrusted-owner-no-sha", "error"),
+        ("deploy-prod.yml", "acme/tool@v1", "untrusted-owner", "note"),
+        ("deploy-prod.yml", "acme/tool@v1", "untrusted-owner-no-sha", "warning"),
+        ("deploy-prod.yml", "other/tool@v1", "untrusted-owner", "note"),
+        ("deploy-prod.yml", "other/tool@v1", "untrusted-owner-no-sha", "error"),
+    ]
+    .iter()
+    .map(|(a, b, c, d)| (a.to_string(), b.to_string(), c.to_string(), d.to_string()))
+    .collect();
+    assert_eq!(levels, expected);
+}
+
+#[test]
+fn config_check_prints_the_resolved_rule_levels_for_a_site() {
+    let repo = Repo::new("rule-overrides-check", &[("deploy-prod.yml", &workflow_using(&["acme/tool@v1"]))]).config(SCOPED_OVERRIDES);
+    let output = run(repo.command(&["config", "check", ".github/workflows/deploy-prod.yml", "--action", "acme/tool@v1"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let printed = stdout(&output);
+    let comments: Vec<&str> = printed.lines().filter(|l| l.starts_with("# ") && !l.starts_with("# Effective")).collect();
+    assert_eq!(
+        comments,
+        [
+            "# Rule overrides for acme/tool@v1 in .github/workflows/deploy-prod.yml",
+            "# trigger surface: contributors (push)",
+            "# untrusted-owner: note (entry 2: workflow deploy-*)",
+            "# untrusted-owner-no-sha: warning (entry 3: action acme/*)",
+        ]
+    );
+
+    let output = run(repo.command(&["config", "check", "--action", "other/tool@v1"]), None);
+    let printed = stdout(&output);
+    let comments: Vec<&str> = printed.lines().filter(|l| l.starts_with("# ") && !l.starts_with("# Effective")).collect();
+    assert_eq!(comments, ["# Rule overrides for other/tool@v1", "# untrusted-owner: error (entry 1, unscoped)"]);
+
+    let repo = Repo::new("rule-overrides-none", &[]);
+    let output = run(repo.command(&["config", "check", "--action", "other/tool@v1"]), None);
+    assert!(stdout(&output).contains("# none apply; every rule keeps its built-in level"), "{}", stdout(&output));
+}
+
+#[test]
+fn each_profile_sets_its_documented_rule_levels() {
+    let uses = workflow_using(&["actions/checkout@v4", "actions/setup-node@main", "acme/tool@v1"]);
+    let repo = Repo::new("profiles", &[("ci.yml", &uses)]);
+    let levels = |args: &[&str]| -> Vec<(String, String, String)> {
+        let mut all = vec!["--format", "json"];
+        all.extend(args);
+        let report = json(&repo.scan(&all));
+        report["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| (f["rule"].as_str().unwrap().to_string(), f["action"].as_str().unwrap().to_string(), f["level"].as_str().unwrap().to_string()))
+            .collect()
+    };
+    let site = |rule: &str, action: &str, level: &str| (rule.to_string(), action.to_string(), level.to_string());
+    let untrusted = [site("untrusted-owner", "acme/tool@v1", "warning"), site("untrusted-owner-no-sha", "acme/tool@v1", "error")];
+    assert_eq!(levels(&[]), untrusted);
+    let mut baseline = vec![site("unstable-reference", "actions/setup-node@main", "warning")];
+    baseline.extend(untrusted.clone());
+    assert_eq!(levels(&["--profile", "baseline"]), baseline);
+    let mut strict = vec![site("unstable-reference", "actions/setup-node@main", "error")];
+    strict.extend(untrusted);
+    assert_eq!(levels(&["--profile", "strict"]), strict);
+    assert_eq!(
+        levels(&["--profile", "paranoid"]),
+        [
+            site("tag-pinned-critical", "actions/checkout@v4", "error"),
+            site("unstable-reference", "actions/setup-node@main", "error"),
+            site("tag-pinned-critical", "acme/tool@v1", "error"),
+            site("untrusted-owner", "acme/tool@v1", "error"),
+            site("untrusted-owner-no-sha", "acme/tool@v1", "error"),
+        ]
+    );
+
+    // A key the config sets wins over the profile's
+    let repo = Repo::new("profiles-user-wins", &[("ci.yml", &uses)]).config("trusted_owners: [actions, acme]\nunstable_ref_level: note\n");
+    let report = json(&repo.scan(&["--format", "json", "--profile", "strict"]));
+    assert_eq!(report["findings"][0]["level"], "note", "{}", report);
+}
+
+#[test]
+fn config_check_and_profile_help_show_the_profiles() {
+    let repo = Repo::new("profiles-check", &[]);
+    let output = run(repo.command(&["--profile", "paranoid", "config", "check"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let printed = stdout(&output);
+    assert!(printed.contains("over the paranoid profile"), "{}", printed);
+    for key in ["critical_by_default: true", "fail_on_unchecked: true", "unstable_ref_level: error"] {
+        assert!(printed.contains(key), "{}: {}", key, printed);
+    }
+
+    let output = run(repo.command(&["--profile", "help"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    for profile in ["baseline:", "strict:", "paranoid:"] {
+        assert!(stdout(&output).contains(profile), "{}", stdout(&output));
+    }
+
+    let output = run(repo.command(&["--profile", "lax", "config", "check"]), None);
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("unknown profile lax"), "{}", stderr(&output));
+}
+
+#[test]
+fn trusted_repos_and_owner_globs_decide_trust_and_bad_globs_are_config_errors() {
+    let uses = workflow_using(&["docker/build-push-action@v5", "docker/login-action@v3", "acme-platform/deploy@v1"]);
+    let repo = Repo::new("trust-globs", &[("ci.yml", &uses)]).config("trusted_owners: [Acme-*]\ntrusted_repos: [docker/build-push-action]\n");
+    let report = json(&repo.scan(&["--format", "json"]));
+    let untrusted: Vec<&str> =
+        report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "untrusted-owner").map(|f| f["action"].as_str().unwrap()).collect();
+    assert_eq!(untrusted, ["docker/login-action@v3"], "{}", report);
+
+    let repo = repo.config("trusted_owners: ['acme-[']\n");
+    let output = repo.scan(&[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert!(stderr(&output).contains("trusted_owners entry acme-[:"), "{}", stderr(&output));
+}
+
+#[test]
+fn default_trust_applies_only_without_trusted_owners() {
+    let uses = workflow_using(&["actions/checkout@v4", "github/codeql-action/init@v3", "acme/tool@v1"]);
+    let untrusted = |output: &std::process::Output| -> Vec<String> {
+        let report = json(output);
+        report["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|f| f["rule"] == "untrusted-owner")
+            .map(|f| f["action"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let notice = "Using built-in default trust: actions, github";
+
+    let repo = Repo::new("default-trust", &[("ci.yml", &uses)]).config("unstable_ref_level: warning\n");
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(untrusted(&output), ["acme/tool@v1"]);
+    assert!(stderr(&output).contains(notice), "{}", stderr(&output));
+
+    let output = repo.scan(&["--format", "json", "--no-default-trust"]);
+    assert_eq!(untrusted(&output), ["actions/checkout@v4", "github/codeql-action/init@v3", "acme/tool@v1"]);
+    assert!(!stderr(&output).contains(notice), "{}", stderr(&output));
+
+    let repo = repo.config("trusted_owners: [acme]\n");
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(untrusted(&output), ["actions/checkout@v4", "github/codeql-action/init@v3"]);
+    assert!(!stderr(&output).contains(notice), "{}", stderr(&output));
+}
+
+#[test]
+fn pin_policies_set_the_pin_per_pattern_and_bad_levels_are_config_errors() {
+    let uses = workflow_using(&["actions/checkout@v4", "acme/deploy-prod@v1", "acme/internal@main", "acme/release@v2"]);
+    let config = "trusted_owners: [actions, acme]\npin_policies:\n  '*/*': {pin: tag}\n  acme/deploy-*: {pin: sha}\n  acme/internal: {pin: branch-allowed}\n  acme/release: {pin: sha, allowed_refs: [v2]}\n  acme/unused: {pin: any}\n";
+    let repo = Repo::new("pin-policies", &[("ci.yml", &uses)]).config(config);
+    let output = repo.scan(&["--format", "json", "--strict"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    let violations: Vec<(&str, &str)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|f| f["rule"] != "unused-config-entry")
+        .map(|f| (f["rule"].as_str().unwrap(), f["message"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        violations,
+        [("pin-policy", "Dependency acme/deploy-prod@v1 is pinned to a tag (v1), but pin_policies entry acme/deploy-* (pin: sha) requires a full commit SHA")],
+        "{}",
+        report
+    );
+    let unused: Vec<&str> =
+        report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "unused-config-entry").map(|f| f["message"].as_str().unwrap()).collect();
+    assert!(unused.len() == 1 && unused[0].contains("acme/unused"), "{:?}", unused);
+
+    let repo = repo.config("pin_policies:\n  acme/*: {pin: always}\n");
+    let output = repo.scan(&[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert!(stderr(&output).contains("pin_policies.acme/*.pin: does not match any allowed shape"), "{}", stderr(&output));
+}
+
+/// `config check --online` against a server knowing only the `actions` and `docker` accounts
+fn check_online(repo: &Repo, server: &MockServer) -> std::process::Output {
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "config", "check", "--online"]);
+    command.env("GITHUB_TOKEN", "test-token");
+    run(command, None)
+}
+
+#[test]
+fn online_check_warns_about_a_trusted_owner_that_is_not_an_account() {
+    let server = MockServer::replaying("accounts.json");
+    let repo = repo("config-online").config("trusted_owners: [actions, dokcer, \"acme-*\"]\n");
+    let output = check_online(&repo, &server);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let err = stderr(&output);
+    assert!(err.contains("Warning: trusted_owners entry dokcer is not a GitHub account; did you mean docker?"), "{}", err);
+    assert!(!err.contains("entry actions"), "{}", err);
+    // Globs are never looked up
+    let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, ["/api/v3/users/actions", "/api/v3/users/dokcer"]);
+}
+
+#[test]
+fn online_check_answers_again_from_the_cache() {
+    let server = MockServer::replaying("accounts.json");
+    let repo = repo("config-online-cached").config("trusted_owners: [actions, dokcer]\n");
+    check_online(&repo, &server);
+    let before = server.requests().len();
+    let output = check_online(&repo, &server);
+    assert!(stderr(&output).contains("entry dokcer is not a GitHub account"), "{}", stderr(&output));
+    assert_eq!(server.requests().len(), before);
+}
+
+#[test]
+fn online_check_reports_an_owner_it_could_not_verify() {
+    let server = MockServer::answering(500);
+    let repo = repo("config-online-failing").config("trusted_owners: [actions]\n");
+    let output = check_online(&repo, &server);
+    assert!(stderr(&output).contains("Warning: could not verify trusted_owners entry actions: "), "{}", stderr(&output));
+}
+
+#[test]
+fn offline_check_looks_nothing_up() {
+    let server = MockServer::replaying("accounts.json");
+    let repo = repo("config-offline").config("trusted_owners: [dokcer]\n");
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "config", "check"]);
+    command.env("GITHUB_TOKEN", "test-token");
+    let output = run(command, None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(!stderr(&output).contains("dokcer"), "{}", stderr(&output));
+    assert!(server.requests().is_empty());
+}
+
+fn validate(repo: &Repo, file: &str) -> std::process::Output {
+    run(repo.command_without_config(&["config", "validate", file]), None)
+}
+
+#[test]
+fn validate_lists_every_bad_key_and_entry_with_its_line() {
+    let repo = repo("config-validate-bad");
+    let config = "trusted-owners: [actions]\nsettings:\n  critical_dependencies: [actions/checkout@v4]\ntrusted_repos: [notarepo]\n";
+    write_files(&repo.root, &[("bad.yml", config)]);
+    let output = validate(&repo, "bad.yml");
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    let expected = "Error: bad.yml: 3 problems:\n  \
+                    line 1: unknown key trusted-owners; did you mean trusted_owners?\n  \
+                    line 2: unknown key settings; it holds critical_dependencies, which is a top-level key; is it indented one level too deep?\n  \
+                    line 4: trusted_repos entry notarepo is not owner/repo\n";
+    assert!(stderr(&output).contains(expected), "{}", stderr(&output));
+    assert!(stdout(&output).is_empty(), "nothing is scanned: {}", stdout(&output));
+}
+
+#[test]
+fn validate_warns_about_duplicates_and_passes_a_good_config() {
+    let repo = repo("config-validate-good");
+    write_files(&repo.root, &[("dup.yml", "trusted_owners: [actions]\nblocked_owners:\n  - evil\n  - Evil\n")]);
+    let output = validate(&repo, "dup.yml");
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "dup.yml: valid\n");
+    assert!(stderr(&output).contains("Warning: dup.yml: line 4: blocked_owners lists Evil more than once"), "{}", stderr(&output));
+}
+
+#[test]
+fn validate_needs_the_file_to_exist() {
+    let repo = repo("config-validate-missing");
+    let output = validate(&repo, "missing.yml");
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("missing.yml: no such config file"), "{}", stderr(&output));
+}
+
+#[test]
+fn a_scan_with_an_unknown_key_stops_before_scanning() {
+    let repo = repo("config-unknown-scan").config("trusted_owners: [actions]\ncritical_dependency: [actions/checkout@v4]\n");
+    let output = repo.scan(&[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert!(stderr(&output).contains("line 2: unknown key critical_dependency; did you mean critical_dependencies?"), "{}", stderr(&output));
+}
@@ -0,0 +1,39 @@
+//! Config hygiene: config entries that matched no action in the workflows they govern, listed
+//! after the scan and failing it only under `--fail-on-unused-config`
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    let uses = workflow_using(&["actions/checkout@v4", &format!("acme/tool@{}", SHA)]);
+    Repo::new(name, &[("ci.yml", &uses)])
+        .config("trusted_owners: [actions, acme, gone-org]\ncritical_dependencies: [actions/checkout@v4, old/renamed@v1]\n")
+}
+
+#[test]
+fn entries_that_matched_nothing_are_listed_without_failing_the_scan() {
+    let repo = repo("hygiene-listed");
+    let output = repo.scan(&["--ascii"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let out = stdout(&output);
+    let hygiene = &out[out.find("Config hygiene:").unwrap_or_else(|| panic!("{}", out))..];
+    let config = repo.root.join("scanner.yml").display().to_string();
+    assert!(hygiene.contains(&format!("trusted_owners entry gone-org in {} matched no action", config)), "{}", out);
+    assert!(hygiene.contains(&format!("critical_dependencies entry old/renamed@v1 in {} matched no action", config)), "{}", out);
+    assert!(!hygiene.contains("entry actions ") && !hygiene.contains("entry acme "), "{}", out);
+}
+
+#[test]
+fn fail_on_unused_config_fails_the_scan_and_filtered_scans_skip_the_check() {
+    let repo = repo("hygiene-fail");
+    let output = repo.scan(&["--format", "json", "--fail-on-unused-config"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(json(&output)["exit"]["reason"], "2 unused config entries (--fail-on-unused-config)");
+
+    // Entries for the actions filtered out would look unused
+    let output = repo.scan(&["--format", "json", "--fail-on-unused-config", "--only", "actions/*"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    assert!(report["findings"].as_array().unwrap().iter().all(|f| f["rule"] != "unused-config-entry"), "{}", report);
+}
@@ -0,0 +1,48 @@
+//! Pins to a branch: the referenced repository's real default branch with API access, from
+//! tests/fixtures/http/default-branch.json where acme/tool's is `develop`, and the configurable
+//! name list without it
+
+mod common;
+
+use common::*;
+
+/// The unstable-reference messages of a `--format json` report
+fn unstable(output: &std::process::Output) -> Vec<String> {
+    let report = json(output);
+    let findings = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "unstable-reference");
+    findings.map(|f| f["message"].as_str().unwrap().to_string()).collect()
+}
+
+fn repo(name: &str, uses: &str, config: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&[uses]))]).config(&format!("trusted_owners: [acme]\ncritical_dependencies: [{}]\n{}", uses, config))
+}
+
+#[test]
+fn a_pin_to_the_default_branch_is_flagged_by_its_name() {
+    let repo = repo("default-branch", "acme/tool@develop", "");
+    let output = run(repo.replaying("default-branch.json", &["--source", "github", "--format", "json", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(unstable(&output), ["Critical dependency acme/tool@develop is pinned to develop, the default branch of acme/tool"]);
+
+    // Offline, develop is not on the name list
+    let output = repo.scan(&["--format", "json"]);
+    assert!(unstable(&output).is_empty(), "{:?}", unstable(&output));
+}
+
+#[test]
+fn another_branch_name_is_not_the_default_branch() {
+    let repo = repo("default-branch-other", "acme/tool@stable", "");
+    let output = run(repo.replaying("default-branch.json", &["--source", "github", "--format", "json", ".github/workflows"]), None);
+    assert!(unstable(&output).is_empty(), "{:?}", unstable(&output));
+}
+
+#[test]
+fn offline_the_configured_names_replace_the_built_in_ones() {
+    let named = repo("default-branch-names", "acme/tool@trunk", "unstable_ref_names: [trunk]\n");
+    let output = named.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(unstable(&output).len(), 1, "{}", stdout(&output));
+
+    let main = repo("default-branch-main", "acme/tool@main", "unstable_ref_names: [trunk]\n");
+    assert!(unstable(&main.scan(&["--format", "json"])).is_empty());
+}
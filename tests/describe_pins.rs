@@ -0,0 +1,42 @@
+//! `--describe-pins`: SHA pins shown with the version they resolve to, from
+//! tests/fixtures/http/describe-pins.json where acme/tool's v4.2.2 and v4 tag the `a…` commit and
+//! the `b…` commit is 3 past them; other repositories have no tags
+
+mod common;
+
+use common::*;
+
+const TAGGED: &str = "acme/tool@aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+const AHEAD: &str = "acme/tool@bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+const COMMENTED: &str = "acme/other@cccccccccccccccccccccccccccccccccccccccc";
+const BARE: &str = "acme/bare@dddddddddddddddddddddddddddddddddddddddd";
+
+fn scan(name: &str, args: &[&str]) -> std::process::Output {
+    let steps = format!("      - uses: {}\n      - uses: {}\n      - uses: {} # v1.0\n      - uses: {}\n", TAGGED, AHEAD, COMMENTED, BARE);
+    let workflow = format!("on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n{}", steps);
+    let repo = Repo::new(name, &[("ci.yml", &workflow)]).config("trusted_owners: [actions]\n");
+    let server = MockServer::replaying("describe-pins.json");
+    let mut command = repo.networked(&["--api-url", &server.base, "--source", "github", "--describe-pins"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+#[test]
+fn each_pin_shows_its_tag_its_nearest_tag_its_comment_or_that_it_is_unknown() {
+    let output = scan("describe-text", &["--ascii"]);
+    let out = stdout(&output);
+    for shown in ["acme/tool@aaaaaaa… (v4.2.2)", "acme/tool@bbbbbbb… (v4.2.2+3)", "acme/other@ccccccc… (v1.0 per its comment)", "acme/bare@ddddddd… (unknown version)"] {
+        assert!(out.contains(shown), "{} in {}", shown, out);
+    }
+}
+
+#[test]
+fn json_carries_the_version_as_a_field_and_leaves_messages_alone() {
+    let report = json(&scan("describe-json", &["--format", "json"]));
+    let versions: Vec<(&str, Option<&str>)> =
+        report["actions"].as_array().unwrap().iter().map(|a| (a["action"].as_str().unwrap(), a["pin_version"].as_str())).collect();
+    assert_eq!(versions, [(BARE, None), (COMMENTED, Some("v1.0")), (TAGGED, Some("v4.2.2")), (AHEAD, Some("v4.2.2+3"))], "{}", report);
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["action"] == AHEAD).unwrap();
+    assert_eq!(finding["pin_version"], "v4.2.2+3");
+    assert!(finding["message"].as_str().unwrap().contains(AHEAD), "{}", finding);
+}
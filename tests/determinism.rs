@@ -0,0 +1,84 @@
+//! Two scans of the same workflows print the same bytes, so CI logs and snapshots can be diffed
+
+mod common;
+
+use common::*;
+
+/// Jobs and steps out of alphabetical order, several findings per line and the same actions in
+/// more than one file
+const WORKFLOW: &str = "on: pull_request_target
+jobs:
+  zulu:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: zeta/one@v1
+      - uses: ACME/Deploy@main
+      - uses: actions/checkout@v4
+  alpha:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: beta/two@v2
+      - uses: docker://alpine
+      - uses: zeta/one@v1
+";
+
+#[test]
+fn repeated_scans_are_byte_identical() {
+    let repo = Repo::new(
+        "determinism",
+        &[("release.yml", WORKFLOW), ("ci.yml", WORKFLOW), ("b.yaml", &workflow_using(&["gamma/three@v3", "ACME/Deploy@main"]))],
+    );
+    for format in ["text", "json", "sarif"] {
+        let scan = || repo.scan(&["--strict", "--timestamp", "2024-01-01T00:00:00Z", "--format", format]);
+        let (first, second) = (scan(), scan());
+        assert_ne!(code(&first), 0, "{}: the fixture has failing findings", format);
+        assert_eq!(code(&first), code(&second), "{}", format);
+        assert_eq!(stdout(&first), stdout(&second), "{}: stdout differs between runs", format);
+        assert_eq!(stderr(&first), stderr(&second), "{}: stderr differs between runs", format);
+    }
+}
+
+#[test]
+fn the_report_does_not_depend_on_how_many_lookups_run_at_once() {
+    let server = MockServer::replaying("vulnerable.json");
+    let api = format!("{}/api/v3", server.base);
+    let osv = format!("{}/osv", server.base);
+    let uses = ["acme/tool@v1.1.0", "actions/checkout@v4", "zeta/one@v1", "beta/two@v2", "gamma/three@v3", "acme/tool@v1.1.0"];
+    let repo = Repo::new("determinism-concurrency", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions]\n");
+    let scan = |concurrency: &str| {
+        let mut command = repo.networked(&["--api-url", &api, "--advisory-api-url", &api, "--osv-api-url", &osv, "--no-cache"]);
+        command.args(["--concurrency", concurrency, "--timestamp", "2024-01-01T00:00:00Z", "--format", "json"]).arg(repo.workflows());
+        run(command, None)
+    };
+    let serial = scan("1");
+    assert_eq!(code(&serial), 3, "{}", stderr(&serial));
+    assert_eq!(json(&serial)["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "known-vulnerability").count(), 1);
+    let requests = server.requests().len();
+    for concurrency in ["4", "16"] {
+        let parallel = scan(concurrency);
+        assert_eq!(code(&parallel), code(&serial), "--concurrency {}", concurrency);
+        assert_eq!(stdout(&parallel), stdout(&serial), "--concurrency {}: the report differs from the serial scan", concurrency);
+    }
+    // Each scan asks once per repository, however many ask at once
+    assert_eq!(server.requests().len(), requests * 3);
+}
+
+#[test]
+fn findings_are_sorted_by_file_line_rule_and_action() {
+    let repo = Repo::new("determinism-order", &[("release.yml", WORKFLOW), ("ci.yml", WORKFLOW)]);
+    let report = json(&repo.scan(&["--strict", "--format", "json"]));
+    let keys: Vec<(String, u64, String, String)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|f| f["file"].is_string())
+        .map(|f| {
+            let text = |key: &str| f[key].as_str().unwrap_or_default().to_string();
+            (text("file"), f["line"].as_u64().unwrap_or_default(), text("rule"), text("action"))
+        })
+        .collect();
+    assert!(keys.len() > 4, "{:?}", keys);
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+}
@@ -0,0 +1,95 @@
+//! `--diff` and `--diff-base`: only the `uses:` entries a change adds or modifies fail the scan,
+//! the unchanged ones are listed without failing, and added and deleted workflow files are
+//! accounted for
+
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::process::Command;
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Jane", "-c", "user.email=jane@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+}
+
+/// A checkout whose workflow was `base` under base/ and is `head` now
+fn changed(name: &str, base: &[&str], head: &[&str]) -> Repo {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(head))]);
+    write_files(&repo.root.join("base"), &[("ci.yml", &workflow_using(base))]);
+    repo
+}
+
+fn unchanged(report: &serde_json::Value, action: &str) -> Vec<bool> {
+    let findings = report["findings"].as_array().unwrap().iter().filter(|f| f["action"] == action);
+    findings.map(|f| f["suppression"]["unchanged"] == true).collect()
+}
+
+#[test]
+fn only_a_changed_entry_fails_and_the_unchanged_ones_are_listed() {
+    let repo = changed("diff-changed", &["acme/deploy@v1"], &["actions/checkout@v4", "acme/deploy@v1", "acme/tool@main"]);
+    let output = run(repo.command(&["--format", "json", "--diff", "base", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "acme/tool is new: {}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["diff"]["unchanged_actions"], serde_json::json!(["acme/deploy@v1"]), "{}", report);
+    let deploy = unchanged(&report, "acme/deploy@v1");
+    assert!(!deploy.is_empty() && deploy.iter().all(|u| *u), "a moved line is no change: {}", report);
+    let tool = unchanged(&report, "acme/tool@main");
+    assert!(!tool.is_empty() && tool.iter().all(|u| !u), "{}", report);
+    assert_eq!(report["summary"]["skipped_unchanged"], 1, "{}", report);
+
+    let text = stdout(&run(repo.command(&["--diff", "base", ".github/workflows"]), None));
+    assert!(text.contains("\nUnchanged since base, so not failing the scan:\n"), "{}", text);
+    assert!(text.contains("- skipped as unchanged since base: 1 actions"), "{}", text);
+}
+
+#[test]
+fn a_changed_ref_is_a_change() {
+    let repo = changed("diff-ref", &["acme/deploy@v1"], &["acme/deploy@v2"]);
+    let output = run(repo.command(&["--format", "json", "--diff", "base", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(unchanged(&json(&output), "acme/deploy@v2").iter().filter(|u| **u).count(), 0);
+
+    let same = changed("diff-same", &["acme/deploy@v1"], &["acme/deploy@v1"]);
+    let output = run(same.command(&["--diff", "base", ".github/workflows"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+}
+
+#[test]
+fn added_workflows_are_all_changed_and_deleted_ones_are_listed() {
+    let repo = changed("diff-files", &["acme/deploy@v1"], &["acme/deploy@v1"]);
+    write_files(&repo.workflows(), &[("release.yml", &workflow_using(&["acme/deploy@v1"]))]);
+    write_files(&repo.root.join("base"), &[("old.yml", &workflow_using(&["acme/old@v1"]))]);
+    let output = run(repo.command(&["--format", "json", "--diff", "base", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "release.yml adds the action: {}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["diff"]["added_workflows"], serde_json::json!([".github/workflows/release.yml"]), "{}", report);
+    assert_eq!(report["diff"]["deleted_workflows"], serde_json::json!(["base/old.yml"]), "{}", report);
+    assert_eq!(report["diff"]["unchanged_actions"], serde_json::json!([]), "not unchanged in every file: {}", report);
+}
+
+#[test]
+fn diff_base_compares_with_a_git_revision() {
+    let repo = Repo::new("diff-base", &[("ci.yml", &workflow_using(&["acme/deploy@v1"])), ("old.yml", &workflow_using(&["acme/old@v1"]))]);
+    git(&repo.root, &["init", "-q"]);
+    git(&repo.root, &["add", ".github"]);
+    git(&repo.root, &["commit", "-q", "-m", "Add CI"]);
+    std::fs::remove_file(repo.workflow("old.yml")).unwrap();
+    write_files(&repo.workflows(), &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/deploy@v1"]))]);
+
+    let output = run(repo.command(&["--format", "json", "--diff-base", "HEAD", ".github/workflows"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["diff"]["base"], "HEAD", "{}", report);
+    assert_eq!(report["diff"]["unchanged_actions"], serde_json::json!(["acme/deploy@v1"]), "{}", report);
+    assert_eq!(report["diff"]["deleted_workflows"], serde_json::json!([".github/workflows/old.yml"]), "{}", report);
+
+    let output = run(repo.command(&["--diff-base", "no-such-ref", ".github/workflows"]), None);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert!(stderr(&output).contains("--diff-base no-such-ref is not a commit of this checkout"), "{}", stderr(&output));
+}
@@ -0,0 +1,88 @@
+//! Workflow files that lean on YAML features: `<<:` merge keys bringing in a job's steps, and
+//! several `---` documents in one file, each scanned and located in its own lines
+
+mod common;
+
+use common::*;
+
+/// A job template whose steps two jobs merge in, one of them adding a key of its own
+const MERGED: &str = "on: push
+x-template: &build
+  runs-on: ubuntu-latest
+  steps:
+    - uses: actions/checkout@v4
+    - uses: acme/tool@main
+jobs:
+  build:
+    <<: *build
+  test:
+    <<: *build
+    timeout-minutes: 10
+";
+
+/// Two documents with a job id in common, the second using an action the first does not
+const TWO_DOCUMENTS: &str = "on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+---
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: acme/tool@main
+  lint:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: acme/lint@v1
+";
+
+/// The `(rule, job, line)` of each finding
+fn located(report: &serde_json::Value) -> Vec<(String, String, Option<u64>)> {
+    let findings = report["findings"].as_array().unwrap().iter();
+    let text = |f: &serde_json::Value, key: &str| f[key].as_str().unwrap_or_default().to_string();
+    findings.map(|f| (text(f, "rule"), text(f, "job"), f["line"].as_u64())).collect()
+}
+
+fn actions(report: &serde_json::Value) -> Vec<&str> {
+    report["actions"].as_array().unwrap().iter().map(|a| a["action"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn a_uses_merged_in_through_a_merge_key_is_found_at_its_anchor() {
+    let repo = Repo::new("documents-merge", &[("ci.yml", MERGED)]);
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(actions(&report), ["acme/tool@main", "actions/checkout@v4"], "{}", report);
+    let expected = ["untrusted-owner", "untrusted-owner", "untrusted-owner-no-sha", "untrusted-owner-no-sha"];
+    assert_eq!(located(&report).iter().map(|(rule, _, _)| rule.as_str()).collect::<Vec<_>>(), expected, "{}", report);
+    for (_, job, line) in located(&report) {
+        assert!(job == "build" || job == "test", "each job merging the template is checked: {}", report);
+        assert_eq!(line, Some(6), "the step is located at the anchor: {}", report);
+    }
+}
+
+#[test]
+fn every_document_of_a_file_is_scanned_at_its_own_lines() {
+    let repo = Repo::new("documents-two", &[("ci.yml", TWO_DOCUMENTS)]);
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(actions(&report), ["acme/lint@v1", "acme/tool@main", "actions/checkout@v4"], "{}", report);
+    assert_eq!(report["summary"]["workflows_scanned"], 1, "{}", report);
+    let found: Vec<(String, Option<u64>)> = located(&report).into_iter().filter(|(rule, _, _)| rule == "untrusted-owner").map(|(_, job, line)| (job, line)).collect();
+    let expected = [("build (document 2)", Some(12)), ("lint", Some(16))];
+    assert_eq!(found, expected.map(|(job, line)| (job.to_string(), line)), "{}", report);
+}
+
+#[test]
+fn a_document_of_only_comments_adds_nothing() {
+    let source = format!("# generated, do not edit\n---\n{}---\n# trailing notes\n", TWO_DOCUMENTS.split("---\n").next().unwrap());
+    let repo = Repo::new("documents-comments", &[("ci.yml", &source)]);
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(actions(&json(&output)), ["actions/checkout@v4"]);
+}
@@ -0,0 +1,100 @@
+//! Keys repeated in a mapping: every value is scanned, and the repeat is a warning, or an error
+//! under `--strict`
+
+mod common;
+
+use common::*;
+
+/// A job with a second `steps:` list
+const STEPS: &str = "on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+    steps:
+      - uses: actions/setup-node@v4
+";
+
+/// Two jobs with the same id
+const JOBS: &str = "on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+";
+
+/// A step with a second `uses:`
+const USES: &str = "on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - name: Check out
+        uses: actions/checkout@v4
+        uses: actions/setup-node@v4
+";
+
+fn duplicates(report: &serde_json::Value) -> Vec<(String, String, u64)> {
+    let findings = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "duplicate-key");
+    findings.map(|f| (f["level"].as_str().unwrap().to_string(), f["message"].as_str().unwrap().to_string(), f["line"].as_u64().unwrap())).collect()
+}
+
+#[test]
+fn repeated_keys_are_warnings_by_default() {
+    for (file, content, message, line) in [
+        ("steps.yml", STEPS, "jobs.build: duplicate key steps at lines 5 and 7", 7),
+        ("jobs.yml", JOBS, "jobs: duplicate key build at lines 3 and 7", 7),
+        ("uses.yml", USES, "jobs.build.steps[0]: duplicate key uses at lines 7 and 8", 8),
+    ] {
+        let repo = Repo::new("duplicate-warning", &[(file, content)]);
+        let output = repo.scan(&["--format", "json"]);
+        assert_eq!(code(&output), 0, "{}: {}", file, stderr(&output));
+        let found = duplicates(&json(&output));
+        assert_eq!(found.len(), 1, "{}: {:?}", file, found);
+        assert_eq!(found[0].0, "warning");
+        assert!(found[0].1.starts_with(message), "{}: {}", file, found[0].1);
+        assert_eq!(found[0].2, line);
+    }
+}
+
+#[test]
+fn repeated_keys_fail_under_strict() {
+    for (file, content) in [("steps.yml", STEPS), ("jobs.yml", JOBS), ("uses.yml", USES)] {
+        let repo = Repo::new("duplicate-strict", &[(file, content)]);
+        let output = repo.scan(&["--format", "json", "--strict"]);
+        assert_eq!(code(&output), 2, "{}: {}", file, stderr(&output));
+        let found = duplicates(&json(&output));
+        assert_eq!(found.len(), 1, "{}: {:?}", file, found);
+        assert_eq!(found[0].0, "error");
+        assert!(found[0].1.ends_with("(failing due to --strict)"), "{}", found[0].1);
+    }
+}
+
+#[test]
+fn every_value_of_a_repeated_key_is_scanned() {
+    let untrusted = |content: &str| content.replacen("actions/setup-node@v4", "acme/deploy@v1", 1);
+    let jobs = JOBS.rsplit_once("actions/checkout@v4").map(|(head, tail)| format!("{}acme/deploy@v1{}", head, tail)).unwrap();
+    for (file, content, line) in [("steps.yml", untrusted(STEPS), 8), ("jobs.yml", jobs, 10), ("uses.yml", untrusted(USES), 8)] {
+        let repo = Repo::new("duplicate-coverage", &[(file, &content)]);
+        let report = json(&repo.scan(&["--format", "json"]));
+        let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "untrusted-owner-no-sha");
+        let finding = finding.unwrap_or_else(|| panic!("{}: the repeat was not scanned: {}", file, report["findings"]));
+        assert_eq!(finding["action"], "acme/deploy@v1");
+        assert_eq!(finding["line"], line, "{}", file);
+    }
+}
+
+#[test]
+fn three_of_a_key_are_two_repeats() {
+    let content = STEPS.to_string() + "    steps:\n      - uses: actions/cache@v4\n";
+    let repo = Repo::new("duplicate-three", &[("ci.yml", &content)]);
+    let report = json(&repo.scan(&["--format", "json"]));
+    let lines: Vec<u64> = duplicates(&report).into_iter().map(|(_, _, line)| line).collect();
+    assert_eq!(lines, [7, 9]);
+}
@@ -0,0 +1,60 @@
+//! `uses:` references holding expressions the matrix cannot resolve, and malformed references,
+//! as located findings in the JSON report
+
+mod common;
+
+use common::*;
+
+fn findings(output: &std::process::Output, rule: &str) -> Vec<serde_json::Value> {
+    json(output)["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == rule).cloned().collect()
+}
+
+fn scan(name: &str, uses: &[&str], config: &str, args: &[&str]) -> std::process::Output {
+    let workflow = workflow_using(uses).replace("on: push", "on:\n  workflow_call:\n    inputs:\n      ref: {type: string}");
+    let repo = Repo::new(name, &[("ci.yml", &workflow)]).config(config);
+    let mut all = vec!["--format", "json"];
+    all.extend(args);
+    repo.scan(&all)
+}
+
+#[test]
+fn an_expression_in_the_ref_is_a_warning_at_its_line_with_the_literal_value() {
+    let output = scan("dynamic-ref", &["actions/checkout@v4", "my-org/deploy-action@${{ inputs.ref }}"], "trusted_owners: [actions]\n", &[]);
+    let dynamic = findings(&output, "dynamic-reference");
+    assert_eq!(dynamic.len(), 1, "{}", stdout(&output));
+    assert_eq!(dynamic[0]["level"], "warning");
+    assert_eq!(dynamic[0]["action"], "my-org/deploy-action@${{ inputs.ref }}");
+    assert_eq!(dynamic[0]["file"], ".github/workflows/ci.yml");
+    assert_eq!(dynamic[0]["line"], 10);
+    assert!(dynamic[0]["message"].as_str().unwrap().contains("uses my-org/deploy-action@${{ inputs.ref }}, which cannot be resolved statically"), "{}", dynamic[0]);
+    assert!(findings(&output, "malformed-reference").is_empty(), "{}", stdout(&output));
+}
+
+#[test]
+fn strict_or_a_critical_owner_repo_makes_it_an_error() {
+    let uses = ["my-org/deploy-action@${{ inputs.ref }}"];
+    let strict = scan("dynamic-strict", &uses, "trusted_owners: [actions]\n", &["--strict"]);
+    let dynamic = findings(&strict, "dynamic-reference");
+    assert_eq!(dynamic[0]["level"], "error");
+    assert!(dynamic[0]["message"].as_str().unwrap().ends_with(" (failing due to --strict)"), "{}", dynamic[0]);
+    assert_ne!(code(&strict), 0);
+
+    let critical = scan("dynamic-critical", &uses, "trusted_owners: [actions]\ncritical_dependencies: [my-org/deploy-action@v2]\n", &[]);
+    let dynamic = findings(&critical, "dynamic-reference");
+    assert_eq!(dynamic[0]["level"], "error");
+    assert!(dynamic[0]["message"].as_str().unwrap().ends_with(" (a critical dependency)"), "{}", dynamic[0]);
+
+    // With the owner/repo itself dynamic there is no critical dependency to match
+    let unknown = scan("dynamic-owner", &["${{ inputs.action }}@v1"], "trusted_owners: [actions]\ncritical_by_default: false\n", &[]);
+    assert_eq!(findings(&unknown, "dynamic-reference")[0]["level"], "warning", "{}", stdout(&unknown));
+}
+
+#[test]
+fn a_malformed_reference_is_reported_where_it_is_used() {
+    let output = scan("malformed-ref", &["actions/checkout@v4", "not-an-action"], "trusted_owners: [actions]\n", &[]);
+    let malformed = findings(&output, "malformed-reference");
+    assert_eq!(malformed.len(), 1, "{}", stdout(&output));
+    assert_eq!(malformed[0]["action"], "not-an-action");
+    assert_eq!(malformed[0]["line"], 10);
+    assert!(findings(&output, "dynamic-reference").is_empty());
+}
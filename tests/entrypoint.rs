@@ -0,0 +1,57 @@
+//! The Docker action's entrypoint: how the action inputs become scanner arguments
+
+#![cfg(unix)]
+
+mod common;
+
+use common::*;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+/// Run entrypoint.sh in a checkout holding `files`, with `inputs` as the action's args and a stub
+/// scanner on `PATH` printing each argument it gets on a line of its own
+fn arguments(name: &str, files: &[(&str, &str)], inputs: &[&str]) -> Vec<String> {
+    let dir = scratch(name);
+    write_files(&dir, files);
+    let stub = dir.join("bin/gh-action-security-scanner");
+    write_files(&dir, &[("bin/gh-action-security-scanner", "#!/bin/sh\nfor arg in \"$@\"; do printf '%s\\n' \"$arg\"; done\n")]);
+    std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", dir.join("bin").display(), std::env::var("PATH").unwrap_or_default());
+    let mut command = Command::new("sh");
+    command.arg(concat!(env!("CARGO_MANIFEST_DIR"), "/entrypoint.sh")).args(inputs).current_dir(&dir).env("PATH", path);
+    let output = run(command, None);
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).lines().map(str::to_string).collect()
+}
+
+#[test]
+fn config_path_with_spaces_stays_one_argument() {
+    let args = arguments("entrypoint-spaces", &[], &["my workflows/ci.yml", "--strict", "--config", "policy dir/scanner config.yml", "", ""]);
+    assert_eq!(args, ["my workflows/ci.yml", "--strict", "--config", "policy dir/scanner config.yml"]);
+}
+
+#[test]
+fn empty_inputs_add_no_arguments() {
+    let args = arguments("entrypoint-empty", &[], &["", "", "--config", "", "", ""]);
+    assert!(args.is_empty(), "{:?}", args);
+}
+
+#[test]
+fn flags_follow_the_workflow_path() {
+    let args = arguments("entrypoint-flags", &[], &["ci.yml", "", "--config", "", "--self", "--submit-dependency-snapshot"]);
+    assert_eq!(args, ["ci.yml", "--self", "--submit-dependency-snapshot"]);
+}
+
+#[test]
+fn conventional_config_is_used_when_no_input_names_one() {
+    let files = [(".github/gh-action-security-config.yaml", "{}\n")];
+    let args = arguments("entrypoint-conventional", &files, &["", "", "--config", "", "", ""]);
+    assert_eq!(args, ["--config", ".github/gh-action-security-config.yaml"]);
+}
+
+#[test]
+fn config_input_wins_over_the_conventional_file() {
+    let files = [(".github/gh-action-security-config.yaml", "{}\n")];
+    let args = arguments("entrypoint-input", &files, &["", "", "--config", "policy.yml", "", ""]);
+    assert_eq!(args, ["--config", "policy.yml"]);
+}
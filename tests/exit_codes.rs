@@ -0,0 +1,164 @@
+//! `explain-exit-code` and the `exit` object of JSON reports: the number the process exits with,
+//! the object's `code` and `name`, and what `explain-exit-code` prints for it always agree. Also
+//! the clean, passed-with-warnings and failed outcomes each scan ends in.
+
+mod common;
+
+use common::*;
+
+/// What `explain-exit-code` prints for `number`, split into the name and the meaning
+fn explain(number: i32) -> (String, String) {
+    let repo = Repo::new("exit-explain", &[]);
+    let output = run(repo.command_without_config(&["explain-exit-code", &number.to_string()]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let line = stdout(&output);
+    let (printed, rest) = line.trim_end().split_once(' ').unwrap();
+    assert_eq!(printed, number.to_string());
+    let (name, meaning) = rest.split_once(": ").unwrap();
+    (name.to_string(), meaning.to_string())
+}
+
+#[test]
+fn every_documented_code_is_explained() {
+    for (number, name) in [
+        (0, "pass"),
+        (1, "vulnerabilities"),
+        (2, "policy-violations"),
+        (3, "vulnerabilities-and-policy-violations"),
+        (4, "artifact-failure"),
+        (5, "usage-error"),
+        (6, "unsupported-porcelain"),
+        (10, "operational-error"),
+    ] {
+        let (explained, meaning) = explain(number);
+        assert_eq!(explained, name);
+        assert!(!meaning.is_empty(), "{}", number);
+    }
+}
+
+#[test]
+fn an_unknown_code_is_an_error_listing_the_known_ones() {
+    let repo = Repo::new("exit-unknown", &[]);
+    let output = run(repo.command_without_config(&["explain-exit-code", "7"]), None);
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("exit code 7 is not one the scanner uses (known: 0 pass, 1 vulnerabilities, 2 policy-violations"), "{}", stderr(&output));
+}
+
+#[test]
+fn the_report_exit_object_matches_the_process_exit() {
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    let cases: [(&str, Option<&str>, &[&str], i32); 7] = [
+        ("exit-clean", Some("clean.json"), &[], 0),
+        ("exit-vulnerable", Some("vulnerable.json"), &[], 1),
+        ("exit-both", Some("vulnerable.json"), &["evil/tool@v1"], 3),
+        ("exit-unrecorded", Some("clean.json"), &["acme/other@v2"], 10),
+        ("exit-policy", None, &["evil/tool@v1"], 2),
+        ("exit-offline", None, &[], 0),
+        ("exit-zero", Some("vulnerable.json"), &["--exit-zero"], 0),
+    ];
+    for (name, fixture, extra, expected) in cases {
+        let (more, flags): (Vec<&str>, Vec<&str>) = extra.iter().partition(|e| !e.starts_with("--"));
+        let all: Vec<&str> = uses.iter().chain(&more).copied().collect();
+        let repo = Repo::new(name, &[("ci.yml", &workflow_using(&all))]).config("trusted_owners: [actions, acme]\nblocked_owners: [evil]\n");
+        let workflows = repo.workflows();
+        let mut args = vec!["--format", "json"];
+        args.extend(&flags);
+        args.push(workflows.to_str().unwrap());
+        let output = match fixture {
+            Some(fixture) => run(repo.replaying(fixture, &args), None),
+            None => run(repo.command(&args), None),
+        };
+        assert_eq!(code(&output), expected, "{}: {}", name, stderr(&output));
+
+        let exit = &json(&output)["exit"];
+        let process = exit.get("process_code").unwrap_or(&exit["code"]);
+        assert_eq!(process.as_i64(), Some(expected as i64), "{}: {}", name, exit);
+        let reported = exit["code"].as_i64().unwrap() as i32;
+        assert_eq!(exit["name"].as_str().unwrap(), explain(reported).0, "{}", name);
+        assert!(!exit["reason"].as_str().unwrap().is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn clean_warned_and_failed_scans_each_have_their_banner_outcome_and_code() {
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    // Listing checkout as critical turns its tag pin into a warning
+    let critical = "trusted_owners: [actions, acme]\ncritical_dependencies: [actions/checkout@v4]\n";
+    for (config, fixture, flags, exit, outcome, conclusion, banner) in [
+        ("trusted_owners: [actions, acme]\n", "clean.json", &[][..], 0, "clean", "success", "[OK] All actions passed security checks!"),
+        (critical, "clean.json", &[][..], 0, "passed-with-warnings", "neutral", "[WARN] All actions passed security checks, with 1 warning"),
+        (critical, "clean.json", &["--fail-on-warnings"][..], 2, "failed", "failure", "Security scan failed!"),
+        ("trusted_owners: [actions, acme]\n", "vulnerable.json", &[][..], 1, "failed", "failure", "Security scan failed!"),
+    ] {
+        let repo = Repo::new("exit-outcome", &[("ci.yml", &workflow_using(&uses))]).config(config);
+        let workflows = repo.workflows();
+        let mut args = vec!["--ascii", "--format", "json"];
+        args.extend(flags);
+        args.push(workflows.to_str().unwrap());
+        let output = run(repo.replaying(fixture, &args), None);
+        assert_eq!(code(&output), exit, "{} {:?}: {}", fixture, flags, stderr(&output));
+        let report = json(&output);
+        assert_eq!((report["outcome"].as_str(), report["conclusion"].as_str()), (Some(outcome), Some(conclusion)), "{:?}", flags);
+        assert!(stderr(&output).contains(banner), "{:?}: {}", flags, stderr(&output));
+    }
+}
+
+#[test]
+fn a_failed_artifact_fails_a_passing_scan_and_leaves_a_failing_one_to_its_findings() {
+    for (uses, expected, line) in [
+        ("actions/checkout@v4", 4, "- exit 4 (artifact-failure) instead: some artifacts could not be written"),
+        ("acme/tool@v1", 2, "- some artifacts could not be written either"),
+    ] {
+        let repo = Repo::new("exit-artifact", &[("ci.yml", &workflow_using(&[uses]))]);
+        // A file where the directory should be: no artifact can be written
+        write_files(&repo.root, &[("out", "")]);
+        let output = repo.scan(&["--output-dir", "out"]);
+        assert_eq!(code(&output), expected, "{}: {}", uses, stderr(&output));
+        let printed = stdout(&output);
+        assert!(printed.contains("[FAIL] json: out/report.json not written: "), "{}: {}", uses, printed);
+        assert!(printed.contains(line), "{}: {}", uses, printed);
+
+        let zero = repo.scan(&["--output-dir", "out", "--exit-zero"]);
+        assert_eq!(code(&zero), 0, "{}: {}", uses, stderr(&zero));
+    }
+}
+
+#[test]
+fn a_run_that_stops_before_scanning_exits_with_the_code_of_its_reason() {
+    let repo = Repo::new("exit-early", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let cases: [(&[&str], &str, &str); 4] = [
+        (&["--no-such-flag"], "usage-error", "error: Found argument '--no-such-flag'"),
+        (&["-", "-"], "usage-error", "Error: - is given more than once"),
+        (&["--porcelain", "v9"], "unsupported-porcelain", "Error: "),
+        (&["--help"], "pass", ""),
+    ];
+    for (args, name, message) in cases {
+        let output = repo.scan(args);
+        assert_eq!(explain(code(&output)).0, name, "{:?}: {}", args, stderr(&output));
+        assert!(stderr(&output).starts_with(message), "{:?}: {}", args, stderr(&output));
+    }
+
+    let broken = Repo::new("exit-early-config", &[]).config("trusted_owners: [actions\n");
+    let output = broken.scan(&[]);
+    assert_eq!(explain(code(&output)).0, "operational-error", "{}", stderr(&output));
+    assert!(stderr(&output).contains("scanner.yml"), "{}", stderr(&output));
+}
+
+#[test]
+fn exit_zero_exits_0_and_the_report_keeps_the_code_it_would_have_been() {
+    let repo = Repo::new("exit-zero-audit", &[("ci.yml", &workflow_using(&["acme/tool@v1.1.0"]))]).config("trusted_owners: [acme]\n");
+    let workflows = repo.workflows();
+    let args = ["--exit-zero", "--format", "json", "--output-dir", "out", workflows.to_str().unwrap()];
+    let output = run(repo.replaying("vulnerable.json", &args), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let exit = &json(&output)["exit"];
+    assert_eq!((exit["code"].as_i64(), exit["name"].as_str(), exit["process_code"].as_i64()), (Some(1), Some("vulnerabilities"), Some(0)), "{}", exit);
+    // The artifacts are written in full, with the same record
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(repo.root.join("out/report.json")).unwrap()).unwrap();
+    assert_eq!(written["exit"], *exit);
+
+    // A broken invocation is not a clean audit
+    let broken = Repo::new("exit-zero-broken", &[]).config("trusted_owners: [actions\n");
+    assert_eq!(explain(code(&broken.scan(&["--exit-zero"]))).0, "operational-error");
+    assert_eq!(explain(code(&broken.scan(&["--exit-zero", "--no-such-flag"]))).0, "usage-error");
+}
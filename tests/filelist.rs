@@ -0,0 +1,45 @@
+//! `--stdin-filelist`: the scan of the paths piped in
+
+mod common;
+
+use common::*;
+
+#[test]
+fn duplicate_entries_are_scanned_once() {
+    let repo = Repo::new("filelist-dup", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let path = repo.workflow("ci.yml");
+    let list = format!("{}\n{}\n", path.display(), path.display());
+    let output = run(repo.command(&["--stdin-filelist", "--format", "json"]), Some(list.as_bytes()));
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["files"].as_array().unwrap().len(), 1);
+    assert_eq!(report["actions_scanned"], 1);
+}
+
+#[test]
+fn nul_separated_list_is_read_with_z() {
+    let repo = Repo::new("filelist-nul", &[("a b.yml", &workflow_using(&["actions/checkout@v4"])), ("c.yml", &workflow_using(&["actions/cache@v4"]))]);
+    let list = format!("{}\0{}\0", repo.workflow("a b.yml").display(), repo.workflow("c.yml").display());
+    let output = run(repo.command(&["--stdin-filelist", "-z", "--format", "json"]), Some(list.as_bytes()));
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(json(&output)["files"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn missing_files_are_a_usage_error_naming_them() {
+    let repo = Repo::new("filelist-missing", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let list = format!("{}\n{}\n", repo.workflow("ci.yml").display(), repo.workflow("gone.yml").display());
+    let output = run(repo.command(&["--stdin-filelist"]), Some(list.as_bytes()));
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("gone.yml"), "{}", stderr(&output));
+}
+
+#[test]
+fn empty_list_is_a_usage_error_unless_allowed() {
+    let repo = Repo::new("filelist-empty", &[]);
+    let output = run(repo.command(&["--stdin-filelist"]), Some(b"\n"));
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("--allow-empty"), "{}", stderr(&output));
+    let output = run(repo.command(&["--stdin-filelist", "--allow-empty", "--format", "json"]), Some(b""));
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+}
@@ -0,0 +1,32 @@
+//! `--only` and `--skip`: the actions a scan checks, and the partial scan made obvious in the
+//! summary
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    let uses = ["acme/deploy@v1", "acme/internal@v1", "other/tool@v1"];
+    Repo::new(name, &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions]\n")
+}
+
+#[test]
+fn filtered_out_actions_are_neither_checked_nor_reported() {
+    let output = repo("filters-json").scan(&["--format", "json", "--only", "acme/*", "--skip", "acme/internal"]);
+    let report = json(&output);
+    let actions: Vec<&str> = report["actions"].as_array().unwrap().iter().map(|a| a["action"].as_str().unwrap()).collect();
+    assert_eq!(actions, ["acme/deploy@v1"], "{}", report);
+    assert_eq!(report["filtered_out"], 2, "{}", report);
+    let findings = report["findings"].as_array().unwrap();
+    assert!(findings.iter().all(|f| f["action"] != "acme/internal@v1" && f["action"] != "other/tool@v1"), "{}", report);
+}
+
+#[test]
+fn the_summary_says_the_scan_was_partial() {
+    let output = repo("filters-text").scan(&["--ascii", "--skip", "acme/*"]);
+    let out = stdout(&output);
+    assert!(out.contains("Partial scan: 2 action(s) filtered out by --only/--skip, 1 checked"), "{}", out);
+
+    let output = repo("filters-none").scan(&["--ascii"]);
+    assert!(!stdout(&output).contains("Partial scan"), "{}", stdout(&output));
+}
@@ -0,0 +1,157 @@
+//! `fix`, `fix --dry-run` and `fix --emit-patch`, with tags resolved from tests/fixtures/http/fix.json:
+//! `acme/tool@v1` is a lightweight tag, `acme/deploy@v2` an annotated one and `acme/gone@v3`
+//! does not exist
+
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::process::Command;
+
+const TOOL: &str = "1111111111111111111111111111111111111111";
+const DEPLOY: &str = "3333333333333333333333333333333333333333";
+
+const CI: &str = "\
+on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: acme/tool@v1
+      - run: make
+      - run: make test
+      - run: make lint
+      - run: make docs
+      - run: make package
+      - uses: acme/deploy@v2 # deploy it
+      - uses: acme/gone@v3
+      - uses: acme/branchy@main
+";
+const RELEASE: &str = "on: push\njobs:\n  release:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: 'acme/tool@v1'";
+
+fn git(dir: &Path, args: &[&str]) -> std::process::Output {
+    Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// A committed checkout of the two workflows, and `fix --emit-patch fixes.patch` run over it
+fn emit_patch(name: &str) -> (Repo, std::process::Output) {
+    let repo = Repo::new(name, &[("ci.yml", CI), ("release.yml", RELEASE)]);
+    for args in [&["init", "-q"][..], &["add", "-A"], &["commit", "-qm", "base"]] {
+        assert!(git(&repo.root, args).status.success(), "git {:?}", args);
+    }
+    let ci = repo.workflow("ci.yml");
+    let release = repo.workflow("release.yml");
+    let args = ["fix", "--emit-patch", "fixes.patch", ci.to_str().unwrap(), release.to_str().unwrap()];
+    let output = run(repo.replaying("fix.json", &args), None);
+    (repo, output)
+}
+
+#[test]
+fn the_patch_applies_cleanly_and_leaves_the_tree_alone() {
+    let (repo, output) = emit_patch("fix-patch");
+    // Unfixable findings remain, so fix still fails with policy violations
+    assert_eq!(code(&output), 2, "{}\n{}", stdout(&output), stderr(&output));
+    assert!(stdout(&output).contains("Wrote fixes for 2 file(s) to fixes.patch; apply them with git apply"), "{}", stdout(&output));
+    assert!(stderr(&output).contains("Warning: could not resolve acme/gone@v3: tag v3 not found\n"), "{}", stderr(&output));
+    assert_eq!(std::fs::read_to_string(repo.workflow("ci.yml")).unwrap(), CI, "the working tree is not written");
+
+    let check = git(&repo.root, &["apply", "--check", "fixes.patch"]);
+    assert!(check.status.success(), "{}", String::from_utf8_lossy(&check.stderr));
+    assert!(git(&repo.root, &["apply", "fixes.patch"]).status.success());
+    let fixed = CI
+        .replace("acme/tool@v1", &format!("acme/tool@{} # v1", TOOL))
+        .replace("acme/deploy@v2 # deploy it", &format!("acme/deploy@{} # v2", DEPLOY));
+    assert_eq!(std::fs::read_to_string(repo.workflow("ci.yml")).unwrap(), fixed);
+    let released = RELEASE.replace("'acme/tool@v1'", &format!("'acme/tool@{}' # v1", TOOL));
+    assert_eq!(std::fs::read_to_string(repo.workflow("release.yml")).unwrap(), released);
+}
+
+#[test]
+fn the_patch_is_grouped_by_file_with_hunks_and_an_unfixable_trailer() {
+    let (repo, _) = emit_patch("fix-patch-shape");
+    let patch = std::fs::read_to_string(repo.root.join("fixes.patch")).unwrap();
+    let headers: Vec<&str> = patch.lines().filter(|l| l.starts_with("diff --git") || l.starts_with("@@")).collect();
+    assert_eq!(
+        headers,
+        [
+            "diff --git a/.github/workflows/ci.yml b/.github/workflows/ci.yml",
+            // Six lines apart, the two pins' contexts touch and make one hunk
+            "@@ -4,12 +4,12 @@",
+            "diff --git a/.github/workflows/release.yml b/.github/workflows/release.yml",
+            "@@ -3,4 +3,4 @@",
+        ]
+    );
+    assert!(patch.contains("\\ No newline at end of file\n"), "{}", patch);
+    assert!(patch.ends_with("\n# Findings that could not be fixed automatically:\n# - acme/gone@v3\n# - acme/branchy@main\n"), "{}", patch);
+}
+
+#[test]
+fn nothing_to_fix_writes_an_empty_patch() {
+    let repo = Repo::new("fix-patch-none", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let ci = repo.workflow("ci.yml");
+    let output = run(repo.replaying("fix.json", &["fix", "--emit-patch", "fixes.patch", ci.to_str().unwrap()]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(stdout(&output).contains("Nothing to fix; wrote fixes.patch with no changes"), "{}", stdout(&output));
+    assert_eq!(std::fs::read_to_string(repo.root.join("fixes.patch")).unwrap(), "");
+}
+
+const REPEATED: &str = "\
+on: push
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: acme/tool@v1
+      - uses: acme/tool@${{ matrix.version }}
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: acme/tool@v1 # again
+";
+
+#[test]
+fn dry_run_prints_the_diff_and_writes_nothing() {
+    let repo = Repo::new("fix-dry-run", &[("ci.yml", REPEATED)]);
+    let ci = repo.workflow("ci.yml");
+    let output = run(repo.replaying("fix.json", &["fix", "--dry-run", ci.to_str().unwrap()]), None);
+    // The expression ref is left for a human, so fix still fails
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(std::fs::read_to_string(&ci).unwrap(), REPEATED);
+    let out = stdout(&output);
+    let pinned = format!("acme/tool@{} # v1", TOOL);
+    assert!(out.contains(&format!("@@ -6 +6 @@\n-      - uses: acme/tool@v1\n+      - uses: {}\n", pinned)), "{}", out);
+    assert!(out.contains(&format!("@@ -11 +11 @@\n-      - uses: acme/tool@v1 # again\n+      - uses: {}\n", pinned)), "{}", out);
+    assert!(!out.contains("+      - uses: acme/tool@${{"), "{}", out);
+    assert!(out.contains("Findings that could not be fixed automatically:\n- acme/tool@${{ matrix.version }}"), "{}", out);
+}
+
+#[test]
+fn every_occurrence_is_rewritten_in_place() {
+    let repo = Repo::new("fix-in-place", &[("ci.yml", REPEATED)]);
+    let ci = repo.workflow("ci.yml");
+    let output = run(repo.replaying("fix.json", &["fix", ci.to_str().unwrap()]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let fixed = REPEATED
+        .replace("acme/tool@v1 # again", &format!("acme/tool@{} # v1", TOOL))
+        .replace("acme/tool@v1\n", &format!("acme/tool@{} # v1\n", TOOL));
+    assert_eq!(std::fs::read_to_string(&ci).unwrap(), fixed);
+}
+
+#[test]
+fn each_file_gets_the_pin_comment_style_of_its_own_config() {
+    let repo = Repo::new("fix-scoped-style", &[("release.yml", RELEASE)]);
+    write_files(&repo.root, &[("teams/web/dependency-scanner.yaml", "pin_comment_format: 'tag={version}'\n"), ("teams/web/ci.yml", RELEASE)]);
+    let release = repo.workflow("release.yml");
+    let output = run(repo.replaying("fix.json", &["fix", "teams/web/ci.yml", release.to_str().unwrap()]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let scoped = std::fs::read_to_string(repo.root.join("teams/web/ci.yml")).unwrap();
+    assert_eq!(scoped, RELEASE.replace("'acme/tool@v1'", &format!("'acme/tool@{}' # tag=v1", TOOL)));
+    let root = std::fs::read_to_string(&release).unwrap();
+    assert_eq!(root, RELEASE.replace("'acme/tool@v1'", &format!("'acme/tool@{}' # v1", TOOL)));
+}
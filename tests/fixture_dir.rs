@@ -0,0 +1,51 @@
+//! `fixtures record` against a local server standing in for GitHub and OSV, then `--fixtures`
+//! replaying the recorded directory with nothing listening: the replayed scan decides as the
+//! recorded one did
+
+mod common;
+
+use common::*;
+
+/// The scan of `repo` with every endpoint at `base`, `args` going before the workflows and
+/// `subcommand` after them
+fn scan_at(repo: &Repo, base: &str, args: &[&str], subcommand: &[&str]) -> std::process::Output {
+    let (api, osv) = (format!("{}/api/v3", base), format!("{}/osv", base));
+    let mut command = repo.networked(&["--api-url", &api, "--advisory-api-url", &api, "--osv-api-url", &osv]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows()).args(subcommand);
+    run(command, None)
+}
+
+#[test]
+fn a_recorded_fixture_directory_replays_without_the_network() {
+    let repo = Repo::new("fixture-dir", &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@v1.1.0"]))])
+        .config("trusted_owners: [actions, acme]\n");
+    let fixtures = repo.root.join("fixtures");
+    let dir = fixtures.to_str().unwrap();
+
+    let server = MockServer::replaying("vulnerable.json");
+    let recorded = scan_at(&repo, &server.base, &["--format", "json"], &["fixtures", "record", dir]);
+    assert_eq!(code(&recorded), 1, "{}", stderr(&recorded));
+    assert!(!server.requests().is_empty());
+    for file in ["acme/tool/advisories.json", "acme/tool/osv.json", "actions/checkout/advisories.json", "actions/checkout/osv.json"] {
+        let fixture: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(fixtures.join(file)).unwrap()).unwrap();
+        assert_eq!(fixture["status"], 200, "{}: {}", file, fixture);
+    }
+    drop(server);
+
+    // Nothing answers on the discard port, so any lookup that got past the fixtures would fail
+    let replayed = scan_at(&repo, "http://127.0.0.1:9", &["--fixtures", dir, "--frozen", "--format", "json"], &[]);
+    assert_eq!(code(&replayed), 1, "{}", stderr(&replayed));
+    let report = json(&replayed);
+    assert_eq!(report["summary"]["complete"], true, "{}", report);
+    assert_eq!(rules(&report), rules(&json(&recorded)), "{}", report);
+    assert!(report["findings"].as_array().unwrap().iter().any(|f| f["message"].as_str().unwrap().contains("GHSA-abcd-efgh-ijkl")), "{}", report);
+}
+
+#[test]
+fn a_frozen_replay_fails_a_lookup_it_has_no_fixture_for() {
+    let repo = Repo::new("fixture-dir-missing", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let fixtures = repo.root.join("fixtures");
+    let output = scan_at(&repo, "http://127.0.0.1:9", &["--fixtures", fixtures.to_str().unwrap(), "--frozen", "--format", "json"], &[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert!(stderr(&output).contains("(--frozen)"), "{}", stderr(&output));
+}
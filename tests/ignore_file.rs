@@ -0,0 +1,73 @@
+//! The ignore file: entries that suppress findings until they expire, entries keyed on another
+//! scanner's rule ids under `--rule-id-compat`, and malformed entries that stop the scan naming
+//! the file
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str, ignore: &str) -> Repo {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&["acme/deploy@v1"]))]).config("trusted_owners: [actions]\n");
+    write_files(&repo.root, &[("ignore.yaml", ignore)]);
+    repo
+}
+
+fn scan(repo: &Repo, args: &[&str]) -> std::process::Output {
+    let mut all = vec!["--ascii", "--ignore-file", "ignore.yaml"];
+    all.extend(args);
+    all.push(".github/workflows");
+    run(repo.command(&all), None)
+}
+
+#[test]
+fn a_malformed_entry_stops_the_scan_naming_the_file() {
+    let malformed = repo("ignore-malformed", "- action: acme/deploy\n  reason: accepted\n- reason: no target\n");
+    let output = scan(&malformed, &[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    let err = stderr(&output);
+    assert!(err.contains("ignore.yaml"), "{}", err);
+    assert!(err.contains("entry 2 names no action, advisory or rule"), "{}", err);
+
+    let unexplained = repo("ignore-no-reason", "- action: acme/deploy\n  reason: \"  \"\n");
+    let err = stderr(&scan(&unexplained, &[]));
+    assert!(err.contains("ignore.yaml") && err.contains("entry 1 (acme/deploy) has an empty reason"), "{}", err);
+}
+
+#[test]
+fn an_expired_entry_lets_the_finding_fail_again_with_a_warning() {
+    let current = repo("ignore-current", "- action: acme/deploy\n  reason: vendoring soon\n  expires: 2999-01-01\n");
+    let output = scan(&current, &[]);
+    assert_eq!(code(&output), 0, "{}", stdout(&output));
+    assert!(stdout(&output).contains(": suppressed until 2999-01-01 (vendoring soon)"), "{}", stdout(&output));
+
+    let expired = repo("ignore-expired", "- action: acme/deploy\n  reason: vendoring soon\n  expires: 2020-01-01\n");
+    let output = scan(&expired, &[]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(
+        out.contains("[FAIL] Non-trusted action acme/deploy@v1 should use commit SHA instead of tag/branch: suppression expired on 2020-01-01 and it counts again (was: vendoring soon)"),
+        "{}",
+        out
+    );
+
+    let report = json(&scan(&expired, &["--format", "json"]));
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "untrusted-owner-no-sha").unwrap();
+    assert_eq!(finding["suppression"]["expired"], true, "{}", finding);
+}
+
+#[test]
+fn an_entry_keyed_on_a_zizmor_id_suppresses_the_finding_under_rule_id_compat() {
+    let repo = repo("ignore-compat", "- rule: unpinned-uses\n  action: acme/deploy\n  reason: migrated from zizmor\n");
+    let output = scan(&repo, &[]);
+    assert_eq!(code(&output), 2, "{}", stdout(&output));
+    assert!(stdout(&output).contains("ignore file entry unpinned-uses for acme/deploy matched no finding"), "{}", stdout(&output));
+
+    let output = scan(&repo, &["--rule-id-compat", "zizmor", "--format", "json"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "unpinned-uses").unwrap();
+    assert_eq!(finding["suppression"]["reason"], "migrated from zizmor", "{}", finding);
+    // The rule narrows the entry: the action's other finding is not suppressed
+    let untrusted = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "forbidden-uses").unwrap();
+    assert!(untrusted["suppression"].is_null(), "{}", untrusted);
+}
@@ -0,0 +1,64 @@
+//! `exempt_immutable_releases`: tag pins of critical dependencies against a server whose releases
+//! API marks one release immutable, one mutable and leaves the field out of the third
+
+mod common;
+
+use common::*;
+
+const CRITICAL: &str = "critical_dependencies: [actions/checkout@v4.2.2, actions/cache@v4.2.0, actions/setup-node@v4.1.0]\n";
+
+fn scan(repo: &Repo, server: &MockServer) -> std::process::Output {
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").arg(repo.workflows());
+    run(command, None)
+}
+
+fn repo(name: &str, config: &str) -> Repo {
+    let uses = ["actions/checkout@v4.2.2", "actions/cache@v4.2.0", "actions/setup-node@v4.1.0"];
+    Repo::new(name, &[("ci.yml", &workflow_using(&uses))]).config(&format!("trusted_owners: [actions]\n{}{}", CRITICAL, config))
+}
+
+fn pinned(report: &serde_json::Value, rule: &str) -> Vec<String> {
+    let findings = report["findings"].as_array().unwrap();
+    findings.iter().filter(|f| f["rule"] == rule).map(|f| f["action"].as_str().unwrap().to_string()).collect()
+}
+
+#[test]
+fn only_an_immutable_release_is_exempt_from_the_sha_pin() {
+    let server = MockServer::replaying("immutable.json");
+    let output = scan(&repo("immutable", "exempt_immutable_releases: true\n"), &server);
+    let report = json(&output);
+    assert_eq!(pinned(&report, "tag-pinned-critical"), ["actions/cache@v4.2.0", "actions/setup-node@v4.1.0"], "{}", stderr(&output));
+    let notes = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "immutable-release").collect::<Vec<_>>();
+    assert_eq!(notes.len(), 1, "{}", report["findings"]);
+    assert_eq!(notes[0]["message"], "actions/checkout@v4.2.2 needs no SHA pin: tag is immutable (v4.2.2 release)");
+}
+
+#[test]
+fn the_exemption_is_off_by_default() {
+    let server = MockServer::replaying("immutable.json");
+    let output = scan(&repo("immutable-off", ""), &server);
+    assert_eq!(pinned(&json(&output), "tag-pinned-critical").len(), 3, "{}", stderr(&output));
+    assert!(!server.requests().iter().any(|r| r.path.contains("/releases/")));
+}
+
+#[test]
+fn release_lookups_are_cached() {
+    let server = MockServer::replaying("immutable.json");
+    let repo = repo("immutable-cached", "exempt_immutable_releases: true\n");
+    scan(&repo, &server);
+    let releases = |server: &MockServer| server.requests().iter().filter(|r| r.path.contains("/releases/")).count();
+    assert_eq!(releases(&server), 3);
+    let output = scan(&repo, &server);
+    assert_eq!(releases(&server), 3);
+    assert_eq!(pinned(&json(&output), "immutable-release"), ["actions/checkout@v4.2.2"]);
+}
+
+#[test]
+fn a_failed_lookup_keeps_the_sha_pin_findings() {
+    let server = MockServer::answering(500);
+    let output = scan(&repo("immutable-failing", "exempt_immutable_releases: true\n"), &server);
+    assert_eq!(pinned(&json(&output), "tag-pinned-critical").len(), 3, "{}", stderr(&output));
+    assert!(stderr(&output).contains("Warning: could not check whether actions/checkout@v4.2.2 is an immutable release: "), "{}", stderr(&output));
+}
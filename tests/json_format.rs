@@ -0,0 +1,98 @@
+//! `--format json`: the parsed actions, their failed checks and advisories, and the verdict on
+//! stdout with every human-readable line on stderr; lookups come from tests/fixtures/http
+
+mod common;
+
+use common::*;
+
+const CI: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/tool@v1.1.0\n";
+
+fn scan(name: &str, config: &str, fixture: &str, format: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", CI)]).config(config);
+    let mut args = format.to_vec();
+    args.push(".github/workflows");
+    run(repo.replaying(fixture, &args), None)
+}
+
+fn failing(format: &[&str]) -> std::process::Output {
+    scan("json-failing", "trusted_owners: [actions]\ncritical_dependencies: [actions/checkout@v4]\n", "vulnerable.json", format)
+}
+
+fn action<'a>(report: &'a serde_json::Value, action: &str) -> &'a serde_json::Value {
+    report["actions"].as_array().unwrap().iter().find(|a| a["action"] == action).unwrap_or_else(|| panic!("no {} in {}", action, report["actions"]))
+}
+
+#[test]
+fn each_action_carries_its_parsed_reference_failed_checks_and_advisories() {
+    let output = failing(&["--format", "json"]);
+    let report = json(&output);
+    let tool = action(&report, "acme/tool@v1.1.0");
+    assert_eq!(tool["owner"], "acme");
+    assert_eq!(tool["repo"], "tool");
+    assert_eq!(tool["version"], "v1.1.0");
+    assert_eq!(tool["is_commit_sha"], false);
+    assert_eq!(tool["failed_checks"], serde_json::json!(["known-vulnerability", "untrusted-owner", "untrusted-owner-no-sha"]));
+    let advisories = tool["advisories"].as_array().unwrap();
+    assert_eq!(advisories.len(), 1);
+    assert_eq!(advisories[0]["id"], "GHSA-abcd-efgh-ijkl");
+    assert_eq!(advisories[0]["title"], "Command injection in acme/tool");
+    assert_eq!(advisories[0]["severity"], "high");
+
+    let checkout = action(&report, "actions/checkout@v4");
+    assert_eq!(checkout["failed_checks"], serde_json::json!(["tag-pinned-critical"]));
+    assert_eq!(checkout["advisories"], serde_json::json!([]));
+}
+
+#[test]
+fn the_verdict_and_exit_code_are_those_of_the_text_output() {
+    let text = failing(&[]);
+    let output = failing(&["--format", "json"]);
+    let report = json(&output);
+    assert_eq!(code(&output), code(&text));
+    assert_eq!(report["passed"], false);
+    assert_eq!(report["exit"]["code"], 3);
+    assert_eq!(report["exit"]["name"], "vulnerabilities-and-policy-violations");
+
+    let clean = scan("json-clean", "trusted_owners: [actions, acme]\n", "clean.json", &["--format", "json"]);
+    assert_eq!(code(&clean), 0, "{}", stderr(&clean));
+    assert_eq!(json(&clean)["passed"], true);
+}
+
+#[test]
+fn files_list_each_workflow_with_its_verdict_and_counts() {
+    let clean = workflow_using(&[&format!("actions/checkout@{}", SHA)]);
+    let repo = Repo::new("json-files", &[("ci.yml", CI), ("clean.yml", &clean)]).config("trusted_owners: [actions]\n");
+    let output = run(repo.command(&["--format", "json", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let files = json(&output)["files"].clone();
+    assert_eq!(
+        files,
+        serde_json::json!([
+            { "file": ".github/workflows/ci.yml", "passed": false, "worst": "error", "counts": { "error": 1, "warning": 1, "note": 0 } },
+            { "file": ".github/workflows/clean.yml", "passed": true, "counts": { "error": 0, "warning": 0, "note": 0 } },
+        ])
+    );
+}
+
+#[test]
+fn stdout_holds_only_the_report_and_the_chatter_goes_to_stderr() {
+    let output = failing(&["--format", "json"]);
+    let out = stdout(&output);
+    assert!(serde_json::from_str::<serde_json::Value>(&out).is_ok(), "{}", out);
+    assert!(!out.contains("[FAIL]"), "{}", out);
+    let err = stderr(&output);
+    assert!(err.contains("Vulnerability found in acme/tool@v1.1.0!"), "{}", err);
+    assert!(err.contains("[FAIL] Security scan failed!"), "{}", err);
+
+    // The text output goes to stdout as before
+    let text = failing(&[]);
+    assert!(stdout(&text).contains("[FAIL] Security scan failed!"), "{}", stdout(&text));
+}
+
+#[test]
+fn porcelain_conflicts_with_format() {
+    let output = failing(&["--format", "json", "--porcelain", "v1"]);
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("'--format <format>' cannot be used with '--porcelain <porcelain>'"), "{}", stderr(&output));
+    assert!(stdout(&output).is_empty(), "{}", stdout(&output));
+}
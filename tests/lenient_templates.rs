@@ -0,0 +1,41 @@
+//! `--lenient-templates`: workflows whose template placeholders break YAML parsing are read line
+//! by line, with findings at their lines and placeholder references reported as dynamic
+
+mod common;
+
+use common::*;
+
+const HELM: &str = "on: push\njobs:\n  build:\n{{- if .Values.selfHosted }}\n    runs-on: self-hosted\n{{- end }}\n    steps:\n      - uses: {{ .Values.checkout }}\n      - uses: acme/tool@v1\n      - uses: acme/setup@${SETUP_VERSION}\n";
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("chart.yml", HELM)]).config("trusted_owners: [actions]\n")
+}
+
+#[test]
+fn without_the_flag_the_template_is_an_unreadable_workflow() {
+    let output = repo("lenient-off").scan(&["--format", "json"]);
+    let report = json(&output);
+    assert_eq!(report["exit"]["reason"], "1 unreadable workflow >= error threshold", "{}", report);
+    assert!(report["findings"].as_array().unwrap().iter().all(|f| f["action"] != "acme/tool@v1"), "{}", report);
+}
+
+#[test]
+fn a_template_is_parsed_leniently_with_findings_at_their_lines() {
+    let repo = repo("lenient-on");
+    let output = repo.scan(&["--format", "json", "--lenient-templates"]);
+    let report = json(&output);
+    let located: Vec<(&str, &str, u64)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|f| f["rule"] != "unused-config-entry")
+        .map(|f| (f["rule"].as_str().unwrap(), f["action"].as_str().unwrap(), f["line"].as_u64().unwrap()))
+        .collect();
+    assert!(located.contains(&("untrusted-owner-no-sha", "acme/tool@v1", 9)), "{:?}", located);
+    assert!(located.contains(&("dynamic-reference", "acme/setup@${SETUP_VERSION}", 10)), "{:?}", located);
+    assert!(located.iter().all(|(_, action, _)| !action.contains(".Values")), "{:?}", located);
+    assert!(stderr(&output).contains("parsing leniently (--lenient-templates)"), "{}", stderr(&output));
+
+    let out = stdout(&repo.scan(&["--ascii", "--lenient-templates"]));
+    assert!(out.contains("Parsed leniently (--lenient-templates; workflow-level rules skipped):"), "{}", out);
+}
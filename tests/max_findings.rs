@@ -0,0 +1,43 @@
+//! `--max-findings` and `--truncate-structured`: the text lists the most severe findings and
+//! counts the rest, and JSON and SARIF list everything unless told to truncate too
+
+mod common;
+
+use common::*;
+
+/// Twelve untrusted unpinned actions: an error and a warning each, and a note that the trusted owner matched nothing
+fn repo(name: &str) -> Repo {
+    let uses: Vec<String> = (0..12).map(|i| format!("acme/tool{}@v1", i)).collect();
+    Repo::new(name, &[("ci.yml", &workflow_using(&uses.iter().map(String::as_str).collect::<Vec<_>>()))])
+}
+
+#[test]
+fn the_text_counts_the_findings_beyond_the_limit() {
+    let output = repo("max-findings-text").scan(&["--max-findings", "5"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let printed = stdout(&output);
+    let listed = printed.split("\nFindings (highest severity first):\n").nth(1).expect(&printed);
+    let lines: Vec<&str> = listed.lines().take(6).collect();
+    assert!(lines[..5].iter().all(|line| line.starts_with("[FAIL] ")), "errors first: {:?}", lines);
+    assert_eq!(lines[5], "...and 20 more findings (see JSON report)");
+}
+
+#[test]
+fn structured_output_lists_everything_unless_truncated() {
+    let repo = repo("max-findings-json");
+    let full = json(&repo.scan(&["--max-findings", "5", "--format", "json"]));
+    assert_eq!(full["findings"].as_array().unwrap().len(), 25, "{}", full);
+    assert!(full.get("truncated_findings").is_none(), "{}", full);
+
+    let output = repo.scan(&["--max-findings", "5", "--truncate-structured", "--format", "json"]);
+    assert_eq!(code(&output), 2, "the decision still sees everything: {}", stderr(&output));
+    let truncated = json(&output);
+    let findings = truncated["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 5, "{}", truncated);
+    assert!(findings.iter().all(|f| f["level"] == "error"), "{}", truncated);
+    assert_eq!(truncated["truncated_findings"], 20);
+    assert_eq!(truncated["summary"]["findings"]["untrusted_owner"], 12, "the summary counts everything");
+
+    let sarif = json(&repo.scan(&["--max-findings", "5", "--truncate-structured", "--format", "sarif"]));
+    assert_eq!(sarif["runs"][0]["results"].as_array().unwrap().len(), 5, "{}", sarif);
+}
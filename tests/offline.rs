@@ -0,0 +1,91 @@
+//! `--offline` against a local server standing in for GitHub and OSV: no request reaches it, the
+//! local checks still decide the exit, and the report lists what was skipped. Online scans with
+//! every endpoint pointed at the server run end to end without the internet.
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str, uses: &[&str]) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config("trusted_owners: [actions]\n")
+}
+
+/// The scanner with every outbound base URL at `server`, and `args` before the workflows
+fn pointed_at(server: &MockServer, repo: &Repo, args: &[&str]) -> std::process::Output {
+    let (api, osv) = (format!("{}/api/v3", server.base), format!("{}/osv", server.base));
+    let mut command = repo.networked(&["--api-url", &api, "--advisory-api-url", &api, "--osv-api-url", &osv]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+#[test]
+fn an_offline_scan_sends_nothing_and_lists_what_it_skipped() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("offline-skipped", &["actions/checkout@v4", "acme/tool@v1.1.0"]);
+    let output = pointed_at(&server, &repo, &["--offline", "--verify-shas", "--format", "json"]);
+    assert!(server.requests().is_empty(), "{:?}", server.requests());
+    assert_eq!(code(&output), 2, "the local checks still decide: {}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(rules(&report), ["untrusted-owner", "untrusted-owner-no-sha"], "{}", report);
+    assert_eq!(
+        report["skipped_checks"],
+        serde_json::json!([
+            "GitHub and OSV advisory lookups",
+            "default-branch and release lookups of pins",
+            "vendored upstream checks",
+            "commit verification (--verify-shas)"
+        ])
+    );
+    assert_eq!(report["summary"]["complete"], false);
+    assert!(stderr(&output).contains("Offline: skipped GitHub and OSV advisory lookups"), "{}", stderr(&output));
+}
+
+#[test]
+fn a_green_offline_scan_says_it_was_offline() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("offline-green", &["actions/checkout@v4"]);
+    let output = pointed_at(&server, &repo, &["--offline"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let printed = stdout(&output);
+    assert!(printed.contains("- coverage: OFFLINE, local checks only; skipped GitHub and OSV advisory lookups"), "{}", printed);
+    assert!(printed.contains("No local check failed; network checks were skipped (--offline)"), "{}", printed);
+
+    let output = pointed_at(&server, &repo, &["--offline", "--require-complete", "--format", "json"]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert_eq!(json(&output)["exit"]["name"], "operational-error");
+    assert!(server.requests().is_empty(), "{:?}", server.requests());
+}
+
+#[test]
+fn offline_refuses_options_that_need_the_network() {
+    let repo = repo("offline-conflicts", &["actions/checkout@v4"]);
+    for option in ["--submit-dependency-snapshot", "--pushgateway-url=http://127.0.0.1:9", "--record-http=out.json"] {
+        let output = run(repo.command(&[option, ".github/workflows"]), None);
+        assert_eq!(code(&output), 5, "{}", option);
+        let name = option.split('=').next().unwrap();
+        assert!(stderr(&output).contains(name) && stderr(&output).contains("--offline"), "{}: {}", option, stderr(&output));
+    }
+}
+
+#[test]
+fn online_scans_against_a_local_server_run_end_to_end() {
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    for (fixture, trusted, expected, name) in [
+        ("clean.json", "[actions, acme]", 0, "pass"),
+        ("vulnerable.json", "[actions, acme]", 1, "vulnerabilities"),
+        ("vulnerable.json", "[actions]", 3, "vulnerabilities-and-policy-violations"),
+    ] {
+        let server = MockServer::replaying(fixture);
+        let repo = Repo::new("offline-online", &[("ci.yml", &workflow_using(&uses))]).config(&format!("trusted_owners: {}\n", trusted));
+        let output = pointed_at(&server, &repo, &["--format", "json"]);
+        assert_eq!(code(&output), expected, "{} {}: {}", fixture, trusted, stderr(&output));
+        let report = json(&output);
+        assert_eq!(report["exit"]["name"], name, "{}", report);
+        assert!(report.get("skipped_checks").is_none(), "{}", report);
+        assert_eq!(report["summary"]["complete"], true, "{}", report);
+        let requests = server.requests();
+        assert!(requests.iter().any(|r| r.path.starts_with("/api/v3/advisories?")), "{:?}", requests);
+        assert!(requests.iter().any(|r| r.method == "POST" && r.path == "/osv/v1/query"), "{:?}", requests);
+        assert!(requests.iter().filter(|r| r.path.starts_with("/api/v3/")).all(|r| r.authorized), "{:?}", requests);
+    }
+}
@@ -0,0 +1,66 @@
+//! `--check-outdated` and `--fail-on-outdated` against a server where actions/checkout's latest
+//! release is v4.2.2 and acme/tool, with no releases, is tagged up to v1.2.0
+
+mod common;
+
+use common::*;
+
+/// The commit acme/tool's v1.1.0 and v1 tags point at
+const V1_1_0: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+fn scan(server: &MockServer, name: &str, uses: &[&str], args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config("trusted_owners: [actions, acme]\n");
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+fn outdated(output: &std::process::Output) -> Vec<serde_json::Value> {
+    json(output)["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "outdated").cloned().collect()
+}
+
+#[test]
+fn a_tag_behind_the_latest_release_is_a_warning_with_both_versions() {
+    let server = MockServer::replaying("outdated.json");
+    let output = scan(&server, "outdated-tag", &["actions/checkout@v3"], &["--check-outdated"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let findings = outdated(&output);
+    assert_eq!(findings.len(), 1, "{}", stdout(&output));
+    assert_eq!(findings[0]["level"], "warning");
+    assert_eq!(findings[0]["message"], "actions/checkout@v3 is outdated: pinned to v3, latest is v4.2.2");
+    assert_eq!(findings[0]["update"], serde_json::json!({ "current": "v3", "latest": "v4.2.2" }));
+}
+
+#[test]
+fn a_major_tag_follows_its_minor_releases() {
+    let server = MockServer::replaying("outdated.json");
+    let output = scan(&server, "outdated-major", &["actions/checkout@v4", "actions/checkout@v4.2.2"], &["--check-outdated"]);
+    assert!(outdated(&output).is_empty(), "{}", stdout(&output));
+}
+
+#[test]
+fn a_sha_pin_is_compared_by_its_tag_and_tags_stand_in_for_releases() {
+    let server = MockServer::replaying("outdated.json");
+    let pin = format!("acme/tool@{}", V1_1_0);
+    let output = scan(&server, "outdated-sha", &[&pin], &["--check-outdated"]);
+    let findings = outdated(&output);
+    assert_eq!(findings.len(), 1, "{}\n{}", stdout(&output), stderr(&output));
+    assert_eq!(findings[0]["update"], serde_json::json!({ "current": "v1.1.0", "latest": "v1.2.0" }));
+}
+
+#[test]
+fn fail_on_outdated_makes_them_errors() {
+    let server = MockServer::replaying("outdated.json");
+    let output = scan(&server, "outdated-fail", &["actions/checkout@v3"], &["--fail-on-outdated"]);
+    assert_ne!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(outdated(&output)[0]["level"], "error");
+}
+
+#[test]
+fn releases_are_only_looked_up_with_check_outdated() {
+    let server = MockServer::replaying("outdated.json");
+    let output = scan(&server, "outdated-off", &["actions/checkout@v3"], &[]);
+    assert!(outdated(&output).is_empty());
+    assert!(!server.requests().iter().any(|r| r.path.contains("/releases/latest")));
+}
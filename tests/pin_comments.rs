@@ -0,0 +1,116 @@
+//! The version comment next to a SHA pin: `--require-pinning-comment-style` holds every pin to
+//! the house style of `pin_comment_pattern`, and `--verify-pin-comments` checks the tag it names
+//! against a server replaying tests/fixtures/http/pin-comments.json
+
+mod common;
+
+use common::*;
+
+/// The `(line, message)` of each pin-comment finding
+fn pin_comments(output: &std::process::Output) -> Vec<(u64, String)> {
+    let report = json(output);
+    let matching = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "pin-comment");
+    matching.map(|f| (f["line"].as_u64().unwrap(), f["message"].as_str().unwrap().to_string())).collect()
+}
+
+/// A workflow pinning `actions/checkout` by SHA four times, with each of `comments` after it
+fn commented(comments: [&str; 4]) -> String {
+    let uses: Vec<String> = comments.iter().map(|c| format!("actions/checkout@{}{}", SHA, c)).collect();
+    workflow_using(&uses.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+#[test]
+fn every_default_comment_style_is_accepted_and_the_rest_warn() {
+    let workflow = commented(["  # v4.2.2", " # tag=v4.2.2", " # renovate: tag=v4.2.2", ""]);
+    let repo = Repo::new("pin-comment-default", &[("ci.yml", &workflow)]);
+    let output = repo.scan(&["--require-pinning-comment-style", "--format", "json"]);
+    assert_eq!(code(&output), 0, "a pin-comment finding warns: {}", stderr(&output));
+    let expected = format!("SHA pin actions/checkout@{} has no version comment", SHA);
+    assert_eq!(pin_comments(&output), [(9, expected)]);
+
+    let plain = repo.scan(&["--format", "json"]);
+    assert_eq!(pin_comments(&plain), [], "only under the flag");
+}
+
+#[test]
+fn an_organisation_pattern_replaces_the_default() {
+    let workflow = commented([" # pinned: v4.2.2", " # v4.2.2", " # pinned:", " # pinned: v4"]);
+    let repo = Repo::new("pin-comment-custom", &[("ci.yml", &workflow)]).config("trusted_owners: [actions]\npin_comment_pattern: '^pinned: (?P<version>v\\S+)$'\n");
+    let output = repo.scan(&["--require-pinning-comment-style", "--format", "json"]);
+    let found = pin_comments(&output);
+    let lines: Vec<u64> = found.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, [7, 8], "{:?}", found);
+    assert!(found[0].1.ends_with("has a comment not in the pin comment style: # v4.2.2"), "{}", found[0].1);
+    assert!(found[1].1.ends_with("has a comment not in the pin comment style: # pinned:"), "{}", found[1].1);
+}
+
+#[test]
+fn a_pattern_without_a_version_group_fails_the_config() {
+    let repo = Repo::new("pin-comment-invalid", &[("ci.yml", &commented(["", "", "", ""]))]).config("pin_comment_pattern: '^pinned: \\S+$'\n");
+    let output = repo.scan(&["--require-pinning-comment-style"]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert!(stderr(&output).contains("pin_comment_pattern ^pinned: \\S+$ has no named version group"), "{}", stderr(&output));
+}
+
+/// acme/tool's v1.1.0, a lightweight tag
+const V1_1_0: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+/// acme/tool's v1.2.0, behind an annotated tag object
+const V1_2_0: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+/// The `(line, message)` of each pin-comment-mismatch finding in a scan of `uses` with
+/// `--verify-pin-comments`, how many requests named a tag, and the exit code
+fn verified(name: &str, uses: &[&str]) -> (Vec<(u64, String)>, usize, i32) {
+    let server = MockServer::replaying("pin-comments.json");
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config("trusted_owners: [actions, acme]\n");
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--format", "json", "--verify-pin-comments"]);
+    command.env("GITHUB_TOKEN", "test-token").arg(repo.workflows());
+    let output = run(command, None);
+    let report = json(&output);
+    let matching = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "pin-comment-mismatch");
+    let found = matching.map(|f| (f["line"].as_u64().unwrap(), f["message"].as_str().unwrap().to_string())).collect();
+    let tag_requests = server.requests().iter().filter(|r| r.path.contains("/git/")).count();
+    (found, tag_requests, code(&output))
+}
+
+#[test]
+fn comments_naming_the_pinned_tag_pass_annotated_or_not() {
+    let lightweight = format!("acme/tool@{} # v1.1.0", V1_1_0);
+    let annotated = format!("acme/tool@{} # v1.2.0", V1_2_0);
+    let (found, tag_requests, code) = verified("pin-comment-verified", &[&lightweight, &annotated]);
+    assert_eq!(found, []);
+    assert_eq!(tag_requests, 3, "the annotated tag is dereferenced");
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn a_comment_naming_another_tag_is_a_mismatch_naming_the_real_one() {
+    let stale = format!("acme/tool@{} # v1.1.0", V1_2_0);
+    let (found, _, code) = verified("pin-comment-stale", &[&stale]);
+    let expected = format!(
+        "Pin comment mismatch: acme/tool@{} is commented # v1.1.0, but v1.1.0 is commit {}; the pinned commit is v1.2.0",
+        V1_2_0, V1_1_0
+    );
+    assert_eq!(found, [(6, expected)]);
+    assert_eq!(code, 2, "a mismatch is a policy violation");
+}
+
+#[test]
+fn a_comment_naming_no_tag_is_a_mismatch() {
+    let unknown = format!("acme/tool@{} # v9.9.9", "c".repeat(40));
+    let (found, _, _) = verified("pin-comment-unknown", &[&unknown]);
+    let expected = format!(
+        "Pin comment mismatch: acme/tool@{} is commented # v9.9.9, but acme/tool has no tag v9.9.9; no tag points at the pinned commit",
+        "c".repeat(40)
+    );
+    assert_eq!(found, [(6, expected)]);
+}
+
+#[test]
+fn pins_without_a_comment_are_not_looked_up() {
+    let bare = format!("acme/tool@{}", V1_1_0);
+    let (found, tag_requests, code) = verified("pin-comment-bare", &[&bare, "acme/tool@v1.1.0 # v1.2.0"]);
+    assert_eq!(found, []);
+    assert_eq!(tag_requests, 0, "a tag pin's comment is not a pin comment either");
+    assert_eq!(code, 0);
+}
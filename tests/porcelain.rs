@@ -0,0 +1,66 @@
+//! `--porcelain v1`, with and without `-z`, against golden files: the records on stdout byte for
+//! byte, and the human-readable output on stderr
+
+mod common;
+
+use common::*;
+
+const GOLDEN_V1: &[u8] = include_bytes!("golden/porcelain-v1.txt");
+const GOLDEN_V1_Z: &[u8] = include_bytes!("golden/porcelain-v1.nul");
+
+/// One workflow that fails on two untrusted actions and one that passes, scanned by a path
+/// relative to the fixture so the records do not depend on where it lives
+fn scan(args: &[&str]) -> std::process::Output {
+    let ci = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/deploy@v1\n      - uses: acme/notify@main\n";
+    let ok = workflow_using(&["actions/setup-node@v4"]).replace("build:", "ok:");
+    let repo = Repo::new("porcelain", &[("ci.yml", ci), ("ok.yml", &ok)]);
+    let mut all = args.to_vec();
+    all.push(".github/workflows");
+    run(repo.command(&all), None)
+}
+
+/// Byte for byte, with both shown as text when they differ
+fn assert_golden(actual: &[u8], golden: &[u8]) {
+    assert!(
+        actual == golden,
+        "porcelain output differs from the golden file\nexpected:\n{}\nactual:\n{}",
+        String::from_utf8_lossy(golden),
+        String::from_utf8_lossy(actual)
+    );
+}
+
+#[test]
+fn v1_matches_the_golden_file() {
+    let output = scan(&["--porcelain", "v1"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_golden(&output.stdout, GOLDEN_V1);
+}
+
+#[test]
+fn v1_nul_terminated_matches_the_golden_file() {
+    let output = scan(&["--porcelain", "v1", "-z"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_golden(&output.stdout, GOLDEN_V1_Z);
+}
+
+#[test]
+fn nul_terminated_records_carry_the_same_fields() {
+    let lines: Vec<Vec<&[u8]>> = GOLDEN_V1.split(|b| *b == b'\n').filter(|l| !l.is_empty()).map(|l| l.split(|b| *b == b'\t').collect()).collect();
+    let fields: Vec<&[u8]> = GOLDEN_V1_Z.strip_suffix(b"\0").unwrap().split(|b| *b == 0).collect();
+    assert_eq!(lines.concat(), fields);
+}
+
+#[test]
+fn human_output_goes_to_stderr() {
+    let output = scan(&["--porcelain", "v1"]);
+    assert!(stderr(&output).contains("Security scan failed!"), "{}", stderr(&output));
+    assert!(!stdout(&output).contains("Security scan failed!"));
+}
+
+#[test]
+fn json_report_owns_stdout() {
+    let output = scan(&["--format", "json"]);
+    let report = json(&output);
+    assert_eq!(rules(&report), ["untrusted-owner", "untrusted-owner-no-sha", "untrusted-owner", "untrusted-owner-no-sha"]);
+    assert!(stderr(&output).contains("Security scan failed!"));
+}
@@ -0,0 +1,71 @@
+//! `--pushgateway-url` against an in-process listener standing in for the Pushgateway, with the
+//! scan's own lookups answered from tests/fixtures/http/clean.json
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@v1.1.0"]))])
+}
+
+fn scan(repo: &Repo, url: &str, args: &[&str]) -> std::process::Output {
+    let workflows = repo.workflows();
+    let mut all = vec!["--pushgateway-url", url];
+    all.extend(args);
+    all.push(workflows.to_str().unwrap());
+    let mut command = repo.replaying("clean.json", &all);
+    command.env("GITHUB_REPOSITORY", "acme/repo");
+    run(command, None)
+}
+
+#[test]
+fn the_scan_pushes_its_metrics_to_the_group() {
+    let gateway = MockServer::answering(200);
+    let repo = repo("pushgateway");
+    let output = scan(&repo, &format!("{}/", gateway.base), &["--pushgateway-job", "nightly"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let [push] = gateway.requests().try_into().unwrap_or_else(|r| panic!("one push expected: {:?}", r));
+    assert_eq!(push.method, "PUT");
+    // job nightly, instance from $GITHUB_REPOSITORY
+    assert_eq!(push.path, "/metrics/job@base64/bmlnaHRseQ==/instance@base64/YWNtZS9yZXBv");
+    assert_eq!(push.content_type.as_deref(), Some("text/plain; version=0.0.4"));
+    for sample in [
+        "dependency_scanner_findings{level=\"error\"} 1\n",
+        "dependency_scanner_findings{level=\"warning\"} 1\n",
+        "dependency_scanner_actions_scanned 2\n",
+        "dependency_scanner_unchecked_actions 0\n",
+        "dependency_scanner_exit_code 2\n",
+        "# TYPE dependency_scanner_scan_duration_seconds gauge\n",
+    ] {
+        assert!(push.body.contains(sample), "{}: {}", sample, push.body);
+    }
+    for line in push.body.lines().filter(|l| !l.starts_with('#')) {
+        let (series, value) = line.rsplit_once(' ').unwrap();
+        assert!(series.starts_with("dependency_scanner_") && value.parse::<f64>().is_ok(), "{}", line);
+    }
+
+    let output = scan(&repo, &gateway.base, &["--pushgateway-instance", "runner-1"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let push = gateway.requests().pop().unwrap();
+    assert_eq!(push.path, "/metrics/job@base64/ZGVwZW5kZW5jeS1zY2FubmVy/instance@base64/cnVubmVyLTE=");
+}
+
+#[test]
+fn a_failed_push_warns_and_keeps_the_exit_code() {
+    let repo = repo("pushgateway-failing");
+    let refusing = MockServer::answering(500);
+    let output = scan(&repo, &refusing.base, &[]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert!(stderr(&output).contains(&format!("Warning: Pushgateway push to {} returned 500", refusing.base)), "{}", stderr(&output));
+
+    // Nothing listens on a port just released
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let output = scan(&repo, &format!("http://{}", closed), &[]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert!(stderr(&output).contains(&format!("Warning: Pushgateway push to http://{} failed", closed)), "{}", stderr(&output));
+
+    let clean = Repo::new("pushgateway-clean", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let output = scan(&clean, &refusing.base, &[]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+}
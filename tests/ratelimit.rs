@@ -0,0 +1,52 @@
+//! Rate-limited GitHub lookups against a local server that refuses requests as an exhausted
+//! primary limit does, then answers as tests/fixtures/http/vulnerable.json
+
+mod common;
+
+use common::*;
+
+fn scan(server: &MockServer, name: &str, args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&["acme/tool@v1.1.0"]))]).config("trusted_owners: [actions, acme]\n");
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--concurrency", "1", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+fn advisory_queries(server: &MockServer) -> usize {
+    server.requests().iter().filter(|r| r.path.starts_with("/api/v3/advisories?")).count()
+}
+
+#[test]
+fn a_refused_request_is_retried_until_the_limit_lifts() {
+    let server = MockServer::rate_limited(2, 0, "vulnerable.json");
+    let output = scan(&server, "ratelimit-retry", &[]);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    assert_eq!(json(&output)["findings"][0]["advisory"]["id"], "GHSA-abcd-efgh-ijkl");
+    assert!(stderr(&output).contains("Rate limited. Retrying after 0 seconds (1 of 3)..."), "{}", stderr(&output));
+    assert!(stderr(&output).contains("(2 of 3)"), "{}", stderr(&output));
+    assert_eq!(server.requests()[..2].iter().filter(|r| r.authorized).count(), 2, "{:?}", server.requests());
+}
+
+#[test]
+fn after_its_last_retry_the_action_is_left_unchecked() {
+    let server = MockServer::rate_limited(usize::MAX, 0, "vulnerable.json");
+    let output = scan(&server, "ratelimit-give-up", &["--max-retries", "1"]);
+    assert_eq!(code(&output), 10, "a spent limit is not a pass: {}", stderr(&output));
+    assert_eq!(advisory_queries(&server), 2, "{:?}", server.requests());
+    let report = json(&output);
+    assert_eq!(report["unchecked"], serde_json::json!(["acme/tool@v1.1.0"]), "{}", report);
+    let error = report["errors"][0].as_str().unwrap();
+    assert!(error.starts_with("Failed to check acme/tool@v1.1.0: http://") && error.ends_with("/advisories?ecosystem=actions&affects=acme/tool&per_page=100: rate limited (retry after 0s)"), "{}", error);
+    assert!(!stderr(&output).contains("(2 of 1)"), "{}", stderr(&output));
+}
+
+#[test]
+fn a_limit_that_lifts_too_late_is_not_waited_for() {
+    let server = MockServer::rate_limited(usize::MAX, 3600, "vulnerable.json");
+    let output = scan(&server, "ratelimit-too-late", &[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    assert_eq!(json(&output)["unchecked"], serde_json::json!(["acme/tool@v1.1.0"]));
+    assert_eq!(advisory_queries(&server), 1, "{:?}", server.requests());
+    assert!(stderr(&output).contains("Rate limited; the limit lifts in 3600s, too long to wait"), "{}", stderr(&output));
+}
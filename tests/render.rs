@@ -0,0 +1,115 @@
+//! The text and markdown renderers over the same findings, in Unicode and in plain ASCII, against
+//! golden files
+
+mod common;
+
+use common::*;
+use gh_action_security_scanner::artifacts::{self, Artifact};
+use gh_action_security_scanner::clock::{self, Clock};
+use gh_action_security_scanner::config::Config;
+use gh_action_security_scanner::decision;
+use gh_action_security_scanner::render::{self, Charset, Marker};
+use gh_action_security_scanner::{ScanOptions, ScanOutcome, Scanner};
+
+const GOLDEN_UNICODE: &str = include_str!("golden/render-unicode.txt");
+const GOLDEN_ASCII: &str = include_str!("golden/render-ascii.txt");
+
+/// A failing scan with a warning, a policy violation and a pinned action, at a pinned time
+async fn outcome(repo: &Repo) -> ScanOutcome {
+    let config = Config { trusted_owners: Some(["actions".to_string()].into_iter().collect()), ..Default::default() };
+    let options = ScanOptions { offline: true, ..Default::default() };
+    let clock = Clock::Fixed(clock::parse_timestamp("2024-01-02T03:04:05Z").unwrap());
+    let scanner = Scanner::new(config, options).with_clock(clock);
+    scanner.scan(&[repo.workflows()]).await.unwrap()
+}
+
+/// Everything the library renders as text, in `charset`, with the fixture's location left out.
+/// The decision is taken again, as its reason is worded in the charset too.
+fn rendered(repo: &Repo, outcome: &ScanOutcome, charset: Charset) -> String {
+    render::with_charset(charset, || {
+        let decision = decision::decide(&outcome.report, &outcome.decision.policy);
+        let mut out = Vec::new();
+        decision.print_files(&mut out).unwrap();
+        outcome.report.summary.as_ref().unwrap().print(&mut out).unwrap();
+        decision.print(&mut out).unwrap();
+        decision.print_banner(&mut out).unwrap();
+        let markdown = repo.root.join("report.md");
+        artifacts::write_report(&markdown, Artifact::Markdown, &outcome.report, &decision).result.unwrap();
+        out.extend(b"\n");
+        out.extend(std::fs::read(markdown).unwrap());
+        String::from_utf8(out).unwrap().replace(&repo.root.display().to_string(), "<repo>")
+    })
+}
+
+fn assert_golden(actual: &str, golden: &str) {
+    assert!(actual == golden, "rendering differs from the golden file\nexpected:\n{}\nactual:\n{}", golden, actual);
+}
+
+fn repo() -> Repo {
+    let ci = "on: pull_request\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/deploy@v1\n";
+    Repo::new("render", &[("ci.yml", ci)])
+}
+
+#[tokio::test]
+async fn both_charsets_render_the_same_findings() {
+    let repo = repo();
+    let outcome = outcome(&repo).await;
+    let unicode = rendered(&repo, &outcome, Charset::Unicode);
+    let ascii = rendered(&repo, &outcome, Charset::Ascii);
+    assert_golden(&unicode, GOLDEN_UNICODE);
+    assert_golden(&ascii, GOLDEN_ASCII);
+    assert_eq!(unicode.lines().count(), ascii.lines().count(), "only the markers differ");
+}
+
+#[tokio::test]
+async fn the_ascii_renderer_is_plain_ascii() {
+    let repo = repo();
+    let outcome = outcome(&repo).await;
+    let ascii = rendered(&repo, &outcome, Charset::Ascii);
+    assert!(ascii.is_ascii(), "{}", ascii.chars().filter(|c| !c.is_ascii()).collect::<String>());
+    for marker in ["[FAIL]", "[WARN]"] {
+        assert!(ascii.contains(marker), "{} is missing", marker);
+    }
+    assert!(!rendered(&repo, &outcome, Charset::Unicode).is_ascii());
+}
+
+#[test]
+fn with_charset_restores_the_charset_it_replaced() {
+    let before = render::charset();
+    render::with_charset(Charset::Ascii, || {
+        assert_eq!(render::charset(), Charset::Ascii);
+        render::with_charset(Charset::Unicode, || assert_eq!(render::charset(), Charset::Unicode));
+        assert_eq!(render::charset(), Charset::Ascii);
+    });
+    assert_eq!(render::charset(), before);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn runs_side_by_side_each_render_in_their_own_charset() {
+    let rendered = |charset| {
+        render::in_charset(charset, async {
+            tokio::task::yield_now().await;
+            Marker::Fail.to_string()
+        })
+    };
+    let (ascii, unicode) = tokio::join!(tokio::spawn(rendered(Charset::Ascii)), tokio::spawn(rendered(Charset::Unicode)));
+    assert_eq!(ascii.unwrap(), "[FAIL]");
+    assert_eq!(unicode.unwrap(), "❌");
+    assert_eq!(render::charset(), Charset::Unicode, "outside any run");
+}
+
+#[test]
+fn the_binary_falls_back_to_ascii_off_a_terminal_or_for_a_dumb_one() {
+    let repo = repo();
+    for (case, env) in [("piped", None), ("TERM=dumb", Some(("TERM", "dumb"))), ("C locale", Some(("LC_ALL", "C")))] {
+        let mut command = repo.command(&[".github/workflows"]);
+        command.env_remove("NO_COLOR");
+        if let Some((name, value)) = env {
+            command.env(name, value);
+        }
+        let output = run(command, None);
+        assert_eq!(code(&output), 2, "{}: {}", case, stderr(&output));
+        assert!(stdout(&output).is_ascii(), "{}: {}", case, stdout(&output));
+        assert!(stdout(&output).contains("[FAIL] Security scan failed!"), "{}: {}", case, stdout(&output));
+    }
+}
@@ -0,0 +1,232 @@
+//! The full scan pipeline with GitHub and OSV answered from the recorded fixtures in
+//! tests/fixtures/http: `clean.json` knows no advisories for `actions/checkout@v4` and
+//! `acme/tool@v1.1.0`, `vulnerable.json` has GitHub and OSV both report one for `acme/tool`
+//! fixed in 1.2.0, and `unfixed.json` reports it with no patched version
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str, uses: &[&str]) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config("trusted_owners: [actions, acme]\n")
+}
+
+fn replay(repo: &Repo, fixture: &str) -> std::process::Output {
+    let workflows = repo.workflows();
+    run(repo.replaying(fixture, &["--format", "json", workflows.to_str().unwrap()]), None)
+}
+
+#[test]
+fn clean_lookups_pass() {
+    let repo = repo("replay-clean", &["actions/checkout@v4", "acme/tool@v1.1.0"]);
+    let output = replay(&repo, "clean.json");
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    assert!(rules(&report).is_empty(), "{}", report);
+    assert!(report["unchecked"].as_array().unwrap().is_empty(), "{}", report);
+}
+
+#[test]
+fn recorded_advisory_fails_the_scan() {
+    let repo = repo("replay-vulnerable", &["actions/checkout@v4", "acme/tool@v1.1.0"]);
+    let output = replay(&repo, "vulnerable.json");
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(rules(&report), ["known-vulnerability"]);
+    let finding = &report["findings"][0];
+    assert_eq!(finding["action"], "acme/tool@v1.1.0");
+    assert_eq!(finding["severity"], "high");
+    assert_eq!(finding["advisory"]["id"], "GHSA-abcd-efgh-ijkl");
+    // The GitHub advisory and the OSV record of it are one finding
+    assert_eq!(finding["advisory"]["sources"], serde_json::json!(["github", "osv"]));
+    assert!(finding["message"].as_str().unwrap().contains("fix available: 1.2.0"), "{}", finding);
+}
+
+#[test]
+fn fixed_version_passes_on_the_github_record() {
+    // Requests match on their body: the fixture's OSV query is about v1.1.0, so the one about
+    // v1.2.0 goes unanswered and GitHub's range alone decides
+    let repo = repo("replay-fixed", &["actions/checkout@v4", "acme/tool@v1.2.0"]);
+    let output = replay(&repo, "vulnerable.json");
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(rules(&json(&output)).is_empty(), "{}", stdout(&output));
+    let warning = "acme/tool OSV: https://api.osv.dev/v1/query: no recorded response for POST https://api.osv.dev/v1/query (using the other source)";
+    assert!(stderr(&output).contains(warning), "{}", stderr(&output));
+}
+
+#[test]
+fn unrecorded_request_is_a_lookup_error() {
+    let repo = repo("replay-unrecorded", &["actions/checkout@v4", "acme/other@v2"]);
+    let output = replay(&repo, "clean.json");
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["unchecked"], serde_json::json!(["acme/other@v2"]), "{}", report);
+    let message = "no recorded response for GET https://api.github.com/advisories?ecosystem=actions&affects=acme/other&per_page=100";
+    assert!(stderr(&output).contains(message), "{}", stderr(&output));
+}
+
+#[test]
+fn the_cvss_score_goes_through_the_configured_severity_mapping() {
+    // The fixture's advisory is labelled high with a CVSS score of 8.1
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    let stricter = "trusted_owners: [actions, acme]\nseverity_mapping: {critical: \">=7.0\", high: \">=4.0\", medium: \">=0.1\", low: 0.0-0.0}\n";
+    for (config, severity, args, exit) in [
+        ("trusted_owners: [actions, acme]\n", "high", &["--fail-on-severity", "critical"][..], 0),
+        (stricter, "critical", &[][..], 1),
+        (stricter, "critical", &["--fail-on-severity", "critical"][..], 1),
+    ] {
+        let repo = Repo::new("replay-severity", &[("ci.yml", &workflow_using(&uses))]).config(config);
+        let workflows = repo.workflows();
+        let mut all = vec!["--format", "json", workflows.to_str().unwrap()];
+        all.extend(args);
+        let output = run(repo.replaying("vulnerable.json", &all), None);
+        assert_eq!(code(&output), exit, "{} {:?}: {}", config, args, stderr(&output));
+        assert_eq!(json(&output)["findings"][0]["severity"], severity, "{}", config);
+    }
+}
+
+#[test]
+fn a_moderate_advisory_fails_unless_the_threshold_is_raised() {
+    // Under this table the fixture's 8.1 is medium, which GitHub labels moderate
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    let config = "trusted_owners: [actions, acme]\nseverity_mapping: {critical: \">=9.5\", high: \">=9.0\", medium: \">=4.0\", low: \">=0.0\"}\n";
+    for (args, exit, level) in [
+        (&[][..], 1, "error"),
+        (&["--fail-on-severity", "low"][..], 1, "error"),
+        (&["--fail-on-severity", "high"][..], 0, "warning"),
+    ] {
+        let repo = Repo::new("replay-moderate", &[("ci.yml", &workflow_using(&uses))]).config(config);
+        let workflows = repo.workflows();
+        let mut all = vec!["--format", "json", workflows.to_str().unwrap()];
+        all.extend(args);
+        let output = run(repo.replaying("vulnerable.json", &all), None);
+        assert_eq!(code(&output), exit, "{:?}: {}", args, stderr(&output));
+        let finding = &json(&output)["findings"][0];
+        assert_eq!((finding["severity"].as_str(), finding["level"].as_str()), (Some("medium"), Some(level)), "{:?}", args);
+    }
+}
+
+#[test]
+fn an_advisory_without_a_fix_only_warns_under_fail_only_if_fix_available() {
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    for (toggle, fixture, exit, level, note) in [
+        ("false", "unfixed.json", 1, "error", "no fix available"),
+        ("true", "unfixed.json", 0, "warning", "no fix available"),
+        ("true", "vulnerable.json", 1, "error", "fix available: 1.2.0"),
+    ] {
+        let config = format!("trusted_owners: [actions, acme]\nfail_only_if_fix_available: {}\n", toggle);
+        let repo = Repo::new("replay-unfixed", &[("ci.yml", &workflow_using(&uses))]).config(&config);
+        let output = replay(&repo, fixture);
+        assert_eq!(code(&output), exit, "{} {}: {}", toggle, fixture, stderr(&output));
+        let report = json(&output);
+        let finding = &report["findings"][0];
+        assert_eq!((finding["rule"].as_str(), finding["level"].as_str()), (Some("known-vulnerability"), Some(level)), "{}", report);
+        assert!(finding["message"].as_str().unwrap().ends_with(&format!("({})", note)), "{}", finding);
+    }
+}
+
+#[test]
+fn an_advisory_inside_its_grace_period_is_due_in_every_output() {
+    // The fixture's advisory was published 2024-01-02T03:04:05Z
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    let repo = Repo::new("replay-grace", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions, acme]\ngrace_period_days: 14\n");
+    let workflows = repo.workflows();
+    let scan = |timestamp: &str, format: &str| {
+        let args = ["--timestamp", timestamp, "--format", format, "--output-dir", "out", workflows.to_str().unwrap()];
+        run(repo.replaying("vulnerable.json", &args), None)
+    };
+
+    let output = scan("2024-01-10T00:00:00Z", "json");
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    let finding = &report["findings"][0];
+    assert_eq!((finding["level"].as_str(), finding["due_by"].as_str()), (Some("warning"), Some("2024-01-16")), "{}", finding);
+    let markdown = std::fs::read_to_string(repo.root.join("out/report.md")).unwrap();
+    assert!(markdown.contains("| high | Command injection in acme/tool (due by 2024-01-16) |"), "{}", markdown);
+    let output = scan("2024-01-10T00:00:00Z", "text");
+    assert!(stdout(&output).contains("fix available: 1.2.0, due by 2024-01-16"), "{}", stdout(&output));
+
+    let output = scan("2024-01-16T03:04:05Z", "json");
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    let finding = &json(&output)["findings"][0];
+    assert_eq!((finding["level"].as_str(), finding.get("due_by")), (Some("error"), None), "{}", finding);
+}
+
+#[test]
+fn github_advisories_apply_to_the_versions_in_their_ranges() {
+    // tests/fixtures/http/tj-actions.json records GitHub's answer for tj-actions/changed-files:
+    // GHSA-mrrh-fwg8-r2c3 below 46.0.1 and GHSA-mcph-m25j-8j63 below 41
+    for (version, exit, advisories) in [
+        ("v45.0.7", 1, &["GHSA-mrrh-fwg8-r2c3"][..]),
+        ("v40.0.0", 1, &["GHSA-mcph-m25j-8j63", "GHSA-mrrh-fwg8-r2c3"][..]),
+        ("v46.0.1", 0, &[][..]),
+        // A partial tag may resolve to a vulnerable release, so both still apply
+        ("v46", 1, &["GHSA-mcph-m25j-8j63", "GHSA-mrrh-fwg8-r2c3"][..]),
+    ] {
+        let uses = format!("tj-actions/changed-files@{}", version);
+        let repo = Repo::new("replay-ranges", &[("ci.yml", &workflow_using(&["actions/checkout@v4", &uses]))]).config("trusted_owners: [actions, tj-actions]\n");
+        let workflows = repo.workflows();
+        let output = run(repo.replaying("tj-actions.json", &["--source", "github", "--format", "json", workflows.to_str().unwrap()]), None);
+        assert_eq!(code(&output), exit, "{}: {}", version, stderr(&output));
+        let report = json(&output);
+        let found: Vec<&str> = report["findings"].as_array().unwrap().iter().map(|f| f["advisory"]["id"].as_str().unwrap()).collect();
+        assert_eq!(found, advisories, "{}", version);
+        assert!(report["findings"].as_array().unwrap().iter().all(|f| f["severity"] == "high"), "{}", report);
+    }
+}
+
+#[test]
+fn advisories_since_leaves_out_older_advisories_unless_they_fail_the_scan() {
+    // The fixture's advisory was published 2024-01-02T03:04:05Z
+    let uses = ["actions/checkout@v4", "acme/tool@v1.1.0"];
+    let repo = repo("replay-since", &uses);
+    let workflows = repo.workflows();
+    let scan = |args: &[&str]| {
+        let mut all = vec!["--format", "json"];
+        all.extend(args);
+        all.push(workflows.to_str().unwrap());
+        run(repo.replaying("vulnerable.json", &all), None)
+    };
+    let counts = |report: &serde_json::Value| (report["advisories_since"]["filtered"].as_u64(), report["advisories_since"]["kept"].as_u64());
+
+    // A cutoff at or before publication keeps it, whatever the offset it is written in
+    for since in ["2024-01-02", "2024-01-02T03:04:05Z", "2024-01-02T04:04:05+01:00"] {
+        let output = scan(&["--advisories-since", since]);
+        assert_eq!(code(&output), 1, "{}: {}", since, stderr(&output));
+        let report = json(&output);
+        assert_eq!(rules(&report), ["known-vulnerability"], "{}", since);
+        assert_eq!(counts(&report), (Some(0), Some(0)), "{}", since);
+    }
+
+    // After it: the error still fails and stays, unless --fail-only-recent
+    for since in ["2024-01-03", "2024-01-02T03:04:05-00:01"] {
+        let output = scan(&["--advisories-since", since]);
+        assert_eq!(code(&output), 1, "{}: {}", since, stderr(&output));
+        let report = json(&output);
+        assert_eq!(rules(&report), ["known-vulnerability"], "{}", since);
+        assert_eq!(counts(&report), (Some(0), Some(1)), "{}", since);
+    }
+    let output = scan(&["--advisories-since", "2024-01-03", "--fail-only-recent"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    assert!(rules(&report).is_empty(), "{}", report);
+    assert_eq!(counts(&report), (Some(1), Some(0)));
+    assert_eq!(report["advisories_since"]["since"], "2024-01-03T00:00:00Z");
+    let output = run(repo.replaying("vulnerable.json", &["--advisories-since", "2024-01-03", workflows.to_str().unwrap()]), None);
+    let summary = "Advisories published before 2024-01-03T00:00:00Z: 0 left out (--advisories-since); 1 older still shown because they fail the scan";
+    assert!(stdout(&output).contains(summary), "{}", stdout(&output));
+
+    // Inside its grace period the advisory only warns, so it goes with the other older ones
+    let graced = Repo::new("replay-since-grace", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions, acme]\ngrace_period_days: 14\n");
+    let workflows = graced.workflows();
+    let args = ["--format", "json", "--timestamp", "2024-01-10T00:00:00Z", "--advisories-since", "2024-01-03", workflows.to_str().unwrap()];
+    let output = run(graced.replaying("vulnerable.json", &args), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let report = json(&output);
+    assert!(rules(&report).is_empty(), "{}", report);
+    assert_eq!(counts(&report), (Some(1), Some(0)));
+
+    let output = scan(&["--advisories-since", "01/03/2024"]);
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("01/03/2024 is neither a date (2024-01-01) nor an RFC 3339 time"), "{}", stderr(&output));
+}
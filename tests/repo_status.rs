@@ -0,0 +1,87 @@
+//! `--check-repo-status` against a server where one action's repository is archived, one is
+//! disabled and one no longer exists
+
+mod common;
+
+use common::*;
+
+const USES: [&str; 4] = ["actions/checkout@v4", "acme/old@v1", "acme/banned@v1", "acme/gone@v1"];
+
+fn scan(server: &MockServer, name: &str, config: &str, args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&USES))]).config(config);
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+/// The `(action, level, message)` of each repository finding
+fn statuses(output: &std::process::Output) -> Vec<(String, String, String)> {
+    let report = json(output);
+    let findings = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"].as_str().unwrap().starts_with("repository-"));
+    findings
+        .map(|f| {
+            let text = |key: &str| format!("{}:{}", f["rule"].as_str().unwrap(), f[key].as_str().unwrap());
+            (text("action"), f["level"].as_str().unwrap().to_string(), f["message"].as_str().unwrap().to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn archived_disabled_and_missing_repositories_are_flagged() {
+    let server = MockServer::replaying("repo-status.json");
+    let output = scan(&server, "repo-status", "trusted_owners: [actions, acme]\n", &["--check-repo-status"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let expected = [
+        (
+            "repository-archived:acme/old@v1",
+            "warning",
+            "Action acme/old@v1 comes from acme/old, which is archived (archived 2023-05-01T00:00:00Z, last pushed 2023-04-30T12:00:00Z): \
+             it gets no more security fixes",
+        ),
+        (
+            "repository-disabled:acme/banned@v1",
+            "error",
+            "Action acme/banned@v1 comes from acme/banned, which GitHub has disabled; workflows cannot fetch it",
+        ),
+        (
+            "repository-not-found:acme/gone@v1",
+            "error",
+            "Action acme/gone@v1 comes from acme/gone, which does not exist (deleted, or private to this token); workflows cannot run it, \
+             and the name can be registered by someone else",
+        ),
+    ];
+    let mut found = statuses(&output);
+    found.sort();
+    let expected: Vec<(String, String, String)> = expected.iter().map(|(a, l, m)| (a.to_string(), l.to_string(), m.to_string())).collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn an_archived_critical_dependency_is_an_error() {
+    let server = MockServer::replaying("repo-status.json");
+    let config = "trusted_owners: [actions, acme]\ncritical_dependencies: [acme/old@v1]\n";
+    let output = scan(&server, "repo-status-critical", config, &["--check-repo-status"]);
+    let archived = statuses(&output).into_iter().find(|(action, _, _)| action.starts_with("repository-archived")).unwrap();
+    assert_eq!(archived.1, "error");
+    assert!(archived.2.ends_with(", and it is a critical dependency"), "{}", archived.2);
+}
+
+#[test]
+fn repositories_are_looked_up_once_and_only_when_asked() {
+    let server = MockServer::replaying("repo-status.json");
+    let output = scan(&server, "repo-status-off", "trusted_owners: [actions, acme]\n", &[]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(statuses(&output).is_empty());
+    let lookups = |server: &MockServer| server.requests().iter().filter(|r| USES.iter().any(|u| r.path.ends_with(&format!("/repos/{}", u.split('@').next().unwrap())))).count();
+    assert_eq!(lookups(&server), 0);
+
+    let repo = Repo::new("repo-status-cached", &[("ci.yml", &workflow_using(&USES))]).config("trusted_owners: [actions, acme]\n");
+    let api = format!("{}/api/v3", server.base);
+    for _ in 0..2 {
+        let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--check-repo-status"]);
+        command.env("GITHUB_TOKEN", "test-token").arg(repo.workflows());
+        run(command, None);
+    }
+    assert_eq!(lookups(&server), 4, "the second scan answers from the cache");
+}
@@ -0,0 +1,57 @@
+//! `--report`: the markdown report of a scan with a vulnerable, a mis-pinned and an untrusted
+//! action, and of a clean one, against golden files; lookups come from tests/fixtures/http
+
+mod common;
+
+use common::*;
+
+const GOLDEN: &str = include_str!("golden/report.md");
+const GOLDEN_CLEAN: &str = include_str!("golden/report-clean.md");
+const CI: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/tool@v1.1.0\n";
+
+/// The workflow scanned by a relative path with a pinned clock, writing `--report report.out`
+fn report(name: &str, config: &str, fixture: &str, args: &[&str]) -> (std::process::Output, String) {
+    let repo = Repo::new(name, &[("ci.yml", CI)]).config(config);
+    let mut all = vec!["--timestamp", "2024-01-02T03:04:05Z", "--report", "report.out"];
+    all.extend(args);
+    all.push(".github/workflows");
+    let output = run(repo.replaying(fixture, &all), None);
+    let written = std::fs::read_to_string(repo.root.join("report.out")).unwrap_or_else(|e| panic!("{}: {}", e, stderr(&output)));
+    (output, written)
+}
+
+fn failing(args: &[&str]) -> (std::process::Output, String) {
+    report("report-failing", "trusted_owners: [actions]\ncritical_dependencies: [actions/checkout@v4]\n", "vulnerable.json", args)
+}
+
+#[test]
+fn the_markdown_report_matches_the_golden_file() {
+    let (output, written) = failing(&[]);
+    assert_eq!(code(&output), 3, "{}", stderr(&output));
+    assert!(written == GOLDEN, "report differs from the golden file\nexpected:\n{}\nactual:\n{}", GOLDEN, written);
+}
+
+#[test]
+fn a_clean_scan_reports_all_clear() {
+    let (output, written) = report("report-clean", "trusted_owners: [actions, acme]\n", "clean.json", &["--report-format", "markdown"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(written == GOLDEN_CLEAN, "report differs from the golden file\nexpected:\n{}\nactual:\n{}", GOLDEN_CLEAN, written);
+}
+
+#[test]
+fn the_tables_hold_what_the_json_report_holds() {
+    let (output, markdown) = failing(&["--format", "json"]);
+    let report = json(&output);
+    let findings = report["findings"].as_array().unwrap();
+    let advisory = findings.iter().find(|f| f["rule"] == "known-vulnerability").unwrap();
+    let id = advisory["advisory"]["id"].as_str().unwrap();
+    assert!(markdown.contains(&format!("[{}](https://github.com/advisories/{})", id, id)), "{}", markdown);
+    for pin in findings.iter().filter(|f| ["tag-pinned-critical", "untrusted-owner-no-sha"].contains(&f["rule"].as_str().unwrap())) {
+        let row = format!("| `{}` | `{}` | {} |", pin["action"].as_str().unwrap(), pin["file"].as_str().unwrap(), pin["line"]);
+        assert!(markdown.contains(&row), "{}: {}", row, markdown);
+    }
+
+    // The JSON report written by --report is the one --format json prints
+    let (output, written) = failing(&["--format", "json", "--report-format", "json"]);
+    assert_eq!(written, stdout(&output));
+}
@@ -0,0 +1,138 @@
+//! `review-bump` over a git checkout where a Dependabot-style branch changes pins, with tag and
+//! advisory lookups answered from tests/fixtures/http/review-bump.json
+
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::process::Command;
+
+const BASE: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4.1.1\n      - uses: actions/setup-node@v4.0.0\n";
+/// The commit the fixture's `v4.1.2` tag resolves to
+const V4_1_2: &str = "b4ffde65f46336ab88eb53be808477a3936bae11";
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// A checkout whose `main` has [`BASE`] and whose Dependabot branch, checked out, replaces `from`
+/// in it with `to`; returns the repo with the base and head commits
+fn checkout(name: &str, from: &str, to: &str) -> (Repo, String, String) {
+    checkout_from(name, BASE, from, to)
+}
+
+/// [`checkout`] with `workflow` on `main` instead of [`BASE`]
+fn checkout_from(name: &str, workflow: &str, from: &str, to: &str) -> (Repo, String, String) {
+    let repo = Repo::new(name, &[("ci.yml", workflow)]);
+    git(&repo.root, &["init", "-q", "-b", "main"]);
+    git(&repo.root, &["add", "-A"]);
+    git(&repo.root, &["commit", "-qm", "base"]);
+    let base = git(&repo.root, &["rev-parse", "HEAD"]);
+    git(&repo.root, &["checkout", "-qb", "dependabot/github_actions/actions/checkout-4.1.2"]);
+    std::fs::write(repo.workflow("ci.yml"), workflow.replace(from, to)).unwrap();
+    git(&repo.root, &["commit", "-qam", "Bump actions/checkout"]);
+    let head = git(&repo.root, &["rev-parse", "HEAD"]);
+    (repo, base, head)
+}
+
+fn review(repo: &Repo, base: &str, head: &str) -> std::process::Output {
+    run(repo.replaying("review-bump.json", &["review-bump", "--base", base, "--head", head]), None)
+}
+
+#[test]
+fn a_dependabot_event_reviews_only_the_changed_pin() {
+    let (repo, base, head) = checkout("bump-event", "checkout@v4.1.1", "checkout@v4.1.2");
+    let mut event: serde_json::Value = serde_json::from_str(include_str!("fixtures/events/dependabot-pull-request.json")).unwrap();
+    event["pull_request"]["base"]["sha"] = base.clone().into();
+    event["pull_request"]["head"]["sha"] = head.clone().into();
+    write_files(&repo.root, &[("event.json", &event.to_string())]);
+
+    let mut command = repo.replaying("review-bump.json", &["review-bump", "--output", "verdict.md"]);
+    command.env("GITHUB_EVENT_PATH", repo.root.join("event.json"));
+    let output = run(command, None);
+    assert_eq!(code(&output), 0, "{}\n{}", stdout(&output), stderr(&output));
+    let verdict = std::fs::read_to_string(repo.root.join("verdict.md")).unwrap();
+    let expected = format!(
+        "### Action bump review: {}...{}\n\n\
+         **Verdict: [OK] pass** (no findings >= error threshold)\n\n\
+         | Workflow | Action | From | To | Result |\n|---|---|---|---|---|\n\
+         | `.github/workflows/ci.yml`:6 | `actions/checkout` | `v4.1.1` | `v4.1.2` | [OK] |\n",
+        &base[..7],
+        &head[..7]
+    );
+    assert_eq!(verdict, expected);
+}
+
+#[test]
+fn a_downgrade_fails_the_review() {
+    let (repo, base, head) = checkout("bump-downgrade", "checkout@v4.1.1", "checkout@v4.1.0");
+    let output = review(&repo, &base, &head);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let verdict = stdout(&output);
+    assert!(verdict.contains("| `actions/checkout` | `v4.1.1` | `v4.1.0` | [FAIL] |"), "{}", verdict);
+    assert!(verdict.contains("- [FAIL] **pin-downgrade** `.github/workflows/ci.yml:6`: actions/checkout moves from v4.1.1 down to v4.1.0"), "{}", verdict);
+    assert!(!verdict.contains("setup-node"), "unchanged pins are not reviewed: {}", verdict);
+}
+
+#[test]
+fn a_sha_pin_must_be_the_commit_its_tag_comment_names() {
+    let pinned = |sha: &str| format!("checkout@{} # v4.1.2", sha);
+    let (repo, base, head) = checkout("bump-sha", "checkout@v4.1.1", &pinned(V4_1_2));
+    let output = review(&repo, &base, &head);
+    assert_eq!(code(&output), 0, "{}\n{}", stdout(&output), stderr(&output));
+    assert!(stdout(&output).contains("| `b4ffde6` (`v4.1.2`) | [OK] |"), "{}", stdout(&output));
+
+    let (repo, base, head) = checkout("bump-sha-mismatch", "checkout@v4.1.1", &pinned(SHA));
+    let output = review(&repo, &base, &head);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let mismatch = format!("**pin-tag-mismatch** `.github/workflows/ci.yml:6`: actions/checkout is pinned to {} but tag v4.1.2 resolves to {}", SHA, V4_1_2);
+    assert!(stdout(&output).contains(&mismatch), "{}", stdout(&output));
+}
+
+#[test]
+fn a_change_that_moves_no_pin_has_nothing_to_review() {
+    let (repo, base, head) = checkout("bump-none", "runs-on: ubuntu-latest", "runs-on: ubuntu-24.04");
+    let output = review(&repo, &base, &head);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(stdout(&output).contains("[OK] No action pins changed."), "{}", stdout(&output));
+}
+
+#[test]
+fn a_repository_the_base_does_not_use_needs_approval() {
+    let (repo, base, head) = checkout("bump-new", "actions/setup-node@v4.0.0", "acme/tool@v1.1.0");
+    let output = review(&repo, &base, &head);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let verdict = stdout(&output);
+    assert!(verdict.contains("**new-dependency** `.github/workflows/ci.yml:7`: acme/tool is a new dependency and needs security approval in approved_new_dependencies ("), "{}", verdict);
+}
+
+#[test]
+fn an_approved_or_trusted_new_repository_passes() {
+    let (repo, base, head) = checkout("bump-new-approved", "actions/setup-node@v4.0.0", "acme/tool@v1.1.0");
+    let repo = repo.config("trusted_owners: [actions]\napproved_new_dependencies: [Acme/Tool]\n");
+    let output = review(&repo, &base, &head);
+    assert!(!stdout(&output).contains("new-dependency"), "{}", stdout(&output));
+    // Only the pinning check is left to fail the untrusted owner's tag pin
+    assert!(stdout(&output).contains("| `acme/tool` | (new) | `v1.1.0` | [FAIL] |"), "{}", stdout(&output));
+
+    let (repo, base, head) = checkout("bump-new-trusted", "actions/setup-node@v4.0.0", "actions/cache@v4.0.0");
+    let output = review(&repo, &base, &head);
+    assert_eq!(code(&output), 0, "{}\n{}", stdout(&output), stderr(&output));
+    assert!(!stdout(&output).contains("new-dependency"), "{}", stdout(&output));
+}
+
+#[test]
+fn a_version_bump_of_a_repository_the_base_uses_is_not_new() {
+    let workflow = BASE.replace("actions/setup-node@v4.0.0", "acme/tool@v1.0.0");
+    let (repo, base, head) = checkout_from("bump-existing", &workflow, "acme/tool@v1.0.0", "acme/tool@v1.1.0");
+    let output = review(&repo, &base, &head);
+    assert!(stdout(&output).contains("`acme/tool` | `v1.0.0` | `v1.1.0`"), "{}", stdout(&output));
+    assert!(!stdout(&output).contains("new-dependency"), "{}", stdout(&output));
+}
@@ -0,0 +1,503 @@
+//! Workflow-level rules, which look at a whole job or workflow rather than one `uses:`
+
+mod common;
+
+use common::*;
+
+/// The `(job, message)` of each finding of `rule` in a `--format json` report
+fn findings(output: &std::process::Output, rule: &str) -> Vec<(String, String)> {
+    let report = json(output);
+    let matching = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == rule);
+    matching.map(|f| (f["job"].as_str().unwrap_or_default().to_string(), f["message"].as_str().unwrap().to_string())).collect()
+}
+
+/// A job `deploy` granting `permissions` (a YAML flow value), or inheriting the workflow's when
+/// `None`, and running `uses`
+fn oidc_workflow(workflow_permissions: Option<&str>, job_permissions: Option<&str>, uses: &[&str]) -> String {
+    let workflow = workflow_permissions.map(|p| format!("permissions: {}\n", p)).unwrap_or_default();
+    let job = job_permissions.map(|p| format!("    permissions: {}\n", p)).unwrap_or_default();
+    let steps: String = uses.iter().enumerate().map(|(i, u)| format!("      - name: Step {}\n        uses: {}\n", i, u)).collect();
+    format!("on: push\n{}jobs:\n  deploy:\n    runs-on: ubuntu-latest\n{}    steps:\n{}", workflow, job, steps)
+}
+
+#[test]
+fn id_token_write_with_untrusted_actions_lists_the_offending_steps() {
+    let pinned = format!("actions/checkout@{}", SHA);
+    for (case, workflow_permissions, job_permissions, scope) in [
+        ("workflow-level grant", Some("{id-token: write}"), None, "workflow-level"),
+        ("job-level grant", None, Some("{id-token: write, contents: read}"), "job-level"),
+        ("write-all", Some("write-all"), None, "workflow-level"),
+    ] {
+        let workflow = oidc_workflow(workflow_permissions, job_permissions, &[&pinned, "acme/deploy@v1", "actions/setup-node@v4"]);
+        let repo = Repo::new("oidc", &[("ci.yml", &workflow)]);
+        let found = findings(&repo.scan(&["--format", "json"]), "oidc-untrusted-action");
+        assert_eq!(found.len(), 1, "{}: {:?}", case, found);
+        let (job, message) = &found[0];
+        assert_eq!(job, "deploy", "{}", case);
+        assert!(message.contains(&format!("({})", scope)), "{}: {}", case, message);
+        assert!(
+            message.contains("\"Step 1\" (acme/deploy@v1), \"Step 2\" (actions/setup-node@v4)"),
+            "{}: untrusted and unpinned steps are listed: {}",
+            case,
+            message
+        );
+        assert!(!message.contains("Step 0"), "{}: the trusted pinned step is not: {}", case, message);
+        assert!(message.contains("Move the OIDC token exchange into its own job"), "{}: {}", case, message);
+    }
+}
+
+#[test]
+fn id_token_write_without_risky_steps_or_without_the_grant_is_fine() {
+    let pinned = format!("actions/checkout@{}", SHA);
+    for (case, workflow_permissions, job_permissions, uses) in [
+        ("only trusted pinned steps", Some("{id-token: write}"), None, vec![pinned.as_str()]),
+        ("job permissions replace the workflow grant", Some("{id-token: write}"), Some("{contents: read}"), vec!["acme/deploy@v1"]),
+        ("id-token read", Some("{id-token: read}"), None, vec!["acme/deploy@v1"]),
+        ("no permissions at all", None, None, vec!["acme/deploy@v1"]),
+        ("read-all", Some("read-all"), None, vec!["acme/deploy@v1"]),
+    ] {
+        let repo = Repo::new("oidc-fine", &[("ci.yml", &oidc_workflow(workflow_permissions, job_permissions, &uses))]);
+        let found = findings(&repo.scan(&["--format", "json"]), "oidc-untrusted-action");
+        assert!(found.is_empty(), "{}: {:?}", case, found);
+    }
+}
+
+#[test]
+fn only_the_jobs_that_mix_the_grant_and_risky_steps_are_flagged() {
+    let workflow = "on: push
+permissions:
+  id-token: write
+jobs:
+  mint:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: aws-actions/configure-aws-credentials@0123456789abcdef0123456789abcdef01234567
+  build:
+    runs-on: ubuntu-latest
+    permissions:
+      contents: read
+    steps:
+      - uses: acme/build@v1
+  publish:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: acme/publish@v1
+";
+    let repo = Repo::new("oidc-jobs", &[("ci.yml", workflow)]).config("trusted_owners: [actions, aws-actions]\n");
+    let found = findings(&repo.scan(&["--format", "json"]), "oidc-untrusted-action");
+    let jobs: Vec<&str> = found.iter().map(|(job, _)| job.as_str()).collect();
+    assert_eq!(jobs, ["publish"], "{:?}", found);
+}
+
+/// Every job shape the extractor meets: steps, an aliased job, a reusable workflow call, and four
+/// it cannot see into
+const SHAPES: &str = "on: push
+jobs:
+  base: &base
+    runs-on: ubuntu-latest
+    steps:
+      - uses: acme/aliased@v1
+  copy: *base
+  call:
+    uses: acme/workflows/.github/workflows/deploy.yml@v2
+  templated:
+    runs-on: ubuntu-latest
+    steps:
+  bare:
+    runs-on: ubuntu-latest
+  scalar: oops
+  empty:
+";
+
+#[test]
+fn every_job_shape_is_extracted_or_warned_about() {
+    let repo = Repo::new("shapes", &[("ci.yml", SHAPES)]);
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+
+    let actions: Vec<&str> = report["actions"].as_array().unwrap().iter().map(|a| a["action"].as_str().unwrap()).collect();
+    assert_eq!(actions, ["acme/aliased@v1", "acme/workflows/.github/workflows/deploy.yml@v2"]);
+    let jobs_of = |action: &str| -> Vec<String> {
+        let findings = report["findings"].as_array().unwrap().iter();
+        let mut jobs: Vec<String> =
+            findings.filter(|f| f["rule"] == "untrusted-owner" && f["action"] == action).map(|f| f["job"].as_str().unwrap().to_string()).collect();
+        jobs.sort();
+        jobs
+    };
+    assert_eq!(jobs_of("acme/aliased@v1"), ["base", "copy"], "the aliased job is checked as its own job");
+    assert_eq!(jobs_of("acme/workflows/.github/workflows/deploy.yml@v2"), ["call"]);
+
+    let shapes = findings(&output, "workflow-shape");
+    assert_eq!(
+        shapes,
+        [
+            ("templated".to_string(), "Job templated has a null steps list; no actions could be extracted from it".to_string()),
+            ("bare".to_string(), "Job bare has neither steps nor uses; no actions could be extracted from it".to_string()),
+            ("scalar".to_string(), "Job scalar is not a mapping; no actions could be extracted from it".to_string()),
+            ("empty".to_string(), "Job empty is empty; no actions could be extracted from it".to_string()),
+        ]
+    );
+    let levels = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "workflow-shape").map(|f| f["level"].as_str().unwrap());
+    assert!(levels.into_iter().all(|level| level == "warning"));
+}
+
+#[test]
+fn a_workflow_of_odd_shapes_only_warns() {
+    let workflow = "on: push\njobs:\n  templated:\n    runs-on: ubuntu-latest\n    steps:\n  bare:\n    runs-on: ubuntu-latest\n";
+    let repo = Repo::new("shapes-only", &[("ci.yml", workflow)]);
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert_eq!(findings(&output, "workflow-shape").len(), 2);
+}
+
+/// A workflow whose secret `DEPLOY_TOKEN` is declared at `scope` (workflow, job or step), with a
+/// job running `uses`
+fn secret_env_workflow(scope: &str, uses: &str) -> String {
+    let env = |indent: &str| format!("{}env:\n{}  DEPLOY_TOKEN: ${{{{ secrets.DEPLOY_TOKEN }}}}\n{}  REGION: eu-west-1\n", indent, indent, indent);
+    let workflow_env = if scope == "workflow" { env("") } else { String::new() };
+    let job_env = if scope == "job" { env("    ") } else { String::new() };
+    let step_env = if scope == "step" { env("        ") } else { String::new() };
+    format!(
+        "on: push\n{}jobs:\n  deploy:\n    runs-on: ubuntu-latest\n{}    steps:\n      - uses: {}\n{}",
+        workflow_env, job_env, uses, step_env
+    )
+}
+
+/// The `(job, line, message)` of each secret-env-exposure finding
+fn secret_env(output: &std::process::Output) -> Vec<(String, u64, String)> {
+    let report = json(output);
+    let matching = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "secret-env-exposure");
+    let text = |f: &serde_json::Value, key: &str| f[key].as_str().unwrap_or_default().to_string();
+    matching.map(|f| (text(f, "job"), f["line"].as_u64().unwrap(), text(f, "message"))).collect()
+}
+
+#[test]
+fn secret_env_at_workflow_or_job_scope_is_flagged_at_its_line() {
+    for (scope, job, line, declared_for) in [("workflow", "", 3, "the workflow"), ("job", "deploy", 6, "job deploy")] {
+        let repo = Repo::new("secret-env", &[("ci.yml", &secret_env_workflow(scope, "acme/deploy@v1"))]);
+        let found = secret_env(&repo.scan(&["--format", "json"]));
+        assert_eq!(found.len(), 1, "{}: only the secret-valued entry: {:?}", scope, found);
+        let (found_job, found_line, message) = &found[0];
+        assert_eq!((found_job.as_str(), *found_line), (job, line), "{}: {}", scope, message);
+        assert_eq!(
+            message,
+            &format!(
+                "Secret-valued env DEPLOY_TOKEN is declared for {}, exposing it to untrusted or unpinned actions; scope it to the step that needs it",
+                declared_for
+            )
+        );
+    }
+}
+
+#[test]
+fn secret_env_at_step_scope_is_never_flagged() {
+    let repo = Repo::new("secret-env-step", &[("ci.yml", &secret_env_workflow("step", "acme/deploy@v1"))]);
+    assert_eq!(secret_env(&repo.scan(&["--format", "json"])), []);
+}
+
+#[test]
+fn secret_env_is_fine_when_every_action_is_trusted_and_pinned() {
+    let pinned = format!("actions/checkout@{}", SHA);
+    for scope in ["workflow", "job"] {
+        let repo = Repo::new("secret-env-pinned", &[("ci.yml", &secret_env_workflow(scope, &pinned))]);
+        assert_eq!(secret_env(&repo.scan(&["--format", "json"])), [], "{}", scope);
+    }
+    // A trusted action on a tag is unpinned, so it counts
+    let repo = Repo::new("secret-env-tag", &[("ci.yml", &secret_env_workflow("job", "actions/checkout@v4"))]);
+    assert_eq!(secret_env(&repo.scan(&["--format", "json"])).len(), 1);
+}
+
+#[test]
+fn insecure_downloads_are_only_checked_under_scan_run_steps() {
+    let workflow = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - run: |\n          curl -fsSL https://example.com/ok\n          curl -k https://example.com/install.sh | sh\n";
+    let repo = Repo::new("insecure-download", &[("ci.yml", workflow)]);
+    assert!(findings(&repo.scan(&["--format", "json"]), "insecure-download").is_empty());
+
+    let output = repo.scan(&["--format", "json", "--scan-run-steps"]);
+    let found = findings(&output, "insecure-download");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, "build");
+    assert!(found[0].1.contains("(curl-insecure): `curl -k`"), "{}", found[0].1);
+    let report = json(&output);
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "insecure-download").unwrap();
+    assert_eq!((finding["line"].as_u64(), finding["level"].as_str()), (Some(8), Some("warning")));
+}
+
+#[test]
+fn github_scripts_are_only_checked_under_scan_github_script() {
+    let workflow = "on: pull_request_target\njobs:\n  triage:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/github-script@v7\n        with:\n          script: |\n            core.info(context.payload.pull_request.title)\n            core.info('${{ github.event.pull_request.title }}')\n";
+    let repo = Repo::new("github-script", &[("ci.yml", workflow)]);
+    assert!(findings(&repo.scan(&["--format", "json"]), "github-script").is_empty());
+
+    let output = repo.scan(&["--format", "json", "--scan-github-script"]);
+    let found = findings(&output, "github-script");
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].0, "triage");
+    assert!(found[0].1.ends_with("with full token access: `core.info('${{ github.event.pull_request.title }}')`"), "{}", found[0].1);
+    let report = json(&output);
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "github-script").unwrap();
+    assert_eq!((finding["line"].as_u64(), finding["level"].as_str()), (Some(10), Some("error")));
+}
+
+/// A `pull_request_target` job `build` running the given checkout steps, each the YAML of a
+/// list item after `- `
+fn checkout_workflow(steps: &[&str]) -> String {
+    let steps: String = steps.iter().map(|s| format!("      - {}\n", s)).collect();
+    format!("on: pull_request_target\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n{}      - run: make\n", steps)
+}
+
+fn overlays(steps: &[&str]) -> Vec<(String, Option<u64>, String)> {
+    let repo = Repo::new("checkout-overlay", &[("ci.yml", &checkout_workflow(steps))]);
+    let report = json(&repo.scan(&["--format", "json"]));
+    let matching = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "checkout-overlay");
+    matching.map(|f| (f["level"].as_str().unwrap().to_string(), f["line"].as_u64(), f["message"].as_str().unwrap().to_string())).collect()
+}
+
+#[test]
+fn a_pull_request_checkout_over_the_base_checkout_is_flagged_at_both_steps() {
+    let pinned = format!("actions/checkout@{}", SHA);
+    let head = format!("uses: {}\n        with:\n          ref: ${{{{ github.event.pull_request.head.sha }}}}", pinned);
+    let found = overlays(&["uses: actions/checkout@v4", &head]);
+    assert_eq!(
+        found,
+        [(
+            "error".to_string(),
+            Some(7),
+            "Job build, step 2 (line 7) checks out untrusted ref ${{ github.event.pull_request.head.sha }} into the workspace on top of step 1 (line 6); \
+             later steps run the overlaid files, so give it its own `path`"
+                .to_string()
+        )]
+    );
+
+    // Any pin and any spelling of the action is the same checkout
+    let found = overlays(&["uses: Actions/Checkout@v3", "uses: actions/checkout@main\n        with:\n          ref: refs/pull/${{ github.event.number }}/merge\n          path: ."]);
+    assert_eq!(found.iter().map(|f| f.0.as_str()).collect::<Vec<_>>(), ["error"], "{:?}", found);
+}
+
+#[test]
+fn another_repository_in_the_same_directory_is_a_warning() {
+    let found = overlays(&["uses: actions/checkout@v4", "uses: actions/checkout@v4\n        with:\n          repository: acme/tools"]);
+    let [(level, line, message)] = found.as_slice() else { panic!("{:?}", found) };
+    assert_eq!((level.as_str(), *line), ("warning", Some(7)));
+    assert!(message.contains("checks out repository acme/tools into the workspace on top of step 1 (line 6)"), "{}", message);
+
+    let found = overlays(&[
+        "uses: actions/checkout@v4\n        with:\n          path: src",
+        "uses: actions/checkout@v4\n        with:\n          repository: acme/tools\n          path: ./src/",
+    ]);
+    assert!(found[0].2.contains("into src on top of step 1"), "{:?}", found);
+}
+
+#[test]
+fn checkouts_into_distinct_paths_are_not_flagged() {
+    for steps in [
+        &["uses: actions/checkout@v4", "uses: actions/checkout@v4\n        with:\n          repository: acme/tools\n          path: tools"][..],
+        &[
+            "uses: actions/checkout@v4\n        with:\n          path: base",
+            "uses: actions/checkout@v4\n        with:\n          ref: ${{ github.event.pull_request.head.sha }}\n          path: head",
+        ][..],
+        // The same repository and a trusted ref again, or the workflow's own repository spelled out
+        &["uses: actions/checkout@v4", "uses: actions/checkout@v4\n        with:\n          ref: main"][..],
+        &["uses: actions/checkout@v4", "uses: actions/checkout@v4\n        with:\n          repository: ${{ github.repository }}"][..],
+        // Only a later untrusted checkout poisons the earlier one
+        &["uses: actions/checkout@v4\n        with:\n          ref: ${{ github.head_ref }}", "uses: actions/checkout@v4\n        with:\n          ref: ${{ github.head_ref }}"][..],
+    ] {
+        assert_eq!(overlays(steps), [], "{:?}", steps);
+    }
+}
+
+#[test]
+fn checkouts_in_different_jobs_do_not_overlay() {
+    let workflow = "on: pull_request_target\njobs:\n  base:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n  head:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n        with:\n          ref: ${{ github.event.pull_request.head.sha }}\n";
+    let repo = Repo::new("checkout-jobs", &[("ci.yml", workflow)]);
+    assert!(findings(&repo.scan(&["--format", "json"]), "checkout-overlay").is_empty());
+}
+
+#[test]
+fn actions_in_a_subdirectory_are_checked_as_their_repository() {
+    let ci = workflow_using(&["github/codeql-action/init@v3", "acme/suite/tools/lint@v1"]);
+    let repo = Repo::new("subpath", &[("ci.yml", &ci)]).config("trusted_owners: [actions, github]\n");
+    let output = repo.scan(&["--format", "json"]);
+    let report = json(&output);
+    assert_eq!(rules(&report), ["unused-config-entry", "untrusted-owner", "untrusted-owner-no-sha"], "{}", report);
+    let untrusted = findings(&output, "untrusted-owner-no-sha");
+    assert_eq!(untrusted[0].1, "Non-trusted action acme/suite/tools/lint@v1 should use commit SHA instead of tag/branch");
+    assert!(stderr(&output).contains("0 skipped or malformed"), "{}", stderr(&output));
+
+    // Trust keys on owner/repo, whatever the path
+    let repo = Repo::new("subpath-trusted", &[("ci.yml", &ci)]).config("trusted_owners: [github]\ntrusted_repos: [acme/suite]\n");
+    assert_eq!(rules(&json(&repo.scan(&["--format", "json"]))), Vec::<&str>::new());
+}
+
+#[test]
+fn local_actions_are_checked_on_disk_and_docker_images_by_digest() {
+    let digest = format!("docker://alpine@sha256:{}", "0".repeat(64));
+    let ci = workflow_using(&["actions/checkout@v4", "./.github/actions/setup", "./.github/actions/gone", "docker://alpine:3.19", &digest]);
+    let repo = Repo::new("local-and-docker", &[("ci.yml", &ci), ("../actions/setup/action.yml", "runs:\n  using: composite\n  steps: []\n")]);
+    let output = repo.scan(&["--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let missing = findings(&output, "missing-local-action");
+    assert_eq!(missing.len(), 1, "{:?}", missing);
+    assert_eq!(missing[0].1, "Local action ./.github/actions/gone does not exist in the repository");
+    assert!(findings(&output, "docker-tag-pinned").is_empty(), "Docker Hub tags pass outside --strict");
+
+    let output = repo.scan(&["--format", "json", "--strict"]);
+    let pinned = findings(&output, "docker-tag-pinned");
+    assert_eq!(pinned.len(), 1, "only the tag, not the digest: {:?}", pinned);
+    assert!(pinned[0].1.starts_with("Docker image docker://alpine:3.19 from docker.io is referenced by tag 3.19"), "{}", pinned[0].1);
+}
+
+#[test]
+fn rule_id_compat_reports_zizmor_and_pinact_ids_and_warns_about_unmapped_rules() {
+    let ci = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/deploy@v1\n";
+    let repo = Repo::new("rule-id-compat", &[("ci.yml", ci)]).config("trusted_owners: [actions, github]\n");
+    let native = repo.scan(&["--format", "json"]);
+    assert_eq!(rules(&json(&native)), ["unused-config-entry", "untrusted-owner", "untrusted-owner-no-sha"]);
+    assert!(!stderr(&native).contains("no zizmor equivalent"));
+
+    let output = repo.scan(&["--format", "json", "--rule-id-compat", "zizmor"]);
+    assert_eq!(code(&output), code(&native));
+    assert_eq!(rules(&json(&output)), ["unused-config-entry", "forbidden-uses", "unpinned-uses"]);
+    assert!(stderr(&output).contains("Warning: no zizmor equivalent for unused-config-entry; reported under their own ids"), "{}", stderr(&output));
+
+    let output = repo.scan(&["--format", "json", "--rule-id-compat", "pinact"]);
+    assert_eq!(code(&output), code(&native));
+    assert_eq!(rules(&json(&output)), ["unused-config-entry", "untrusted-owner", "unpinned"]);
+    let warning = "Warning: no pinact equivalent for untrusted-owner, unused-config-entry; reported under their own ids";
+    assert!(stderr(&output).contains(warning), "{}", stderr(&output));
+
+    let output = repo.scan(&["--rule-id-compat", "semgrep"]);
+    assert_eq!(code(&output), 5);
+    assert!(stderr(&output).contains("unknown tool semgrep (supported: zizmor, pinact)"), "{}", stderr(&output));
+}
+
+#[test]
+fn images_on_untrusted_registries_need_a_digest_outside_strict() {
+    let ci = workflow_using(&["actions/checkout@v4", "docker://quay.io/foo/bar:latest", "docker://ghcr.io/actions/tool:v1", "docker://ghcr.io/acme/tool:v1"]);
+    let repo = Repo::new("trusted-registries", &[("ci.yml", &ci)]).config("trusted_owners: [actions]\ntrusted_registries: [docker.io, ghcr.io/acme]\ntrust_ghcr_owners: true\n");
+    let pinned = findings(&repo.scan(&["--format", "json"]), "docker-tag-pinned");
+    assert_eq!(pinned.len(), 1, "{:?}", pinned);
+    assert_eq!(
+        pinned[0].1,
+        "Docker image docker://quay.io/foo/bar:latest from quay.io is referenced by tag latest, not a digest; \
+         pin it as docker://quay.io/foo/bar@sha256:... (quay.io is not a trusted registry)"
+    );
+}
+
+#[test]
+fn an_action_repeated_across_jobs_is_reported_at_every_occurrence() {
+    let workflow = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: acme/tool@v1\n      - run: make\n      - name: Again\n        uses: acme/tool@v1\n  test:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: acme/tool@v1\n";
+    let repo = Repo::new("rules-occurrences", &[("ci.yml", workflow)]);
+    let report = json(&repo.scan(&["--format", "json"]));
+    let sites: Vec<(&str, &str, u64)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|f| f["rule"] == "untrusted-owner")
+        .map(|f| (f["job"].as_str().unwrap(), f["step"].as_str().unwrap(), f["line"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(sites, [("build", "step 1", 6), ("build", "\"Again\"", 9), ("test", "step 1", 13)], "{}", report);
+}
+
+#[test]
+fn refs_with_slashes_or_ats_give_findings_for_their_kind() {
+    let uses = workflow_using(&["acme/tool@releases/v1", "acme/internal@v1@stable", "acme/lint/sub@feature/a@b"]);
+    let repo = Repo::new("rules-ref-shapes", &[("ci.yml", &uses)]).config("trusted_owners: [acme]\nunstable_ref_level: warning\n");
+    let output = repo.scan(&["--format", "json"]);
+    let report = json(&output);
+    let found: Vec<(&str, &str)> = report["findings"].as_array().unwrap().iter().map(|f| (f["rule"].as_str().unwrap(), f["action"].as_str().unwrap())).collect();
+    assert_eq!(found, [("unstable-reference", "acme/tool@releases/v1"), ("unstable-reference", "acme/lint/sub@feature/a@b")], "{}", report);
+    assert!(stderr(&output).contains("0 skipped or malformed"), "{}", stderr(&output));
+}
+
+#[test]
+fn only_abbreviated_shas_are_flagged_among_hex_pins() {
+    let sha256 = "F".repeat(64);
+    let uses = [
+        "acme/short@a5ac7e5".to_string(),
+        format!("acme/upper@{}", SHA.to_uppercase()),
+        format!("acme/sha256@{}", sha256),
+        format!("acme/lower@{}", SHA),
+    ];
+    let uses: Vec<&str> = uses.iter().map(String::as_str).collect();
+    let repo = Repo::new("rules-hex-pins", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [acme]\n");
+    let report = json(&repo.scan(&["--format", "json", "--strict"]));
+    let pins: Vec<(&str, &str)> = report["findings"].as_array().unwrap().iter().map(|f| (f["rule"].as_str().unwrap(), f["action"].as_str().unwrap())).collect();
+    assert_eq!(pins, [("abbreviated-sha", "acme/short@a5ac7e5")], "{}", report);
+    let kinds: Vec<(&str, &str)> = report["actions"].as_array().unwrap().iter().map(|a| (a["repo"].as_str().unwrap(), a["ref_kind"].as_str().unwrap())).collect();
+    assert_eq!(kinds, [("lower", "full-sha"), ("sha256", "full-sha"), ("short", "abbreviated-sha"), ("upper", "full-sha")]);
+}
+
+/// A workflow on `issue_comment` whose `deploy` job has `guard` (YAML lines at job indentation)
+/// and deploys with a secret
+fn comment_workflow(guard: &str) -> String {
+    format!(
+        "on: issue_comment\njobs:\n  deploy:\n    runs-on: ubuntu-latest\n{}    steps:\n      - run: ./deploy.sh\n        env:\n          TOKEN: ${{{{ secrets.DEPLOY_TOKEN }}}}\n",
+        guard
+    )
+}
+
+#[test]
+fn an_unguarded_comment_trigger_using_secrets_is_flagged() {
+    for (case, guard, line) in [
+        ("no if:", "", 3),
+        ("an if: about the comment text only", "    if: contains(github.event.comment.body, '/deploy')\n", 5),
+    ] {
+        let repo = Repo::new("comment-unguarded", &[("ci.yml", &comment_workflow(guard))]);
+        let output = repo.scan(&["--format", "json"]);
+        let found = findings(&output, "unguarded-comment-trigger");
+        assert_eq!(found.len(), 1, "{}: {:?}", case, found);
+        let (job, message) = &found[0];
+        assert_eq!(job, "deploy", "{}", case);
+        assert!(message.starts_with("Job deploy runs on issue_comment and uses secrets.DEPLOY_TOKEN, but nothing checks who commented"), "{}: {}", case, message);
+        let report = json(&output);
+        let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "unguarded-comment-trigger").unwrap();
+        assert_eq!(finding["line"], line, "{}", case);
+    }
+}
+
+#[test]
+fn a_comment_trigger_guarded_by_association_or_permission_is_not_flagged() {
+    let association = "    if: contains(fromJSON('[\"OWNER\", \"MEMBER\"]'), github.event.comment.author_association)\n";
+    let permission_step = "    steps:\n      - uses: actions-cool/check-user-permission@v2\n        with:\n          require: write\n";
+    let permission = comment_workflow("").replace("    steps:\n", permission_step);
+    let needs_guard = format!(
+        "on: issue_comment\njobs:\n  check:\n    runs-on: ubuntu-latest\n{}    steps:\n      - run: true\n{}",
+        association,
+        comment_workflow("    needs: check\n").split_once("jobs:\n").unwrap().1
+    );
+    for (case, workflow) in [
+        ("author_association in if:", comment_workflow(association)),
+        ("the commenter's login", comment_workflow("    if: github.event.comment.user.login == 'octocat'\n")),
+        ("a permission-check action", permission),
+        ("needing a guarded job", needs_guard),
+    ] {
+        let repo = Repo::new("comment-guarded", &[("ci.yml", &workflow)]);
+        let found = findings(&repo.scan(&["--format", "json"]), "unguarded-comment-trigger");
+        assert_eq!(found, [], "{}", case);
+    }
+}
+
+#[test]
+fn too_many_untrusted_owners_is_one_scan_level_finding_listing_the_biggest_first() {
+    let uses = ["zeta/tool@v1", "acme/build@v1", "acme/deploy@v1", "beta/tool@v1", "actions/checkout@v4"];
+    let repo = || Repo::new("owner-budget", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions]\nmax_untrusted_owners: 2\n");
+    let report = json(&repo().scan(&["--format", "json"]));
+    let budget: Vec<&serde_json::Value> = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "too-many-untrusted-owners").collect();
+    assert_eq!(budget.len(), 1, "{}", report);
+    assert_eq!(
+        budget[0]["message"],
+        "3 distinct non-trusted owners exceed max_untrusted_owners of 2: acme (2 actions), beta (1 action), zeta (1 action)"
+    );
+    assert_eq!(budget[0]["level"], "error");
+    assert!(budget[0].get("file").is_none() && budget[0].get("line").is_none(), "{}", budget[0]);
+
+    let sarif = json(&repo().scan(&["--format", "sarif"]));
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    let result = results.iter().find(|r| r["ruleId"] == "too-many-untrusted-owners").unwrap();
+    assert!(result.get("locations").is_none(), "{}", result);
+
+    let within = Repo::new("owner-budget-ok", &[("ci.yml", &workflow_using(&uses))]).config("trusted_owners: [actions]\nmax_untrusted_owners: 3\n");
+    assert!(findings(&within.scan(&["--format", "json"]), "too-many-untrusted-owners").is_empty());
+}
@@ -0,0 +1,48 @@
+//! `--check-runtime`: the `runs.using` of each action's metadata, from tests/fixtures/http/runtime.json,
+//! where acme/legacy runs on the deprecated node16 and acme/modern on node20
+
+mod common;
+
+use common::*;
+
+fn scan(name: &str, uses: &[&str], args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config("trusted_owners: [acme]\n");
+    let mut all = vec!["--check-runtime", "--source", "github", "--format", "json"];
+    all.extend(args);
+    all.push(".github/workflows");
+    run(repo.replaying("runtime.json", &all), None)
+}
+
+fn runtime_findings(output: &std::process::Output) -> Vec<serde_json::Value> {
+    let report = json(output);
+    report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "deprecated-runtime").cloned().collect()
+}
+
+#[test]
+fn a_deprecated_node_runtime_warns_and_fails_under_strict() {
+    let output = scan("runtime-deprecated", &["acme/legacy@v1"], &[]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let findings = runtime_findings(&output);
+    assert_eq!(findings.len(), 1, "{}", stderr(&output));
+    assert_eq!(findings[0]["action"], "acme/legacy@v1");
+    assert_eq!(findings[0]["level"], "warning");
+    assert_eq!(
+        findings[0]["message"],
+        "Action acme/legacy@v1 runs on node16, a Node.js runtime GitHub has deprecated; runners warn about it or refuse it; update it to a release on a supported runtime"
+    );
+
+    let output = scan("runtime-deprecated-strict", &["acme/legacy@v1"], &["--strict"]);
+    assert_ne!(code(&output), 0, "{}", stderr(&output));
+    let findings = runtime_findings(&output);
+    assert_eq!(findings.len(), 1, "{}", stderr(&output));
+    assert_eq!(findings[0]["level"], "error");
+    assert!(findings[0]["message"].as_str().unwrap().ends_with("(failing due to --strict)"), "{}", findings[0]);
+}
+
+#[test]
+fn a_current_node_runtime_passes() {
+    let output = scan("runtime-current", &["acme/modern@v1"], &[]);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    assert!(runtime_findings(&output).is_empty(), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("Failed"), "{}", stderr(&output));
+}
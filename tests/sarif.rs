@@ -0,0 +1,88 @@
+//! `--format sarif`: one workflow with a tag-pinned critical dependency and an untrusted action
+//! that tests/fixtures/http/vulnerable.json reports an advisory for, against a golden file
+
+mod common;
+
+use common::*;
+
+const GOLDEN: &str = include_str!("golden/sarif.json");
+const CI: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/tool@v1.1.0\n";
+
+/// The workflow scanned by a relative path with a pinned clock, so the log does not depend on
+/// where or when it ran
+fn scan(name: &str, args: &[&str]) -> (Repo, std::process::Output) {
+    let repo = Repo::new(name, &[("ci.yml", CI)]).config("trusted_owners: [actions]\ncritical_dependencies: [actions/checkout@v4]\n");
+    let mut all = vec!["--format", "sarif", "--timestamp", "2024-01-02T03:04:05Z"];
+    all.extend(args);
+    all.push(".github/workflows");
+    let output = run(repo.replaying("vulnerable.json", &all), None);
+    assert_eq!(code(&output), 3, "{}", stderr(&output));
+    (repo, output)
+}
+
+#[test]
+fn the_log_matches_the_golden_file() {
+    let (_, output) = scan("sarif-golden", &[]);
+    let log = stdout(&output);
+    assert!(log == GOLDEN, "SARIF differs from the golden file\nexpected:\n{}\nactual:\n{}", GOLDEN, log);
+    assert!(stderr(&output).contains("Security scan failed!"), "the text output goes to stderr");
+}
+
+#[test]
+fn each_result_has_a_declared_rule_and_points_at_its_uses_line() {
+    let log: serde_json::Value = serde_json::from_str(GOLDEN).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    let declared: Vec<&str> = run["tool"]["driver"]["rules"].as_array().unwrap().iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(declared, ["known-vulnerability", "tag-pinned-critical", "untrusted-owner", "untrusted-owner-no-sha"]);
+
+    for result in run["results"].as_array().unwrap() {
+        let rule = result["ruleId"].as_str().unwrap();
+        assert!(declared.contains(&rule), "{}", rule);
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], ".github/workflows/ci.yml");
+        let uses = if rule == "tag-pinned-critical" { "actions/checkout@v4" } else { "acme/tool@v1.1.0" };
+        let line = CI.lines().position(|l| l.ends_with(uses)).unwrap() + 1;
+        assert_eq!(location["region"]["startLine"].as_u64(), Some(line as u64), "{}", rule);
+    }
+}
+
+#[test]
+fn the_advisory_severity_sets_the_rule_security_severity() {
+    let log: serde_json::Value = serde_json::from_str(GOLDEN).unwrap();
+    let run = &log["runs"][0];
+    let vulnerability = run["results"].as_array().unwrap().iter().find(|r| r["ruleId"] == "known-vulnerability").unwrap();
+    assert_eq!(vulnerability["properties"]["severity"], "high");
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    assert_eq!(rules[0]["properties"]["security-severity"], "8.0");
+    // Policy findings have no advisory, so neither they nor their rules claim a severity
+    let policy: Vec<&serde_json::Value> = run["results"].as_array().unwrap().iter().filter(|r| r["ruleId"] != "known-vulnerability").collect();
+    assert!(policy.iter().all(|r| r.get("properties").is_none()), "{:?}", policy);
+    assert!(rules[1..].iter().all(|r| r["properties"].get("security-severity").is_none()), "{:?}", rules);
+}
+
+#[test]
+fn the_sarif_artifact_is_the_same_log() {
+    let (repo, output) = scan("sarif-artifact", &["--output-dir", "out"]);
+    let artifact = std::fs::read_to_string(repo.root.join("out/report.sarif")).unwrap();
+    assert_eq!(artifact, stdout(&output));
+}
+
+#[test]
+fn the_run_gives_each_workflow_file_its_verdict() {
+    let clean = workflow_using(&[&format!("actions/checkout@{}", SHA)]);
+    let repo = Repo::new("sarif-verdicts", &[("ci.yml", CI), ("clean.yml", &clean)]).config("trusted_owners: [actions]\n");
+    let output = run(repo.command(&["--format", "sarif", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let log: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let verdicts = &log["runs"][0]["properties"]["fileVerdicts"];
+    assert_eq!(verdicts.as_object().unwrap().len(), 2, "{}", verdicts);
+    let failed = &verdicts[".github/workflows/ci.yml"];
+    assert_eq!(failed["passed"], false);
+    assert_eq!(failed["worst"], "error");
+    assert_eq!(failed["counts"]["error"], 1, "{}", failed);
+    assert_eq!(
+        verdicts[".github/workflows/clean.yml"],
+        serde_json::json!({ "passed": true, "counts": { "error": 0, "warning": 0, "note": 0 } })
+    );
+}
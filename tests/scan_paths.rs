@@ -0,0 +1,117 @@
+//! Scan paths: any mix of workflow files and directories in one run, with `--recursive` also
+//! walking the directories' subdirectories, `--workflows-dir` in place of the default directory,
+//! symlinked directories followed until they loop, and the default directory when no path is given
+
+mod common;
+
+use common::*;
+
+/// The workflow files the report scanned, in path order
+fn files(report: &serde_json::Value) -> Vec<String> {
+    report["configs"].as_object().unwrap().keys().cloned().collect()
+}
+
+fn nested(name: &str) -> Repo {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&["acme/top@main"]))]);
+    write_files(
+        &repo.root,
+        &[
+            (".github/workflows/nested/deep.yaml", &workflow_using(&["acme/deep@main"])),
+            ("other/release.yml", &workflow_using(&["acme/release@main"])),
+            (".github/workflows/notes.txt", "not a workflow"),
+        ],
+    );
+    repo
+}
+
+#[test]
+fn a_directory_is_scanned_without_its_subdirectories_unless_recursive() {
+    let repo = nested("paths-recursive");
+    let report = json(&run(repo.command(&["--format", "json", ".github/workflows"]), None));
+    assert_eq!(files(&report), [".github/workflows/ci.yml"], "{}", report);
+    assert_eq!(report["summary"]["workflows_scanned"], 1);
+
+    let report = json(&run(repo.command(&["--recursive", "--format", "json", ".github/workflows"]), None));
+    assert_eq!(files(&report), [".github/workflows/ci.yml", ".github/workflows/nested/deep.yaml"], "{}", report);
+    assert_eq!(report["summary"]["workflows_scanned"], 2);
+}
+
+#[test]
+fn files_and_directories_are_scanned_together_in_one_run() {
+    let repo = nested("paths-several");
+    let output = run(repo.command(&["--format", "json", ".github/workflows", "other/release.yml", ".github/workflows/nested/deep.yaml"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["summary"]["workflows_scanned"], 3, "{}", report);
+    assert_eq!(files(&report), [".github/workflows/ci.yml", ".github/workflows/nested/deep.yaml", "other/release.yml"], "{}", report);
+    let failing: Vec<&String> = report["workflows"].as_object().unwrap().keys().collect();
+    assert_eq!(failing.len(), 3, "each workflow has its own findings: {}", report);
+}
+
+#[test]
+fn a_missing_scan_path_fails_naming_it() {
+    let repo = nested("paths-missing");
+    let output = run(repo.command(&[".github/workflows", "no/such.yml"]), None);
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("no/such.yml"), "{}", stderr(&output));
+}
+
+#[test]
+fn workflows_dirs_replace_the_default_directory_and_paths_replace_them() {
+    let repo = nested("paths-workflows-dir");
+    write_files(&repo.root, &[("templates/deploy.yml", &workflow_using(&["acme/deploy@main"]))]);
+    let output = run(repo.command(&["--format", "json", "--workflows-dir", "other", "--workflows-dir", "templates"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(files(&json(&output)), ["other/release.yml", "templates/deploy.yml"]);
+
+    let output = run(repo.command(&["--format", "json", "--workflows-dir", "other", ".github/workflows"]), None);
+    assert_eq!(files(&json(&output)), [".github/workflows/ci.yml"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn a_symlink_cycle_is_warned_about_and_the_scan_finishes() {
+    let repo = nested("paths-symlink-loop");
+    write_files(&repo.root, &[("templates/deploy.yml", &workflow_using(&["acme/deploy@main"]))]);
+    let workflows = repo.workflows();
+    std::os::unix::fs::symlink("../../templates", workflows.join("shared")).unwrap();
+    std::os::unix::fs::symlink(".", workflows.join("loop")).unwrap();
+
+    let output = run(repo.command(&["--format", "json", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    // The linked-in workflow is reported where it really is, and each file once
+    assert_eq!(files(&json(&output)), [".github/workflows/ci.yml", "templates/deploy.yml"]);
+    let err = stderr(&output);
+    assert!(err.contains("Warning: .github/workflows/loop links back to"), "{}", err);
+    assert!(err.contains("; not following the symlink cycle"), "{}", err);
+}
+
+#[test]
+fn without_paths_the_workspace_then_the_current_directory_is_scanned() {
+    let repo = nested("paths-default");
+    let output = run(repo.command(&["--ascii"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert!(stdout(&output).contains("Scanned: ./.github/workflows (default from the current directory)"), "{}", stdout(&output));
+
+    let workspace = nested("paths-default-workspace");
+    write_files(&workspace.root, &[(".github/workflows/deploy.yml", &workflow_using(&["acme/deploy@main"]))]);
+    let mut command = repo.command(&["--format", "json"]);
+    command.env("GITHUB_WORKSPACE", &*workspace.root);
+    let output = run(command, None);
+    let scanned = files(&json(&output));
+    assert_eq!(scanned.len(), 2, "{:?}", scanned);
+    assert!(scanned.iter().all(|f| f.starts_with(workspace.root.to_str().unwrap())), "{:?}", scanned);
+    assert!(scanned.iter().any(|f| f.ends_with("deploy.yml")), "{:?}", scanned);
+}
+
+#[test]
+fn without_paths_or_a_default_directory_the_error_lists_what_was_tried() {
+    let repo = nested("paths-no-default");
+    std::fs::remove_dir_all(repo.workflows()).unwrap();
+    let output = run(repo.command(&[]), None);
+    assert_eq!(code(&output), 5, "{}", stderr(&output));
+    let err = stderr(&output);
+    assert!(err.contains("no workflow path given and no default workflow directory found; tried:"), "{}", err);
+    assert!(err.contains("$GITHUB_WORKSPACE/.github/workflows (GITHUB_WORKSPACE is not set)"), "{}", err);
+    assert!(err.contains("./.github/workflows (not a directory)"), "{}", err);
+}
@@ -0,0 +1,541 @@
+//! The library's `Scanner`, driven without the binary: offline scans of workflows and actions, a
+//! stdin workflow, and advisories from a source of the test's own
+
+mod common;
+
+use common::*;
+use gh_action_security_scanner::baseline::Baseline;
+use gh_action_security_scanner::config::{Config, ConfigSet};
+use gh_action_security_scanner::decision::ExitPolicy;
+use gh_action_security_scanner::diff::Base;
+use gh_action_security_scanner::http::{HttpClient, HttpRequest, HttpResponse, Offline, ResponseFuture};
+use base64::Engine;
+use gh_action_security_scanner::report::{Finding, Level, Rule};
+use gh_action_security_scanner::{ActionRef, AdvisoryFuture, AdvisorySource, AdvisorySources, GitHubAdvisory, ScanError, ScanEvents, ScanOptions, Scanner};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+fn trusting(owners: &[&str]) -> Config {
+    Config { trusted_owners: Some(owners.iter().map(|o| o.to_string()).collect()), ..Default::default() }
+}
+
+fn offline() -> ScanOptions {
+    ScanOptions { offline: true, ..Default::default() }
+}
+
+/// The rule ids of the findings, sorted
+fn rule_ids(findings: &[Finding]) -> Vec<&'static str> {
+    let mut ids: Vec<&'static str> = findings.iter().map(|f| f.rule.id()).collect();
+    ids.sort();
+    ids
+}
+
+/// Advisories the test hands out, recording which lookups asked for them
+struct Fixed {
+    advisories: Vec<GitHubAdvisory>,
+    asked: Arc<Mutex<Vec<(String, bool)>>>,
+}
+
+impl AdvisorySource for Fixed {
+    fn advisories<'a>(&'a self, action_ref: &'a ActionRef, open_only: bool) -> AdvisoryFuture<'a> {
+        self.asked.lock().unwrap().push((action_ref.full_name(), open_only));
+        let advisories = self.advisories.clone();
+        Box::pin(async move { Ok(advisories) })
+    }
+}
+
+fn advisory(id: &str) -> GitHubAdvisory {
+    serde_json::from_value(serde_json::json!({
+        "ghsa_id": id,
+        "summary": "Command injection in the action",
+        "severity": "high",
+        "sources": ["github"],
+    }))
+    .unwrap()
+}
+
+/// The findings told to the events, in order
+#[derive(Default)]
+struct Recorded {
+    findings: Mutex<Vec<String>>,
+    phases: Mutex<Vec<String>>,
+    warnings: Mutex<Vec<String>>,
+}
+
+struct Recorder(Arc<Recorded>);
+
+impl ScanEvents for Recorder {
+    fn finding(&self, finding: &Finding) {
+        self.0.findings.lock().unwrap().push(finding.rule.id().to_string());
+    }
+
+    fn phase(&self, phase: &str) {
+        self.0.phases.lock().unwrap().push(phase.to_string());
+    }
+
+    fn warning(&self, message: &str) {
+        self.0.warnings.lock().unwrap().push(message.to_string());
+    }
+}
+
+#[tokio::test]
+async fn scans_a_workflow_under_the_config_it_is_given() {
+    let repo = Repo::new("lib-workflow", &[("ci.yml", &workflow_using(&["actions/checkout@main", "acme/deploy@v1"]))]);
+    let scanner = Scanner::new(trusting(&["actions"]), offline());
+    let report = scanner.scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+    assert_eq!(rule_ids(&report.findings), ["untrusted-owner", "untrusted-owner-no-sha"]);
+
+    let report = Scanner::new(trusting(&["actions", "acme"]), offline()).scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+    assert_eq!(rule_ids(&report.findings), Vec::<&str>::new());
+    assert_eq!(report.actions_scanned, 2);
+    assert!(!report.skipped_checks.is_empty(), "offline scans list what they skipped");
+}
+
+#[tokio::test]
+async fn scan_decides_the_exit_and_tells_the_events() {
+    let repo = Repo::new("lib-decide", &[("ci.yml", &workflow_using(&["acme/deploy@v1"]))]);
+    let recorded = Arc::new(Recorded::default());
+    let scanner = Scanner::new(trusting(&["actions"]), offline()).with_events(Box::new(Recorder(recorded.clone())));
+    let outcome = scanner.scan(&[repo.workflows()]).await.unwrap();
+    assert_eq!(outcome.decision.exit.code, 2);
+    assert_eq!(outcome.inventory.len(), 1);
+    // Policy findings of each action reach `finding` through `policy_finding`
+    assert_eq!(*recorded.findings.lock().unwrap(), ["untrusted-owner", "untrusted-owner-no-sha"]);
+    assert_eq!(*recorded.phases.lock().unwrap(), ["load", "policy", "network", "decide"]);
+}
+
+#[tokio::test]
+async fn without_default_trust_every_owner_is_untrusted() {
+    let repo = Repo::new("lib-trust", &[("ci.yml", &workflow_using(&[&format!("actions/checkout@{}", SHA)]))]);
+    let workflow = repo.workflow("ci.yml");
+    let trusted = Scanner::new(Config::default(), offline()).scan_workflow(&workflow).await.unwrap();
+    assert!(rule_ids(&trusted.findings).is_empty());
+
+    let configs = ConfigSet::from(Config::default()).without_default_trust();
+    let untrusted = Scanner::new(configs, offline()).scan_workflow(&workflow).await.unwrap();
+    assert_eq!(rule_ids(&untrusted.findings), ["untrusted-owner"]);
+}
+
+#[tokio::test]
+async fn stdin_workflow_is_scanned_under_its_name() {
+    let source = workflow_using(&["acme/deploy@v1"]);
+    let scanner = Scanner::new(trusting(&["actions"]), offline()).with_stdin_workflow("piped.yml", source.clone());
+    let outcome = scanner.scan(&[PathBuf::from("-")]).await.unwrap();
+    let finding = outcome.report.findings.iter().find(|f| f.rule == Rule::UntrustedOwnerNoSha).unwrap();
+    assert_eq!(finding.file.as_deref(), Some("piped.yml"));
+    assert_eq!(outcome.report.workflow_source("piped.yml").unwrap(), source);
+}
+
+#[tokio::test]
+async fn warnings_reach_the_events_once_per_run() {
+    let recorded = Arc::new(Recorded::default());
+    let baseline = Baseline { scanner_version: Some("999.0.0".to_string()), ..Default::default() };
+    let options = ScanOptions { lenient_templates: true, baseline: Some(("old.json".to_string(), baseline)), ..offline() };
+    let scanner = Scanner::new(trusting(&["actions"]), options)
+        .with_stdin_workflow("templated.yml", "jobs: {{ jobs }}\n  - uses: acme/deploy@v1\n".to_string())
+        .with_events(Box::new(Recorder(recorded.clone())));
+    scanner.scan(&[PathBuf::from("-")]).await.unwrap();
+    scanner.scan(&[PathBuf::from("-")]).await.unwrap();
+
+    let warnings = recorded.warnings.lock().unwrap();
+    let newer: Vec<&String> = warnings.iter().filter(|w| w.contains("a newer major version")).collect();
+    assert_eq!(newer.len(), 1, "{:?}", warnings);
+    assert!(newer[0].starts_with("Warning: old.json was written by gh-action-security-scanner 999.0.0"), "{}", newer[0]);
+    let lenient = warnings.iter().filter(|w| w.ends_with("parsing leniently (--lenient-templates)")).count();
+    assert_eq!(lenient, 2, "one per scan of the workflow: {:?}", warnings);
+}
+
+#[tokio::test]
+async fn stdin_path_without_a_stdin_workflow_is_an_error() {
+    let scanner = Scanner::new(trusting(&["actions"]), offline());
+    assert!(scanner.scan(&[PathBuf::from("-")]).await.is_err());
+}
+
+#[tokio::test]
+async fn advisories_come_from_the_source_given() {
+    let asked = Arc::new(Mutex::new(Vec::new()));
+    let source = Fixed { advisories: vec![advisory("GHSA-aaaa-bbbb-cccc")], asked: asked.clone() };
+    let scanner = Scanner::new(trusting(&["acme"]), ScanOptions::default())
+        .with_client(Box::new(Offline))
+        .with_advisory_source(Box::new(source));
+    let action = format!("acme/deploy@{}", SHA);
+    let report = scanner.scan_actions(&[action.parse().unwrap()]).await.unwrap();
+
+    let vulnerabilities: Vec<&Finding> = report.findings.iter().filter(|f| f.rule == Rule::KnownVulnerability).collect();
+    assert_eq!(vulnerabilities.len(), 1, "{:?}", rule_ids(&report.findings));
+    assert_eq!(vulnerabilities[0].action, action);
+    assert!(report.unchecked.is_empty());
+    assert_eq!(*asked.lock().unwrap(), [("acme/deploy".to_string(), true)]);
+}
+
+#[tokio::test]
+async fn actions_without_advisories_pass() {
+    let source = Fixed { advisories: Vec::new(), asked: Arc::default() };
+    let scanner = Scanner::new(trusting(&["acme"]), ScanOptions::default())
+        .with_client(Box::new(Offline))
+        .with_advisory_source(Box::new(source));
+    let report = scanner.scan_actions(&[format!("acme/deploy@{}", SHA).parse().unwrap()]).await.unwrap();
+    assert!(!report.findings.iter().any(|f| f.rule == Rule::KnownVulnerability));
+    assert!(report.unchecked.is_empty());
+}
+
+#[tokio::test]
+async fn unreachable_databases_leave_the_action_unchecked() {
+    let scanner = Scanner::new(trusting(&["acme"]), ScanOptions::default()).with_client(Box::new(Offline));
+    let action = format!("acme/deploy@{}", SHA);
+    let report = scanner.scan_actions(&[action.parse().unwrap()]).await.unwrap();
+    assert_eq!(report.unchecked, [action]);
+}
+
+/// Advisories by repository: `vulnerable` ones have one, `down` ones cannot be looked up
+struct Stubbed {
+    vulnerable: &'static [&'static str],
+    down: &'static [&'static str],
+}
+
+impl AdvisorySource for Stubbed {
+    fn advisories<'a>(&'a self, action_ref: &'a ActionRef, _: bool) -> AdvisoryFuture<'a> {
+        let name = action_ref.full_name();
+        let result = match () {
+            _ if self.down.contains(&name.as_str()) => Err(ScanError::Http { status: 503, url: format!("https://advisories.example/{}", name) }),
+            _ if self.vulnerable.contains(&name.as_str()) => Ok(vec![advisory("GHSA-aaaa-bbbb-cccc")]),
+            _ => Ok(Vec::new()),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+#[tokio::test]
+async fn exit_codes_follow_what_was_found() {
+    let safe = format!("acme/safe@{}", SHA);
+    let vulnerable = format!("acme/vulnerable@{}", SHA);
+    let down = format!("acme/down@{}", SHA);
+    let tagged = "evil/tagged@v1".to_string();
+    // Vulnerabilities win over lookup errors, which win over policy violations
+    let matrix: [(&str, Vec<&String>, i32); 8] = [
+        ("pass", vec![&safe], 0),
+        ("vulnerability", vec![&safe, &vulnerable], 1),
+        ("policy", vec![&safe, &tagged], 2),
+        ("error", vec![&safe, &down], 10),
+        ("vulnerability and policy", vec![&vulnerable, &tagged], 3),
+        ("vulnerability and error", vec![&vulnerable, &down], 1),
+        ("policy and error", vec![&tagged, &down], 10),
+        ("all three", vec![&vulnerable, &tagged, &down], 3),
+    ];
+    for (case, uses, code) in matrix {
+        let uses: Vec<&str> = uses.into_iter().map(String::as_str).collect();
+        let repo = Repo::new("lib-exit", &[("ci.yml", &workflow_using(&uses))]);
+        let source = Stubbed { vulnerable: &["acme/vulnerable"], down: &["acme/down"] };
+        let scanner = Scanner::new(trusting(&["acme"]), ScanOptions::default())
+            .with_client(Box::new(Offline))
+            .with_advisory_source(Box::new(source));
+        let outcome = scanner.scan(&[repo.workflows()]).await.unwrap();
+        let decision = &outcome.decision;
+        assert_eq!(decision.exit.code, code, "{}: {}", case, decision.exit.reason);
+        assert_eq!(decision.errors.len(), usize::from(uses.contains(&down.as_str())), "{}", case);
+    }
+}
+
+#[tokio::test]
+async fn a_failed_lookup_is_an_error_with_the_action_unchecked() {
+    let scanner = Scanner::new(trusting(&["acme"]), ScanOptions::default())
+        .with_client(Box::new(Offline))
+        .with_advisory_source(Box::new(Stubbed { vulnerable: &[], down: &["acme/deploy"] }));
+    let action = format!("acme/deploy@{}", SHA);
+    let report = scanner.scan_actions(&[action.parse().unwrap()]).await.unwrap();
+    assert_eq!(report.unchecked, [action]);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].ends_with("https://advisories.example/acme/deploy: unexpected HTTP status 503"), "{}", report.errors[0]);
+}
+
+/// Answers every request with `status` and `headers`
+struct Refusing(u16, &'static [(&'static str, &'static str)]);
+
+impl HttpClient for Refusing {
+    fn send(&self, _: HttpRequest) -> ResponseFuture<'_> {
+        let headers = self.1.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        let response = HttpResponse { status: self.0, headers, body: "{}".to_string() };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[tokio::test]
+async fn a_failed_lookup_keeps_its_status_url_and_rate_limit() {
+    let action_ref: ActionRef = format!("acme/tool@{}", SHA).parse().unwrap();
+    let url = "https://api.github.com/advisories?ecosystem=actions&affects=acme/tool&per_page=100";
+    for sources in [AdvisorySources::Github, AdvisorySources::All] {
+        let options = ScanOptions { sources, ..Default::default() };
+        let unavailable = Scanner::new(trusting(&["acme"]), options.clone()).with_client(Box::new(Refusing(503, &[])));
+        match unavailable.advisories(&action_ref, true).await {
+            Err(ScanError::Http { status, url: failed }) => assert_eq!((status, failed.as_str()), (503, url), "{:?}", sources),
+            other => panic!("{:?}: {:?}", sources, other.map(|(advisories, _)| advisories.len())),
+        }
+        let limited = Scanner::new(trusting(&["acme"]), options).with_client(Box::new(Refusing(429, &[("retry-after", "30")])));
+        match limited.advisories(&action_ref, true).await {
+            Err(ScanError::RateLimited { url: failed, retry_after }) => assert_eq!((failed.as_str(), retry_after), (url, Some(30)), "{:?}", sources),
+            other => panic!("{:?}: {:?}", sources, other.map(|(advisories, _)| advisories.len())),
+        }
+    }
+}
+
+/// Answers every request with an empty list, counting what it was sent
+#[derive(Default)]
+struct Empty(Arc<AtomicUsize>);
+
+impl HttpClient for Empty {
+    fn send(&self, _: HttpRequest) -> ResponseFuture<'_> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Box::pin(async { Ok(HttpResponse { status: 200, headers: Default::default(), body: "[]".to_string() }) })
+    }
+}
+
+#[tokio::test]
+async fn each_scan_counts_its_own_requests() {
+    let options = || ScanOptions { sources: AdvisorySources::Github, ..Default::default() };
+    let first = Repo::new("lib-requests-first", &[("ci.yml", &workflow_using(&[&format!("acme/a@{}", SHA), &format!("acme/b@{}", SHA)]))]);
+    let second = Repo::new("lib-requests-second", &[("ci.yml", &workflow_using(&[&format!("acme/c@{}", SHA)]))]);
+    let sent = Arc::new(AtomicUsize::new(0));
+    let scanner = Scanner::new(trusting(&["acme"]), options()).with_client(Box::new(Empty(sent.clone())));
+    let requests = |outcome: &gh_action_security_scanner::ScanOutcome| outcome.report.summary.as_ref().unwrap().api_requests;
+
+    let outcome = scanner.scan(&[first.workflows()]).await.unwrap();
+    let after_first = sent.load(Ordering::Relaxed);
+    assert!(after_first >= 2, "one advisory lookup per repository");
+    assert_eq!(requests(&outcome), after_first);
+    // The same scanner again, then a scanner of its own: neither inherits the counts before it
+    let outcome = scanner.scan(&[second.workflows()]).await.unwrap();
+    assert_eq!(requests(&outcome), sent.load(Ordering::Relaxed) - after_first);
+    let fresh = Empty::default();
+    let fresh_sent = fresh.0.clone();
+    let outcome = Scanner::new(trusting(&["acme"]), options()).with_client(Box::new(fresh)).scan(&[second.workflows()]).await.unwrap();
+    assert_eq!(requests(&outcome), fresh_sent.load(Ordering::Relaxed));
+    assert_eq!(outcome.report.summary.unwrap().cache_hits, 0);
+}
+
+#[tokio::test]
+async fn a_missing_diff_base_is_an_options_error() {
+    let repo = Repo::new("lib-diff-missing", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let options = ScanOptions { diff: Some(Base::Path(repo.root.join("no-such-base"))), ..offline() };
+    match Scanner::new(Config::default(), options).scan(&[repo.workflows()]).await {
+        Err(ScanError::Options(message)) => assert!(message.ends_with("no-such-base does not exist"), "{}", message),
+        other => panic!("{:?}", other.map(|outcome| outcome.report.findings.len())),
+    }
+}
+
+/// A GitHub API serving the action.yml of composite actions, each with the `uses:` of its steps;
+/// any other repository has none
+struct Composites(&'static [(&'static str, &'static [&'static str])]);
+
+impl HttpClient for Composites {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        let repository = request.url.split_once("/repos/").and_then(|(_, rest)| rest.split_once("/contents/action.yml"));
+        let composite = repository.and_then(|(name, _)| self.0.iter().find(|(composite, _)| *composite == name));
+        let response = match composite {
+            Some((_, uses)) => {
+                let steps: String = uses.iter().map(|u| format!("    - uses: {}\n", u)).collect();
+                let metadata = format!("runs:\n  using: composite\n  steps:\n{}", steps);
+                let content = base64::engine::general_purpose::STANDARD.encode(metadata);
+                HttpResponse { status: 200, headers: Default::default(), body: serde_json::json!({ "content": content }).to_string() }
+            }
+            None => HttpResponse { status: 404, headers: Default::default(), body: "{}".to_string() },
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+fn no_advisories() -> Box<Fixed> {
+    Box::new(Fixed { advisories: Vec::new(), asked: Arc::default() })
+}
+
+#[tokio::test]
+async fn transitive_dependencies_are_checked_under_the_config_of_each_workflow() {
+    let uses = workflow_using(&[&format!("acme/release@{}", SHA)]);
+    let root = scratch("lib-transitive");
+    write_files(&root, &[("trusting/workflows/ci.yml", &uses), ("trusting/dependency-scanner.yaml", "trusted_owners: [acme, evil]\n"), ("strict/workflows/ci.yml", &uses)]);
+    let (trusting_dir, strict_dir) = (root.join("trusting/workflows"), root.join("strict/workflows"));
+    let options = ScanOptions { transitive: Some(1), ..Default::default() };
+    let scanner = Scanner::new(trusting(&["acme"]), options)
+        .with_client(Box::new(Composites(&[("acme/release", &["evil/tool@v1"])])))
+        .with_advisory_source(no_advisories());
+    let outcome = scanner.scan(&[trusting_dir.clone(), strict_dir.clone()]).await.unwrap();
+
+    let nested: Vec<&Finding> = outcome.report.findings.iter().filter(|f| f.action == "evil/tool@v1").collect();
+    let strict_file = strict_dir.join("ci.yml").display().to_string();
+    assert!(!nested.is_empty());
+    for finding in &nested {
+        assert_eq!(finding.file.as_deref(), Some(strict_file.as_str()), "{}", finding.message);
+        assert_eq!(finding.line, Some(6));
+        assert_eq!(finding.chain, [format!("acme/release@{}", SHA)]);
+    }
+    assert_eq!(rule_ids(&nested.into_iter().cloned().collect::<Vec<_>>()), ["untrusted-owner", "untrusted-owner-no-sha"]);
+    let verdicts: Vec<(String, bool)> = outcome.decision.files.iter().map(|v| (v.file.clone(), v.passed)).collect();
+    assert_eq!(verdicts, [(strict_file, false), (trusting_dir.join("ci.yml").display().to_string(), true)]);
+}
+
+/// `acme/release` uses `acme/build`, which uses `evil/deep` and, in a cycle, `acme/release`;
+/// both `acme/release` and `acme/lint` use `evil/tool`
+const TREE: &[(&str, &[&str])] = &[
+    ("acme/release", &["acme/build@v1", "evil/tool@v1"]),
+    ("acme/build", &["evil/deep@v1", "acme/release@v1"]),
+    ("acme/lint", &["evil/tool@v1"]),
+];
+
+async fn scan_tree(depth: usize, max_depth_fail: Option<usize>) -> gh_action_security_scanner::ScanOutcome {
+    let repo = Repo::new("lib-tree", &[("ci.yml", &workflow_using(&["acme/release@v1", "acme/lint@v1"]))]);
+    let options = ScanOptions { transitive: Some(depth), max_depth_fail, ..Default::default() };
+    let scanner = Scanner::new(trusting(&["acme"]), options).with_client(Box::new(Composites(TREE))).with_advisory_source(no_advisories());
+    scanner.scan(&[repo.workflows()]).await.unwrap()
+}
+
+#[tokio::test]
+async fn transitive_summary_measures_the_dependency_tree() {
+    let outcome = scan_tree(3, None).await;
+    let nested: Vec<(String, Vec<String>)> =
+        outcome.report.transitive.clone().unwrap().into_iter().map(|n| (n.action, n.chain)).collect();
+    let chain = |actions: &[&str]| actions.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        nested,
+        [
+            ("evil/tool@v1".to_string(), chain(&["acme/lint@v1"])),
+            ("acme/build@v1".to_string(), chain(&["acme/release@v1"])),
+            ("evil/deep@v1".to_string(), chain(&["acme/release@v1", "acme/build@v1"])),
+        ]
+    );
+    let summary = gh_action_security_scanner::summary::Summary::new(&outcome.report, 1, std::time::Duration::ZERO);
+    let transitive = summary.transitive.unwrap();
+    assert_eq!(transitive.dependencies, 3);
+    assert_eq!(transitive.max_depth, 2);
+    let top: Vec<(String, usize)> = transitive.top_contributors.into_iter().map(|c| (c.action, c.dependencies)).collect();
+    assert_eq!(top, [("acme/release@v1".to_string(), 2), ("acme/lint@v1".to_string(), 1)]);
+    assert_eq!(summary.actions_checked, 5);
+}
+
+#[tokio::test]
+async fn depth_limits_what_transitive_follows() {
+    let outcome = scan_tree(1, None).await;
+    let transitive = outcome.report.transitive.as_deref().unwrap();
+    assert_eq!(transitive.iter().map(|n| n.depth()).max(), Some(1));
+    assert_eq!(transitive.len(), 2);
+}
+
+#[tokio::test]
+async fn max_depth_fail_flags_deeper_chains() {
+    let outcome = scan_tree(3, Some(1)).await;
+    let deep: Vec<&Finding> = outcome.report.findings.iter().filter(|f| f.rule == Rule::DependencyDepth).collect();
+    assert_eq!(deep.len(), 1, "{:?}", rule_ids(&outcome.report.findings));
+    assert_eq!(deep[0].action, "evil/deep@v1");
+    assert!(deep[0].message.ends_with("dependency chain of depth 2 exceeds --max-depth-fail 1"), "{}", deep[0].message);
+    assert_eq!(deep[0].level, Level::Error);
+    assert_eq!(deep[0].file.as_deref().map(|f| f.ends_with("ci.yml")), Some(true));
+
+    let outcome = scan_tree(3, Some(2)).await;
+    assert!(!outcome.report.findings.iter().any(|f| f.rule == Rule::DependencyDepth));
+}
+
+/// Fails every metadata lookup, and finds nothing else
+struct UnreadableMetadata;
+
+impl HttpClient for UnreadableMetadata {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        let metadata = request.url.contains("/contents/action.yml");
+        Box::pin(async move {
+            if metadata {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            Ok(HttpResponse { status: 404, headers: Default::default(), body: "{}".to_string() })
+        })
+    }
+}
+
+#[tokio::test]
+async fn a_composite_that_cannot_be_read_leaves_the_scan_incomplete() {
+    let repo = Repo::new("lib-unreadable", &[("ci.yml", &workflow_using(&["acme/release@v1"]))]);
+    let exit = ExitPolicy { require_complete: true, ..Default::default() };
+    let options = ScanOptions { transitive: Some(1), exit, ..Default::default() };
+    let recorded = Arc::new(Recorded::default());
+    let scanner = Scanner::new(trusting(&["acme"]), options)
+        .with_client(Box::new(UnreadableMetadata))
+        .with_advisory_source(no_advisories())
+        .with_events(Box::new(Recorder(recorded.clone())));
+    let outcome = scanner.scan(&[repo.workflows()]).await.unwrap();
+
+    let error = "Could not read the action metadata of acme/release@v1: GET /repos/acme/release/contents/action.yml?ref=v1 failed: connection reset";
+    assert_eq!(outcome.report.unchecked, ["acme/release@v1"]);
+    assert_eq!(outcome.report.errors, [error]);
+    assert!(recorded.warnings.lock().unwrap().iter().any(|w| w == error));
+    assert_eq!(outcome.decision.exit.code, 10);
+    assert!(outcome.decision.exit.reason.contains("1 unchecked action (--require-complete)"), "{}", outcome.decision.exit.reason);
+}
+
+/// A GitHub API whose only release is `acme/foo`'s latest, `v2`
+struct Releases;
+
+impl HttpClient for Releases {
+    fn send(&self, request: HttpRequest) -> ResponseFuture<'_> {
+        let response = match request.url.ends_with("/repos/acme/foo/releases/latest") {
+            true => HttpResponse { status: 200, headers: Default::default(), body: r#"{"tag_name": "v2"}"#.to_string() },
+            false => HttpResponse { status: 404, headers: Default::default(), body: "{}".to_string() },
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[tokio::test]
+async fn vendored_actions_are_checked_against_their_upstream() {
+    let local = "./.github/actions/vendored-foo";
+    let repo = Repo::new("lib-vendored", &[("ci.yml", &workflow_using(&[local]))]);
+    let config = Config {
+        vendored_actions: Some([(local.to_string(), "acme/foo@v1".to_string())].into()),
+        ..trusting(&[])
+    };
+    let scanner = Scanner::new(config, ScanOptions::default())
+        .with_client(Box::new(Releases))
+        .with_advisory_source(Box::new(Stubbed { vulnerable: &["acme/foo"], down: &[] }));
+    let report = scanner.scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+
+    assert_eq!(rule_ids(&report.findings), ["known-vulnerability", "vendored-outdated"]);
+    assert!(report.findings.iter().all(|f| f.action == local), "findings are about the local copy");
+    let outdated = report.findings.iter().find(|f| f.rule == Rule::VendoredOutdated).unwrap();
+    assert_eq!(outdated.message, format!("Vendored action {} is at v1 but upstream acme/foo has released v2", local));
+    let provenance: Vec<(&str, &str, &str)> = report.vendored.iter().map(|v| (v.local.as_str(), v.upstream.as_str(), v.source.as_str())).collect();
+    assert_eq!(provenance, [(local, "acme/foo@v1", "config")]);
+}
+
+#[tokio::test]
+async fn a_vendored_copy_of_the_latest_release_without_advisories_is_clean() {
+    let local = "./.github/actions/vendored-foo";
+    let repo = Repo::new("lib-vendored-clean", &[("ci.yml", &workflow_using(&[local]))]);
+    let config = Config {
+        vendored_actions: Some([(local.to_string(), "acme/foo@v2".to_string())].into()),
+        ..trusting(&[])
+    };
+    let scanner = Scanner::new(config, ScanOptions::default()).with_client(Box::new(Releases)).with_advisory_source(no_advisories());
+    let report = scanner.scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+    assert_eq!(rule_ids(&report.findings), Vec::<&str>::new());
+    assert_eq!(report.vendored.len(), 1);
+}
+
+#[tokio::test]
+async fn local_actions_without_provenance_are_not_looked_up() {
+    let repo = Repo::new("lib-local", &[("ci.yml", &workflow_using(&["./.github/actions/build"]))]);
+    let source = Fixed { advisories: vec![advisory("GHSA-aaaa-bbbb-cccc")], asked: Arc::default() };
+    let asked = source.asked.clone();
+    let scanner = Scanner::new(trusting(&[]), ScanOptions::default()).with_client(Box::new(Releases)).with_advisory_source(Box::new(source));
+    let report = scanner.scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+    assert!(!report.findings.iter().any(|f| f.rule == Rule::KnownVulnerability), "{:?}", rule_ids(&report.findings));
+    assert!(report.vendored.is_empty());
+    assert!(asked.lock().unwrap().is_empty(), "{:?}", asked.lock().unwrap());
+}
+
+#[tokio::test]
+async fn builtin_indicators_apply_whatever_the_config_unless_turned_off() {
+    let compromised = "tj-actions/changed-files@0e58ed8671d6b60d0890c21b07f8835ace038e67";
+    let repo = Repo::new("lib-indicators", &[("ci.yml", &workflow_using(&[compromised]))]);
+    let report = Scanner::new(trusting(&["tj-actions"]), offline()).scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+    assert_eq!(rule_ids(&report.findings), ["compromised-version"]);
+
+    let options = ScanOptions { builtin_indicators: false, ..offline() };
+    let report = Scanner::new(trusting(&["tj-actions"]), options).scan_workflow(&repo.workflow("ci.yml")).await.unwrap();
+    assert_eq!(rule_ids(&report.findings), Vec::<&str>::new());
+}
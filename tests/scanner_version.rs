@@ -0,0 +1,54 @@
+//! `minimum_scanner_version` in the config, and the warning about replay fixtures recorded by a
+//! newer scanner
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str, minimum: &str) -> Repo {
+    let config = format!("trusted_owners: [actions]\nminimum_scanner_version: \"{}\"\n", minimum);
+    Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]).config(&config)
+}
+
+#[test]
+fn a_config_requiring_a_newer_scanner_is_a_usage_error() {
+    let output = repo("minimum-newer", "99.0.0").scan(&[]);
+    assert_eq!(code(&output), 5, "{}", stderr(&output));
+    let expected = format!(
+        "scanner.yml: this config requires gh-action-security-scanner 99.0.0 or newer, but this is {}; upgrade the scanner",
+        env!("CARGO_PKG_VERSION")
+    );
+    assert!(stderr(&output).contains(&expected), "{}", stderr(&output));
+}
+
+#[test]
+fn this_version_or_an_older_one_passes() {
+    for minimum in ["0.0.1", env!("CARGO_PKG_VERSION"), "v0.1"] {
+        let output = repo("minimum-older", minimum).scan(&[]);
+        assert_eq!(code(&output), 0, "{}: {}", minimum, stderr(&output));
+    }
+}
+
+#[test]
+fn a_minimum_that_is_not_a_version_is_a_usage_error() {
+    let output = repo("minimum-invalid", "latest").scan(&[]);
+    assert_eq!(code(&output), 5, "{}", stderr(&output));
+    assert!(stderr(&output).contains("minimum_scanner_version latest is not a version such as 0.9.0"), "{}", stderr(&output));
+}
+
+#[test]
+fn a_fixture_from_a_newer_major_version_is_warned_about_once() {
+    let repo = Repo::new("fixture-newer", &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@v1.1.0"]))]);
+    let mut recorded: serde_json::Value = serde_json::from_str(include_str!("fixtures/http/vulnerable.json")).unwrap();
+    recorded["scanner_version"] = "99.0.0".into();
+    write_files(&repo.root, &[("newer.json", &recorded.to_string())]);
+    let mut command = repo.networked(&["--replay-http", "newer.json"]);
+    command.arg(repo.workflows());
+    let output = run(command, None);
+    let warnings = stderr(&output).matches("a newer major version than this").count();
+    assert_eq!(warnings, 1, "{}", stderr(&output));
+    assert!(stderr(&output).contains("newer.json was written by gh-action-security-scanner 99.0.0"), "{}", stderr(&output));
+
+    let output = run(repo.replaying("vulnerable.json", &[repo.workflows().to_str().unwrap()]), None);
+    assert!(!stderr(&output).contains("a newer major version"), "{}", stderr(&output));
+}
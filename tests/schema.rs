@@ -0,0 +1,88 @@
+//! `schema config` and `schema report`: the schemas printed, the example config and generated
+//! reports checked against them
+
+mod common;
+
+use common::*;
+use gh_action_security_scanner::config::Config;
+use gh_action_security_scanner::schema;
+
+const EXAMPLE: &str = include_str!("fixtures/config/example.yaml");
+
+fn printed(which: &str) -> serde_json::Value {
+    let repo = Repo::new(&format!("schema-{}", which), &[]);
+    let output = run(repo.command(&["schema", which]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    json(&output)
+}
+
+#[test]
+fn the_subcommands_print_the_schemas() {
+    assert_eq!(printed("config"), serde_json::to_value(schema::config_schema()).unwrap());
+    assert_eq!(printed("report"), serde_json::to_value(schema::report_schema()).unwrap());
+    assert_eq!(printed("config")["$schema"], "http://json-schema.org/draft-07/schema#");
+}
+
+#[test]
+fn the_example_config_uses_every_key() {
+    let example: serde_yaml::Value = serde_yaml::from_str(EXAMPLE).unwrap();
+    let missing: Vec<String> = schema::config_keys().into_iter().filter(|key| example.get(key.as_str()).is_none()).collect();
+    assert!(missing.is_empty(), "tests/fixtures/config/example.yaml lacks {:?}", missing);
+}
+
+#[test]
+fn the_example_config_round_trips_through_the_schema() {
+    let example: serde_yaml::Value = serde_yaml::from_str(EXAMPLE).unwrap();
+    schema::validate_config(&example).unwrap();
+    let config: Config = serde_yaml::from_value(example).unwrap();
+    let written = serde_yaml::to_value(&config).unwrap();
+    schema::validate_config(&written).unwrap();
+    let reread: Config = serde_yaml::from_value(written.clone()).unwrap();
+    assert_eq!(serde_yaml::to_value(&reread).unwrap(), written);
+}
+
+#[test]
+fn structural_mistakes_are_named_by_key_path() {
+    for (config, error) in [
+        ("trusted_owners: actions\n", "trusted_owners: expected array"),
+        ("max_untrusted_owners: lots\n", "max_untrusted_owners: expected integer"),
+        ("rule_overrides:\n  - rule: unstable-reference\n", "rule_overrides[0].level: missing"),
+        ("pin_policies:\n  acme/*:\n    pin: always\n", "pin_policies.acme/*.pin: does not match any allowed shape"),
+    ] {
+        let document: serde_yaml::Value = serde_yaml::from_str(config).unwrap();
+        let message = schema::validate_config(&document).unwrap_err();
+        assert!(message.starts_with(error), "{}: {}", config, message);
+    }
+}
+
+#[test]
+fn the_loader_reports_schema_errors_under_validate_config() {
+    let repo = Repo::new("schema-loader", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]).config("trusted_owners: actions\n");
+    let workflows = repo.workflows();
+    let output = run(repo.command(&["--validate-config", workflows.to_str().unwrap()]), None);
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("trusted_owners: expected array"), "{}", stderr(&output));
+}
+
+#[test]
+fn generated_reports_match_the_report_schema() {
+    let ci = workflow_using(&["actions/checkout@v4", "acme/tool@v1.1.0", "acme/deploy@main", "docker://alpine:3.19", "./.github/actions/local"]);
+    let repo = Repo::new("schema-report", &[("ci.yml", &ci)]).config("trusted_owners: [actions]\nblocked_owners: [evil]\n");
+    let workflows = repo.workflows();
+    let offline = repo.scan(&["--format", "json", "--strict"]);
+    let replayed = run(repo.replaying("vulnerable.json", &["--format", "json", workflows.to_str().unwrap()]), None);
+    for output in [offline, replayed] {
+        let report = json(&output);
+        assert!(!rules(&report).is_empty(), "{}", report);
+        schema::validate_report(&report).unwrap_or_else(|e| panic!("{}\n{}", e, report));
+    }
+}
+
+#[test]
+fn a_report_of_the_wrong_shape_is_rejected() {
+    let repo = Repo::new("schema-wrong", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let mut report = json(&repo.scan(&["--format", "json"]));
+    schema::validate_report(&report).unwrap();
+    report["findings"] = serde_json::json!("none");
+    assert_eq!(schema::validate_report(&report).unwrap_err(), "findings: expected array");
+}
@@ -0,0 +1,54 @@
+//! Directory-scoped configs: the nearest `dependency-scanner.yaml` above a workflow merged over
+//! the root config, except for the keys the root config enforces
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str, root_config: &str) -> Repo {
+    let repo = Repo::new(name, &[]).config(root_config);
+    let workflow = workflow_using(&["acme/deploy@v1"]);
+    write_files(
+        &repo.root,
+        &[
+            ("teams/web/dependency-scanner.yaml", "trusted_owners: [actions, acme]\n"),
+            ("teams/web/ci.yml", &workflow),
+            ("teams/api/ci.yml", &workflow),
+        ],
+    );
+    repo
+}
+
+/// The files of the untrusted-owner findings in a `--format json` report
+fn untrusted_in(output: &std::process::Output) -> Vec<String> {
+    let report = json(output);
+    let findings = report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "untrusted-owner-no-sha");
+    findings.map(|f| f["file"].as_str().unwrap().to_string()).collect()
+}
+
+#[test]
+fn each_workflow_is_governed_by_its_nearest_config() {
+    let repo = repo("scoped-nearest", "trusted_owners: [actions]\n");
+    let output = run(repo.command(&["--format", "json", "teams/web/ci.yml", "teams/api/ci.yml"]), None);
+    assert_eq!(untrusted_in(&output), ["teams/api/ci.yml"], "{}", stderr(&output));
+    let configs = &json(&output)["configs"];
+    let root = repo.root.join("scanner.yml").display().to_string();
+    assert_eq!(configs["teams/api/ci.yml"], root.as_str(), "{}", configs);
+    let scoped = repo.root.join("teams/web/dependency-scanner.yaml").display().to_string();
+    assert_eq!(configs["teams/web/ci.yml"], format!("{} over {}", scoped, root).as_str(), "{}", configs);
+}
+
+#[test]
+fn enforced_keys_of_the_root_config_win_over_a_scoped_config() {
+    let repo = repo("scoped-enforced", "trusted_owners: [actions]\nenforced: [trusted_owners]\n");
+    let output = run(repo.command(&["--format", "json", "teams/web/ci.yml", "teams/api/ci.yml"]), None);
+    assert_eq!(untrusted_in(&output), ["teams/api/ci.yml", "teams/web/ci.yml"], "{}", stderr(&output));
+
+    let output = run(repo.command(&["config", "check", "teams/web/ci.yml"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.starts_with("# Effective config for teams/web/ci.yml ("), "{}", out);
+    assert!(out.contains("# trusted_owners is enforced by "), "{}", out);
+    assert!(out.contains("scanner.yml; the scoped value is ignored\n"), "{}", out);
+    assert!(out.contains("trusted_owners:\n- actions\n"), "{}", out);
+}
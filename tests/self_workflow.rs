@@ -0,0 +1,56 @@
+//! `--self`: only the running workflow, named by `GITHUB_WORKFLOW_REF` or the event payload, and a
+//! clear failure when it is not in the checkout
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["acme/ci@main"])), ("release.yml", &workflow_using(&["acme/release@main"]))])
+}
+
+/// The workflow files the report scanned
+fn files(output: &std::process::Output) -> Vec<String> {
+    json(output)["configs"].as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn the_workflow_ref_names_the_one_file_scanned() {
+    let repo = repo("self-ref");
+    let mut command = repo.command(&["--self", "--format", "json"]);
+    command.env("GITHUB_WORKFLOW_REF", "acme/app/.github/workflows/release.yml@refs/heads/main");
+    let output = run(command, None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(files(&output), [".github/workflows/release.yml"]);
+
+    let mut command = repo.command(&["--self", "--ascii"]);
+    command.env("GITHUB_WORKFLOW_REF", "acme/app/.github/workflows/release.yml@refs/heads/main");
+    assert!(stdout(&run(command, None)).contains("Scanned: ./.github/workflows/release.yml (--self) at "));
+}
+
+#[test]
+fn without_a_workflow_ref_the_event_payload_names_the_file() {
+    let repo = repo("self-event");
+    write_files(&repo.root, &[("event.json", "{\"workflow\": \".github/workflows/ci.yml\"}")]);
+    let mut command = repo.command(&["--self", "--format", "json"]);
+    command.env_remove("GITHUB_WORKFLOW_REF").env("GITHUB_EVENT_PATH", repo.root.join("event.json"));
+    let output = run(command, None);
+    assert_eq!(files(&output), [".github/workflows/ci.yml"], "{}", stderr(&output));
+}
+
+#[test]
+fn a_workflow_outside_the_checkout_or_a_malformed_ref_fails_clearly() {
+    let repo = repo("self-missing");
+    let mut command = repo.command(&["--self"]);
+    command.env("GITHUB_WORKFLOW_REF", "acme/shared/.github/workflows/reusable.yml@refs/tags/v1");
+    let output = run(command, None);
+    assert_ne!(code(&output), 0);
+    let err = stderr(&output);
+    assert!(err.contains("--self: workflow .github/workflows/reusable.yml from acme/shared is not in the checkout"), "{}", err);
+
+    let mut command = repo.command(&["--self"]);
+    command.env("GITHUB_WORKFLOW_REF", "acme/app/.github/workflows/ci.yml@main");
+    let output = run(command, None);
+    assert_ne!(code(&output), 0);
+    assert!(stderr(&output).contains("GITHUB_WORKFLOW_REF is not of the form owner/repo/path@refs/...: "), "{}", stderr(&output));
+}
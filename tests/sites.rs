@@ -0,0 +1,69 @@
+//! Where findings apply: a workflow with several jobs and steps, tests/fixtures/workflows/release.yml,
+//! whose findings name the job and the step (by name, else by index) of each use of the action
+
+mod common;
+
+use common::*;
+
+const RELEASE: &str = include_str!("fixtures/workflows/release.yml");
+
+fn scan(name: &str, args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("release.yml", RELEASE)]).config("trusted_owners: [actions]\n");
+    let mut all = vec!["--ascii"];
+    all.extend(args);
+    all.push(".github/workflows");
+    run(repo.command(&all), None)
+}
+
+#[test]
+fn the_text_output_lists_each_failing_use_at_its_job_and_step() {
+    let output = scan("sites-text", &["--strict"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let out = stdout(&output);
+    let listed = out.split("Actions with insecure version pinning:\n").nth(1).unwrap_or_else(|| panic!("{}", out));
+    let listed: Vec<&str> = listed.lines().take_while(|l| l.starts_with("- ")).collect();
+    assert_eq!(
+        listed,
+        [
+            "- .github/workflows/release.yml > build > \"Lint\": acme/lint@v1 - untrusted owner no sha",
+            "- .github/workflows/release.yml > deploy > \"Post release notes\": actions/github-script@main - unstable reference",
+            "- .github/workflows/release.yml > deploy > step 3: acme/lint@v1 - untrusted owner no sha",
+        ],
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn findings_carry_the_job_and_step_of_each_use() {
+    let output = scan("sites-json", &["--strict", "--format", "json"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    let mut sites: Vec<(String, String, String, String)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|f| f["level"] == "error")
+        .map(|f| {
+            let field = |key: &str| f[key].as_str().unwrap_or_else(|| panic!("{} has no {}", f, key)).to_string();
+            (field("action"), field("job"), field("step"), field("rule"))
+        })
+        .collect();
+    sites.sort();
+    let site = |action: &str, job: &str, step: &str, rule: &str| (action.to_string(), job.to_string(), step.to_string(), rule.to_string());
+    assert_eq!(
+        sites,
+        [
+            site("acme/lint@v1", "build", "\"Lint\"", "untrusted-owner-no-sha"),
+            site("acme/lint@v1", "deploy", "step 3", "untrusted-owner-no-sha"),
+            site("actions/github-script@main", "deploy", "\"Post release notes\"", "unstable-reference"),
+        ]
+    );
+
+    // The matrix job's steps are there once, and the unflagged checkouts not at all
+    let tree = &report["workflows"][".github/workflows/release.yml"];
+    let steps = |job: &str| tree[job].as_object().unwrap().keys().cloned().collect::<Vec<String>>();
+    assert_eq!(steps("build"), ["\"Lint\""]);
+    assert_eq!(steps("deploy"), ["\"Post release notes\"", "step 3"]);
+    assert_eq!(tree["build"]["\"Lint\""].as_array().unwrap().len(), 2, "{}", tree);
+}
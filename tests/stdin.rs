@@ -0,0 +1,79 @@
+//! A workflow path of `-`: the workflow read from stdin, reported as `<stdin>` or under
+//! `--filename`, alone or with workflow files, and the invocations that cannot work
+
+mod common;
+
+use common::*;
+
+const PIPED: &str = "on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: actions/checkout@v4\n      - uses: acme/tool@v1.1.0\n";
+
+fn piped(repo: &Repo, args: &[&str]) -> std::process::Output {
+    run(repo.command(args), Some(PIPED.as_bytes()))
+}
+
+fn files(report: &serde_json::Value) -> Vec<String> {
+    report["configs"].as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn the_piped_workflow_is_scanned_as_stdin() {
+    let repo = Repo::new("stdin-plain", &[]);
+    let output = piped(&repo, &["--format", "json", "-"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(files(&report), ["<stdin>"]);
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "untrusted-owner-no-sha").unwrap();
+    assert_eq!((finding["file"].as_str(), finding["line"].as_u64()), (Some("<stdin>"), Some(7)));
+}
+
+#[test]
+fn filename_names_it_in_every_location_aware_output() {
+    let repo = Repo::new("stdin-filename", &[]);
+    let output = piped(&repo, &["--format", "sarif", "--filename", ".github/workflows/ci.yml", "-"]);
+    let log: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert!(!results.is_empty());
+    for result in results {
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], ".github/workflows/ci.yml", "{}", result);
+    }
+
+    let output = piped(&repo, &["--format", "github", "--filename", ".github/workflows/ci.yml", "-"]);
+    assert!(stdout(&output).contains("::error file=.github/workflows/ci.yml,line=7,title=untrusted-owner-no-sha::"), "{}", stdout(&output));
+}
+
+#[test]
+fn stdin_and_workflow_files_are_scanned_together() {
+    let repo = Repo::new("stdin-mixed", &[("extra.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let extra = repo.workflow("extra.yml");
+    let output = piped(&repo, &["--format", "json", "--filename", "piped.yml", "-", extra.to_str().unwrap()]);
+    let mut scanned = files(&json(&output));
+    scanned.sort();
+    assert_eq!(scanned.len(), 2, "{:?}", scanned);
+    assert!(scanned.contains(&"piped.yml".to_string()) && scanned.iter().any(|f| f.ends_with("extra.yml")), "{:?}", scanned);
+}
+
+#[test]
+fn network_checks_run_on_the_piped_workflow() {
+    let repo = Repo::new("stdin-online", &[]);
+    let output = run(repo.replaying("vulnerable.json", &["--format", "json", "-"]), Some(PIPED.as_bytes()));
+    assert_eq!(code(&output), 3, "{}", stderr(&output));
+    assert!(rules(&json(&output)).contains(&"known-vulnerability".to_string()));
+}
+
+#[test]
+fn invocations_that_cannot_work_say_why() {
+    let repo = Repo::new("stdin-conflicts", &[("ci.yml", PIPED)]);
+    let workflow = repo.workflow("ci.yml");
+    for (args, message) in [
+        (vec!["-", "-"], "- is given more than once; stdin holds one workflow"),
+        (vec!["--token", "-", "-"], "- and --token - both read stdin"),
+        (vec!["--filename", "ci.yml", workflow.to_str().unwrap()], "--filename names the workflow read from stdin; pass - as a workflow path"),
+        (vec!["--annotate-workflow", "--in-place", "-"], "--in-place cannot write the annotated workflow back to stdin"),
+    ] {
+        let output = piped(&repo, &args);
+        assert_eq!(code(&output), 5, "{:?}", args);
+        assert!(stderr(&output).contains(message), "{:?}: {}", args, stderr(&output));
+        assert!(stdout(&output).is_empty(), "{:?}: nothing is scanned: {}", args, stdout(&output));
+    }
+}
@@ -0,0 +1,77 @@
+//! The end-of-run summary: counts per category, API requests against cache hits, and coverage
+//! made explicit when lookups fail, which `--require-complete` turns into a failure
+
+mod common;
+
+use common::*;
+
+/// Lookups against a local server, with GitHub's advisories only
+fn scan(repo: &Repo, server: &MockServer, args: &[&str]) -> std::process::Output {
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+fn repo(name: &str, uses: &[&str]) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(uses))]).config("trusted_owners: [actions]\n")
+}
+
+#[test]
+fn the_summary_counts_findings_per_category() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("summary-counts", &["actions/checkout@v4", "acme/tool@v1.1.0", "not-an-action"]);
+    let output = scan(&repo, &server, &["--format", "json"]);
+    assert_eq!(code(&output), 3, "{}", stderr(&output));
+    let summary = &json(&output)["summary"];
+    assert_eq!(summary["workflows_scanned"], 1, "{}", summary);
+    assert_eq!(summary["actions_checked"], 3, "{}", summary);
+    let findings = &summary["findings"];
+    assert_eq!(findings["vulnerable"], 1, "{}", summary);
+    assert_eq!(findings["insecure_pinning"], 1, "{}", summary);
+    assert_eq!(findings["untrusted_owner"], 1, "{}", summary);
+    assert_eq!(findings["skipped_or_malformed"], 1, "{}", summary);
+    assert_eq!(summary["complete"], true, "{}", summary);
+    assert_eq!(summary["api_requests"], 2, "{}", summary);
+    assert_eq!(summary["cache_hits"], 0, "{}", summary);
+}
+
+#[test]
+fn a_second_scan_counts_cache_hits_instead_of_requests() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("summary-cache", &["actions/checkout@v4", "acme/tool@v1.1.0"]);
+    scan(&repo, &server, &[]);
+    let output = scan(&repo, &server, &[]);
+    assert_eq!(code(&output), 3, "{}", stderr(&output));
+    assert!(stdout(&output).contains("- API requests: 0 sent, 2 answered from the cache"), "{}", stdout(&output));
+    assert!(stdout(&output).contains("- coverage: complete"), "{}", stdout(&output));
+}
+
+#[test]
+fn failed_lookups_make_the_coverage_incomplete() {
+    let server = MockServer::answering(500);
+    let repo = repo("summary-incomplete", &["actions/checkout@v4", "acme/tool@v1.1.0"]);
+    let output = scan(&repo, &server, &[]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    let coverage = "- coverage: INCOMPLETE, 2 of 2 actions could not be checked \
+                    (acme/tool@v1.1.0, actions/checkout@v4); the scan says nothing of them";
+    assert!(stdout(&output).contains(coverage), "{}", stdout(&output));
+    assert!(!stdout(&output).contains("All actions passed"), "{}", stdout(&output));
+
+    let report = json(&scan(&repo, &server, &["--format", "json"]));
+    assert_eq!(report["summary"]["complete"], false, "{}", report);
+    assert_eq!(report["summary"]["incomplete"], serde_json::json!(["acme/tool@v1.1.0", "actions/checkout@v4"]));
+}
+
+#[test]
+fn require_complete_applies_to_the_actions_left_unchecked() {
+    let server = MockServer::answering(500);
+    let repo = repo("summary-require", &["acme/tool@v1.1.0"]);
+    let output = scan(&repo, &server, &["--require-complete"]);
+    assert_eq!(code(&output), 10, "{}", stderr(&output));
+    let chain = "- unchecked actions: 1 (acme/tool@v1.1.0), --require-complete applied";
+    assert!(stdout(&output).contains(chain), "{}", stdout(&output));
+
+    let without = scan(&repo, &server, &[]);
+    assert!(stdout(&without).contains("--fail-on-unchecked not set"), "{}", stdout(&without));
+}
@@ -0,0 +1,53 @@
+//! Each workflow's trigger surface in the reports, and rule levels that follow it
+
+mod common;
+
+use common::*;
+
+const FORK_PR: &str = "on: pull_request_target\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: acme/tool@v1\n";
+const NIGHTLY: &str = "on:\n  schedule:\n    - cron: '0 0 * * *'\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - uses: acme/tool@v1\n";
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("fork.yml", FORK_PR), ("nightly.yml", NIGHTLY)])
+}
+
+#[test]
+fn each_workflow_reports_its_trigger_surface() {
+    let repo = repo("surface-reported");
+    let output = repo.scan(&["--repo-visibility", "public"]);
+    // The surface is progress, so it goes to stderr with the other per-workflow lines
+    assert!(stderr(&output).contains("Trigger surface: anyone (pull_request_target)"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Trigger surface: internal (schedule)"), "{}", stderr(&output));
+
+    let report = json(&repo.scan(&["--repo-visibility", "public", "--format", "json", "--output-dir", "out"]));
+    let surfaces: Vec<(&str, &str)> = report["trigger_surfaces"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(file, s)| (file.rsplit('/').next().unwrap(), s["surface"].as_str().unwrap()))
+        .collect();
+    assert_eq!(surfaces, [("fork.yml", "anyone"), ("nightly.yml", "internal")], "{}", report);
+
+    let markdown = std::fs::read_to_string(repo.root.join("out/report.md")).unwrap();
+    assert!(markdown.contains("| anyone (pull_request_target) |"), "{}", markdown);
+
+    let report = json(&repo.scan(&["--repo-visibility", "private", "--format", "json"]));
+    let fork = report["trigger_surfaces"].as_object().unwrap().iter().find(|(file, _)| file.ends_with("fork.yml")).unwrap().1;
+    assert_eq!(fork["surface"], "contributors", "{}", report);
+}
+
+#[test]
+fn rule_levels_can_scale_with_the_trigger_surface() {
+    let repo = repo("surface-overrides").config(
+        "trusted_owners: [actions]\nrule_overrides:\n  - {rule: untrusted-owner-no-sha, level: warning}\n  - {rule: untrusted-owner-no-sha, trigger_surface: anyone, level: error}\n",
+    );
+    let report = json(&repo.scan(&["--repo-visibility", "public", "--format", "json"]));
+    let levels: Vec<(&str, &str)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|f| f["rule"] == "untrusted-owner-no-sha")
+        .map(|f| (f["file"].as_str().unwrap().rsplit('/').next().unwrap(), f["level"].as_str().unwrap()))
+        .collect();
+    assert_eq!(levels, [("fork.yml", "error"), ("nightly.yml", "warning")], "{}", report);
+}
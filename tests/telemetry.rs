@@ -0,0 +1,58 @@
+//! `--otlp-endpoint` (the `otlp` feature) against an in-process collector, and against one that
+//! never answers, with the scan's own lookups replayed from tests/fixtures/http/vulnerable.json
+#![cfg(feature = "otlp")]
+
+mod common;
+
+use common::*;
+use std::time::{Duration, Instant};
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@v1.1.0"]))]).config("trusted_owners: [actions, acme]\n")
+}
+
+fn scan(repo: &Repo, args: &[&str]) -> std::process::Output {
+    let workflows = repo.workflows();
+    let mut all = args.to_vec();
+    all.push(workflows.to_str().unwrap());
+    run(repo.replaying("vulnerable.json", &all), None)
+}
+
+#[test]
+fn the_scan_exports_its_trace_and_metrics() {
+    let collector = MockServer::answering(200);
+    let repo = repo("otlp");
+    let output = scan(&repo, &["--otlp-endpoint", &collector.base]);
+    assert_eq!(code(&output), 1, "{}", stderr(&output));
+    let requests = collector.requests();
+    let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, ["/v1/traces", "/v1/metrics"]);
+
+    let traces: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let spans = traces["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap();
+    let id_of = |name: &str| spans.iter().find(|s| s["name"] == name).unwrap_or_else(|| panic!("{}: {:?}", name, spans))["spanId"].clone();
+    for phase in ["load", "policy", "network", "decide"] {
+        let span = spans.iter().find(|s| s["name"] == phase).unwrap();
+        assert_eq!(span["parentSpanId"], id_of("scan"), "{}", phase);
+    }
+    let lookup = spans.iter().find(|s| s["name"].as_str().unwrap().starts_with("GET https://api.github.com/advisories")).unwrap();
+    assert_eq!(lookup["parentSpanId"], id_of("network"), "{}", lookup);
+
+    let metrics = &requests[1].body;
+    assert!(metrics.contains("\"scanner.findings\"") && metrics.contains("known-vulnerability"), "{}", metrics);
+}
+
+#[test]
+fn an_unreachable_collector_costs_the_timeout_and_keeps_the_exit_code() {
+    let repo = repo("otlp-unreachable");
+    let without = scan(&repo, &[]);
+    // Connections queue in the backlog and are never answered
+    let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", silent.local_addr().unwrap());
+    let started = Instant::now();
+    let output = scan(&repo, &["--otlp-endpoint", &endpoint, "--otlp-timeout", "1s"]);
+    assert!(started.elapsed() < Duration::from_secs(10), "{:?}", started.elapsed());
+    assert_eq!(code(&output), code(&without), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Warning: OTLP traces export failed"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Warning: OTLP metrics export failed"), "{}", stderr(&output));
+}
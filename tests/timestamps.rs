@@ -0,0 +1,72 @@
+//! `--timestamp` and `SOURCE_DATE_EPOCH`: every structured artifact rendered with a pinned clock,
+//! against golden files
+
+mod common;
+
+use common::*;
+
+const TIMESTAMP: &str = "2024-01-02T03:04:05Z";
+/// [`TIMESTAMP`] in seconds since the epoch
+const EPOCH: &str = "1704164645";
+
+const GOLDEN: [(&str, &str); 4] = [
+    ("report.json", include_str!("golden/artifacts/report.json")),
+    ("report.sarif", include_str!("golden/artifacts/report.sarif")),
+    ("report.md", include_str!("golden/artifacts/report.md")),
+    ("report.cdx.json", include_str!("golden/artifacts/report.cdx.json")),
+];
+
+/// Write every artifact for one failing workflow, scanned by relative paths so nothing in them
+/// depends on where the fixture lives; they are in the repo's `out` directory
+fn artifacts(name: &str, args: &[&str], epoch: Option<&str>) -> Repo {
+    let ci = workflow_using(&["actions/checkout@v4", "acme/deploy@v1", "docker://alpine:3.19"]);
+    let repo = Repo::new(name, &[("ci.yml", &ci)]);
+    let mut command = repo.command_without_config(&["--config", "scanner.yml", "--ascii", "--output-dir", "out"]);
+    command.args(args).arg(".github/workflows");
+    if let Some(epoch) = epoch {
+        command.env("SOURCE_DATE_EPOCH", epoch);
+    }
+    let output = run(command, None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    repo
+}
+
+fn assert_golden(repo: &Repo) {
+    for (file, golden) in GOLDEN {
+        let actual = std::fs::read_to_string(repo.root.join("out").join(file)).unwrap();
+        assert!(actual == golden, "{} differs from the golden file\nexpected:\n{}\nactual:\n{}", file, golden, actual);
+    }
+}
+
+#[test]
+fn pinned_artifacts_match_the_golden_files() {
+    assert_golden(&artifacts("timestamp", &["--timestamp", TIMESTAMP], None));
+}
+
+#[test]
+fn source_date_epoch_pins_the_same_time() {
+    assert_golden(&artifacts("source-date-epoch", &[], Some(EPOCH)));
+}
+
+#[test]
+fn timestamp_wins_over_source_date_epoch() {
+    assert_golden(&artifacts("timestamp-wins", &["--timestamp", TIMESTAMP], Some("0")));
+}
+
+#[test]
+fn an_unpinned_clock_stamps_the_current_time() {
+    let repo = artifacts("unpinned", &[], None);
+    let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(repo.root.join("out/report.json")).unwrap()).unwrap();
+    assert_ne!(report["generated_at"], TIMESTAMP);
+    assert!(report["generated_at"].as_str().unwrap().ends_with('Z'), "{}", report["generated_at"]);
+}
+
+#[test]
+fn a_malformed_source_date_epoch_is_an_error() {
+    let repo = Repo::new("bad-epoch", &[("ci.yml", &workflow_using(&["actions/checkout@v4"]))]);
+    let mut command = repo.command(&[".github/workflows"]);
+    command.env("SOURCE_DATE_EPOCH", "yesterday");
+    let output = run(command, None);
+    assert_eq!(code(&output), 10);
+    assert!(stderr(&output).contains("SOURCE_DATE_EPOCH is not a number of seconds: yesterday"), "{}", stderr(&output));
+}
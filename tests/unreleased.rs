@@ -0,0 +1,39 @@
+//! SHA pins that no release contains, from tests/fixtures/http/unreleased.json: acme/ahead is
+//! pinned 3 commits past v2.0.0, acme/released to a commit v2.0.0 contains, and acme/backport to
+//! the v1.9.0 release beside it
+
+mod common;
+
+use common::*;
+
+const AHEAD: &str = "acme/ahead@1111111111111111111111111111111111111111";
+const RELEASED: &str = "acme/released@2222222222222222222222222222222222222222";
+const BACKPORT: &str = "acme/backport@3333333333333333333333333333333333333333";
+
+fn scan(name: &str, config: &str) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&[AHEAD, RELEASED, BACKPORT]))]).config(&format!("trusted_owners: [acme]\n{}", config));
+    run(repo.replaying("unreleased.json", &["--source", "github", "--format", "json", ".github/workflows"]), None)
+}
+
+fn unreleased(output: &std::process::Output) -> Vec<serde_json::Value> {
+    let report = json(output);
+    report["findings"].as_array().unwrap().iter().filter(|f| f["rule"] == "unreleased-commit").cloned().collect()
+}
+
+#[test]
+fn only_a_pin_past_every_release_is_flagged() {
+    let output = scan("unreleased-default", "");
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let found = unreleased(&output);
+    assert_eq!(found.len(), 1, "{}", stderr(&output));
+    assert_eq!(found[0]["action"], AHEAD);
+    assert_eq!(found[0]["level"], "warning");
+    assert_eq!(found[0]["message"], format!("{} is pinned to unreleased commit (3 commits ahead of v2.0.0)", AHEAD));
+}
+
+#[test]
+fn the_level_is_configurable() {
+    let output = scan("unreleased-error", "unreleased_commit_level: error\n");
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    assert_eq!(unreleased(&output)[0]["level"], "error");
+}
@@ -0,0 +1,43 @@
+//! `vendor <action>`: what the scanner knows about one action, with lookups it cannot make marked
+//! unknown, and the exit code the action would give a scan under the current config
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[]).config("trusted_owners: [actions]\n")
+}
+
+#[test]
+fn offline_lookups_are_marked_unknown_and_the_policy_still_decides() {
+    let output = run(repo("vendor-offline").command(&["vendor", "acme/tool@v1"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.contains("  Trusted owner:    no\n"), "{}", out);
+    assert!(out.contains("  Advisories:       unknown (offline)\n"), "{}", out);
+    assert!(out.contains("  Latest release:   unknown (offline)\n"), "{}", out);
+    assert!(out.contains("[FAIL] Non-trusted action acme/tool@v1 should use commit SHA instead of tag/branch"), "{}", out);
+    assert!(out.contains("  Policy:           [FAIL] fails the current policy"), "{}", out);
+}
+
+#[test]
+fn recorded_advisories_are_summarized_and_fail_a_vulnerable_ref() {
+    // tests/fixtures/http/vulnerable.json: acme/tool had an advisory fixed in 1.2.0
+    let repo = repo("vendor-replayed");
+    let output = run(repo.replaying("vulnerable.json", &["--source", "github", "vendor", "acme/tool@v1.1.0"]), None);
+    assert_eq!(code(&output), 3, "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.contains("  Advisories:       1 open, 1 total, latest 2024-01-02T03:04:05Z\n"), "{}", out);
+    assert!(out.contains("[FAIL] GHSA-abcd-efgh-ijkl: Command injection in acme/tool (fix available: 1.2.0)"), "{}", out);
+
+    let output = run(repo.replaying("vulnerable.json", &["--source", "github", "vendor", "actions/checkout@v4", "--json"]), None);
+    assert_eq!(code(&output), 0, "{}", stderr(&output));
+    let summary = json(&output);
+    assert_eq!(summary["trusted_owner"], true);
+    assert_eq!(summary["passes_policy"], true);
+    assert_eq!(summary["advisories"]["open"], 0);
+    // Nothing recorded for the release and the repository: unknown, and not failing the command
+    let unknown: Vec<&str> = summary["unknown"].as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(unknown, ["latest_release", "repository"], "{}", summary);
+}
@@ -0,0 +1,44 @@
+//! `--quiet` and `--verbose`: how much progress goes to stderr, while stdout keeps only the report
+//! in the chosen format
+
+mod common;
+
+use common::*;
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["actions/checkout@v4", "acme/tool@v1"]))]).config("trusted_owners: [actions]\n")
+}
+
+#[test]
+fn progress_goes_to_stderr_and_quiet_drops_it_but_not_the_report() {
+    let repo = repo("verbosity-quiet");
+    let normal = repo.scan(&["--ascii"]);
+    assert!(stderr(&normal).contains("Scanning 2 actions from 1 workflow file(s)"), "{}", stderr(&normal));
+    assert!(!stdout(&normal).contains("Scanning 2 actions"), "{}", stdout(&normal));
+
+    let quiet = repo.scan(&["--ascii", "--quiet"]);
+    assert_eq!(code(&quiet), code(&normal));
+    assert!(!stderr(&quiet).contains("Scanning"), "{}", stderr(&quiet));
+    assert!(stdout(&quiet).contains("[FAIL] Non-trusted action acme/tool@v1 should use commit SHA instead of tag/branch"), "{}", stdout(&quiet));
+    assert!(stdout(&quiet).contains("Security scan failed!"), "{}", stdout(&quiet));
+
+    // Whatever the chatter, stdout is the report alone
+    let report = json(&repo.scan(&["--format", "json", "--verbose"]));
+    assert_eq!(report["exit"]["code"], 2, "{}", report);
+}
+
+#[test]
+fn verbose_adds_each_request_with_its_status() {
+    let server = MockServer::replaying("vulnerable.json");
+    let repo = repo("verbosity-verbose");
+    let scan = |args: &[&str]| {
+        let mut command = repo.networked(&["--api-url", &server.base, "--source", "github"]);
+        command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+        run(command, None)
+    };
+    let request = format!("GET {}/advisories?ecosystem=actions&affects=acme/tool&per_page=100 -> 200 in ", server.base);
+    let verbose = scan(&["--verbose"]);
+    assert!(stderr(&verbose).contains(&request), "{}", stderr(&verbose));
+    assert!(!stdout(&verbose).contains(&request), "{}", stdout(&verbose));
+    assert!(!stderr(&scan(&["--no-cache"])).contains(" -> 200 in "));
+}
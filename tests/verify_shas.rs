@@ -0,0 +1,64 @@
+//! `--verify-shas`: SHA pins checked against a server where one commit is a branch head, one does
+//! not exist and one is on no branch or tag, as a commit pushed to a fork would be
+
+mod common;
+
+use common::*;
+
+/// The head of `main`
+const ON_MAIN: &str = "1111111111111111111111111111111111111111";
+const MISSING: &str = "2222222222222222222222222222222222222222";
+/// The commit the v1.2.0 release tag points at
+const V1_2_0: &str = "3333333333333333333333333333333333333333";
+const FORKED: &str = "4444444444444444444444444444444444444444";
+
+fn scan(server: &MockServer, name: &str, sha: &str, args: &[&str]) -> std::process::Output {
+    let repo = Repo::new(name, &[("ci.yml", &workflow_using(&[&format!("acme/tool@{}", sha)]))]).config("trusted_owners: [acme]\n");
+    let api = format!("{}/api/v3", server.base);
+    let mut command = repo.networked(&["--api-url", &api, "--source", "github", "--format", "json"]);
+    command.env("GITHUB_TOKEN", "test-token").args(args).arg(repo.workflows());
+    run(command, None)
+}
+
+fn finding(output: &std::process::Output, rule: &str) -> Option<serde_json::Value> {
+    json(output)["findings"].as_array().unwrap().iter().find(|f| f["rule"] == rule).cloned()
+}
+
+#[test]
+fn a_commit_the_repository_does_not_have_is_flagged_with_the_release_to_pin() {
+    let server = MockServer::replaying("verify-shas.json");
+    let output = scan(&server, "verify-missing", MISSING, &["--verify-shas"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let missing = finding(&output, "missing-commit").unwrap_or_else(|| panic!("{}", stdout(&output)));
+    let expected = format!(
+        "acme/tool@{} is pinned to commit 222222222222, which does not exist in acme/tool; re-pin to the SHA of the intended release tag, e.g. acme/tool@{} # v1.2.0 for the latest release",
+        MISSING, V1_2_0
+    );
+    assert_eq!(missing["message"], expected.as_str());
+}
+
+#[test]
+fn a_commit_on_no_branch_or_tag_is_flagged_as_unreachable() {
+    let server = MockServer::replaying("verify-shas.json");
+    let output = scan(&server, "verify-forked", FORKED, &["--verify-shas"]);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let unreachable = finding(&output, "unreachable-commit").unwrap_or_else(|| panic!("{}", stdout(&output)));
+    assert!(unreachable["message"].as_str().unwrap().contains("is on no branch or recent tag of acme/tool"), "{}", unreachable);
+    assert!(finding(&output, "missing-commit").is_none());
+}
+
+#[test]
+fn a_branch_head_passes() {
+    let server = MockServer::replaying("verify-shas.json");
+    let output = scan(&server, "verify-main", ON_MAIN, &["--verify-shas"]);
+    assert!(finding(&output, "missing-commit").is_none() && finding(&output, "unreachable-commit").is_none(), "{}", stdout(&output));
+    assert!(server.requests().iter().any(|r| r.path.ends_with(&format!("/commits/{}", ON_MAIN))));
+}
+
+#[test]
+fn commits_are_only_looked_up_with_verify_shas() {
+    let server = MockServer::replaying("verify-shas.json");
+    let output = scan(&server, "verify-off", MISSING, &[]);
+    assert!(finding(&output, "missing-commit").is_none(), "{}", stdout(&output));
+    assert!(!server.requests().iter().any(|r| r.path.contains("/commits/")));
+}
@@ -0,0 +1,56 @@
+//! `--repo-visibility`: the visibility assumed, given or looked up in tests/fixtures/http/visibility.json,
+//! and the visibility-sensitive rules it downgrades in private repositories
+
+mod common;
+
+use common::*;
+
+const SELF_HOSTED_PR: &str = "on: pull_request\njobs:\n  build:\n    runs-on: [self-hosted, linux]\n    steps:\n      - run: make\n";
+
+fn repo(name: &str, config: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", SELF_HOSTED_PR)]).config(config)
+}
+
+/// The level of the self-hosted-pull-request finding and the visibility note of a JSON report
+fn judged(output: &std::process::Output) -> (String, String) {
+    let report = json(output);
+    let finding = report["findings"].as_array().unwrap().iter().find(|f| f["rule"] == "self-hosted-pull-request").unwrap();
+    (finding["level"].as_str().unwrap().to_string(), report["visibility"].as_str().unwrap().to_string())
+}
+
+#[test]
+fn private_repositories_downgrade_the_finding_unless_the_config_sets_its_level() {
+    let repo = repo("visibility-given", "trusted_owners: [actions]\n");
+    let public = repo.scan(&["--format", "json", "--repo-visibility", "public"]);
+    assert_eq!(code(&public), 2, "{}", stderr(&public));
+    assert_eq!(judged(&public), ("error".to_string(), "public (--repo-visibility)".to_string()));
+
+    let private = repo.scan(&["--format", "json", "--repo-visibility", "private"]);
+    assert_eq!(code(&private), 0, "{}", stderr(&private));
+    assert_eq!(judged(&private), ("warning".to_string(), "private (--repo-visibility)".to_string()));
+
+    let configured = repo.config("trusted_owners: [actions]\nprivate_repo_levels:\n  self-hosted-pull-request: note\n");
+    let output = configured.scan(&["--format", "json", "--repo-visibility", "private"]);
+    assert_eq!(judged(&output).0, "note");
+}
+
+#[test]
+fn auto_looks_the_repository_up_and_says_so_in_the_summary() {
+    let server = MockServer::replaying("visibility.json");
+    let repo = repo("visibility-auto", "trusted_owners: [actions]\n");
+    let scan = |repository: &str, args: &[&str]| {
+        let mut command = repo.networked(&["--api-url", &server.base, "--repo-visibility", "auto"]);
+        command.env("GITHUB_TOKEN", "test-token").env("GITHUB_REPOSITORY", repository).args(args).arg(repo.workflows());
+        run(command, None)
+    };
+
+    let internal = scan("acme/internal-app", &["--format", "json"]);
+    assert_eq!(judged(&internal), ("warning".to_string(), "internal (acme/internal-app via the repos API)".to_string()));
+    let public = scan("acme/site", &["--format", "json"]);
+    assert_eq!(judged(&public), ("error".to_string(), "public (acme/site via the repos API)".to_string()));
+
+    let text = scan("acme/site", &["--ascii"]);
+    assert!(stdout(&text).contains("Repository visibility: public (acme/site via the repos API)"), "{}", stdout(&text));
+    let unknown = scan("acme/gone", &["--format", "json"]);
+    assert_eq!(judged(&unknown), ("error".to_string(), "assumed public (acme/gone not found)".to_string()));
+}
@@ -0,0 +1,50 @@
+//! `--format github`: annotations as workflow commands, chosen by default inside a GitHub
+//! Actions job
+
+mod common;
+
+use common::*;
+
+const ANNOTATION: &str = "::error file=.github/workflows/ci.yml,line=6,title=untrusted-owner-no-sha::Non-trusted action acme/deploy@v1 should use commit SHA instead of tag/branch";
+
+fn repo(name: &str) -> Repo {
+    Repo::new(name, &[("ci.yml", &workflow_using(&["acme/deploy@v1"]))]).config("trusted_owners: [actions]\n")
+}
+
+#[test]
+fn each_failing_use_is_an_error_annotation_at_its_line() {
+    let repo = repo("github-format");
+    let output = run(repo.command(&["--format", "github", ".github/workflows"]), None);
+    assert_eq!(code(&output), 2, "{}", stderr(&output));
+    let out = stdout(&output);
+    let lines: Vec<&str> = out.lines().filter(|l| l.starts_with("::")).collect();
+    assert_eq!(
+        lines[..4],
+        [
+            "::group::Action security scan: .github/workflows/ci.yml",
+            "::warning file=.github/workflows/ci.yml,line=6,title=untrusted-owner::Action acme/deploy@v1 is from non-trusted owner acme",
+            ANNOTATION,
+            "::endgroup::",
+        ],
+        "{}",
+        out
+    );
+    // A finding about no file, such as an unused config entry, follows the groups unlocated
+    assert!(lines[4..].iter().all(|l| l.starts_with("::notice title=")), "{}", out);
+}
+
+#[test]
+fn inside_actions_the_format_defaults_to_github() {
+    let repo = repo("github-auto");
+    let mut command = repo.command(&[".github/workflows"]);
+    command.env("GITHUB_ACTIONS", "true");
+    let output = run(command, None);
+    assert!(stdout(&output).lines().any(|l| l == ANNOTATION), "{}", stdout(&output));
+
+    // Outside a job, or with a format asked for, there are no workflow commands
+    let output = run(repo.command(&[".github/workflows"]), None);
+    assert!(!stdout(&output).contains("::error"), "{}", stdout(&output));
+    let mut command = repo.command(&["--format", "text", ".github/workflows"]);
+    command.env("GITHUB_ACTIONS", "true");
+    assert!(!stdout(&run(command, None)).contains("::error"));
+}